@@ -3,10 +3,14 @@ extern crate bencher;
 
 use bencher::Bencher;
 use std::collections::BTreeMap;
+use treers::adapters::StdBTreeAdapter;
+use treers::bench_support::{delete_heavy, insert_only, range_scan, read_write, shuffled_keys};
 use treers::bst::BST;
 use treers::btree::BalancedTree;
+use treers::implicit::ImplicitTree;
 use treers::rbtree::RedBlackTree;
-use treers::SedgewickMap;
+use treers::sort::tree_sort;
+use treers::{Map, OrderedMap};
 
 fn bst_add_one_thousand_left_rotate(b: &mut Bencher) {
     let mut bst = BST::new();
@@ -80,6 +84,187 @@ fn btree_add_one_thousand_right_rotate(b: &mut Bencher) {
     });
 }
 
+fn btree_put_sorted_one_thousand(b: &mut Bencher) {
+    b.iter(|| {
+        let mut btree = BalancedTree::new();
+        for i in 1..=1000_u64 {
+            btree.put(i, i + 1);
+        }
+        btree
+    });
+}
+
+fn btree_push_max_one_thousand(b: &mut Bencher) {
+    b.iter(|| {
+        let mut btree = BalancedTree::new();
+        for i in 1..=1000_u64 {
+            btree.push_max(i, i + 1);
+        }
+        btree
+    });
+}
+
+fn shuffled_one_thousand() -> Vec<u64> {
+    let mut seed = 42_u64;
+    (0..1_000)
+        .map(|_| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            seed >> 33
+        })
+        .collect()
+}
+
+fn tree_sort_one_thousand(b: &mut Bencher) {
+    let v = shuffled_one_thousand();
+    b.iter(|| tree_sort(v.clone()));
+}
+
+fn std_sort_one_thousand(b: &mut Bencher) {
+    let v = shuffled_one_thousand();
+    b.iter(|| {
+        let mut v = v.clone();
+        v.sort();
+        v
+    });
+}
+
+fn filled<M: Map<u64, u64> + Default>(keys: &[u64]) -> M {
+    let mut map = M::default();
+    insert_only(&mut map, keys);
+    map
+}
+
+fn read_write_ten_thousand<M: Map<u64, u64> + Default>(b: &mut Bencher) {
+    let keys = shuffled_keys(2_000, 42);
+    let mut map: M = filled(&keys[..1_000]);
+    b.iter(|| read_write(&mut map, &keys, 10_000));
+}
+
+fn range_scan_one_hundred<M: OrderedMap<u64, u64> + Default>(b: &mut Bencher) {
+    let keys = shuffled_keys(1_000, 42);
+    let map: M = filled(&keys);
+    b.iter(|| range_scan(&map, &keys[..100], 1 << 28));
+}
+
+fn bst_read_write(b: &mut Bencher) {
+    read_write_ten_thousand::<BST<u64, u64>>(b);
+}
+
+fn rbtree_read_write(b: &mut Bencher) {
+    read_write_ten_thousand::<RedBlackTree<u64, u64>>(b);
+}
+
+fn btree_read_write(b: &mut Bencher) {
+    read_write_ten_thousand::<BalancedTree<u64, u64>>(b);
+}
+
+fn std_btree_read_write(b: &mut Bencher) {
+    read_write_ten_thousand::<StdBTreeAdapter<u64, u64>>(b);
+}
+
+fn bst_range_scan(b: &mut Bencher) {
+    range_scan_one_hundred::<BST<u64, u64>>(b);
+}
+
+fn rbtree_range_scan(b: &mut Bencher) {
+    range_scan_one_hundred::<RedBlackTree<u64, u64>>(b);
+}
+
+fn btree_range_scan(b: &mut Bencher) {
+    range_scan_one_hundred::<BalancedTree<u64, u64>>(b);
+}
+
+fn std_btree_range_scan(b: &mut Bencher) {
+    range_scan_one_hundred::<StdBTreeAdapter<u64, u64>>(b);
+}
+
+fn get_ten_thousand<M: Map<u64, u64>>(b: &mut Bencher, map: &M, keys: &[u64]) {
+    b.iter(|| {
+        keys.iter()
+            .cycle()
+            .take(10_000)
+            .filter(|k| map.get(k).is_some())
+            .count()
+    });
+}
+
+fn dense_keys() -> Vec<u64> {
+    shuffled_keys(1_000, 42)
+        .iter()
+        .map(|k| k % 100_000)
+        .collect()
+}
+
+fn btree_get_dense(b: &mut Bencher) {
+    let keys = dense_keys();
+    let map = BalancedTree::from_sorted_iter((0..100_000_u64).map(|k| (k, k)));
+    get_ten_thousand(b, &map, &keys);
+}
+
+fn implicit_get_dense(b: &mut Bencher) {
+    let keys = dense_keys();
+    let map = ImplicitTree::from_sorted_iter((0..100_000_u64).map(|k| (k, k)));
+    get_ten_thousand(b, &map, &keys);
+}
+
+// Sorted puts leave a list, 90% of lookups go to 10 hot keys
+fn skewed() -> (BST<u64, u64>, Vec<u64>) {
+    let mut bst = BST::new();
+    for k in 0..1_000_u64 {
+        bst.put(k, k);
+    }
+    let lookups = shuffled_keys(10_000, 42)
+        .iter()
+        .map(|k| if k % 10 == 0 { k % 1_000 } else { 500 + k % 10 })
+        .collect();
+    (bst, lookups)
+}
+
+fn bst_get_skewed(b: &mut Bencher) {
+    let (bst, lookups) = skewed();
+    b.iter(|| lookups.iter().filter(|k| bst.get(k).is_some()).count());
+}
+
+fn bst_get_to_root_skewed(b: &mut Bencher) {
+    let (mut bst, lookups) = skewed();
+    b.iter(|| {
+        lookups
+            .iter()
+            .filter(|k| bst.get_to_root(k).is_some())
+            .count()
+    });
+}
+
+fn filled_btree() -> BalancedTree<u64, u64> {
+    let mut btree = BalancedTree::with_node_bytes(1024);
+    insert_only(&mut btree, &shuffled_keys(10_000, 42));
+    btree
+}
+
+fn btree_range_sum(b: &mut Bencher) {
+    let btree = filled_btree();
+    b.iter(|| btree.range(..).map(|(_, v)| *v).sum::<u64>());
+}
+
+fn btree_range_chunks_sum(b: &mut Bencher) {
+    let btree = filled_btree();
+    b.iter(|| {
+        btree
+            .range_chunks(..)
+            .map(|c| c.values().sum::<u64>())
+            .sum::<u64>()
+    });
+}
+
+// delete isn't implemented by the trees yet
+fn std_btree_delete_heavy(b: &mut Bencher) {
+    let keys = shuffled_keys(1_000, 42);
+    b.iter(|| {
+        let mut map = StdBTreeAdapter::new();
+        delete_heavy(&mut map, &keys, |map, k| map.remove(k).is_some())
+    });
+}
+
 benchmark_group!(
     benches,
     bst_add_one_thousand_left_rotate,
@@ -90,6 +275,25 @@ benchmark_group!(
     rbtree_add_one_thousand_right_rotate,
     btree_add_one_thousand_left_rotate,
     btree_add_one_thousand_right_rotate,
+    btree_put_sorted_one_thousand,
+    btree_push_max_one_thousand,
+    tree_sort_one_thousand,
+    std_sort_one_thousand,
+    bst_read_write,
+    rbtree_read_write,
+    btree_read_write,
+    std_btree_read_write,
+    bst_range_scan,
+    rbtree_range_scan,
+    btree_range_scan,
+    std_btree_range_scan,
+    btree_range_sum,
+    btree_range_chunks_sum,
+    std_btree_delete_heavy,
+    btree_get_dense,
+    implicit_get_dense,
+    bst_get_skewed,
+    bst_get_to_root_skewed,
 );
 
 benchmark_main!(benches);
@@ -0,0 +1,47 @@
+use treers::alloc_stats::{measure, CountingAlloc, Counts};
+use treers::bench_support::{insert_only, range_scan, read_write, shuffled_keys};
+use treers::bst::BST;
+use treers::btree::BalancedTree;
+use treers::rbtree::RedBlackTree;
+use treers::OrderedMap;
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn report<M: OrderedMap<u64, u64> + Default>(name: &str, keys: &[u64]) {
+    let row = |op: &str, c: Counts| {
+        println!(
+            "{:<8} {:<10} {:>8} {:>8} {:>8} {:>10}",
+            name, op, c.allocs, c.deallocs, c.reallocs, c.bytes
+        )
+    };
+    let (map, c) = measure(|| {
+        let mut map = M::default();
+        insert_only(&mut map, keys);
+        map
+    });
+    row("put", c);
+    let (mut map, c) = measure(|| {
+        let mut map = map;
+        read_write(&mut map, keys, keys.len());
+        map
+    });
+    row("read", c);
+    let (_, c) = measure(|| range_scan(&map, &keys[..100], 1 << 24));
+    row("range", c);
+    let (_, c) = measure(|| map.put(u64::MAX, 0));
+    row("put one", c);
+    let (_, c) = measure(|| drop(map));
+    row("drop", c);
+}
+
+fn main() {
+    let keys = shuffled_keys(10_000, 42);
+    println!(
+        "{:<8} {:<10} {:>8} {:>8} {:>8} {:>10}",
+        "tree", "op", "allocs", "deallocs", "reallocs", "bytes"
+    );
+    report::<BST<u64, u64>>("bst", &keys);
+    report::<RedBlackTree<u64, u64>>("rbtree", &keys);
+    report::<BalancedTree<u64, u64>>("btree", &keys);
+}
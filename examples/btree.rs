@@ -1,5 +1,5 @@
 use treers::btree::BalancedTree;
-use treers::SedgewickMap;
+use treers::{Map, OrderedMap};
 
 fn main() {
     let mut btree = BalancedTree::new();
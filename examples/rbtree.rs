@@ -1,5 +1,5 @@
 use treers::rbtree::RedBlackTree;
-use treers::{SedgewickMap, Traversals, TreeTraversal};
+use treers::{Map, OrderedMap, SedgewickMap, Traversals, TreeTraversal};
 
 fn left_rotate() {
     let mut rbtree: RedBlackTree<u32, u32> = RedBlackTree::new();
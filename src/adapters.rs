@@ -0,0 +1,262 @@
+use crate::{Map, OrderedMap};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::ops::{Bound, RangeBounds};
+
+/// `BTreeMap` from the standard library behind the crate traits.
+///
+/// Implements `Map` and `OrderedMap`, so generic code written against
+/// the trees can be run against the std map too, e.g. as a reference
+/// in tests. Std maps keep their shape private, hence no `SedgewickMap`.
+///
+/// # Examples
+///
+/// ```
+/// use treers::adapters::StdBTreeAdapter;
+/// use treers::{Map, OrderedMap};
+///
+/// let mut map: StdBTreeAdapter<char, i32> = StdBTreeAdapter::new();
+/// map.put('b', 2);
+/// map.put('a', 1);
+/// map.put('c', 3);
+/// assert_eq!(map.get(&'a'), Some(&1));
+/// assert_eq!(map.min(), Some(&'a'));
+/// assert_eq!(map.floor(&'d'), Some(&'c'));
+/// assert_eq!(map.smallest(2).as_slice(), &[(&'a', &1), (&'b', &2)]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StdBTreeAdapter<K, V> {
+    map: BTreeMap<K, V>,
+}
+
+impl<K: Ord, V> StdBTreeAdapter<K, V> {
+    /// Inits a new empty adapter.
+    pub const fn new() -> Self {
+        Self {
+            map: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the wrapped `BTreeMap`.
+    pub fn into_inner(self) -> BTreeMap<K, V> {
+        self.map
+    }
+
+    /// Removes a key, returning its value if it was in the map.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.map.remove(key)
+    }
+}
+
+impl<K: Ord, V> Default for StdBTreeAdapter<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> From<BTreeMap<K, V>> for StdBTreeAdapter<K, V> {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        Self { map }
+    }
+}
+
+impl<K: Ord, V> Map<K, V> for StdBTreeAdapter<K, V> {
+    fn size(&self) -> usize {
+        self.map.len()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        self.map.insert(key, value);
+    }
+}
+
+impl<K: Ord, V> OrderedMap<K, V> for StdBTreeAdapter<K, V> {
+    fn min(&self) -> Option<&K> {
+        self.map.keys().next()
+    }
+
+    fn max(&self) -> Option<&K> {
+        self.map.keys().next_back()
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        self.map
+            .range((Bound::Unbounded, Bound::Included(key)))
+            .next_back()
+            .map(|(k, _)| k)
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        self.map
+            .range((Bound::Included(key), Bound::Unbounded))
+            .next()
+            .map(|(k, _)| k)
+    }
+
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.iter().take(n).collect::<Vec<_>>().into_iter()
+    }
+
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.map
+            .iter()
+            .rev()
+            .take(n)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        let bounds = (range.start_bound(), range.end_bound());
+        // BTreeMap::range panics on inverted bounds, the trees return nothing
+        let inverted = match bounds {
+            (Bound::Included(lo), Bound::Included(hi)) => lo > hi,
+            (Bound::Included(lo), Bound::Excluded(hi))
+            | (Bound::Excluded(lo), Bound::Included(hi))
+            | (Bound::Excluded(lo), Bound::Excluded(hi)) => lo >= hi,
+            _ => false,
+        };
+        if inverted {
+            Vec::new().into_iter()
+        } else {
+            self.map.range(bounds).collect::<Vec<_>>().into_iter()
+        }
+    }
+}
+
+/// `HashMap` from the standard library behind the `Map` trait.
+///
+/// Keys are unordered, so only `Map` is implemented.
+///
+/// # Examples
+///
+/// ```
+/// use treers::adapters::StdHashAdapter;
+/// use treers::Map;
+///
+/// let mut map: StdHashAdapter<&str, i32> = StdHashAdapter::new();
+/// map.put("one", 1);
+/// map.put("two", 2);
+/// assert_eq!(map.size(), 2_usize);
+/// assert!(map.contains(&"one"));
+/// assert_eq!(map.get(&"three"), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StdHashAdapter<K: Hash + Eq, V> {
+    map: HashMap<K, V>,
+}
+
+impl<K: Hash + Eq, V> StdHashAdapter<K, V> {
+    /// Inits a new empty adapter.
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+        }
+    }
+
+    /// Returns the wrapped `HashMap`.
+    pub fn into_inner(self) -> HashMap<K, V> {
+        self.map
+    }
+}
+
+impl<K: Hash + Eq, V> Default for StdHashAdapter<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V> From<HashMap<K, V>> for StdHashAdapter<K, V> {
+    fn from(map: HashMap<K, V>) -> Self {
+        Self { map }
+    }
+}
+
+impl<K: Hash + Eq, V> Map<K, V> for StdHashAdapter<K, V> {
+    fn size(&self) -> usize {
+        self.map.len()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        self.map.insert(key, value);
+    }
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::{StdBTreeAdapter, StdHashAdapter};
+    use crate::bst::BST;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use crate::{Map, OrderedMap};
+    use std::collections::BTreeMap;
+    use std::ops::Bound;
+
+    fn filled<M: Map<u32, u32> + Default>() -> M {
+        let mut map = M::default();
+        for i in [5_u32, 3, 8, 1, 4, 7, 9, 2, 6] {
+            map.put(i, i * 10);
+        }
+        map
+    }
+
+    fn same_queries<A: OrderedMap<u32, u32>, B: OrderedMap<u32, u32>>(a: &A, b: &B) {
+        assert_eq!(a.size(), b.size());
+        assert_eq!(a.min(), b.min());
+        assert_eq!(a.max(), b.max());
+        for key in 0_u32..11 {
+            assert_eq!(a.get(&key), b.get(&key));
+            assert_eq!(a.floor(&key), b.floor(&key));
+            assert_eq!(a.ceiling(&key), b.ceiling(&key));
+            assert_eq!(a.range(key..).as_slice(), b.range(key..).as_slice());
+            assert_eq!(
+                a.smallest(key as usize).as_slice(),
+                b.smallest(key as usize).as_slice()
+            );
+            assert_eq!(
+                a.largest(key as usize).as_slice(),
+                b.largest(key as usize).as_slice()
+            );
+        }
+        let inverted = (Bound::Excluded(4), Bound::Excluded(4));
+        assert_eq!(a.range(inverted).as_slice(), b.range(inverted).as_slice());
+        let reversed = (Bound::Included(6), Bound::Included(3));
+        assert_eq!(a.range(reversed).as_slice(), b.range(reversed).as_slice());
+    }
+
+    #[test]
+    fn test_std_btree_adapter_matches_trees() {
+        let std: StdBTreeAdapter<u32, u32> = filled();
+        same_queries(&std, &filled::<BST<u32, u32>>());
+        same_queries(&std, &filled::<RedBlackTree<u32, u32>>());
+        same_queries(&std, &filled::<BalancedTree<u32, u32>>());
+    }
+
+    #[test]
+    fn test_std_hash_adapter() {
+        let mut map: StdHashAdapter<u32, u32> = filled();
+        assert_eq!(map.size(), 9_usize);
+        assert_eq!(map.get(&4), Some(&40));
+        map.put(4, 44);
+        assert_eq!(map.get(&4), Some(&44));
+        assert_eq!(map.size(), 9_usize);
+        assert!(!map.contains(&10));
+        assert_eq!(map.into_inner().len(), 9_usize);
+    }
+
+    #[test]
+    fn test_adapter_conversions() {
+        let inner: BTreeMap<u32, u32> = (1_u32..4).map(|i| (i, i)).collect();
+        let map = StdBTreeAdapter::from(inner.clone());
+        assert_eq!(map.max(), Some(&3));
+        assert_eq!(map.into_inner(), inner);
+    }
+}
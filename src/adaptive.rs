@@ -0,0 +1,287 @@
+use crate::bst::BST;
+use crate::rbtree::RedBlackTree;
+use crate::{Map, OrderedMap, SedgewickMap};
+use std::cmp::Ordering;
+use std::ops::RangeBounds;
+
+// Default `c` of the `c * lg n` depth limit
+const DEPTH_FACTOR: usize = 3_usize;
+
+/// A map that starts as a plain `BST` and rebuilds itself into a
+/// `RedBlackTree` once the `BST` degenerates.
+///
+/// A `BST` is the fastest to fill with random keys, but sorted or nearly
+/// sorted keys turn it into a list. After every `put` the depth of the put
+/// key is checked, and once it is over `c * lg n` the entries move to a
+/// `RedBlackTree` for good. Random keys rarely trigger it, sorted ones do
+/// after a handful of puts.
+///
+/// # Examples
+///
+/// ```
+/// use treers::adaptive::AdaptiveMap;
+/// use treers::{Map, SedgewickMap};
+///
+/// let mut map: AdaptiveMap<u32, u32> = AdaptiveMap::new();
+/// map.put(2, 2);
+/// map.put(1, 1);
+/// map.put(3, 3);
+/// assert!(!map.is_upgraded());
+///
+/// for k in 4..1000 {
+///     map.put(k, k);
+/// }
+/// assert!(map.is_upgraded());
+/// assert_eq!(map.get(&500), Some(&500));
+/// assert!(map.height() < Some(20_usize));
+/// ```
+#[derive(Debug)]
+pub struct AdaptiveMap<K: Ord + Clone, V: Clone> {
+    tree: Tree<K, V>,
+    factor: usize,
+}
+
+#[derive(Debug)]
+enum Tree<K: Ord + Clone, V: Clone> {
+    Bst(BST<K, V>),
+    RedBlack(RedBlackTree<K, V>),
+}
+
+impl<K: Ord + Clone, V: Clone> AdaptiveMap<K, V> {
+    /// Inits a new empty map, upgraded once a key is put deeper than
+    /// `3 * lg n`.
+    pub const fn new() -> Self {
+        Self::with_factor(DEPTH_FACTOR)
+    }
+
+    /// Inits a new empty map, upgraded once a key is put deeper than
+    /// `factor * lg n`.
+    pub const fn with_factor(factor: usize) -> Self {
+        Self {
+            tree: Tree::Bst(BST::new()),
+            factor,
+        }
+    }
+
+    /// Returns `true` once the map has moved to a `RedBlackTree`.
+    pub const fn is_upgraded(&self) -> bool {
+        matches!(self.tree, Tree::RedBlack(_))
+    }
+
+    fn upgrade(&mut self) {
+        if let Tree::Bst(ref mut bst) = self.tree {
+            let mut rbt = RedBlackTree::new();
+            for (k, v) in std::mem::take(bst).into_sorted_vec() {
+                rbt.put(k, v);
+            }
+            self.tree = Tree::RedBlack(rbt);
+        }
+    }
+
+    fn as_map(&self) -> &dyn SedgewickMap<K, V> {
+        match self.tree {
+            Tree::Bst(ref bst) => bst,
+            Tree::RedBlack(ref rbt) => rbt,
+        }
+    }
+}
+
+// Deepest a key may be put before upgrading, `lg n` rounded up
+const fn depth_limit(factor: usize, size: usize) -> usize {
+    factor * (usize::BITS - size.leading_zeros()) as usize
+}
+
+// Count of nodes above `key`
+fn depth<K: Ord, V>(bst: &BST<K, V>, key: &K) -> usize {
+    let mut depth = 0_usize;
+    let mut node = bst.root();
+    while let Some(n) = node {
+        node = match key.cmp(n.key()) {
+            Ordering::Less => n.left().root(),
+            Ordering::Greater => n.right().root(),
+            Ordering::Equal => break,
+        };
+        depth += 1_usize;
+    }
+    depth
+}
+
+impl<K: Ord + Clone, V: Clone> Default for AdaptiveMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Map<K, V> for AdaptiveMap<K, V> {
+    fn size(&self) -> usize {
+        self.as_map().size()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.as_map().get(key)
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        match self.tree {
+            Tree::Bst(ref mut bst) => {
+                let probe = key.clone();
+                bst.put(key, value);
+                if depth(bst, &probe).gt(&depth_limit(self.factor, bst.size())) {
+                    self.upgrade();
+                }
+            }
+            Tree::RedBlack(ref mut rbt) => rbt.put(key, value),
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> OrderedMap<K, V> for AdaptiveMap<K, V> {
+    fn min(&self) -> Option<&K> {
+        self.as_map().min()
+    }
+
+    fn max(&self) -> Option<&K> {
+        self.as_map().max()
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        self.as_map().floor(key)
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        self.as_map().ceiling(key)
+    }
+
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.as_map().smallest(n)
+    }
+
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.as_map().largest(n)
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        match self.tree {
+            Tree::Bst(ref bst) => bst.range(range),
+            Tree::RedBlack(ref rbt) => rbt.range(range),
+        }
+    }
+
+    fn try_fold_range<R, B, E, F>(&self, range: R, init: B, f: F) -> Result<B, E>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(B, &K, &V) -> Result<B, E>,
+    {
+        match self.tree {
+            Tree::Bst(ref bst) => bst.try_fold_range(range, init, f),
+            Tree::RedBlack(ref rbt) => rbt.try_fold_range(range, init, f),
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for AdaptiveMap<K, V> {
+    fn height(&self) -> Option<usize> {
+        self.as_map().height()
+    }
+
+    fn leaf_depths(&self) -> Vec<usize> {
+        self.as_map().leaf_depths()
+    }
+
+    fn delete_min(&mut self) -> Option<(K, V)> {
+        match self.tree {
+            Tree::Bst(ref mut bst) => bst.delete_min(),
+            Tree::RedBlack(ref mut rbt) => rbt.delete_min(),
+        }
+    }
+
+    fn delete_max(&mut self) -> Option<(K, V)> {
+        match self.tree {
+            Tree::Bst(ref mut bst) => bst.delete_max(),
+            Tree::RedBlack(ref mut rbt) => rbt.delete_max(),
+        }
+    }
+
+    fn rank(&self, key: &K) -> usize {
+        self.as_map().rank(key)
+    }
+
+    fn select(&self, rank: usize) -> Option<&K> {
+        self.as_map().select(rank)
+    }
+
+    fn successor(&self, key: &K) -> Option<(&K, &V)> {
+        self.as_map().successor(key)
+    }
+
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)> {
+        self.as_map().predecessor(key)
+    }
+
+    fn partition_point<P: FnMut(&K) -> bool>(&self, pred: P) -> Option<&K> {
+        match self.tree {
+            Tree::Bst(ref bst) => bst.partition_point(pred),
+            Tree::RedBlack(ref rbt) => rbt.partition_point(pred),
+        }
+    }
+
+    fn clear(&mut self) {
+        // back to a plain `BST`, keys put next may well be random
+        if let Tree::Bst(ref mut bst) = self.tree {
+            bst.clear();
+        }
+        self.tree = Tree::Bst(BST::new());
+    }
+
+    fn drain(&mut self) -> std::vec::IntoIter<(K, V)> {
+        let entries = match self.tree {
+            Tree::Bst(ref mut bst) => bst.drain(),
+            Tree::RedBlack(ref mut rbt) => rbt.drain(),
+        };
+        self.tree = Tree::Bst(BST::new());
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AdaptiveMap;
+    use crate::bench_support::shuffled_keys;
+    use crate::{Map, OrderedMap, SedgewickMap};
+    use std::collections::BTreeMap;
+
+    fn fill(map: &mut AdaptiveMap<u32, u32>, keys: &[u32]) -> BTreeMap<u32, u32> {
+        let mut model = BTreeMap::new();
+        for (i, k) in keys.iter().enumerate() {
+            map.put(*k, i as u32);
+            model.insert(*k, i as u32);
+            assert_eq!(map.size(), model.len());
+        }
+        assert!(map.range(..).eq(model.iter()));
+        model
+    }
+
+    #[test]
+    fn test_sorted_keys_upgrade() {
+        let mut map = AdaptiveMap::new();
+        let keys: Vec<u32> = (0_u32..2000).collect();
+        let model = fill(&mut map, &keys);
+        assert!(map.is_upgraded());
+        assert!(map.height().is_some_and(|h| h.lt(&(2_usize * 11_usize))));
+        assert_eq!(map.max(), model.keys().next_back());
+        assert_eq!(map.floor(&5000), Some(&1999));
+    }
+
+    #[test]
+    fn test_random_keys_stay() {
+        let mut map = AdaptiveMap::new();
+        let keys: Vec<u32> = shuffled_keys(2000, 42).iter().map(|k| *k as u32).collect();
+        fill(&mut map, &keys);
+        assert!(!map.is_upgraded());
+
+        // a tight factor gives up on random keys too
+        let mut map = AdaptiveMap::with_factor(1);
+        fill(&mut map, &keys);
+        assert!(map.is_upgraded());
+    }
+}
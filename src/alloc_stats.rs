@@ -0,0 +1,226 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ops::Sub;
+
+/// Allocator calls counted on one thread.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counts {
+    /// Count of allocations, zeroed ones included
+    pub allocs: usize,
+    /// Count of deallocations
+    pub deallocs: usize,
+    /// Count of reallocations, each also counted in `bytes`
+    pub reallocs: usize,
+    /// Bytes requested by allocations and reallocations
+    pub bytes: usize,
+}
+
+impl Counts {
+    const ZERO: Counts = Counts {
+        allocs: 0_usize,
+        deallocs: 0_usize,
+        reallocs: 0_usize,
+        bytes: 0_usize,
+    };
+}
+
+impl Sub for Counts {
+    type Output = Counts;
+
+    fn sub(self, other: Counts) -> Counts {
+        Counts {
+            allocs: self.allocs.wrapping_sub(other.allocs),
+            deallocs: self.deallocs.wrapping_sub(other.deallocs),
+            reallocs: self.reallocs.wrapping_sub(other.reallocs),
+            bytes: self.bytes.wrapping_sub(other.bytes),
+        }
+    }
+}
+
+thread_local! {
+    // const init, so touching it never allocates
+    static COUNTS: Cell<Counts> = const { Cell::new(Counts::ZERO) };
+}
+
+fn record<F: FnOnce(&mut Counts)>(f: F) {
+    // fails only while the thread is torn down, those calls are not counted
+    let _ = COUNTS.try_with(|c| {
+        let mut counts = c.get();
+        f(&mut counts);
+        c.set(counts);
+    });
+}
+
+/// The system allocator, counting calls made on every thread.
+///
+/// Counting has to be installed as the global allocator of the binary,
+/// then `measure` reports what a closure allocated, e.g. a `put` of every
+/// tree for the same keys. Counts are kept per thread, so tests running
+/// in parallel don't mix them up.
+///
+/// # Examples
+///
+/// ```
+/// use treers::alloc_stats::{measure, CountingAlloc};
+/// use treers::rbtree::RedBlackTree;
+/// use treers::Map;
+///
+/// #[global_allocator]
+/// static GLOBAL: CountingAlloc = CountingAlloc;
+///
+/// fn main() {
+///     let (rbt, counts) = measure(|| {
+///         let mut rbt = RedBlackTree::new();
+///         for k in 0..100 {
+///             rbt.put(k, k);
+///         }
+///         rbt
+///     });
+///     assert!(counts.allocs >= 100_usize);
+///
+///     // lookups don't allocate
+///     let (found, counts) = measure(|| rbt.get(&50).copied());
+///     assert_eq!(found, Some(50));
+///     assert_eq!(counts, Default::default());
+///
+///     let ((), counts) = measure(|| drop(rbt));
+///     assert!(counts.deallocs >= 100_usize);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CountingAlloc;
+
+// Every call is passed on to `System` unchanged
+#[allow(unsafe_code)]
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(|c| {
+            c.allocs += 1_usize;
+            c.bytes += layout.size();
+        });
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(|c| {
+            c.allocs += 1_usize;
+            c.bytes += layout.size();
+        });
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record(|c| c.deallocs += 1_usize);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(|c| {
+            c.reallocs += 1_usize;
+            c.bytes += new_size;
+        });
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Returns counts of the current thread since it started.
+///
+/// All zeros unless `CountingAlloc` is the global allocator.
+pub fn counts() -> Counts {
+    COUNTS.try_with(Cell::get).unwrap_or_default()
+}
+
+/// Runs `f`, returning its result and the allocator calls it made on the
+/// current thread.
+pub fn measure<R, F: FnOnce() -> R>(f: F) -> (R, Counts) {
+    let before = counts();
+    let result = f();
+    (result, counts() - before)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{measure, CountingAlloc};
+
+    #[global_allocator]
+    static GLOBAL: CountingAlloc = CountingAlloc;
+
+    #[test]
+    fn test_counts_vec() {
+        let (vec, counts) = measure(|| {
+            let mut vec: Vec<u64> = Vec::with_capacity(4);
+            vec.extend(0_u64..8);
+            vec
+        });
+        assert_eq!(counts.allocs, 1_usize);
+        assert_eq!(counts.reallocs, 1_usize);
+        assert_eq!(counts.bytes, 4_usize * 8 + vec.capacity() * 8);
+        let ((), counts) = measure(|| drop(vec));
+        assert_eq!(counts.deallocs, 1_usize);
+    }
+
+    #[cfg(all(feature = "bst", feature = "rbtree", feature = "btree"))]
+    #[test]
+    fn test_trees_free_everything() {
+        use crate::bench_support::{insert_only, shuffled_keys};
+        use crate::bst::BST;
+        use crate::btree::BalancedTree;
+        use crate::rbtree::RedBlackTree;
+        use crate::Map;
+
+        fn allocs<M: Map<u64, u64> + Default>(keys: &[u64]) -> usize {
+            let (map, built) = measure(|| {
+                let mut map = M::default();
+                insert_only(&mut map, keys);
+                map
+            });
+            let ((), dropped) = measure(|| drop(map));
+            assert_eq!(built.allocs, dropped.deallocs + built.deallocs);
+            built.allocs
+        }
+        let keys = shuffled_keys(1000, 3);
+        // a node and two empty links per key
+        assert_eq!(allocs::<BST<u64, u64>>(&keys), 2_usize * 1000);
+        // rotations and splits copy nodes, but nothing is kept around
+        assert!(allocs::<RedBlackTree<u64, u64>>(&keys).gt(&1000_usize));
+        assert!(allocs::<BalancedTree<u64, u64>>(&keys).gt(&250_usize));
+    }
+
+    #[cfg(all(feature = "bst", feature = "rbtree"))]
+    #[test]
+    fn test_deleted_nodes_are_reused() {
+        use crate::bench_support::shuffled_keys;
+        use crate::bst::BST;
+        use crate::rbtree::RedBlackTree;
+        use crate::{Map, OrderedMap, SedgewickMap};
+
+        let keys = shuffled_keys(1000, 5);
+        let mut bst: BST<u64, u64> = BST::new();
+        let mut rbtree: RedBlackTree<u64, u64> = RedBlackTree::new();
+        for k in &keys {
+            bst.put(*k, *k);
+            rbtree.put(*k, *k);
+        }
+        // a queue under churn, every round deletes the minimum and puts a
+        // new maximum; the first round fills the pools
+        let churn = |bst: &mut BST<u64, u64>, rbtree: &mut RedBlackTree<u64, u64>, round: u64| {
+            for i in 0..100 {
+                let k = 1000 * round + i;
+                bst.delete_min();
+                bst.put(k, k);
+                rbtree.delete(&rbtree.min().copied().unwrap_or_default());
+                rbtree.put(k, k);
+            }
+        };
+        churn(&mut bst, &mut rbtree, 1);
+        let ((), counts) = measure(|| churn(&mut bst, &mut rbtree, 2));
+        assert_eq!((counts.allocs, counts.deallocs), (0_usize, 0_usize));
+        assert_eq!((bst.size(), rbtree.size()), (1000_usize, 1000_usize));
+
+        while bst.delete_min().is_some() {}
+        let ((), counts) = measure(|| bst.shrink_to_fit());
+        assert_eq!(counts.deallocs, 2_usize * 1000 + 2);
+        let ((), counts) = measure(|| rbtree.shrink_to_fit());
+        assert!(counts.deallocs.gt(&0_usize));
+    }
+}
@@ -0,0 +1,676 @@
+use crate::{reaches_above, reaches_below, Map, OrderedMap, SedgewickMap};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::RangeBounds;
+
+// Index of a missing child or of an empty tree
+const NIL: u32 = u32::MAX;
+
+struct Node<K, V> {
+    // `None` once moved out of a freed slot
+    entry: Option<(K, V)>,
+    size: usize,
+    left: u32,
+    right: u32,
+}
+
+struct Root {
+    node: u32,
+    generation: u32,
+}
+
+/// A `Copy` id of a tree of a `TreeArena`, valid until the tree is
+/// dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TreeId {
+    slot: u32,
+    generation: u32,
+}
+
+/// Node storage shared by many small trees, e.g. one per tenant or
+/// session.
+///
+/// Nodes of all trees sit in one `Vec`, linked by `u32` indexes instead of
+/// boxes, so small trees pack tightly and allocate nothing once the
+/// arena is warm. `drop_tree` and `clear` are `O(1)`: they only hand the
+/// root over to the free list, and the nodes below it are reclaimed one
+/// by one as later puts reuse their parents, their entries dropped then.
+///
+/// Every tree is an unbalanced BST, as `bst::BST`, reached through an
+/// `ArenaTree` implementing `SedgewickMap`.
+///
+/// # Examples
+///
+/// ```
+/// use treers::arena::TreeArena;
+/// use treers::{Map, OrderedMap, SedgewickMap};
+///
+/// let mut arena = TreeArena::new();
+/// let alice = arena.new_tree().id();
+/// let bob = arena.new_tree().id();
+/// for (i, page) in ["/", "/cart", "/pay"].iter().enumerate() {
+///     arena.tree(alice).unwrap().put(i, *page);
+/// }
+/// arena.tree(bob).unwrap().put(0, "/");
+///
+/// let mut visits = arena.tree(alice).unwrap();
+/// assert_eq!((visits.size(), visits.max()), (3_usize, Some(&2)));
+/// assert_eq!(visits.select(1), Some(&1));
+///
+/// // a session ends, its nodes are free for the next ones
+/// assert!(arena.drop_tree(alice));
+/// assert!(arena.tree(alice).is_none());
+/// assert_eq!(arena.trees(), 1_usize);
+/// let carol = arena.new_tree().id();
+/// arena.tree(carol).unwrap().put(0, "/login");
+/// assert_eq!(arena.node_slots(), 4_usize);
+/// ```
+pub struct TreeArena<K, V> {
+    nodes: Vec<Node<K, V>>,
+    // Roots of freed subtrees, their children are freed with them
+    free: Vec<u32>,
+    roots: Vec<Root>,
+    free_roots: Vec<u32>,
+}
+
+impl<K, V> TreeArena<K, V> {
+    /// Inits an arena without trees.
+    pub const fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            roots: Vec::new(),
+            free_roots: Vec::new(),
+        }
+    }
+
+    /// Inits an arena without trees, with room for `nodes` nodes.
+    pub fn with_capacity(nodes: usize) -> Self {
+        Self {
+            nodes: Vec::with_capacity(nodes),
+            ..Self::new()
+        }
+    }
+
+    /// Adds an empty tree and returns it.
+    pub fn new_tree(&mut self) -> ArenaTree<'_, K, V> {
+        let slot = match self.free_roots.pop() {
+            Some(slot) => slot,
+            None => {
+                let slot = u32::try_from(self.roots.len()).expect("more than u32::MAX trees");
+                self.roots.push(Root {
+                    node: NIL,
+                    generation: 0_u32,
+                });
+                slot
+            }
+        };
+        ArenaTree { arena: self, slot }
+    }
+
+    /// Returns the tree of `id`, `None` once it is dropped.
+    pub fn tree(&mut self, id: TreeId) -> Option<ArenaTree<'_, K, V>> {
+        let root = self.roots.get(id.slot as usize)?;
+        // dropping a tree moves its slot on a generation, old ids go stale
+        if root.generation.ne(&id.generation) {
+            return None;
+        }
+        Some(ArenaTree {
+            arena: self,
+            slot: id.slot,
+        })
+    }
+
+    /// Drops the tree of `id` in `O(1)`, returning `false` if it was
+    /// dropped already.
+    pub fn drop_tree(&mut self, id: TreeId) -> bool {
+        match self.tree(id) {
+            Some(mut tree) => tree.teardown(),
+            None => return false,
+        }
+        let root = &mut self.roots[id.slot as usize];
+        root.generation = root.generation.wrapping_add(1_u32);
+        self.free_roots.push(id.slot);
+        true
+    }
+
+    /// Returns a count of trees.
+    pub const fn trees(&self) -> usize {
+        self.roots.len() - self.free_roots.len()
+    }
+
+    /// Returns a count of node slots, in use or free.
+    pub const fn node_slots(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // Puts an entry into a free slot, or a new one
+    fn alloc(&mut self, key: K, value: V) -> u32 {
+        let node = Node {
+            entry: Some((key, value)),
+            size: 1_usize,
+            left: NIL,
+            right: NIL,
+        };
+        match self.free.pop() {
+            Some(i) => {
+                let old = std::mem::replace(&mut self.nodes[i as usize], node);
+                // children of a freed slot are freed in turn
+                for &child in &[old.left, old.right] {
+                    if child.ne(&NIL) {
+                        self.free.push(child);
+                    }
+                }
+                i
+            }
+            None => {
+                let i = u32::try_from(self.nodes.len()).expect("more than u32::MAX nodes");
+                self.nodes.push(node);
+                i
+            }
+        }
+    }
+
+    // Frees the slot of one node, its children are none of its business
+    fn release(&mut self, i: u32) -> Option<(K, V)> {
+        let node = &mut self.nodes[i as usize];
+        node.left = NIL;
+        node.right = NIL;
+        self.free.push(i);
+        node.entry.take()
+    }
+
+    fn node(&self, i: u32) -> &Node<K, V> {
+        &self.nodes[i as usize]
+    }
+
+    fn size_of(&self, i: u32) -> usize {
+        if i.eq(&NIL) {
+            0_usize
+        } else {
+            self.node(i).size
+        }
+    }
+
+    // Entries of nodes in a tree are always there
+    fn entry(&self, i: u32) -> (&K, &V) {
+        match &self.node(i).entry {
+            Some((k, v)) => (k, v),
+            None => unreachable!(),
+        }
+    }
+
+    fn key(&self, i: u32) -> &K {
+        self.entry(i).0
+    }
+}
+
+impl<K, V> Default for TreeArena<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> fmt::Debug for TreeArena<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TreeArena")
+            .field("trees", &self.trees())
+            .field("node_slots", &self.nodes.len())
+            .finish()
+    }
+}
+
+/// A tree of a `TreeArena`, borrowing the arena while in use.
+pub struct ArenaTree<'a, K, V> {
+    arena: &'a mut TreeArena<K, V>,
+    slot: u32,
+}
+
+impl<K, V> ArenaTree<'_, K, V> {
+    /// Returns the id to get the tree back from its arena.
+    pub fn id(&self) -> TreeId {
+        TreeId {
+            slot: self.slot,
+            generation: self.arena.roots[self.slot as usize].generation,
+        }
+    }
+
+    fn root(&self) -> u32 {
+        self.arena.roots[self.slot as usize].node
+    }
+
+    fn set_root(&mut self, i: u32) {
+        self.arena.roots[self.slot as usize].node = i;
+    }
+
+    // Frees the root, the nodes below it go with it
+    fn teardown(&mut self) {
+        let root = self.root();
+        if root.ne(&NIL) {
+            self.arena.free.push(root);
+            self.set_root(NIL);
+        }
+    }
+
+    // Indexes of the first `n` nodes in key order, or in reverse
+    fn in_order(&self, n: usize, reverse: bool) -> Vec<u32> {
+        let arena = &*self.arena;
+        let mut out = Vec::with_capacity(n.min(arena.size_of(self.root())));
+        let mut stack = Vec::new();
+        let mut at = self.root();
+        while out.len().lt(&n) {
+            while at.ne(&NIL) {
+                stack.push(at);
+                let node = arena.node(at);
+                at = if reverse { node.right } else { node.left };
+            }
+            match stack.pop() {
+                Some(i) => {
+                    out.push(i);
+                    let node = arena.node(i);
+                    at = if reverse { node.left } else { node.right };
+                }
+                None => break,
+            }
+        }
+        out
+    }
+
+    // Unlinks the leftmost, or rightmost, node and frees it
+    fn delete_end(&mut self, right: bool) -> Option<(K, V)> {
+        let mut parent = NIL;
+        let mut at = self.root();
+        if at.eq(&NIL) {
+            return None;
+        }
+        loop {
+            let node = self.arena.node(at);
+            let next = if right { node.right } else { node.left };
+            if next.eq(&NIL) {
+                break;
+            }
+            self.arena.nodes[at as usize].size -= 1_usize;
+            parent = at;
+            at = next;
+        }
+        let node = self.arena.node(at);
+        let rest = if right { node.left } else { node.right };
+        if parent.eq(&NIL) {
+            self.set_root(rest);
+        } else if right {
+            self.arena.nodes[parent as usize].right = rest;
+        } else {
+            self.arena.nodes[parent as usize].left = rest;
+        }
+        self.arena.release(at)
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for ArenaTree<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.in_order(usize::MAX, false);
+        f.debug_map()
+            .entries(entries.into_iter().map(|i| self.arena.entry(i)))
+            .finish()
+    }
+}
+
+impl<K: Ord, V> Map<K, V> for ArenaTree<'_, K, V> {
+    fn size(&self) -> usize {
+        self.arena.size_of(self.root())
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        let mut at = self.root();
+        while at.ne(&NIL) {
+            let node = self.arena.node(at);
+            let (k, v) = self.arena.entry(at);
+            at = match key.cmp(k) {
+                Ordering::Less => node.left,
+                Ordering::Greater => node.right,
+                Ordering::Equal => return Some(v),
+            };
+        }
+        None
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        let mut path = Vec::new();
+        let mut at = self.root();
+        let mut side = Ordering::Equal;
+        while at.ne(&NIL) {
+            side = key.cmp(self.arena.key(at));
+            let node = &mut self.arena.nodes[at as usize];
+            match side {
+                Ordering::Less => {
+                    path.push(at);
+                    at = node.left;
+                }
+                Ordering::Greater => {
+                    path.push(at);
+                    at = node.right;
+                }
+                Ordering::Equal => {
+                    node.entry = Some((key, value));
+                    return;
+                }
+            }
+        }
+        let leaf = self.arena.alloc(key, value);
+        for &i in &path {
+            self.arena.nodes[i as usize].size += 1_usize;
+        }
+        match path.last() {
+            Some(&parent) if side.eq(&Ordering::Less) => {
+                self.arena.nodes[parent as usize].left = leaf
+            }
+            Some(&parent) => self.arena.nodes[parent as usize].right = leaf,
+            None => self.set_root(leaf),
+        }
+    }
+}
+
+impl<K: Ord, V> OrderedMap<K, V> for ArenaTree<'_, K, V> {
+    fn min(&self) -> Option<&K> {
+        self.in_order(1_usize, false)
+            .first()
+            .map(|&i| self.arena.key(i))
+    }
+
+    fn max(&self) -> Option<&K> {
+        self.in_order(1_usize, true)
+            .first()
+            .map(|&i| self.arena.key(i))
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        let mut found = None;
+        let mut at = self.root();
+        while at.ne(&NIL) {
+            let k = self.arena.key(at);
+            at = match key.cmp(k) {
+                Ordering::Less => self.arena.node(at).left,
+                Ordering::Greater => {
+                    found = Some(k);
+                    self.arena.node(at).right
+                }
+                Ordering::Equal => return Some(k),
+            };
+        }
+        found
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        let mut found = None;
+        let mut at = self.root();
+        while at.ne(&NIL) {
+            let k = self.arena.key(at);
+            at = match key.cmp(k) {
+                Ordering::Less => {
+                    found = Some(k);
+                    self.arena.node(at).left
+                }
+                Ordering::Greater => self.arena.node(at).right,
+                Ordering::Equal => return Some(k),
+            };
+        }
+        found
+    }
+
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let vec: Vec<(&K, &V)> = self
+            .in_order(n, false)
+            .into_iter()
+            .map(|i| self.arena.entry(i))
+            .collect();
+        vec.into_iter()
+    }
+
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let vec: Vec<(&K, &V)> = self
+            .in_order(n, true)
+            .into_iter()
+            .map(|i| self.arena.entry(i))
+            .collect();
+        vec.into_iter()
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        let mut vec = Vec::new();
+        let mut stack = Vec::new();
+        let mut at = self.root();
+        loop {
+            // the left subtree only if the range reaches below the key
+            while at.ne(&NIL) {
+                stack.push(at);
+                at = if reaches_below(&range, self.arena.key(at)) {
+                    self.arena.node(at).left
+                } else {
+                    NIL
+                };
+            }
+            let i = match stack.pop() {
+                Some(i) => i,
+                None => break,
+            };
+            let (k, v) = self.arena.entry(i);
+            if range.contains(k) {
+                vec.push((k, v));
+            }
+            if !reaches_above(&range, k) {
+                break;
+            }
+            at = self.arena.node(i).right;
+        }
+        vec.into_iter()
+    }
+}
+
+impl<K: Ord, V> SedgewickMap<K, V> for ArenaTree<'_, K, V> {
+    fn height(&self) -> Option<usize> {
+        self.leaf_depths().into_iter().max()
+    }
+
+    fn leaf_depths(&self) -> Vec<usize> {
+        let mut vec = Vec::new();
+        let mut stack = Vec::new();
+        if self.root().ne(&NIL) {
+            stack.push((self.root(), 0_usize));
+        }
+        while let Some((i, depth)) = stack.pop() {
+            let node = self.arena.node(i);
+            if node.left.eq(&NIL) && node.right.eq(&NIL) {
+                vec.push(depth);
+            }
+            // right first, leaves come out from left to right
+            for &child in &[node.right, node.left] {
+                if child.ne(&NIL) {
+                    stack.push((child, depth + 1_usize));
+                }
+            }
+        }
+        vec
+    }
+
+    fn delete_min(&mut self) -> Option<(K, V)> {
+        self.delete_end(false)
+    }
+
+    fn delete_max(&mut self) -> Option<(K, V)> {
+        self.delete_end(true)
+    }
+
+    // `O(1)`, the nodes are reclaimed by later puts
+    fn clear(&mut self) {
+        self.teardown();
+    }
+
+    fn drain(&mut self) -> std::vec::IntoIter<(K, V)> {
+        let order = self.in_order(usize::MAX, false);
+        let vec: Vec<(K, V)> = order
+            .into_iter()
+            .filter_map(|i| self.arena.nodes[i as usize].entry.take())
+            .collect();
+        self.teardown();
+        vec.into_iter()
+    }
+
+    fn rank(&self, key: &K) -> usize {
+        let mut rank = 0_usize;
+        let mut at = self.root();
+        while at.ne(&NIL) {
+            let node = self.arena.node(at);
+            at = match key.cmp(self.arena.key(at)) {
+                Ordering::Less => node.left,
+                Ordering::Greater => {
+                    rank += self.arena.size_of(node.left) + 1_usize;
+                    node.right
+                }
+                Ordering::Equal => return rank + self.arena.size_of(node.left),
+            };
+        }
+        rank
+    }
+
+    fn select(&self, rank: usize) -> Option<&K> {
+        let mut rank = rank;
+        let mut at = self.root();
+        while at.ne(&NIL) {
+            let node = self.arena.node(at);
+            let left = self.arena.size_of(node.left);
+            at = match rank.cmp(&left) {
+                Ordering::Less => node.left,
+                Ordering::Greater => {
+                    rank -= left + 1_usize;
+                    node.right
+                }
+                Ordering::Equal => return Some(self.arena.key(at)),
+            };
+        }
+        None
+    }
+
+    fn partition_point<P: FnMut(&K) -> bool>(&self, mut pred: P) -> Option<&K> {
+        let mut found = None;
+        let mut at = self.root();
+        while at.ne(&NIL) {
+            let k = self.arena.key(at);
+            let node = self.arena.node(at);
+            at = if pred(k) {
+                node.right
+            } else {
+                found = Some(k);
+                node.left
+            };
+        }
+        found
+    }
+
+    fn successor(&self, key: &K) -> Option<(&K, &V)> {
+        let mut found = None;
+        let mut at = self.root();
+        while at.ne(&NIL) {
+            let (k, v) = self.arena.entry(at);
+            let node = self.arena.node(at);
+            at = if key.lt(k) {
+                found = Some((k, v));
+                node.left
+            } else {
+                node.right
+            };
+        }
+        found
+    }
+
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)> {
+        let mut found = None;
+        let mut at = self.root();
+        while at.ne(&NIL) {
+            let (k, v) = self.arena.entry(at);
+            let node = self.arena.node(at);
+            at = if key.gt(k) {
+                found = Some((k, v));
+                node.right
+            } else {
+                node.left
+            };
+        }
+        found
+    }
+}
+
+#[cfg(all(test, feature = "bst"))]
+mod tests {
+    use super::{TreeArena, TreeId};
+    use crate::bench_support::shuffled_keys;
+    use crate::bst::BST;
+    use crate::{Map, OrderedMap, SedgewickMap};
+
+    #[test]
+    fn test_against_bst() {
+        let mut arena = TreeArena::new();
+        let ids: Vec<TreeId> = (0..8).map(|_| arena.new_tree().id()).collect();
+        let mut models: Vec<BST<u64, u64>> = (0..8).map(|_| BST::new()).collect();
+        for (i, k) in shuffled_keys(4000, 34).into_iter().enumerate() {
+            let (k, t) = (k % 300, i % 8);
+            let mut tree = arena.tree(ids[t]).unwrap();
+            match i % 7 {
+                0 => assert_eq!(tree.delete_min(), models[t].delete_min()),
+                1 => assert_eq!(tree.delete_max(), models[t].delete_max()),
+                _ => {
+                    tree.put(k, i as u64);
+                    models[t].put(k, i as u64);
+                }
+            }
+        }
+        for (id, model) in ids.iter().zip(&models) {
+            let tree = arena.tree(*id).unwrap();
+            assert_eq!(tree.size(), model.size());
+            assert_eq!(tree.height(), model.height());
+            assert_eq!(tree.leaf_depths(), model.leaf_depths());
+            assert!(tree.smallest(20).eq(model.smallest(20)));
+            assert!(tree.largest(20).eq(model.largest(20)));
+            assert!(tree.range(50..250).eq(model.range(50..250)));
+            for k in 0_u64..302 {
+                assert_eq!(tree.get(&k), model.get(&k));
+                assert_eq!(tree.floor(&k), model.floor(&k));
+                assert_eq!(tree.ceiling(&k), model.ceiling(&k));
+                assert_eq!(tree.rank(&k), model.rank(&k));
+                assert_eq!(tree.successor(&k), model.successor(&k));
+                assert_eq!(tree.predecessor(&k), model.predecessor(&k));
+                assert_eq!(tree.select(k as usize), model.select(k as usize));
+            }
+            assert_eq!(
+                tree.partition_point(|k| *k < 100),
+                model.partition_point(|k| *k < 100)
+            );
+        }
+    }
+
+    #[test]
+    fn test_teardown_reuses_slots() {
+        let mut arena = TreeArena::new();
+        let first = arena.new_tree().id();
+        for k in 0_u32..100 {
+            arena.tree(first).unwrap().put(k, k.to_string());
+        }
+        assert_eq!(arena.node_slots(), 100_usize);
+        assert!(arena.drop_tree(first));
+        assert!(!arena.drop_tree(first));
+        // the freed slots serve a new tree, also in the slot of the old one
+        let second = arena.new_tree().id();
+        assert_ne!(first, second);
+        for k in (0_u32..100).rev() {
+            arena.tree(second).unwrap().put(k, k.to_string());
+        }
+        assert_eq!(arena.node_slots(), 100_usize);
+        let mut tree = arena.tree(second).unwrap();
+        assert_eq!(tree.min(), Some(&0));
+        assert_eq!(tree.drain().len(), 100_usize);
+        assert!(tree.is_empty());
+        tree.put(1, String::from("1"));
+        assert_eq!(arena.node_slots(), 100_usize);
+        assert_eq!(arena.trees(), 1_usize);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::rbtree::RedBlackTree;
+use crate::{Error, Map};
+
+/// One change of a batch given to `RedBlackTree::apply`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op<K, V> {
+    Put(K, V),
+    /// Removes a key, the batch fails if the key is missing by then.
+    Delete(K),
+}
+
+impl<K: Ord + Clone, V: Clone> RedBlackTree<K, V> {
+    /// Applies a batch of puts and deletes as a unit, in order.
+    ///
+    /// The whole batch is checked before the tree is touched: a delete of
+    /// a key that is neither in the tree nor put earlier in the batch, or
+    /// that is deleted twice, fails it with `Error::MissingKey` and leaves
+    /// the tree as it was.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::batch::Op;
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Error, Map};
+    ///
+    /// let mut rbtree = RedBlackTree::new();
+    /// rbtree.put(1, 'a');
+    /// rbtree.put(2, 'b');
+    ///
+    /// rbtree.apply(vec![Op::Put(3, 'c'), Op::Delete(1), Op::Delete(3)]).unwrap();
+    /// assert_eq!((rbtree.get(&1), rbtree.get(&3)), (None, None));
+    ///
+    /// let err = rbtree.apply(vec![Op::Put(4, 'd'), Op::Delete(1)]).unwrap_err();
+    /// assert_eq!(err, Error::MissingKey(1));
+    /// // nothing of a failed batch is applied
+    /// assert_eq!(rbtree.get(&4), None);
+    /// assert_eq!(rbtree.size(), 1_usize);
+    /// ```
+    pub fn apply<I: IntoIterator<Item = Op<K, V>>>(&mut self, ops: I) -> Result<(), Error<K>> {
+        let ops: Vec<Op<K, V>> = ops.into_iter().collect();
+        // keys the batch touched, `true` while they are present
+        let mut present: RedBlackTree<K, bool> = RedBlackTree::new();
+        for op in &ops {
+            match op {
+                Op::Put(k, _) => present.put(k.clone(), true),
+                Op::Delete(k) => {
+                    let held = present.get(k).copied().unwrap_or_else(|| self.contains(k));
+                    if !held {
+                        return Err(Error::MissingKey(k.clone()));
+                    }
+                    present.put(k.clone(), false);
+                }
+            }
+        }
+        for op in ops {
+            match op {
+                Op::Put(k, v) => self.put(k, v),
+                Op::Delete(k) => {
+                    self.delete(&k);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Op;
+    use crate::bench_support::shuffled_keys;
+    use crate::rbtree::RedBlackTree;
+    use crate::{Error, Map, OrderedMap};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_apply_matches_model() {
+        let mut rbtree = RedBlackTree::new();
+        let mut model = BTreeMap::new();
+        let keys = shuffled_keys(2000, 8);
+        for batch in keys.chunks(50) {
+            let ops: Vec<Op<u64, u64>> = batch
+                .iter()
+                .map(|&k| {
+                    if k % 3_u64 == 0_u64 && model.contains_key(&(k % 300)) {
+                        Op::Delete(k % 300)
+                    } else {
+                        Op::Put(k % 300, k)
+                    }
+                })
+                .collect();
+            let before = rbtree.to_sorted_vec();
+            match rbtree.apply(ops.clone()) {
+                Ok(()) => {
+                    for op in ops {
+                        match op {
+                            Op::Put(k, v) => model.insert(k, v),
+                            Op::Delete(k) => model.remove(&k),
+                        };
+                    }
+                }
+                // a key deleted twice in one batch
+                Err(Error::MissingKey(_)) => assert_eq!(rbtree.to_sorted_vec(), before),
+                Err(e) => panic!("{}", e),
+            }
+            assert!(rbtree.range(..).eq(model.iter()));
+        }
+        assert!(!model.is_empty());
+    }
+
+    #[test]
+    fn test_apply_validates_in_order() {
+        let mut rbtree: RedBlackTree<u32, u32> = RedBlackTree::new();
+        assert_eq!(rbtree.apply(vec![Op::Delete(1)]), Err(Error::MissingKey(1)));
+        assert!(rbtree
+            .apply(vec![Op::Put(1, 1), Op::Delete(1), Op::Put(1, 2)])
+            .is_ok());
+        assert_eq!(rbtree.get(&1), Some(&2));
+        let err = rbtree
+            .apply(vec![Op::Delete(1), Op::Delete(1)])
+            .unwrap_err();
+        assert_eq!(err.to_string(), "missing key: 1");
+        assert_eq!(rbtree.get(&1), Some(&2));
+        assert!(rbtree.apply(Vec::new()).is_ok());
+    }
+}
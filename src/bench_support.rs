@@ -0,0 +1,171 @@
+use crate::{Map, OrderedMap};
+
+/// Returns `n` pseudo random keys, same for the same `seed`.
+///
+/// # Examples
+///
+/// ```
+/// use treers::bench_support::shuffled_keys;
+///
+/// let keys = shuffled_keys(100, 42);
+/// assert_eq!(keys.len(), 100_usize);
+/// assert_eq!(keys, shuffled_keys(100, 42));
+/// assert_ne!(keys, shuffled_keys(100, 43));
+/// ```
+pub fn shuffled_keys(n: usize, seed: u64) -> Vec<u64> {
+    let mut state = seed;
+    (0..n)
+        .map(|_| {
+            state = next_state(state);
+            state >> 33
+        })
+        .collect()
+}
+
+// Knuth's MMIX linear congruential generator
+const fn next_state(state: u64) -> u64 {
+    state
+        .wrapping_mul(6_364_136_223_846_793_005)
+        .wrapping_add(1_442_695_040_888_963_407)
+}
+
+/// Insert-only workload, puts every key of `keys`.
+///
+/// Returns size of the map afterwards.
+///
+/// # Examples
+///
+/// ```
+/// use treers::bench_support::insert_only;
+/// use treers::rbtree::RedBlackTree;
+///
+/// let mut rbtree = RedBlackTree::new();
+/// assert_eq!(insert_only(&mut rbtree, &[3, 1, 2, 1]), 3_usize);
+/// ```
+pub fn insert_only<M: Map<u64, u64>>(map: &mut M, keys: &[u64]) -> usize {
+    for &k in keys {
+        map.put(k, k);
+    }
+    map.size()
+}
+
+/// Read-mostly workload, `ops` operations over `keys` where every tenth
+/// one is a put and the rest are gets.
+///
+/// Returns a count of hits, so a benchmark can't optimize the gets away.
+///
+/// # Examples
+///
+/// ```
+/// use treers::bench_support::{insert_only, read_write, shuffled_keys};
+/// use treers::btree::BalancedTree;
+///
+/// let keys = shuffled_keys(1000, 7);
+/// let mut btree = BalancedTree::new();
+/// insert_only(&mut btree, &keys[..500]);
+/// let hits = read_write(&mut btree, &keys, 10_000);
+/// assert!(hits > 0_usize && hits < 9000_usize);
+/// ```
+pub fn read_write<M: Map<u64, u64>>(map: &mut M, keys: &[u64], ops: usize) -> usize {
+    let mut hits = 0_usize;
+    if keys.is_empty() {
+        return hits;
+    }
+    let mut state = ops as u64;
+    for op in 0..ops {
+        state = next_state(state);
+        let k = keys[(state >> 33) as usize % keys.len()];
+        if op % 10_usize == 9_usize {
+            map.put(k, k);
+        } else if map.get(&k).is_some() {
+            hits += 1_usize;
+        }
+    }
+    hits
+}
+
+/// Delete-heavy workload, puts every key of `keys` and then takes back
+/// every other one by `delete`, followed by a lookup of every key.
+///
+/// Deletion isn't part of the `Map` trait, so the caller passes it in.
+/// Returns a count of keys still found.
+///
+/// # Examples
+///
+/// ```
+/// use treers::adapters::StdBTreeAdapter;
+/// use treers::bench_support::delete_heavy;
+///
+/// let keys: Vec<u64> = (0..100).collect();
+/// let mut map = StdBTreeAdapter::new();
+/// let left = delete_heavy(&mut map, &keys, |map, k| map.remove(k).is_some());
+/// assert_eq!(left, 50_usize);
+/// ```
+pub fn delete_heavy<M, F>(map: &mut M, keys: &[u64], mut delete: F) -> usize
+where
+    M: Map<u64, u64>,
+    F: FnMut(&mut M, &u64) -> bool,
+{
+    insert_only(map, keys);
+    for k in keys.iter().step_by(2_usize) {
+        delete(map, k);
+    }
+    keys.iter().filter(|k| map.contains(k)).count()
+}
+
+/// Range-scan workload, sums values of the `width` keys wide range
+/// starting at every key of `starts`.
+///
+/// # Examples
+///
+/// ```
+/// use treers::bench_support::{insert_only, range_scan};
+/// use treers::bst::BST;
+///
+/// let mut bst = BST::new();
+/// insert_only(&mut bst, &(0..100).collect::<Vec<u64>>());
+/// assert_eq!(range_scan(&bst, &[0, 95], 10), 45_u64 + 485);
+/// ```
+pub fn range_scan<M: OrderedMap<u64, u64>>(map: &M, starts: &[u64], width: u64) -> u64 {
+    starts
+        .iter()
+        .map(|&lo| {
+            map.range(lo..lo.saturating_add(width))
+                .map(|(_, v)| *v)
+                .fold(0_u64, u64::wrapping_add)
+        })
+        .fold(0_u64, u64::wrapping_add)
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::{delete_heavy, insert_only, range_scan, read_write, shuffled_keys};
+    use crate::adapters::StdBTreeAdapter;
+    use crate::bst::BST;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use crate::OrderedMap;
+
+    fn workloads<M: OrderedMap<u64, u64> + Default>() -> (usize, usize, u64) {
+        let keys = shuffled_keys(2000, 1);
+        let mut map = M::default();
+        let size = insert_only(&mut map, &keys[..1000]);
+        let hits = read_write(&mut map, &keys, 5000);
+        (size, hits, range_scan(&map, &keys[..100], 1 << 20))
+    }
+
+    #[test]
+    fn test_workloads_agree() {
+        let std = workloads::<StdBTreeAdapter<u64, u64>>();
+        assert_eq!(std, workloads::<BST<u64, u64>>());
+        assert_eq!(std, workloads::<RedBlackTree<u64, u64>>());
+        assert_eq!(std, workloads::<BalancedTree<u64, u64>>());
+        assert_eq!(read_write(&mut BST::new(), &[], 10), 0_usize);
+
+        let keys: Vec<u64> = (0_u64..1001).map(|i| i * 3).collect();
+        let mut map = StdBTreeAdapter::new();
+        let left = delete_heavy(&mut map, &keys, |map, k| map.remove(k).is_some());
+        assert_eq!(left, 500_usize);
+        assert_eq!(map.min(), Some(&3));
+    }
+}
@@ -1,6 +1,18 @@
-use crate::{SedgewickMap, TreeTraversal};
+use crate::entry::{Entry, OccupiedEntry, VacantEntry};
+use crate::node::{self, BinaryTreeNode};
+use crate::paren::{self, ParenNode, Parsed, Parts};
+use crate::pool::Pool;
+use crate::view::{self, SubrangeView, TreeView};
+#[cfg(feature = "rayon")]
+use crate::PARALLEL_CUTOFF;
+use crate::{
+    reaches_above, reaches_below, Map, OrderedMap, ParseTreeError, PutPolicy, SedgewickMap,
+    Traversals, TreeTraversal,
+};
 use std::cmp::Ordering;
-use std::ops::Index;
+use std::fmt::{self, Display};
+use std::ops::{Index, RangeBounds};
+use std::str::FromStr;
 
 /// 3.2 Binary Search Tree
 ///
@@ -10,7 +22,7 @@ use std::ops::Index;
 ///
 /// ```
 /// use treers::bst::BST;
-/// use treers::SedgewickMap;
+/// use treers::{Map, SedgewickMap};
 ///
 /// let mut bst: BST<char, i32> = BST::new();
 /// bst.put('c', 3);
@@ -30,7 +42,15 @@ use std::ops::Index;
 /// assert_eq!(bst.height(), Some(2_usize));
 /// ```
 #[derive(Debug)]
-pub enum BST<K: Ord, V> {
+pub struct BST<K: Ord, V> {
+    root: Tree<K, V>,
+    // Boxes of deleted nodes for new ones, kept by the root only
+    spare: Pool<BST<K, V>>,
+}
+
+// Nodes are only built inside the module, so sizes always add up
+#[derive(Debug)]
+enum Tree<K: Ord, V> {
     Node {
         k: K,
         v: V,
@@ -38,10 +58,10 @@ pub enum BST<K: Ord, V> {
         left: Box<BST<K, V>>,
         right: Box<BST<K, V>>,
     },
-    NIL,
+    Nil,
 }
 
-impl<K: Ord, V> SedgewickMap<K, V> for BST<K, V> {
+impl<K: Ord, V> BST<K, V> {
     /// Inits a new instance of Binary Search Tree.
     ///
     /// # Examples
@@ -50,47 +70,1941 @@ impl<K: Ord, V> SedgewickMap<K, V> for BST<K, V> {
     ///
     /// ```
     /// use treers::bst::BST;
-    /// use treers::SedgewickMap;
+    /// use treers::Map;
+    ///
+    /// let bst: BST<char, i32> = BST::new();
+    /// assert!(bst.is_empty());
+    /// ```
+    pub const fn new() -> Self {
+        BST {
+            root: Tree::Nil,
+            spare: Pool::new(),
+        }
+    }
+
+    /// Returns a handle to the root node, `None` for an empty `BST`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::Map;
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// bst.put('b', 2);
+    /// bst.put('a', 1);
+    ///
+    /// let root = bst.root().unwrap();
+    /// assert_eq!((root.key(), root.value()), (&'b', &2));
+    /// assert_eq!(root.size(), 2_usize);
+    /// assert_eq!(root.left().root().map(|n| *n.key()), Some('a'));
+    /// assert!(root.right().is_empty());
+    /// ```
+    pub const fn root(&self) -> Option<NodeRef<'_, K, V>> {
+        match self.root {
+            Tree::Node {
+                ref k,
+                ref v,
+                size,
+                ref left,
+                ref right,
+            } => Some(NodeRef {
+                k,
+                v,
+                size,
+                left,
+                right,
+            }),
+            Tree::Nil => None,
+        }
+    }
+
+    /// Returns the count of keys in the subtree rooted at the node of `key`,
+    /// the size the node keeps, `None` if `key` is missing.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::Map;
+    ///
+    /// let mut tree: BST<u32, ()> = BST::new();
+    /// for k in [4, 2, 6, 1, 3] {
+    ///     tree.put(k, ());
+    /// }
+    /// // 4 at the root, 2 above 1 and 3
+    /// assert_eq!(tree.subtree_size(&4), Some(5_usize));
+    /// assert_eq!(tree.subtree_size(&2), Some(3_usize));
+    /// assert_eq!(tree.subtree_size(&6), Some(1_usize));
+    /// assert_eq!(tree.subtree_size(&9), None);
+    /// ```
+    pub fn subtree_size(&self, key: &K) -> Option<usize> {
+        let node = self.root()?;
+        match key.cmp(node.key()) {
+            Ordering::Less => node.left().subtree_size(key),
+            Ordering::Greater => node.right().subtree_size(key),
+            _ => Some(node.size()),
+        }
+    }
+
+    // Splits off the root node, `Err` with the tree if it is empty
+    pub(crate) fn into_root(self) -> Result<(K, V, Self, Self), Self> {
+        match self.root {
+            Tree::Node {
+                k,
+                v,
+                size: _,
+                left,
+                right,
+            } => Ok((k, v, *left, *right)),
+            Tree::Nil => Err(BST::new()),
+        }
+    }
+
+    // Joins a node with its children, counting its size again
+    pub(crate) fn from_root(k: K, v: V, left: Self, right: Self) -> Self {
+        BST {
+            root: Tree::Node {
+                size: 1_usize + left.size() + right.size(),
+                k,
+                v,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+            spare: Pool::new(),
+        }
+    }
+
+    /// Returns the memory of deleted nodes to the allocator.
+    ///
+    /// `delete`, `delete_min` and `delete_max` keep the memory of the nodes
+    /// they remove, and `put` and `entry` build new nodes in it, so a tree
+    /// with as many deletes as puts stops allocating. Once a tree has shrunk
+    /// for good, this releases what it kept.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut queue: BST<u32, &str> = BST::new();
+    /// for (k, job) in [(3, "c"), (1, "a"), (2, "b")] {
+    ///     queue.put(k, job);
+    /// }
+    /// // the node of 4 takes the memory of the node of 1
+    /// assert_eq!(queue.delete_min(), Some((1, "a")));
+    /// queue.put(4, "d");
+    ///
+    /// while queue.delete_min().is_some() {}
+    /// queue.shrink_to_fit();
+    /// assert!(queue.is_empty());
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        self.spare.clear();
+    }
+
+    /// Removes `key`, returning its value, `None` if `key` is missing.
+    ///
+    /// A node with two children takes the key and value of its successor,
+    /// which is removed from the right subtree instead, as in Hibbard
+    /// deletion.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut bst: BST<u32, u32> = BST::new();
+    /// for k in [4, 2, 6, 1, 3, 5, 7] {
+    ///     bst.put(k, k * 10);
+    /// }
+    ///
+    /// assert_eq!(bst.delete(&4), Some(40));
+    /// assert_eq!(bst.delete(&4), None);
+    /// assert_eq!(bst.size(), 6_usize);
+    /// assert_eq!(bst.floor(&4), Some(&3));
+    /// assert_eq!(bst.root().map(|n| *n.key()), Some(5));
+    /// ```
+    pub fn delete(&mut self, key: &K) -> Option<V> {
+        let mut spare = std::mem::take(&mut self.spare);
+        let found = self.delete_in(key, &mut spare);
+        self.spare = spare;
+        found
+    }
+
+    /// Keeps only the entries for which `f` returns `true`, visiting them
+    /// in key order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut bst: BST<u32, u32> = BST::new();
+    /// for k in [4, 2, 6, 1, 3, 5, 7] {
+    ///     bst.put(k, k * 10);
+    /// }
+    ///
+    /// bst.retain(|k, _| k % 2 == 0);
+    /// assert_eq!(bst.size(), 3_usize);
+    /// assert!(bst.range(..).map(|(k, _)| *k).eq([2, 4, 6]));
+    /// ```
+    pub fn retain<F: FnMut(&K, &V) -> bool>(&mut self, mut f: F) {
+        let mut spare = std::mem::take(&mut self.spare);
+        self.retain_in(&mut f, &mut spare);
+        self.spare = spare;
+    }
+
+    // `delete` of a tree whose free nodes are in `spare`
+    fn delete_in(&mut self, key: &K, spare: &mut Pool<Self>) -> Option<V> {
+        match self.root {
+            Tree::Node {
+                ref k,
+                v: _,
+                size: _,
+                left: _,
+                right: _,
+            } if key.eq(k) => self.remove_root(spare).map(|(_, v)| v),
+            Tree::Node {
+                ref k,
+                v: _,
+                ref mut size,
+                ref mut left,
+                ref mut right,
+            } => {
+                let found = if key.lt(k) {
+                    left.delete_in(key, spare)
+                } else {
+                    right.delete_in(key, spare)
+                };
+                if found.is_some() {
+                    *size -= 1_usize;
+                }
+                found
+            }
+            Tree::Nil => None,
+        }
+    }
+
+    // `retain` of a tree whose free nodes are in `spare`, returns a count
+    // of removed nodes
+    fn retain_in<F: FnMut(&K, &V) -> bool>(&mut self, f: &mut F, spare: &mut Pool<Self>) -> usize {
+        let (removed, keep) = match self.root {
+            Tree::Node {
+                ref k,
+                ref v,
+                ref mut size,
+                ref mut left,
+                ref mut right,
+            } => {
+                let removed = left.retain_in(f, spare);
+                let keep = f(k, v);
+                let removed = removed + right.retain_in(f, spare);
+                *size -= removed;
+                (removed, keep)
+            }
+            Tree::Nil => return 0_usize,
+        };
+        if keep {
+            removed
+        } else {
+            self.remove_root(spare);
+            removed + 1_usize
+        }
+    }
+
+    // Removes the root node, its successor taking its place when it has
+    // two children, keeping the freed boxes in `spare`
+    fn remove_root(&mut self, spare: &mut Pool<Self>) -> Option<(K, V)> {
+        match self.root {
+            Tree::Node {
+                ref mut k,
+                ref mut v,
+                ref mut size,
+                ref left,
+                ref mut right,
+            } if !left.is_empty() && !right.is_empty() => {
+                right.remove_min(spare).map(|(next_k, next_v)| {
+                    *size -= 1_usize;
+                    (std::mem::replace(k, next_k), std::mem::replace(v, next_v))
+                })
+            }
+            _ => match std::mem::replace(&mut self.root, Tree::Nil) {
+                Tree::Node {
+                    k,
+                    v,
+                    size: _,
+                    mut left,
+                    mut right,
+                } => {
+                    let child = if left.is_empty() {
+                        &mut right
+                    } else {
+                        &mut left
+                    };
+                    self.root = std::mem::replace(&mut child.root, Tree::Nil);
+                    spare.keep(left);
+                    spare.keep(right);
+                    Some((k, v))
+                }
+                Tree::Nil => None,
+            },
+        }
+    }
+
+    // `put` of a tree whose free nodes are in `spare`
+    fn put_in(&mut self, key: K, value: V, spare: &mut Pool<Self>) {
+        match &mut self.root {
+            Tree::Node {
+                ref k,
+                ref mut v,
+                ref mut size,
+                ref mut left,
+                ref mut right,
+            } => {
+                match key.cmp(k) {
+                    Ordering::Less => left.put_in(key, value, spare),
+                    Ordering::Greater => right.put_in(key, value, spare),
+                    Ordering::Equal => *v = value,
+                }
+                *size = 1_usize + left.size() + right.size();
+            }
+            Tree::Nil => {
+                // Insert a leaf node
+                self.root = Tree::leaf(key, value, spare);
+            }
+        }
+    }
+
+    // Removes the leftmost node, keeping its boxes in `spare`
+    fn remove_min(&mut self, spare: &mut Pool<Self>) -> Option<(K, V)> {
+        match self.root {
+            Tree::Node {
+                k: _,
+                v: _,
+                ref mut size,
+                ref mut left,
+                right: _,
+            } if !left.is_empty() => {
+                *size -= 1_usize;
+                left.remove_min(spare)
+            }
+            _ => match std::mem::replace(&mut self.root, Tree::Nil) {
+                Tree::Node {
+                    k,
+                    v,
+                    size: _,
+                    left,
+                    mut right,
+                } => {
+                    self.root = std::mem::replace(&mut right.root, Tree::Nil);
+                    spare.keep(left);
+                    spare.keep(right);
+                    Some((k, v))
+                }
+                Tree::Nil => None,
+            },
+        }
+    }
+
+    // Removes the rightmost node, keeping its boxes in `spare`
+    fn remove_max(&mut self, spare: &mut Pool<Self>) -> Option<(K, V)> {
+        match self.root {
+            Tree::Node {
+                k: _,
+                v: _,
+                ref mut size,
+                left: _,
+                ref mut right,
+            } if !right.is_empty() => {
+                *size -= 1_usize;
+                right.remove_max(spare)
+            }
+            _ => match std::mem::replace(&mut self.root, Tree::Nil) {
+                Tree::Node {
+                    k,
+                    v,
+                    size: _,
+                    mut left,
+                    right,
+                } => {
+                    self.root = std::mem::replace(&mut left.root, Tree::Nil);
+                    spare.keep(left);
+                    spare.keep(right);
+                    Some((k, v))
+                }
+                Tree::Nil => None,
+            },
+        }
+    }
+
+    // Value of the root node, editing it leaves the shape as it is
+    pub(crate) const fn root_value_mut(&mut self) -> Option<&mut V> {
+        match self.root {
+            Tree::Node {
+                k: _,
+                ref mut v,
+                size: _,
+                left: _,
+                right: _,
+            } => Some(v),
+            Tree::Nil => None,
+        }
+    }
+}
+
+/// A node of a `BST`, borrowed for inspection.
+///
+/// Nodes can only be read through it, so sizes and key order stay the
+/// ones the tree keeps.
+#[derive(Debug)]
+pub struct NodeRef<'a, K: Ord, V> {
+    k: &'a K,
+    v: &'a V,
+    size: usize,
+    left: &'a BST<K, V>,
+    right: &'a BST<K, V>,
+}
+
+impl<K: Ord, V> Clone for NodeRef<'_, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: Ord, V> Copy for NodeRef<'_, K, V> {}
+
+impl<'a, K: Ord, V> NodeRef<'a, K, V> {
+    /// Returns the key of the node.
+    pub const fn key(&self) -> &'a K {
+        self.k
+    }
+
+    /// Returns the value of the node.
+    pub const fn value(&self) -> &'a V {
+        self.v
+    }
+
+    /// Returns the count of entries in the subtree of the node.
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the left subtree, keys less than the key of the node.
+    pub const fn left(&self) -> &'a BST<K, V> {
+        self.left
+    }
+
+    /// Returns the right subtree, keys greater than the key of the node.
+    pub const fn right(&self) -> &'a BST<K, V> {
+        self.right
+    }
+}
+
+/// A lazy in-order iterator over the entries of a `BST`, made by `iter`.
+///
+/// It keeps the path of left turns still to visit, at most `height + 1`
+/// nodes, so every `next` is `O(1)` amortized.
+#[derive(Debug)]
+pub struct Iter<'a, K: Ord, V> {
+    stack: Vec<&'a BST<K, V>>,
+    remaining: usize,
+}
+
+impl<K: Ord, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Iter {
+            stack: self.stack.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Iter<'a, K, V> {
+    fn new(tree: &'a BST<K, V>) -> Self {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            remaining: tree.size(),
+        };
+        iter.push_left(tree);
+        iter
+    }
+
+    // Stacks `tree` and its left spine, the smallest key on top
+    fn push_left(&mut self, mut tree: &'a BST<K, V>) {
+        while let Tree::Node {
+            k: _,
+            v: _,
+            size: _,
+            ref left,
+            right: _,
+        } = tree.root
+        {
+            self.stack.push(tree);
+            tree = left;
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &self.stack.pop()?.root {
+            Tree::Node {
+                ref k,
+                ref v,
+                size: _,
+                left: _,
+                ref right,
+            } => {
+                self.push_left(right);
+                self.remaining -= 1_usize;
+                Some((k, v))
+            }
+            Tree::Nil => None,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: Ord, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K: Ord, V> std::iter::FusedIterator for Iter<'_, K, V> {}
+
+impl<'a, K: Ord, V> IntoIterator for &'a BST<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A lazy in-order iterator taking apart a `BST`, made by `into_iter`.
+///
+/// It owns the path of left turns still to visit, with the right subtree
+/// of each of those nodes, and frees the nodes as it passes them.
+#[derive(Debug)]
+pub struct IntoIter<K: Ord, V> {
+    stack: Vec<(K, V, BST<K, V>)>,
+    remaining: usize,
+}
+
+impl<K: Ord, V> IntoIter<K, V> {
+    // Stacks `tree` and its left spine, the smallest key on top
+    fn push_left(&mut self, mut tree: BST<K, V>) {
+        while let Tree::Node {
+            k,
+            v,
+            size: _,
+            left,
+            right,
+        } = tree.root
+        {
+            self.stack.push((k, v, *right));
+            tree = *left;
+        }
+    }
+}
+
+impl<K: Ord, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (k, v, right) = self.stack.pop()?;
+        self.push_left(right);
+        self.remaining -= 1_usize;
+        Some((k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: Ord, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K: Ord, V> std::iter::FusedIterator for IntoIter<K, V> {}
+
+impl<K: Ord, V> IntoIterator for BST<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut iter = IntoIter {
+            stack: Vec::new(),
+            remaining: self.size(),
+        };
+        iter.push_left(self);
+        iter
+    }
+}
+
+impl<K: Ord, V> Map<K, V> for BST<K, V> {
+    /// Returns a size of elements in `BST`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::Map;
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// assert_eq!(bst.size(), 0_usize);
+    /// bst.put('a', 1);
+    /// bst.put('b', 2);
+    /// bst.put('c', 3);
+    /// bst.put('d', 4);
+    /// assert_eq!(bst.size(), 4_usize);
+    /// ```
+    fn size(&self) -> usize {
+        match self.root {
+            Tree::Node {
+                k: _,
+                v: _,
+                ref size,
+                left: _,
+                right: _,
+            } => *size,
+            _ => 0_usize,
+        }
+    }
+
+    /// Returns a reference to optional reference to value.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::Map;
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// bst.put('a', 1);
+    /// assert_eq!(bst.get(&'a'), Some(&1));
+    /// assert_eq!(bst.get(&'b'), None);
+    /// assert_eq!(bst[&'a'], 1);
+    /// ```
+    fn get(&self, key: &K) -> Option<&V> {
+        match &self.root {
+            Tree::Node {
+                ref k,
+                ref v,
+                size: _,
+                ref left,
+                ref right,
+            } => match key.cmp(k) {
+                Ordering::Less => left.get(key),
+                Ordering::Greater => right.get(key),
+                _ => Some(v),
+            },
+            _ => None,
+        }
+    }
+
+    /// Insert a key-value pair into the `BST`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::Map;
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// assert!(bst.is_empty());
+    ///
+    /// bst.put('a', 1_i32);
+    /// assert_eq!(bst.is_empty(), false);
+    /// assert_eq!(bst.get(&'a'), Some(&1_i32));
+    /// assert_eq!(bst[&'a'], 1_i32);
+    ///
+    /// bst.put('a', 2_i32);
+    /// assert_eq!(bst.get(&'a'), Some(&2_i32));
+    /// assert_eq!(bst.size(), 1_usize);
+    /// ```
+    fn put(&mut self, key: K, value: V) {
+        let mut spare = std::mem::take(&mut self.spare);
+        self.put_in(key, value, &mut spare);
+        self.spare = spare;
+    }
+
+    fn put_with_policy(&mut self, key: K, value: V, policy: &mut PutPolicy<'_, V>) -> bool {
+        match self.entry(key) {
+            Entry::Occupied(mut e) => {
+                policy.resolve(e.get_mut(), value);
+                false
+            }
+            Entry::Vacant(e) => {
+                e.insert(value);
+                true
+            }
+        }
+    }
+
+    /// Checks if `BST` node is empty.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::Map;
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// assert!(bst.is_empty());
+    /// bst.put('a', 2);
+    /// assert_eq!(bst.is_empty(), false);
+    /// ```
+    fn is_empty(&self) -> bool {
+        !matches!(self.root, Tree::Node { .. })
+    }
+}
+
+impl<K: Ord, V> OrderedMap<K, V> for BST<K, V> {
+    /// Returns a optional reference to minimal key
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// assert_eq!(bst.min(), None);
+    /// bst.put('c', 1);
+    /// bst.put('a', 2);
+    /// bst.put('b', 3);
+    /// bst.put('d', 4);
+    /// assert_eq!(bst.min(), Some(&'a'));
+    /// ```
+    fn min(&self) -> Option<&K> {
+        match &self.root {
+            Tree::Node {
+                ref k,
+                v: _,
+                size: _,
+                ref left,
+                right: _,
+            } => {
+                if let Some(l) = left.min() {
+                    Some(l)
+                } else {
+                    Some(k)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns a optional reference to maximum key
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// assert_eq!(bst.max(), None);
+    /// bst.put('c', 1);
+    /// bst.put('a', 2);
+    /// bst.put('b', 3);
+    /// bst.put('d', 4);
+    /// assert_eq!(bst.max(), Some(&'d'));
+    /// ```
+    fn max(&self) -> Option<&K> {
+        match &self.root {
+            Tree::Node {
+                ref k,
+                v: _,
+                size: _,
+                left: _,
+                ref right,
+            } => {
+                if let Some(l) = right.max() {
+                    Some(l)
+                } else {
+                    Some(k)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns a optional reference to the largest key less than or equal to `key`
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// assert_eq!(bst.floor(&'c'), None);
+    /// bst.put('b', 1);
+    /// bst.put('d', 2);
+    /// assert_eq!(bst.floor(&'a'), None);
+    /// assert_eq!(bst.floor(&'b'), Some(&'b'));
+    /// assert_eq!(bst.floor(&'c'), Some(&'b'));
+    /// assert_eq!(bst.floor(&'z'), Some(&'d'));
+    /// ```
+    fn floor(&self, key: &K) -> Option<&K> {
+        match &self.root {
+            Tree::Node {
+                ref k,
+                v: _,
+                size: _,
+                ref left,
+                ref right,
+            } => match key.cmp(k) {
+                Ordering::Less => left.floor(key),
+                Ordering::Greater => right.floor(key).or(Some(k)),
+                _ => Some(k),
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns a optional reference to the smallest key greater than or equal to `key`
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// assert_eq!(bst.ceiling(&'c'), None);
+    /// bst.put('b', 1);
+    /// bst.put('d', 2);
+    /// assert_eq!(bst.ceiling(&'a'), Some(&'b'));
+    /// assert_eq!(bst.ceiling(&'c'), Some(&'d'));
+    /// assert_eq!(bst.ceiling(&'d'), Some(&'d'));
+    /// assert_eq!(bst.ceiling(&'z'), None);
+    /// ```
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        match &self.root {
+            Tree::Node {
+                ref k,
+                v: _,
+                size: _,
+                ref left,
+                ref right,
+            } => match key.cmp(k) {
+                Ordering::Less => left.ceiling(key).or(Some(k)),
+                Ordering::Greater => right.ceiling(key),
+                _ => Some(k),
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the `n` entries with the smallest keys, in ascending order.
+    ///
+    /// Only the visited part of `BST` is walked, not the whole of it.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// bst.put('c', 3);
+    /// bst.put('a', 1);
+    /// bst.put('d', 4);
+    /// bst.put('b', 2);
+    /// assert_eq!(bst.smallest(2).as_slice(), &[(&'a', &1), (&'b', &2)]);
+    /// ```
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let mut vec = Vec::with_capacity(std::cmp::min(n, self.size()));
+        self.take_smallest(&mut vec, n);
+        vec.into_iter()
+    }
+
+    /// Returns the `n` entries with the largest keys, in descending order.
+    ///
+    /// Only the visited part of `BST` is walked, not the whole of it.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// bst.put('c', 3);
+    /// bst.put('a', 1);
+    /// bst.put('d', 4);
+    /// bst.put('b', 2);
+    /// assert_eq!(bst.largest(2).as_slice(), &[(&'d', &4), (&'c', &3)]);
+    /// ```
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let mut vec = Vec::with_capacity(std::cmp::min(n, self.size()));
+        self.take_largest(&mut vec, n);
+        vec.into_iter()
+    }
+
+    /// Returns entries of `BST` with keys inside `range`, in key order.
+    ///
+    /// Subtrees outside of `range` are not visited.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// for (v, k) in ('a'..='f').enumerate() {
+    ///     bst.put(k, v as i32);
+    /// }
+    /// assert_eq!(bst.range('b'..'d').as_slice(), &[(&'b', &1), (&'c', &2)]);
+    /// assert_eq!(bst.range('e'..).as_slice(), &[(&'e', &4), (&'f', &5)]);
+    /// assert_eq!(bst.range(..='a').as_slice(), &[(&'a', &0)]);
+    /// ```
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        let mut vec = Vec::new();
+        self.take_range(&range, &mut vec);
+        vec.into_iter()
+    }
+
+    fn try_fold_range<R, B, E, F>(&self, range: R, init: B, mut f: F) -> Result<B, E>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(B, &K, &V) -> Result<B, E>,
+    {
+        self.try_fold_at(&range, init, &mut f)
+    }
+}
+
+impl<K: Ord, V> SedgewickMap<K, V> for BST<K, V> {
+    /// Get height of `BST`.
+    ///
+    /// BST is not balanced tree, so in worst-case scenario, height will be
+    /// same as size, like a Linked-List.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// bst.put('a', 1);
+    /// bst.put('b', 2);
+    /// bst.put('c', 3);
+    /// bst.put('d', 4);
+    /// //  a         <-- height: 0
+    /// // / \
+    /// //    b       <-- height: 1
+    /// //   / \
+    /// //      c     <-- height: 2
+    /// //     / \
+    /// //        d   <-- height: 3
+    /// // Note -The Height of binary tree with single node is taken as zero.
+    /// assert_eq!(bst.get(&'a'), Some(&1_i32));
+    /// assert_eq!(bst.height(), Some(3_usize));
+    /// assert_eq!(bst.size(), 4_usize);
+    /// ```
+    fn height(&self) -> Option<usize> {
+        let h = self.get_height();
+        if h > 0_usize {
+            Some(h - 1)
+        } else {
+            None
+        }
+    }
+
+    fn leaf_depths(&self) -> Vec<usize> {
+        self.root.node().map_or_else(Vec::new, node::leaf_depths)
+    }
+
+    /// Removes the leftmost node, its right subtree takes its place.
+    ///
+    /// The memory of the node is kept for the next new nodes, until
+    /// `shrink_to_fit` is called.
+    fn delete_min(&mut self) -> Option<(K, V)> {
+        let mut spare = std::mem::take(&mut self.spare);
+        let min = self.remove_min(&mut spare);
+        self.spare = spare;
+        min
+    }
+
+    /// Removes the rightmost node, its left subtree takes its place.
+    ///
+    /// The memory of the node is kept for the next new nodes, until
+    /// `shrink_to_fit` is called.
+    fn delete_max(&mut self) -> Option<(K, V)> {
+        let mut spare = std::mem::take(&mut self.spare);
+        let max = self.remove_max(&mut spare);
+        self.spare = spare;
+        max
+    }
+
+    fn rank(&self, key: &K) -> usize {
+        match &self.root {
+            Tree::Node {
+                ref k,
+                v: _,
+                size: _,
+                ref left,
+                ref right,
+            } => match key.cmp(k) {
+                Ordering::Less => left.rank(key),
+                Ordering::Greater => left.size() + 1_usize + right.rank(key),
+                _ => left.size(),
+            },
+            _ => 0_usize,
+        }
+    }
+
+    fn select(&self, rank: usize) -> Option<&K> {
+        match &self.root {
+            Tree::Node {
+                ref k,
+                v: _,
+                size: _,
+                ref left,
+                ref right,
+            } => match rank.cmp(&left.size()) {
+                Ordering::Less => left.select(rank),
+                Ordering::Greater => right.select(rank - left.size() - 1_usize),
+                _ => Some(k),
+            },
+            _ => None,
+        }
+    }
+
+    fn partition_point<P: FnMut(&K) -> bool>(&self, mut pred: P) -> Option<&K> {
+        let mut found = None;
+        let mut tree = &self.root;
+        while let Tree::Node {
+            ref k,
+            v: _,
+            size: _,
+            ref left,
+            ref right,
+        } = tree
+        {
+            if pred(k) {
+                tree = &right.root;
+            } else {
+                found = Some(k);
+                tree = &left.root;
+            }
+        }
+        found
+    }
+
+    fn successor(&self, key: &K) -> Option<(&K, &V)> {
+        let mut found = None;
+        let mut tree = &self.root;
+        while let Tree::Node {
+            ref k,
+            ref v,
+            size: _,
+            ref left,
+            ref right,
+        } = tree
+        {
+            if key.lt(k) {
+                found = Some((k, v));
+                tree = &left.root;
+            } else {
+                tree = &right.root;
+            }
+        }
+        found
+    }
+
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)> {
+        let mut found = None;
+        let mut tree = &self.root;
+        while let Tree::Node {
+            ref k,
+            ref v,
+            size: _,
+            ref left,
+            ref right,
+        } = tree
+        {
+            if key.gt(k) {
+                found = Some((k, v));
+                tree = &right.root;
+            } else {
+                tree = &left.root;
+            }
+        }
+        found
+    }
+
+    fn clear(&mut self) {
+        // nodes are taken apart one by one, a recursive drop of a
+        // degenerate tree would take a stack frame per node
+        let mut stack = vec![std::mem::replace(&mut self.root, Tree::Nil)];
+        while let Some(tree) = stack.pop() {
+            if let Tree::Node {
+                k: _,
+                v: _,
+                size: _,
+                left,
+                right,
+            } = tree
+            {
+                stack.push(left.root);
+                stack.push(right.root);
+            }
+        }
+    }
+
+    fn drain(&mut self) -> std::vec::IntoIter<(K, V)> {
+        let mut vec = Vec::with_capacity(self.size());
+        // an in-order walk taking the nodes apart, the stack holds the
+        // nodes whose left subtrees are being drained
+        let mut stack = Vec::new();
+        let mut tree = std::mem::replace(&mut self.root, Tree::Nil);
+        loop {
+            match tree {
+                Tree::Node {
+                    k,
+                    v,
+                    size: _,
+                    left,
+                    right,
+                } => {
+                    stack.push((k, v, right));
+                    tree = left.root;
+                }
+                Tree::Nil => match stack.pop() {
+                    Some((k, v, right)) => {
+                        vec.push((k, v));
+                        tree = right.root;
+                    }
+                    None => break,
+                },
+            }
+        }
+        vec.into_iter()
+    }
+}
+
+// `left` and `right` skip empty subtrees, so only nodes are handed out
+impl<K: Ord, V> BinaryTreeNode for Tree<K, V> {
+    type Key = K;
+    type Value = V;
+
+    fn key(&self) -> &K {
+        match self {
+            Tree::Node {
+                ref k,
+                v: _,
+                size: _,
+                left: _,
+                right: _,
+            } => k,
+            Tree::Nil => unreachable!(),
+        }
+    }
+
+    fn value(&self) -> &V {
+        match self {
+            Tree::Node {
+                k: _,
+                ref v,
+                size: _,
+                left: _,
+                right: _,
+            } => v,
+            Tree::Nil => unreachable!(),
+        }
+    }
+
+    fn left(&self) -> Option<&Self> {
+        match self {
+            Tree::Node {
+                k: _,
+                v: _,
+                size: _,
+                ref left,
+                right: _,
+            } => left.root.node(),
+            Tree::Nil => None,
+        }
+    }
+
+    fn right(&self) -> Option<&Self> {
+        match self {
+            Tree::Node {
+                k: _,
+                v: _,
+                size: _,
+                left: _,
+                ref right,
+            } => right.root.node(),
+            Tree::Nil => None,
+        }
+    }
+}
+
+impl<K: Ord, V> Tree<K, V> {
+    // A node without children, their boxes taken from `spare`
+    fn leaf(k: K, v: V, spare: &mut Pool<BST<K, V>>) -> Self {
+        Tree::Node {
+            k,
+            v,
+            size: 1_usize,
+            left: spare.boxed(BST::new()),
+            right: spare.boxed(BST::new()),
+        }
+    }
+
+    const fn node(&self) -> Option<&Self> {
+        match self {
+            Tree::Node { .. } => Some(self),
+            Tree::Nil => None,
+        }
+    }
+}
+
+impl<K: Ord, V> TreeTraversal<K, V> for BST<K, V> {
+    /// Returns traverse pre ordered
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, Traversals, TreeTraversal};
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// bst.put('c', 3);
+    /// bst.put('d', 4);
+    /// bst.put('b', 2);
+    /// bst.put('a', 1);
+    /// //    c
+    /// //   / \
+    /// //  b   d
+    /// // /
+    /// // a
+    /// assert_eq!(bst.traverse(&Traversals::PreOrder).as_slice(),
+    ///       &[(&'c', &3), (&'b', &2), (&'a', &1), (&'d', &4)]);
+    /// ```
+    fn pre_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>) {
+        if let Some(node) = self.root.node() {
+            node::pre_order(node, vec);
+        }
+    }
+
+    /// Returns traverse in ordered
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, Traversals, TreeTraversal};
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// bst.put('c', 3);
+    /// bst.put('d', 4);
+    /// bst.put('b', 2);
+    /// bst.put('a', 1);
+    /// //    c
+    /// //   / \
+    /// //  b   d
+    /// // /
+    /// // a
+    /// assert_eq!(bst.traverse(&Traversals::InOrder).as_slice(),
+    ///       &[(&'a', &1), (&'b', &2), (&'c', &3), (&'d', &4)]);
+    /// ```
+    fn in_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>) {
+        if let Some(node) = self.root.node() {
+            node::in_order(node, vec);
+        }
+    }
+
+    /// Returns traverse post ordered
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, Traversals, TreeTraversal};
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// bst.put('c', 3);
+    /// bst.put('d', 4);
+    /// bst.put('b', 2);
+    /// bst.put('a', 1);
+    /// //    c
+    /// //   / \
+    /// //  b   d
+    /// // /
+    /// // a
+    /// assert_eq!(bst.traverse(&Traversals::PostOrder).as_slice(),
+    ///       &[(&'a', &1), (&'b', &2), (&'d', &4), (&'c', &3)]);
+    /// ```
+    fn post_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>) {
+        if let Some(node) = self.root.node() {
+            node::post_order(node, vec);
+        }
+    }
+
+    /// Returns traverse level ordered
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, Traversals, TreeTraversal};
+    ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// bst.put('c', 3);
+    /// bst.put('d', 4);
+    /// bst.put('b', 2);
+    /// bst.put('a', 1);
+    /// //    c
+    /// //   / \
+    /// //  b   d
+    /// // /
+    /// // a
+    /// assert_eq!(bst.traverse(&Traversals::LevelOrder).as_slice(),
+    ///       &[(&'c', &3), (&'b', &2), (&'d', &4), (&'a', &1)]);
+    /// ```
+    fn level_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>, level: usize) {
+        if let Some(node) = self.root.node() {
+            node::level_order(node, vec, level);
+        }
+    }
+}
+
+// internal methods
+impl<K: Ord, V> BST<K, V> {
+    fn get_height(&self) -> usize {
+        match &self.root {
+            Tree::Node {
+                k: _,
+                v: _,
+                size: _,
+                ref left,
+                ref right,
+            } => 1_usize + std::cmp::max(left.get_height(), right.get_height()),
+            _ => 0_usize,
+        }
+    }
+    fn take_smallest<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>, n: usize) {
+        if let Tree::Node {
+            ref k,
+            ref v,
+            size: _,
+            ref left,
+            ref right,
+        } = &self.root
+        {
+            left.take_smallest(vec, n);
+            if vec.len().lt(&n) {
+                vec.push((k, v));
+                right.take_smallest(vec, n);
+            }
+        }
+    }
+    fn take_largest<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>, n: usize) {
+        if let Tree::Node {
+            ref k,
+            ref v,
+            size: _,
+            ref left,
+            ref right,
+        } = &self.root
+        {
+            right.take_largest(vec, n);
+            if vec.len().lt(&n) {
+                vec.push((k, v));
+                left.take_largest(vec, n);
+            }
+        }
+    }
+    fn take_range<'a, R: RangeBounds<K>>(&'a self, range: &R, vec: &mut Vec<(&'a K, &'a V)>) {
+        if let Tree::Node {
+            ref k,
+            ref v,
+            size: _,
+            ref left,
+            ref right,
+        } = &self.root
+        {
+            if reaches_below(range, k) {
+                left.take_range(range, vec);
+            }
+            if range.contains(k) {
+                vec.push((k, v));
+            }
+            if reaches_above(range, k) {
+                right.take_range(range, vec);
+            }
+        }
+    }
+    /// Easter egg: invert a BST :)
+    pub fn invert(&mut self) {
+        if let Tree::Node {
+            k: _,
+            v: _,
+            size: _,
+            ref mut left,
+            ref mut right,
+        } = &mut self.root
+        {
+            left.invert();
+            right.invert();
+            std::mem::swap(left, right);
+        }
+    }
+
+    fn try_fold_at<R, B, E, F>(&self, range: &R, init: B, f: &mut F) -> Result<B, E>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(B, &K, &V) -> Result<B, E>,
+    {
+        let mut acc = init;
+        if let Tree::Node {
+            ref k,
+            ref v,
+            size: _,
+            ref left,
+            ref right,
+        } = &self.root
+        {
+            if reaches_below(range, k) {
+                acc = left.try_fold_at(range, acc, f)?;
+            }
+            if range.contains(k) {
+                acc = f(acc, k, v)?;
+            }
+            if reaches_above(range, k) {
+                acc = right.try_fold_at(range, acc, f)?;
+            }
+        }
+        Ok(acc)
+    }
+
+    /// Checks if `other` is built of nodes in exactly the same places,
+    /// keys and values aside.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::Map;
+    ///
+    /// let mut a: BST<char, i32> = BST::new();
+    /// let mut b: BST<u32, ()> = BST::new();
+    /// for (k, n) in [('b', 20), ('a', 10), ('c', 30)] {
+    ///     a.put(k, 0);
+    ///     b.put(n, ());
+    /// }
+    /// assert!(a.same_shape(&b));
+    ///
+    /// b.put(40, ());
+    /// assert!(!a.same_shape(&b));
+    /// ```
+    pub fn same_shape<L: Ord, W>(&self, other: &BST<L, W>) -> bool {
+        match (&self.root, &other.root) {
+            (
+                Tree::Node {
+                    k: _,
+                    v: _,
+                    size,
+                    ref left,
+                    ref right,
+                },
+                Tree::Node {
+                    k: _,
+                    v: _,
+                    size: other_size,
+                    left: ref other_left,
+                    right: ref other_right,
+                },
+            ) => {
+                size.eq(other_size) && left.same_shape(other_left) && right.same_shape(other_right)
+            }
+            (Tree::Nil, Tree::Nil) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if `other` turns into the shape of `self` by swapping
+    /// children of some nodes, keys and values aside.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::Map;
+    ///
+    /// let mut a: BST<i32, ()> = BST::new();
+    /// let mut b: BST<i32, ()> = BST::new();
+    /// for k in [2, 1, 3, 4] {
+    ///     a.put(k, ());
+    /// }
+    /// for k in [3, 4, 2, 1] {
+    ///     b.put(k, ());
+    /// }
+    /// assert!(!a.same_shape(&b));
+    /// assert!(a.is_isomorphic(&b));
+    ///
+    /// b.invert();
+    /// assert!(a.is_isomorphic(&b));
+    /// ```
+    pub fn is_isomorphic<L: Ord, W>(&self, other: &BST<L, W>) -> bool {
+        match (&self.root, &other.root) {
+            (
+                Tree::Node {
+                    k: _,
+                    v: _,
+                    size,
+                    ref left,
+                    ref right,
+                },
+                Tree::Node {
+                    k: _,
+                    v: _,
+                    size: other_size,
+                    left: ref other_left,
+                    right: ref other_right,
+                },
+            ) => {
+                // subtree sizes rule out most pairings without walking them
+                size.eq(other_size)
+                    && ((left.size().eq(&other_left.size())
+                        && left.is_isomorphic(other_left)
+                        && right.is_isomorphic(other_right))
+                        || (left.size().eq(&other_right.size())
+                            && left.is_isomorphic(other_right)
+                            && right.is_isomorphic(other_left)))
+            }
+            (Tree::Nil, Tree::Nil) => true,
+            _ => false,
+        }
+    }
+
+    /// Rebuilds the `BST` into a perfectly balanced shape, the same for
+    /// the same keys whatever order they were put in.
+    ///
+    /// Every node holds the middle entry of its subtree, the upper one of
+    /// two middles, so left subtrees are never smaller than right ones.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut a: BST<u32, ()> = BST::new();
+    /// let mut b: BST<u32, ()> = BST::new();
+    /// for k in 0..7 {
+    ///     a.put(k, ());
+    ///     b.put(6 - k, ());
+    /// }
+    /// assert_eq!(a.height(), Some(6_usize));
+    ///
+    /// a.canonicalize();
+    /// b.canonicalize();
+    /// assert_eq!(a.height(), Some(2_usize));
+    /// assert!(a.same_shape(&b));
+    /// ```
+    pub fn canonicalize(&mut self) {
+        let entries = std::mem::take(self).into_sorted_vec();
+        let size = entries.len();
+        *self = Self::balanced(&mut entries.into_iter(), size);
+    }
+
+    pub(crate) fn balanced<I: Iterator<Item = (K, V)>>(entries: &mut I, size: usize) -> Self {
+        if size.eq(&0_usize) {
+            return BST::new();
+        }
+        let left = Self::balanced(entries, size / 2_usize);
+        match entries.next() {
+            Some((k, v)) => BST {
+                root: Tree::Node {
+                    k,
+                    v,
+                    size,
+                    left: Box::new(left),
+                    right: Box::new(Self::balanced(entries, size - size / 2_usize - 1_usize)),
+                },
+                spare: Pool::new(),
+            },
+            None => BST::new(),
+        }
+    }
+
+    /// Looks up a batch of keys sorted in ascending order, returning values
+    /// in the same order as `keys`.
+    ///
+    /// Keys are split among subtrees as the tree is walked, so every node
+    /// is visited once for the whole batch, and subtrees without a key of
+    /// the batch are skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `keys` are not sorted.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::Map;
+    ///
+    /// let mut bst: BST<u32, char> = BST::new();
+    /// for (i, c) in ('a'..='j').enumerate() {
+    ///     bst.put(i as u32 * 10, c);
+    /// }
+    /// assert_eq!(
+    ///     bst.get_sorted(&[0, 5, 20, 20, 90, 100]),
+    ///     [Some(&'a'), None, Some(&'c'), Some(&'c'), Some(&'j'), None]
+    /// );
+    /// ```
+    pub fn get_sorted(&self, keys: &[K]) -> Vec<Option<&V>> {
+        debug_assert!(keys.windows(2).all(|w| w[0].le(&w[1])));
+        let mut out = vec![None; keys.len()];
+        self.fill_sorted(keys, &mut out);
+        out
+    }
+
+    fn fill_sorted<'a>(&'a self, keys: &[K], out: &mut [Option<&'a V>]) {
+        if keys.is_empty() {
+            return;
+        }
+        if let Tree::Node {
+            ref k,
+            ref v,
+            size: _,
+            ref left,
+            ref right,
+        } = &self.root
+        {
+            let lo = keys.partition_point(|q| q.lt(k));
+            let hi = lo + keys[lo..].partition_point(|q| q.eq(k));
+            left.fill_sorted(&keys[..lo], &mut out[..lo]);
+            out[lo..hi].iter_mut().for_each(|o| *o = Some(v));
+            right.fill_sorted(&keys[hi..], &mut out[hi..]);
+        }
+    }
+
+    /// Looks up `key` and rotates its node up to the root, a
+    /// self-adjusting lookup instead of `get`.
+    ///
+    /// Keys looked up often stay near the root, so skewed access
+    /// patterns get short paths without a balanced tree. This is plain
+    /// move-to-root, one rotation per level, not splaying. A missing key
+    /// leaves the tree as it was.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap, SedgewickMap};
+    ///
+    /// let mut bst = BST::new();
+    /// for k in 0..100 {
+    ///     bst.put(k, k * 2);
+    /// }
+    /// assert_eq!(bst.height(), Some(99_usize));
+    ///
+    /// assert_eq!(bst.get_to_root(&50), Some(&100));
+    /// assert_eq!(bst.height(), Some(50_usize));
+    /// assert_eq!(bst.get_to_root(&500), None);
+    /// assert_eq!(bst.floor(&70), Some(&70));
+    /// ```
+    pub fn get_to_root(&mut self, key: &K) -> Option<&V> {
+        if !self.move_to_root(key) {
+            return None;
+        }
+        match &mut self.root {
+            Tree::Node {
+                k: _,
+                ref v,
+                size: _,
+                left: _,
+                right: _,
+            } => Some(v),
+            Tree::Nil => None,
+        }
+    }
+
+    /// Puts `key` and rotates its node up to the root, so a key is found
+    /// quickly right after it was put. The self-adjusting counterpart of
+    /// `put`, also known as root insertion.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap};
     ///
-    /// let bst: BST<char, i32> = BST::new();
-    /// assert!(bst.is_empty());
+    /// let mut bst = BST::new();
+    /// bst.put_to_root('b', 2);
+    /// bst.put_to_root('a', 1);
+    /// bst.put_to_root('c', 3);
+    /// assert_eq!(bst.to_paren_string(), "c:3(a:1(-,b:2),-)");
+    /// assert_eq!(bst.size(), 3_usize);
     /// ```
-    fn new() -> Self {
-        BST::NIL
+    pub fn put_to_root(&mut self, key: K, value: V) {
+        match &mut self.root {
+            Tree::Node {
+                ref k,
+                ref mut v,
+                ref mut size,
+                ref mut left,
+                ref mut right,
+            } => match key.cmp(k) {
+                Ordering::Less => {
+                    left.put_to_root(key, value);
+                    *size = 1_usize + left.size() + right.size();
+                    self.rotate_right();
+                }
+                Ordering::Greater => {
+                    right.put_to_root(key, value);
+                    *size = 1_usize + left.size() + right.size();
+                    self.rotate_left();
+                }
+                Ordering::Equal => *v = value,
+            },
+            Tree::Nil => self.put(key, value),
+        }
     }
 
-    /// Returns a size of elements in `BST`.
+    // Rotates the node of `key` up to here, `false` if it is missing
+    fn move_to_root(&mut self, key: &K) -> bool {
+        match &mut self.root {
+            Tree::Node {
+                ref k,
+                v: _,
+                size: _,
+                ref mut left,
+                ref mut right,
+            } => match key.cmp(k) {
+                Ordering::Less => {
+                    let found = left.move_to_root(key);
+                    if found {
+                        self.rotate_right();
+                    }
+                    found
+                }
+                Ordering::Greater => {
+                    let found = right.move_to_root(key);
+                    if found {
+                        self.rotate_left();
+                    }
+                    found
+                }
+                Ordering::Equal => true,
+            },
+            Tree::Nil => false,
+        }
+    }
+
+    // Lifts the left child up, no-op without one
+    pub(crate) fn rotate_right(&mut self) {
+        if let Tree::Node {
+            k,
+            v,
+            size,
+            left,
+            right,
+        } = std::mem::take(self).root
+        {
+            self.root = match left.root {
+                Tree::Node {
+                    k: lk,
+                    v: lv,
+                    size: _,
+                    left: ll,
+                    right: lr,
+                } => Tree::Node {
+                    k: lk,
+                    v: lv,
+                    size,
+                    left: ll,
+                    right: Box::new(BST {
+                        root: Tree::Node {
+                            size: 1_usize + lr.size() + right.size(),
+                            k,
+                            v,
+                            left: lr,
+                            right,
+                        },
+                        spare: Pool::new(),
+                    }),
+                },
+                Tree::Nil => Tree::Node {
+                    k,
+                    v,
+                    size,
+                    left,
+                    right,
+                },
+            };
+        }
+    }
+
+    // Lifts the right child up, no-op without one
+    pub(crate) fn rotate_left(&mut self) {
+        if let Tree::Node {
+            k,
+            v,
+            size,
+            left,
+            right,
+        } = std::mem::take(self).root
+        {
+            self.root = match right.root {
+                Tree::Node {
+                    k: rk,
+                    v: rv,
+                    size: _,
+                    left: rl,
+                    right: rr,
+                } => Tree::Node {
+                    k: rk,
+                    v: rv,
+                    size,
+                    left: Box::new(BST {
+                        root: Tree::Node {
+                            size: 1_usize + left.size() + rl.size(),
+                            k,
+                            v,
+                            left,
+                            right: rl,
+                        },
+                        spare: Pool::new(),
+                    }),
+                    right: rr,
+                },
+                Tree::Nil => Tree::Node {
+                    k,
+                    v,
+                    size,
+                    left,
+                    right,
+                },
+            };
+        }
+    }
+
+    /// Returns the value of `key`, putting the value `f` returns first if the
+    /// key is missing.
+    ///
+    /// On an error of `f` nothing is put and the error is returned, so a
+    /// fallible constructor runs only for missing keys, without a separate
+    /// `contains` and `put`.
     ///
     /// # Examples
     ///
     /// Basic usage:
     ///
     /// ```
+    /// use std::num::ParseIntError;
     /// use treers::bst::BST;
-    /// use treers::SedgewickMap;
+    /// use treers::Map;
     ///
-    /// let mut bst: BST<char, i32> = BST::new();
-    /// assert_eq!(bst.size(), 0_usize);
-    /// bst.put('a', 1);
-    /// bst.put('b', 2);
-    /// bst.put('c', 3);
-    /// bst.put('d', 4);
-    /// assert_eq!(bst.size(), 4_usize);
+    /// let mut bst: BST<&str, u32> = BST::new();
+    /// let parsed: Result<&mut u32, ParseIntError> = bst.get_or_try_insert_with("a", || "12".parse());
+    /// *parsed.unwrap() += 1;
+    /// assert_eq!(bst.get(&"a"), Some(&13));
+    ///
+    /// // a present key never calls `f`
+    /// let again = bst.get_or_try_insert_with("a", || "not called".parse::<u32>());
+    /// assert_eq!(again.copied(), Ok(13));
+    ///
+    /// assert!(bst.get_or_try_insert_with("b", || "x".parse::<u32>()).is_err());
+    /// assert_eq!(bst.size(), 1_usize);
     /// ```
-    fn size(&self) -> usize {
-        match &self {
-            BST::Node {
-                k: _,
-                v: _,
-                ref size,
-                left: _,
-                right: _,
-            } => *size,
-            _ => 0_usize,
+    pub fn get_or_try_insert_with<E, F: FnOnce() -> Result<V, E>>(
+        &mut self,
+        key: K,
+        f: F,
+    ) -> Result<&mut V, E> {
+        match self.entry(key) {
+            Entry::Occupied(e) => Ok(e.into_mut()),
+            Entry::Vacant(e) => Ok(e.insert(f()?)),
         }
     }
 
-    /// Returns a reference to optional reference to value.
+    /// Returns the entry of `key`, found by one descent.
+    ///
+    /// A vacant entry keeps the sizes of the nodes on its path and only
+    /// bumps them when it is filled, so `or_insert` descends just once.
     ///
     /// # Examples
     ///
@@ -98,32 +2012,109 @@ impl<K: Ord, V> SedgewickMap<K, V> for BST<K, V> {
     ///
     /// ```
     /// use treers::bst::BST;
-    /// use treers::SedgewickMap;
+    /// use treers::Map;
     ///
-    /// let mut bst: BST<char, i32> = BST::new();
-    /// bst.put('a', 1);
-    /// assert_eq!(bst.get(&'a'), Some(&1));
-    /// assert_eq!(bst.get(&'b'), None);
-    /// assert_eq!(bst[&'a'], 1);
+    /// let mut bst: BST<&str, Vec<u32>> = BST::new();
+    /// bst.entry("a").or_insert_with(Vec::new).push(1);
+    /// bst.entry("a").or_insert_with(Vec::new).push(2);
+    /// assert_eq!(bst.get(&"a"), Some(&vec![1, 2]));
+    /// assert_eq!(bst.entry("b").key(), &"b");
+    /// assert_eq!(bst.size(), 1_usize);
     /// ```
-    fn get(&self, key: &K) -> Option<&V> {
-        match self {
-            BST::Node {
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        Self::entry_at(&mut self.root, key, Vec::new(), &mut self.spare)
+    }
+
+    fn entry_at<'a>(
+        tree: &'a mut Tree<K, V>,
+        key: K,
+        mut sizes: Vec<&'a mut usize>,
+        spare: &'a mut Pool<Self>,
+    ) -> Entry<'a, K, V> {
+        match *tree {
+            Tree::Node {
                 ref k,
-                ref v,
-                size: _,
-                ref left,
-                ref right,
+                ref mut v,
+                ref mut size,
+                ref mut left,
+                ref mut right,
             } => match key.cmp(k) {
-                Ordering::Less => left.get(key),
-                Ordering::Greater => right.get(key),
-                _ => Some(v),
+                Ordering::Less => {
+                    sizes.push(size);
+                    Self::entry_at(&mut left.root, key, sizes, spare)
+                }
+                Ordering::Greater => {
+                    sizes.push(size);
+                    Self::entry_at(&mut right.root, key, sizes, spare)
+                }
+                _ => Entry::Occupied(OccupiedEntry::new(key, v)),
             },
-            _ => None,
+            Tree::Nil => Entry::Vacant(VacantEntry::new(
+                key,
+                Box::new(move |key, value| {
+                    for size in sizes {
+                        *size += 1_usize;
+                    }
+                    *tree = Tree::leaf(key, value, spare);
+                    match *tree {
+                        Tree::Node {
+                            k: _,
+                            ref mut v,
+                            size: _,
+                            left: _,
+                            right: _,
+                        } => v,
+                        // a leaf was just put in its place
+                        Tree::Nil => unreachable!(),
+                    }
+                }),
+            )),
         }
     }
 
-    /// Insert a key-value pair into the `BST`.
+    /// Streams entries to `writer` in key order, each one written by `fmt`,
+    /// then flushes it.
+    ///
+    /// Entries go to `fmt` as the tree is walked, nothing is collected on
+    /// the way, so a tree of any size is exported in constant memory.
+    /// `dump::tsv` and `dump::jsonl` write common formats, a closure any
+    /// other; pass files in a `BufWriter`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use treers::bst::BST;
+    /// use treers::{dump, Map};
+    ///
+    /// let mut bst: BST<u32, &str> = BST::new();
+    /// bst.put(2, "b");
+    /// bst.put(1, "a");
+    ///
+    /// let mut out = Vec::new();
+    /// bst.write_in_order(&mut out, dump::tsv).unwrap();
+    /// assert_eq!(out, b"1\ta\n2\tb\n");
+    ///
+    /// let mut out = Vec::new();
+    /// bst.write_in_order(&mut out, |w, k, v| write!(w, "{}={};", k, v)).unwrap();
+    /// assert_eq!(out, b"1=a;2=b;");
+    /// ```
+    pub fn write_in_order<W, F>(&self, mut writer: W, mut fmt: F) -> std::io::Result<()>
+    where
+        W: std::io::Write,
+        F: FnMut(&mut W, &K, &V) -> std::io::Result<()>,
+    {
+        if let Some(node) = self.root.node() {
+            node::try_in_order(node, &mut |k: &K, v: &V| fmt(&mut writer, k, v))?;
+        }
+        writer.flush()
+    }
+
+    /// Rebuilds the `BST` with the same shape, transforming every value with `f`.
+    ///
+    /// `f` is called once per entry, in key order.
     ///
     /// # Examples
     ///
@@ -131,49 +2122,74 @@ impl<K: Ord, V> SedgewickMap<K, V> for BST<K, V> {
     ///
     /// ```
     /// use treers::bst::BST;
-    /// use treers::SedgewickMap;
+    /// use treers::{Map, SedgewickMap};
     ///
     /// let mut bst: BST<char, i32> = BST::new();
-    /// assert!(bst.is_empty());
+    /// bst.put('b', 2);
+    /// bst.put('a', 1);
+    /// bst.put('c', 3);
     ///
-    /// bst.put('a', 1_i32);
-    /// assert_eq!(bst.is_empty(), false);
-    /// assert_eq!(bst.get(&'a'), Some(&1_i32));
-    /// assert_eq!(bst[&'a'], 1_i32);
+    /// let bst: BST<char, String> = bst.map_values(|k, v| format!("{}{}", k, v));
+    /// assert_eq!(bst.get(&'a'), Some(&String::from("a1")));
+    /// assert_eq!(bst.get(&'c'), Some(&String::from("c3")));
+    /// assert_eq!(bst.height(), Some(1_usize));
     /// ```
-    fn put(&mut self, key: K, value: V) {
-        match self {
-            BST::Node {
-                ref k,
-                v: _,
-                ref mut size,
-                ref mut left,
-                ref mut right,
+    pub fn map_values<U, F: FnMut(&K, V) -> U>(self, mut f: F) -> BST<K, U> {
+        self.map_with(&mut f)
+    }
+
+    fn map_with<U, F: FnMut(&K, V) -> U>(self, f: &mut F) -> BST<K, U> {
+        match self.root {
+            Tree::Node {
+                k,
+                v,
+                size,
+                left,
+                right,
             } => {
-                match key.cmp(k) {
-                    Ordering::Less => left.put(key, value),
-                    Ordering::Greater => right.put(key, value),
-                    _ => {}
-                }
-                *size = 1_usize + left.size() + right.size();
-            }
-            BST::NIL => {
-                // Insert a leaf node
-                *self = BST::Node {
-                    k: key,
-                    v: value,
-                    size: 1,
-                    left: Box::new(BST::NIL),
-                    right: Box::new(BST::NIL),
+                let left = Box::new((*left).map_with(f));
+                let v = f(&k, v);
+                let right = Box::new((*right).map_with(f));
+                BST {
+                    root: Tree::Node {
+                        k,
+                        v,
+                        size,
+                        left,
+                        right,
+                    },
+                    spare: Pool::new(),
                 }
             }
+            Tree::Nil => BST::new(),
         }
     }
 
-    /// Get height of `BST`.
+    /// Returns a lazy iterator over entries in key order, nothing is
+    /// collected up front, so stopping early skips the rest of the tree.
     ///
-    /// BST is not balanced tree, so in worst-case scenario, height will be
-    /// same as size, like a Linked-List.
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, SedgewickMap, Traversals, TreeTraversal};
+    ///
+    /// let mut bst: BST<u32, char> = BST::new();
+    /// for (k, v) in [(3, 'c'), (1, 'a'), (4, 'd'), (2, 'b')] {
+    ///     bst.put(k, v);
+    /// }
+    /// let first_two: Vec<(&u32, &char)> = bst.iter().take(2).collect();
+    /// assert_eq!(first_two, [(&1, &'a'), (&2, &'b')]);
+    /// assert!(bst.iter().eq(bst.traverse(&Traversals::InOrder)));
+    /// assert_eq!((&bst).into_iter().len(), 4_usize);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter::new(self)
+    }
+
+    /// Returns cloned key-value pairs of `BST` in key order.
     ///
     /// # Examples
     ///
@@ -181,36 +2197,67 @@ impl<K: Ord, V> SedgewickMap<K, V> for BST<K, V> {
     ///
     /// ```
     /// use treers::bst::BST;
-    /// use treers::SedgewickMap;
+    /// use treers::Map;
     ///
     /// let mut bst: BST<char, i32> = BST::new();
+    /// bst.put('c', 3);
     /// bst.put('a', 1);
     /// bst.put('b', 2);
-    /// bst.put('c', 3);
-    /// bst.put('d', 4);
-    /// //  a         <-- height: 0
-    /// // / \
-    /// //    b       <-- height: 1
-    /// //   / \
-    /// //      c     <-- height: 2
-    /// //     / \
-    /// //        d   <-- height: 3
-    /// // Note -The Height of binary tree with single node is taken as zero.
-    /// assert_eq!(bst.get(&'a'), Some(&1_i32));
-    /// assert_eq!(bst.height(), Some(3_usize));
-    /// assert_eq!(bst.size(), 4_usize);
+    /// assert_eq!(bst.to_sorted_vec(), vec![('a', 1), ('b', 2), ('c', 3)]);
+    /// assert_eq!(bst.size(), 3_usize);
     /// ```
-    fn height(&self) -> Option<usize> {
-        let h = self.get_height();
-        if h > 0_usize {
-            Some(h - 1)
-        } else {
-            None
+    pub fn to_sorted_vec(&self) -> Vec<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut vec = Vec::with_capacity(self.size());
+        for (k, v) in self.traverse(&Traversals::InOrder) {
+            vec.push((k.clone(), v.clone()));
         }
+        vec
     }
 
-    /// Checks if `BST` node is empty.
+    /// Consumes `BST`, returning its key-value pairs in key order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
     ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::Map;
+    ///
+    /// let mut bst: BST<char, String> = BST::new();
+    /// bst.put('b', String::from("b"));
+    /// bst.put('a', String::from("a"));
+    /// assert_eq!(
+    ///     bst.into_sorted_vec(),
+    ///     vec![('a', String::from("a")), ('b', String::from("b"))]
+    /// );
+    /// ```
+    pub fn into_sorted_vec(self) -> Vec<(K, V)> {
+        let mut vec = Vec::with_capacity(self.size());
+        self.into_in_order(&mut vec);
+        vec
+    }
+
+    fn into_in_order(self, vec: &mut Vec<(K, V)>) {
+        if let Tree::Node {
+            k,
+            v,
+            size: _,
+            left,
+            right,
+        } = self.root
+        {
+            left.into_in_order(vec);
+            vec.push((k, v));
+            right.into_in_order(vec);
+        }
+    }
+
+    /// Consumes `BST`, returning its keys in key order.
     ///
     /// # Examples
     ///
@@ -218,19 +2265,20 @@ impl<K: Ord, V> SedgewickMap<K, V> for BST<K, V> {
     ///
     /// ```
     /// use treers::bst::BST;
-    /// use treers::SedgewickMap;
+    /// use treers::Map;
     ///
     /// let mut bst: BST<char, i32> = BST::new();
-    /// assert!(bst.is_empty());
-    /// bst.put('a', 2);
-    /// assert_eq!(bst.is_empty(), false);
+    /// bst.put('b', 2);
+    /// bst.put('a', 1);
+    /// bst.put('c', 3);
+    /// let keys: Vec<char> = bst.into_keys().collect();
+    /// assert_eq!(keys, vec!['a', 'b', 'c']);
     /// ```
-    fn is_empty(&self) -> bool {
-        !matches!(*self, BST::Node { .. })
+    pub fn into_keys(self) -> impl Iterator<Item = K> {
+        self.into_iter().map(|(k, _)| k)
     }
 
-    /// Returns a optional reference to minimal key
-    ///
+    /// Consumes `BST`, returning its values in key order.
     ///
     /// # Examples
     ///
@@ -238,37 +2286,40 @@ impl<K: Ord, V> SedgewickMap<K, V> for BST<K, V> {
     ///
     /// ```
     /// use treers::bst::BST;
-    /// use treers::SedgewickMap;
+    /// use treers::Map;
     ///
     /// let mut bst: BST<char, i32> = BST::new();
-    /// assert_eq!(bst.min(), None);
-    /// bst.put('c', 1);
-    /// bst.put('a', 2);
-    /// bst.put('b', 3);
-    /// bst.put('d', 4);
-    /// assert_eq!(bst.min(), Some(&'a'));
+    /// bst.put('b', 2);
+    /// bst.put('a', 1);
+    /// bst.put('c', 3);
+    /// let values: Vec<i32> = bst.into_values().collect();
+    /// assert_eq!(values, vec![1, 2, 3]);
     /// ```
-    fn min(&self) -> Option<&K> {
-        match self {
-            BST::Node {
-                ref k,
-                v: _,
-                size: _,
-                ref left,
-                right: _,
-            } => {
-                if let Some(l) = left.min() {
-                    Some(l)
-                } else {
-                    Some(k)
-                }
-            }
-            _ => None,
-        }
+    pub fn into_values(self) -> impl Iterator<Item = V> {
+        self.into_iter().map(|(_, v)| v)
     }
 
-    /// Returns a optional reference to maximum key
+    /// Returns a read-only, copyable view over `BST`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::Map;
     ///
+    /// let mut bst: BST<char, i32> = BST::new();
+    /// bst.put('a', 1);
+    /// let view = bst.view();
+    /// assert_eq!(view.get(&'a'), Some(&1));
+    /// assert_eq!(view.size(), 1_usize);
+    /// ```
+    pub fn view(&self) -> TreeView<'_, K, V> {
+        TreeView::from(self)
+    }
+
+    /// Returns a read-only view over keys of `BST` inside `range`.
     ///
     /// # Examples
     ///
@@ -276,117 +2327,145 @@ impl<K: Ord, V> SedgewickMap<K, V> for BST<K, V> {
     ///
     /// ```
     /// use treers::bst::BST;
-    /// use treers::SedgewickMap;
+    /// use treers::{Map, OrderedMap};
     ///
     /// let mut bst: BST<char, i32> = BST::new();
-    /// assert_eq!(bst.max(), None);
-    /// bst.put('c', 1);
-    /// bst.put('a', 2);
-    /// bst.put('b', 3);
-    /// bst.put('d', 4);
-    /// assert_eq!(bst.max(), Some(&'d'));
+    /// for (v, k) in ('a'..='f').enumerate() {
+    ///     bst.put(k, v as i32);
+    /// }
+    /// let sub = bst.subrange_view('b'..'e');
+    /// assert_eq!(sub.size(), 3_usize);
+    /// assert_eq!(sub.min(), Some(&'b'));
+    /// assert_eq!(sub.max(), Some(&'d'));
+    /// assert_eq!(sub.get(&'f'), None);
     /// ```
+    pub fn subrange_view<R: RangeBounds<K>>(&self, range: R) -> SubrangeView<'_, K, V>
+    where
+        K: Clone,
+    {
+        self.view().subrange(range)
+    }
+}
+
+impl<K: Ord, V> view::Read<K, V> for BST<K, V> {
+    fn size(&self) -> usize {
+        Map::size(self)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        Map::get(self, key)
+    }
+
+    fn min(&self) -> Option<&K> {
+        OrderedMap::min(self)
+    }
+
     fn max(&self) -> Option<&K> {
-        match self {
-            BST::Node {
-                ref k,
-                v: _,
-                size: _,
-                left: _,
-                ref right,
-            } => {
-                if let Some(l) = right.max() {
-                    Some(l)
-                } else {
-                    Some(k)
-                }
-            }
-            _ => None,
-        }
+        OrderedMap::max(self)
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        OrderedMap::floor(self, key)
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        OrderedMap::ceiling(self, key)
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        OrderedMap::range(self, range)
+    }
+
+    fn rank(&self, key: &K) -> usize {
+        SedgewickMap::rank(self, key)
+    }
+
+    fn successor(&self, key: &K) -> Option<(&K, &V)> {
+        SedgewickMap::successor(self, key)
+    }
+
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)> {
+        SedgewickMap::predecessor(self, key)
     }
 }
 
-impl<K: Ord + Clone, V: Clone> TreeTraversal<K, V> for BST<K, V> {
-    /// Returns traverse pre ordered
-    ///
+#[cfg(feature = "rayon")]
+impl<K: Ord + Sync, V: Sync> BST<K, V> {
+    /// Calls `f` on every entry with key inside `range`, splitting the
+    /// walk across subtrees on multiple threads. Entries are visited in no
+    /// particular order.
     ///
     /// # Examples
     ///
     /// Basic usage:
     ///
     /// ```
+    /// use std::sync::atomic::{AtomicU64, Ordering};
     /// use treers::bst::BST;
-    /// use treers::{SedgewickMap, TreeTraversal, Traversals};
+    /// use treers::Map;
     ///
-    /// let mut bst: BST<char, i32> = BST::new();
-    /// bst.put('c', 3);
-    /// bst.put('d', 4);
-    /// bst.put('b', 2);
-    /// bst.put('a', 1);
-    /// //    c
-    /// //   / \
-    /// //  b   d
-    /// // /
-    /// // a
-    /// assert_eq!(bst.traverse(&Traversals::PreOrder).as_slice(),
-    ///       &[(&'c', &3), (&'b', &2), (&'a', &1), (&'d', &4)]);
+    /// let mut bst: BST<u64, u64> = BST::new();
+    /// for i in 0..10_000 {
+    ///     bst.put((i * 7919) % 10_000, 1);
+    /// }
+    ///
+    /// let sum = AtomicU64::new(0);
+    /// bst.par_range_for_each(100..200, |k, _| {
+    ///     sum.fetch_add(*k, Ordering::Relaxed);
+    /// });
+    /// assert_eq!(sum.into_inner(), (100..200).sum::<u64>());
     /// ```
-    fn pre_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>) {
-        if let BST::Node {
-            ref k,
-            ref v,
-            size: _,
-            ref left,
-            ref right,
-        } = self
-        {
-            vec.push((k, v));
-            left.pre_order(vec);
-            right.pre_order(vec);
-        }
+    pub fn par_range_for_each<R, F>(&self, range: R, f: F)
+    where
+        R: RangeBounds<K> + Sync,
+        F: Fn(&K, &V) + Sync,
+    {
+        self.par_range_walk(&range, &f);
     }
 
-    /// Returns traverse in ordered
-    ///
-    ///
-    /// # Examples
-    ///
-    /// Basic usage:
-    ///
-    /// ```
-    /// use treers::bst::BST;
-    /// use treers::{SedgewickMap, TreeTraversal, Traversals};
-    ///
-    /// let mut bst: BST<char, i32> = BST::new();
-    /// bst.put('c', 3);
-    /// bst.put('d', 4);
-    /// bst.put('b', 2);
-    /// bst.put('a', 1);
-    /// //    c
-    /// //   / \
-    /// //  b   d
-    /// // /
-    /// // a
-    /// assert_eq!(bst.traverse(&Traversals::InOrder).as_slice(),
-    ///       &[(&'a', &1), (&'b', &2), (&'c', &3), (&'d', &4)]);
-    /// ```
-    fn in_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>) {
-        if let BST::Node {
+    fn par_range_walk<R, F>(&self, range: &R, f: &F)
+    where
+        R: RangeBounds<K> + Sync,
+        F: Fn(&K, &V) + Sync,
+    {
+        if self.size().lt(&PARALLEL_CUTOFF) {
+            let mut vec = Vec::new();
+            self.take_range(range, &mut vec);
+            return vec.into_iter().for_each(|(k, v)| f(k, v));
+        }
+        if let Tree::Node {
             ref k,
             ref v,
             size: _,
             ref left,
             ref right,
-        } = self
+        } = &self.root
         {
-            left.in_order(vec);
-            vec.push((k, v));
-            right.in_order(vec);
+            rayon::join(
+                || {
+                    if reaches_below(range, k) {
+                        left.par_range_walk(range, f);
+                    }
+                },
+                || {
+                    if reaches_above(range, k) {
+                        right.par_range_walk(range, f);
+                    }
+                },
+            );
+            if range.contains(k) {
+                f(k, v);
+            }
         }
     }
+}
 
-    /// Returns traverse post ordered
+/// Queries of maps keyed by pairs.
+impl<A: Ord, B: Ord, V> BST<(A, B), V> {
+    /// Returns entries whose key starts with `prefix`, in key order.
     ///
+    /// Subtrees with first components on the wrong side of `prefix` are
+    /// skipped, so only the matching part of the tree is walked.
     ///
     /// # Examples
     ///
@@ -394,38 +2473,55 @@ impl<K: Ord + Clone, V: Clone> TreeTraversal<K, V> for BST<K, V> {
     ///
     /// ```
     /// use treers::bst::BST;
-    /// use treers::{SedgewickMap, TreeTraversal, Traversals};
+    /// use treers::Map;
     ///
-    /// let mut bst: BST<char, i32> = BST::new();
-    /// bst.put('c', 3);
-    /// bst.put('d', 4);
-    /// bst.put('b', 2);
-    /// bst.put('a', 1);
-    /// //    c
-    /// //   / \
-    /// //  b   d
-    /// // /
-    /// // a
-    /// assert_eq!(bst.traverse(&Traversals::PostOrder).as_slice(),
-    ///       &[(&'a', &1), (&'b', &2), (&'d', &4), (&'c', &3)]);
+    /// let mut bst: BST<(&str, u32), char> = BST::new();
+    /// bst.put(("bob", 2), 'b');
+    /// bst.put(("ann", 1), 'a');
+    /// bst.put(("bob", 1), 'c');
+    /// bst.put(("cid", 1), 'd');
+    /// assert_eq!(
+    ///     bst.range_prefix(&"bob").as_slice(),
+    ///     &[(&("bob", 1), &'c'), (&("bob", 2), &'b')]
+    /// );
+    /// assert_eq!(bst.range_prefix(&"dan").len(), 0_usize);
     /// ```
-    fn post_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>) {
-        if let BST::Node {
+    pub fn range_prefix(&self, prefix: &A) -> std::vec::IntoIter<(&(A, B), &V)> {
+        let mut vec = Vec::new();
+        self.take_prefix(prefix, &mut vec);
+        vec.into_iter()
+    }
+
+    fn take_prefix<'a>(&'a self, prefix: &A, vec: &mut Vec<(&'a (A, B), &'a V)>) {
+        if let Tree::Node {
             ref k,
             ref v,
             size: _,
             ref left,
             ref right,
-        } = self
+        } = &self.root
         {
-            left.post_order(vec);
-            right.post_order(vec);
-            vec.push((k, v));
+            let ord = k.0.cmp(prefix);
+            if ord.ne(&Ordering::Less) {
+                left.take_prefix(prefix, vec);
+            }
+            if ord.eq(&Ordering::Equal) {
+                vec.push((k, v));
+            }
+            if ord.ne(&Ordering::Greater) {
+                right.take_prefix(prefix, vec);
+            }
         }
     }
+}
 
-    /// Returns traverse level ordered
+impl<K: Ord + Display, V: Display> BST<K, V> {
+    /// Returns the exact shape of the tree in a parenthesized notation,
+    /// read back by `parse`.
     ///
+    /// A node is `key:value(left,right)`, an empty tree `-`, and a node
+    /// without children leaves the parentheses out. Keys and values must
+    /// not display `:`, `,` or parentheses.
     ///
     /// # Examples
     ///
@@ -433,80 +2529,93 @@ impl<K: Ord + Clone, V: Clone> TreeTraversal<K, V> for BST<K, V> {
     ///
     /// ```
     /// use treers::bst::BST;
-    /// use treers::{SedgewickMap, TreeTraversal, Traversals};
+    /// use treers::Map;
     ///
-    /// let mut bst: BST<char, i32> = BST::new();
-    /// bst.put('c', 3);
-    /// bst.put('d', 4);
-    /// bst.put('b', 2);
-    /// bst.put('a', 1);
-    /// //    c
-    /// //   / \
-    /// //  b   d
-    /// // /
-    /// // a
-    /// assert_eq!(bst.traverse(&Traversals::LevelOrder).as_slice(),
-    ///       &[(&'c', &3), (&'b', &2), (&'d', &4), (&'a', &1)]);
+    /// let mut bst = BST::new();
+    /// for (k, v) in [('c', 3), ('b', 2), ('d', 4), ('a', 1)] {
+    ///     bst.put(k, v);
+    /// }
+    /// assert_eq!(bst.to_paren_string(), "c:3(b:2(a:1,-),d:4)");
+    ///
+    /// let parsed: BST<char, i32> = "c:3(b:2(a:1,-),d:4)".parse().unwrap();
+    /// assert_eq!(parsed.to_paren_string(), bst.to_paren_string());
+    /// assert_eq!(parsed.size(), 4_usize);
     /// ```
-    fn level_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>, level: usize) {
-        if let BST::Node {
-            ref k,
-            ref v,
-            size: _,
-            ref left,
-            ref right,
-        } = self
-        {
-            match level {
-                0 => vec.push((k, v)),
-                _ => {
-                    left.level_order(vec, level - 1);
-                    right.level_order(vec, level - 1);
-                }
-            }
-        }
+    pub fn to_paren_string(&self) -> String {
+        let mut out = String::new();
+        paren::write(self, &mut out);
+        out
     }
 }
 
-// internal methods
-impl<K: Ord, V> BST<K, V> {
-    fn get_height(&self) -> usize {
-        match self {
-            BST::Node {
-                k: _,
-                v: _,
+impl<K: Ord + Display, V: Display> ParenNode for BST<K, V> {
+    type Key = K;
+    type Value = V;
+
+    fn parts(&self) -> Option<Parts<'_, Self>> {
+        match &self.root {
+            Tree::Node {
+                ref k,
+                ref v,
                 size: _,
                 ref left,
                 ref right,
-            } => 1_usize + std::cmp::max(left.get_height(), right.get_height()),
-            _ => 0_usize,
+            } => Some((k, v, false, left, right)),
+            Tree::Nil => None,
         }
     }
-    /// Easter egg: invert a BST :)
-    pub fn invert(&mut self) {
-        if let BST::Node {
-            k: _,
-            v: _,
-            size: _,
-            ref mut left,
-            ref mut right,
-        } = self
-        {
-            left.invert();
-            right.invert();
-            std::mem::swap(left, right);
+}
+
+impl<K: Ord, V> BST<K, V> {
+    fn from_parsed(parsed: Parsed<K, V>) -> Self {
+        match parsed {
+            Parsed::Node {
+                at: _,
+                k,
+                v,
+                red,
+                left,
+                right,
+            } => {
+                // parsed without colors
+                debug_assert!(!red);
+                let (left, right) = (BST::from_parsed(*left), BST::from_parsed(*right));
+                BST {
+                    root: Tree::Node {
+                        size: 1_usize + left.size() + right.size(),
+                        k,
+                        v,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    },
+                    spare: Pool::new(),
+                }
+            }
+            Parsed::Nil => BST::new(),
         }
     }
 }
 
-impl<K: Ord + Clone, V: Clone> Default for BST<K, V> {
+impl<K: Ord + FromStr, V: FromStr> FromStr for BST<K, V> {
+    type Err = ParseTreeError;
+
+    /// Rebuilds a tree of the exact shape written by `to_paren_string`.
+    ///
+    /// Fails on malformed text, keys or values that don't parse, and keys
+    /// out of search tree order.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        paren::parse(s, false).map(BST::from_parsed)
+    }
+}
+
+impl<K: Ord, V> Default for BST<K, V> {
     /// Creates an empty `BST<K, V>`.
     fn default() -> BST<K, V> {
         BST::new()
     }
 }
 
-impl<K: Ord + Clone, V: Clone> Index<&K> for BST<K, V> {
+impl<K: Ord, V> Index<&K> for BST<K, V> {
     type Output = V;
 
     /// Returns a reference to the value corresponding to the supplied key.
@@ -520,9 +2629,16 @@ impl<K: Ord + Clone, V: Clone> Index<&K> for BST<K, V> {
     }
 }
 
+impl<K: Ord + Display, V: Display> Display for BST<K, V> {
+    /// Writes the tree as `to_paren_string` does, so `parse` reads it back.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_paren_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{SedgewickMap, BST};
+    use super::{Map, OrderedMap, SedgewickMap, BST};
     use crate::{Traversals, TreeTraversal};
 
     #[test]
@@ -536,7 +2652,7 @@ mod tests {
         let mut bst: BST<i32, i32> = BST::new();
         bst.put(1, 2);
         bst.put(2, 3);
-        assert_eq!(bst.is_empty(), false);
+        assert!(!bst.is_empty());
     }
 
     #[test]
@@ -576,7 +2692,7 @@ mod tests {
         let mut bst: BST<i32, i32> = BST::new();
         bst.put(1_i32, -1_i32);
         assert!(bst.contains(&1_i32));
-        assert_eq!(bst.contains(&-1_i32), false);
+        assert!(!bst.contains(&-1_i32));
     }
 
     #[test]
@@ -601,7 +2717,7 @@ mod tests {
     fn test_min() {
         let mut bst: BST<u32, u32> = BST::new();
         assert_eq!(bst.min(), None);
-        for i in vec![6_u32, 4, 5, 2, 1, 3] {
+        for i in [6_u32, 4, 5, 2, 1, 3] {
             bst.put(i, i);
         }
         assert_eq!(bst.min(), Some(&1_u32));
@@ -612,17 +2728,33 @@ mod tests {
     fn test_max() {
         let mut bst: BST<u32, u32> = BST::new();
         assert_eq!(bst.max(), None);
-        for i in vec![6_u32, 4, 5, 2, 1, 3] {
+        for i in [6_u32, 4, 5, 2, 1, 3] {
             bst.put(i, i);
         }
         assert_eq!(bst.max(), Some(&6_u32));
         assert_eq!(bst.get(bst.max().unwrap()), bst.max());
     }
 
+    #[test]
+    fn test_floor_and_ceiling() {
+        let mut bst: BST<u32, u32> = BST::new();
+        for i in [60_u32, 40, 50, 20, 10, 30] {
+            bst.put(i, i);
+        }
+        assert_eq!(bst.floor(&5_u32), None);
+        assert_eq!(bst.floor(&35_u32), Some(&30_u32));
+        assert_eq!(bst.floor(&40_u32), Some(&40_u32));
+        assert_eq!(bst.floor(&99_u32), Some(&60_u32));
+        assert_eq!(bst.ceiling(&5_u32), Some(&10_u32));
+        assert_eq!(bst.ceiling(&35_u32), Some(&40_u32));
+        assert_eq!(bst.ceiling(&40_u32), Some(&40_u32));
+        assert_eq!(bst.ceiling(&99_u32), None);
+    }
+
     #[test]
     fn test_in_order() {
         let mut bst: BST<char, i32> = BST::new();
-        let res = vec!['a', 'b', 'c', 'd'];
+        let res = ['a', 'b', 'c', 'd'];
         let mut it = res.iter();
         bst.put('c', 3);
         bst.put('d', 4);
@@ -637,7 +2769,7 @@ mod tests {
     #[test]
     fn test_pre_order() {
         let mut bst: BST<char, i32> = BST::new();
-        let res = vec!['c', 'b', 'a', 'd'];
+        let res = ['c', 'b', 'a', 'd'];
         let mut it = res.iter();
         bst.put('c', 3);
         bst.put('d', 4);
@@ -652,7 +2784,7 @@ mod tests {
     #[test]
     fn test_post_order() {
         let mut bst: BST<char, i32> = BST::new();
-        let res = vec!['a', 'b', 'd', 'c'];
+        let res = ['a', 'b', 'd', 'c'];
         let mut it = res.iter();
         bst.put('c', 3);
         bst.put('d', 4);
@@ -664,6 +2796,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_map_values() {
+        let mut bst: BST<char, i32> = BST::new();
+        for (i, c) in (1..).zip(['c', 'd', 'b', 'a']) {
+            bst.put(c, i);
+        }
+        let mut seen = Vec::new();
+        let mapped = bst.map_values(|k, v| {
+            seen.push(*k);
+            v * 10
+        });
+        assert_eq!(seen, ['a', 'b', 'c', 'd']);
+        assert_eq!(mapped.size(), 4_usize);
+        assert_eq!(mapped.height(), Some(2_usize));
+        assert_eq!(
+            mapped.traverse(&Traversals::PreOrder).as_slice(),
+            &[(&'c', &10), (&'b', &30), (&'a', &40), (&'d', &20)]
+        );
+    }
+
+    #[test]
+    fn test_sorted_vec() {
+        let mut bst: BST<u32, String> = BST::new();
+        assert!(bst.to_sorted_vec().is_empty());
+        for i in [6_u32, 4, 5, 2, 1, 3] {
+            bst.put(i, i.to_string());
+        }
+        let expected: Vec<(u32, String)> = (1..=6_u32).map(|i| (i, i.to_string())).collect();
+        assert_eq!(bst.to_sorted_vec(), expected);
+        let sorted = bst.into_sorted_vec();
+        assert_eq!(sorted.capacity(), 6_usize);
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn test_into_keys_and_values() {
+        let build = || {
+            let mut bst: BST<u32, String> = BST::new();
+            for i in [6_u32, 4, 5, 2, 1, 3] {
+                bst.put(i, i.to_string());
+            }
+            bst
+        };
+        let keys: Vec<u32> = build().into_keys().collect();
+        assert_eq!(keys, (1..=6_u32).collect::<Vec<_>>());
+        let values: Vec<String> = build().into_values().collect();
+        assert_eq!(values, ["1", "2", "3", "4", "5", "6"]);
+        let mut iter = build().into_iter();
+        assert_eq!(iter.len(), 6_usize);
+        assert_eq!(iter.next(), Some((1, String::from("1"))));
+        assert_eq!(iter.len(), 5_usize);
+
+        // a right spine, taken apart without a walk of the rest
+        let mut spine = BST::new();
+        for k in (0_u32..1_000_000).rev() {
+            spine = BST::from_root(k, k, BST::new(), spine);
+        }
+        assert!(spine.into_keys().eq(0_u32..1_000_000));
+    }
+
     #[test]
     fn test_level_order() {
         //     c
@@ -679,7 +2871,7 @@ mod tests {
         //        \
         //         a
         let mut bst: BST<char, i32> = BST::new();
-        let res = vec!['c', 'b', 'd', 'a'];
+        let res = ['c', 'b', 'd', 'a'];
         let mut it = res.iter();
         bst.put('c', 3);
         bst.put('d', 4);
@@ -690,11 +2882,359 @@ mod tests {
             assert_eq!(*a, *it.next().unwrap());
         }
         bst.invert();
-        let res = vec!['c', 'd', 'b', 'a'];
+        let res = ['c', 'd', 'b', 'a'];
         it = res.iter();
         assert_eq!(bst.size(), res.len());
         for (a, _) in bst.traverse(&Traversals::LevelOrder) {
             assert_eq!(*a, *it.next().unwrap());
         }
     }
+
+    #[test]
+    fn test_same_shape_and_isomorphism() {
+        let mut a: BST<u32, u32> = BST::new();
+        let mut b: BST<u32, u32> = BST::new();
+        for k in [4_u32, 2, 6, 1, 3, 5, 7] {
+            a.put(k, k);
+            b.put(k * 10, 0);
+        }
+        assert!(a.same_shape(&b) && a.is_isomorphic(&b));
+        assert!(BST::<u32, u32>::new().same_shape(&BST::<char, ()>::new()));
+        assert!(!a.same_shape(&BST::<u32, u32>::new()));
+
+        let mut mirrored: BST<u32, u32> = BST::new();
+        for k in [4_u32, 6, 2, 7, 5, 3, 1] {
+            mirrored.put(8 - k, k);
+        }
+        mirrored.invert();
+        assert!(a.same_shape(&mirrored));
+
+        // chains to the left and to the right only differ by swaps
+        let mut left: BST<u32, ()> = BST::new();
+        let mut right: BST<u32, ()> = BST::new();
+        let mut zigzag: BST<u32, ()> = BST::new();
+        for k in 0_u32..5 {
+            left.put(5 - k, ());
+            right.put(k, ());
+        }
+        for k in [0_u32, 4, 1, 3, 2] {
+            zigzag.put(k, ());
+        }
+        assert!(!left.same_shape(&right));
+        assert!(left.is_isomorphic(&right) && left.is_isomorphic(&zigzag));
+        assert!(!left.is_isomorphic(&a));
+    }
+
+    #[test]
+    fn test_get_sorted() {
+        let mut tree: BST<u32, u32> = BST::new();
+        for i in 0_u32..1000 {
+            tree.put((i * 7919) % 1000 * 3, i);
+        }
+        let mut keys: Vec<u32> = (0_u32..600).map(|i| (i * 104_729) % 3100).collect();
+        keys.sort_unstable();
+        let expected: Vec<Option<&u32>> = keys.iter().map(|k| tree.get(k)).collect();
+        assert_eq!(tree.get_sorted(&keys), expected);
+        assert!(tree.get_sorted(&[]).is_empty());
+        assert_eq!(BST::<u32, u32>::new().get_sorted(&[1, 2]), [None, None]);
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let mut canonical: Option<BST<u32, u32>> = None;
+        for step in [1_u32, 3, 7, 11] {
+            let mut bst: BST<u32, u32> = BST::new();
+            for i in 0_u32..100 {
+                bst.put((i * step) % 100, i);
+            }
+            bst.canonicalize();
+            assert_eq!(bst.size(), 100_usize);
+            assert_eq!(bst.height(), Some(6_usize));
+            assert!(bst.range(..).map(|(k, _)| *k).eq(0_u32..100));
+            if let Some(ref first) = canonical {
+                assert!(bst.same_shape(first));
+            }
+            canonical = Some(bst);
+        }
+        let mut empty: BST<u32, u32> = BST::new();
+        empty.canonicalize();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_range_prefix() {
+        let mut bst: BST<(u32, u32), u32> = BST::new();
+        for i in 0_u32..500 {
+            let key = ((i * 7919) % 20, (i * 31) % 97);
+            bst.put(key, i);
+        }
+        for prefix in 0_u32..22 {
+            let expected: Vec<_> = bst.range(..).filter(|(k, _)| k.0 == prefix).collect();
+            assert_eq!(bst.range_prefix(&prefix).as_slice(), expected.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_empty_level_order() {
+        let bst: BST<char, i32> = BST::new();
+        assert_eq!(bst.traverse(&Traversals::LevelOrder).len(), 0_usize);
+    }
+
+    fn depth_of(bst: &BST<u32, u32>, key: &u32) -> usize {
+        match bst.root() {
+            Some(node) if key.ne(node.key()) => {
+                let child = if key.lt(node.key()) {
+                    node.left()
+                } else {
+                    node.right()
+                };
+                1_usize + depth_of(child, key)
+            }
+            _ => 0_usize,
+        }
+    }
+
+    // Checks every node size and the key order
+    fn assert_sizes(bst: &BST<u32, u32>) -> usize {
+        match bst.root() {
+            Some(node) => {
+                let k = node.key();
+                assert!(node.left().max().is_none_or(|m| m.lt(k)));
+                assert!(node.right().min().is_none_or(|m| m.gt(k)));
+                let size = 1_usize + assert_sizes(node.left()) + assert_sizes(node.right());
+                assert_eq!(node.size(), size);
+                size
+            }
+            None => 0_usize,
+        }
+    }
+
+    #[test]
+    fn test_move_to_root() {
+        let mut bst: BST<u32, u32> = BST::new();
+        let mut roots: BST<u32, u32> = BST::new();
+        for i in 0_u32..500 {
+            let k = (i * 7919) % 500;
+            bst.put(k, i);
+            roots.put_to_root(k, i);
+            assert_eq!(
+                roots.to_paren_string().split(':').next(),
+                Some(k.to_string().as_str())
+            );
+        }
+        roots.put_to_root(7, 0);
+        bst.put(7, 0);
+        assert_eq!(assert_sizes(&roots), 500_usize);
+        assert!(roots.range(..).eq(bst.range(..)));
+
+        // a few hot keys end up on short paths
+        for _ in 0_u32..3 {
+            for k in [250_u32, 3, 499, 120] {
+                assert_eq!(bst.get_to_root(&k).copied(), roots.get(&k).copied());
+            }
+        }
+        assert_eq!(assert_sizes(&bst), 500_usize);
+        assert!(bst.range(..).eq(roots.range(..)));
+        assert_eq!(bst.min(), Some(&0));
+        assert_eq!(bst.get_to_root(&1000), None);
+        // every move pushes the earlier ones down by a level at most
+        for (depth, k) in [120_u32, 499, 3, 250].iter().enumerate() {
+            assert!(depth_of(&bst, k).le(&depth));
+        }
+    }
+
+    #[test]
+    fn test_paren_round_trip() {
+        let mut bst: BST<i32, String> = BST::new();
+        assert_eq!(bst.to_paren_string(), "-");
+        assert!("-".parse::<BST<i32, String>>().is_ok_and(|t| t.is_empty()));
+        for i in 0_i32..200 {
+            let k = (i * 7919) % 301 - 150;
+            bst.put(k, format!("v{}", i % 7));
+        }
+        let text = bst.to_paren_string();
+        let parsed: BST<i32, String> = text.parse().unwrap();
+        assert_eq!(parsed.to_paren_string(), text);
+        assert_eq!(parsed.size(), bst.size());
+        assert_eq!(parsed.height(), bst.height());
+        assert_eq!(parsed.range(..).as_slice(), bst.range(..).as_slice());
+
+        // negative keys and an empty right child
+        let parsed: BST<i32, i32> = "-1:-1(-2:0,-)".parse().unwrap();
+        assert_eq!(parsed.min(), Some(&-2));
+        assert_eq!(parsed.size(), 2_usize);
+    }
+
+    #[test]
+    fn test_paren_errors() {
+        for (text, offset) in [
+            ("", 0_usize),
+            ("a:1(", 4),
+            ("a:1(-,-", 7),
+            ("a:1,b:2", 3),
+            ("b:2(c:3,-)", 0),
+            ("b:2(a:1,b:3)", 8),
+            ("bb:2", 0),
+            ("b", 1),
+            ("b:x", 2),
+        ] {
+            let err = text.parse::<BST<char, u32>>().unwrap_err();
+            assert_eq!(err.offset(), offset, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_traverse_without_clone() {
+        use std::any::Any;
+
+        // neither keys nor values are `Clone`
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct Id(u32);
+
+        let mut bst: BST<Id, Box<dyn Any>> = BST::new();
+        bst.put(Id(2), Box::new("two"));
+        bst.put(Id(1), Box::new(1_u8));
+        bst.put(Id(3), Box::new(3.0_f64));
+        let keys: Vec<u32> = bst
+            .traverse(&Traversals::PostOrder)
+            .map(|(k, _)| k.0)
+            .collect();
+        assert_eq!(keys, vec![1_u32, 3, 2]);
+        assert_eq!(bst[&Id(1)].downcast_ref::<u8>(), Some(&1_u8));
+        assert_eq!(bst[&Id(2)].downcast_ref::<&str>(), Some(&"two"));
+        let empty: BST<Id, Box<dyn Any>> = BST::default();
+        assert_eq!(empty.traverse(&Traversals::LevelOrder).len(), 0_usize);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_range_for_each() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mut bst: BST<u32, u32> = BST::new();
+        for i in 0_u32..5000 {
+            bst.put((i * 7919) % 5000, i);
+        }
+        for (lo, hi) in [(0_u32, 5000_u32), (10, 11), (700, 3100), (6000, 7000)] {
+            let count = AtomicUsize::new(0);
+            let sum = AtomicUsize::new(0);
+            bst.par_range_for_each(lo..hi, |k, _| {
+                count.fetch_add(1, Ordering::Relaxed);
+                sum.fetch_add(*k as usize, Ordering::Relaxed);
+            });
+            let expected = (lo..hi.min(5000)).map(|k| k as usize);
+            assert_eq!(count.into_inner(), expected.clone().count());
+            assert_eq!(sum.into_inner(), expected.sum::<usize>());
+        }
+    }
+
+    #[test]
+    fn test_get_or_try_insert_with() {
+        let mut bst: BST<u32, u32> = BST::new();
+        let mut plain: BST<u32, u32> = BST::new();
+        for i in 0_u32..1000 {
+            let k = (i * 7919) % 500;
+            let v = bst.get_or_try_insert_with(k, || if k % 7 == 0 { Err(k) } else { Ok(i) });
+            match v {
+                Ok(v) => *v += 1,
+                Err(k) => assert_eq!(k % 7, 0_u32),
+            }
+            if k % 7 != 0 {
+                if let Some(v) = plain.get(&k).copied() {
+                    plain.put(k, v + 1);
+                } else {
+                    plain.put(k, i + 1);
+                }
+            }
+        }
+        assert_eq!(bst.size(), plain.size());
+        assert_eq!(bst.height(), plain.height());
+        assert!(bst.range(..).eq(plain.range(..)));
+        assert_eq!(
+            bst.root().map(|n| n.left().size()),
+            plain.root().map(|n| n.left().size())
+        );
+    }
+
+    #[test]
+    fn test_clear_degenerate() {
+        // a million nodes in a line, built bottom-up without puts
+        let mut bst = BST::new();
+        for k in (0_u32..1_000_000).rev() {
+            bst = BST::from_root(k, k, BST::new(), bst);
+        }
+        assert_eq!(bst.size(), 1_000_000_usize);
+        bst.clear();
+        assert!(bst.is_empty());
+        bst.put(1, 1);
+        assert_eq!(bst.size(), 1_usize);
+    }
+
+    #[test]
+    fn test_drain_degenerate() {
+        let mut bst = BST::new();
+        for k in 0_u32..1_000_000 {
+            bst = BST::from_root(k, k, bst, BST::new());
+        }
+        assert!(bst.drain().map(|(k, _)| k).eq(0_u32..1_000_000));
+        assert!(bst.is_empty());
+    }
+
+    #[test]
+    fn test_iter_is_lazy() {
+        let mut bst = BST::new();
+        let mut keys = std::collections::BTreeSet::new();
+        for k in crate::bench_support::shuffled_keys(3000, 32) {
+            bst.put(k, k);
+            keys.insert(k);
+        }
+        assert!(bst.iter().eq(bst.traverse(&Traversals::InOrder)));
+        assert!(bst.iter().map(|(k, _)| k).eq(keys.iter()));
+        let mut iter = bst.iter();
+        iter.next();
+        assert_eq!(iter.len(), keys.len() - 1_usize);
+
+        // a right spine, the first entries come without a walk of the rest
+        let mut spine = BST::new();
+        for k in (0_u32..1_000_000).rev() {
+            spine = BST::from_root(k, k, BST::new(), spine);
+        }
+        assert!(spine.iter().take(3).map(|(k, _)| *k).eq(0_u32..3));
+        let mut found = None;
+        for (k, v) in &spine {
+            if v % 1000 == 999 {
+                found = Some(*k);
+                break;
+            }
+        }
+        assert_eq!(found, Some(999));
+        assert_eq!(spine.drain().len(), 1_000_000_usize);
+    }
+
+    #[test]
+    fn test_delete_and_retain() {
+        let mut bst = BST::new();
+        let mut keys = std::collections::BTreeSet::new();
+        for k in crate::bench_support::shuffled_keys(2000, 7) {
+            bst.put(k, k);
+            keys.insert(k);
+        }
+        for k in crate::bench_support::shuffled_keys(2000, 8)
+            .iter()
+            .step_by(3)
+        {
+            assert_eq!(bst.delete(k), keys.take(k));
+            assert_eq!(bst.size(), keys.len());
+        }
+        assert!(bst.iter().map(|(k, _)| k).eq(keys.iter()));
+        // the node sizes still add up
+        assert!(keys.iter().enumerate().all(|(i, k)| bst.rank(k).eq(&i)));
+
+        bst.retain(|k, _| k % 4 != 1);
+        keys.retain(|k| k % 4 != 1);
+        assert!(bst.iter().map(|(k, _)| k).eq(keys.iter()));
+        assert!(keys.iter().enumerate().all(|(i, k)| bst.rank(k).eq(&i)));
+        bst.retain(|_, _| false);
+        assert!(bst.is_empty());
+    }
 }
@@ -1,14 +1,24 @@
-use crate::SedgewickMap;
-use std::ops::Index;
+use crate::view::{self, SubrangeView, TreeView};
+use crate::{reaches_above, reaches_below, Map, OrderedMap, PutPolicy, SedgewickMap};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Bound, Index, RangeBounds};
 
-// TODO: add M size in constructor?
-const M: usize = 4_usize;
+// Below this height parallel operations run sequentially
+#[cfg(feature = "rayon")]
+const PARALLEL_HEIGHT: usize = 5_usize;
+
+// Default fanout, also the smallest one `with_node_bytes` picks
+pub(crate) const M: usize = 4_usize;
 
 // TODO: make stack memory array
 type Node<K, V> = Vec<Entry<K, V>>;
 
+// Root node of a part of a tree, with its height
+type Part<K, V> = (Node<K, V>, usize);
+
 #[derive(Debug)]
-struct Entry<K: Ord + Clone, V: Clone> {
+struct Entry<K: Ord, V> {
     key: K,
     val: Option<V>,
     next: Node<K, V>,
@@ -25,14 +35,7 @@ impl<K: Clone + Ord, V: Clone> Clone for Entry<K, V> {
 }
 
 impl<K: Ord + Clone, V: Clone> Entry<K, V> {
-    fn new(key: K, val: Option<V>) -> Self {
-        Self {
-            key,
-            val,
-            next: Vec::with_capacity(M),
-        }
-    }
-    fn create(key: K, val: Option<V>, next: Node<K, V>) -> Self {
+    const fn create(key: K, val: Option<V>, next: Node<K, V>) -> Self {
         Self { key, val, next }
     }
 }
@@ -45,7 +48,7 @@ impl<K: Ord + Clone, V: Clone> Entry<K, V> {
 ///
 /// ```
 /// use treers::btree::BalancedTree;
-/// use treers::SedgewickMap;
+/// use treers::{Map, SedgewickMap};
 ///
 /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
 /// btree.put('c', 3);
@@ -63,13 +66,278 @@ impl<K: Ord + Clone, V: Clone> Entry<K, V> {
 /// assert_eq!(btree.height(), Some(1_usize));
 /// ```
 #[derive(Debug)]
-pub struct BalancedTree<K: Ord + Clone, V: Clone> {
+pub struct BalancedTree<K: Ord, V> {
     root: Node<K, V>,
     size: usize,
     height: usize,
+    variant: Variant,
+    auto_trim: bool,
+    // Most entries a node holds before it splits, `M` above
+    fanout: usize,
+    // Bumped on every change of node layout, invalidates hints
+    version: usize,
 }
 
-impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for BalancedTree<K, V> {
+/// Strategy of `BalancedTree` for a node overflowing on insert.
+///
+/// `BTree` splits the node in half. `BStar` first moves an entry to an
+/// adjacent sibling with room, and splits only when both siblings are
+/// full, so nodes stay fuller and scans touch fewer of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    BTree,
+    BStar,
+}
+
+/// Position of a leaf in a `BalancedTree`, returned by `put_hint` to start
+/// the next insert there.
+///
+/// A hint is valid until the node layout of its tree changes, a stale one
+/// only makes `put_hint` take the full descent.
+#[derive(Debug, Clone)]
+pub struct Hint<K> {
+    path: Vec<usize>,
+    low: Option<K>,
+    high: Option<K>,
+    version: usize,
+}
+
+/// Consecutive entries of one leaf of a `BalancedTree`, borrowed in place,
+/// as `range_chunks` returns them.
+#[derive(Debug)]
+pub struct Chunk<'a, K: Ord + Clone, V: Clone> {
+    entries: &'a [Entry<K, V>],
+}
+
+impl<K: Ord + Clone, V: Clone> Clone for Chunk<'_, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Copy for Chunk<'_, K, V> {}
+
+impl<'a, K: Ord + Clone, V: Clone> Chunk<'a, K, V> {
+    /// Returns a count of entries, never `0`.
+    pub const fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `false`, chunks of a range hold at least one entry.
+    pub const fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the `i`-th entry of the chunk.
+    pub fn get(&self, i: usize) -> Option<(&'a K, &'a V)> {
+        let e = self.entries.get(i)?;
+        e.val.as_ref().map(|v| (&e.key, v))
+    }
+
+    /// Returns the entries in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a K, &'a V)> {
+        self.entries
+            .iter()
+            .filter_map(|e| e.val.as_ref().map(|v| (&e.key, v)))
+    }
+
+    /// Returns the keys in order.
+    pub fn keys(&self) -> impl Iterator<Item = &'a K> {
+        self.entries.iter().map(|e| &e.key)
+    }
+
+    /// Returns the values in key order.
+    pub fn values(&self) -> impl Iterator<Item = &'a V> {
+        self.entries.iter().filter_map(|e| e.val.as_ref())
+    }
+}
+
+/// A lazy iterator over the leaves of a `BalancedTree` holding keys of a
+/// range, a `Chunk` of each, made by `range_chunks`.
+///
+/// It walks down as `range` does, keeping the path to the current leaf,
+/// and skips subtrees outside the range without visiting them.
+#[derive(Debug)]
+pub struct Chunks<'a, K: Ord + Clone, V: Clone, R> {
+    // Nodes on the path with the index of their next child, the root first
+    stack: Vec<(&'a [Entry<K, V>], usize)>,
+    height: usize,
+    range: R,
+}
+
+impl<K: Ord + Clone, V: Clone, R: Clone> Clone for Chunks<'_, K, V, R> {
+    fn clone(&self) -> Self {
+        Chunks {
+            stack: self.stack.clone(),
+            height: self.height,
+            range: self.range.clone(),
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone, R: RangeBounds<K>> Iterator for Chunks<'a, K, V, R> {
+    type Item = Chunk<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let range = &self.range;
+        loop {
+            let leaf = self.stack.len().gt(&self.height);
+            let (node, j) = self.stack.last_mut()?;
+            let node: &'a [Entry<K, V>] = node;
+            if leaf {
+                self.stack.pop();
+                let lo = node.partition_point(|e| before_start(range, &e.key));
+                let hi = node.partition_point(|e| !past_end(range, &e.key)).max(lo);
+                if hi.lt(&node.len()) {
+                    // the range ends in this leaf
+                    self.stack.clear();
+                }
+                if lo.lt(&hi) {
+                    return Some(Chunk {
+                        entries: &node[lo..hi],
+                    });
+                }
+                continue;
+            }
+            let i = *j;
+            *j += 1_usize;
+            let e = match node.get(i) {
+                Some(e) => e,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            if i.gt(&0_usize) && !range.contains(&e.key) && !reaches_above(range, &e.key) {
+                self.stack.clear();
+                continue;
+            }
+            if (i + 1_usize).lt(&node.len()) && !reaches_below(range, &node[i + 1].key) {
+                continue;
+            }
+            self.stack.push((&e.next, 0_usize));
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone, R: RangeBounds<K>> std::iter::FusedIterator for Chunks<'_, K, V, R> {}
+
+/// A lazy iterator over the entries of a `BalancedTree` in key order, made
+/// by `iter`.
+///
+/// It keeps an iterator over each node on the path down to the current
+/// leaf, `height + 1` of them, and skips the entries of internal nodes,
+/// which only hold links.
+#[derive(Debug)]
+pub struct Iter<'a, K: Ord + Clone, V: Clone> {
+    stack: Vec<std::slice::Iter<'a, Entry<K, V>>>,
+    remaining: usize,
+}
+
+impl<K: Ord + Clone, V: Clone> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Iter {
+            stack: self.stack.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let e = match self.stack.last_mut()?.next() {
+                Some(e) => e,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            match e.val {
+                Some(ref v) => {
+                    self.remaining -= 1_usize;
+                    return Some((&e.key, v));
+                }
+                None => self.stack.push(e.next.iter()),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K: Ord + Clone, V: Clone> std::iter::FusedIterator for Iter<'_, K, V> {}
+
+impl<'a, K: Ord + Clone, V: Clone> IntoIterator for &'a BalancedTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A lazy iterator taking apart a `BalancedTree` in key order, made by
+/// `into_iter`.
+///
+/// It owns the rest of each node on the path down to the current leaf,
+/// `height + 1` of them, and frees the nodes as it passes them.
+#[derive(Debug)]
+pub struct IntoIter<K: Ord + Clone, V: Clone> {
+    stack: Vec<std::vec::IntoIter<Entry<K, V>>>,
+    remaining: usize,
+}
+
+impl<K: Ord + Clone, V: Clone> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Entry { key, val, next } = match self.stack.last_mut()?.next() {
+                Some(e) => e,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            match val {
+                Some(v) => {
+                    self.remaining -= 1_usize;
+                    return Some((key, v));
+                }
+                None => self.stack.push(next.into_iter()),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K: Ord + Clone, V: Clone> std::iter::FusedIterator for IntoIter<K, V> {}
+
+impl<K: Ord + Clone, V: Clone> IntoIterator for BalancedTree<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            stack: vec![self.root.into_iter()],
+            remaining: self.size,
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> BalancedTree<K, V> {
     /// Inits a new instance of Balanced Tree.
     ///
     /// # Examples
@@ -78,20 +346,29 @@ impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for BalancedTree<K, V> {
     ///
     /// ```
     /// use treers::btree::BalancedTree;
-    /// use treers::SedgewickMap;
+    /// use treers::Map;
     ///
     /// let btree: BalancedTree<char, i32> = BalancedTree::new();
     /// assert!(btree.is_empty());
     /// ```
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             root: Vec::with_capacity(M),
             size: 0_usize,
             height: 0_usize,
+            variant: Variant::BTree,
+            auto_trim: false,
+            fanout: M,
+            version: 0_usize,
         }
     }
 
-    /// Returns a size of elements in `BST`.
+    /// Inits a new instance of Balanced Tree with the fanout fitting a node
+    /// into about `bytes` bytes, but at least the default of 4.
+    ///
+    /// Small keys and values get wide nodes, so the tree is flatter and
+    /// every node read brings more entries along, e.g. 256 or 512 bytes
+    /// for a few cache lines per node.
     ///
     /// # Examples
     ///
@@ -99,46 +376,72 @@ impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for BalancedTree<K, V> {
     ///
     /// ```
     /// use treers::btree::BalancedTree;
-    /// use treers::SedgewickMap;
+    /// use treers::{Map, SedgewickMap};
     ///
-    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
-    /// assert_eq!(btree.size(), 0_usize);
-    /// btree.put('a', 1);
-    /// btree.put('b', 2);
-    /// btree.put('c', 3);
-    /// btree.put('d', 4);
-    /// assert_eq!(btree.size(), 4_usize);
+    /// let mut wide: BalancedTree<u32, u32> = BalancedTree::with_node_bytes(512);
+    /// let mut plain: BalancedTree<u32, u32> = BalancedTree::new();
+    /// for i in 0..1000 {
+    ///     wide.put(i, i);
+    ///     plain.put(i, i);
+    /// }
+    /// assert!(wide.fanout() > plain.fanout());
+    /// assert!(wide.height() < plain.height());
+    /// assert_eq!(plain.fanout(), 4_usize);
+    ///
+    /// let big: BalancedTree<[u8; 1024], u32> = BalancedTree::with_node_bytes(512);
+    /// assert_eq!(big.fanout(), 4_usize);
     /// ```
-    fn size(&self) -> usize {
-        self.size
+    pub fn with_node_bytes(bytes: usize) -> Self {
+        let fanout = (bytes / std::mem::size_of::<Entry<K, V>>()).max(M);
+        Self {
+            root: Vec::with_capacity(fanout),
+            fanout,
+            ..Self::new()
+        }
     }
 
-    // TODO: fix lifetime params
-    /// Returns a reference to optional reference to value.
+    /// Returns the most entries a node holds before it splits.
+    pub const fn fanout(&self) -> usize {
+        self.fanout
+    }
+
+    /// Inits a new instance of Balanced Tree, handling overflows by `variant`.
     ///
     /// # Examples
     ///
     /// Basic usage:
     ///
     /// ```
-    /// use treers::btree::BalancedTree;
-    /// use treers::SedgewickMap;
+    /// use treers::btree::{BalancedTree, Variant};
+    /// use treers::{Map, SedgewickMap};
     ///
-    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
-    /// btree.put('a', 1);
-    /// assert_eq!(btree.get(&'a'), Some(&1));
-    /// assert_eq!(btree.get(&'b'), None);
-    /// assert_eq!(btree[&'a'], 1);
+    /// let mut btree: BalancedTree<u32, u32> = BalancedTree::with_variant(Variant::BStar);
+    /// let mut plain: BalancedTree<u32, u32> = BalancedTree::new();
+    /// for i in 0..1000 {
+    ///     btree.put(i, i);
+    ///     plain.put(i, i);
+    /// }
+    /// assert_eq!(btree.variant(), Variant::BStar);
+    /// assert_eq!(btree.get(&500), Some(&500));
+    /// assert!(btree.height() < plain.height());
     /// ```
-    fn get(&self, key: &K) -> Option<&V> {
-        if self.is_empty() {
-            None
-        } else {
-            search(&self.root, key.clone(), self.height)
+    pub fn with_variant(variant: Variant) -> Self {
+        Self {
+            variant,
+            ..Self::new()
         }
     }
 
-    /// Insert a key-value pair into the `BTree`.
+    /// Returns a strategy used on node overflows.
+    pub const fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Sets if every `put` trims spare capacity off the nodes it changes,
+    /// see `shrink_to_fit`.
+    ///
+    /// Trimmed nodes reallocate when they grow again, so it suits trees
+    /// that are built once and then mostly read.
     ///
     /// # Examples
     ///
@@ -146,35 +449,91 @@ impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for BalancedTree<K, V> {
     ///
     /// ```
     /// use treers::btree::BalancedTree;
-    /// use treers::SedgewickMap;
+    /// use treers::Map;
     ///
-    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
-    /// assert!(btree.is_empty());
+    /// let mut btree: BalancedTree<u32, u32> = BalancedTree::new();
+    /// btree.set_auto_trim(true);
+    /// for i in 0..100 {
+    ///     btree.put(i, i);
+    /// }
+    /// assert!(btree.auto_trim());
+    /// assert_eq!(btree.get(&42), Some(&42));
+    /// ```
+    pub const fn set_auto_trim(&mut self, auto_trim: bool) {
+        self.auto_trim = auto_trim;
+    }
+
+    /// Returns `true` if `put` trims the nodes it changes.
+    pub const fn auto_trim(&self) -> bool {
+        self.auto_trim
+    }
+
+    /// Inserts a key-value pair, starting at the leaf of `hint` when `key`
+    /// belongs there, and returns a hint for the next insert.
+    ///
+    /// While a leaf has room, inserts next to a previous one skip the
+    /// descent from the root and compare `key` with that leaf only, so
+    /// runs of nearby keys are cheap. Otherwise it's the same as `put`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
     ///
-    /// btree.put('a', 1_i32);
-    /// assert_eq!(btree.is_empty(), false);
-    /// assert_eq!(btree.get(&'a'), Some(&1_i32));
-    /// assert_eq!(btree[&'a'], 1_i32);
     /// ```
-    fn put(&mut self, key: K, value: V) {
-        if let Some(u) = insert(&mut self.root, key, value, self.height) {
-            // need to split the root
-            let mut t: Node<K, V> = Vec::with_capacity(M / 2);
-            t.push(Entry::create(
-                self.root[0].key.clone(),
-                None,
-                self.root.clone(),
-            ));
-            t.push(Entry::create(u[0].key.clone(), None, u));
-            self.root = t;
-            self.height += 1;
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut btree: BalancedTree<u32, u32> = BalancedTree::new();
+    /// for i in (0..1000).step_by(10) {
+    ///     btree.put(i, i);
+    /// }
+    /// // fills the gap between 500 and 510
+    /// let mut hint = None;
+    /// for i in 501..510 {
+    ///     hint = Some(btree.put_hint(hint.as_ref(), i, i));
+    /// }
+    /// assert_eq!(btree.size(), 109_usize);
+    /// assert!(btree.range(500..=510).map(|(k, _)| *k).eq(500..=510));
+    /// ```
+    pub fn put_hint(&mut self, hint: Option<&Hint<K>>, key: K, value: V) -> Hint<K> {
+        if let Some(hint) = hint.filter(|h| h.covers(&key, self.version)) {
+            if let Some(leaf) = leaf_at(&mut self.root, &hint.path) {
+                let i = leaf.iter().position(|e| !e.key.lt(&key));
+                match i {
+                    Some(i) if leaf[i].key.eq(&key) => {
+                        leaf[i].val = Some(value);
+                        return hint.clone();
+                    }
+                    // the leaf has room, no split can change the layout
+                    i if (leaf.len() + 1_usize).lt(&self.fanout) => {
+                        let e = Entry::create(key, Some(value), Vec::new());
+                        leaf.insert(i.unwrap_or(leaf.len()), e);
+                        if self.auto_trim {
+                            leaf.shrink_to_fit();
+                        }
+                        self.size += 1_usize;
+                        return hint.clone();
+                    }
+                    _ => {}
+                }
+            }
         }
-        self.size += 1;
+        let hint_key = key.clone();
+        self.put(key, value);
+        self.hint(&hint_key)
     }
 
-    /// Get height of `BTree`.
+    /// Appends a key greater than every key in the `BTree`, walking down
+    /// the rightmost nodes only.
     ///
-    /// BTree is balanced tree. TODO: add more text
+    /// A full node first passes an entry to its left sibling and splits in
+    /// half only once that one is full too, so sorted bulk loads leave all
+    /// nodes but the last two full, like `from_sorted_iter`, instead of half
+    /// empty, and every node stays at least half full for `delete`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not greater than the current max key.
     ///
     /// # Examples
     ///
@@ -182,34 +541,44 @@ impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for BalancedTree<K, V> {
     ///
     /// ```
     /// use treers::btree::BalancedTree;
-    /// use treers::SedgewickMap;
+    /// use treers::{Map, OrderedMap, SedgewickMap};
     ///
-    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
-    /// assert_eq!(btree.height(), Some(0_usize));
-    /// btree.put('a', 1);
-    /// btree.put('b', 2);
-    /// btree.put('c', 3);
-    /// btree.put('d', 4);
-    /// btree.put('e', 5);
-    /// btree.put('f', 6);
-    /// btree.put('g', 7);
-    /// //    |a|c|e|         <-- height: 0
-    /// //    /  |  \
-    /// // |b|  |d|  |f|g|    <-- height: 1
-    /// //
-    /// // Note -The Height of balanced tree with single node is taken as zero,
-    /// //       but empty BTree is 0, not None.
-    /// assert_eq!(btree.height(), Some(1_usize));
-    /// assert_eq!(btree.get(&'g'), Some(&7_i32));
-    /// assert_eq!(btree[&'g'], 7_i32);
-    /// assert_eq!(btree.size(), 7_usize);
+    /// let mut pushed: BalancedTree<u32, u32> = BalancedTree::new();
+    /// let mut plain: BalancedTree<u32, u32> = BalancedTree::new();
+    /// for i in 0..1000 {
+    ///     pushed.push_max(i, i);
+    ///     plain.put(i, i);
+    /// }
+    /// assert_eq!(pushed.max(), Some(&999));
+    /// assert!(pushed.range(..).eq(plain.range(..)));
+    /// assert!(pushed.height() < plain.height());
     /// ```
-    fn height(&self) -> Option<usize> {
-        Some(self.height)
+    pub fn push_max(&mut self, key: K, value: V) {
+        assert!(
+            self.max().is_none_or(|max| max.lt(&key)),
+            "push_max key is not greater than the max key"
+        );
+        // leaf entries never get children, so no room is kept for them
+        let e = Entry::create(key, Some(value), Vec::new());
+        push_last(&mut self.root, e, self.height, self.fanout);
+        if self.root.len().eq(&self.fanout) {
+            let u = split_half(&mut self.root, false);
+            let old = std::mem::take(&mut self.root);
+            self.root = Vec::with_capacity(self.fanout);
+            self.root.push(Entry::create(old[0].key.clone(), None, old));
+            self.root.push(Entry::create(u[0].key.clone(), None, u));
+            self.height += 1_usize;
+        }
+        self.version = self.version.wrapping_add(1_usize);
+        self.size += 1_usize;
     }
 
-    /// Returns a optional reference to minimal key
+    /// Removes `key` and returns its value.
     ///
+    /// A node left with fewer than half of `fanout()` entries borrows one from
+    /// a sibling, or merges with it when the sibling has no entry to spare;
+    /// an emptied node without a sibling is dropped, its parent refilled in
+    /// turn. A root left with one child is dropped, so the tree gets lower.
     ///
     /// # Examples
     ///
@@ -217,33 +586,73 @@ impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for BalancedTree<K, V> {
     ///
     /// ```
     /// use treers::btree::BalancedTree;
-    /// use treers::SedgewickMap;
+    /// use treers::{Map, SedgewickMap};
     ///
-    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
-    /// assert_eq!(btree.min(), None);
-    /// btree.put('c', 1);
-    /// btree.put('a', 2);
-    /// btree.put('b', 3);
-    /// btree.put('d', 4);
-    /// assert_eq!(btree.min(), Some(&'a'));
+    /// let mut btree: BalancedTree<u32, u32> = BalancedTree::new();
+    /// for i in 0..100 {
+    ///     btree.put(i, i * 10);
+    /// }
+    /// let height = btree.height();
+    ///
+    /// assert_eq!(btree.delete(&40), Some(400));
+    /// assert_eq!(btree.delete(&40), None);
+    /// assert_eq!(btree.size(), 99_usize);
+    ///
+    /// for i in 0..90 {
+    ///     btree.delete(&i);
+    /// }
+    /// assert!(btree.height() < height);
+    /// assert_eq!(btree.get(&95), Some(&950));
     /// ```
-    fn min(&self) -> Option<&K> {
-        if self.is_empty() {
-            return None;
+    pub fn delete(&mut self, key: &K) -> Option<V> {
+        let policy = Policy {
+            fanout: self.fanout,
+            b_star: self.variant.eq(&Variant::BStar),
+            trim: self.auto_trim,
+        };
+        let v = delete(&mut self.root, key, self.height, policy)?;
+        while self.height.gt(&0_usize) && self.root.len().eq(&1_usize) {
+            // the root has one child left
+            self.root = self.root.pop().map(|e| e.next).unwrap_or_default();
+            self.height -= 1_usize;
+        }
+        if self.root.is_empty() {
+            self.height = 0_usize;
         }
+        self.version = self.version.wrapping_add(1_usize);
+        self.size -= 1_usize;
+        Some(v)
+    }
+
+    // Path to the leaf that may hold `key`, with bounds of keys it covers
+    fn hint(&self, key: &K) -> Hint<K> {
+        let mut hint = Hint {
+            path: Vec::with_capacity(self.height),
+            low: None,
+            high: None,
+            version: self.version,
+        };
         let mut node = &self.root;
-        loop {
-            let next = &node[0].next;
-            if !next.is_empty() {
-                node = &next;
-            } else {
-                return Some(&node[0].key);
+        for _ in 0_usize..self.height {
+            let j = child_index(node, key);
+            // deeper routing keys are the tighter bounds
+            if j.gt(&0_usize) {
+                hint.low = Some(node[j].key.clone());
+            }
+            if let Some(e) = node.get(j + 1_usize) {
+                hint.high = Some(e.key.clone());
             }
+            hint.path.push(j);
+            node = &node[j].next;
         }
+        hint
     }
 
-    /// Returns a optional reference to maximum key
+    /// Releases spare capacity of every node.
     ///
+    /// Nodes are allocated for `M` entries, so after splits most of them
+    /// are half empty, and leaf entries hold room for children they never
+    /// get. For a large tree that is done growing, this reclaims that memory.
     ///
     /// # Examples
     ///
@@ -251,111 +660,2173 @@ impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for BalancedTree<K, V> {
     ///
     /// ```
     /// use treers::btree::BalancedTree;
-    /// use treers::SedgewickMap;
+    /// use treers::{Map, OrderedMap};
     ///
-    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
-    /// assert_eq!(btree.max(), None);
-    /// btree.put('c', 1);
-    /// btree.put('a', 2);
-    /// btree.put('b', 3);
-    /// btree.put('d', 4);
-    /// assert_eq!(btree.max(), Some(&'d'));
+    /// let mut btree: BalancedTree<u32, u32> = BalancedTree::new();
+    /// for i in 0..1000 {
+    ///     btree.put(i, i);
+    /// }
+    /// btree.shrink_to_fit();
+    /// assert_eq!(btree.size(), 1000_usize);
+    /// assert!(btree.range(..).map(|(k, _)| *k).eq(0..1000));
+    ///
+    /// // still grows as usual
+    /// btree.put(1000, 1000);
+    /// assert_eq!(btree.max(), Some(&1000));
     /// ```
-    fn max(&self) -> Option<&K> {
-        if self.is_empty() {
-            return None;
+    pub fn shrink_to_fit(&mut self) {
+        trim(&mut self.root);
+    }
+
+    /// Builds a Balanced Tree bottom up from entries in strictly ascending
+    /// key order, without a single split.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if keys are not strictly ascending.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap, SedgewickMap};
+    ///
+    /// let btree = BalancedTree::from_sorted_iter((0..1000).map(|i| (i, i * 2)));
+    /// assert_eq!(btree.size(), 1000_usize);
+    /// assert_eq!(btree.get(&500), Some(&1000));
+    /// assert_eq!(btree.max(), Some(&999));
+    /// assert_eq!(btree.height(), Some(6_usize));
+    /// ```
+    pub fn from_sorted_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::build_sorted(iter, M)
+    }
+
+    fn build_sorted<I: IntoIterator<Item = (K, V)>>(iter: I, fanout: usize) -> Self {
+        let entries: Vec<Entry<K, V>> = iter
+            .into_iter()
+            .map(|(k, v)| Entry::create(k, Some(v), Vec::new()))
+            .collect();
+        debug_assert!(entries.windows(2).all(|w| w[0].key.lt(&w[1].key)));
+        let size = entries.len();
+        if size.eq(&0_usize) {
+            return Self {
+                fanout,
+                ..Self::new()
+            };
         }
-        let mut node = &self.root;
-        loop {
-            let next = &node[node.len() - 1].next;
-            if !next.is_empty() {
-                node = &next;
-            } else {
-                return Some(&node[node.len() - 1].key);
-            }
+        let mut level = pack(entries, fanout);
+        let mut height = 0_usize;
+        while level.len().gt(&1_usize) {
+            let parents = level
+                .into_iter()
+                .map(|node| Entry::create(node[0].key.clone(), None, node))
+                .collect();
+            level = pack(parents, fanout);
+            height += 1_usize;
+        }
+        Self {
+            root: level.pop().unwrap_or_default(),
+            size,
+            height,
+            variant: Variant::BTree,
+            auto_trim: false,
+            fanout,
+            version: 0_usize,
         }
     }
 }
 
-// TODO: fix lifetime params for search!
-fn search<'a, K, V>(node: &'a [Entry<K, V>], key: K, height: usize) -> Option<&'a V>
+// Groups entries evenly into nodes of at most `fanout - 1` entries
+fn pack<K, V>(entries: Vec<Entry<K, V>>, fanout: usize) -> Vec<Node<K, V>>
 where
-    K: Ord + Clone + 'a,
-    V: Clone + 'a,
+    K: Ord + Clone,
+    V: Clone,
 {
-    if height.eq(&0_usize) {
-        for n in node {
-            if key.eq(&n.key) {
-                return n.val.as_ref();
+    let nodes = (entries.len() + fanout - 2_usize) / (fanout - 1_usize);
+    let (base, extra) = (entries.len() / nodes, entries.len() % nodes);
+    let mut entries = entries.into_iter();
+    (0..nodes)
+        .map(|i| {
+            let len = if i.lt(&extra) { base + 1_usize } else { base };
+            let mut node = Vec::with_capacity(fanout);
+            node.extend(entries.by_ref().take(len));
+            node
+        })
+        .collect()
+}
+
+impl<K: Ord, V> BalancedTree<K, V> {
+    /// Returns a read-only, copyable view over `BTree`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// btree.put('a', 1);
+    /// let view = btree.view();
+    /// assert_eq!(view.get(&'a'), Some(&1));
+    /// assert_eq!(view.size(), 1_usize);
+    /// ```
+    pub fn view(&self) -> TreeView<'_, K, V> {
+        TreeView::from(self)
+    }
+
+    /// Returns a read-only view over keys of `BTree` inside `range`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// for (v, k) in ('a'..='f').enumerate() {
+    ///     btree.put(k, v as i32);
+    /// }
+    /// let sub = btree.subrange_view('b'..'e');
+    /// assert_eq!(sub.size(), 3_usize);
+    /// assert_eq!(sub.min(), Some(&'b'));
+    /// assert_eq!(sub.max(), Some(&'d'));
+    /// assert_eq!(sub.get(&'f'), None);
+    /// ```
+    pub fn subrange_view<R: RangeBounds<K>>(&self, range: R) -> SubrangeView<'_, K, V>
+    where
+        K: Clone,
+    {
+        self.view().subrange(range)
+    }
+}
+
+impl<K: Ord, V> view::Read<K, V> for BalancedTree<K, V> {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        if self.size.eq(&0_usize) {
+            None
+        } else {
+            search(&self.root, key, self.height)
+        }
+    }
+
+    fn min(&self) -> Option<&K> {
+        if self.size.eq(&0_usize) {
+            return None;
+        }
+        let mut node = &self.root;
+        loop {
+            let next = &node[0].next;
+            if !next.is_empty() {
+                node = next;
+            } else {
+                return Some(&node[0].key);
             }
         }
-    } else {
-        for j in 0..node.len() {
-            if (j + 1).eq(&node.len()) || key.lt(&node[j + 1].key) {
-                return search(&node[j].next, key, height - 1_usize);
+    }
+
+    fn max(&self) -> Option<&K> {
+        if self.size.eq(&0_usize) {
+            return None;
+        }
+        let mut node = &self.root;
+        loop {
+            let next = &node[node.len() - 1].next;
+            if !next.is_empty() {
+                node = next;
+            } else {
+                return Some(&node[node.len() - 1].key);
+            }
+        }
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        floor(&self.root, key, self.height)
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        ceiling(&self.root, key, self.height)
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        let mut vec = Vec::new();
+        take_range(&self.root, &range, &mut vec);
+        vec.into_iter()
+    }
+
+    fn rank(&self, key: &K) -> usize {
+        rank(&self.root, key, self.height)
+    }
+
+    fn successor(&self, key: &K) -> Option<(&K, &V)> {
+        successor(&self.root, key, self.height)
+    }
+
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)> {
+        predecessor(&self.root, key, self.height)
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Map<K, V> for BalancedTree<K, V> {
+    /// Returns a size of elements in `BST`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// assert_eq!(btree.size(), 0_usize);
+    /// btree.put('a', 1);
+    /// btree.put('b', 2);
+    /// btree.put('c', 3);
+    /// btree.put('d', 4);
+    /// assert_eq!(btree.size(), 4_usize);
+    /// ```
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    // TODO: fix lifetime params
+    /// Returns a reference to optional reference to value.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// btree.put('a', 1);
+    /// assert_eq!(btree.get(&'a'), Some(&1));
+    /// assert_eq!(btree.get(&'b'), None);
+    /// assert_eq!(btree[&'a'], 1);
+    /// ```
+    fn get(&self, key: &K) -> Option<&V> {
+        view::Read::get(self, key)
+    }
+
+    /// Insert a key-value pair into the `BTree`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// assert!(btree.is_empty());
+    ///
+    /// btree.put('a', 1_i32);
+    /// assert_eq!(btree.is_empty(), false);
+    /// assert_eq!(btree.get(&'a'), Some(&1_i32));
+    /// assert_eq!(btree[&'a'], 1_i32);
+    ///
+    /// btree.put('a', 2_i32);
+    /// assert_eq!(btree.get(&'a'), Some(&2_i32));
+    /// assert_eq!(btree.size(), 1_usize);
+    /// ```
+    fn put(&mut self, key: K, value: V) {
+        // existing key keeps its place, only the value changes
+        if let Some(v) = search_mut(&mut self.root, &key, self.height) {
+            *v = value;
+            return;
+        }
+        self.insert_new(key, value);
+    }
+
+    fn put_with_policy(&mut self, key: K, value: V, policy: &mut PutPolicy<'_, V>) -> bool {
+        if let Some(v) = search_mut(&mut self.root, &key, self.height) {
+            policy.resolve(v, value);
+            return false;
+        }
+        self.insert_new(key, value);
+        true
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> BalancedTree<K, V> {
+    // Inserts a key known to be missing, splitting nodes on the way up
+    fn insert_new(&mut self, key: K, value: V) {
+        let policy = Policy {
+            fanout: self.fanout,
+            b_star: self.variant.eq(&Variant::BStar),
+            trim: self.auto_trim,
+        };
+        let split = insert(&mut self.root, key, value, self.height, policy);
+        // B* leaves an overflowing root for the caller to split
+        let split = split.or_else(|| {
+            if self.root.len().eq(&self.fanout) {
+                Some(split_half(&mut self.root, self.auto_trim))
+            } else {
+                None
             }
+        });
+        if let Some(u) = split {
+            // need to split the root
+            let mut t: Node<K, V> = Vec::with_capacity(M / 2);
+            t.push(Entry::create(
+                self.root[0].key.clone(),
+                None,
+                self.root.clone(),
+            ));
+            t.push(Entry::create(u[0].key.clone(), None, u));
+            self.root = t;
+            self.height += 1;
+        }
+        self.version = self.version.wrapping_add(1_usize);
+        self.size += 1;
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> OrderedMap<K, V> for BalancedTree<K, V> {
+    /// Returns a optional reference to minimal key
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// assert_eq!(btree.min(), None);
+    /// btree.put('c', 1);
+    /// btree.put('a', 2);
+    /// btree.put('b', 3);
+    /// btree.put('d', 4);
+    /// assert_eq!(btree.min(), Some(&'a'));
+    /// ```
+    fn min(&self) -> Option<&K> {
+        view::Read::min(self)
+    }
+
+    /// Returns a optional reference to maximum key
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// assert_eq!(btree.max(), None);
+    /// btree.put('c', 1);
+    /// btree.put('a', 2);
+    /// btree.put('b', 3);
+    /// btree.put('d', 4);
+    /// assert_eq!(btree.max(), Some(&'d'));
+    /// ```
+    fn max(&self) -> Option<&K> {
+        view::Read::max(self)
+    }
+
+    /// Returns a optional reference to the largest key less than or equal to `key`
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// assert_eq!(btree.floor(&'c'), None);
+    /// btree.put('b', 1);
+    /// btree.put('d', 2);
+    /// assert_eq!(btree.floor(&'a'), None);
+    /// assert_eq!(btree.floor(&'b'), Some(&'b'));
+    /// assert_eq!(btree.floor(&'c'), Some(&'b'));
+    /// assert_eq!(btree.floor(&'z'), Some(&'d'));
+    /// ```
+    fn floor(&self, key: &K) -> Option<&K> {
+        floor(&self.root, key, self.height)
+    }
+
+    /// Returns a optional reference to the smallest key greater than or equal to `key`
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// assert_eq!(btree.ceiling(&'c'), None);
+    /// btree.put('b', 1);
+    /// btree.put('d', 2);
+    /// assert_eq!(btree.ceiling(&'a'), Some(&'b'));
+    /// assert_eq!(btree.ceiling(&'c'), Some(&'d'));
+    /// assert_eq!(btree.ceiling(&'d'), Some(&'d'));
+    /// assert_eq!(btree.ceiling(&'z'), None);
+    /// ```
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        ceiling(&self.root, key, self.height)
+    }
+
+    /// Returns the `n` entries with the smallest keys, in ascending order.
+    ///
+    /// Only the visited part of `BTree` is walked, not the whole of it.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// btree.put('c', 3);
+    /// btree.put('a', 1);
+    /// btree.put('d', 4);
+    /// btree.put('b', 2);
+    /// assert_eq!(btree.smallest(2).as_slice(), &[(&'a', &1), (&'b', &2)]);
+    /// ```
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let mut vec = Vec::with_capacity(std::cmp::min(n, self.size()));
+        take_smallest(&self.root, &mut vec, n);
+        vec.into_iter()
+    }
+
+    /// Returns the `n` entries with the largest keys, in descending order.
+    ///
+    /// Only the visited part of `BTree` is walked, not the whole of it.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// btree.put('c', 3);
+    /// btree.put('a', 1);
+    /// btree.put('d', 4);
+    /// btree.put('b', 2);
+    /// assert_eq!(btree.largest(2).as_slice(), &[(&'d', &4), (&'c', &3)]);
+    /// ```
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let mut vec = Vec::with_capacity(std::cmp::min(n, self.size()));
+        take_largest(&self.root, &mut vec, n);
+        vec.into_iter()
+    }
+
+    /// Returns entries of `BTree` with keys inside `range`, in key order.
+    ///
+    /// Subtrees outside of `range` are not visited.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// for (v, k) in ('a'..='f').enumerate() {
+    ///     btree.put(k, v as i32);
+    /// }
+    /// assert_eq!(btree.range('b'..'d').as_slice(), &[(&'b', &1), (&'c', &2)]);
+    /// assert_eq!(btree.range('e'..).as_slice(), &[(&'e', &4), (&'f', &5)]);
+    /// assert_eq!(btree.range(..='a').as_slice(), &[(&'a', &0)]);
+    /// ```
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        let mut vec = Vec::new();
+        take_range(&self.root, &range, &mut vec);
+        vec.into_iter()
+    }
+
+    fn try_fold_range<R, B, E, F>(&self, range: R, init: B, mut f: F) -> Result<B, E>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(B, &K, &V) -> Result<B, E>,
+    {
+        try_fold_range(&self.root, &range, init, &mut f)
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for BalancedTree<K, V> {
+    /// Get height of `BTree`.
+    ///
+    /// BTree is balanced tree, every leaf is at the same height.
+    /// Empty tree has no height, `None`, same as other trees.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// assert_eq!(btree.height(), None);
+    /// btree.put('a', 1);
+    /// assert_eq!(btree.height(), Some(0_usize));
+    /// btree.put('b', 2);
+    /// btree.put('c', 3);
+    /// btree.put('d', 4);
+    /// btree.put('e', 5);
+    /// btree.put('f', 6);
+    /// btree.put('g', 7);
+    /// //    |a|c|e|         <-- height: 0
+    /// //    /  |  \
+    /// // |b|  |d|  |f|g|    <-- height: 1
+    /// //
+    /// // Note -The Height of balanced tree with single node is taken as zero.
+    /// assert_eq!(btree.height(), Some(1_usize));
+    /// assert_eq!(btree.get(&'g'), Some(&7_i32));
+    /// assert_eq!(btree[&'g'], 7_i32);
+    /// assert_eq!(btree.size(), 7_usize);
+    /// ```
+    fn height(&self) -> Option<usize> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.height)
+        }
+    }
+
+    fn leaf_depths(&self) -> Vec<usize> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        vec![self.height; leaves(&self.root, self.height)]
+    }
+
+    fn delete_min(&mut self) -> Option<(K, V)> {
+        let key = self.min()?.clone();
+        self.delete(&key).map(|v| (key, v))
+    }
+
+    fn delete_max(&mut self) -> Option<(K, V)> {
+        let key = self.max()?.clone();
+        self.delete(&key).map(|v| (key, v))
+    }
+
+    fn rank(&self, key: &K) -> usize {
+        rank(&self.root, key, self.height)
+    }
+
+    fn select(&self, rank: usize) -> Option<&K> {
+        select(&self.root, rank, self.height)
+    }
+
+    fn partition_point<P: FnMut(&K) -> bool>(&self, mut pred: P) -> Option<&K> {
+        partition_point(&self.root, &mut pred, self.height)
+    }
+
+    fn successor(&self, key: &K) -> Option<(&K, &V)> {
+        successor(&self.root, key, self.height)
+    }
+
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)> {
+        predecessor(&self.root, key, self.height)
+    }
+
+    fn clear(&mut self) {
+        self.root = Vec::with_capacity(self.fanout);
+        self.size = 0_usize;
+        self.height = 0_usize;
+        self.version = self.version.wrapping_add(1_usize);
+    }
+
+    fn drain(&mut self) -> std::vec::IntoIter<(K, V)> {
+        let mut vec = Vec::with_capacity(self.size);
+        into_in_order(std::mem::take(&mut self.root), &mut vec);
+        self.clear();
+        vec.into_iter()
+    }
+}
+
+// TODO: fix lifetime params for search!
+fn search<'a, K, V>(node: &'a [Entry<K, V>], key: &K, height: usize) -> Option<&'a V>
+where
+    K: Ord + 'a,
+    V: 'a,
+{
+    if height.eq(&0_usize) {
+        for n in node {
+            if key.eq(&n.key) {
+                return n.val.as_ref();
+            }
+        }
+    } else {
+        for j in 0..node.len() {
+            if (j + 1).eq(&node.len()) || key.lt(&node[j + 1].key) {
+                return search(&node[j].next, key, height - 1_usize);
+            }
+        }
+    }
+    None
+}
+
+// Same as `search`, for replacing a value in place
+fn search_mut<'a, K, V>(node: &'a mut [Entry<K, V>], key: &K, height: usize) -> Option<&'a mut V>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    if height.eq(&0_usize) {
+        node.iter_mut()
+            .find(|n| key.eq(&n.key))
+            .and_then(|n| n.val.as_mut())
+    } else {
+        let j = child_index(node, key);
+        search_mut(&mut node[j].next, key, height - 1_usize)
+    }
+}
+
+// Count of entries under `node`, nodes keep no sizes
+fn count<K, V>(node: &[Entry<K, V>], height: usize) -> usize
+where
+    K: Ord,
+{
+    if height.eq(&0_usize) {
+        node.len()
+    } else {
+        node.iter().map(|e| count(&e.next, height - 1_usize)).sum()
+    }
+}
+
+fn rank<K, V>(node: &[Entry<K, V>], key: &K, height: usize) -> usize
+where
+    K: Ord,
+{
+    if height.eq(&0_usize) {
+        return node.iter().filter(|e| e.key.lt(key)).count();
+    }
+    let j = child_index(node, key);
+    let before: usize = node[..j]
+        .iter()
+        .map(|e| count(&e.next, height - 1_usize))
+        .sum();
+    before
+        + node
+            .get(j)
+            .map_or(0_usize, |e| rank(&e.next, key, height - 1_usize))
+}
+
+fn select<K, V>(node: &[Entry<K, V>], mut rank: usize, height: usize) -> Option<&K>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    if height.eq(&0_usize) {
+        return node.get(rank).map(|e| &e.key);
+    }
+    for e in node {
+        let n = count(&e.next, height - 1_usize);
+        if rank.lt(&n) {
+            return select(&e.next, rank, height - 1_usize);
+        }
+        rank -= n;
+    }
+    None
+}
+
+// Index of the child whose subtree may hold `key`
+fn child_index<K, V>(node: &[Entry<K, V>], key: &K) -> usize
+where
+    K: Ord,
+{
+    let mut j = 0_usize;
+    while (j + 1_usize).lt(&node.len()) && !key.lt(&node[j + 1].key) {
+        j += 1;
+    }
+    j
+}
+
+fn first<K, V>(node: &[Entry<K, V>], height: usize) -> Option<&K>
+where
+    K: Ord,
+{
+    let e = node.first()?;
+    if height.eq(&0_usize) {
+        Some(&e.key)
+    } else {
+        first(&e.next, height - 1_usize)
+    }
+}
+
+fn last<K, V>(node: &[Entry<K, V>], height: usize) -> Option<&K>
+where
+    K: Ord,
+{
+    let e = node.last()?;
+    if height.eq(&0_usize) {
+        Some(&e.key)
+    } else {
+        last(&e.next, height - 1_usize)
+    }
+}
+
+fn floor<'a, K, V>(node: &'a [Entry<K, V>], key: &K, height: usize) -> Option<&'a K>
+where
+    K: Ord,
+{
+    if height.eq(&0_usize) {
+        return node.iter().rev().map(|e| &e.key).find(|k| !key.lt(k));
+    }
+    if node.is_empty() {
+        return None;
+    }
+    let j = child_index(node, key);
+    match floor(&node[j].next, key, height - 1_usize) {
+        None if j.gt(&0_usize) => last(&node[j - 1].next, height - 1_usize),
+        found => found,
+    }
+}
+
+fn ceiling<'a, K, V>(node: &'a [Entry<K, V>], key: &K, height: usize) -> Option<&'a K>
+where
+    K: Ord,
+{
+    if height.eq(&0_usize) {
+        return node.iter().map(|e| &e.key).find(|k| !k.lt(&key));
+    }
+    if node.is_empty() {
+        return None;
+    }
+    let j = child_index(node, key);
+    match ceiling(&node[j].next, key, height - 1_usize) {
+        None if (j + 1_usize).lt(&node.len()) => first(&node[j + 1].next, height - 1_usize),
+        found => found,
+    }
+}
+
+// Keys of a child are no less than its separator, so the first child with
+// a failing separator starts with a failing key
+fn partition_point<'a, K, V, P>(
+    node: &'a [Entry<K, V>],
+    pred: &mut P,
+    height: usize,
+) -> Option<&'a K>
+where
+    K: Ord + Clone,
+    V: Clone,
+    P: FnMut(&K) -> bool,
+{
+    if height.eq(&0_usize) {
+        return node.iter().map(|e| &e.key).find(|k| !pred(k));
+    }
+    if node.is_empty() {
+        return None;
+    }
+    let j = node[1..].partition_point(|e| pred(&e.key));
+    match partition_point(&node[j].next, pred, height - 1_usize) {
+        None if (j + 1_usize).lt(&node.len()) => first(&node[j + 1].next, height - 1_usize),
+        found => found,
+    }
+}
+
+fn successor<'a, K, V>(node: &'a [Entry<K, V>], key: &K, height: usize) -> Option<(&'a K, &'a V)>
+where
+    K: Ord,
+{
+    if height.eq(&0_usize) {
+        return node
+            .iter()
+            .filter(|e| key.lt(&e.key))
+            .find_map(|e| e.val.as_ref().map(|v| (&e.key, v)));
+    }
+    if node.is_empty() {
+        return None;
+    }
+    let j = child_index(node, key);
+    match successor(&node[j].next, key, height - 1_usize) {
+        None if (j + 1_usize).lt(&node.len()) => {
+            let mut vec = Vec::with_capacity(1_usize);
+            take_smallest(&node[j + 1].next, &mut vec, 1_usize);
+            vec.pop()
+        }
+        found => found,
+    }
+}
+
+// The child left of the first separator not less than `key` holds the
+// keys less than it
+fn predecessor<'a, K, V>(node: &'a [Entry<K, V>], key: &K, height: usize) -> Option<(&'a K, &'a V)>
+where
+    K: Ord,
+{
+    if height.eq(&0_usize) {
+        return node
+            .iter()
+            .rev()
+            .filter(|e| key.gt(&e.key))
+            .find_map(|e| e.val.as_ref().map(|v| (&e.key, v)));
+    }
+    if node.is_empty() {
+        return None;
+    }
+    let j = node[1..].partition_point(|e| e.key.lt(key));
+    match predecessor(&node[j].next, key, height - 1_usize) {
+        None if j.gt(&0_usize) => {
+            let mut vec = Vec::with_capacity(1_usize);
+            take_largest(&node[j - 1].next, &mut vec, 1_usize);
+            vec.pop()
+        }
+        found => found,
+    }
+}
+
+// Splits sorted `keys` among children, every child gets the keys it may hold
+fn fill_sorted<'a, K, V>(
+    node: &'a [Entry<K, V>],
+    keys: &[K],
+    out: &mut [Option<&'a V>],
+    height: usize,
+) where
+    K: Ord + Clone,
+    V: Clone,
+{
+    let mut from = 0_usize;
+    for (j, e) in node.iter().enumerate() {
+        if from.eq(&keys.len()) {
+            return;
+        }
+        let to = if height.eq(&0_usize) {
+            let lo = from + keys[from..].partition_point(|q| q.lt(&e.key));
+            let hi = lo + keys[lo..].partition_point(|q| q.eq(&e.key));
+            out[lo..hi].iter_mut().for_each(|o| *o = e.val.as_ref());
+            hi
+        } else {
+            let to = match node.get(j + 1_usize) {
+                Some(next) => from + keys[from..].partition_point(|q| q.lt(&next.key)),
+                None => keys.len(),
+            };
+            fill_sorted(
+                &e.next,
+                &keys[from..to],
+                &mut out[from..to],
+                height - 1_usize,
+            );
+            to
+        };
+        from = to;
+    }
+}
+
+fn map_node<K, V, U, F>(node: Node<K, V>, f: &mut F) -> Node<K, U>
+where
+    K: Ord + Clone,
+    V: Clone,
+    U: Clone,
+    F: FnMut(&K, V) -> U,
+{
+    let mut t: Node<K, U> = Vec::with_capacity(node.capacity());
+    for Entry { key, val, next } in node {
+        let next = map_node(next, f);
+        let val = val.map(|v| f(&key, v));
+        t.push(Entry::create(key, val, next));
+    }
+    t
+}
+
+// Collects leaf entries, internal entries only hold the routing keys
+fn in_order<'a, K, V>(node: &'a [Entry<K, V>], vec: &mut Vec<(&'a K, &'a V)>)
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    for e in node {
+        match e.val {
+            Some(ref v) => vec.push((&e.key, v)),
+            None => in_order(&e.next, vec),
+        }
+    }
+}
+
+fn take_smallest<'a, K, V>(node: &'a [Entry<K, V>], vec: &mut Vec<(&'a K, &'a V)>, n: usize)
+where
+    K: Ord,
+{
+    for e in node {
+        if vec.len().ge(&n) {
+            return;
+        }
+        match e.val {
+            Some(ref v) => vec.push((&e.key, v)),
+            None => take_smallest(&e.next, vec, n),
+        }
+    }
+}
+
+fn take_largest<'a, K, V>(node: &'a [Entry<K, V>], vec: &mut Vec<(&'a K, &'a V)>, n: usize)
+where
+    K: Ord,
+{
+    for e in node.iter().rev() {
+        if vec.len().ge(&n) {
+            return;
+        }
+        match e.val {
+            Some(ref v) => vec.push((&e.key, v)),
+            None => take_largest(&e.next, vec, n),
+        }
+    }
+}
+
+fn take_range<'a, K, V, R>(node: &'a [Entry<K, V>], range: &R, vec: &mut Vec<(&'a K, &'a V)>)
+where
+    K: Ord,
+    R: RangeBounds<K>,
+{
+    for (j, e) in node.iter().enumerate() {
+        if let Some(ref v) = e.val {
+            if range.contains(&e.key) {
+                vec.push((&e.key, v));
+            }
+            continue;
+        }
+        // keys of the first child can be lower than its routing key
+        if j.gt(&0_usize) && !range.contains(&e.key) && !reaches_above(range, &e.key) {
+            return;
+        }
+        if (j + 1_usize).lt(&node.len()) && !reaches_below(range, &node[j + 1].key) {
+            continue;
+        }
+        take_range(&e.next, range, vec);
+    }
+}
+
+// Same walk as `take_range`, folding entries instead of taking them
+fn try_fold_range<K, V, R, B, E, F>(
+    node: &[Entry<K, V>],
+    range: &R,
+    init: B,
+    f: &mut F,
+) -> Result<B, E>
+where
+    K: Ord + Clone,
+    V: Clone,
+    R: RangeBounds<K>,
+    F: FnMut(B, &K, &V) -> Result<B, E>,
+{
+    let mut acc = init;
+    for (j, e) in node.iter().enumerate() {
+        if let Some(ref v) = e.val {
+            if range.contains(&e.key) {
+                acc = f(acc, &e.key, v)?;
+            }
+            continue;
+        }
+        if j.gt(&0_usize) && !range.contains(&e.key) && !reaches_above(range, &e.key) {
+            break;
+        }
+        if (j + 1_usize).lt(&node.len()) && !reaches_below(range, &node[j + 1].key) {
+            continue;
+        }
+        acc = try_fold_range(&e.next, range, acc, f)?;
+    }
+    Ok(acc)
+}
+
+fn before_start<K: Ord, R: RangeBounds<K>>(range: &R, key: &K) -> bool {
+    match range.start_bound() {
+        Bound::Included(lo) => key.lt(lo),
+        Bound::Excluded(lo) => key.le(lo),
+        Bound::Unbounded => false,
+    }
+}
+
+fn past_end<K: Ord, R: RangeBounds<K>>(range: &R, key: &K) -> bool {
+    match range.end_bound() {
+        Bound::Included(hi) => key.gt(hi),
+        Bound::Excluded(hi) => key.ge(hi),
+        Bound::Unbounded => false,
+    }
+}
+
+// Same walk as `take_range`, for keys with a first component of `prefix`
+fn take_prefix<'a, A, B, V>(
+    node: &'a [Entry<(A, B), V>],
+    prefix: &A,
+    vec: &mut Vec<(&'a (A, B), &'a V)>,
+) where
+    A: Ord + Clone,
+    B: Ord + Clone,
+    V: Clone,
+{
+    for (j, e) in node.iter().enumerate() {
+        if let Some(ref v) = e.val {
+            if e.key.0.eq(prefix) {
+                vec.push((&e.key, v));
+            }
+            continue;
+        }
+        // keys of the first child can be lower than its routing key
+        if j.gt(&0_usize) && e.key.0.gt(prefix) {
+            return;
+        }
+        if (j + 1_usize).lt(&node.len()) && node[j + 1].key.0.lt(prefix) {
+            continue;
+        }
+        take_prefix(&e.next, prefix, vec);
+    }
+}
+
+// Same walk as `take_range`, children of tall nodes are spawned as tasks
+#[cfg(feature = "rayon")]
+fn par_range_walk<K, V, R, F>(node: &[Entry<K, V>], range: &R, height: usize, f: &F)
+where
+    K: Ord + Clone + Sync,
+    V: Clone + Sync,
+    R: RangeBounds<K> + Sync,
+    F: Fn(&K, &V) + Sync,
+{
+    if height.lt(&PARALLEL_HEIGHT) {
+        let mut vec = Vec::new();
+        take_range(node, range, &mut vec);
+        return vec.into_iter().for_each(|(k, v)| f(k, v));
+    }
+    rayon::scope(|s| {
+        for (j, e) in node.iter().enumerate() {
+            if j.gt(&0_usize) && !range.contains(&e.key) && !reaches_above(range, &e.key) {
+                return;
+            }
+            if (j + 1_usize).lt(&node.len()) && !reaches_below(range, &node[j + 1].key) {
+                continue;
+            }
+            s.spawn(move |_| par_range_walk(&e.next, range, height - 1_usize, f));
+        }
+    });
+}
+
+// Calls `f` with entries in order, stopping at the first error
+fn try_in_order<K, V, E, F>(node: &[Entry<K, V>], f: &mut F) -> Result<(), E>
+where
+    K: Ord + Clone,
+    V: Clone,
+    F: FnMut(&K, &V) -> Result<(), E>,
+{
+    for e in node {
+        match e.val {
+            Some(ref v) => f(&e.key, v)?,
+            None => try_in_order(&e.next, f)?,
+        }
+    }
+    Ok(())
+}
+
+fn into_in_order<K, V>(node: Node<K, V>, vec: &mut Vec<(K, V)>)
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    for Entry { key, val, next } in node {
+        match val {
+            Some(v) => vec.push((key, v)),
+            None => into_in_order(next, vec),
+        }
+    }
+}
+
+fn leaves<K, V>(node: &[Entry<K, V>], height: usize) -> usize
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    if height.eq(&0_usize) {
+        return 1_usize;
+    }
+    node.iter().map(|e| leaves(&e.next, height - 1_usize)).sum()
+}
+
+// How `insert` and `delete` handle nodes, taken from the tree
+#[derive(Clone, Copy)]
+struct Policy {
+    fanout: usize,
+    // Overflowing nodes are not split, but left to the parent, which
+    // first tries to move an entry to a sibling
+    b_star: bool,
+    // Changed nodes are left without spare capacity
+    trim: bool,
+}
+
+fn insert<K, V>(
+    h: &mut Node<K, V>,
+    key: K,
+    val: V,
+    height: usize,
+    policy: Policy,
+) -> Option<Node<K, V>>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    let Policy {
+        fanout,
+        b_star,
+        trim,
+    } = policy;
+    let mut j = 0;
+    // leaf entries never get children, so no room is kept for them
+    let mut t = Entry::create(key.clone(), Some(val.clone()), Vec::new());
+    if height == 0_usize {
+        // External Node
+        while j < h.len() {
+            if key.lt(&h[j].key) {
+                break;
+            }
+            j += 1;
+        }
+    } else {
+        // Internal Node
+        while j < h.len() {
+            if (j + 1_usize).eq(&h.len()) || key.lt(&h[j + 1].key) {
+                let split = match insert(&mut h[j].next, key, val, height - 1_usize, policy) {
+                    None if h[j].next.len().eq(&fanout) && !redistribute(h, j, height, fanout) => {
+                        Some(split_half(&mut h[j].next, trim))
+                    }
+                    split => split,
+                };
+                if trim {
+                    // siblings may have taken an entry from `redistribute`
+                    let siblings = j.saturating_sub(1_usize)..(j + 2_usize).min(h.len());
+                    h[siblings].iter_mut().for_each(|e| e.next.shrink_to_fit());
+                }
+                if let Some(u) = split {
+                    t.key = u[0].key.clone();
+                    t.val = None;
+                    t.next = u;
+                    j += 1;
+                    break;
+                } else {
+                    return None;
+                }
+            }
+            j += 1;
+        }
+    }
+    let mut i = h.len();
+    while i.gt(&j) {
+        if i.eq(&h.len()) {
+            h.push(h[i - 1].clone());
+        } else {
+            h.swap(i, i - 1);
+        }
+        i -= 1;
+    }
+    if j.eq(&h.len()) {
+        h.push(t);
+    } else {
+        h[j] = t;
+    }
+
+    if h.len().lt(&fanout) || b_star {
+        if trim {
+            h.shrink_to_fit();
+        }
+        None
+    } else {
+        Some(split_half(h, trim))
+    }
+}
+
+fn delete<K, V>(h: &mut Node<K, V>, key: &K, height: usize, policy: Policy) -> Option<V>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    if height.eq(&0_usize) {
+        let j = h.iter().position(|e| key.eq(&e.key))?;
+        let v = h.remove(j).val;
+        if policy.trim {
+            h.shrink_to_fit();
+        }
+        return v;
+    }
+    let j = child_index(h, key);
+    let v = delete(&mut h[j].next, key, height - 1_usize, policy)?;
+    if h[j].next.len().lt(&(policy.fanout / 2_usize)) {
+        refill(h, j, height, policy);
+    }
+    Some(v)
+}
+
+// Borrows an entry from a sibling of underfull child `j` of `h`, or merges
+// the child with a sibling without one to spare
+fn refill<K, V>(h: &mut Node<K, V>, j: usize, height: usize, policy: Policy)
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    let internal = height.gt(&1_usize);
+    let half = policy.fanout / 2_usize;
+    if h.len().eq(&1_usize) {
+        // a lone child has no sibling, an empty one is dropped and `h`,
+        // then underfull too, is refilled by its parent
+        if h[0].next.is_empty() {
+            h.clear();
+        }
+        return;
+    }
+    if j.gt(&0_usize) && h[j - 1].next.len().gt(&half) {
+        if let Some(e) = h[j - 1].next.pop() {
+            // keys of a first child can be lower than its routing key
+            if internal {
+                let routing = h[j].key.clone();
+                if let Some(first) = h[j].next.first_mut() {
+                    first.key = routing;
+                }
+            }
+            h[j].key = e.key.clone();
+            h[j].next.insert(0, e);
+        }
+    } else if (j + 1_usize).lt(&h.len()) && h[j + 1].next.len().gt(&half) {
+        let mut e = h[j + 1].next.remove(0);
+        if internal {
+            e.key = h[j + 1].key.clone();
+        }
+        h[j].next.push(e);
+        h[j + 1].key = h[j + 1].next[0].key.clone();
+    } else {
+        // the right one of the pair moves into the left one
+        let left = if j.gt(&0_usize) { j - 1_usize } else { j };
+        if (left + 1_usize).lt(&h.len()) {
+            let Entry { key, val: _, next } = h.remove(left + 1_usize);
+            let mut next = next.into_iter();
+            if let Some(mut first) = next.next() {
+                if internal {
+                    first.key = key;
+                }
+                h[left].next.push(first);
+            }
+            h[left].next.extend(next);
+        }
+    }
+    if policy.trim {
+        h.shrink_to_fit();
+        let siblings = j.saturating_sub(1_usize)..(j + 2_usize).min(h.len());
+        h[siblings].iter_mut().for_each(|e| e.next.shrink_to_fit());
+    }
+}
+
+// Split node in half
+fn split_half<K, V>(h: &mut Node<K, V>, trim: bool) -> Node<K, V>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    let t = h.split_off(h.len() / 2_usize);
+    if trim {
+        h.shrink_to_fit();
+    }
+    t
+}
+
+// Pushes `e` to the last leaf, a full child first passes an entry to its
+// left sibling, and splits in half once that one is full too; a full `h`
+// is left to the caller
+fn push_last<K, V>(h: &mut Node<K, V>, e: Entry<K, V>, height: usize, fanout: usize)
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    if height.eq(&0_usize) {
+        h.push(e);
+        return;
+    }
+    let last = h.len() - 1_usize;
+    push_last(&mut h[last].next, e, height - 1_usize, fanout);
+    if h[last].next.len().eq(&fanout) && !redistribute(h, last, height, fanout) {
+        let u = split_half(&mut h[last].next, false);
+        h.push(Entry::create(u[0].key.clone(), None, u));
+    }
+}
+
+impl<K: Ord> Hint<K> {
+    fn covers(&self, key: &K, version: usize) -> bool {
+        self.version.eq(&version)
+            && self.low.as_ref().is_none_or(|low| low.le(key))
+            && self.high.as_ref().is_none_or(|high| key.lt(high))
+    }
+}
+
+fn leaf_at<'a, K, V>(node: &'a mut Node<K, V>, path: &[usize]) -> Option<&'a mut Node<K, V>>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    match path.split_first() {
+        Some((j, rest)) => leaf_at(&mut node.get_mut(*j)?.next, rest),
+        None => Some(node),
+    }
+}
+
+fn trim<K, V>(node: &mut Node<K, V>)
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    node.shrink_to_fit();
+    for e in node.iter_mut() {
+        trim(&mut e.next);
+    }
+}
+
+// Moves an entry of overflowing child `j` of `h` to a sibling with room
+fn redistribute<K, V>(h: &mut Node<K, V>, j: usize, height: usize, fanout: usize) -> bool
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    let internal = height.gt(&1_usize);
+    if j.gt(&0_usize) && h[j - 1].next.len().lt(&(fanout - 1_usize)) {
+        let mut e = h[j].next.remove(0);
+        // keys of a first child can be lower than its routing key
+        if internal {
+            e.key = h[j].key.clone();
+        }
+        h[j - 1].next.push(e);
+        h[j].key = h[j].next[0].key.clone();
+        return true;
+    }
+    if (j + 1_usize).lt(&h.len()) && h[j + 1].next.len().lt(&(fanout - 1_usize)) {
+        if let Some(e) = h[j].next.pop() {
+            if internal {
+                h[j + 1].next[0].key = h[j + 1].key.clone();
+            }
+            h[j + 1].key = e.key.clone();
+            h[j + 1].next.insert(0, e);
+            return true;
+        }
+    }
+    false
+}
+
+// Drops roots left with one child, a root of no entries is a leaf
+fn lift<K, V>((mut node, mut height): Part<K, V>) -> Part<K, V>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    while height.gt(&0_usize) && node.len().eq(&1_usize) {
+        node = node.pop().map(|e| e.next).unwrap_or_default();
+        height -= 1_usize;
+    }
+    if node.is_empty() {
+        height = 0_usize;
+    }
+    (node, height)
+}
+
+// Joins parts of a tree of heights given, every key of `left` less than
+// those of `right`, either part can be empty
+fn join_parts<K, V>(left: Part<K, V>, right: Part<K, V>, fanout: usize) -> Part<K, V>
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    let ((mut left, hl), (mut right, hr)) = (lift(left), lift(right));
+    let low = match (left.is_empty(), first(&right, hr)) {
+        (false, Some(low)) => low.clone(),
+        (true, _) => return (right, hr),
+        (false, None) => return (left, hl),
+    };
+    let (mut root, mut height) = if hl.ge(&hr) {
+        graft_last(&mut left, hl, right, hr, low, fanout);
+        (left, hl)
+    } else {
+        graft_first(&mut right, hr, left, hl, low, fanout);
+        (right, hr)
+    };
+    if root.len().ge(&fanout) {
+        // need to split the root
+        let u = split_half(&mut root, false);
+        let mut t = Vec::with_capacity(fanout);
+        t.push(Entry::create(root[0].key.clone(), None, root));
+        t.push(Entry::create(u[0].key.clone(), None, u));
+        root = t;
+        height += 1_usize;
+    }
+    (root, height)
+}
+
+// Appends `sub` to the last node of its height under `h`, `low` the min
+// key of `sub`; overflowing nodes split in half, a full `h` is left to the
+// caller
+fn graft_last<K, V>(
+    h: &mut Node<K, V>,
+    height: usize,
+    sub: Node<K, V>,
+    sub_height: usize,
+    low: K,
+    fanout: usize,
+) where
+    K: Ord + Clone,
+    V: Clone,
+{
+    if height.eq(&sub_height) {
+        let mut sub = sub.into_iter();
+        if let Some(mut first) = sub.next() {
+            // keys of a first child can be lower than its routing key
+            if height.gt(&0_usize) {
+                first.key = low;
+            }
+            h.push(first);
+        }
+        h.extend(sub);
+        return;
+    }
+    let last = h.len() - 1_usize;
+    graft_last(
+        &mut h[last].next,
+        height - 1_usize,
+        sub,
+        sub_height,
+        low,
+        fanout,
+    );
+    if h[last].next.len().ge(&fanout) {
+        let u = split_half(&mut h[last].next, false);
+        h.push(Entry::create(u[0].key.clone(), None, u));
+    }
+}
+
+// Same as `graft_last`, prepending `sub` to the first node of its height
+// under `h`, `low` the min key of `h`
+fn graft_first<K, V>(
+    h: &mut Node<K, V>,
+    height: usize,
+    sub: Node<K, V>,
+    sub_height: usize,
+    low: K,
+    fanout: usize,
+) where
+    K: Ord + Clone,
+    V: Clone,
+{
+    if height.eq(&sub_height) {
+        if height.gt(&0_usize) {
+            h[0].key = low;
+        }
+        h.splice(0..0, sub);
+        return;
+    }
+    graft_first(
+        &mut h[0].next,
+        height - 1_usize,
+        sub,
+        sub_height,
+        low,
+        fanout,
+    );
+    if h[0].next.len().ge(&fanout) {
+        let u = split_half(&mut h[0].next, false);
+        h.insert(1_usize, Entry::create(u[0].key.clone(), None, u));
+    }
+}
+
+// Cuts `h` at `key` into parts of the lower and the greater keys, the
+// value of `key` aside, joining the parts of every level on the way up
+fn split_node<K, V>(
+    mut h: Node<K, V>,
+    height: usize,
+    key: &K,
+    fanout: usize,
+) -> (Part<K, V>, Option<V>, Part<K, V>)
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    if height.eq(&0_usize) {
+        let j = h.iter().position(|e| !e.key.lt(key)).unwrap_or(h.len());
+        let mut greater = h.split_off(j);
+        let found = if greater.first().is_some_and(|e| e.key.eq(key)) {
+            greater.remove(0_usize).val
+        } else {
+            None
+        };
+        return ((h, 0_usize), found, (greater, 0_usize));
+    }
+    let j = child_index(&h, key);
+    let right = h.split_off(j + 1_usize);
+    let child = h.pop().map(|e| e.next).unwrap_or_default();
+    let (less, found, greater) = split_node(child, height - 1_usize, key, fanout);
+    (
+        join_parts((h, height), less, fanout),
+        found,
+        join_parts(greater, (right, height), fanout),
+    )
+}
+
+impl<K: Ord + Clone, V: Clone> BalancedTree<K, V> {
+    /// Returns the value of `key`, putting the value `f` returns first if the
+    /// key is missing.
+    ///
+    /// On an error of `f` nothing is put and the error is returned, so a
+    /// fallible constructor runs only for missing keys, without a separate
+    /// `contains` and `put`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use std::num::ParseIntError;
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<&str, u32> = BalancedTree::new();
+    /// let parsed: Result<&mut u32, ParseIntError> = btree.get_or_try_insert_with("a", || "12".parse());
+    /// *parsed.unwrap() += 1;
+    /// assert_eq!(btree.get(&"a"), Some(&13));
+    ///
+    /// // a present key never calls `f`
+    /// let again = btree.get_or_try_insert_with("a", || "not called".parse::<u32>());
+    /// assert_eq!(again.copied(), Ok(13));
+    ///
+    /// assert!(btree.get_or_try_insert_with("b", || "x".parse::<u32>()).is_err());
+    /// assert_eq!(btree.size(), 1_usize);
+    /// ```
+    pub fn get_or_try_insert_with<E, F: FnOnce() -> Result<V, E>>(
+        &mut self,
+        key: K,
+        f: F,
+    ) -> Result<&mut V, E> {
+        if !self.contains(&key) {
+            let value = f()?;
+            self.put(key.clone(), value);
+        }
+        match search_mut(&mut self.root, &key, self.height) {
+            Some(v) => Ok(v),
+            None => unreachable!(),
+        }
+    }
+
+    /// Streams entries to `writer` in key order, each one written by `fmt`,
+    /// then flushes it.
+    ///
+    /// Entries go to `fmt` as the tree is walked, nothing is collected on
+    /// the way, so a tree of any size is exported in constant memory.
+    /// `dump::tsv` and `dump::jsonl` write common formats, a closure any
+    /// other; pass files in a `BufWriter`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use treers::btree::BalancedTree;
+    /// use treers::{dump, Map};
+    ///
+    /// let mut btree: BalancedTree<u32, &str> = BalancedTree::new();
+    /// btree.put(2, "b");
+    /// btree.put(1, "a");
+    ///
+    /// let mut out = Vec::new();
+    /// btree.write_in_order(&mut out, dump::tsv).unwrap();
+    /// assert_eq!(out, b"1\ta\n2\tb\n");
+    ///
+    /// let mut out = Vec::new();
+    /// btree.write_in_order(&mut out, |w, k, v| write!(w, "{}={};", k, v)).unwrap();
+    /// assert_eq!(out, b"1=a;2=b;");
+    /// ```
+    pub fn write_in_order<W, F>(&self, mut writer: W, mut fmt: F) -> std::io::Result<()>
+    where
+        W: std::io::Write,
+        F: FnMut(&mut W, &K, &V) -> std::io::Result<()>,
+    {
+        try_in_order(&self.root, &mut |k: &K, v: &V| fmt(&mut writer, k, v))?;
+        writer.flush()
+    }
+
+    /// Rebuilds the `BTree` with the same node layout, transforming every
+    /// value with `f`.
+    ///
+    /// `f` is called once per entry, in key order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// for (v, k) in ('a'..='g').enumerate() {
+    ///     btree.put(k, v as i32);
+    /// }
+    ///
+    /// let btree: BalancedTree<char, String> = btree.map_values(|k, v| format!("{}{}", k, v));
+    /// assert_eq!(btree.get(&'a'), Some(&String::from("a0")));
+    /// assert_eq!(btree.get(&'g'), Some(&String::from("g6")));
+    /// assert_eq!(btree.height(), Some(1_usize));
+    /// ```
+    pub fn map_values<U: Clone, F: FnMut(&K, V) -> U>(self, mut f: F) -> BalancedTree<K, U> {
+        BalancedTree {
+            root: map_node(self.root, &mut f),
+            size: self.size,
+            height: self.height,
+            variant: self.variant,
+            auto_trim: self.auto_trim,
+            fanout: self.fanout,
+            version: self.version,
+        }
+    }
+
+    /// Rebuilds the `BTree` bottom up, see `from_sorted_iter`, into a shape
+    /// that is the same for the same keys whatever order they were put in.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut a: BalancedTree<u32, ()> = BalancedTree::new();
+    /// let mut b: BalancedTree<u32, ()> = BalancedTree::new();
+    /// for k in 0..1000 {
+    ///     a.put(k, ());
+    ///     b.put(999 - k, ());
+    /// }
+    /// a.canonicalize();
+    /// b.canonicalize();
+    /// assert_eq!(format!("{:?}", a), format!("{:?}", b));
+    /// assert_eq!(a.height(), Some(6_usize));
+    /// ```
+    pub fn canonicalize(&mut self) {
+        let (variant, auto_trim) = (self.variant, self.auto_trim);
+        let version = self.version.wrapping_add(1_usize);
+        let fanout = self.fanout;
+        let entries = std::mem::take(self).into_sorted_vec();
+        *self = Self {
+            variant,
+            auto_trim,
+            version,
+            ..Self::build_sorted(entries, fanout)
+        };
+        if auto_trim {
+            self.shrink_to_fit();
+        }
+    }
+
+    /// Looks up a batch of keys sorted in ascending order, returning values
+    /// in the same order as `keys`.
+    ///
+    /// Keys are split among subtrees as the tree is walked, so every node
+    /// is visited once for the whole batch, and subtrees without a key of
+    /// the batch are skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `keys` are not sorted.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<u32, char> = BalancedTree::new();
+    /// for (i, c) in ('a'..='j').enumerate() {
+    ///     btree.put(i as u32 * 10, c);
+    /// }
+    /// assert_eq!(
+    ///     btree.get_sorted(&[0, 5, 20, 20, 90, 100]),
+    ///     [Some(&'a'), None, Some(&'c'), Some(&'c'), Some(&'j'), None]
+    /// );
+    /// ```
+    pub fn get_sorted(&self, keys: &[K]) -> Vec<Option<&V>> {
+        debug_assert!(keys.windows(2).all(|w| w[0].le(&w[1])));
+        let mut out = vec![None; keys.len()];
+        if !self.is_empty() {
+            fill_sorted(&self.root, keys, &mut out, self.height);
+        }
+        out
+    }
+
+    /// Returns a lazy iterator over entries in key order, descending the
+    /// nodes as it goes, so stopping early leaves the rest of the tree
+    /// unvisited.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<u32, char> = BalancedTree::new();
+    /// for (k, v) in [(3, 'c'), (1, 'a'), (4, 'd'), (2, 'b'), (5, 'e')] {
+    ///     btree.put(k, v);
+    /// }
+    /// let word: String = btree.iter().map(|(_, v)| *v).collect();
+    /// assert_eq!(word, "abcde");
+    /// assert_eq!(btree.iter().len(), 5_usize);
+    /// assert_eq!((&btree).into_iter().nth(3), Some((&4, &'d')));
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            stack: vec![self.root.iter()],
+            remaining: self.size,
+        }
+    }
+
+    /// Returns cloned key-value pairs of `BTree` in key order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// btree.put('c', 3);
+    /// btree.put('a', 1);
+    /// btree.put('b', 2);
+    /// assert_eq!(btree.to_sorted_vec(), vec![('a', 1), ('b', 2), ('c', 3)]);
+    /// assert_eq!(btree.size(), 3_usize);
+    /// ```
+    pub fn to_sorted_vec(&self) -> Vec<(K, V)> {
+        let mut vec = Vec::with_capacity(self.size);
+        in_order(&self.root, &mut vec);
+        vec.into_iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// Consumes `BTree`, returning its key-value pairs in key order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<char, String> = BalancedTree::new();
+    /// btree.put('b', String::from("b"));
+    /// btree.put('a', String::from("a"));
+    /// assert_eq!(
+    ///     btree.into_sorted_vec(),
+    ///     vec![('a', String::from("a")), ('b', String::from("b"))]
+    /// );
+    /// ```
+    pub fn into_sorted_vec(self) -> Vec<(K, V)> {
+        let mut vec = Vec::with_capacity(self.size);
+        into_in_order(self.root, &mut vec);
+        vec
+    }
+
+    /// Consumes `BTree`, returning its keys in key order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// btree.put('b', 2);
+    /// btree.put('a', 1);
+    /// btree.put('c', 3);
+    /// let keys: Vec<char> = btree.into_keys().collect();
+    /// assert_eq!(keys, vec!['a', 'b', 'c']);
+    /// ```
+    pub fn into_keys(self) -> impl Iterator<Item = K> {
+        self.into_iter().map(|(k, _)| k)
+    }
+
+    /// Consumes `BTree`, returning its values in key order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<char, i32> = BalancedTree::new();
+    /// btree.put('b', 2);
+    /// btree.put('a', 1);
+    /// btree.put('c', 3);
+    /// let values: Vec<i32> = btree.into_values().collect();
+    /// assert_eq!(values, vec![1, 2, 3]);
+    /// ```
+    pub fn into_values(self) -> impl Iterator<Item = V> {
+        self.into_iter().map(|(_, v)| v)
+    }
+
+    /// Returns entries with keys inside `range` a leaf at a time, each
+    /// chunk a slice of one leaf borrowed in place, in key order.
+    ///
+    /// Nothing is copied per entry and leaves are reached lazily, one step
+    /// of the walk per chunk, so bulk scans run over the leaves as they
+    /// are; leaves are as wide as `fanout`, pick one with `with_node_bytes`
+    /// for longer chunks.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<u32, u64> = BalancedTree::with_node_bytes(256);
+    /// for k in 0..1000 {
+    ///     btree.put(k, u64::from(k));
+    /// }
+    /// let chunks: Vec<_> = btree.range_chunks(100..900).collect();
+    /// assert_eq!(chunks.iter().map(|c| c.len()).sum::<usize>(), 800_usize);
+    /// let sum: u64 = chunks.iter().map(|c| c.values().sum::<u64>()).sum();
+    /// assert_eq!(sum, (100..900).sum::<u64>());
+    /// assert_eq!(chunks[0].get(0), Some((&100, &100)));
+    /// ```
+    pub fn range_chunks<R: RangeBounds<K>>(&self, range: R) -> Chunks<'_, K, V, R> {
+        let mut stack = Vec::with_capacity(self.height + 1_usize);
+        stack.push((self.root.as_slice(), 0_usize));
+        Chunks {
+            stack,
+            height: self.height,
+            range,
+        }
+    }
+
+    /// Joins `left`, a `key` and `right` into a single `Balanced Tree`,
+    /// in `O(log n)`.
+    ///
+    /// Every key of `left` has to be less than `key`, and every key of
+    /// `right` greater than it. The lower tree is grafted whole onto the
+    /// spine of the taller one, next to its nodes of the same height, so
+    /// only nodes on that spine change. `right` of another fanout than
+    /// `left` is rebuilt first.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if keys are not ordered that way.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut left: BalancedTree<u32, char> = BalancedTree::new();
+    /// let mut right: BalancedTree<u32, char> = BalancedTree::new();
+    /// for (k, c) in (0..100).zip('a'..) {
+    ///     left.put(k, c);
+    /// }
+    /// right.put(200, 'z');
+    ///
+    /// let btree = BalancedTree::join(left, 150, 'e', right);
+    /// assert_eq!(btree.size(), 102_usize);
+    /// assert_eq!(btree.get(&150), Some(&'e'));
+    /// assert_eq!(btree.max(), Some(&200));
+    /// ```
+    pub fn join(left: Self, key: K, value: V, right: Self) -> Self {
+        debug_assert!(left.max().is_none_or(|k| k.lt(&key)));
+        debug_assert!(right.min().is_none_or(|k| k.gt(&key)));
+        let mut tree = Self::join_pair(left, right);
+        tree.insert_new(key, value);
+        tree
+    }
+
+    /// Splits the tree into trees of the keys less and greater than `key`,
+    /// returning the value of `key` too.
+    ///
+    /// Nodes on the search path are cut at `key` and the parts of every
+    /// level are joined on the way up, in `O(log n)`. Nodes keep no sizes,
+    /// so the size of the lower tree is counted, a walk of its nodes.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut btree: BalancedTree<u32, u32> = BalancedTree::new();
+    /// for i in 0..100 {
+    ///     btree.put(i, i * 10);
+    /// }
+    ///
+    /// let (less, value, greater) = btree.split(&40);
+    /// assert_eq!(less.size(), 40_usize);
+    /// assert_eq!(value, Some(400));
+    /// assert_eq!(greater.size(), 59_usize);
+    /// assert_eq!((less.max(), greater.min()), (Some(&39), Some(&41)));
+    /// ```
+    pub fn split(self, key: &K) -> (Self, Option<V>, Self) {
+        let Self {
+            root,
+            size,
+            height,
+            variant,
+            auto_trim,
+            fanout,
+            version,
+        } = self;
+        let ((less, less_height), found, (greater, greater_height)) =
+            split_node(root, height, key, fanout);
+        let less_size = count(&less, less_height);
+        let greater_size = size - less_size - usize::from(found.is_some());
+        let tree = |root, height, size| Self {
+            root,
+            size,
+            height,
+            variant,
+            auto_trim,
+            fanout,
+            version: version.wrapping_add(1_usize),
+        };
+        (
+            tree(less, less_height, less_size),
+            found,
+            tree(greater, greater_height, greater_size),
+        )
+    }
+
+    /// Returns a `Balanced Tree` with keys of both trees, on equal keys
+    /// values of `self` are kept.
+    ///
+    /// Both trees are walked in key order and the merged entries packed
+    /// bottom-up as in `from_sorted_iter`, in `O(n + m)`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut a: BalancedTree<u32, char> = BalancedTree::new();
+    /// let mut b: BalancedTree<u32, char> = BalancedTree::new();
+    /// a.put(1, 'a');
+    /// a.put(2, 'a');
+    /// b.put(2, 'b');
+    /// b.put(3, 'b');
+    ///
+    /// let union = a.union(b);
+    /// assert_eq!(union.range(..).as_slice(), &[(&1, &'a'), (&2, &'a'), (&3, &'b')]);
+    /// ```
+    pub fn union(self, other: Self) -> Self {
+        self.merge(other, |_| true)
+    }
+
+    /// Returns a `Balanced Tree` with keys present in both trees, with
+    /// values of `self`, merged as in `union`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut a: BalancedTree<u32, char> = BalancedTree::new();
+    /// let mut b: BalancedTree<u32, char> = BalancedTree::new();
+    /// a.put(1, 'a');
+    /// a.put(2, 'a');
+    /// b.put(2, 'b');
+    /// b.put(3, 'b');
+    ///
+    /// let intersection = a.intersection(b);
+    /// assert_eq!(intersection.range(..).as_slice(), &[(&2, &'a')]);
+    /// ```
+    pub fn intersection(self, other: Self) -> Self {
+        self.merge(other, |side| side.eq(&Ordering::Equal))
+    }
+
+    /// Returns a `Balanced Tree` with keys of `self` not present in
+    /// `other`, merged as in `union`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut a: BalancedTree<u32, char> = BalancedTree::new();
+    /// let mut b: BalancedTree<u32, char> = BalancedTree::new();
+    /// a.put(1, 'a');
+    /// a.put(2, 'a');
+    /// b.put(2, 'b');
+    /// b.put(3, 'b');
+    ///
+    /// let difference = a.difference(b);
+    /// assert_eq!(difference.range(..).as_slice(), &[(&1, &'a')]);
+    /// ```
+    pub fn difference(self, other: Self) -> Self {
+        self.merge(other, |side| side.eq(&Ordering::Less))
+    }
+
+    // Joins trees, every key of `left` less than those of `right`
+    fn join_pair(left: Self, right: Self) -> Self {
+        let right = if right.fanout.eq(&left.fanout) {
+            right
+        } else {
+            Self::build_sorted(right.into_sorted_vec(), left.fanout)
+        };
+        let (root, height) = join_parts(
+            (left.root, left.height),
+            (right.root, right.height),
+            left.fanout,
+        );
+        Self {
+            root,
+            size: left.size + right.size,
+            height,
+            version: left.version.wrapping_add(1_usize),
+            ..left
         }
     }
-    None
-}
 
-fn insert<K, V>(h: &mut Node<K, V>, key: K, val: V, height: usize) -> Option<Node<K, V>>
-where
-    K: Ord + Clone,
-    V: Clone,
-{
-    let mut j = 0;
-    let mut t = Entry::new(key.clone(), Some(val.clone()));
-    if height == 0_usize {
-        // External Node
-        while j < h.len() {
-            if key.lt(&h[j].key) {
-                break;
+    // Walks both trees in key order, keeping an entry only in `self`
+    // (`Less`), only in `other` (`Greater`) or in both (`Equal`, with the
+    // value of `self`) if `keep` says so
+    fn merge<F: Fn(Ordering) -> bool>(self, other: Self, keep: F) -> Self {
+        let (fanout, variant, auto_trim) = (self.fanout, self.variant, self.auto_trim);
+        let mut a = self.into_sorted_vec().into_iter().peekable();
+        let mut b = other.into_sorted_vec().into_iter().peekable();
+        let mut merged = Vec::with_capacity(a.len() + b.len());
+        loop {
+            let side = match (a.peek(), b.peek()) {
+                (Some((x, _)), Some((y, _))) => x.cmp(y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            let entry = match side {
+                Ordering::Less => a.next(),
+                Ordering::Greater => b.next(),
+                Ordering::Equal => b.next().and(a.next()),
+            };
+            if keep(side) {
+                merged.extend(entry);
             }
-            j += 1;
         }
-    } else {
-        // Internal Node
-        while j < h.len() {
-            if (j + 1_usize).eq(&h.len()) || key.lt(&h[j + 1].key) {
-                if let Some(u) = insert(&mut h[j].next, key, val, height - 1_usize) {
-                    t.key = u[0].key.clone();
-                    t.val = None;
-                    t.next = u;
-                    j += 1;
-                    break;
-                } else {
-                    return None;
-                }
-            }
-            j += 1;
+        let mut tree = Self::build_sorted(merged, fanout);
+        tree.variant = variant;
+        tree.auto_trim = auto_trim;
+        if auto_trim {
+            trim(&mut tree.root);
         }
+        tree
     }
-    let mut i = h.len();
-    while i.gt(&j) {
-        if i.eq(&h.len()) {
-            h.push(h[i - 1].clone());
-        } else {
-            h.swap(i, i - 1);
+}
+
+#[cfg(feature = "rayon")]
+impl<K: Ord + Clone + Sync, V: Clone + Sync> BalancedTree<K, V> {
+    /// Calls `f` on every entry with key inside `range`, splitting the
+    /// walk across subtrees on multiple threads. Entries are visited in no
+    /// particular order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<u64, u64> = BalancedTree::new();
+    /// for i in 0..10_000 {
+    ///     btree.put(i, 1);
+    /// }
+    ///
+    /// let sum = AtomicU64::new(0);
+    /// btree.par_range_for_each(100..200, |k, _| {
+    ///     sum.fetch_add(*k, Ordering::Relaxed);
+    /// });
+    /// assert_eq!(sum.into_inner(), (100..200).sum::<u64>());
+    /// ```
+    pub fn par_range_for_each<R, F>(&self, range: R, f: F)
+    where
+        R: RangeBounds<K> + Sync,
+        F: Fn(&K, &V) + Sync,
+    {
+        if !self.is_empty() {
+            par_range_walk(&self.root, &range, self.height, &f);
         }
-        i -= 1;
-    }
-    if j.eq(&h.len()) {
-        h.push(t);
-    } else {
-        h[j] = t;
     }
+}
 
-    if h.len().lt(&M) {
-        None
-    } else {
-        // Split node in half
-        let mut t: Node<K, V> = Vec::with_capacity(M / 2);
-        // TODO: work for M=4, find a better solution!
-        for _ in 0..(M / 2) {
-            t.push(h.remove(M / 2));
-        }
-        Some(t)
+/// Queries of maps keyed by pairs.
+impl<A: Ord + Clone, B: Ord + Clone, V: Clone> BalancedTree<(A, B), V> {
+    /// Returns entries whose key starts with `prefix`, in key order.
+    ///
+    /// Children with first components on the wrong side of `prefix` are
+    /// skipped, so only the matching part of the tree is walked.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::Map;
+    ///
+    /// let mut btree: BalancedTree<(u32, u32), u32> = BalancedTree::new();
+    /// for i in 0..1000 {
+    ///     btree.put((i % 10, i), i);
+    /// }
+    /// let sevens: Vec<u32> = btree.range_prefix(&7).map(|(_, v)| *v).collect();
+    /// assert_eq!(sevens.len(), 100_usize);
+    /// assert_eq!(sevens[..3], [7, 17, 27]);
+    /// ```
+    pub fn range_prefix(&self, prefix: &A) -> std::vec::IntoIter<(&(A, B), &V)> {
+        let mut vec = Vec::new();
+        take_prefix(&self.root, prefix, &mut vec);
+        vec.into_iter()
     }
 }
 
@@ -381,10 +2852,26 @@ impl<K: Ord + Clone, V: Clone> Index<&K> for BalancedTree<K, V> {
     }
 }
 
+impl<K: Ord + Clone + fmt::Display, V: Clone + fmt::Display> fmt::Display for BalancedTree<K, V> {
+    /// Writes the entries in key order as `{key: value, ...}`, nodes of a
+    /// B-tree have no notation to parse back.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("{")?;
+        for (i, (k, v)) in self.range(..).enumerate() {
+            if i.gt(&0_usize) {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}: {}", k, v)?;
+        }
+        f.write_str("}")
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::{Variant, M};
     use crate::btree::BalancedTree;
-    use crate::SedgewickMap;
+    use crate::{Map, OrderedMap, SedgewickMap};
 
     #[test]
     fn test_is_empty() {
@@ -397,14 +2884,15 @@ mod tests {
         let mut btree = BalancedTree::new();
         btree.put(1, 2);
         btree.put(2, 4);
-        assert_eq!(btree.is_empty(), false);
+        assert!(!btree.is_empty());
     }
 
     #[test]
     fn test_size_zero() {
         let btree: BalancedTree<i32, i32> = BalancedTree::new();
         assert_eq!(btree.size(), 0_usize);
-        assert_eq!(btree.height(), Some(0));
+        assert_eq!(btree.height(), None);
+        assert_eq!(btree.levels(), 0_usize);
     }
 
     #[test]
@@ -437,7 +2925,87 @@ mod tests {
         let mut btree: BalancedTree<i32, i32> = BalancedTree::new();
         btree.put(1_i32, -1_i32);
         assert!(btree.contains(&1_i32));
-        assert_eq!(btree.contains(&-1_i32), false);
+        assert!(!btree.contains(&-1_i32));
+    }
+
+    #[test]
+    fn test_floor_and_ceiling() {
+        let mut btree: BalancedTree<i32, i32> = BalancedTree::new();
+        for i in (2..=1_000_i32).step_by(2) {
+            btree.put(i, i);
+        }
+        assert_eq!(btree.floor(&1_i32), None);
+        assert_eq!(btree.ceiling(&1_i32), Some(&2_i32));
+        assert_eq!(btree.floor(&1_001_i32), Some(&1_000_i32));
+        assert_eq!(btree.ceiling(&1_001_i32), None);
+        for i in (3..1_000_i32).step_by(2) {
+            assert_eq!(btree.floor(&i), Some(&(i - 1)));
+            assert_eq!(btree.ceiling(&i), Some(&(i + 1)));
+            assert_eq!(btree.floor(&(i + 1)), Some(&(i + 1)));
+            assert_eq!(btree.ceiling(&(i - 1)), Some(&(i - 1)));
+        }
+    }
+
+    #[test]
+    fn test_map_values() {
+        let mut btree: BalancedTree<i32, i32> = BalancedTree::new();
+        for i in (1..=1_000_i32).rev() {
+            btree.put(i, i);
+        }
+        let mut last = 0_i32;
+        let mapped = btree.map_values(|k, v| {
+            assert!(last.lt(k));
+            last = *k;
+            i64::from(v) * 2
+        });
+        assert_eq!(last, 1_000_i32);
+        assert_eq!(mapped.size(), 1_000_usize);
+        assert_eq!(mapped.height(), Some(8_usize));
+        for i in 1..=1_000_i32 {
+            assert_eq!(mapped.get(&i), Some(&(i64::from(i) * 2)));
+        }
+    }
+
+    #[test]
+    fn test_sorted_vec() {
+        let mut btree: BalancedTree<u32, String> = BalancedTree::new();
+        assert!(btree.to_sorted_vec().is_empty());
+        for i in (1..=1_000_u32).rev() {
+            btree.put(i, i.to_string());
+        }
+        let expected: Vec<(u32, String)> = (1..=1_000_u32).map(|i| (i, i.to_string())).collect();
+        assert_eq!(btree.to_sorted_vec(), expected);
+        let sorted = btree.into_sorted_vec();
+        assert_eq!(sorted.capacity(), 1_000_usize);
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn test_into_keys_and_values() {
+        let build = || {
+            let mut btree: BalancedTree<u32, String> = BalancedTree::new();
+            for i in [6_u32, 4, 5, 2, 1, 3] {
+                btree.put(i, i.to_string());
+            }
+            btree
+        };
+        let keys: Vec<u32> = build().into_keys().collect();
+        assert_eq!(keys, (1..=6_u32).collect::<Vec<_>>());
+        let values: Vec<String> = build().into_values().collect();
+        assert_eq!(values, ["1", "2", "3", "4", "5", "6"]);
+        let mut iter = build().into_iter();
+        assert_eq!(iter.len(), 6_usize);
+        assert_eq!(iter.next(), Some((1, String::from("1"))));
+        assert_eq!(iter.len(), 5_usize);
+
+        let mut btree = BalancedTree::new();
+        let mut keys = crate::bench_support::shuffled_keys(2000, 4);
+        for k in &keys {
+            btree.put(*k, *k);
+        }
+        keys.sort_unstable();
+        keys.dedup();
+        assert!(btree.into_iter().eq(keys.into_iter().map(|k| (k, k))));
     }
 
     #[test]
@@ -469,4 +3037,589 @@ mod tests {
         assert_eq!(btree.get(&501_i32), Some(&502_i32));
         assert!(btree.contains(&501_i32));
     }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_range_for_each() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mut btree: BalancedTree<u32, u32> = BalancedTree::new();
+        for i in 0_u32..20000 {
+            btree.put((i * 7919) % 20000, i);
+        }
+        for (lo, hi) in [(0_u32, 20000_u32), (10, 11), (700, 13100), (26000, 27000)] {
+            let count = AtomicUsize::new(0);
+            let sum = AtomicUsize::new(0);
+            btree.par_range_for_each(lo..hi, |k, _| {
+                count.fetch_add(1, Ordering::Relaxed);
+                sum.fetch_add(*k as usize, Ordering::Relaxed);
+            });
+            let expected = (lo..hi.min(20000)).map(|k| k as usize);
+            assert_eq!(count.into_inner(), expected.clone().count());
+            assert_eq!(sum.into_inner(), expected.sum::<usize>());
+        }
+    }
+
+    // Count of nodes and entries on leaf level
+    fn leaf_fill(node: &[super::Entry<u32, u32>], height: usize) -> (usize, usize) {
+        if height.eq(&0_usize) {
+            (1_usize, node.len())
+        } else {
+            node.iter()
+                .map(|e| leaf_fill(&e.next, height - 1_usize))
+                .fold((0, 0), |(n, m), (a, b)| (n + a, m + b))
+        }
+    }
+
+    #[test]
+    fn test_b_star() {
+        let keys: Vec<u32> = (0_u32..3000).map(|i| (i * 7919) % 3000).collect();
+        for order in [(0_u32..3000).collect::<Vec<u32>>(), keys] {
+            let mut btree: BalancedTree<u32, u32> = BalancedTree::with_variant(Variant::BStar);
+            let mut plain: BalancedTree<u32, u32> = BalancedTree::new();
+            for k in order.iter() {
+                btree.put(*k, *k * 2);
+                plain.put(*k, *k * 2);
+            }
+            assert_eq!(btree.size(), 3000_usize);
+            assert!(btree.range(..).eq(plain.range(..)));
+            for k in 0_u32..3000 {
+                assert_eq!(btree.get(&k), Some(&(k * 2)));
+                assert_eq!(btree.floor(&k), Some(&k));
+                assert_eq!(btree.ceiling(&k), Some(&k));
+            }
+            assert!(btree.range(100..200).map(|(k, _)| *k).eq(100..200));
+
+            let (nodes, entries) = leaf_fill(&btree.root, btree.height);
+            let (plain_nodes, _) = leaf_fill(&plain.root, plain.height);
+            assert_eq!(entries, 3000_usize);
+            assert!(nodes.lt(&plain_nodes));
+            assert!((entries * 3).ge(&(nodes * (M - 1_usize) * 2)));
+        }
+    }
+
+    #[test]
+    fn test_from_sorted_iter() {
+        for n in [0_u32, 1, 2, 3, 4, 5, 7, 10, 100, 1000] {
+            let mut btree = BalancedTree::from_sorted_iter((0..n).map(|i| (i * 2, i)));
+            assert_eq!(btree.size(), n as usize);
+            assert!(btree.range(..).map(|(k, _)| *k).eq((0..n).map(|i| i * 2)));
+            for i in 0..n {
+                assert_eq!(btree.get(&(i * 2)), Some(&i));
+                assert_eq!(btree.floor(&(i * 2 + 1)), Some(&(i * 2)));
+            }
+            let (nodes, entries) = leaf_fill(&btree.root, btree.height);
+            if n.gt(&1_u32) {
+                assert!(entries.ge(&(nodes * 2_usize)));
+            }
+            // keeps working as a regular tree
+            for i in 0..n {
+                btree.put(i * 2 + 1, i);
+            }
+            assert!(btree.range(..).map(|(k, _)| *k).eq(0..n * 2));
+        }
+    }
+
+    #[test]
+    fn test_range_prefix() {
+        let mut btree: BalancedTree<(u32, u32), u32> = BalancedTree::new();
+        for i in 0_u32..2000 {
+            btree.put(((i * 7919) % 40, (i * 31) % 97), i);
+        }
+        for prefix in 0_u32..42 {
+            let expected: Vec<_> = btree.range(..).filter(|(k, _)| k.0 == prefix).collect();
+            assert_eq!(btree.range_prefix(&prefix).as_slice(), expected.as_slice());
+        }
+    }
+
+    // Count of entries allocated and not used, in all nodes
+    fn slack(node: &super::Node<u32, u32>) -> usize {
+        node.capacity() - node.len() + node.iter().map(|e| slack(&e.next)).sum::<usize>()
+    }
+
+    #[test]
+    fn test_shrink_to_fit() {
+        let keys: Vec<u32> = (0_u32..3000).map(|i| (i * 7919) % 3000).collect();
+        for variant in [Variant::BTree, Variant::BStar] {
+            let mut btree = BalancedTree::with_variant(variant);
+            let mut trimmed = BalancedTree::with_variant(variant);
+            trimmed.set_auto_trim(true);
+            for k in keys.iter() {
+                btree.put(*k, *k);
+                trimmed.put(*k, *k);
+            }
+            assert!(slack(&btree.root).gt(&0_usize));
+            assert_eq!(slack(&trimmed.root), 0_usize);
+            assert!(trimmed.range(..).eq(btree.range(..)));
+
+            btree.shrink_to_fit();
+            assert_eq!(slack(&btree.root), 0_usize);
+            for k in 3000_u32..3100 {
+                btree.put(k, k);
+            }
+            assert!(btree.range(..).map(|(k, _)| *k).eq(0_u32..3100));
+        }
+    }
+
+    #[test]
+    fn test_put_hint() {
+        for variant in [Variant::BTree, Variant::BStar] {
+            let mut btree = BalancedTree::with_variant(variant);
+            let mut plain = BalancedTree::with_variant(variant);
+            let mut hint = None;
+            let mut fast = 0_usize;
+            let mut x = 3_u32;
+            for i in 0_u32..3000 {
+                x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                // runs of nearby keys with jumps in between
+                let k = if i % 50_u32 == 0_u32 {
+                    (x >> 8) % 100_000
+                } else {
+                    hint.as_ref()
+                        .and_then(|h: &super::Hint<u32>| h.low)
+                        .unwrap_or(0)
+                        + (x >> 8) % 64
+                };
+                let version = btree.version;
+                hint = Some(btree.put_hint(hint.as_ref(), k, i));
+                if btree.version.eq(&version) {
+                    fast += 1_usize;
+                }
+                plain.put(k, i);
+                assert_eq!(btree.size(), plain.size());
+            }
+            assert!(btree.range(..).eq(plain.range(..)));
+            // leaves of `M - 1` entries take about every other insert
+            assert!(fast.gt(&600_usize));
+
+            // hints from before a layout change are not used
+            let stale = btree.put_hint(None, 1_000_000, 0);
+            btree.canonicalize();
+            btree.put_hint(Some(&stale), 1_000_001, 1);
+            assert_eq!(btree.max(), Some(&1_000_001));
+            assert_eq!(btree.get(&1_000_000), Some(&0));
+        }
+    }
+
+    #[test]
+    fn test_push_max() {
+        let mut btree = BalancedTree::new();
+        for k in 0_u32..3000 {
+            btree.push_max(k * 2, k);
+        }
+        assert_eq!(btree.size(), 3000_usize);
+        assert!(btree
+            .range(..)
+            .map(|(k, _)| *k)
+            .eq((0_u32..3000).map(|k| k * 2)));
+        // all leaves but the last two are full
+        let (nodes, entries) = leaf_fill(&btree.root, btree.height);
+        assert!(entries.gt(&((nodes - 1_usize) * (M - 1_usize))));
+        assert!(min_fill(&btree.root, btree.height).ge(&(M / 2_usize)));
+
+        // keeps working as a regular tree
+        for k in 0_u32..3000 {
+            btree.put(k * 2 + 1, k);
+        }
+        assert!(btree.range(..).map(|(k, _)| *k).eq(0_u32..6000));
+        assert_eq!(btree.floor(&100_000), Some(&5999));
+    }
+
+    #[test]
+    fn test_push_max_keeps_nodes_half_full() {
+        let entry = std::mem::size_of::<super::Entry<u32, u32>>();
+        for fanout in [M, 5_usize, 7, 16] {
+            let mut btree = BalancedTree::with_node_bytes(entry * fanout);
+            for k in 0_u32..2000 {
+                btree.push_max(k, k);
+                if btree.height.gt(&0_usize) {
+                    assert!(btree.root.len().ge(&2_usize));
+                    assert!(min_fill(&btree.root, btree.height).ge(&(fanout / 2_usize)));
+                }
+                assert!(widest(&btree.root).lt(&fanout));
+            }
+            assert!(btree.range(..).map(|(k, _)| *k).eq(0_u32..2000));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "not greater than the max key")]
+    fn test_push_max_panic() {
+        let mut btree: BalancedTree<u32, u32> = BalancedTree::new();
+        btree.push_max(2, 2);
+        btree.push_max(2, 1);
+    }
+
+    // Largest node anywhere in the tree
+    fn widest<K: Ord + Clone, V: Clone>(node: &super::Node<K, V>) -> usize {
+        node.iter()
+            .map(|e| widest(&e.next))
+            .fold(node.len(), usize::max)
+    }
+
+    #[test]
+    fn test_with_node_bytes() {
+        let entry = std::mem::size_of::<super::Entry<u32, u32>>();
+        assert_eq!(BalancedTree::<u32, u32>::with_node_bytes(0).fanout(), M);
+        assert_eq!(
+            BalancedTree::<u32, u32>::with_node_bytes(entry * 7).fanout(),
+            7_usize
+        );
+        let keys: Vec<u32> = (0_u32..3000).map(|i| (i * 7919) % 3000).collect();
+        for variant in [Variant::BTree, Variant::BStar] {
+            for fanout in [5_usize, 7, 16, 33] {
+                let mut btree = BalancedTree::with_node_bytes(entry * fanout);
+                btree.variant = variant;
+                let mut plain = BalancedTree::with_variant(variant);
+                for k in keys.iter() {
+                    btree.put(*k, *k);
+                    plain.put(*k, *k);
+                }
+                assert!(btree.range(..).eq(plain.range(..)));
+                assert!(widest(&btree.root).lt(&fanout));
+                assert!(btree.height().lt(&plain.height()));
+
+                btree.canonicalize();
+                assert_eq!(btree.fanout(), fanout);
+                for k in 3000_u32..3500 {
+                    btree.push_max(k, k);
+                }
+                assert!(btree.range(..).map(|(k, _)| *k).eq(0_u32..3500));
+                assert!(widest(&btree.root).lt(&fanout));
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_sorted() {
+        let mut tree: BalancedTree<u32, u32> = BalancedTree::new();
+        for i in 0_u32..1000 {
+            tree.put((i * 7919) % 1000 * 3, i);
+        }
+        let mut keys: Vec<u32> = (0_u32..600).map(|i| (i * 104_729) % 3100).collect();
+        keys.sort_unstable();
+        let expected: Vec<Option<&u32>> = keys.iter().map(|k| tree.get(k)).collect();
+        assert_eq!(tree.get_sorted(&keys), expected);
+        assert!(tree.get_sorted(&[]).is_empty());
+        assert_eq!(
+            BalancedTree::<u32, u32>::new().get_sorted(&[1, 2]),
+            [None, None]
+        );
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let mut canonical = None;
+        for step in [1_u32, 3, 7, 11] {
+            let mut btree = BalancedTree::with_variant(Variant::BStar);
+            for i in 0_u32..500 {
+                btree.put((i * step) % 500, i);
+            }
+            btree.canonicalize();
+            assert_eq!(btree.variant(), Variant::BStar);
+            assert_eq!(btree.size(), 500_usize);
+            assert!(btree.range(..).map(|(k, _)| *k).eq(0_u32..500));
+            let shape = format!("{:?}", btree.map_values(|_, _| ()));
+            if let Some(ref first) = canonical {
+                assert_eq!(&shape, first);
+            }
+            canonical = Some(shape);
+        }
+    }
+
+    // Fewest entries of a node below the root, every leaf at `height`
+    fn min_fill<K: Ord + Clone, V: Clone>(node: &super::Node<K, V>, height: usize) -> usize {
+        if height.eq(&0_usize) {
+            return node.len();
+        }
+        node.iter()
+            .map(|e| e.next.len().min(min_fill(&e.next, height - 1_usize)))
+            .min()
+            .unwrap_or(0_usize)
+    }
+
+    #[test]
+    fn test_delete() {
+        for variant in [Variant::BTree, Variant::BStar] {
+            let mut btree = BalancedTree::with_variant(variant);
+            let mut model = std::collections::BTreeMap::new();
+            let keys = crate::bench_support::shuffled_keys(3000, 11);
+            for k in keys.iter() {
+                btree.put(*k % 1000, *k);
+                model.insert(*k % 1000, *k);
+            }
+            for (i, k) in keys.iter().enumerate() {
+                assert_eq!(btree.delete(&(*k % 1000)), model.remove(&(*k % 1000)));
+                if i % 7_usize == 0_usize {
+                    btree.put(*k % 300, i as u64);
+                    model.insert(*k % 300, i as u64);
+                }
+                assert_eq!(btree.size(), model.len());
+                if btree.height.gt(&0_usize) {
+                    assert!(btree.root.len().ge(&2_usize));
+                    assert!(min_fill(&btree.root, btree.height).ge(&(M / 2_usize)));
+                }
+            }
+            assert!(btree.range(..).eq(model.iter()));
+            for k in 0_u64..1000 {
+                assert_eq!(btree.get(&k), model.get(&k));
+                assert_eq!(
+                    btree.floor(&k),
+                    model.range(..=k).next_back().map(|(k, _)| k)
+                );
+            }
+            for k in model.keys() {
+                assert!(btree.delete(k).is_some());
+            }
+            assert!(btree.is_empty());
+            assert_eq!(btree.height(), None);
+            assert_eq!(btree.height, 0_usize);
+        }
+
+        let mut packed = BalancedTree::from_sorted_iter((0_u32..500).map(|i| (i, i)));
+        for i in (0_u32..500).rev().step_by(2) {
+            assert_eq!(packed.delete(&i), Some(i));
+        }
+        assert!(packed
+            .range(..)
+            .map(|(k, _)| *k)
+            .eq((0_u32..500).step_by(2)));
+    }
+
+    #[test]
+    fn test_delete_after_push_max() {
+        let mut btree = BalancedTree::new();
+        for k in [3_u32, 8] {
+            btree.put(k, k);
+        }
+        btree.push_max(9, 9);
+        btree.push_max(10, 10);
+        btree.put(5, 5);
+        btree.push_max(11, 11);
+        btree.put(15, 15);
+        btree.push_max(16, 16);
+        assert_eq!(btree.delete_max(), Some((16, 16)));
+        assert_eq!(btree.max(), Some(&15));
+        assert_eq!(btree.delete_max(), Some((15, 15)));
+        assert!(btree.iter().map(|(k, _)| *k).eq([3, 5, 8, 9, 10, 11]));
+
+        let entry = std::mem::size_of::<super::Entry<u64, u64>>();
+        for variant in [Variant::BTree, Variant::BStar] {
+            for fanout in [M, 5_usize, 8] {
+                let mut btree = BalancedTree::with_node_bytes(entry * fanout);
+                btree.variant = variant;
+                let mut model = std::collections::BTreeMap::new();
+                let mut next = 0_u64;
+                for (i, x) in crate::bench_support::shuffled_keys(4000, 38)
+                    .into_iter()
+                    .enumerate()
+                {
+                    match x % 5 {
+                        0 | 1 => {
+                            next += 1_u64 + x % 3;
+                            btree.push_max(next, x);
+                            model.insert(next, x);
+                        }
+                        2 => {
+                            let k = x % (next + 1_u64);
+                            btree.put(k, x);
+                            model.insert(k, x);
+                        }
+                        3 => {
+                            let k = x % (next + 1_u64);
+                            assert_eq!(btree.delete(&k), model.remove(&k));
+                        }
+                        _ => assert_eq!(btree.delete_max(), model.pop_last()),
+                    }
+                    assert_eq!(btree.size(), model.len());
+                    assert_eq!(btree.max(), model.keys().next_back());
+                    if btree.height.gt(&0_usize) {
+                        assert!(btree.root.len().ge(&2_usize));
+                        assert!(min_fill(&btree.root, btree.height).gt(&0_usize));
+                    }
+                    if i % 97_usize == 0_usize {
+                        assert!(btree.iter().eq(model.iter()));
+                    }
+                }
+                assert!(btree.iter().eq(model.iter()));
+                while let Some(e) = model.pop_last() {
+                    assert_eq!(btree.delete_max(), Some(e));
+                }
+                assert_eq!((btree.height(), btree.height), (None, 0_usize));
+            }
+        }
+    }
+
+    // Nodes within fanout, non-root ones at least half full, every key
+    // reached through the routing keys
+    fn assert_valid(btree: &BalancedTree<u64, u64>) {
+        assert!(widest(&btree.root).lt(&btree.fanout));
+        if btree.height.gt(&0_usize) {
+            assert!(btree.root.len().ge(&2_usize));
+            assert!(min_fill(&btree.root, btree.height).ge(&(btree.fanout / 2_usize)));
+        }
+        assert_eq!(btree.iter().count(), btree.size());
+        assert!(btree.iter().all(|(k, v)| btree.get(k).eq(&Some(v))));
+    }
+
+    fn filled(fanout: usize, keys: impl Iterator<Item = u64>) -> BalancedTree<u64, u64> {
+        let entry = std::mem::size_of::<super::Entry<u64, u64>>();
+        let mut btree = BalancedTree::with_node_bytes(entry * fanout);
+        for k in keys {
+            btree.put(k, k);
+        }
+        btree
+    }
+
+    #[test]
+    fn test_split_and_join() {
+        for fanout in [M, 5_usize, 8] {
+            let keys = crate::bench_support::shuffled_keys(1500, 42);
+            let model: std::collections::BTreeMap<u64, u64> =
+                keys.iter().map(|k| (*k % 3000, *k % 3000)).collect();
+            for at in keys.iter().take(30).map(|k| *k % 3100) {
+                let btree = filled(fanout, model.keys().copied());
+                let (less, found, greater) = btree.split(&at);
+                assert_valid(&less);
+                assert_valid(&greater);
+                assert_eq!(found, model.get(&at).copied());
+                assert!(less.iter().eq(model.range(..at)));
+                assert!(greater
+                    .iter()
+                    .eq(model.range((std::ops::Bound::Excluded(at), std::ops::Bound::Unbounded))));
+
+                let joined = match found {
+                    Some(v) => BalancedTree::join(less, at, v, greater),
+                    None => BalancedTree::join_pair(less, greater),
+                };
+                assert_valid(&joined);
+                assert!(joined.iter().eq(model.iter()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_join_heights() {
+        for fanout in [M, 5_usize, 8] {
+            for (small, large) in [
+                (0_u64, 5000_u64),
+                (1, 5000),
+                (3, 5000),
+                (40, 5000),
+                (900, 5000),
+            ] {
+                // the lower tree on either side
+                let left = filled(fanout, 0..small);
+                let right = filled(fanout, small + 1..large);
+                let height = left.height.max(right.height);
+                let joined = BalancedTree::join(left, small, small, right);
+                assert_valid(&joined);
+                assert!(joined.height.le(&(height + 1_usize)));
+                assert!(joined.iter().map(|(k, _)| *k).eq(0..large));
+
+                let left = filled(fanout, 0..large - small - 1);
+                let right = filled(fanout, large - small..large);
+                let joined = BalancedTree::join(left, large - small - 1, 0, right);
+                assert_valid(&joined);
+                assert!(joined.iter().map(|(k, _)| *k).eq(0..large));
+            }
+        }
+        // another fanout is rebuilt to the one of `left`
+        let joined = BalancedTree::join(filled(8, 0..100), 100, 100, filled(M, 101..200));
+        assert_eq!(joined.fanout(), 8_usize);
+        assert_valid(&joined);
+        assert!(joined.iter().map(|(k, _)| *k).eq(0..200));
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = crate::bench_support::shuffled_keys(800, 43);
+        let b = crate::bench_support::shuffled_keys(800, 44);
+        let a: std::collections::BTreeSet<u64> = a.iter().map(|k| *k % 1200).collect();
+        let b: std::collections::BTreeSet<u64> = b.iter().map(|k| *k % 1200).collect();
+        let tree = |keys: &std::collections::BTreeSet<u64>, v| {
+            let mut btree = filled(5_usize, keys.iter().copied());
+            keys.iter().for_each(|k| btree.put(*k, v));
+            btree
+        };
+        let union = tree(&a, 0_u64).union(tree(&b, 1_u64));
+        assert_valid(&union);
+        assert!(union.iter().map(|(k, _)| k).eq(a.union(&b)));
+        assert!(union.iter().all(|(k, v)| v.eq(&u64::from(!a.contains(k)))));
+        assert_eq!(union.fanout(), 5_usize);
+
+        let intersection = tree(&a, 0_u64).intersection(tree(&b, 1_u64));
+        assert_valid(&intersection);
+        assert!(intersection.iter().map(|(k, _)| k).eq(a.intersection(&b)));
+        assert!(intersection.iter().all(|(_, v)| v.eq(&0_u64)));
+
+        let difference = tree(&a, 0_u64).difference(tree(&b, 1_u64));
+        assert_valid(&difference);
+        assert!(difference.iter().map(|(k, _)| k).eq(a.difference(&b)));
+        assert!(BalancedTree::new()
+            .union(tree(&b, 1_u64))
+            .iter()
+            .map(|(k, _)| k)
+            .eq(b.iter()));
+    }
+
+    #[test]
+    fn test_iter() {
+        for variant in [Variant::BTree, Variant::BStar] {
+            let mut btree = BalancedTree::with_variant(variant);
+            let mut model = std::collections::BTreeMap::new();
+            assert_eq!(btree.iter().next(), None);
+            for k in crate::bench_support::shuffled_keys(3000, 35) {
+                btree.put(k, k + 1_u64);
+                model.insert(k, k + 1_u64);
+            }
+            assert!(btree.iter().eq(model.iter()));
+            let mut iter = btree.iter();
+            for seen in 0..model.len() {
+                assert_eq!(iter.len(), model.len() - seen);
+                assert!(iter.stack.len().le(&(btree.height().unwrap() + 1_usize)));
+                iter.next();
+            }
+            assert_eq!((iter.next(), iter.len()), (None, 0_usize));
+            for _ in 0..1000 {
+                btree.delete_min();
+                model.pop_first();
+            }
+            assert!((&btree).into_iter().eq(model.iter()));
+        }
+    }
+
+    #[test]
+    fn test_range_chunks() {
+        use std::ops::Bound::{Excluded, Included, Unbounded};
+
+        for variant in [Variant::BTree, Variant::BStar] {
+            let mut btree = BalancedTree::with_variant(variant);
+            for k in crate::bench_support::shuffled_keys(600, 19) {
+                btree.put(k * 2_u64, k);
+            }
+            for range in [
+                (Unbounded, Unbounded),
+                (Included(100_u64), Excluded(400_u64)),
+                (Excluded(100), Included(400)),
+                (Included(401), Included(401)),
+                (Included(401), Excluded(401)),
+                (Excluded(1198), Unbounded),
+            ] {
+                let mut lazy = btree.range_chunks(range);
+                if lazy.next().is_some() {
+                    // the path down to one leaf, not every chunk
+                    assert!(lazy.stack.len().le(&(btree.height + 1_usize)));
+                }
+                let chunks: Vec<_> = btree.range_chunks(range).collect();
+                assert!(chunks.iter().all(|c| !c.is_empty() && c.len().le(&M)));
+                let flat = chunks.iter().flat_map(|c| c.iter());
+                assert!(flat.eq(btree.range(range)));
+                assert!(chunks
+                    .iter()
+                    .flat_map(|c| c.keys())
+                    .eq(btree.range(range).map(|(k, _)| k)));
+            }
+        }
+    }
 }
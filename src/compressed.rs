@@ -0,0 +1,364 @@
+use crate::btree::M;
+use crate::Map;
+
+/// A Balanced Tree for `String` keys, storing each node's keys as one
+/// common prefix and a suffix per entry.
+///
+/// Same layout and splits as `BalancedTree`, but sets of keys sharing long
+/// prefixes, like URLs or file paths, keep the shared bytes once per node.
+/// A lookup strips the node prefix from the key once and then compares
+/// suffixes only, or skips the suffixes when the key doesn't have the
+/// prefix at all.
+///
+/// # Examples
+///
+/// ```
+/// use treers::compressed::CompressedTree;
+/// use treers::Map;
+///
+/// let mut tree: CompressedTree<u32> = CompressedTree::new();
+/// for (i, page) in ["index", "about", "blog/1", "blog/2", "blog/3"].iter().enumerate() {
+///     tree.put(format!("https://example.com/{}", page), i as u32);
+/// }
+/// assert_eq!(tree.get(&String::from("https://example.com/blog/2")), Some(&3));
+/// assert_eq!(tree.get(&String::from("https://example.com/blog")), None);
+/// assert_eq!(tree.min(), Some(String::from("https://example.com/about")));
+///
+/// // 5 keys of 24 and more bytes, routing keys of internal nodes included
+/// assert!(tree.key_bytes() < 5_usize * 24_usize);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CompressedTree<V: Clone> {
+    root: Node<V>,
+    size: usize,
+    height: usize,
+}
+
+// Keys of a node are `prefix` followed by the suffix of every entry
+#[derive(Debug, Clone)]
+struct Node<V: Clone> {
+    prefix: String,
+    entries: Vec<Entry<V>>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry<V: Clone> {
+    suffix: String,
+    val: Option<V>,
+    next: Node<V>,
+}
+
+impl<V: Clone> Node<V> {
+    const fn new() -> Self {
+        Self {
+            prefix: String::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    fn key(&self, j: usize) -> String {
+        [self.prefix.as_str(), self.entries[j].suffix.as_str()].concat()
+    }
+
+    // Count of keys less than or equal to `key`
+    fn count_le(&self, key: &str) -> usize {
+        match key.strip_prefix(self.prefix.as_str()) {
+            Some(rest) => self.entries.partition_point(|e| e.suffix.as_str().le(rest)),
+            // without the prefix, `key` is on one side of all keys
+            None if key.lt(self.prefix.as_str()) => 0_usize,
+            None => self.entries.len(),
+        }
+    }
+
+    // Index of the child whose subtree may hold `key`
+    fn child_index(&self, key: &str) -> usize {
+        self.count_le(key).saturating_sub(1_usize)
+    }
+
+    // Inserts at `j`, shortening the prefix if `key` doesn't start with it
+    fn insert(&mut self, j: usize, key: String, val: Option<V>, next: Node<V>) {
+        if self.entries.is_empty() {
+            self.prefix = key;
+            self.entries.push(Entry {
+                suffix: String::new(),
+                val,
+                next,
+            });
+            return;
+        }
+        if !key.starts_with(self.prefix.as_str()) {
+            let keep = common_prefix(&self.prefix, &key);
+            let dropped = self.prefix.split_off(keep);
+            for e in &mut self.entries {
+                e.suffix.insert_str(0_usize, &dropped);
+            }
+        }
+        let suffix = key[self.prefix.len()..].to_string();
+        self.entries.insert(j, Entry { suffix, val, next });
+    }
+
+    // Moves bytes shared by all suffixes into the prefix, keys are sorted
+    // so the first and the last suffix are enough
+    fn tighten(&mut self) {
+        let shared = match (self.entries.first(), self.entries.last()) {
+            (Some(first), Some(last)) => common_prefix(&first.suffix, &last.suffix),
+            _ => return,
+        };
+        if shared.gt(&0_usize) {
+            self.prefix.push_str(&self.entries[0].suffix[..shared]);
+            for e in &mut self.entries {
+                e.suffix.drain(..shared);
+            }
+        }
+    }
+
+    // Split node in half, both halves get the longest prefix they can
+    fn split_half(&mut self) -> Node<V> {
+        let mut t = Node {
+            prefix: self.prefix.clone(),
+            entries: self.entries.split_off(M / 2_usize),
+        };
+        self.tighten();
+        t.tighten();
+        t
+    }
+
+    fn key_bytes(&self) -> usize {
+        self.prefix.len()
+            + self
+                .entries
+                .iter()
+                .map(|e| e.suffix.len() + e.next.key_bytes())
+                .sum::<usize>()
+    }
+}
+
+// Length in bytes of the common prefix, cut back to a char boundary
+fn common_prefix(a: &str, b: &str) -> usize {
+    let mut len = a
+        .bytes()
+        .zip(b.bytes())
+        .take_while(|(x, y)| x.eq(y))
+        .count();
+    while !a.is_char_boundary(len) {
+        len -= 1_usize;
+    }
+    len
+}
+
+impl<V: Clone> CompressedTree<V> {
+    /// Inits a new empty tree.
+    pub const fn new() -> Self {
+        Self {
+            root: Node::new(),
+            size: 0_usize,
+            height: 0_usize,
+        }
+    }
+
+    /// Returns the height of the tree, `None` for an empty tree.
+    pub fn height(&self) -> Option<usize> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.height)
+        }
+    }
+
+    /// Returns bytes held by stored keys, prefixes and suffixes of leaf and
+    /// internal nodes together.
+    pub fn key_bytes(&self) -> usize {
+        self.root.key_bytes()
+    }
+
+    /// Returns the smallest key.
+    pub fn min(&self) -> Option<String> {
+        let mut node = &self.root;
+        for _ in 0_usize..self.height {
+            node = &node.entries.first()?.next;
+        }
+        node.entries.first().map(|_| node.key(0_usize))
+    }
+
+    /// Returns the largest key.
+    pub fn max(&self) -> Option<String> {
+        let mut node = &self.root;
+        for _ in 0_usize..self.height {
+            node = &node.entries.last()?.next;
+        }
+        node.entries.len().checked_sub(1_usize).map(|j| node.key(j))
+    }
+
+    /// Returns key-value pairs in key order, with full keys.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::compressed::CompressedTree;
+    /// use treers::Map;
+    ///
+    /// let mut tree: CompressedTree<()> = CompressedTree::new();
+    /// for path in ["/usr/lib", "/usr/bin", "/etc", "/usr/bin/env"] {
+    ///     tree.put(path.to_string(), ());
+    /// }
+    /// let keys: Vec<String> = tree.to_sorted_vec().into_iter().map(|(k, _)| k).collect();
+    /// assert_eq!(keys, ["/etc", "/usr/bin", "/usr/bin/env", "/usr/lib"]);
+    /// ```
+    pub fn to_sorted_vec(&self) -> Vec<(String, V)> {
+        let mut vec = Vec::with_capacity(self.size);
+        in_order(&self.root, &mut vec);
+        vec
+    }
+}
+
+fn search<'a, V: Clone>(node: &'a Node<V>, key: &str, height: usize) -> Option<&'a V> {
+    if height.eq(&0_usize) {
+        let rest = key.strip_prefix(node.prefix.as_str())?;
+        node.entries
+            .iter()
+            .find(|e| e.suffix.eq(rest))
+            .and_then(|e| e.val.as_ref())
+    } else {
+        let next = &node.entries.get(node.child_index(key))?.next;
+        search(next, key, height - 1_usize)
+    }
+}
+
+fn search_mut<'a, V: Clone>(node: &'a mut Node<V>, key: &str, height: usize) -> Option<&'a mut V> {
+    if height.eq(&0_usize) {
+        let rest = key.strip_prefix(node.prefix.as_str())?;
+        node.entries
+            .iter_mut()
+            .find(|e| e.suffix.eq(rest))
+            .and_then(|e| e.val.as_mut())
+    } else {
+        let j = node.child_index(key);
+        search_mut(&mut node.entries.get_mut(j)?.next, key, height - 1_usize)
+    }
+}
+
+fn insert<V: Clone>(h: &mut Node<V>, key: String, val: V, height: usize) -> Option<Node<V>> {
+    if height.eq(&0_usize) {
+        let j = h.count_le(&key);
+        h.insert(j, key, Some(val), Node::new());
+    } else {
+        let j = h.child_index(&key);
+        let u = insert(&mut h.entries[j].next, key, val, height - 1_usize)?;
+        h.insert(j + 1_usize, u.key(0_usize), None, u);
+    }
+    if h.entries.len().lt(&M) {
+        None
+    } else {
+        Some(h.split_half())
+    }
+}
+
+fn in_order<V: Clone>(node: &Node<V>, vec: &mut Vec<(String, V)>) {
+    for (j, e) in node.entries.iter().enumerate() {
+        match e.val {
+            Some(ref v) => vec.push((node.key(j), v.clone())),
+            None => in_order(&e.next, vec),
+        }
+    }
+}
+
+impl<V: Clone> Default for CompressedTree<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Clone> Map<String, V> for CompressedTree<V> {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn get(&self, key: &String) -> Option<&V> {
+        search(&self.root, key, self.height)
+    }
+
+    fn put(&mut self, key: String, value: V) {
+        // existing key keeps its place, only the value changes
+        if let Some(v) = search_mut(&mut self.root, &key, self.height) {
+            *v = value;
+            return;
+        }
+        if let Some(u) = insert(&mut self.root, key, value, self.height) {
+            // need to split the root
+            let old = std::mem::replace(&mut self.root, Node::new());
+            self.root.insert(0_usize, old.key(0_usize), None, old);
+            self.root.insert(1_usize, u.key(0_usize), None, u);
+            self.height += 1_usize;
+        }
+        self.size += 1_usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompressedTree, Node};
+    use crate::Map;
+    use std::collections::BTreeMap;
+
+    // Checks every node prefix is the longest one shared by its keys
+    fn check_prefixes(node: &Node<u32>) {
+        for e in &node.entries {
+            check_prefixes(&e.next);
+        }
+        if node.entries.len().ge(&2_usize) {
+            let first = &node.entries[0].suffix;
+            let last = &node.entries[node.entries.len() - 1].suffix;
+            assert!(first.chars().next() != last.chars().next());
+        }
+    }
+
+    fn full_bytes(node: &Node<u32>) -> usize {
+        (0_usize..node.entries.len())
+            .map(|j| node.key(j).len() + full_bytes(&node.entries[j].next))
+            .sum()
+    }
+
+    #[test]
+    fn test_urls_match_model() {
+        let mut tree: CompressedTree<u32> = CompressedTree::new();
+        let mut model: BTreeMap<String, u32> = BTreeMap::new();
+        let mut x = 5_u32;
+        for i in 0_u32..3000 {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let host = ["a.com", "b.org", "čaj.hr"][(x >> 28) as usize % 3];
+            let key = format!("https://{}/api/v1/items/{}", host, (x >> 8) % 1000);
+            tree.put(key.clone(), i);
+            model.insert(key, i);
+        }
+        tree.put(String::new(), 7);
+        model.insert(String::new(), 7);
+
+        assert_eq!(tree.size(), model.len());
+        for (k, v) in &model {
+            assert_eq!(tree.get(k), Some(v));
+        }
+        for missing in ["https://a.com/items/", "https://d.net", "z"] {
+            assert_eq!(tree.get(&missing.to_string()), None);
+        }
+        let sorted: Vec<(String, u32)> = model.clone().into_iter().collect();
+        assert_eq!(tree.to_sorted_vec(), sorted);
+        assert_eq!(tree.min(), model.keys().next().cloned());
+        assert_eq!(tree.max(), model.keys().last().cloned());
+        check_prefixes(&tree.root);
+
+        // against full keys in the same layout, routing keys included
+        assert!(tree.key_bytes() * 3_usize < full_bytes(&tree.root) * 2_usize);
+    }
+
+    #[test]
+    fn test_empty() {
+        let tree: CompressedTree<u32> = CompressedTree::default();
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), None);
+        assert_eq!(tree.min(), None);
+        assert_eq!(tree.max(), None);
+        assert_eq!(tree.get(&String::from("a")), None);
+    }
+}
@@ -0,0 +1,428 @@
+use crate::btree::M;
+use std::borrow::Borrow;
+use std::mem;
+// Latches of loom under model checking, built with `--cfg loom`, see
+// `testing::loom`
+#[cfg(all(loom, feature = "loom"))]
+use loom::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(all(loom, feature = "loom"))]
+use loom::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(not(all(loom, feature = "loom")))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::PoisonError;
+#[cfg(not(all(loom, feature = "loom")))]
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+// Default fanout, wider than `M` so a descent takes fewer latches
+const FANOUT: usize = 16_usize;
+
+type Leaf<K, V> = Vec<(K, V)>;
+// Children by their first key, routed as in `BalancedTree`
+type Inner<K, V> = Vec<(K, Link<K, V>)>;
+
+#[derive(Debug)]
+enum Link<K, V> {
+    Leaf(Arc<RwLock<Leaf<K, V>>>),
+    Inner(Arc<RwLock<Inner<K, V>>>),
+}
+
+impl<K, V> Clone for Link<K, V> {
+    fn clone(&self) -> Self {
+        match self {
+            Link::Leaf(leaf) => Link::Leaf(Arc::clone(leaf)),
+            Link::Inner(node) => Link::Inner(Arc::clone(node)),
+        }
+    }
+}
+
+/// A B-tree shared by threads, every node behind its own `RwLock`.
+///
+/// Descents latch hand over hand (crabbing): a node is latched before the
+/// latch of its parent is released, so readers and writers in different
+/// subtrees never wait for each other. A `get` takes read latches only.
+/// An `insert` takes read latches down to its leaf and a write latch on
+/// the leaf alone, and only when the leaf is full descends again with
+/// write latches, splitting every full node on the way down, so no split
+/// ever climbs back up and at most two latches are held at once.
+///
+/// `remove` takes a key out of its leaf and never merges nodes, leaves
+/// may run empty. Values are cloned out, no reference outlives a latch.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use treers::concurrent::ConcurrentTree;
+///
+/// let tree = ConcurrentTree::new();
+/// thread::scope(|s| {
+///     for t in 0..4_u32 {
+///         let tree = &tree;
+///         s.spawn(move || {
+///             for i in 0..1000 {
+///                 tree.insert(i * 4 + t, t);
+///             }
+///         });
+///     }
+/// });
+/// assert_eq!(tree.size(), 4000_usize);
+/// assert_eq!(tree.get(&2001), Some(1));
+/// assert_eq!(tree.remove(&2001), Some(1));
+/// assert!(!tree.contains(&2001));
+/// ```
+#[derive(Debug)]
+pub struct ConcurrentTree<K, V> {
+    // Latched above the root, so a root split swaps it under no descent
+    root: RwLock<Link<K, V>>,
+    size: AtomicUsize,
+    fanout: usize,
+}
+
+impl<K: Ord + Clone, V: Clone> ConcurrentTree<K, V> {
+    /// Inits an empty tree of 16 entries per node.
+    pub fn new() -> Self {
+        Self::with_fanout(FANOUT)
+    }
+
+    /// Inits an empty tree of `fanout` entries per node, at least `M`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::concurrent::ConcurrentTree;
+    ///
+    /// let tree: ConcurrentTree<u32, u32> = ConcurrentTree::with_fanout(64);
+    /// assert_eq!(tree.fanout(), 64_usize);
+    /// assert_eq!(ConcurrentTree::<u32, u32>::with_fanout(1).fanout(), 4_usize);
+    /// ```
+    pub fn with_fanout(fanout: usize) -> Self {
+        Self {
+            root: RwLock::new(Link::Leaf(Arc::default())),
+            size: AtomicUsize::new(0_usize),
+            fanout: fanout.max(M),
+        }
+    }
+
+    /// Returns the most entries a node holds before it splits.
+    pub const fn fanout(&self) -> usize {
+        self.fanout
+    }
+
+    /// Returns a count of entries, racing with writers of other threads.
+    pub fn size(&self) -> usize {
+        self.size.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the tree holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.size().eq(&0_usize)
+    }
+
+    /// Returns a clone of the value of `key`.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let root = read(&self.root);
+        let link = root.clone();
+        search(root, &link, key)
+    }
+
+    /// Returns `true` if `key` is in the tree.
+    pub fn contains(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Puts `value` under `key`, returning the value it replaced.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let fanout = self.fanout;
+        let root = read(&self.root);
+        let link = root.clone();
+        let put = with_leaf(root, &link, key, |leaf, key| {
+            match leaf.binary_search_by(|(k, _)| k.cmp(&key)) {
+                Ok(i) => Ok(Some(mem::replace(&mut leaf[i].1, value))),
+                Err(i) if leaf.len().lt(&fanout) => {
+                    leaf.insert(i, (key, value));
+                    Ok(None)
+                }
+                Err(_) => Err((key, value)),
+            }
+        });
+        let old = match put {
+            Ok(old) => old,
+            // the leaf is full, split it on a descent of write latches
+            Err((key, value)) => {
+                let root = self.split_root();
+                let link = root.clone();
+                insert(root, &link, key, value, fanout)
+            }
+        };
+        if old.is_none() {
+            self.size.fetch_add(1_usize, Ordering::Relaxed);
+        }
+        old
+    }
+
+    /// Removes `key`, returning its value.
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let root = read(&self.root);
+        let link = root.clone();
+        let old = with_leaf(root, &link, key, |leaf, key| {
+            let i = leaf.binary_search_by(|(k, _)| k.cmp(key)).ok()?;
+            Some(leaf.remove(i).1)
+        });
+        if old.is_some() {
+            self.size.fetch_sub(1_usize, Ordering::Relaxed);
+        }
+        old
+    }
+
+    /// Returns all entries sorted by key.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::concurrent::ConcurrentTree;
+    ///
+    /// let tree = ConcurrentTree::new();
+    /// for k in [3, 1, 2] {
+    ///     tree.insert(k, k * 10);
+    /// }
+    /// assert_eq!(tree.into_sorted_vec(), vec![(1, 10), (2, 20), (3, 30)]);
+    /// ```
+    pub fn into_sorted_vec(self) -> Vec<(K, V)> {
+        let mut vec = Vec::with_capacity(self.size());
+        let root = self
+            .root
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        into_in_order(root, &mut vec);
+        vec
+    }
+
+    // Latches the root pointer for a descent of write latches, growing the
+    // tree first if the root is full; every change of the root comes from
+    // a descent through the pointer, so the root keeps its room
+    fn split_root(&self) -> RwLockWriteGuard<'_, Link<K, V>> {
+        let mut root = write(&self.root);
+        if let Some(sibling) = split_full(&root, self.fanout) {
+            let first = first_key(&root);
+            let old = root.clone();
+            *root = Link::Inner(Arc::new(RwLock::new(vec![(first, old), sibling])));
+        }
+        root
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Default for ConcurrentTree<K, V> {
+    /// Creates an empty `ConcurrentTree<K, V>`.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A panic of `Ord` or `Clone` is raised before any node changes, nodes
+// stay whole, so latches a panic poisoned are taken anyway
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn child_index<K: Ord, V>(node: &[(K, Link<K, V>)], key: &K) -> usize {
+    let mut j = 0_usize;
+    while (j + 1_usize).lt(&node.len()) && !key.lt(&node[j + 1].0) {
+        j += 1;
+    }
+    j
+}
+
+// Latches `link`, then releases `above`, the latch of its parent
+fn search<K: Ord, V: Clone, G>(above: G, link: &Link<K, V>, key: &K) -> Option<V> {
+    match link {
+        Link::Leaf(leaf) => {
+            let leaf = read(leaf);
+            drop(above);
+            let i = leaf.binary_search_by(|(k, _)| k.cmp(key)).ok()?;
+            Some(leaf[i].1.clone())
+        }
+        Link::Inner(node) => {
+            let node = read(node);
+            drop(above);
+            let child = node[child_index(&node, key)].1.clone();
+            search(node, &child, key)
+        }
+    }
+}
+
+// Read latches down to the leaf of `key`, a write latch on the leaf
+fn with_leaf<K, V, T, G, R, F>(above: G, link: &Link<K, V>, key: T, f: F) -> R
+where
+    K: Ord,
+    T: Borrow<K>,
+    F: FnOnce(&mut Leaf<K, V>, T) -> R,
+{
+    match link {
+        Link::Leaf(leaf) => {
+            let mut leaf = write(leaf);
+            drop(above);
+            f(&mut leaf, key)
+        }
+        Link::Inner(node) => {
+            let node = read(node);
+            drop(above);
+            let child = node[child_index(&node, key.borrow())].1.clone();
+            with_leaf(node, &child, key, f)
+        }
+    }
+}
+
+// Write latches down to the leaf of `key`, splitting full children before
+// stepping into them, so the latched node always has room for a sibling
+fn insert<K: Ord + Clone, V, G>(
+    above: G,
+    link: &Link<K, V>,
+    key: K,
+    value: V,
+    fanout: usize,
+) -> Option<V> {
+    match link {
+        Link::Leaf(leaf) => {
+            let mut leaf = write(leaf);
+            drop(above);
+            match leaf.binary_search_by(|(k, _)| k.cmp(&key)) {
+                Ok(i) => Some(mem::replace(&mut leaf[i].1, value)),
+                Err(i) => {
+                    leaf.insert(i, (key, value));
+                    None
+                }
+            }
+        }
+        Link::Inner(node) => {
+            let mut node = write(node);
+            drop(above);
+            let mut j = child_index(&node, &key);
+            if let Some(sibling) = split_full(&node[j].1, fanout) {
+                node.insert(j + 1_usize, sibling);
+                j = child_index(&node, &key);
+            }
+            let child = node[j].1.clone();
+            insert(node, &child, key, value, fanout)
+        }
+    }
+}
+
+// Moves the upper half of a full node into a new sibling, returned with
+// its first key; the caller holds the write latch of the parent
+fn split_full<K: Clone, V>(link: &Link<K, V>, fanout: usize) -> Option<(K, Link<K, V>)> {
+    match link {
+        Link::Leaf(leaf) => {
+            let mut leaf = write(leaf);
+            if leaf.len().lt(&fanout) {
+                return None;
+            }
+            let half = leaf.len() / 2_usize;
+            let first = leaf[half].0.clone();
+            let upper = leaf.split_off(half);
+            Some((first, Link::Leaf(Arc::new(RwLock::new(upper)))))
+        }
+        Link::Inner(node) => {
+            let mut node = write(node);
+            if node.len().lt(&fanout) {
+                return None;
+            }
+            let half = node.len() / 2_usize;
+            let first = node[half].0.clone();
+            let upper = node.split_off(half);
+            Some((first, Link::Inner(Arc::new(RwLock::new(upper)))))
+        }
+    }
+}
+
+// First key under a full node, so never of an empty leaf
+fn first_key<K: Clone, V>(link: &Link<K, V>) -> K {
+    match link {
+        Link::Leaf(leaf) => read(leaf)[0].0.clone(),
+        Link::Inner(node) => read(node)[0].0.clone(),
+    }
+}
+
+fn into_in_order<K, V>(link: Link<K, V>, vec: &mut Vec<(K, V)>) {
+    match link {
+        Link::Leaf(leaf) => vec.extend(take(leaf)),
+        Link::Inner(node) => {
+            for (_, child) in take(node) {
+                into_in_order(child, vec);
+            }
+        }
+    }
+}
+
+// Moves out the contents of a node of an owned tree, no other `Arc` of it
+// is left once every descent is over
+fn take<T: Default>(node: Arc<RwLock<T>>) -> T {
+    match Arc::try_unwrap(node) {
+        Ok(lock) => lock.into_inner().unwrap_or_else(PoisonError::into_inner),
+        Err(node) => mem::take(&mut *write(&node)),
+    }
+}
+
+#[cfg(all(test, not(all(loom, feature = "loom"))))]
+mod tests {
+    use super::ConcurrentTree;
+    use crate::bench_support::shuffled_keys;
+    use std::thread;
+
+    #[test]
+    fn test_threads_against_model() {
+        let tree = ConcurrentTree::with_fanout(4);
+        let keys = shuffled_keys(8000, 16);
+        thread::scope(|s| {
+            for part in keys.chunks(1000) {
+                let tree = &tree;
+                s.spawn(move || {
+                    for &k in part {
+                        assert_eq!(tree.insert(k, k), None);
+                        assert_eq!(tree.get(&k), Some(k));
+                    }
+                });
+            }
+            // readers racing the writers only ever see whole values
+            for part in keys.chunks(4000) {
+                let tree = &tree;
+                s.spawn(move || {
+                    for &k in part {
+                        assert!(tree.get(&k).is_none_or(|v| v.eq(&k)));
+                    }
+                });
+            }
+        });
+        assert_eq!(tree.size(), keys.len());
+        thread::scope(|s| {
+            for part in keys.chunks(1000) {
+                let tree = &tree;
+                s.spawn(move || {
+                    for &k in part {
+                        if k % 2_u64 == 0_u64 {
+                            assert_eq!(tree.remove(&k), Some(k));
+                        } else {
+                            assert_eq!(tree.insert(k, k + 1_u64), Some(k));
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(tree.remove(&0), None);
+        let mut expected: Vec<(u64, u64)> = keys
+            .iter()
+            .filter(|k| *k % 2_u64 == 1_u64)
+            .map(|&k| (k, k + 1_u64))
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(tree.size(), expected.len());
+        assert_eq!(tree.into_sorted_vec(), expected);
+    }
+}
@@ -0,0 +1,292 @@
+use crate::{OrderedMap, Traversals, TreeTraversal};
+use std::cmp::Ordering;
+use std::fmt::{Debug, Write};
+
+// Most differing keys listed, the rest are only counted
+const MAX_ROWS: usize = 20_usize;
+
+/// Renders entries of two maps side by side where they differ, `None`
+/// when both hold the same entries.
+///
+/// Keys missing on one side and keys with different values get a row
+/// each, in key order, so a failing test shows what differs instead of
+/// two derived `Debug` dumps.
+///
+/// # Examples
+///
+/// ```
+/// use treers::bst::BST;
+/// use treers::btree::BalancedTree;
+/// use treers::diff::diff_format;
+/// use treers::Map;
+///
+/// let mut bst = BST::new();
+/// let mut btree = BalancedTree::new();
+/// for k in 1..=4 {
+///     bst.put(k, k * 10);
+///     btree.put(k, k * 10);
+/// }
+/// assert_eq!(diff_format(&bst, &btree), None);
+///
+/// bst.put(5, 50);
+/// btree.put(2, 21);
+/// let diff = diff_format(&bst, &btree).unwrap();
+/// assert_eq!(
+///     diff,
+///     "trees differ at 2 of 5 keys, sizes 5 and 4\n\
+///      key | left | right\n\
+///      2   | 20   | 21\n\
+///      5   | 50   | -\n"
+/// );
+/// ```
+pub fn diff_format<K, V, A, B>(left: &A, right: &B) -> Option<String>
+where
+    K: Ord + Debug,
+    V: PartialEq + Debug,
+    A: OrderedMap<K, V>,
+    B: OrderedMap<K, V>,
+{
+    let mut rows = Vec::new();
+    let mut keys = 0_usize;
+    let (mut l, mut r) = (left.range(..).peekable(), right.range(..).peekable());
+    loop {
+        let order = match (l.peek(), r.peek()) {
+            (Some((a, _)), Some((b, _))) => a.cmp(b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+        keys += 1_usize;
+        match order {
+            Ordering::Less => {
+                if let Some((k, v)) = l.next() {
+                    rows.push([debug(k), debug(v), String::from("-")]);
+                }
+            }
+            Ordering::Greater => {
+                if let Some((k, v)) = r.next() {
+                    rows.push([debug(k), String::from("-"), debug(v)]);
+                }
+            }
+            Ordering::Equal => {
+                if let (Some((k, a)), Some((_, b))) = (l.next(), r.next()) {
+                    if a.ne(b) {
+                        rows.push([debug(k), debug(a), debug(b)]);
+                    }
+                }
+            }
+        }
+    }
+    if rows.is_empty() {
+        return None;
+    }
+    let mut out = format!(
+        "trees differ at {} of {} keys, sizes {} and {}\n",
+        rows.len(),
+        keys,
+        left.size(),
+        right.size()
+    );
+    let more = rows.len().saturating_sub(MAX_ROWS);
+    rows.truncate(MAX_ROWS);
+    render(&mut out, "key", &rows);
+    if more.gt(&0_usize) {
+        let _ = writeln!(out, "... and {} more", more);
+    }
+    Some(out)
+}
+
+/// Same as `diff_format`, and for trees holding the same entries tells
+/// where their shapes diverge, `None` when they are the same trees.
+///
+/// Keys in pre-order fix the shape of a search tree, so the first key
+/// out of place in a pre-order walk is reported, with both heights.
+///
+/// # Examples
+///
+/// ```
+/// use treers::bst::BST;
+/// use treers::diff::diff_shapes;
+/// use treers::Map;
+///
+/// let mut a = BST::new();
+/// let mut b = BST::new();
+/// for (x, y) in [(2, 1), (1, 2), (3, 3)] {
+///     a.put(x, ());
+///     b.put(y, ());
+/// }
+/// assert_eq!(
+///     diff_shapes(&a, &b).unwrap(),
+///     "trees differ in shape, heights 1 and 2\n\
+///      pre-order | left | right\n\
+///      0         | 2    | 1\n"
+/// );
+/// ```
+pub fn diff_shapes<K, V, A, B>(left: &A, right: &B) -> Option<String>
+where
+    K: Ord + Debug,
+    V: PartialEq + Debug,
+    A: TreeTraversal<K, V>,
+    B: TreeTraversal<K, V>,
+{
+    if let Some(diff) = diff_format(left, right) {
+        return Some(diff);
+    }
+    let (l, r) = (
+        left.traverse(&Traversals::PreOrder),
+        right.traverse(&Traversals::PreOrder),
+    );
+    let (i, (a, b)) = l.zip(r).enumerate().find(|(_, (a, b))| a.0.ne(b.0))?;
+    let mut out = format!(
+        "trees differ in shape, heights {} and {}\n",
+        debug(&left.height().unwrap_or_default()),
+        debug(&right.height().unwrap_or_default())
+    );
+    render(
+        &mut out,
+        "pre-order",
+        &[[i.to_string(), debug(a.0), debug(b.0)]],
+    );
+    Some(out)
+}
+
+/// Asserts that two maps hold the same entries, showing `diff_format` of
+/// them on failure.
+///
+/// # Examples
+///
+/// ```should_panic
+/// use treers::assert_trees_equal;
+/// use treers::bst::BST;
+/// use treers::rbtree::RedBlackTree;
+/// use treers::Map;
+///
+/// let mut bst = BST::new();
+/// let mut rbtree = RedBlackTree::new();
+/// bst.put(1, "one");
+/// rbtree.put(1, "uno");
+/// // panics with `1   | "one" | "uno"` in the message
+/// assert_trees_equal!(bst, rbtree);
+/// ```
+#[macro_export]
+macro_rules! assert_trees_equal {
+    ($left:expr, $right:expr $(,)?) => {
+        if let Some(diff) = $crate::diff::diff_format(&$left, &$right) {
+            panic!("assertion `left == right` failed\n{}", diff);
+        }
+    };
+}
+
+/// Asserts that two trees hold the same entries in the same shape,
+/// showing `diff_shapes` of them on failure.
+#[macro_export]
+macro_rules! assert_same_tree {
+    ($left:expr, $right:expr $(,)?) => {
+        if let Some(diff) = $crate::diff::diff_shapes(&$left, &$right) {
+            panic!("assertion `left == right` failed\n{}", diff);
+        }
+    };
+}
+
+fn debug<T: Debug>(value: &T) -> String {
+    format!("{:?}", value)
+}
+
+// Writes a header and `rows` in aligned columns, `first` names the first
+fn render(out: &mut String, first: &str, rows: &[[String; 3]]) {
+    let header = [first, "left", "right"];
+    let mut widths = header.map(str::len);
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.len());
+        }
+    }
+    let mut line = |cells: [&str; 3]| {
+        let _ = writeln!(
+            out,
+            "{:<a$} | {:<b$} | {}",
+            cells[0],
+            cells[1],
+            cells[2],
+            a = widths[0],
+            b = widths[1]
+        );
+    };
+    line(header);
+    for row in rows {
+        line([row[0].as_str(), row[1].as_str(), row[2].as_str()]);
+    }
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::{diff_format, diff_shapes};
+    use crate::bst::BST;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use crate::Map;
+
+    #[test]
+    fn test_diff_format() {
+        let mut bst = BST::new();
+        let mut btree = BalancedTree::new();
+        for k in 0_u32..100 {
+            bst.put(k, k);
+            btree.put(k + 50_u32, k + 50_u32);
+        }
+        assert_trees_equal!(bst, bst);
+        let diff = diff_format(&bst, &btree).unwrap();
+        let lines: Vec<&str> = diff.lines().collect();
+        assert_eq!(
+            lines[0],
+            "trees differ at 100 of 150 keys, sizes 100 and 100"
+        );
+        assert_eq!(lines[1], "key | left | right");
+        assert_eq!(lines[2], "0   | 0    | -");
+        assert_eq!(lines.last(), Some(&"... and 80 more"));
+        assert_eq!(lines.len(), 23_usize);
+
+        // right side missing everything
+        let diff = diff_format(&RedBlackTree::new(), &btree).unwrap();
+        assert!(diff.starts_with("trees differ at 100 of 100 keys, sizes 0 and 100\n"));
+        assert!(diff.contains("\n50  | -    | 50\n"));
+    }
+
+    #[test]
+    fn test_diff_shapes() {
+        let mut bst = BST::new();
+        let mut rbtree = RedBlackTree::new();
+        for k in 0_u32..8 {
+            bst.put(k, ());
+            rbtree.put(k, ());
+        }
+        let mut same = BST::new();
+        for k in 0_u32..8 {
+            same.put(k, ());
+        }
+        assert_same_tree!(bst, same);
+        assert_eq!(
+            diff_shapes(&bst, &rbtree).unwrap().lines().next(),
+            Some("trees differ in shape, heights 7 and 3")
+        );
+        assert_eq!(diff_format(&bst, &rbtree), None);
+        let mut other = BST::new();
+        other.put(0_u32, ());
+        assert!(diff_shapes(&bst, &other)
+            .unwrap()
+            .starts_with("trees differ at 7 of 8 keys"));
+    }
+
+    #[test]
+    #[should_panic(expected = "2   | 2    | 3")]
+    fn test_assert_trees_equal_fails() {
+        let mut bst = BST::new();
+        let mut btree = BalancedTree::new();
+        for k in 0_u32..4 {
+            bst.put(k, k);
+            btree.put(k, k);
+        }
+        btree.put(2_u32, 3_u32);
+        assert_trees_equal!(bst, btree);
+    }
+}
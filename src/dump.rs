@@ -0,0 +1,43 @@
+use std::fmt::Display;
+use std::io::{Result, Write};
+
+/// Writes an entry as a line of its key and value, separated by a tab, a
+/// `fmt` of `write_in_order`.
+///
+/// Keys and values are written by `Display` as they are, tabs or newlines
+/// in them are not escaped.
+///
+/// # Examples
+///
+/// ```
+/// use treers::dump;
+///
+/// let mut out = Vec::new();
+/// dump::tsv(&mut out, &"a", &1).unwrap();
+/// assert_eq!(out, b"a\t1\n");
+/// ```
+pub fn tsv<W: Write, K: Display, V: Display>(writer: &mut W, key: &K, value: &V) -> Result<()> {
+    writeln!(writer, "{}\t{}", key, value)
+}
+
+/// Writes an entry as a `[key, value]` JSON line, as `io::write_jsonl` does.
+///
+/// # Examples
+///
+/// ```
+/// use treers::dump;
+///
+/// let mut out = Vec::new();
+/// dump::jsonl(&mut out, &"a", &[1, 2]).unwrap();
+/// assert_eq!(out, b"[\"a\",[1,2]]\n");
+/// ```
+#[cfg(feature = "io")]
+pub fn jsonl<W, K, V>(writer: &mut W, key: &K, value: &V) -> Result<()>
+where
+    W: Write,
+    K: serde::Serialize,
+    V: serde::Serialize,
+{
+    serde_json::to_writer(&mut *writer, &(key, value))?;
+    writer.write_all(b"\n")
+}
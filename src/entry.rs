@@ -0,0 +1,192 @@
+use std::fmt;
+
+// Puts the entry of a vacant key, returning its value; each tree keeps
+// what it needs of the descent
+type Fill<'a, K, V> = Box<dyn FnOnce(K, V) -> &'a mut V + 'a>;
+
+/// A key of a map and its place in it, found by one search, as `entry`
+/// of `BST` and `RedBlackTree` returns it.
+///
+/// # Examples
+///
+/// ```
+/// use treers::bst::BST;
+/// use treers::Map;
+///
+/// let mut counts: BST<char, u32> = BST::new();
+/// for c in "abracadabra".chars() {
+///     *counts.entry(c).or_insert(0) += 1;
+/// }
+/// assert_eq!(counts.get(&'a'), Some(&5));
+/// assert_eq!(counts.get(&'d'), Some(&1));
+///
+/// counts.entry('z').and_modify(|n| *n += 1).or_default();
+/// counts.entry('a').and_modify(|n| *n += 1).or_default();
+/// assert_eq!((counts.get(&'z'), counts.get(&'a')), (Some(&0), Some(&6)));
+/// ```
+#[derive(Debug)]
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+impl<'a, K, V> Entry<'a, K, V> {
+    /// Returns the key of the entry.
+    pub const fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(e) => e.key(),
+            Entry::Vacant(e) => e.key(),
+        }
+    }
+
+    /// Returns the value of the entry, putting `default` first if it is
+    /// vacant.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Returns the value of the entry, putting the result of `f` first if
+    /// it is vacant. `f` is only called for a vacant entry.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(f()),
+        }
+    }
+
+    /// Returns the value of the entry, putting `V::default()` first if it
+    /// is vacant.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Calls `f` with the value of an occupied entry, a vacant one is
+    /// returned as it is.
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut e) => {
+                f(e.get_mut());
+                Entry::Occupied(e)
+            }
+            Entry::Vacant(e) => Entry::Vacant(e),
+        }
+    }
+}
+
+/// An entry of a key in its map.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, K, V> {
+    key: K,
+    value: &'a mut V,
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    pub(crate) const fn new(key: K, value: &'a mut V) -> Self {
+        Self { key, value }
+    }
+
+    /// Returns the key of the entry, the one given to `entry`.
+    pub const fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns a reference to the value.
+    pub const fn get(&self) -> &V {
+        self.value
+    }
+
+    /// Returns a mutable reference to the value, for as long as the entry.
+    pub const fn get_mut(&mut self) -> &mut V {
+        self.value
+    }
+
+    /// Returns a mutable reference to the value, for as long as the map
+    /// is borrowed.
+    pub fn into_mut(self) -> &'a mut V {
+        self.value
+    }
+
+    /// Replaces the value, returning the old one.
+    pub const fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.value, value)
+    }
+}
+
+/// A place of a key missing in its map.
+pub struct VacantEntry<'a, K, V> {
+    key: K,
+    fill: Fill<'a, K, V>,
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V> {
+    pub(crate) fn new(key: K, fill: Fill<'a, K, V>) -> Self {
+        Self { key, fill }
+    }
+
+    /// Returns the key of the entry.
+    pub const fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns the key, leaving the map as it is.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Puts `value` under the key, returning a mutable reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        (self.fill)(self.key, value)
+    }
+}
+
+impl<K: fmt::Debug, V> fmt::Debug for VacantEntry<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VacantEntry")
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree"))]
+mod tests {
+    use super::Entry;
+    use crate::bench_support::shuffled_keys;
+    use crate::bst::BST;
+    use crate::rbtree::RedBlackTree;
+    use crate::{Map, OrderedMap, SedgewickMap};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_counts_against_model() {
+        let mut bst = BST::new();
+        let mut rbtree = RedBlackTree::new();
+        let mut model = BTreeMap::new();
+        for k in shuffled_keys(3000, 17) {
+            *bst.entry(k % 500).or_insert(0_u64) += k;
+            *rbtree.entry(k % 500).or_default() += k;
+            *model.entry(k % 500).or_insert(0_u64) += k;
+        }
+        assert!(bst.range(..).eq(model.iter()));
+        assert!(rbtree.range(..).eq(model.iter()));
+        // sizes bumped on the path of every vacant entry filled
+        for (i, k) in model.keys().enumerate() {
+            assert_eq!((bst.rank(k), bst.select(i)), (i, Some(k)));
+        }
+
+        match bst.entry(7) {
+            Entry::Occupied(mut e) => {
+                let old = e.insert(1);
+                assert_eq!((e.key(), e.get(), Some(&old)), (&7, &1, model.get(&7)));
+            }
+            Entry::Vacant(_) => panic!("7 is in the tree"),
+        }
+        match rbtree.entry(501) {
+            Entry::Vacant(e) => assert_eq!(e.into_key(), 501),
+            Entry::Occupied(_) => panic!("501 is not in the tree"),
+        }
+        assert_eq!((bst.size(), rbtree.size()), (model.len(), model.len()));
+    }
+}
@@ -0,0 +1,296 @@
+use std::fmt;
+#[cfg(feature = "rbtree")]
+use std::ops::Range;
+
+/// Errors returned by fallible map operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error<K> {
+    /// A key met twice where keys have to be unique.
+    DuplicateKey(DuplicateKeyError<K>),
+    /// A map was modified while a cursor over it was still in use.
+    ConcurrentModification,
+    /// A batch deleted a key the map doesn't hold.
+    MissingKey(K),
+}
+
+impl<K: fmt::Debug> fmt::Display for Error<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::DuplicateKey(e) => e.fmt(f),
+            Error::ConcurrentModification => write!(f, "map modified during iteration"),
+            Error::MissingKey(k) => write!(f, "missing key: {:?}", k),
+        }
+    }
+}
+
+impl<K: fmt::Debug> std::error::Error for Error<K> {}
+
+impl<K> From<DuplicateKeyError<K>> for Error<K> {
+    fn from(e: DuplicateKeyError<K>) -> Self {
+        Error::DuplicateKey(e)
+    }
+}
+
+/// A key met twice while building a map with `try_from_iter`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use treers::bst::BST;
+/// use treers::{Error, Map};
+///
+/// let err = BST::try_from_iter(vec![(1, 'a'), (2, 'b'), (1, 'c')]).unwrap_err();
+/// assert_eq!(err.key(), &1);
+/// assert_eq!(err.to_string(), "duplicate key: 1");
+///
+/// let err: Error<i32> = err.into();
+/// assert_eq!(err.to_string(), "duplicate key: 1");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKeyError<K> {
+    key: K,
+}
+
+impl<K> DuplicateKeyError<K> {
+    /// Wraps the duplicated key.
+    pub const fn new(key: K) -> Self {
+        Self { key }
+    }
+
+    /// Returns a reference to the duplicated key.
+    pub const fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns the duplicated key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+}
+
+impl<K: fmt::Debug> fmt::Display for DuplicateKeyError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "duplicate key: {:?}", self.key)
+    }
+}
+
+impl<K: fmt::Debug> std::error::Error for DuplicateKeyError<K> {}
+
+/// Keys of a map breaking the order their `Ord` promised, found by
+/// `validate_ordering`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OrderingError<K> {
+    /// Keys next to each other in the tree, the first not less than the
+    /// second, or the two comparing alike both ways.
+    OutOfOrder(K, K),
+    /// A key in the tree a search for it misses.
+    Unreachable(K),
+}
+
+impl<K: fmt::Debug> fmt::Display for OrderingError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderingError::OutOfOrder(a, b) => write!(f, "keys out of order: {:?} then {:?}", a, b),
+            OrderingError::Unreachable(k) => write!(f, "key unreachable by search: {:?}", k),
+        }
+    }
+}
+
+impl<K: fmt::Debug> std::error::Error for OrderingError<K> {}
+
+/// Text that is not a tree in the parenthesized notation of `to_paren_string`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use treers::bst::BST;
+///
+/// let err = "b:2(a:1".parse::<BST<char, u32>>().unwrap_err();
+/// assert_eq!(err.offset(), 7_usize);
+/// assert_eq!(err.to_string(), "invalid tree at byte 7, expected `,`");
+/// ```
+#[cfg(any(feature = "bst", feature = "rbtree"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTreeError {
+    offset: usize,
+    expected: &'static str,
+}
+
+#[cfg(any(feature = "bst", feature = "rbtree"))]
+impl ParseTreeError {
+    pub(crate) const fn new(offset: usize, expected: &'static str) -> Self {
+        Self { offset, expected }
+    }
+
+    /// Returns the byte offset in the text where parsing failed.
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+#[cfg(any(feature = "bst", feature = "rbtree"))]
+impl fmt::Display for ParseTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid tree at byte {}, expected {}",
+            self.offset, self.expected
+        )
+    }
+}
+
+#[cfg(any(feature = "bst", feature = "rbtree"))]
+impl std::error::Error for ParseTreeError {}
+
+/// Text that is not a `Script` of recorded map calls.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use treers::record::Script;
+///
+/// let err = "put\t1\ta\nput\t2".parse::<Script<u32, char>>().unwrap_err();
+/// assert_eq!(err.line(), 2_usize);
+/// assert_eq!(err.to_string(), "invalid script at line 2, expected a value");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseScriptError {
+    line: usize,
+    expected: &'static str,
+}
+
+impl ParseScriptError {
+    pub(crate) const fn new(line: usize, expected: &'static str) -> Self {
+        Self { line, expected }
+    }
+
+    /// Returns the line of the text where parsing failed, counted from 1.
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for ParseScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid script at line {}, expected {}",
+            self.line, self.expected
+        )
+    }
+}
+
+impl std::error::Error for ParseScriptError {}
+
+/// Text that is not a CIDR block like `10.0.0.0/8`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use treers::ip::Cidr;
+///
+/// let err = "10.0.0.0/33".parse::<Cidr>().unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "invalid CIDR block, expected a prefix length within the address"
+/// );
+/// ```
+#[cfg(feature = "rbtree")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCidrError {
+    expected: &'static str,
+}
+
+#[cfg(feature = "rbtree")]
+impl ParseCidrError {
+    pub(crate) const fn new(expected: &'static str) -> Self {
+        Self { expected }
+    }
+}
+
+#[cfg(feature = "rbtree")]
+impl fmt::Display for ParseCidrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid CIDR block, expected {}", self.expected)
+    }
+}
+
+#[cfg(feature = "rbtree")]
+impl std::error::Error for ParseCidrError {}
+
+/// A booking of `IntervalMap::book` overlapping one already there.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use treers::interval::IntervalMap;
+///
+/// let mut rooms = IntervalMap::new();
+/// rooms.book(9_u32..11, "standup").unwrap();
+/// let err = rooms.book(10..12, "review").unwrap_err();
+/// assert_eq!(err.booked(), &(9..11));
+/// assert_eq!(err.to_string(), "range overlaps booking 9..11");
+/// ```
+#[cfg(feature = "rbtree")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict<K> {
+    booked: Range<K>,
+}
+
+#[cfg(feature = "rbtree")]
+impl<K> Conflict<K> {
+    pub(crate) const fn new(booked: Range<K>) -> Self {
+        Self { booked }
+    }
+
+    /// Returns the range of the booking in the way.
+    pub const fn booked(&self) -> &Range<K> {
+        &self.booked
+    }
+}
+
+#[cfg(feature = "rbtree")]
+impl<K: fmt::Debug> fmt::Display for Conflict<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "range overlaps booking {:?}", self.booked)
+    }
+}
+
+#[cfg(feature = "rbtree")]
+impl<K: fmt::Debug> std::error::Error for Conflict<K> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{DuplicateKeyError, Error, OrderingError};
+
+    #[test]
+    fn test_display() {
+        let err: Error<u32> = DuplicateKeyError::new(7).into();
+        assert_eq!(err.to_string(), "duplicate key: 7");
+        assert_eq!(
+            Error::<u32>::ConcurrentModification.to_string(),
+            "map modified during iteration"
+        );
+        assert_eq!(Error::MissingKey("a").to_string(), "missing key: \"a\"");
+        assert_eq!(
+            OrderingError::OutOfOrder(2, 1).to_string(),
+            "keys out of order: 2 then 1"
+        );
+        assert_eq!(
+            OrderingError::Unreachable(3).to_string(),
+            "key unreachable by search: 3"
+        );
+    }
+}
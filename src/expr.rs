@@ -0,0 +1,229 @@
+use crate::node::{self, BinaryTreeNode};
+use crate::Traversals;
+use std::fmt;
+
+/// An arithmetic operator of an `Expr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Op {
+    /// Returns the symbol of the operator.
+    pub const fn symbol(self) -> char {
+        match self {
+            Op::Add => '+',
+            Op::Sub => '-',
+            Op::Mul => '*',
+            Op::Div => '/',
+        }
+    }
+
+    /// Applies the operator, `None` on overflow or division by zero.
+    pub const fn apply(self, a: i64, b: i64) -> Option<i64> {
+        match self {
+            Op::Add => a.checked_add(b),
+            Op::Sub => a.checked_sub(b),
+            Op::Mul => a.checked_mul(b),
+            Op::Div => a.checked_div(b),
+        }
+    }
+
+    // Binding strength, multiplication before addition
+    const fn precedence(self) -> u8 {
+        match self {
+            Op::Add | Op::Sub => 1_u8,
+            Op::Mul | Op::Div => 2_u8,
+        }
+    }
+}
+
+/// A node of an `Expr`, an operand or an operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Token {
+    Num(i64),
+    Op(Op),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Num(n) => write!(f, "{}", n),
+            Token::Op(op) => write!(f, "{}", op.symbol()),
+        }
+    }
+}
+
+/// An arithmetic expression tree, operands in leaves and operators in
+/// internal nodes.
+///
+/// It is a `BinaryTreeNode` keyed by tokens, so evaluation and postfix
+/// printing are post-order walks of `node::traverse`, as with any other
+/// tree of the crate.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use treers::expr::{Expr, Op};
+///
+/// // (1 + 2) * (7 - 3)
+/// let expr = Expr::op(
+///     Op::Mul,
+///     Expr::op(Op::Add, Expr::num(1), Expr::num(2)),
+///     Expr::op(Op::Sub, Expr::num(7), Expr::num(3)),
+/// );
+/// assert_eq!(expr.eval(), Some(12));
+/// assert_eq!(expr.to_string(), "(1 + 2) * (7 - 3)");
+/// assert_eq!(expr.to_postfix(), "1 2 + 7 3 - *");
+///
+/// let by_zero = Expr::op(Op::Div, Expr::num(1), Expr::num(0));
+/// assert_eq!(by_zero.eval(), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr {
+    token: Token,
+    // Operands of an operator, `None` for a number
+    operands: Option<Box<(Expr, Expr)>>,
+}
+
+impl Expr {
+    /// Inits a leaf holding the number `n`.
+    pub const fn num(n: i64) -> Self {
+        Expr {
+            token: Token::Num(n),
+            operands: None,
+        }
+    }
+
+    /// Inits an operator node applying `op` to `left` and `right`.
+    pub fn op(op: Op, left: Expr, right: Expr) -> Self {
+        Expr {
+            token: Token::Op(op),
+            operands: Some(Box::new((left, right))),
+        }
+    }
+
+    /// Returns the value of the expression, `None` on overflow or
+    /// division by zero.
+    pub fn eval(&self) -> Option<i64> {
+        let mut stack = Vec::new();
+        for (token, _) in node::traverse(self, &Traversals::PostOrder) {
+            match *token {
+                Token::Num(n) => stack.push(n),
+                Token::Op(op) => {
+                    let b = stack.pop()?;
+                    let a = stack.pop()?;
+                    stack.push(op.apply(a, b)?);
+                }
+            }
+        }
+        stack.pop()
+    }
+
+    /// Returns the expression in postfix notation, tokens separated by
+    /// spaces.
+    pub fn to_postfix(&self) -> String {
+        node::traverse(self, &Traversals::PostOrder)
+            .map(|(token, _)| token.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Returns the expression in prefix notation, tokens separated by
+    /// spaces.
+    pub fn to_prefix(&self) -> String {
+        node::traverse(self, &Traversals::PreOrder)
+            .map(|(token, _)| token.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // Writes an operand of `op`, in parentheses if it binds looser, or as
+    // tight on the right, where dropping them would change the tree
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, op: Op, right: bool) -> fmt::Result {
+        let wrap = match self.token {
+            Token::Op(inner) => {
+                inner.precedence().lt(&op.precedence())
+                    || (right && inner.precedence().eq(&op.precedence()))
+            }
+            Token::Num(_) => false,
+        };
+        if wrap {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl BinaryTreeNode for Expr {
+    type Key = Token;
+    type Value = ();
+
+    fn key(&self) -> &Token {
+        &self.token
+    }
+
+    fn value(&self) -> &() {
+        &()
+    }
+
+    fn left(&self) -> Option<&Self> {
+        self.operands.as_ref().map(|operands| &operands.0)
+    }
+
+    fn right(&self) -> Option<&Self> {
+        self.operands.as_ref().map(|operands| &operands.1)
+    }
+}
+
+impl fmt::Display for Expr {
+    /// Writes the expression in infix notation with as few parentheses as
+    /// keep its tree.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.token, self.left(), self.right()) {
+            (Token::Op(op), Some(left), Some(right)) => {
+                left.fmt_operand(f, op, false)?;
+                write!(f, " {} ", op.symbol())?;
+                right.fmt_operand(f, op, true)
+            }
+            (token, _, _) => write!(f, "{}", token),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expr, Op};
+    use crate::node;
+
+    #[test]
+    fn test_infix_keeps_shape() {
+        let (a, b, c) = (Expr::num(8), Expr::num(4), Expr::num(2));
+        let left = Expr::op(Op::Sub, Expr::op(Op::Sub, a.clone(), b.clone()), c.clone());
+        let right = Expr::op(Op::Sub, a.clone(), Expr::op(Op::Sub, b.clone(), c.clone()));
+        assert_eq!(left.to_string(), "8 - 4 - 2");
+        assert_eq!(right.to_string(), "8 - (4 - 2)");
+        assert_eq!((left.eval(), right.eval()), (Some(2), Some(6)));
+
+        let mixed = Expr::op(Op::Add, a, Expr::op(Op::Mul, b, Expr::num(-2)));
+        assert_eq!(mixed.to_string(), "8 + 4 * -2");
+        assert_eq!(mixed.to_prefix(), "+ 8 * 4 -2");
+        assert_eq!(mixed.eval(), Some(0));
+        assert_eq!(node::height(&mixed), 2_usize);
+        assert_eq!(c.to_postfix(), "2");
+    }
+
+    #[test]
+    fn test_eval_overflow() {
+        let expr = Expr::op(Op::Mul, Expr::num(i64::MAX), Expr::num(2));
+        assert_eq!(expr.eval(), None);
+        let expr = Expr::op(Op::Div, Expr::num(i64::MIN), Expr::num(-1));
+        assert_eq!(expr.eval(), None);
+    }
+}
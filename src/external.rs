@@ -0,0 +1,344 @@
+use crate::btree::BalancedTree;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+// Tells apart runs of builders working at the same time
+static BUILDS: AtomicUsize = AtomicUsize::new(0_usize);
+
+/// Builds a `BalancedTree` from inputs too large to sort in memory.
+///
+/// Entries are buffered up to `run_capacity`, sorted and spilled to temp
+/// files as runs of JSON lines. Runs are merged in a single pass, which
+/// feeds `BalancedTree::from_sorted_iter`. That collects the merged entries
+/// before packing them into nodes, so memory peaks at about twice the final
+/// tree rather than at the size of the input. On duplicate keys the latest
+/// entry is kept.
+///
+/// A run file found out of key order when read back, as after a change on
+/// disk, fails the build with `io::ErrorKind::InvalidData`.
+///
+/// # Examples
+///
+/// ```
+/// use treers::external::ExternalBuilder;
+/// use treers::{Map, OrderedMap};
+///
+/// let input = (0_u32..10_000).rev().map(|i| (i % 5000, i));
+/// let btree = ExternalBuilder::new().run_capacity(1000).build(input).unwrap();
+/// assert_eq!(btree.size(), 5000_usize);
+/// assert_eq!(btree.get(&42), Some(&42));
+/// assert_eq!(btree.max(), Some(&4999));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExternalBuilder {
+    run_capacity: usize,
+    temp_dir: PathBuf,
+}
+
+impl ExternalBuilder {
+    /// Inits a builder keeping a million entries in memory per run and
+    /// spilling into the system temp directory.
+    pub fn new() -> Self {
+        Self {
+            run_capacity: 1_usize << 20,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+
+    /// Sets a count of entries sorted in memory before spilling a run.
+    pub fn run_capacity(mut self, run_capacity: usize) -> Self {
+        self.run_capacity = run_capacity.max(1_usize);
+        self
+    }
+
+    /// Sets a directory for run files.
+    pub fn temp_dir<P: AsRef<Path>>(mut self, temp_dir: P) -> Self {
+        self.temp_dir = temp_dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Consumes `iter` and builds the tree, run files are removed before
+    /// returning.
+    pub fn build<K, V, I>(&self, iter: I) -> io::Result<BalancedTree<K, V>>
+    where
+        K: Ord + Clone + Serialize + DeserializeOwned,
+        V: Clone + Serialize + DeserializeOwned,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut runs = Runs {
+            prefix: self.temp_dir.join(format!(
+                "treers-{}-{}",
+                std::process::id(),
+                BUILDS.fetch_add(1_usize, AtomicOrdering::Relaxed)
+            )),
+            created: Vec::new(),
+        };
+        let mut pending = Vec::new();
+        let mut buffer = Vec::with_capacity(self.run_capacity.min(1_usize << 16));
+        for entry in iter {
+            buffer.push(entry);
+            if buffer.len().ge(&self.run_capacity) {
+                sort_run(&mut buffer);
+                let path = runs.next_path();
+                write_run(buffer.drain(..).map(Ok), &path)?;
+                pending.push(path);
+            }
+        }
+        sort_run(&mut buffer);
+        if pending.is_empty() {
+            return Ok(BalancedTree::from_sorted_iter(buffer));
+        }
+        // Keeps open files bounded, merged groups stay in run order
+        while pending.len().gt(&MERGE_WIDTH) {
+            let mut merged = Vec::with_capacity(pending.len() / MERGE_WIDTH + 1_usize);
+            for group in pending.chunks(MERGE_WIDTH) {
+                let mut merge: Merge<K, V> = Merge::new(group, Vec::new())?;
+                let path = runs.next_path();
+                write_run(std::iter::from_fn(|| merge.next_entry().transpose()), &path)?;
+                for consumed in group {
+                    fs::remove_file(consumed)?;
+                }
+                merged.push(path);
+            }
+            pending = merged;
+        }
+        let mut merge = Merge::new(&pending, buffer)?;
+        let mut failure = None;
+        let btree =
+            BalancedTree::from_sorted_iter(std::iter::from_fn(|| match merge.next_entry() {
+                Ok(entry) => entry,
+                Err(e) => {
+                    failure = Some(e);
+                    None
+                }
+            }));
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(btree),
+        }
+    }
+}
+
+impl Default for ExternalBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Most of runs merged at once
+const MERGE_WIDTH: usize = 64_usize;
+
+// Names run files and removes them, also on errors
+struct Runs {
+    prefix: PathBuf,
+    created: Vec<PathBuf>,
+}
+
+impl Runs {
+    fn next_path(&mut self) -> PathBuf {
+        let mut name = self.prefix.clone().into_os_string();
+        name.push(format!("-{}.jsonl", self.created.len()));
+        self.created.push(PathBuf::from(name));
+        self.created[self.created.len() - 1_usize].clone()
+    }
+}
+
+impl Drop for Runs {
+    fn drop(&mut self) {
+        for path in self.created.iter() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// Stable sort by key, keeping the latest of equal keys
+fn sort_run<K: Ord, V>(buffer: &mut Vec<(K, V)>) {
+    buffer.sort_by(|a, b| a.0.cmp(&b.0));
+    buffer.dedup_by(|later, earlier| {
+        if later.0.eq(&earlier.0) {
+            std::mem::swap(later, earlier);
+            true
+        } else {
+            false
+        }
+    });
+}
+
+fn write_run<K, V, I>(entries: I, path: &Path) -> io::Result<()>
+where
+    K: Serialize,
+    V: Serialize,
+    I: Iterator<Item = io::Result<(K, V)>>,
+{
+    let mut writer = BufWriter::new(File::create(path)?);
+    for entry in entries {
+        serde_json::to_writer(&mut writer, &entry?)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+// Head of a run, ordered by key and then by run, later runs are newer
+struct Head<K, V> {
+    key: K,
+    run: usize,
+    value: V,
+}
+
+impl<K: Ord, V> PartialEq for Head<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).eq(&Ordering::Equal)
+    }
+}
+
+impl<K: Ord, V> Eq for Head<K, V> {}
+
+impl<K: Ord, V> PartialOrd for Head<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V> Ord for Head<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then_with(|| self.run.cmp(&other.run))
+    }
+}
+
+enum Run<K, V> {
+    File(Lines<BufReader<File>>),
+    Memory(std::vec::IntoIter<(K, V)>),
+}
+
+impl<K: DeserializeOwned, V: DeserializeOwned> Run<K, V> {
+    fn next_entry(&mut self) -> io::Result<Option<(K, V)>> {
+        match self {
+            Run::File(lines) => match lines.next() {
+                Some(line) => Ok(Some(serde_json::from_str(&line?)?)),
+                None => Ok(None),
+            },
+            Run::Memory(entries) => Ok(entries.next()),
+        }
+    }
+}
+
+// K-way merge of sorted runs, yielding every key once
+struct Merge<K, V> {
+    runs: Vec<Run<K, V>>,
+    heap: BinaryHeap<Reverse<Head<K, V>>>,
+}
+
+impl<K: Ord + DeserializeOwned, V: DeserializeOwned> Merge<K, V> {
+    fn new(paths: &[PathBuf], last: Vec<(K, V)>) -> io::Result<Self> {
+        let mut runs = Vec::with_capacity(paths.len() + 1_usize);
+        for path in paths {
+            runs.push(Run::File(BufReader::new(File::open(path)?).lines()));
+        }
+        runs.push(Run::Memory(last.into_iter()));
+        let mut merge = Self {
+            runs,
+            heap: BinaryHeap::new(),
+        };
+        for run in 0..merge.runs.len() {
+            merge.refill(run, None)?;
+        }
+        Ok(merge)
+    }
+
+    // Pushes the next entry of `run`, which has to come after `last`, the
+    // key just taken from it
+    fn refill(&mut self, run: usize, last: Option<&K>) -> io::Result<()> {
+        if let Some((key, value)) = self.runs[run].next_entry()? {
+            if last.is_some_and(|last| key.le(last)) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("run {} out of key order", run),
+                ));
+            }
+            self.heap.push(Reverse(Head { key, run, value }));
+        }
+        Ok(())
+    }
+
+    fn next_entry(&mut self) -> io::Result<Option<(K, V)>> {
+        let mut head = match self.heap.pop() {
+            Some(Reverse(head)) => head,
+            None => return Ok(None),
+        };
+        self.refill(head.run, Some(&head.key))?;
+        while self
+            .heap
+            .peek()
+            .is_some_and(|Reverse(next)| next.key.eq(&head.key))
+        {
+            if let Some(Reverse(newer)) = self.heap.pop() {
+                self.refill(newer.run, Some(&newer.key))?;
+                head = newer;
+            }
+        }
+        Ok(Some((head.key, head.value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExternalBuilder, Merge};
+    use crate::{Map, OrderedMap};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_external_build() {
+        let input: Vec<(u32, u32)> = (0_u32..5000).map(|i| ((i * 7919) % 1500, i)).collect();
+        let expected: BTreeMap<u32, u32> = input.iter().copied().collect();
+        let dir = std::env::temp_dir().join(format!("treers-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for capacity in [1_usize, 3, 100, 10_000] {
+            let btree = ExternalBuilder::new()
+                .run_capacity(capacity)
+                .temp_dir(&dir)
+                .build(input.iter().copied())
+                .unwrap();
+            assert_eq!(btree.size(), expected.len());
+            assert!(btree.range(..).eq(expected.iter()));
+        }
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0_usize);
+        std::fs::remove_dir(&dir).unwrap();
+
+        let empty = ExternalBuilder::new()
+            .build(Vec::<(u32, u32)>::new())
+            .unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_run_out_of_order() {
+        let path = std::env::temp_dir().join(format!("treers-order-{}.jsonl", std::process::id()));
+        std::fs::write(&path, "[1,10]\n[3,30]\n[2,20]\n").unwrap();
+        let mut merge: Merge<u32, u32> =
+            Merge::new(std::slice::from_ref(&path), vec![(2, 0)]).unwrap();
+        assert_eq!(merge.next_entry().unwrap(), Some((1, 10)));
+        assert_eq!(merge.next_entry().unwrap(), Some((2, 0)));
+        // taking 3 reads 2 after it
+        let err = merge.next_entry().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_temp_dir() {
+        let result = ExternalBuilder::new()
+            .run_capacity(2)
+            .temp_dir("/nonexistent/treers")
+            .build((0_u32..10).map(|i| (i, i)));
+        assert!(result.is_err());
+    }
+}
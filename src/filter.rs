@@ -0,0 +1,348 @@
+use crate::{Map, OrderedMap};
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeBounds;
+
+// Counters per expected key, about 1% false positives with `HASHES`
+const COUNTERS_PER_KEY: usize = 10;
+// Counters set by each key
+const HASHES: u64 = 7;
+// Counters are 4 bits, a saturated one is never decremented again, so
+// the filter can't deny a present key
+const SATURATED: u8 = 15;
+
+/// Lookups of a `Filtered` map, by how they were answered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FilterStats {
+    /// Count of lookups finding their key
+    pub hits: u64,
+    /// Count of misses answered by the filter, without a descent
+    pub filtered: u64,
+    /// Count of misses the filter let through to the map
+    pub false_positives: u64,
+}
+
+impl FilterStats {
+    /// Returns a count of lookups not finding their key.
+    pub const fn misses(&self) -> u64 {
+        self.filtered + self.false_positives
+    }
+}
+
+/// A map consulting a counting Bloom filter of its keys before the map
+/// itself, so most lookups of absent keys skip the descent.
+///
+/// The filter keeps ten 4-bit counters per key of the capacity, 5 bytes a
+/// key, and each key bumps 7 of them; a lookup whose counters aren't all
+/// set is a miss without reading the tree. The counters are decremented
+/// on `delete`, so the filter follows the keys.
+/// Past the capacity false positives rise, `rebuild` sizes the filter
+/// again. Keys are hashed by `DefaultHasher`, so `K: Hash`.
+///
+/// Only `Map` and `OrderedMap` are implemented, removing keys through
+/// another trait would leave their counters set.
+///
+/// # Examples
+///
+/// ```
+/// use treers::filter::Filtered;
+/// use treers::rbtree::RedBlackTree;
+/// use treers::Map;
+///
+/// // sessions by token, most requests carry a stale one
+/// let mut sessions = Filtered::new(RedBlackTree::new(), 1000);
+/// for token in 0..1000_u64 {
+///     sessions.put(token * 7919, token);
+/// }
+/// assert_eq!(sessions.get(&7919), Some(&1));
+/// for stale in 0..1000_u64 {
+///     assert!(!sessions.contains(&(stale * 7919 + 1)));
+/// }
+/// let stats = sessions.stats();
+/// assert_eq!((stats.hits, stats.misses()), (1_u64, 1000_u64));
+/// assert!(stats.filtered.gt(&950_u64));
+///
+/// assert_eq!(sessions.delete(&7919), Some(1));
+/// sessions.reset_stats();
+/// assert_eq!(sessions.get(&7919), None);
+/// assert_eq!(sessions.stats().hits, 0_u64);
+/// ```
+pub struct Filtered<M> {
+    map: M,
+    // Two counters a byte, low nibble first
+    counters: Vec<u8>,
+    slots: u64,
+    hits: Cell<u64>,
+    filtered: Cell<u64>,
+    false_positives: Cell<u64>,
+}
+
+impl<M> Filtered<M> {
+    /// Filters lookups of `map`, with a filter sized for `capacity` keys
+    /// holding the keys the map already has.
+    pub fn new<K: Ord + Hash, V>(map: M, capacity: usize) -> Self
+    where
+        M: OrderedMap<K, V>,
+    {
+        let mut filtered = Self {
+            map,
+            counters: Vec::new(),
+            slots: 0_u64,
+            hits: Cell::new(0_u64),
+            filtered: Cell::new(0_u64),
+            false_positives: Cell::new(0_u64),
+        };
+        filtered.rebuild(capacity);
+        filtered
+    }
+
+    /// Sizes the filter again for `capacity` keys, from the keys of the
+    /// map, e.g. once it outgrows the capacity; saturated counters are
+    /// cleared too.
+    pub fn rebuild<K: Ord + Hash, V>(&mut self, capacity: usize)
+    where
+        M: OrderedMap<K, V>,
+    {
+        let slots = capacity.max(1_usize).saturating_mul(COUNTERS_PER_KEY);
+        self.counters = vec![0_u8; slots.div_ceil(2)];
+        self.slots = slots as u64;
+        let hashes: Vec<u64> = self.map.range(..).map(|(k, _)| hash(k)).collect();
+        for h in hashes {
+            self.remember(h);
+        }
+    }
+
+    /// Returns the lookups answered since the stats were last reset.
+    pub const fn stats(&self) -> FilterStats {
+        FilterStats {
+            hits: self.hits.get(),
+            filtered: self.filtered.get(),
+            false_positives: self.false_positives.get(),
+        }
+    }
+
+    /// Zeroes the stats.
+    pub fn reset_stats(&self) {
+        self.hits.set(0_u64);
+        self.filtered.set(0_u64);
+        self.false_positives.set(0_u64);
+    }
+
+    /// Returns `false` if `key` is surely absent, `true` if it may be
+    /// present, without reading the map.
+    pub fn may_contain<K: Hash>(&self, key: &K) -> bool {
+        self.slots_of(hash(key)).all(|i| self.counter(i).gt(&0_u8))
+    }
+
+    /// Returns the bytes taken by the filter.
+    pub const fn filter_bytes(&self) -> usize {
+        self.counters.len()
+    }
+
+    /// Returns a reference to the filtered map.
+    pub const fn get_ref(&self) -> &M {
+        &self.map
+    }
+
+    /// Drops the filter and returns the map.
+    pub fn into_inner(self) -> M {
+        self.map
+    }
+
+    // Counters of the key hashed to `h`, by double hashing
+    fn slots_of(&self, h: u64) -> impl Iterator<Item = usize> {
+        let slots = self.slots;
+        let (h1, h2) = (h & 0xFFFF_FFFF, (h >> 32) | 1_u64);
+        (0..HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % slots) as usize)
+    }
+
+    fn counter(&self, i: usize) -> u8 {
+        (self.counters[i / 2] >> ((i % 2) * 4)) & 0x0F
+    }
+
+    fn set_counter(&mut self, i: usize, c: u8) {
+        let shift = (i % 2) * 4;
+        let byte = &mut self.counters[i / 2];
+        *byte = (*byte & !(0x0F << shift)) | (c << shift);
+    }
+
+    fn remember(&mut self, h: u64) {
+        let slots: Vec<usize> = self.slots_of(h).collect();
+        for i in slots {
+            let c = self.counter(i);
+            if c.lt(&SATURATED) {
+                self.set_counter(i, c + 1_u8);
+            }
+        }
+    }
+
+    #[cfg(any(feature = "rbtree", feature = "btree"))]
+    fn forget(&mut self, h: u64) {
+        let slots: Vec<usize> = self.slots_of(h).collect();
+        for i in slots {
+            let c = self.counter(i);
+            if c.gt(&0_u8) && c.lt(&SATURATED) {
+                self.set_counter(i, c - 1_u8);
+            }
+        }
+    }
+}
+
+fn hash<K: Hash>(key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(feature = "rbtree")]
+impl<K: Ord + Clone + Hash, V: Clone> Filtered<crate::rbtree::RedBlackTree<K, V>> {
+    /// Removes `key`, decrementing its counters, returning its value.
+    pub fn delete(&mut self, key: &K) -> Option<V> {
+        let v = self.map.delete(key)?;
+        self.forget(hash(key));
+        Some(v)
+    }
+}
+
+#[cfg(feature = "btree")]
+impl<K: Ord + Clone + Hash, V: Clone> Filtered<crate::btree::BalancedTree<K, V>> {
+    /// Removes `key`, decrementing its counters, returning its value.
+    pub fn delete(&mut self, key: &K) -> Option<V> {
+        let v = self.map.delete(key)?;
+        self.forget(hash(key));
+        Some(v)
+    }
+}
+
+impl<M: fmt::Debug> fmt::Debug for Filtered<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Filtered")
+            .field("map", &self.map)
+            .field("filter_bytes", &self.counters.len())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl<K: Hash, V, M: Map<K, V>> Map<K, V> for Filtered<M> {
+    fn size(&self) -> usize {
+        self.map.size()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        if !self.may_contain(key) {
+            self.filtered.set(self.filtered.get() + 1_u64);
+            return None;
+        }
+        let found = self.map.get(key);
+        let counter = if found.is_some() {
+            &self.hits
+        } else {
+            &self.false_positives
+        };
+        counter.set(counter.get() + 1_u64);
+        found
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        // a present key already has its counters set
+        if self.map.get(&key).is_none() {
+            self.remember(hash(&key));
+        }
+        self.map.put(key, value);
+    }
+}
+
+impl<K: Ord + Hash, V, M: OrderedMap<K, V>> OrderedMap<K, V> for Filtered<M> {
+    fn min(&self) -> Option<&K> {
+        self.map.min()
+    }
+
+    fn max(&self) -> Option<&K> {
+        self.map.max()
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        self.map.floor(key)
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        self.map.ceiling(key)
+    }
+
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.smallest(n)
+    }
+
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.largest(n)
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.range(range)
+    }
+}
+
+#[cfg(all(test, feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::{FilterStats, Filtered};
+    use crate::bench_support::shuffled_keys;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use crate::{Map, OrderedMap};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_against_model() {
+        let mut map = Filtered::new(BalancedTree::new(), 500);
+        let mut model = BTreeMap::new();
+        for (i, k) in shuffled_keys(3000, 37).into_iter().enumerate() {
+            let k = k % 600;
+            if i % 4 == 0 {
+                assert_eq!(map.delete(&k), model.remove(&k));
+            } else {
+                map.put(k, i);
+                model.insert(k, i);
+            }
+        }
+        map.reset_stats();
+        for k in 0_u64..1200 {
+            assert_eq!(map.get(&k), model.get(&k));
+            // no false negatives, deletes included
+            if model.contains_key(&k) {
+                assert!(map.may_contain(&k));
+            }
+        }
+        let stats = map.stats();
+        assert_eq!(stats.hits, model.len() as u64);
+        assert_eq!(stats.misses(), 1200_u64 - model.len() as u64);
+        assert!(stats.false_positives.lt(&(stats.misses() / 10)));
+        assert!(map.range(..).eq(model.iter()));
+
+        map.rebuild(2000);
+        assert_eq!(map.filter_bytes(), 10_000_usize);
+        map.reset_stats();
+        assert!((0_u64..1200).all(|k| map.get(&k).eq(&model.get(&k))));
+        assert_eq!(map.stats().hits, model.len() as u64);
+    }
+
+    #[test]
+    fn test_seeds_from_map() {
+        let mut tree = RedBlackTree::new();
+        for k in 0_u32..100 {
+            tree.put(k, k);
+        }
+        let mut map = Filtered::new(tree, 100);
+        assert!((0_u32..100).all(|k| map.may_contain(&k)));
+        // a present key is not counted twice, so one delete clears it
+        map.put(7, 70);
+        assert_eq!(map.delete(&7), Some(70));
+        assert_eq!(map.get(&7), None);
+        assert_eq!(map.delete(&7), None);
+        assert_eq!((map.stats().hits, map.stats().misses()), (0_u64, 1_u64));
+        map.reset_stats();
+        assert_eq!(map.stats(), FilterStats::default());
+    }
+}
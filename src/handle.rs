@@ -0,0 +1,295 @@
+use crate::Map;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// A `Copy` id of an entry of a `HandleMap`, valid until the entry is
+/// removed.
+///
+/// Ids are slots of the slab of the map with the generation of the slot,
+/// so an id of a removed entry stays invalid when its slot is reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntryId {
+    slot: u32,
+    generation: u32,
+}
+
+impl EntryId {
+    /// Returns the slot of the entry in the slab, e.g. to index a side
+    /// table; slots of removed entries are reused.
+    pub const fn slot(self) -> usize {
+        self.slot as usize
+    }
+}
+
+impl fmt::Display for EntryId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.slot, self.generation)
+    }
+}
+
+struct Slot<K, V> {
+    generation: u32,
+    // `None` while the slot is free
+    entry: Option<(K, V)>,
+}
+
+/// A map keeping its entries in a slab, reached by key through the tree
+/// or by `EntryId` directly.
+///
+/// The tree maps every key to the id of its entry, the entry itself sits
+/// in a slot of the slab that doesn't move while the tree rebalances.
+/// Other structures, indexes, queues or graphs, hold the `Copy` ids
+/// instead of cloned keys, and a lookup by id is an index into the slab.
+/// Keys are cloned once, into the tree.
+///
+/// # Examples
+///
+/// ```
+/// use treers::handle::HandleMap;
+/// use treers::rbtree::RedBlackTree;
+///
+/// let mut users = HandleMap::new(RedBlackTree::new());
+/// let ada = users.put_with_handle(String::from("ada"), 36);
+/// let bob = users.put_with_handle(String::from("bob"), 41);
+///
+/// // a follower graph of ids, not of names
+/// let follows = vec![(ada, bob), (bob, ada)];
+/// let (from, to) = follows[0];
+/// assert_eq!(users.get_by_handle(from), Some((&String::from("ada"), &36)));
+/// assert_eq!(users.get_by_handle(to).map(|(_, age)| *age), Some(41));
+///
+/// // a put of a present key keeps its id
+/// assert_eq!(users.put_with_handle(String::from("ada"), 37), ada);
+///
+/// users.remove(&String::from("bob"));
+/// assert_eq!(users.get_by_handle(bob), None);
+/// ```
+pub struct HandleMap<K, V, M> {
+    map: M,
+    slots: Vec<Slot<K, V>>,
+    free: Vec<u32>,
+}
+
+impl<K, V, M> HandleMap<K, V, M> {
+    /// Wraps an empty `map` of ids.
+    pub const fn new(map: M) -> Self {
+        Self {
+            map,
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the map of ids.
+    pub const fn get_ref(&self) -> &M {
+        &self.map
+    }
+
+    /// Returns the entry of `id`, `None` once it is removed.
+    pub fn get_by_handle(&self, id: EntryId) -> Option<(&K, &V)> {
+        let slot = self.slots.get(id.slot())?;
+        if slot.generation.ne(&id.generation) {
+            return None;
+        }
+        slot.entry.as_ref().map(|(k, v)| (k, v))
+    }
+
+    /// Returns a mutable reference to the value of `id`, `None` once it is
+    /// removed.
+    pub fn get_by_handle_mut(&mut self, id: EntryId) -> Option<&mut V> {
+        let slot = self.slots.get_mut(id.slot())?;
+        if slot.generation.ne(&id.generation) {
+            return None;
+        }
+        slot.entry.as_mut().map(|(_, v)| v)
+    }
+
+    /// Returns `true` if `id` is of an entry still in the map.
+    pub fn contains_handle(&self, id: EntryId) -> bool {
+        self.get_by_handle(id).is_some()
+    }
+
+    // Puts an entry into a free slot, or a new one
+    fn alloc(&mut self, key: K, value: V) -> EntryId {
+        match self.free.pop() {
+            Some(slot) => {
+                let s = &mut self.slots[slot as usize];
+                s.entry = Some((key, value));
+                EntryId {
+                    slot,
+                    generation: s.generation,
+                }
+            }
+            None => {
+                let slot = u32::try_from(self.slots.len()).expect("more than u32::MAX entries");
+                self.slots.push(Slot {
+                    generation: 0_u32,
+                    entry: Some((key, value)),
+                });
+                EntryId {
+                    slot,
+                    generation: 0_u32,
+                }
+            }
+        }
+    }
+
+    // Frees the slot of `id`, ids of it turn stale
+    #[cfg(any(feature = "bst", feature = "rbtree", feature = "btree"))]
+    fn release(&mut self, id: EntryId) -> Option<(K, V)> {
+        let slot = self.slots.get_mut(id.slot())?;
+        let entry = slot.entry.take()?;
+        slot.generation = slot.generation.wrapping_add(1_u32);
+        self.free.push(id.slot);
+        Some(entry)
+    }
+}
+
+impl<K: Clone, V, M: Map<K, EntryId>> HandleMap<K, V, M> {
+    /// Returns a count of entries.
+    pub fn size(&self) -> usize {
+        self.map.size()
+    }
+
+    /// Returns the id of the entry of `key`.
+    pub fn handle(&self, key: &K) -> Option<EntryId> {
+        self.map.get(key).copied()
+    }
+
+    /// Returns the value of `key`.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let id = self.handle(key)?;
+        self.get_by_handle(id).map(|(_, v)| v)
+    }
+
+    /// Puts `value` under `key` and returns the id of its entry, the id it
+    /// already had if `key` is present.
+    pub fn put_with_handle(&mut self, key: K, value: V) -> EntryId {
+        if let Some(id) = self.handle(&key) {
+            if let Some(v) = self.get_by_handle_mut(id) {
+                *v = value;
+            }
+            return id;
+        }
+        let id = self.alloc(key.clone(), value);
+        self.map.put(key, id);
+        id
+    }
+}
+
+#[cfg(feature = "bst")]
+impl<K: Ord + Clone, V> HandleMap<K, V, crate::bst::BST<K, EntryId>> {
+    /// Removes the entry of `key`, returning its value; its id turns stale.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let id = self.map.delete(key)?;
+        self.release(id).map(|(_, v)| v)
+    }
+
+    /// Removes the entry of `id`, returning it.
+    pub fn remove_by_handle(&mut self, id: EntryId) -> Option<(K, V)> {
+        let key = self.get_by_handle(id)?.0.clone();
+        self.map.delete(&key);
+        self.release(id)
+    }
+}
+
+#[cfg(feature = "rbtree")]
+impl<K: Ord + Clone, V> HandleMap<K, V, crate::rbtree::RedBlackTree<K, EntryId>> {
+    /// Removes the entry of `key`, returning its value; its id turns stale.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let id = self.map.delete(key)?;
+        self.release(id).map(|(_, v)| v)
+    }
+
+    /// Removes the entry of `id`, returning it.
+    pub fn remove_by_handle(&mut self, id: EntryId) -> Option<(K, V)> {
+        let key = self.get_by_handle(id)?.0.clone();
+        self.map.delete(&key);
+        self.release(id)
+    }
+}
+
+#[cfg(feature = "btree")]
+impl<K: Ord + Clone, V> HandleMap<K, V, crate::btree::BalancedTree<K, EntryId>> {
+    /// Removes the entry of `key`, returning its value; its id turns stale.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let id = self.map.delete(key)?;
+        self.release(id).map(|(_, v)| v)
+    }
+
+    /// Removes the entry of `id`, returning it.
+    pub fn remove_by_handle(&mut self, id: EntryId) -> Option<(K, V)> {
+        let key = self.get_by_handle(id)?.0.clone();
+        self.map.delete(&key);
+        self.release(id)
+    }
+}
+
+impl<K, V, M: fmt::Debug> fmt::Debug for HandleMap<K, V, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HandleMap")
+            .field("map", &self.map)
+            .field("slots", &self.slots.len())
+            .field("free", &self.free.len())
+            .finish()
+    }
+}
+
+impl<K, V, M: Default> Default for HandleMap<K, V, M> {
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::{EntryId, HandleMap};
+    use crate::bench_support::shuffled_keys;
+    use crate::bst::BST;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use crate::Map;
+
+    fn stable_ids<M: Map<u64, EntryId> + Default>() {
+        let mut map = HandleMap::new(M::default());
+        let keys = shuffled_keys(1000, 27);
+        let ids: Vec<EntryId> = keys.iter().map(|&k| map.put_with_handle(k, k)).collect();
+        for (k, id) in keys.iter().zip(&ids) {
+            *map.get_by_handle_mut(*id).unwrap() += 1_u64;
+            assert_eq!(map.handle(k), Some(*id));
+        }
+        for (k, id) in keys.iter().zip(&ids) {
+            assert_eq!(map.get_by_handle(*id), Some((k, &(k + 1_u64))));
+            assert_eq!(map.get(k), Some(&(k + 1_u64)));
+        }
+        assert_eq!(map.size(), keys.len());
+    }
+
+    #[test]
+    fn test_ids_survive_rebalancing() {
+        stable_ids::<BST<u64, EntryId>>();
+        stable_ids::<RedBlackTree<u64, EntryId>>();
+        stable_ids::<BalancedTree<u64, EntryId>>();
+
+        // `remove` and `remove_by_handle` are implemented per tree
+        macro_rules! frees {
+            ($tree:expr) => {{
+                let mut map = HandleMap::new($tree);
+                let one = map.put_with_handle(1_u32, "one");
+                let two = map.put_with_handle(2, "two");
+                assert_eq!(map.remove(&1), Some("one"));
+                // the freed slot is reused, under a new generation
+                let uno = map.put_with_handle(1, "uno");
+                assert_eq!(uno.slot(), one.slot());
+                assert_ne!(uno, one);
+                assert!(!map.contains_handle(one) && map.contains_handle(uno));
+                assert_eq!(map.remove_by_handle(two), Some((2, "two")));
+                assert_eq!((map.get(&2), map.size()), (None, 1_usize));
+                assert_eq!(map.remove_by_handle(one), None);
+            }};
+        }
+        frees!(BST::new());
+        frees!(RedBlackTree::new());
+        frees!(BalancedTree::new());
+    }
+}
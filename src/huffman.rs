@@ -0,0 +1,222 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// 5.5 Data Compression: Huffman trie
+///
+/// Prefix-free codes for bytes from their frequencies, as in Robert
+/// Sedgewick book, "Algorithms" 4th edition. Frequent bytes get short
+/// codes and a code is never the start of another one, so an encoded
+/// stream decodes walking the trie from the root, left on `0` and right
+/// on `1`, back to the root at every leaf.
+///
+/// # Examples
+///
+/// ```
+/// use treers::huffman::Huffman;
+///
+/// let text = b"ABRACADABRA!";
+/// let huffman = Huffman::from_bytes(text).unwrap();
+/// assert_eq!(huffman.code(b'A'), Some("0"));
+/// assert_eq!(huffman.code(b'Z'), None);
+///
+/// let encoded = huffman.encode(text).unwrap();
+/// assert_eq!(huffman.encoded_bits(text), Some(28_usize));
+/// assert_eq!(encoded.len(), 4_usize);
+/// assert_eq!(huffman.decode(&encoded, text.len()), Some(text.to_vec()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Huffman {
+    root: Node,
+    // Code of every byte, `None` for bytes of zero frequency
+    table: Vec<Option<String>>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf(u8),
+    Internal(Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn fill_table(&self, code: &mut String, table: &mut [Option<String>]) {
+        match self {
+            Node::Leaf(symbol) => table[*symbol as usize] = Some(code.clone()),
+            Node::Internal(left, right) => {
+                code.push('0');
+                left.fill_table(code, table);
+                code.pop();
+                code.push('1');
+                right.fill_table(code, table);
+                code.pop();
+            }
+        }
+    }
+}
+
+impl Huffman {
+    /// Builds the trie from a count of every byte value, `None` when all
+    /// counts are zero.
+    ///
+    /// Ties are broken by byte value, so equal counts give equal codes.
+    pub fn from_frequencies(freq: &[u64; 256]) -> Option<Self> {
+        // nodes of equal weight pop in the order they were made, leaves
+        // by byte value first
+        let mut heap = BinaryHeap::new();
+        let mut nodes: Vec<Option<Node>> = Vec::new();
+        for (symbol, f) in freq.iter().enumerate() {
+            if f.gt(&0_u64) {
+                heap.push(Reverse((*f, nodes.len())));
+                nodes.push(Some(Node::Leaf(symbol as u8)));
+            }
+        }
+        while heap.len().gt(&1_usize) {
+            let Reverse((fx, x)) = heap.pop()?;
+            let Reverse((fy, y)) = heap.pop()?;
+            let parent = Node::Internal(Box::new(nodes[x].take()?), Box::new(nodes[y].take()?));
+            heap.push(Reverse((fx + fy, nodes.len())));
+            nodes.push(Some(parent));
+        }
+        let Reverse((_, i)) = heap.pop()?;
+        let mut table = vec![None; 256];
+        let root = match nodes[i].take()? {
+            Node::Leaf(symbol) => {
+                // a lone byte still needs a one bit code
+                table[symbol as usize] = Some(String::from("0"));
+                Node::Internal(Box::new(Node::Leaf(symbol)), Box::new(Node::Leaf(symbol)))
+            }
+            root => {
+                root.fill_table(&mut String::new(), &mut table);
+                root
+            }
+        };
+        Some(Self { root, table })
+    }
+
+    /// Builds the trie from frequencies of bytes in `data`, `None` when it
+    /// is empty.
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let mut freq = [0_u64; 256];
+        for b in data {
+            freq[*b as usize] += 1_u64;
+        }
+        Self::from_frequencies(&freq)
+    }
+
+    /// Returns the code of `symbol` as `0` and `1` chars, `None` if it had
+    /// zero frequency.
+    pub fn code(&self, symbol: u8) -> Option<&str> {
+        self.table[symbol as usize].as_deref()
+    }
+
+    /// Returns the code table, every byte with a code and its code, in
+    /// byte order.
+    pub fn codes(&self) -> Vec<(u8, &str)> {
+        (0_u8..=255)
+            .filter_map(|b| self.code(b).map(|c| (b, c)))
+            .collect()
+    }
+
+    /// Returns the length in bits of `data` encoded, `None` if it has a
+    /// byte without a code.
+    pub fn encoded_bits(&self, data: &[u8]) -> Option<usize> {
+        data.iter().map(|b| self.code(*b).map(str::len)).sum()
+    }
+
+    /// Encodes `data`, packing bits from the most significant one and
+    /// padding the last byte with zeros. `None` if it has a byte without
+    /// a code.
+    pub fn encode(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let mut out = Vec::with_capacity(self.encoded_bits(data)?.div_ceil(8_usize));
+        let mut bits = 0_usize;
+        for b in data {
+            for c in self.code(*b)?.bytes() {
+                if bits.is_multiple_of(8_usize) {
+                    out.push(0_u8);
+                }
+                if c.eq(&b'1') {
+                    out[bits / 8_usize] |= 0x80_u8 >> (bits % 8_usize);
+                }
+                bits += 1_usize;
+            }
+        }
+        Some(out)
+    }
+
+    /// Decodes `len` bytes from `encoded`, `None` if it runs out of bits
+    /// first.
+    pub fn decode(&self, encoded: &[u8], len: usize) -> Option<Vec<u8>> {
+        let mut out = Vec::with_capacity(len);
+        let mut bits = 0_usize;
+        while out.len().lt(&len) {
+            let mut node = &self.root;
+            while let Node::Internal(ref left, ref right) = node {
+                let byte = encoded.get(bits / 8_usize)?;
+                node = if (byte << (bits % 8_usize)) & 0x80_u8 == 0_u8 {
+                    left
+                } else {
+                    right
+                };
+                bits += 1_usize;
+            }
+            if let Node::Leaf(symbol) = node {
+                out.push(*symbol);
+            }
+        }
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Huffman;
+    use crate::bench_support::shuffled_keys;
+
+    #[test]
+    fn test_round_trip() {
+        // skewed bytes, low values much more frequent
+        let data: Vec<u8> = shuffled_keys(5000, 9)
+            .iter()
+            .map(|k| (k % 64 + 1).trailing_zeros() as u8 * 17 + (k % 3) as u8)
+            .collect();
+        let huffman = Huffman::from_bytes(&data).unwrap();
+        let encoded = huffman.encode(&data).unwrap();
+        let bits = huffman.encoded_bits(&data).unwrap();
+        assert_eq!(encoded.len(), bits.div_ceil(8));
+        assert_eq!(huffman.decode(&encoded, data.len()), Some(data.clone()));
+        assert!(bits.lt(&(data.len() * 4)));
+        assert_eq!(
+            huffman.decode(&encoded[..encoded.len() / 2], data.len()),
+            None
+        );
+
+        // no code is a prefix of another one
+        let codes = huffman.codes();
+        for (a, x) in &codes {
+            for (b, y) in &codes {
+                assert!(a.eq(b) || !y.starts_with(x));
+            }
+        }
+        assert_eq!(huffman.encode(&[255]), None);
+    }
+
+    #[test]
+    fn test_small_inputs() {
+        assert!(Huffman::from_bytes(&[]).is_none());
+
+        let lone = Huffman::from_bytes(b"zzzz").unwrap();
+        assert_eq!(lone.codes(), vec![(b'z', "0")]);
+        assert_eq!(lone.encode(b"zzzz"), Some(vec![0_u8]));
+        assert_eq!(lone.decode(&[0], 4), Some(b"zzzz".to_vec()));
+        let zero = Huffman::from_bytes(&[0, 0]).unwrap();
+        assert_eq!(zero.codes(), vec![(0_u8, "0")]);
+
+        // 1 + 1 + 2 + 4: codes of 3, 3, 2 and 1 bits
+        let mut freq = [0_u64; 256];
+        for (b, f) in [(b'a', 1_u64), (b'b', 1), (b'c', 2), (b'd', 4)] {
+            freq[b as usize] = f;
+        }
+        let huffman = Huffman::from_frequencies(&freq).unwrap();
+        let lens: Vec<usize> = huffman.codes().iter().map(|(_, c)| c.len()).collect();
+        assert_eq!(lens, vec![3_usize, 3, 2, 1]);
+    }
+}
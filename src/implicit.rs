@@ -0,0 +1,261 @@
+use crate::{Map, OrderedMap};
+use std::ops::{Bound, RangeBounds};
+
+// Keys per node, 16 `u32` keys fill a 64 byte cache line
+const B: usize = 16_usize;
+
+/// A read-mostly map for dense integer keys, kept in an implicit B-tree
+/// layout.
+///
+/// Keys are stored in one array of nodes with `B` keys each, the children
+/// of node `k` are nodes `k * (B + 1) + 1 ..= k * (B + 1) + B + 1`, so there
+/// are no pointers to chase. A lookup reads one cache line per level and
+/// counts keys lower than the searched one in it, close to the speed of a
+/// sorted array search without its cache misses.
+///
+/// Replacing a value is done in place, but putting a new key rebuilds the
+/// layout in O(n), so the map suits keys loaded once with
+/// `from_sorted_iter` and then mostly read.
+///
+/// # Examples
+///
+/// ```
+/// use treers::implicit::ImplicitTree;
+/// use treers::{Map, OrderedMap};
+///
+/// let mut map = ImplicitTree::from_sorted_iter((0_u32..1000).map(|k| (k, k * 2)));
+/// assert_eq!(map.get(&500), Some(&1000));
+/// assert_eq!(map.get(&1000), None);
+///
+/// map.put(2000, 0);
+/// assert_eq!(map.max(), Some(&2000));
+/// assert_eq!(map.floor(&1500), Some(&999));
+/// assert_eq!(map.size(), 1001_usize);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ImplicitTree<K: Ord + Copy, V> {
+    keys: Vec<K>,
+    vals: Vec<V>,
+    // Slot of every key, in key order
+    order: Vec<usize>,
+}
+
+// First slot of the `i`th child of node `k`
+const fn child(k: usize, i: usize) -> usize {
+    (k * (B + 1_usize) + i + 1_usize) * B
+}
+
+// Slots of a layout with `n` keys in key order, from the node at `slot`
+fn in_order(n: usize, slot: usize, out: &mut Vec<usize>) {
+    if slot.ge(&n) {
+        return;
+    }
+    let k = slot / B;
+    for i in 0_usize..B {
+        in_order(n, child(k, i), out);
+        if (slot + i).lt(&n) {
+            out.push(slot + i);
+        }
+    }
+    in_order(n, child(k, B), out);
+}
+
+impl<K: Ord + Copy, V> ImplicitTree<K, V> {
+    /// Inits a new empty map.
+    pub const fn new() -> Self {
+        Self {
+            keys: Vec::new(),
+            vals: Vec::new(),
+            order: Vec::new(),
+        }
+    }
+
+    /// Builds the layout from entries in strictly ascending key order.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if keys are not strictly ascending.
+    pub fn from_sorted_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let entries: Vec<(K, V)> = iter.into_iter().collect();
+        debug_assert!(entries.windows(2).all(|w| w[0].0.lt(&w[1].0)));
+        let n = entries.len();
+        let mut order = Vec::with_capacity(n);
+        in_order(n, 0_usize, &mut order);
+        let mut slots: Vec<Option<(K, V)>> = (0_usize..n).map(|_| None).collect();
+        for (slot, entry) in order.iter().zip(entries) {
+            slots[*slot] = Some(entry);
+        }
+        let (keys, vals) = slots.into_iter().flatten().unzip();
+        Self { keys, vals, order }
+    }
+
+    /// Returns entries in key order, consuming the map.
+    pub fn into_sorted_vec(self) -> Vec<(K, V)> {
+        let mut slots: Vec<Option<(K, V)>> =
+            self.keys.into_iter().zip(self.vals).map(Some).collect();
+        self.order.iter().filter_map(|s| slots[*s].take()).collect()
+    }
+
+    // Slot holding `key`, by a walk down the nodes
+    fn slot(&self, key: &K) -> Option<usize> {
+        let n = self.keys.len();
+        let mut slot = 0_usize;
+        while slot.lt(&n) {
+            let node = &self.keys[slot..n.min(slot + B)];
+            // the node is sorted, counting doesn't branch on every key
+            let i = node.iter().filter(|k| k.lt(&key)).count();
+            if node.get(i).is_some_and(|k| k.eq(key)) {
+                return Some(slot + i);
+            }
+            slot = child(slot / B, i);
+        }
+        None
+    }
+
+    // Count of keys less than `key`, or less than or equal to it with `inclusive`
+    fn rank(&self, key: &K, inclusive: bool) -> usize {
+        self.order.partition_point(|s| {
+            let k = &self.keys[*s];
+            k.lt(key) || (inclusive && k.eq(key))
+        })
+    }
+
+    fn entry(&self, slot: usize) -> (&K, &V) {
+        (&self.keys[slot], &self.vals[slot])
+    }
+}
+
+impl<K: Ord + Copy, V> Default for ImplicitTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Copy, V> Map<K, V> for ImplicitTree<K, V> {
+    fn size(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.slot(key).map(|s| &self.vals[s])
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        if let Some(s) = self.slot(&key) {
+            self.vals[s] = value;
+            return;
+        }
+        let at = self.rank(&key, false);
+        let mut entries = std::mem::take(self).into_sorted_vec();
+        entries.insert(at, (key, value));
+        *self = Self::from_sorted_iter(entries);
+    }
+}
+
+impl<K: Ord + Copy, V> OrderedMap<K, V> for ImplicitTree<K, V> {
+    fn min(&self) -> Option<&K> {
+        self.order.first().map(|s| &self.keys[*s])
+    }
+
+    fn max(&self) -> Option<&K> {
+        self.order.last().map(|s| &self.keys[*s])
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        let rank = self.rank(key, true).checked_sub(1_usize)?;
+        Some(&self.keys[self.order[rank]])
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        let slot = self.order.get(self.rank(key, false))?;
+        Some(&self.keys[*slot])
+    }
+
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.order
+            .iter()
+            .take(n)
+            .map(|s| self.entry(*s))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.order
+            .iter()
+            .rev()
+            .take(n)
+            .map(|s| self.entry(*s))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        let from = match range.start_bound() {
+            Bound::Included(k) => self.rank(k, false),
+            Bound::Excluded(k) => self.rank(k, true),
+            Bound::Unbounded => 0_usize,
+        };
+        let to = match range.end_bound() {
+            Bound::Included(k) => self.rank(k, true),
+            Bound::Excluded(k) => self.rank(k, false),
+            Bound::Unbounded => self.order.len(),
+        };
+        self.order[from..to.max(from)]
+            .iter()
+            .map(|s| self.entry(*s))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ImplicitTree;
+    use crate::{Map, OrderedMap};
+    use std::collections::BTreeMap;
+    use std::ops::Bound;
+
+    #[test]
+    fn test_layout_lookups() {
+        for n in [0_u64, 1, 15, 16, 17, 272, 273, 300, 5000] {
+            let map = ImplicitTree::from_sorted_iter((0..n).map(|k| (k * 2, k)));
+            assert_eq!(map.size(), n as usize);
+            for k in 0..n {
+                assert_eq!(map.get(&(k * 2)), Some(&k));
+                assert_eq!(map.get(&(k * 2 + 1)), None);
+            }
+            assert!(map.range(..).map(|(k, _)| *k).eq((0..n).map(|k| k * 2)));
+            let sorted: Vec<(u64, u64)> = (0..n).map(|k| (k * 2, k)).collect();
+            assert_eq!(map.into_sorted_vec(), sorted);
+        }
+    }
+
+    #[test]
+    fn test_matches_model() {
+        let mut map: ImplicitTree<u32, u32> = ImplicitTree::new();
+        let mut model: BTreeMap<u32, u32> = BTreeMap::new();
+        let mut x = 9_u32;
+        for i in 0_u32..1500 {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let k = (x >> 8) % 1000;
+            map.put(k, i);
+            model.insert(k, i);
+        }
+        assert_eq!(map.size(), model.len());
+        assert!(map.range(..).eq(model.iter()));
+        for k in 0_u32..1001 {
+            assert_eq!(map.get(&k), model.get(&k));
+            assert_eq!(map.floor(&k), model.range(..=k).next_back().map(|(k, _)| k));
+            assert_eq!(map.ceiling(&k), model.range(k..).next().map(|(k, _)| k));
+        }
+        assert_eq!(map.min(), model.keys().next());
+        assert_eq!(map.max(), model.keys().next_back());
+        assert!(map.smallest(10).eq(model.iter().take(10)));
+        assert!(map.largest(10).eq(model.iter().rev().take(10)));
+        let bounds = (Bound::Excluded(100), Bound::Included(200));
+        assert!(map.range(bounds).eq(model.range(bounds)));
+        let reversed = (Bound::Included(300), Bound::Excluded(200));
+        assert_eq!(map.range(reversed).len(), 0_usize);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::rbtree::RedBlackTree;
+use crate::{Map, OrderedMap};
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+/// A `RedBlackTree` keeping a second index of its entries, ordered by value.
+///
+/// Both trees are updated on every `put` and `remove`, so entries can be
+/// looked up by key and scanned by value, e.g. a leaderboard keyed by
+/// player and ranked by score. Entries with equal values are ordered by key.
+///
+/// # Examples
+///
+/// ```
+/// use treers::indexed::IndexedMap;
+/// use treers::Map;
+///
+/// let mut scores: IndexedMap<&str, u32> = IndexedMap::new();
+/// scores.put("ann", 30);
+/// scores.put("bob", 10);
+/// scores.put("cid", 20);
+/// scores.put("bob", 40);
+///
+/// assert_eq!(scores.get(&"bob"), Some(&40));
+/// assert_eq!(scores.min_by_value(), Some((&"cid", &20)));
+/// assert_eq!(scores.max_by_value(), Some((&"bob", &40)));
+/// assert_eq!(
+///     scores.range_by_value(25..).as_slice(),
+///     &[(&"ann", &30), (&"bob", &40)]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct IndexedMap<K: Ord + Clone, V: Ord + Clone> {
+    map: RedBlackTree<K, V>,
+    index: RedBlackTree<ByValue<K, V>, ()>,
+}
+
+// Key of the value index, `Below` and `Above` pose as bounds of a value
+#[derive(Debug, Clone)]
+enum ByValue<K, V> {
+    Below(V),
+    Entry(V, K),
+    Above(V),
+}
+
+impl<K: Ord, V: Ord> ByValue<K, V> {
+    const fn value(&self) -> &V {
+        match self {
+            ByValue::Below(v) | ByValue::Entry(v, _) | ByValue::Above(v) => v,
+        }
+    }
+}
+
+impl<K: Ord, V: Ord> Ord for ByValue<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value()
+            .cmp(other.value())
+            .then_with(|| match (self, other) {
+                (ByValue::Entry(_, a), ByValue::Entry(_, b)) => a.cmp(b),
+                (ByValue::Below(_), ByValue::Below(_)) | (ByValue::Above(_), ByValue::Above(_)) => {
+                    Ordering::Equal
+                }
+                (ByValue::Below(_), _) | (_, ByValue::Above(_)) => Ordering::Less,
+                (_, ByValue::Below(_)) | (ByValue::Above(_), _) => Ordering::Greater,
+            })
+    }
+}
+
+impl<K: Ord, V: Ord> PartialOrd for ByValue<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V: Ord> PartialEq for ByValue<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).eq(&Ordering::Equal)
+    }
+}
+
+impl<K: Ord, V: Ord> Eq for ByValue<K, V> {}
+
+impl<K: Ord + Clone, V: Ord + Clone> IndexedMap<K, V> {
+    /// Inits a new empty map.
+    pub const fn new() -> Self {
+        Self {
+            map: RedBlackTree::new(),
+            index: RedBlackTree::new(),
+        }
+    }
+
+    /// Removes a key from both trees, returning its value if it was in the map.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::indexed::IndexedMap;
+    /// use treers::Map;
+    ///
+    /// let mut map: IndexedMap<char, i32> = IndexedMap::new();
+    /// map.put('a', 2);
+    /// map.put('b', 1);
+    /// assert_eq!(map.remove(&'b'), Some(1));
+    /// assert_eq!(map.remove(&'b'), None);
+    /// assert_eq!(map.min_by_value(), Some((&'a', &2)));
+    /// ```
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let value = self.map.delete(key)?;
+        self.index
+            .delete(&ByValue::Entry(value.clone(), key.clone()));
+        Some(value)
+    }
+
+    /// Returns the entry with the smallest value.
+    pub fn min_by_value(&self) -> Option<(&K, &V)> {
+        self.index.min().and_then(entry)
+    }
+
+    /// Returns the entry with the largest value.
+    pub fn max_by_value(&self) -> Option<(&K, &V)> {
+        self.index.max().and_then(entry)
+    }
+
+    /// Returns entries with values inside `range`, in value order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::indexed::IndexedMap;
+    /// use treers::Map;
+    ///
+    /// let mut map: IndexedMap<u32, char> = IndexedMap::new();
+    /// for (k, v) in [(1, 'c'), (2, 'a'), (3, 'b'), (4, 'a')] {
+    ///     map.put(k, v);
+    /// }
+    /// assert_eq!(
+    ///     map.range_by_value('a'..='a').as_slice(),
+    ///     &[(&2, &'a'), (&4, &'a')]
+    /// );
+    /// assert_eq!(map.range_by_value(..'c').len(), 3_usize);
+    /// ```
+    pub fn range_by_value<R: RangeBounds<V>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        let start = match range.start_bound() {
+            Bound::Included(v) => Bound::Included(ByValue::Below(v.clone())),
+            Bound::Excluded(v) => Bound::Excluded(ByValue::Above(v.clone())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(v) => Bound::Included(ByValue::Above(v.clone())),
+            Bound::Excluded(v) => Bound::Excluded(ByValue::Below(v.clone())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        self.index
+            .range((start, end))
+            .filter_map(|(e, _)| entry(e))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Returns a reference to the key-ordered tree.
+    pub const fn get_ref(&self) -> &RedBlackTree<K, V> {
+        &self.map
+    }
+
+    /// Drops the value index and returns the key-ordered tree.
+    pub fn into_inner(self) -> RedBlackTree<K, V> {
+        self.map
+    }
+}
+
+const fn entry<K, V>(e: &ByValue<K, V>) -> Option<(&K, &V)> {
+    match e {
+        ByValue::Entry(v, k) => Some((k, v)),
+        _ => None,
+    }
+}
+
+impl<K: Ord + Clone, V: Ord + Clone> Default for IndexedMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone, V: Ord + Clone> Map<K, V> for IndexedMap<K, V> {
+    fn size(&self) -> usize {
+        self.map.size()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        if let Some(old) = self.map.get(&key) {
+            let old = ByValue::Entry(old.clone(), key.clone());
+            self.index.delete(&old);
+        }
+        self.map.put(key.clone(), value.clone());
+        self.index.put(ByValue::Entry(value, key), ());
+    }
+}
+
+impl<K: Ord + Clone, V: Ord + Clone> OrderedMap<K, V> for IndexedMap<K, V> {
+    fn min(&self) -> Option<&K> {
+        self.map.min()
+    }
+
+    fn max(&self) -> Option<&K> {
+        self.map.max()
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        self.map.floor(key)
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        self.map.ceiling(key)
+    }
+
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.smallest(n)
+    }
+
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.largest(n)
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.range(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IndexedMap;
+    use crate::{Map, OrderedMap};
+    use std::collections::BTreeMap;
+    use std::ops::Bound;
+
+    #[test]
+    fn test_index_follows_puts_and_removes() {
+        let mut map: IndexedMap<u32, u32> = IndexedMap::new();
+        let mut model: BTreeMap<u32, u32> = BTreeMap::new();
+        let mut x = 7_u32;
+        for step in 0_u32..2000 {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let (k, v) = ((x >> 8) % 300, (x >> 20) % 50);
+            if step % 3_u32 == 0_u32 {
+                assert_eq!(map.remove(&k), model.remove(&k));
+            } else {
+                map.put(k, v);
+                model.insert(k, v);
+            }
+        }
+        assert_eq!(map.size(), model.len());
+        assert!(map.range(..).eq(model.iter()));
+
+        let mut by_value: Vec<(&u32, &u32)> = model.iter().collect();
+        by_value.sort_by_key(|(k, v)| (**v, **k));
+        assert_eq!(map.range_by_value(..).as_slice(), by_value.as_slice());
+        assert_eq!(map.min_by_value(), by_value.first().copied());
+        assert_eq!(map.max_by_value(), by_value.last().copied());
+        for (lo, hi) in [(0_u32, 0_u32), (10, 20), (49, 60)] {
+            let expected: Vec<_> = by_value
+                .iter()
+                .filter(|(_, v)| (lo..=hi).contains(*v))
+                .copied()
+                .collect();
+            assert_eq!(map.range_by_value(lo..=hi).as_slice(), expected.as_slice());
+            let bounds = (Bound::Excluded(lo), Bound::Excluded(hi));
+            let expected: Vec<_> = by_value
+                .iter()
+                .filter(|(_, v)| **v > lo && **v < hi)
+                .copied()
+                .collect();
+            assert_eq!(map.range_by_value(bounds).as_slice(), expected.as_slice());
+        }
+        assert!(map.into_inner().range(..).eq(model.iter()));
+        assert_eq!(IndexedMap::<u32, u32>::new().min_by_value(), None);
+    }
+}
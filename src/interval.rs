@@ -0,0 +1,312 @@
+use crate::rbtree::RedBlackTree;
+use crate::{Conflict, Map, OrderedMap};
+use std::ops::Range;
+
+/// A map from non-overlapping key ranges to values.
+///
+/// Ranges are half open, `start..end`. Inserting a range overwrites the
+/// parts of older ranges it covers, splitting them around it, and merges
+/// with neighbors it touches when they hold an equal value, so adjacent
+/// ranges always differ. Fits IP ranges, tiering rules or calendar slots.
+///
+/// Spans are kept in a `RedBlackTree` by start, a lookup is a `floor`.
+///
+/// # Examples
+///
+/// ```
+/// use treers::interval::IntervalMap;
+///
+/// let mut tiers = IntervalMap::new();
+/// tiers.insert(0_u32..100, "free");
+/// tiers.insert(100..1000, "pro");
+/// tiers.insert(50..150, "trial");
+///
+/// assert_eq!(tiers.get(&10), Some(&"free"));
+/// assert_eq!(tiers.get(&120), Some(&"trial"));
+/// assert_eq!(tiers.get(&150), Some(&"pro"));
+/// assert_eq!(tiers.get(&1000), None);
+/// assert_eq!(
+///     tiers.iter().collect::<Vec<_>>(),
+///     [(0..50, &"free"), (50..150, &"trial"), (150..1000, &"pro")]
+/// );
+///
+/// // equal neighbors merge
+/// tiers.insert(40..60, "free");
+/// tiers.insert(60..150, "free");
+/// assert_eq!(tiers.size(), 2_usize);
+/// assert_eq!(tiers.iter().next(), Some((0..150, &"free")));
+/// ```
+#[derive(Debug, Clone)]
+pub struct IntervalMap<K: Ord + Clone, V: Clone> {
+    // End and value of every span, by start
+    spans: RedBlackTree<K, (K, V)>,
+}
+
+impl<K: Ord + Clone, V: Clone + PartialEq> IntervalMap<K, V> {
+    /// Inits an empty interval map.
+    pub const fn new() -> Self {
+        Self {
+            spans: RedBlackTree::new(),
+        }
+    }
+
+    /// Returns a count of ranges, neighbors with equal values merged.
+    pub fn size(&self) -> usize {
+        self.spans.size()
+    }
+
+    /// Returns `true` if no key is mapped.
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Returns the value of the range holding `point`.
+    pub fn get(&self, point: &K) -> Option<&V> {
+        self.span_of(point).map(|(_, v)| v)
+    }
+
+    /// Returns the range holding `point` and its value.
+    pub fn span_of(&self, point: &K) -> Option<(Range<K>, &V)> {
+        let start = self.spans.floor(point)?;
+        let (end, v) = self.spans.get(start)?;
+        if point.lt(end) {
+            Some((start.clone()..end.clone(), v))
+        } else {
+            None
+        }
+    }
+
+    /// Maps every key of `range` to `value`, an empty range is ignored.
+    pub fn insert(&mut self, range: Range<K>, value: V) {
+        if range.start.lt(&range.end) {
+            let Range { start, end } = self.carve(range, Some(&value));
+            self.spans.put(start, (end, value));
+        }
+    }
+
+    /// Unmaps every key of `range`, splitting ranges sticking out of it.
+    pub fn remove(&mut self, range: Range<K>) {
+        if range.start.lt(&range.end) {
+            self.carve(range, None);
+        }
+    }
+
+    /// Maps `range` to `value` only if no key of it is mapped yet, as a
+    /// reservation of a calendar slot.
+    ///
+    /// Fails with the first range in the way, the map is left as it was.
+    /// An empty range is always free. Touching bookings with an equal value
+    /// merge as `insert` does.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::interval::IntervalMap;
+    ///
+    /// let mut room = IntervalMap::new();
+    /// assert!(room.book(9_u32..10, "ana").is_ok());
+    /// assert!(room.book(10..12, "bo").is_ok());
+    /// assert_eq!(room.book(11..13, "cy").unwrap_err().booked(), &(10..12));
+    ///
+    /// assert!(room.cancel(10..12));
+    /// assert!(!room.cancel(10..12));
+    /// assert!(room.book(11..13, "cy").is_ok());
+    /// ```
+    pub fn book(&mut self, range: Range<K>, value: V) -> Result<(), Conflict<K>> {
+        if let Some(booked) = self.first_overlap(&range) {
+            return Err(Conflict::new(booked));
+        }
+        self.insert(range, value);
+        Ok(())
+    }
+
+    /// Frees `range` if every key of it is booked, returning `true` then.
+    ///
+    /// A range partly free is left as it is, so only booked time is
+    /// cancelled. Bookings sticking out of `range` keep their other keys.
+    pub fn cancel(&mut self, range: Range<K>) -> bool {
+        if range.start.ge(&range.end) {
+            return false;
+        }
+        let mut at = range.start.clone();
+        while at.lt(&range.end) {
+            match self.span_of(&at) {
+                Some((span, _)) => at = span.end,
+                None => return false,
+            }
+        }
+        self.remove(range);
+        true
+    }
+
+    /// Returns the ranges and their values in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (Range<K>, &V)> {
+        self.spans
+            .range(..)
+            .map(|(start, (end, v))| (start.clone()..end.clone(), v))
+    }
+
+    // Range of the first span sharing a key with `range`
+    fn first_overlap(&self, range: &Range<K>) -> Option<Range<K>> {
+        if range.start.ge(&range.end) {
+            return None;
+        }
+        if let Some((span, _)) = self.span_of(&range.start) {
+            return Some(span);
+        }
+        let start = self.spans.ceiling(&range.start)?;
+        let (end, _) = self.spans.get(start)?;
+        if start.lt(&range.end) {
+            Some(start.clone()..end.clone())
+        } else {
+            None
+        }
+    }
+
+    // Takes out the spans overlapping `range` and puts back their parts
+    // outside it. Parts and neighbors touching it with a value equal to
+    // `merge` are dropped instead, the returned range covers them.
+    fn carve(&mut self, range: Range<K>, merge: Option<&V>) -> Range<K> {
+        let Range { mut start, mut end } = range;
+        let mut touching: Vec<(K, bool)> = Vec::new();
+        {
+            let mut candidates = Vec::new();
+            candidates.extend(self.spans.lower(&start));
+            candidates.extend(
+                self.spans
+                    .range(start.clone()..=end.clone())
+                    .map(|(k, _)| k),
+            );
+            for k in candidates {
+                if let Some((span_end, v)) = self.spans.get(k) {
+                    let same = merge.is_some_and(|m| m.eq(v));
+                    let overlaps = k.lt(&end) && span_end.gt(&start);
+                    let touches = k.eq(&end) || span_end.eq(&start);
+                    if overlaps || (same && touches) {
+                        touching.push((k.clone(), same));
+                    }
+                }
+            }
+        }
+        for (k, same) in touching {
+            if let Some((span_end, v)) = self.spans.delete(&k) {
+                if k.lt(&start) {
+                    if same {
+                        start = k;
+                    } else {
+                        self.spans.put(k, (start.clone(), v.clone()));
+                    }
+                }
+                if span_end.gt(&end) {
+                    if same {
+                        end = span_end;
+                    } else {
+                        self.spans.put(end.clone(), (span_end, v));
+                    }
+                }
+            }
+        }
+        start..end
+    }
+}
+
+impl<K: Ord + Clone, V: Clone + PartialEq> Default for IntervalMap<K, V> {
+    /// Creates an empty `IntervalMap<K, V>`.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntervalMap;
+    use crate::bench_support::shuffled_keys;
+
+    #[test]
+    fn test_matches_model() {
+        let mut map = IntervalMap::new();
+        let mut model = [None; 200];
+        let keys = shuffled_keys(600, 11);
+        for (i, pair) in keys.chunks(2).enumerate() {
+            let (a, b) = ((pair[0] % 200) as usize, (pair[1] % 200) as usize);
+            let (start, end) = (a.min(b), a.max(b));
+            // few values, so neighbors merge often
+            let value = (pair[0] % 3_u64) as u8;
+            if i % 5_usize == 4_usize {
+                map.remove(start..end);
+                model[start..end].fill(None);
+            } else {
+                map.insert(start..end, value);
+                model[start..end].fill(Some(value));
+            }
+            for (point, expected) in model.iter().enumerate() {
+                assert_eq!(map.get(&point), expected.as_ref());
+            }
+            // ranges stay sorted, and equal values never touch
+            let spans: Vec<_> = map.iter().collect();
+            for w in spans.windows(2) {
+                assert!(w[0].0.end.le(&w[1].0.start));
+                assert!(w[0].0.end.lt(&w[1].0.start) || w[0].1.ne(w[1].1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_and_span_of() {
+        let mut map = IntervalMap::new();
+        map.insert(0_i32..10, 'a');
+        map.insert(3..5, 'b');
+        assert_eq!(map.size(), 3_usize);
+        assert_eq!(map.span_of(&2), Some((0..3, &'a')));
+        assert_eq!(map.span_of(&4), Some((3..5, &'b')));
+        assert_eq!(map.span_of(&5), Some((5..10, &'a')));
+
+        map.insert(3..5, 'a');
+        assert_eq!(map.size(), 1_usize);
+        map.insert(7..7, 'c');
+        map.remove(9..20);
+        map.remove(-5..1);
+        assert_eq!(map.span_of(&5), Some((1..9, &'a')));
+        assert_eq!((map.get(&0), map.get(&9)), (None, None));
+        map.remove(0..9);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_book_and_cancel() {
+        let mut calendar = IntervalMap::new();
+        let mut model = [None; 100];
+        let keys = shuffled_keys(400, 14);
+        for (i, pair) in keys.chunks(2).enumerate() {
+            let start = (pair[0] % 100) as usize;
+            let end = (start + (pair[1] % 8) as usize).min(100_usize);
+            let free = model[start..end].iter().all(Option::is_none);
+            if i % 3_usize == 2_usize {
+                let booked = start.lt(&end) && model[start..end].iter().all(Option::is_some);
+                assert_eq!(calendar.cancel(start..end), booked);
+                if booked {
+                    model[start..end].fill(None);
+                }
+            } else {
+                match calendar.book(start..end, i) {
+                    Ok(()) => {
+                        assert!(free);
+                        model[start..end].fill(Some(i));
+                    }
+                    Err(conflict) => {
+                        let booked = conflict.booked().clone();
+                        assert!(!free && booked.start.lt(&end) && start.lt(&booked.end));
+                        assert!(model[start..booked.start.max(start)]
+                            .iter()
+                            .all(Option::is_none));
+                    }
+                }
+            }
+            for (point, expected) in model.iter().enumerate() {
+                assert_eq!(calendar.get(&point), expected.as_ref());
+            }
+        }
+    }
+}
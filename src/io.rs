@@ -0,0 +1,346 @@
+use crate::{Map, OrderedMap};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::convert::Infallible;
+use std::fmt;
+use std::io::{BufRead, Read, Write};
+
+/// Failure of `read_csv` or `read_jsonl`, all but `Io` with the 1-based
+/// line of the record in the source.
+#[derive(Debug)]
+pub enum ImportError<E> {
+    Io(std::io::Error),
+    Csv {
+        line: usize,
+        error: csv::Error,
+    },
+    Json {
+        line: usize,
+        error: serde_json::Error,
+    },
+    Convert {
+        line: usize,
+        error: E,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for ImportError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "read failed: {}", e),
+            ImportError::Csv { line, error } => write!(f, "line {}: invalid csv: {}", line, error),
+            ImportError::Json { line, error } => {
+                write!(f, "line {}: invalid json: {}", line, error)
+            }
+            ImportError::Convert { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ImportError<E> {}
+
+/// Conversion hook keeping records as they are.
+pub const fn as_pair<K, V>(pair: (K, V)) -> Result<(K, V), Infallible> {
+    Ok(pair)
+}
+
+/// Streams CSV records into `map`, returning a count of records read.
+///
+/// Every record is deserialized into `T` and turned into a key-value pair
+/// by `convert`. Headers, delimiters etc. are set up on the `csv::Reader`.
+///
+/// # Examples
+///
+/// ```
+/// use treers::io::{as_pair, read_csv};
+/// use treers::rbtree::RedBlackTree;
+/// use treers::Map;
+///
+/// let data = "id,name\n2,b\n1,a\n";
+/// let mut reader = csv::Reader::from_reader(data.as_bytes());
+/// let mut rbtree: RedBlackTree<u32, String> = RedBlackTree::new();
+/// assert_eq!(read_csv(&mut reader, &mut rbtree, as_pair).unwrap(), 2_usize);
+/// assert_eq!(rbtree.get(&1), Some(&String::from("a")));
+///
+/// // Convert records on the way in
+/// let mut reader = csv::Reader::from_reader(data.as_bytes());
+/// let mut upper: RedBlackTree<u32, String> = RedBlackTree::new();
+/// read_csv(&mut reader, &mut upper, |(id, name): (u32, String)| {
+///     Ok::<_, String>((id * 10, name.to_uppercase()))
+/// })
+/// .unwrap();
+/// assert_eq!(upper.get(&20), Some(&String::from("B")));
+/// ```
+pub fn read_csv<K, V, M, T, E, R, F>(
+    reader: &mut csv::Reader<R>,
+    map: &mut M,
+    mut convert: F,
+) -> Result<usize, ImportError<E>>
+where
+    M: Map<K, V>,
+    T: DeserializeOwned,
+    R: Read,
+    F: FnMut(T) -> Result<(K, V), E>,
+{
+    let headers = if reader.has_headers() {
+        Some(reader.headers().map_err(|e| csv_error(e, 1_u64))?.clone())
+    } else {
+        None
+    };
+    let mut raw = csv::StringRecord::new();
+    let mut count = 0_usize;
+    while reader
+        .read_record(&mut raw)
+        .map_err(|e| csv_error(e, reader.position().line()))?
+    {
+        // the line the record starts on, past headers and quoted newlines
+        let line = raw.position().map_or(0_u64, csv::Position::line) as usize;
+        let record: T = raw
+            .deserialize(headers.as_ref())
+            .map_err(|error| ImportError::Csv { line, error })?;
+        count += 1_usize;
+        let (k, v) = convert(record).map_err(|error| ImportError::Convert { line, error })?;
+        map.put(k, v);
+    }
+    Ok(count)
+}
+
+// A failure at the line of `error`, at `line` if it has none
+fn csv_error<E>(error: csv::Error, line: u64) -> ImportError<E> {
+    let line = error.position().map_or(line, csv::Position::line) as usize;
+    ImportError::Csv { line, error }
+}
+
+/// Streams JSON lines into `map`, returning a count of records read.
+///
+/// Every non-blank line is deserialized into `T`, e.g. `[key, value]`
+/// arrays as written by `write_jsonl`, and turned into a key-value pair
+/// by `convert`.
+///
+/// # Examples
+///
+/// ```
+/// use treers::btree::BalancedTree;
+/// use treers::io::{as_pair, read_jsonl};
+/// use treers::Map;
+///
+/// let data = "[\"b\", 2]\n\n[\"a\", 1]\n";
+/// let mut btree: BalancedTree<String, i32> = BalancedTree::new();
+/// assert_eq!(read_jsonl(data.as_bytes(), &mut btree, as_pair).unwrap(), 2_usize);
+/// assert_eq!(btree.get(&String::from("b")), Some(&2));
+/// ```
+pub fn read_jsonl<K, V, M, T, E, R, F>(
+    reader: R,
+    map: &mut M,
+    mut convert: F,
+) -> Result<usize, ImportError<E>>
+where
+    M: Map<K, V>,
+    T: DeserializeOwned,
+    R: BufRead,
+    F: FnMut(T) -> Result<(K, V), E>,
+{
+    let mut count = 0_usize;
+    for (line, text) in (1_usize..).zip(reader.lines()) {
+        let text = text.map_err(ImportError::Io)?;
+        if text.trim().is_empty() {
+            continue;
+        }
+        let record: T =
+            serde_json::from_str(&text).map_err(|error| ImportError::Json { line, error })?;
+        let (k, v) = convert(record).map_err(|error| ImportError::Convert { line, error })?;
+        map.put(k, v);
+        count += 1_usize;
+    }
+    Ok(count)
+}
+
+/// Writes entries of `map` as CSV records, in key order.
+///
+/// # Examples
+///
+/// ```
+/// use treers::bst::BST;
+/// use treers::io::write_csv;
+/// use treers::Map;
+///
+/// let mut bst: BST<u32, &str> = BST::new();
+/// bst.put(2, "b");
+/// bst.put(1, "a, quoted");
+///
+/// let mut writer = csv::Writer::from_writer(Vec::new());
+/// write_csv(&bst, &mut writer).unwrap();
+/// let data = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+/// assert_eq!(data, "1,\"a, quoted\"\n2,b\n");
+/// ```
+pub fn write_csv<K, V, M, W>(map: &M, writer: &mut csv::Writer<W>) -> Result<(), csv::Error>
+where
+    K: Ord + Serialize,
+    V: Serialize,
+    M: OrderedMap<K, V>,
+    W: Write,
+{
+    for entry in map.range(..) {
+        writer.serialize(entry)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes entries of `map` as `[key, value]` JSON lines, in key order.
+///
+/// # Examples
+///
+/// ```
+/// use treers::io::write_jsonl;
+/// use treers::rbtree::RedBlackTree;
+/// use treers::Map;
+///
+/// let mut rbtree: RedBlackTree<&str, u32> = RedBlackTree::new();
+/// rbtree.put("b", 2);
+/// rbtree.put("a", 1);
+///
+/// let mut out = Vec::new();
+/// write_jsonl(&rbtree, &mut out).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "[\"a\",1]\n[\"b\",2]\n");
+/// ```
+pub fn write_jsonl<K, V, M, W>(map: &M, mut writer: W) -> std::io::Result<()>
+where
+    K: Ord + Serialize,
+    V: Serialize,
+    M: OrderedMap<K, V>,
+    W: Write,
+{
+    for entry in map.range(..) {
+        serde_json::to_writer(&mut writer, &entry)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::{as_pair, read_csv, read_jsonl, write_csv, write_jsonl, ImportError};
+    use crate::bst::BST;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use crate::{dump, Map, OrderedMap};
+
+    fn filled<M: Map<u32, String> + Default>() -> M {
+        let mut map = M::default();
+        for i in [5_u32, 3, 8, 1, 4, 7, 9, 2, 6] {
+            map.put(i, format!("v,\"{}\"", i));
+        }
+        map
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let rbtree: RedBlackTree<u32, String> = filled();
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(Vec::new());
+        write_csv(&rbtree, &mut writer).unwrap();
+        let data = writer.into_inner().unwrap();
+
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_slice());
+        let mut btree: BalancedTree<u32, String> = BalancedTree::new();
+        assert_eq!(read_csv(&mut reader, &mut btree, as_pair).unwrap(), 9_usize);
+        assert!(btree.range(..).eq(rbtree.range(..)));
+    }
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        let btree: BalancedTree<u32, String> = filled();
+        let mut out = Vec::new();
+        write_jsonl(&btree, &mut out).unwrap();
+
+        let mut bst: BST<u32, String> = BST::new();
+        assert_eq!(
+            read_jsonl(out.as_slice(), &mut bst, as_pair).unwrap(),
+            9_usize
+        );
+        assert!(bst.range(..).eq(btree.range(..)));
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let mut bst: BST<u32, u32> = BST::new();
+        let err = read_jsonl("[1, 1]\n[2, \"x\"]\n".as_bytes(), &mut bst, as_pair).unwrap_err();
+        assert!(matches!(err, ImportError::Json { line: 2, .. }));
+
+        let err = read_jsonl(
+            "[1, 1]\n\n[0, 3]\n".as_bytes(),
+            &mut bst,
+            |(k, v): (u32, u32)| {
+                if k.eq(&0_u32) {
+                    Err("zero key")
+                } else {
+                    Ok((k, v))
+                }
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ImportError::Convert {
+                line: 3,
+                error: "zero key"
+            }
+        ));
+        assert_eq!(err.to_string(), "line 3: zero key");
+
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader("1,2\nx,3\n".as_bytes());
+        let err = read_csv(&mut reader, &mut bst, as_pair).unwrap_err();
+        assert!(matches!(err, ImportError::Csv { line: 2, .. }));
+        assert!(err.to_string().starts_with("line 2: invalid csv: "));
+        assert_eq!(bst.size(), 1_usize);
+
+        // lines count the header and quoted newlines, not records
+        let mut reader = csv::Reader::from_reader("id,name\n1,\"a\nb\"\n0,c\n".as_bytes());
+        let mut named: BST<u32, String> = BST::new();
+        let err = read_csv(&mut reader, &mut named, |(k, v): (u32, String)| {
+            if k.eq(&0_u32) {
+                Err("zero key")
+            } else {
+                Ok((k, v))
+            }
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "line 4: zero key");
+        assert_eq!(named.get(&1), Some(&String::from("a\nb")));
+    }
+
+    #[test]
+    fn test_write_in_order() {
+        let bst: BST<u32, String> = filled();
+        let rbtree: RedBlackTree<u32, String> = filled();
+        let btree: BalancedTree<u32, String> = filled();
+        let mut expected = Vec::new();
+        write_jsonl(&bst, &mut expected).unwrap();
+        let mut outs = [Vec::new(), Vec::new(), Vec::new()];
+        bst.write_in_order(&mut outs[0], dump::jsonl).unwrap();
+        rbtree.write_in_order(&mut outs[1], dump::jsonl).unwrap();
+        btree.write_in_order(&mut outs[2], dump::jsonl).unwrap();
+        assert!(outs.iter().all(|out| out.eq(&expected)));
+
+        // an error of the writer stops the walk
+        let mut seen = Vec::new();
+        let err = btree
+            .write_in_order(std::io::sink(), |_, k, _| {
+                seen.push(*k);
+                if k.eq(&3_u32) {
+                    Err(std::io::Error::other("disk full"))
+                } else {
+                    Ok(())
+                }
+            })
+            .unwrap_err();
+        assert_eq!(err.to_string(), "disk full");
+        assert_eq!(seen, [1, 2, 3]);
+    }
+}
@@ -0,0 +1,302 @@
+use crate::rbtree::RedBlackTree;
+use crate::{Map, ParseCidrError};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/// A block of addresses, a network address and a prefix length.
+///
+/// Host bits beyond the prefix are cleared, so `10.1.2.3/8` is `10.0.0.0/8`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use std::net::Ipv4Addr;
+/// use treers::ip::Cidr;
+///
+/// let cidr: Cidr = "10.1.2.3/8".parse().unwrap();
+/// assert_eq!(cidr.to_string(), "10.0.0.0/8");
+/// assert_eq!(cidr.prefix_len(), 8_u8);
+/// assert!(cidr.contains(Ipv4Addr::new(10, 200, 0, 1)));
+/// assert_eq!(Cidr::new(Ipv4Addr::LOCALHOST, 33), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Inits the block of the first `prefix_len` bits of `addr`, `None` if
+    /// the address has fewer bits.
+    pub fn new<A: Into<IpAddr>>(addr: A, prefix_len: u8) -> Option<Self> {
+        let addr = addr.into();
+        let width = width(&addr);
+        if prefix_len.gt(&width) {
+            return None;
+        }
+        let shift = u32::from(width - prefix_len);
+        let network = to_bits(&addr).checked_shr(shift).unwrap_or(0_u128);
+        Some(Self {
+            addr: from_bits(&addr, network.checked_shl(shift).unwrap_or(0_u128)),
+            prefix_len,
+        })
+    }
+
+    /// Returns the network address, host bits cleared.
+    pub const fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// Returns the count of leading bits fixed by the block.
+    pub const fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Returns `true` if `addr` is of the same family and inside the block.
+    pub fn contains<A: Into<IpAddr>>(&self, addr: A) -> bool {
+        let addr = addr.into();
+        addr.is_ipv4().eq(&self.addr.is_ipv4())
+            && prefix(&addr, self.prefix_len).eq(&prefix(&self.addr, self.prefix_len))
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = ParseCidrError;
+
+    /// Reads `address/prefix_len`, an address alone is a block of one.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| ParseCidrError::new("an address"))?;
+        let len = match len {
+            Some(len) => len
+                .parse()
+                .map_err(|_| ParseCidrError::new("a prefix length"))?,
+            None => width(&addr),
+        };
+        Self::new(addr, len)
+            .ok_or_else(|| ParseCidrError::new("a prefix length within the address"))
+    }
+}
+
+/// A routing table, finding the longest prefix matching an address.
+///
+/// Blocks of every prefix length are kept in their own `RedBlackTree`,
+/// keyed by the leading bits, so a lookup tries each length that holds
+/// routes, longest first, one search each. IPv4 and IPv6 blocks never
+/// match addresses of the other family. The crate has no crit-bit or
+/// radix tree, the per length trees stand in for one.
+///
+/// # Examples
+///
+/// ```
+/// use std::net::{Ipv4Addr, Ipv6Addr};
+/// use treers::ip::IpTable;
+///
+/// let mut table = IpTable::new();
+/// table.insert("0.0.0.0/0".parse().unwrap(), "default");
+/// table.insert("10.0.0.0/8".parse().unwrap(), "corp");
+/// table.insert("10.20.0.0/16".parse().unwrap(), "lab");
+/// table.insert("2001:db8::/32".parse().unwrap(), "docs");
+///
+/// assert_eq!(table.lookup(Ipv4Addr::new(10, 20, 3, 4)), Some(&"lab"));
+/// assert_eq!(table.lookup(Ipv4Addr::new(10, 21, 3, 4)), Some(&"corp"));
+/// assert_eq!(table.lookup(Ipv4Addr::new(8, 8, 8, 8)), Some(&"default"));
+///
+/// let (cidr, route) = table.longest_match(Ipv4Addr::new(10, 20, 0, 1)).unwrap();
+/// assert_eq!((cidr.to_string(), *route), (String::from("10.20.0.0/16"), "lab"));
+///
+/// let docs: Ipv6Addr = "2001:db8::1".parse().unwrap();
+/// assert_eq!(table.lookup(docs), Some(&"docs"));
+/// assert_eq!(table.lookup(Ipv6Addr::LOCALHOST), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct IpTable<R: Clone> {
+    // Routes by prefix length, then by the leading bits of the block
+    v4: Vec<RedBlackTree<u128, R>>,
+    v6: Vec<RedBlackTree<u128, R>>,
+    size: usize,
+}
+
+impl<R: Clone> IpTable<R> {
+    /// Inits an empty routing table.
+    pub fn new() -> Self {
+        Self {
+            v4: vec![RedBlackTree::new(); 33_usize],
+            v6: vec![RedBlackTree::new(); 129_usize],
+            size: 0_usize,
+        }
+    }
+
+    /// Returns a count of routes.
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns `true` if the table holds no routes.
+    pub const fn is_empty(&self) -> bool {
+        self.size == 0_usize
+    }
+
+    /// Routes the block `cidr` to `route`, replacing an older route of the
+    /// same block.
+    pub fn insert(&mut self, cidr: Cidr, route: R) {
+        let key = prefix(&cidr.addr, cidr.prefix_len);
+        let routes = &mut self.family_mut(&cidr.addr)[usize::from(cidr.prefix_len)];
+        let added = !routes.contains(&key);
+        routes.put(key, route);
+        if added {
+            self.size += 1_usize;
+        }
+    }
+
+    /// Returns the route of exactly the block `cidr`.
+    pub fn get(&self, cidr: &Cidr) -> Option<&R> {
+        self.family(&cidr.addr)[usize::from(cidr.prefix_len)]
+            .get(&prefix(&cidr.addr, cidr.prefix_len))
+    }
+
+    /// Returns the route of the longest block holding `addr`.
+    pub fn lookup<A: Into<IpAddr>>(&self, addr: A) -> Option<&R> {
+        self.longest_match(addr).map(|(_, route)| route)
+    }
+
+    /// Returns the longest block holding `addr` and its route.
+    pub fn longest_match<A: Into<IpAddr>>(&self, addr: A) -> Option<(Cidr, &R)> {
+        let addr = addr.into();
+        self.family(&addr)
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, routes)| !routes.is_empty())
+            .find_map(|(len, routes)| {
+                // lengths are at most 128
+                let len = len as u8;
+                let route = routes.get(&prefix(&addr, len))?;
+                Cidr::new(addr, len).map(|cidr| (cidr, route))
+            })
+    }
+
+    fn family(&self, addr: &IpAddr) -> &[RedBlackTree<u128, R>] {
+        match addr {
+            IpAddr::V4(_) => &self.v4,
+            IpAddr::V6(_) => &self.v6,
+        }
+    }
+
+    fn family_mut(&mut self, addr: &IpAddr) -> &mut [RedBlackTree<u128, R>] {
+        match addr {
+            IpAddr::V4(_) => &mut self.v4,
+            IpAddr::V6(_) => &mut self.v6,
+        }
+    }
+}
+
+impl<R: Clone> Default for IpTable<R> {
+    /// Creates an empty `IpTable<R>`.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const fn width(addr: &IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32_u8,
+        IpAddr::V6(_) => 128_u8,
+    }
+}
+
+fn to_bits(addr: &IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(a) => u128::from(u32::from(*a)),
+        IpAddr::V6(a) => u128::from(*a),
+    }
+}
+
+fn from_bits(family: &IpAddr, bits: u128) -> IpAddr {
+    match family {
+        // v4 bits fit into the low 32
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(bits as u32)),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(bits)),
+    }
+}
+
+// Leading `len` bits of `addr`, the key of its block of that length
+fn prefix(addr: &IpAddr, len: u8) -> u128 {
+    to_bits(addr)
+        .checked_shr(u32::from(width(addr) - len))
+        .unwrap_or(0_u128)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cidr, IpTable};
+    use crate::bench_support::shuffled_keys;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn test_longest_prefix_against_scan() {
+        let mut table = IpTable::new();
+        let mut blocks = Vec::new();
+        let keys = shuffled_keys(400, 9);
+        for (i, pair) in keys.chunks(2).enumerate() {
+            // few distinct leading bytes, so blocks nest
+            let addr = Ipv4Addr::from(((pair[0] % 4_u64) as u32) << 30 | pair[1] as u32);
+            let cidr = Cidr::new(addr, (pair[1] % 33_u64) as u8).unwrap();
+            table.insert(cidr, i);
+            blocks.retain(|(c, _)| c != &cidr);
+            blocks.push((cidr, i));
+        }
+        assert_eq!(table.size(), blocks.len());
+        for k in shuffled_keys(500, 10) {
+            let addr = Ipv4Addr::from(((k % 4_u64) as u32) << 30 | (k as u32) >> 3);
+            let expected = blocks
+                .iter()
+                .filter(|(c, _)| c.contains(addr))
+                .max_by_key(|(c, _)| c.prefix_len());
+            assert_eq!(table.longest_match(addr), expected.map(|(c, r)| (*c, r)));
+        }
+    }
+
+    #[test]
+    fn test_families_and_parse() {
+        let mut table = IpTable::new();
+        table.insert("::/0".parse().unwrap(), 6_u8);
+        assert_eq!(table.lookup(Ipv4Addr::LOCALHOST), None);
+        assert_eq!(table.lookup(Ipv6Addr::LOCALHOST), Some(&6));
+        table.insert("127.0.0.1".parse().unwrap(), 4);
+        assert_eq!(table.lookup(IpAddr::from([127, 0, 0, 1])), Some(&4));
+        assert_eq!(table.lookup(IpAddr::from([127, 0, 0, 2])), None);
+        table.insert("127.0.0.1/32".parse().unwrap(), 5);
+        assert_eq!(table.size(), 2_usize);
+        assert_eq!(table.get(&"::/0".parse().unwrap()), Some(&6));
+
+        for (text, expected) in [
+            ("10.0.0.0/", "a prefix length"),
+            ("10.0.0/8", "an address"),
+            ("::1/129", "a prefix length within the address"),
+        ] {
+            let err = text.parse::<Cidr>().unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("invalid CIDR block, expected {}", expected)
+            );
+        }
+        let full: Cidr = "ffff::1/128".parse().unwrap();
+        assert_eq!(full.to_string(), "ffff::1/128");
+        assert!(!full.contains(Ipv4Addr::LOCALHOST));
+    }
+}
@@ -0,0 +1,238 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Longest key kept inline, without a heap allocation
+pub const INLINE_CAPACITY: usize = 22_usize;
+
+/// A string key stored inline when it's short, on the heap otherwise.
+///
+/// Keys of up to `INLINE_CAPACITY` bytes are kept in the wrapper itself,
+/// so comparing them while walking any of the trees reads the node and
+/// no extra allocation. Longer keys spill into a `Box<str>`. Either way
+/// the wrapper takes as much space as a `String`, and keys order the same
+/// as the strings they hold.
+///
+/// # Examples
+///
+/// ```
+/// use treers::key::SmallKey;
+/// use treers::rbtree::RedBlackTree;
+/// use treers::{Map, OrderedMap};
+///
+/// let mut rbt: RedBlackTree<SmallKey, u32> = RedBlackTree::new();
+/// rbt.put(SmallKey::from("apple"), 1);
+/// rbt.put(SmallKey::from("a rather long key spilling to the heap"), 2);
+/// rbt.put(SmallKey::from("banana"), 3);
+///
+/// assert_eq!(rbt.get(&SmallKey::from("banana")), Some(&3));
+/// assert_eq!(rbt.min().map(SmallKey::as_str), Some("a rather long key spilling to the heap"));
+/// assert!(SmallKey::from("apple").is_inline());
+/// ```
+#[derive(Clone)]
+pub struct SmallKey(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    Heap(Box<str>),
+}
+
+impl SmallKey {
+    /// Inits an empty key.
+    pub const fn new() -> Self {
+        SmallKey(Repr::Inline {
+            len: 0_u8,
+            bytes: [0_u8; INLINE_CAPACITY],
+        })
+    }
+
+    /// Returns bytes of the key.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self.0 {
+            Repr::Inline { len, ref bytes } => &bytes[..len as usize],
+            Repr::Heap(ref s) => s.as_bytes(),
+        }
+    }
+
+    /// Returns the key as a string slice.
+    pub fn as_str(&self) -> &str {
+        match self.0 {
+            // bytes are copied from a `str`, so always valid
+            Repr::Inline { len, ref bytes } => {
+                std::str::from_utf8(&bytes[..len as usize]).unwrap_or_default()
+            }
+            Repr::Heap(ref s) => s,
+        }
+    }
+
+    /// Returns the length of the key in bytes.
+    pub fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    /// Checks if the key is an empty string.
+    pub fn is_empty(&self) -> bool {
+        self.len().eq(&0_usize)
+    }
+
+    /// Checks if the key is kept inline.
+    pub const fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { len: _, bytes: _ })
+    }
+}
+
+impl Default for SmallKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&str> for SmallKey {
+    fn from(s: &str) -> Self {
+        if s.len().gt(&INLINE_CAPACITY) {
+            return SmallKey(Repr::Heap(s.into()));
+        }
+        let mut bytes = [0_u8; INLINE_CAPACITY];
+        bytes[..s.len()].copy_from_slice(s.as_bytes());
+        SmallKey(Repr::Inline {
+            len: s.len() as u8,
+            bytes,
+        })
+    }
+}
+
+impl From<String> for SmallKey {
+    fn from(s: String) -> Self {
+        if s.len().gt(&INLINE_CAPACITY) {
+            SmallKey(Repr::Heap(s.into_boxed_str()))
+        } else {
+            SmallKey::from(s.as_str())
+        }
+    }
+}
+
+impl From<SmallKey> for String {
+    fn from(key: SmallKey) -> Self {
+        match key.0 {
+            Repr::Heap(s) => s.into(),
+            Repr::Inline { len: _, bytes: _ } => key.as_str().to_string(),
+        }
+    }
+}
+
+// Compared as bytes, the same order as `str`
+impl Ord for SmallKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl PartialOrd for SmallKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SmallKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes().eq(other.as_bytes())
+    }
+}
+
+impl Eq for SmallKey {}
+
+impl Hash for SmallKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state);
+    }
+}
+
+impl fmt::Debug for SmallKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for SmallKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SmallKey, INLINE_CAPACITY};
+
+    #[test]
+    fn test_inline_and_heap() {
+        assert_eq!(
+            std::mem::size_of::<SmallKey>(),
+            std::mem::size_of::<String>()
+        );
+        let short = "ü".repeat(INLINE_CAPACITY / 2_usize);
+        let long = format!("{}!", short);
+        assert!(SmallKey::from(short.as_str()).is_inline());
+        assert!(!SmallKey::from(long.as_str()).is_inline());
+        assert!(!SmallKey::from(long.clone()).is_inline());
+        assert_eq!(SmallKey::from(short.as_str()).as_str(), short);
+        assert_eq!(String::from(SmallKey::from(long.clone())), long);
+        assert!(SmallKey::new().is_empty());
+        assert_eq!(SmallKey::default(), SmallKey::from(""));
+    }
+
+    #[test]
+    fn test_orders_as_str() {
+        let words = [
+            "",
+            "a",
+            "ab",
+            "abc",
+            "b",
+            "čaj",
+            "twenty two bytes long!",
+            "twenty two bytes long!!",
+            "twenty two bytes long?",
+            "zzzzzzzzzzzzzzzzzzzzzzzzzzzz",
+        ];
+        for a in &words {
+            for b in &words {
+                let (x, y) = (SmallKey::from(*a), SmallKey::from(*b));
+                assert_eq!(x.cmp(&y), a.cmp(b));
+                assert_eq!(x.eq(&y), a.eq(b));
+            }
+        }
+    }
+
+    #[cfg(all(feature = "bst", feature = "rbtree", feature = "btree"))]
+    #[test]
+    fn test_keys_in_trees() {
+        use crate::bst::BST;
+        use crate::btree::BalancedTree;
+        use crate::rbtree::RedBlackTree;
+        use crate::OrderedMap;
+        use std::collections::BTreeMap;
+
+        fn fill<M: OrderedMap<SmallKey, usize>>(mut map: M) {
+            let mut model = BTreeMap::new();
+            for i in 0_usize..500 {
+                let k = format!("{}", (i * 7919) % 500).repeat(i % 9 + 1_usize);
+                map.put(SmallKey::from(k.as_str()), i);
+                model.insert(k, i);
+            }
+            let found: Vec<(&str, &usize)> = map.range(..).map(|(k, v)| (k.as_str(), v)).collect();
+            let expected: Vec<(&str, &usize)> =
+                model.iter().map(|(k, v)| (k.as_str(), v)).collect();
+            assert_eq!(found, expected);
+            for (k, v) in &model {
+                assert_eq!(map.get(&SmallKey::from(k.clone())), Some(v));
+            }
+        }
+        fill(BST::new());
+        fill(RedBlackTree::new());
+        fill(BalancedTree::new());
+    }
+}
@@ -1,9 +1,11 @@
-#![forbid(dead_code, unsafe_code, unstable_features)]
+#![forbid(dead_code, unstable_features)]
+// `alloc-stats` wraps the system allocator, the only unsafe code allowed
+#![cfg_attr(not(feature = "alloc-stats"), forbid(unsafe_code))]
+#![cfg_attr(feature = "alloc-stats", deny(unsafe_code))]
 #![deny(
     clippy::missing_const_for_fn,
     clippy::redundant_pub_crate,
     future_incompatible,
-    private_in_public,
     single_use_lifetimes,
     trivial_casts,
     trivial_numeric_casts,
@@ -13,22 +15,1038 @@
 )]
 #![allow(clippy::use_self)]
 
+pub mod adapters;
+#[cfg(all(feature = "bst", feature = "rbtree"))]
+pub mod adaptive;
+#[cfg(feature = "alloc-stats")]
+pub mod alloc_stats;
+pub mod arena;
+#[cfg(feature = "rbtree")]
+pub mod batch;
+pub mod bench_support;
+#[cfg(feature = "bst")]
 pub mod bst;
+#[cfg(feature = "btree")]
 pub mod btree;
+#[cfg(feature = "btree")]
+pub mod compressed;
+#[cfg(feature = "btree")]
+pub mod concurrent;
+pub mod diff;
+pub mod dump;
+#[cfg(any(feature = "bst", feature = "rbtree"))]
+pub mod entry;
+mod error;
+pub mod expr;
+#[cfg(all(feature = "io", feature = "btree"))]
+pub mod external;
+pub mod filter;
+pub mod handle;
+pub mod huffman;
+pub mod implicit;
+#[cfg(feature = "rbtree")]
+pub mod indexed;
+#[cfg(feature = "rbtree")]
+pub mod interval;
+#[cfg(feature = "io")]
+pub mod io;
+#[cfg(feature = "rbtree")]
+pub mod ip;
+pub mod key;
+pub mod maxtree;
+pub mod mirror;
+pub mod node;
+pub mod observe;
+#[cfg(feature = "rbtree")]
+pub mod order;
+#[cfg(any(feature = "bst", feature = "rbtree"))]
+mod paren;
+pub mod pinned;
+#[cfg(any(feature = "bst", feature = "rbtree"))]
+mod pool;
+#[cfg(feature = "rbtree")]
+pub mod range2d;
+#[cfg(feature = "rbtree")]
 pub mod rbtree;
+pub mod record;
+pub mod rope;
+#[cfg(feature = "rbtree")]
+pub mod sort;
+#[cfg(all(feature = "io", feature = "rbtree", feature = "btree"))]
+pub mod spill;
+pub mod stats;
+pub mod suffix;
+#[cfg(feature = "loom")]
+pub mod testing;
+#[cfg(feature = "bst")]
+pub mod tombstone;
+pub mod tracked;
+#[cfg(any(feature = "bst", feature = "rbtree", feature = "btree"))]
+pub mod view;
+#[cfg(any(feature = "bst", feature = "rbtree", feature = "btree"))]
+pub mod weak;
+#[cfg(any(feature = "bst", feature = "rbtree"))]
+pub mod zipper;
 
-pub trait SedgewickMap<K: Ord, V> {
-    fn new() -> Self;
+#[cfg(any(feature = "bst", feature = "rbtree"))]
+pub use error::ParseTreeError;
+#[cfg(feature = "rbtree")]
+pub use error::{Conflict, ParseCidrError};
+pub use error::{DuplicateKeyError, Error, OrderingError, ParseScriptError};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+/// Base symbol table API: lookups and insertions by key.
+///
+/// No ordering of keys is assumed, so unordered backends can share it too.
+/// Maps are created with their inherent `new()` or `Default`, which keeps
+/// the trait object safe, so a backend can be picked at runtime.
+///
+/// # Examples
+///
+/// ```
+/// use treers::bst::BST;
+/// use treers::btree::BalancedTree;
+/// use treers::rbtree::RedBlackTree;
+/// use treers::Map;
+///
+/// fn backend(name: &str) -> Box<dyn Map<u32, &'static str>> {
+///     match name {
+///         "bst" => Box::new(BST::new()),
+///         "rbtree" => Box::new(RedBlackTree::new()),
+///         _ => Box::new(BalancedTree::new()),
+///     }
+/// }
+///
+/// for name in &["bst", "rbtree", "btree"] {
+///     let mut map = backend(name);
+///     map.put(1, "one");
+///     map.put(2, "two");
+///     assert_eq!(map.get(&2), Some(&"two"));
+///     assert_eq!(map.size(), 2_usize);
+/// }
+/// ```
+pub trait Map<K, V> {
     fn size(&self) -> usize;
     fn get(&self, key: &K) -> Option<&V>;
     fn put(&mut self, key: K, value: V);
-    fn height(&self) -> Option<usize>;
     fn is_empty(&self) -> bool {
         self.size().eq(&0_usize)
     }
 
-    /// Checks if key exists in `Tree`
+    /// Checks if key exists in `Tree`
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::Map;
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::btree::BalancedTree;
+    ///
+    /// let mut bst: BST<char, u32> = BST::new();
+    /// let mut rbtree: RedBlackTree<char, u32> = RedBlackTree::new();
+    /// let mut btree: BalancedTree<char, u32> = BalancedTree::new();
+    ///
+    /// bst.put('a', 2);
+    /// rbtree.put('a', 2);
+    /// btree.put('a', 2);
+    ///
+    /// assert!(bst.contains(&'a'));
+    /// assert_eq!(bst.contains(&'b'), false);
+    ///
+    /// assert!(rbtree.contains(&'a'));
+    /// assert_eq!(rbtree.contains(&'b'), false);
+    ///
+    /// assert!(btree.contains(&'a'));
+    /// assert_eq!(btree.contains(&'b'), false);
+    /// ```
+    fn contains(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+    /// Builds a map from key-value pairs, resolving duplicate keys with `policy`.
+    ///
+    /// Entries are inserted in order of the first occurrence of their key.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{KeepPolicy, Map};
+    ///
+    /// let pairs = vec![('a', 1), ('b', 2), ('a', 3)];
+    ///
+    /// let first: BST<char, i32> = BST::from_iter_dedup(pairs.clone(), KeepPolicy::First);
+    /// assert_eq!(first.get(&'a'), Some(&1));
+    ///
+    /// let last: BST<char, i32> = BST::from_iter_dedup(pairs.clone(), KeepPolicy::Last);
+    /// assert_eq!(last.get(&'a'), Some(&3));
+    ///
+    /// let sum: BST<char, i32> = BST::from_iter_dedup(pairs.clone(), KeepPolicy::merge_with(|a, b| a + b));
+    /// assert_eq!(sum.get(&'a'), Some(&4));
+    /// assert_eq!(sum.size(), 2_usize);
+    ///
+    /// // merges may keep state of their own
+    /// let weight = 10;
+    /// let decayed: BST<char, i32> =
+    ///     BST::from_iter_dedup(pairs, KeepPolicy::merge_with(|a, b| a * weight + b));
+    /// assert_eq!(decayed.get(&'a'), Some(&13));
+    /// ```
+    fn from_iter_dedup<I>(iter: I, mut policy: KeepPolicy<'_, V>) -> Self
+    where
+        Self: Sized + Default,
+        K: Ord,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut entries: Vec<(usize, K, V)> = iter
+            .into_iter()
+            .enumerate()
+            .map(|(i, (k, v))| (i, k, v))
+            .collect();
+        // stable sort, duplicates stay in input order
+        entries.sort_by(|a, b| a.1.cmp(&b.1));
+        let mut unique: Vec<(usize, K, V)> = Vec::with_capacity(entries.len());
+        for (i, k, v) in entries {
+            match unique.pop() {
+                Some((j, key, old)) if key.eq(&k) => {
+                    let v = match policy {
+                        KeepPolicy::First => old,
+                        KeepPolicy::Last => v,
+                        KeepPolicy::MergeWith(ref mut f) => f(old, v),
+                    };
+                    unique.push((j, key, v));
+                }
+                last => {
+                    unique.extend(last);
+                    unique.push((i, k, v));
+                }
+            }
+        }
+        unique.sort_by_key(|e| e.0);
+        let mut map = Self::default();
+        for (_, k, v) in unique {
+            map.put(k, v);
+        }
+        map
+    }
+
+    /// Puts `value` under `key`, resolving a key already present with
+    /// `policy`; returns `true` if the key was new.
+    ///
+    /// `put` of every map overwrites, as `PutPolicy::Overwrite` does. The
+    /// trees resolve a present key in place, one descent.
+    ///
+    /// # Panics
+    ///
+    /// With `PutPolicy::Panic`, panics if `key` is present, the map is
+    /// left as it was.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, PutPolicy};
+    ///
+    /// let mut hits: BalancedTree<&str, u32> = BalancedTree::new();
+    /// assert!(hits.put_with_policy("/", 1, &mut PutPolicy::KeepExisting));
+    /// assert!(!hits.put_with_policy("/", 5, &mut PutPolicy::KeepExisting));
+    /// assert_eq!(hits.get(&"/"), Some(&1));
+    ///
+    /// // merges count themselves too
+    /// let mut merges = 0;
+    /// let mut count = PutPolicy::merge_with(|old, new| {
+    ///     merges += 1;
+    ///     old + new
+    /// });
+    /// hits.put_with_policy("/", 1, &mut count);
+    /// hits.put_with_policy("/about", 1, &mut count);
+    /// drop(count);
+    /// assert_eq!((hits.get(&"/"), hits.get(&"/about")), (Some(&2), Some(&1)));
+    /// assert_eq!(merges, 1);
+    ///
+    /// hits.put_with_policy("/", 7, &mut PutPolicy::Overwrite);
+    /// assert_eq!(hits.get(&"/"), Some(&7));
+    /// ```
+    fn put_with_policy(&mut self, key: K, value: V, policy: &mut PutPolicy<'_, V>) -> bool {
+        let value = match (self.get(&key), policy) {
+            (None, _) => {
+                self.put(key, value);
+                return true;
+            }
+            (Some(_), PutPolicy::Overwrite) => value,
+            (Some(_), PutPolicy::KeepExisting) => return false,
+            (Some(_), PutPolicy::Panic) => panic!("put of a key already in the map"),
+            (Some(old), PutPolicy::MergeWith(f)) => f(old, value),
+        };
+        self.put(key, value);
+        false
+    }
+
+    /// Builds a map from `iter`, failing on the first key met twice.
+    ///
+    /// For inputs where a duplicate key means corrupt data rather than
+    /// something to merge, see `from_iter_dedup` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let rbtree = RedBlackTree::try_from_iter(vec![('a', 1), ('b', 2)]).unwrap();
+    /// assert_eq!(rbtree.size(), 2_usize);
+    ///
+    /// let err = RedBlackTree::<char, i32>::try_from_iter(vec![('a', 1), ('a', 2)]).unwrap_err();
+    /// assert_eq!(err.into_key(), 'a');
+    /// ```
+    fn try_from_iter<I>(iter: I) -> Result<Self, DuplicateKeyError<K>>
+    where
+        Self: Sized + Default,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut map = Self::default();
+        for (k, v) in iter {
+            if map.contains(&k) {
+                return Err(DuplicateKeyError::new(k));
+            }
+            map.put(k, v);
+        }
+        Ok(map)
+    }
+}
+
+/// Ordered symbol table API, queries that rely on the order of keys.
+pub trait OrderedMap<K: Ord, V>: Map<K, V> {
+    fn min(&self) -> Option<&K>;
+    fn max(&self) -> Option<&K>;
+    fn floor(&self, key: &K) -> Option<&K>;
+    fn ceiling(&self, key: &K) -> Option<&K>;
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)>;
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)>;
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)>
+    where
+        Self: Sized;
+
+    /// Returns the entry with the smallest value by `cmp`, the one of the
+    /// smallest key among equal values.
+    ///
+    /// Values are not ordered by the map, so this scans every entry; see
+    /// `maxtree::MaxTree::max_entry` for trees keeping value maximums.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// // prices by product id
+    /// let mut prices: BST<u32, f64> = BST::new();
+    /// for (id, price) in [(7, 9.5), (2, 3.25), (5, 12.0), (9, 3.25)] {
+    ///     prices.put(id, price);
+    /// }
+    /// let cheapest = prices.min_by_value(|a, b| a.total_cmp(b));
+    /// assert_eq!(cheapest, Some((&2, &3.25)));
+    /// ```
+    fn min_by_value<F: FnMut(&V, &V) -> Ordering>(&self, mut cmp: F) -> Option<(&K, &V)>
+    where
+        Self: Sized,
+    {
+        self.range(..).min_by(|a, b| cmp(a.1, b.1))
+    }
+
+    /// Returns the entry with the largest value by `cmp`, the one of the
+    /// largest key among equal values, scanning as `min_by_value`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// // scores by player, the top one
+    /// let mut scores: BalancedTree<&str, u32> = BalancedTree::new();
+    /// for (player, score) in [("ana", 310), ("bo", 480), ("cy", 480), ("di", 95)] {
+    ///     scores.put(player, score);
+    /// }
+    /// assert_eq!(scores.max_by_value(u32::cmp), Some((&"cy", &480)));
+    /// assert_eq!(scores.max_by_value(|a, b| b.cmp(a)), Some((&"di", &95)));
+    /// ```
+    fn max_by_value<F: FnMut(&V, &V) -> Ordering>(&self, mut cmp: F) -> Option<(&K, &V)>
+    where
+        Self: Sized,
+    {
+        self.range(..).max_by(|a, b| cmp(a.1, b.1))
+    }
+
+    /// Folds entries with keys inside `range` in key order, stopping at the
+    /// first `Err` of `f` and returning it.
+    ///
+    /// The trees walk their nodes in place, without collecting the range
+    /// first, and a stop skips the rest of the walk; other maps fold
+    /// `range`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// // spending by day, summed until a budget runs out
+    /// let mut spent: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// for (day, amount) in [(1, 30), (2, 50), (3, 40), (4, 10)] {
+    ///     spent.put(day, amount);
+    /// }
+    /// let fill = |budget| {
+    ///     spent.try_fold_range(1.., 0, |total, day, amount| match total + amount {
+    ///         total if total > budget => Err(*day),
+    ///         total => Ok(total),
+    ///     })
+    /// };
+    /// assert_eq!(fill(100), Err(3));
+    /// assert_eq!(fill(200), Ok(130));
+    /// ```
+    fn try_fold_range<R, B, E, F>(&self, range: R, init: B, mut f: F) -> Result<B, E>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(B, &K, &V) -> Result<B, E>,
+        Self: Sized,
+    {
+        let mut acc = init;
+        for (k, v) in self.range(range) {
+            acc = f(acc, k, v)?;
+        }
+        Ok(acc)
+    }
+
+    /// Folds entries with keys inside `range` in key order, as
+    /// `try_fold_range` does without stopping.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut btree: BalancedTree<u32, u64> = BalancedTree::new();
+    /// for k in 0..100 {
+    ///     btree.put(k, u64::from(k));
+    /// }
+    /// assert_eq!(btree.fold_range(10..20, 0, |sum, _, v| sum + v), 145);
+    /// assert_eq!(btree.fold_range(.., None, |_, k, _| Some(*k)), Some(99));
+    /// ```
+    fn fold_range<R, B, F>(&self, range: R, init: B, mut f: F) -> B
+    where
+        R: RangeBounds<K>,
+        F: FnMut(B, &K, &V) -> B,
+        Self: Sized,
+    {
+        let folded = self.try_fold_range(range, init, |acc, k, v| {
+            Ok::<B, std::convert::Infallible>(f(acc, k, v))
+        });
+        match folded {
+            Ok(acc) => acc,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Returns the stored key closest to `key`, comparing its floor and
+    /// ceiling by `distance`; when both are equally distant `tie` decides.
+    ///
+    /// `distance(a, b)` is only called with `a <= b`, so it never has to
+    /// go negative: `b.abs_diff(*a)` for integers, `b.duration_since(*a)`
+    /// for `Instant`s.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, OrderedMap, TieBreak};
+    ///
+    /// let dist = |a: &i32, b: &i32| b.abs_diff(*a);
+    /// let mut bst: BST<i32, &str> = BST::new();
+    /// assert_eq!(bst.nearest(&5, TieBreak::Lower, dist), None);
+    /// bst.put(10, "ten");
+    /// bst.put(20, "twenty");
+    /// bst.put(30, "thirty");
+    ///
+    /// assert_eq!(bst.nearest(&12, TieBreak::Lower, dist), Some(&10));
+    /// assert_eq!(bst.nearest(&18, TieBreak::Lower, dist), Some(&20));
+    /// assert_eq!(bst.nearest(&15, TieBreak::Lower, dist), Some(&10));
+    /// assert_eq!(bst.nearest(&15, TieBreak::Upper, dist), Some(&20));
+    /// assert_eq!(bst.nearest(&1, TieBreak::Upper, dist), Some(&10));
+    /// assert_eq!(bst.nearest(&99, TieBreak::Lower, dist), Some(&30));
+    ///
+    /// // the extremes of `i32` are `u32::MAX` apart, no overflow
+    /// bst.put(i32::MIN, "min");
+    /// bst.put(i32::MAX, "max");
+    /// assert_eq!(bst.nearest(&-5, TieBreak::Lower, dist), Some(&10));
+    /// assert_eq!(bst.nearest(&i32::MAX, TieBreak::Lower, dist), Some(&i32::MAX));
+    /// ```
+    ///
+    /// Snapping to the closest timestamp:
+    ///
+    /// ```
+    /// use std::time::{Duration, Instant};
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap, TieBreak};
+    ///
+    /// let start = Instant::now();
+    /// let mut frames = RedBlackTree::new();
+    /// for i in 0..10_u64 {
+    ///     frames.put(start + Duration::from_millis(i * 40), i);
+    /// }
+    /// let at = start + Duration::from_millis(95);
+    /// let snapped = frames.nearest(&at, TieBreak::Lower, |a, b| b.duration_since(*a));
+    /// assert_eq!(snapped.and_then(|k| frames.get(k)), Some(&2));
+    /// ```
+    fn nearest<D, F>(&self, key: &K, tie: TieBreak, mut distance: F) -> Option<&K>
+    where
+        D: Ord,
+        F: FnMut(&K, &K) -> D,
+        Self: Sized,
+    {
+        match (self.floor(key), self.ceiling(key)) {
+            (Some(f), Some(c)) => match distance(f, key).cmp(&distance(key, c)) {
+                Ordering::Less => Some(f),
+                Ordering::Greater => Some(c),
+                Ordering::Equal => match tie {
+                    TieBreak::Lower => Some(f),
+                    TieBreak::Upper => Some(c),
+                },
+            },
+            (f, c) => f.or(c),
+        }
+    }
+
+    /// Returns the minimum and the maximum key together, `None` for an
+    /// empty map, one descent for each end.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut btree: BalancedTree<u32, ()> = BalancedTree::new();
+    /// assert_eq!(btree.key_bounds(), None);
+    /// for k in [40, 10, 30] {
+    ///     btree.put(k, ());
+    /// }
+    /// assert_eq!(btree.key_bounds(), Some((&10, &40)));
+    /// ```
+    fn key_bounds(&self) -> Option<(&K, &K)> {
+        Some((self.min()?, self.max()?))
+    }
+
+    /// Walks the keys in order checking what a broken `Ord` of `K` leaves
+    /// behind: neighbors compared less, in both directions, and every key
+    /// found again by a search. Lookups in a map failing this are wrong.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    /// use std::cmp::Ordering;
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap, OrderingError};
+    ///
+    /// thread_local!(static FLIPPED: Cell<bool> = Cell::new(false));
+    ///
+    /// // a key whose order flips at runtime, a buggy comparator
+    /// #[derive(Debug, Clone, PartialEq, Eq)]
+    /// struct Flaky(u32);
+    ///
+    /// impl Ord for Flaky {
+    ///     fn cmp(&self, other: &Self) -> Ordering {
+    ///         let ord = self.0.cmp(&other.0);
+    ///         if FLIPPED.with(Cell::get) { ord.reverse() } else { ord }
+    ///     }
+    /// }
+    ///
+    /// impl PartialOrd for Flaky {
+    ///     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    ///         Some(self.cmp(other))
+    ///     }
+    /// }
+    ///
+    /// let mut rbtree = RedBlackTree::new();
+    /// for k in 0..8 {
+    ///     rbtree.put(Flaky(k), ());
+    /// }
+    /// assert_eq!(rbtree.validate_ordering(), Ok(()));
+    ///
+    /// FLIPPED.with(|f| f.set(true));
+    /// let err = rbtree.validate_ordering().unwrap_err();
+    /// assert_eq!(err, OrderingError::OutOfOrder(&Flaky(0), &Flaky(1)));
+    /// assert_eq!(err.to_string(), "keys out of order: Flaky(0) then Flaky(1)");
+    /// ```
+    fn validate_ordering<'a>(&'a self) -> Result<(), OrderingError<&'a K>>
+    where
+        V: 'a,
+    {
+        let keys: Vec<&K> = self.smallest(self.size()).map(|(k, _)| k).collect();
+        for pair in keys.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if !(a.cmp(b).eq(&Ordering::Less) && b.cmp(a).eq(&Ordering::Greater)) {
+                return Err(OrderingError::OutOfOrder(a, b));
+            }
+        }
+        match keys.into_iter().find(|k| !self.contains(k)) {
+            Some(k) => Err(OrderingError::Unreachable(k)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Sedgewick's tree maps, ordered maps with a height.
+///
+/// `height` counts links on the longest path from the root, a single
+/// node has height `Some(0)` and an empty tree `None`. `levels` counts
+/// nodes on that path instead, so it is `0` for an empty tree.
+pub trait SedgewickMap<K: Ord, V>: OrderedMap<K, V> {
+    fn height(&self) -> Option<usize>;
+
+    /// Returns the depth of every leaf, a node without children, from left
+    /// to right, the root at depth `0`; all leaves of `BalancedTree` are at
+    /// its height.
+    ///
+    /// The longest depth is `height`, the spread of the rest tells how far
+    /// from balanced the tree is.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut bst: BST<u32, ()> = BST::new();
+    /// let mut rbtree: RedBlackTree<u32, ()> = RedBlackTree::new();
+    /// for k in [4, 2, 1, 3, 5, 6, 7] {
+    ///     bst.put(k, ());
+    ///     rbtree.put(k, ());
+    /// }
+    /// //      4
+    /// //    /   \
+    /// //   2     5
+    /// //  / \     \
+    /// // 1   3     6
+    /// //            \
+    /// //             7
+    /// assert_eq!(bst.leaf_depths(), [2, 2, 3]);
+    ///
+    /// // a histogram, leaves per depth
+    /// let mut histogram = vec![0_usize; rbtree.levels()];
+    /// for depth in rbtree.leaf_depths() {
+    ///     histogram[depth] += 1;
+    /// }
+    /// assert_eq!(histogram.iter().sum::<usize>(), rbtree.leaf_depths().len());
+    /// assert_eq!(rbtree.leaf_depths().iter().max(), rbtree.height().as_ref());
+    /// ```
+    fn leaf_depths(&self) -> Vec<usize>;
+
+    /// Removes the entry of the minimum key and returns it, `None` when
+    /// empty.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap, SedgewickMap};
+    ///
+    /// let mut rbtree: RedBlackTree<u32, char> = RedBlackTree::new();
+    /// rbtree.put(2, 'b');
+    /// rbtree.put(1, 'a');
+    /// rbtree.put(3, 'c');
+    ///
+    /// assert_eq!(rbtree.delete_min(), Some((1, 'a')));
+    /// assert_eq!(rbtree.min(), Some(&2));
+    /// assert_eq!(rbtree.size(), 2_usize);
+    /// ```
+    fn delete_min(&mut self) -> Option<(K, V)>;
+
+    /// Removes the entry of the maximum key and returns it, `None` when
+    /// empty.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, OrderedMap, SedgewickMap};
+    ///
+    /// let mut btree: BalancedTree<u32, char> = BalancedTree::new();
+    /// btree.put(2, 'b');
+    /// btree.put(1, 'a');
+    ///
+    /// assert_eq!(btree.delete_max(), Some((2, 'b')));
+    /// assert_eq!(btree.delete_max(), Some((1, 'a')));
+    /// assert_eq!(btree.delete_max(), None);
+    /// ```
+    fn delete_max(&mut self) -> Option<(K, V)>;
+
+    /// Removes the entry of the minimum key and returns it, as
+    /// `delete_min` does, for maps used as double-ended priority queues.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// // jobs by (deadline, id)
+    /// let mut queue: RedBlackTree<(u64, u32), &str> = RedBlackTree::new();
+    /// queue.put((30, 1), "backup");
+    /// queue.put((10, 2), "email");
+    /// queue.put((20, 3), "report");
+    ///
+    /// assert_eq!(queue.pop_min(), Some(((10, 2), "email")));
+    /// // running late, the job of the latest deadline is dropped
+    /// assert_eq!(queue.pop_max(), Some(((30, 1), "backup")));
+    /// assert_eq!(queue.pop_min(), Some(((20, 3), "report")));
+    /// assert_eq!(queue.pop_min(), None);
+    /// ```
+    fn pop_min(&mut self) -> Option<(K, V)> {
+        self.delete_min()
+    }
+
+    /// Removes the entry of the maximum key and returns it, as
+    /// `delete_max` does.
+    fn pop_max(&mut self) -> Option<(K, V)> {
+        self.delete_max()
+    }
+
+    /// Removes all entries, leaving the map as a new empty one.
+    ///
+    /// A `BST` is taken apart node by node, so clearing even a degenerate
+    /// one of millions of nodes uses no deep recursion.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut bst: BST<u32, u32> = BST::new();
+    /// for k in 0..100 {
+    ///     bst.put(k, k);
+    /// }
+    /// bst.clear();
+    /// assert!(bst.is_empty());
+    /// assert_eq!(bst.height(), None);
+    /// ```
+    fn clear(&mut self);
+
+    /// Removes all entries like `clear`, returning them in key order.
+    ///
+    /// Entries are moved out of the nodes, so neither keys nor values are
+    /// cloned; a `BST` is taken apart without deep recursion here too.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut btree: BalancedTree<u32, String> = BalancedTree::new();
+    /// for k in [3, 1, 2] {
+    ///     btree.put(k, k.to_string());
+    /// }
+    /// let moved: BTreeMap<u32, String> = btree.drain().collect();
+    /// assert!(btree.is_empty());
+    /// assert_eq!(moved.keys().collect::<Vec<_>>(), [&1, &2, &3]);
+    /// ```
+    fn drain(&mut self) -> std::vec::IntoIter<(K, V)>;
+
+    /// Returns a count of keys less than `key`, `key` itself need not be in
+    /// the map.
+    ///
+    /// `BST` and `RedBlackTree` walk one path down by their subtree sizes,
+    /// `BalancedTree` keeps no sizes and counts entries left of the path.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut bst: BST<u32, ()> = BST::new();
+    /// for k in [30, 10, 20, 40] {
+    ///     bst.put(k, ());
+    /// }
+    /// assert_eq!(bst.rank(&10), 0_usize);
+    /// assert_eq!(bst.rank(&25), 2_usize);
+    /// assert_eq!(bst.rank(&99), 4_usize);
+    /// ```
+    fn rank(&self, key: &K) -> usize;
+
+    /// Returns the key with `rank` keys less than it, the smallest one for
+    /// `0`, `None` past the last.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut rbtree: RedBlackTree<u32, ()> = RedBlackTree::new();
+    /// for k in [30, 10, 20, 40] {
+    ///     rbtree.put(k, ());
+    /// }
+    /// assert_eq!(rbtree.select(0), Some(&10));
+    /// assert_eq!(rbtree.select(2), Some(&30));
+    /// assert_eq!(rbtree.select(4), None);
+    /// assert_eq!(rbtree.select(rbtree.rank(&20)), Some(&20));
+    /// ```
+    fn select(&self, rank: usize) -> Option<&K>;
+
+    /// Returns the key at quantile `q` by nearest rank, the smallest key
+    /// with a `q` fraction of keys less than or equal to it, by one
+    /// `select`.
+    ///
+    /// `None` if the map is empty or `q` is outside `0.0..=1.0`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// // latencies in microseconds, with a sequence number to keep repeats
+    /// let mut latencies: RedBlackTree<(u32, u32), ()> = RedBlackTree::new();
+    /// for (seq, us) in (1..=100).map(|us| us * 10).enumerate() {
+    ///     latencies.put((us, seq as u32), ());
+    /// }
+    /// assert_eq!(latencies.quantile(0.99).map(|(us, _)| *us), Some(990));
+    /// assert_eq!(latencies.quantile(0.0).map(|(us, _)| *us), Some(10));
+    /// assert_eq!(latencies.quantile(1.5), None);
+    /// ```
+    fn quantile(&self, q: f64) -> Option<&K> {
+        if !(0.0_f64..=1.0_f64).contains(&q) || self.is_empty() {
+            return None;
+        }
+        // nearest rank, sizes fit into `f64` exactly
+        let rank = (q * self.size() as f64).ceil() as usize;
+        self.select(rank.saturating_sub(1_usize))
+    }
+
+    /// Returns the median key, the lower of the two middle ones for an even
+    /// count, as `quantile(0.5)`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut bst: BST<u32, ()> = BST::new();
+    /// assert_eq!(bst.median(), None);
+    /// for k in [40, 10, 30] {
+    ///     bst.put(k, ());
+    /// }
+    /// assert_eq!(bst.median(), Some(&30));
+    /// bst.put(20, ());
+    /// assert_eq!(bst.median(), Some(&20));
+    /// ```
+    fn median(&self) -> Option<&K> {
+        self.quantile(0.5_f64)
+    }
+
+    /// Returns the first key `pred` is false for, `None` if it holds for
+    /// all of them.
+    ///
+    /// `pred` must be true for a prefix of the keys in order and false for
+    /// the rest, as in `slice::partition_point`; the search then takes one
+    /// path down, `ceiling(key)` being `partition_point(|k| k < key)`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// // events keyed by (seconds, sequence number)
+    /// let mut events: BST<(u64, u32), &str> = BST::new();
+    /// events.put((90, 0), "boot");
+    /// events.put((3600, 0), "login");
+    /// events.put((3600, 1), "sync");
+    /// events.put((7300, 0), "logout");
+    ///
+    /// // the first event of the second hour
+    /// let cutoff = |&(secs, _): &(u64, u32)| secs / 3600 < 1;
+    /// assert_eq!(events.partition_point(cutoff), Some(&(3600, 0)));
+    /// assert_eq!(events.partition_point(|_| true), None);
+    /// ```
+    fn partition_point<P: FnMut(&K) -> bool>(&self, pred: P) -> Option<&K>
+    where
+        Self: Sized;
+
+    /// Returns the entry of the smallest key greater than `key`, `key`
+    /// itself need not be in the map.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// // ids taken so far, the first gap after an id is the next free one
+    /// let mut taken: BalancedTree<u32, &str> = BalancedTree::new();
+    /// for (id, name) in [(1, "a"), (2, "b"), (3, "c"), (7, "d")] {
+    ///     taken.put(id, name);
+    /// }
+    /// let mut id = 1;
+    /// while let Some((next, _)) = taken.successor(&id).filter(|(next, _)| **next == id + 1) {
+    ///     id = *next;
+    /// }
+    /// assert_eq!(id + 1, 4);
+    /// assert_eq!(taken.successor(&4), Some((&7, &"d")));
+    /// assert_eq!(taken.successor(&7), None);
+    /// ```
+    fn successor(&self, key: &K) -> Option<(&K, &V)>;
+
+    /// Returns the entry of the largest key less than `key`, `key` itself
+    /// need not be in the map.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// // events by timestamp, the last one before a crash at 150
+    /// let mut events: RedBlackTree<u64, &str> = RedBlackTree::new();
+    /// events.put(100, "deploy");
+    /// events.put(150, "crash");
+    /// events.put(200, "rollback");
+    /// assert_eq!(events.predecessor(&150), Some((&100, &"deploy")));
+    /// assert_eq!(events.predecessor(&120), Some((&100, &"deploy")));
+    /// assert_eq!(events.predecessor(&100), None);
+    /// ```
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)>;
+
+    /// Returns the first entry in key order `pred` holds for, stepping by
+    /// `successor` so the scan stops at the match without collecting the
+    /// entries before it.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// // stock by item id, the first item to reorder
+    /// let mut stock: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// for (id, count) in [(4, 12), (9, 2), (15, 0), (23, 7)] {
+    ///     stock.put(id, count);
+    /// }
+    /// assert_eq!(stock.find_first(|_, count| *count < 5), Some((&9, &2)));
+    /// assert_eq!(stock.find_first(|id, _| *id > 23), None);
+    /// ```
+    fn find_first<P: FnMut(&K, &V) -> bool>(&self, mut pred: P) -> Option<(&K, &V)>
+    where
+        Self: Sized,
+    {
+        let first = self.min()?;
+        let mut entry = (first, self.get(first)?);
+        while !pred(entry.0, entry.1) {
+            entry = self.successor(entry.0)?;
+        }
+        Some(entry)
+    }
+
+    /// Returns the last entry in key order `pred` holds for, stepping back
+    /// by `predecessor` from the largest key.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// // a log by sequence number, the last error in it
+    /// let mut log: BalancedTree<u64, &str> = BalancedTree::new();
+    /// for (seq, line) in [(1, "ok"), (2, "error: disk"), (3, "ok"), (4, "error: net"), (5, "ok")] {
+    ///     log.put(seq, line);
+    /// }
+    /// let error = |_: &u64, line: &&str| line.starts_with("error");
+    /// assert_eq!(log.find_last(error), Some((&4, &"error: net")));
+    /// assert_eq!(log.find_first(error), Some((&2, &"error: disk")));
+    /// assert_eq!(log.find_last(|_, line| line.is_empty()), None);
+    /// ```
+    fn find_last<P: FnMut(&K, &V) -> bool>(&self, mut pred: P) -> Option<(&K, &V)>
+    where
+        Self: Sized,
+    {
+        let last = self.max()?;
+        let mut entry = (last, self.get(last)?);
+        while !pred(entry.0, entry.1) {
+            entry = self.predecessor(entry.0)?;
+        }
+        Some(entry)
+    }
+
+    /// Returns `true` if some key maps to `value`.
     ///
+    /// Values are not indexed, so this is a scan in key order, `O(n)`,
+    /// stopping at the first match; for debugging and rare reverse
+    /// lookups.
     ///
     /// # Examples
     ///
@@ -36,32 +1054,81 @@ pub trait SedgewickMap<K: Ord, V> {
     ///
     /// ```
     /// use treers::bst::BST;
-    /// use treers::SedgewickMap;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut owners: BST<u32, &str> = BST::new();
+    /// owners.put(7, "ana");
+    /// owners.put(3, "bo");
+    /// assert!(owners.contains_value(&"bo"));
+    /// assert!(!owners.contains_value(&"cy"));
+    /// ```
+    fn contains_value(&self, value: &V) -> bool
+    where
+        V: PartialEq,
+        Self: Sized,
+    {
+        self.try_fold_range(
+            ..,
+            (),
+            |(), _, v| if v.eq(value) { Err(()) } else { Ok(()) },
+        )
+        .is_err()
+    }
+
+    /// Returns the smallest key mapping to `value`, by the scan of
+    /// `find_first`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
     /// use treers::rbtree::RedBlackTree;
-    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, SedgewickMap};
     ///
-    /// let mut bst: BST<char, u32> = BST::new();
-    /// let mut rbtree: RedBlackTree<char, u32> = RedBlackTree::new();
-    /// let mut btree: BalancedTree<char, u32> = BalancedTree::new();
+    /// // a reverse lookup of a port by its service
+    /// let mut ports: RedBlackTree<u16, &str> = RedBlackTree::new();
+    /// ports.put(443, "https");
+    /// ports.put(22, "ssh");
+    /// ports.put(8443, "https");
+    /// assert_eq!(ports.find_by_value(&"https"), Some(&443));
+    /// assert_eq!(ports.find_by_value(&"ftp"), None);
+    /// ```
+    fn find_by_value<'a>(&'a self, value: &V) -> Option<&'a K>
+    where
+        V: PartialEq + 'a,
+        Self: Sized,
+    {
+        self.find_first(|_, v| v.eq(value)).map(|(k, _)| k)
+    }
+
+    /// Returns a count of levels in tree, `height + 1`, or `0` when empty.
     ///
-    /// bst.put('a', 2);
-    /// rbtree.put('a', 2);
-    /// btree.put('a', 2);
+    /// # Examples
     ///
-    /// assert!(bst.contains(&'a'));
-    /// assert_eq!(bst.contains(&'b'), false);
+    /// Basic usage:
     ///
-    /// assert!(rbtree.contains(&'a'));
-    /// assert_eq!(rbtree.contains(&'b'), false);
+    /// ```
+    /// use treers::bst::BST;
+    /// use treers::btree::BalancedTree;
+    /// use treers::{Map, SedgewickMap};
     ///
-    /// assert!(btree.contains(&'a'));
-    /// assert_eq!(btree.contains(&'b'), false);
+    /// let mut bst: BST<u32, u32> = BST::new();
+    /// let mut btree: BalancedTree<u32, u32> = BalancedTree::new();
+    /// assert_eq!(bst.levels(), 0_usize);
+    /// assert_eq!(btree.levels(), 0_usize);
+    ///
+    /// bst.put(1, 1);
+    /// btree.put(1, 1);
+    /// assert_eq!(bst.levels(), 1_usize);
+    /// assert_eq!(btree.levels(), 1_usize);
+    ///
+    /// bst.put(2, 2);
+    /// assert_eq!(bst.levels(), 2_usize);
     /// ```
-    fn contains(&self, key: &K) -> bool {
-        self.get(&key).is_some()
+    fn levels(&self) -> usize {
+        self.height().map_or(0_usize, |h| h + 1_usize)
     }
-    fn min(&self) -> Option<&K>;
-    fn max(&self) -> Option<&K>;
 }
 
 /// A immutable recursive traversals over Binary Trees.
@@ -77,7 +1144,7 @@ pub trait SedgewickMap<K: Ord, V> {
 ///
 /// ```
 /// use treers::bst::BST;
-/// use treers::{SedgewickMap, Traversals, TreeTraversal};
+/// use treers::{Map, Traversals, TreeTraversal};
 ///
 /// let mut bst: BST<char, i32> = BST::new();
 /// bst.put('c', 3);
@@ -113,7 +1180,7 @@ pub trait TreeTraversal<K: Ord, V>: SedgewickMap<K, V> {
             Traversals::InOrder => self.in_order(&mut vec),
             Traversals::PostOrder => self.post_order(&mut vec),
             Traversals::LevelOrder => {
-                for level in 0..=self.height().unwrap() {
+                for level in 0..self.levels() {
                     self.level_order(&mut vec, level);
                 }
             }
@@ -126,6 +1193,201 @@ pub trait TreeTraversal<K: Ord, V>: SedgewickMap<K, V> {
     fn level_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>, level: usize);
 }
 
+/// Rebuilds any ordered map as another backend.
+///
+/// Entries are read in key order and loaded middle first, so a plain `BST`
+/// target comes out balanced instead of degenerating into a list.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use treers::bst::BST;
+/// use treers::btree::BalancedTree;
+/// use treers::rbtree::RedBlackTree;
+/// use treers::{convert, Map, SedgewickMap};
+///
+/// let mut rbtree: RedBlackTree<u32, char> = RedBlackTree::new();
+/// for (k, v) in (1_u32..=7).zip('a'..) {
+///     rbtree.put(k, v);
+/// }
+///
+/// let bst: BST<u32, char> = convert(&rbtree);
+/// assert_eq!(bst.size(), 7_usize);
+/// assert_eq!(bst.get(&4), Some(&'d'));
+/// assert_eq!(bst.height(), Some(2_usize));
+///
+/// let btree: BalancedTree<u32, char> = convert(&bst);
+/// assert_eq!(btree.get(&7), Some(&'g'));
+/// ```
+pub fn convert<K, V, A, B>(a: &A) -> B
+where
+    K: Ord + Clone,
+    V: Clone,
+    A: OrderedMap<K, V>,
+    B: Map<K, V> + Default,
+{
+    let entries: Vec<(&K, &V)> = a.range(..).collect();
+    let mut b = B::default();
+    put_middle_first(&mut b, &entries);
+    b
+}
+
+/// Builds a reverse index of any ordered map, keyed by value.
+///
+/// Every value maps to all keys holding it, in key order. Entries are
+/// loaded middle first, as in `convert`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use treers::bst::BST;
+/// use treers::rbtree::RedBlackTree;
+/// use treers::{invert, Map, OrderedMap};
+///
+/// let mut owners: RedBlackTree<&str, char> = RedBlackTree::new();
+/// owners.put("x.rs", 'a');
+/// owners.put("y.rs", 'b');
+/// owners.put("w.rs", 'a');
+///
+/// let files: BST<char, Vec<&str>> = invert(&owners);
+/// assert_eq!(files.get(&'a'), Some(&vec!["w.rs", "x.rs"]));
+/// assert_eq!(files.get(&'b'), Some(&vec!["y.rs"]));
+/// assert_eq!(files.max(), Some(&'b'));
+/// ```
+pub fn invert<K, V, A, B>(a: &A) -> B
+where
+    K: Ord + Clone,
+    V: Ord + Clone,
+    A: OrderedMap<K, V>,
+    B: Map<V, Vec<K>> + Default,
+{
+    let mut index: BTreeMap<&V, Vec<K>> = BTreeMap::new();
+    for (k, v) in a.range(..) {
+        index.entry(v).or_default().push(k.clone());
+    }
+    let entries: Vec<(&V, &Vec<K>)> = index.iter().map(|(v, keys)| (*v, keys)).collect();
+    let mut b = B::default();
+    put_middle_first(&mut b, &entries);
+    b
+}
+
+fn put_middle_first<K: Clone, V: Clone, B: Map<K, V>>(map: &mut B, entries: &[(&K, &V)]) {
+    if entries.is_empty() {
+        return;
+    }
+    let mid = entries.len() / 2_usize;
+    let (k, v) = entries[mid];
+    map.put(k.clone(), v.clone());
+    put_middle_first(map, &entries[..mid]);
+    put_middle_first(map, &entries[mid + 1..]);
+}
+
+// Below this many entries parallel operations run sequentially
+#[cfg(all(feature = "rayon", any(feature = "bst", feature = "rbtree")))]
+const PARALLEL_CUTOFF: usize = 1024_usize;
+
+// Can `range` hold keys less than `key`?
+fn reaches_below<K: Ord, R: RangeBounds<K>>(range: &R, key: &K) -> bool {
+    match range.start_bound() {
+        Bound::Included(lo) | Bound::Excluded(lo) => lo.lt(key),
+        Bound::Unbounded => true,
+    }
+}
+
+// Can `range` hold keys greater than `key`?
+fn reaches_above<K: Ord, R: RangeBounds<K>>(range: &R, key: &K) -> bool {
+    match range.end_bound() {
+        Bound::Included(hi) | Bound::Excluded(hi) => hi.gt(key),
+        Bound::Unbounded => true,
+    }
+}
+
+/// Picks a side in `nearest` when a key is equally distant from its floor
+/// and its ceiling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    Lower,
+    Upper,
+}
+
+// Merges of duplicate values, the closures may capture state
+type Merge<'a, V> = Box<dyn FnMut(V, V) -> V + 'a>;
+type MergeRef<'a, V> = Box<dyn FnMut(&V, V) -> V + 'a>;
+
+/// Resolves duplicate keys in `from_iter_dedup`.
+///
+/// `First` keeps the earliest value, `Last` keeps the latest one and
+/// `MergeWith` folds values in input order, `f(earlier, later)`.
+pub enum KeepPolicy<'a, V> {
+    First,
+    Last,
+    MergeWith(Merge<'a, V>),
+}
+
+impl<'a, V> KeepPolicy<'a, V> {
+    /// Returns a `MergeWith` of closure `f`.
+    pub fn merge_with<F: FnMut(V, V) -> V + 'a>(f: F) -> Self {
+        KeepPolicy::MergeWith(Box::new(f))
+    }
+}
+
+impl<V> fmt::Debug for KeepPolicy<'_, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            KeepPolicy::First => "First",
+            KeepPolicy::Last => "Last",
+            KeepPolicy::MergeWith(_) => "MergeWith",
+        })
+    }
+}
+
+/// Resolves a key already present in `put_with_policy`.
+///
+/// `Overwrite` replaces the value as `put` does, `KeepExisting` leaves it,
+/// `Panic` treats the put as a bug and `MergeWith` puts `f(existing, new)`.
+pub enum PutPolicy<'a, V> {
+    Overwrite,
+    KeepExisting,
+    Panic,
+    MergeWith(MergeRef<'a, V>),
+}
+
+impl<'a, V> PutPolicy<'a, V> {
+    /// Returns a `MergeWith` of closure `f`.
+    pub fn merge_with<F: FnMut(&V, V) -> V + 'a>(f: F) -> Self {
+        PutPolicy::MergeWith(Box::new(f))
+    }
+
+    // Resolves the value of a present key in place
+    #[cfg(any(feature = "bst", feature = "rbtree", feature = "btree"))]
+    pub(crate) fn resolve(&mut self, old: &mut V, value: V) {
+        match self {
+            PutPolicy::Overwrite => *old = value,
+            PutPolicy::KeepExisting => {}
+            PutPolicy::Panic => panic!("put of a key already in the map"),
+            PutPolicy::MergeWith(f) => {
+                let merged = f(old, value);
+                *old = merged;
+            }
+        }
+    }
+}
+
+impl<V> fmt::Debug for PutPolicy<'_, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PutPolicy::Overwrite => "Overwrite",
+            PutPolicy::KeepExisting => "KeepExisting",
+            PutPolicy::Panic => "Panic",
+            PutPolicy::MergeWith(_) => "MergeWith",
+        })
+    }
+}
+
 pub enum Traversals {
     PreOrder,
     InOrder,
@@ -133,12 +1395,17 @@ pub enum Traversals {
     LevelOrder,
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
 mod tests {
+    use crate::adapters::StdBTreeAdapter;
     use crate::bst::BST;
     use crate::btree::BalancedTree;
     use crate::rbtree::RedBlackTree;
-    use crate::SedgewickMap;
+    use crate::{
+        convert, invert, DuplicateKeyError, Error, KeepPolicy, Map, OrderedMap, PutPolicy,
+        SedgewickMap, TieBreak,
+    };
+    use std::ops::Bound;
 
     #[test]
     fn its_42() {
@@ -159,4 +1426,601 @@ mod tests {
         assert!(is_empty(&rbt));
         assert!(is_empty(&btree));
     }
+
+    fn nearest<M: SedgewickMap<u64, u64>>(map: &mut M) {
+        let dist = |a: &u64, b: &u64| b - a;
+        assert_eq!(map.nearest(&7, TieBreak::Lower, dist), None);
+        for i in (0..=100_u64).step_by(10) {
+            map.put(i, i);
+        }
+        assert_eq!(map.nearest(&7, TieBreak::Lower, dist), Some(&10));
+        assert_eq!(map.nearest(&42, TieBreak::Upper, dist), Some(&40));
+        assert_eq!(map.nearest(&45, TieBreak::Lower, dist), Some(&40));
+        assert_eq!(map.nearest(&45, TieBreak::Upper, dist), Some(&50));
+        assert_eq!(map.nearest(&50, TieBreak::Upper, dist), Some(&50));
+        assert_eq!(map.nearest(&1_000, TieBreak::Lower, dist), Some(&100));
+        // distances off a capturing closure, e.g. a metric of its own
+        let weight = 3_u64;
+        let far = |a: &u64, b: &u64| (b - a) * weight;
+        assert_eq!(map.nearest(&44, TieBreak::Upper, far), Some(&40));
+    }
+
+    #[test]
+    fn test_nearest() {
+        nearest(&mut BST::new());
+        nearest(&mut RedBlackTree::new());
+        nearest(&mut BalancedTree::new());
+    }
+
+    fn from_iter_dedup<M: SedgewickMap<u32, u32> + Default>() {
+        let pairs = || (0..300_u32).map(|i| (i % 100, i));
+        let first = M::from_iter_dedup(pairs(), KeepPolicy::First);
+        let last = M::from_iter_dedup(pairs(), KeepPolicy::Last);
+        let mut merges = 0_usize;
+        let sum = M::from_iter_dedup(
+            pairs(),
+            KeepPolicy::merge_with(|a, b| {
+                merges += 1_usize;
+                a + b
+            }),
+        );
+        assert_eq!(merges, 200_usize);
+        assert_eq!(first.size(), 100_usize);
+        assert_eq!(last.size(), 100_usize);
+        assert_eq!(sum.size(), 100_usize);
+        for k in 0..100_u32 {
+            assert_eq!(first.get(&k), Some(&k));
+            assert_eq!(last.get(&k), Some(&(k + 200)));
+            assert_eq!(sum.get(&k), Some(&(3 * k + 300)));
+        }
+    }
+
+    #[test]
+    fn test_from_iter_dedup() {
+        from_iter_dedup::<BST<u32, u32>>();
+        from_iter_dedup::<RedBlackTree<u32, u32>>();
+        from_iter_dedup::<BalancedTree<u32, u32>>();
+    }
+
+    fn smallest_and_largest<M: SedgewickMap<u32, u32>>(map: &mut M) {
+        assert_eq!(map.smallest(3).count(), 0_usize);
+        assert_eq!(map.largest(3).count(), 0_usize);
+        for i in (1..=1_000_u32).rev() {
+            map.put(i, i * 2);
+        }
+        let small: Vec<_> = map.smallest(3).collect();
+        assert_eq!(small, [(&1, &2), (&2, &4), (&3, &6)]);
+        let large: Vec<_> = map.largest(3).collect();
+        assert_eq!(large, [(&1_000, &2_000), (&999, &1_998), (&998, &1_996)]);
+        assert_eq!(map.smallest(0).count(), 0_usize);
+        assert_eq!(map.largest(5_000).count(), 1_000_usize);
+        assert!(map.smallest(5_000).map(|(k, _)| *k).eq(1..=1_000_u32));
+    }
+
+    #[test]
+    fn test_smallest_and_largest() {
+        smallest_and_largest(&mut BST::new());
+        smallest_and_largest(&mut RedBlackTree::new());
+        smallest_and_largest(&mut BalancedTree::new());
+    }
+
+    fn range<M: SedgewickMap<u32, u32>>(map: &mut M) {
+        assert_eq!(map.range(..).count(), 0_usize);
+        for i in (1..=500_u32).rev() {
+            map.put(i * 2, i);
+        }
+        assert!(map
+            .range(..)
+            .map(|(k, _)| *k)
+            .eq((2..=1_000_u32).step_by(2)));
+        assert!(map.range(10..20).map(|(k, _)| *k).eq([10, 12, 14, 16, 18]));
+        assert!(map.range(11..=20).map(|(k, _)| *k).eq([12, 14, 16, 18, 20]));
+        assert!(map.range(..5).map(|(k, _)| *k).eq([2, 4]));
+        assert!(map.range(995..).map(|(k, _)| *k).eq([996, 998, 1_000]));
+        assert_eq!(map.range(1_001..).count(), 0_usize);
+        assert_eq!(map.range(7..8).count(), 0_usize);
+        let bounds = (Bound::Excluded(&10_u32), Bound::Excluded(&14_u32));
+        assert_eq!(map.range(bounds).as_slice(), &[(&12, &6)]);
+    }
+
+    #[test]
+    fn test_range() {
+        range(&mut BST::new());
+        range(&mut RedBlackTree::new());
+        range(&mut BalancedTree::new());
+    }
+
+    fn fold_ranges<M: SedgewickMap<u32, u32> + Default>() {
+        let mut map = M::default();
+        // 7 is coprime to 400, so every key in 0..400 comes once
+        for k in (0_u32..400).map(|i| i * 7 % 400) {
+            map.put(k * 2, k);
+        }
+        for (lo, hi) in [(0_u32, 800_u32), (11, 400), (100, 101), (799, 900)] {
+            let folded = map.fold_range(lo..hi, Vec::new(), |mut keys, k, _| {
+                keys.push(*k);
+                keys
+            });
+            assert!(folded.iter().eq(map.range(lo..hi).map(|(k, _)| k)));
+        }
+        // a stop skips the rest, `f` sees no entry past it
+        let mut calls = 0_usize;
+        let stopped = map.try_fold_range(.., 0_u32, |sum, k, v| {
+            calls += 1_usize;
+            if k.ge(&100) {
+                Err(sum)
+            } else {
+                Ok(sum + v)
+            }
+        });
+        assert_eq!((stopped, calls), (Err((0..50).sum()), 51_usize));
+    }
+
+    #[test]
+    fn test_fold_range() {
+        fold_ranges::<BST<u32, u32>>();
+        fold_ranges::<RedBlackTree<u32, u32>>();
+        fold_ranges::<BalancedTree<u32, u32>>();
+    }
+
+    #[test]
+    fn test_trait_objects() {
+        let mut maps: Vec<Box<dyn SedgewickMap<u32, u32>>> = vec![
+            Box::new(BST::new()),
+            Box::new(RedBlackTree::new()),
+            Box::new(BalancedTree::new()),
+        ];
+        for map in maps.iter_mut() {
+            assert!(map.is_empty());
+            for i in [5_u32, 3, 8, 1, 4] {
+                map.put(i, i * 10);
+            }
+            assert_eq!(map.size(), 5_usize);
+            assert_eq!(map.get(&3), Some(&30));
+            assert_eq!(map.min(), Some(&1));
+            assert_eq!(map.max(), Some(&8));
+            assert_eq!(map.floor(&7), Some(&5));
+            assert_eq!(map.ceiling(&6), Some(&8));
+            assert_eq!(map.smallest(2).as_slice(), &[(&1, &10), (&3, &30)]);
+        }
+    }
+
+    fn round_trip<A: SedgewickMap<u32, u32> + Default, B: SedgewickMap<u32, u32> + Default>() {
+        let mut a = A::default();
+        for i in 0_u32..100 {
+            a.put(i, i * 2);
+        }
+        let b: B = convert(&a);
+        assert_eq!(b.size(), 100_usize);
+        assert!(b.range(..).eq(a.range(..)));
+        let back: A = convert(&b);
+        assert!(back.range(..).eq(a.range(..)));
+    }
+
+    #[test]
+    fn test_convert() {
+        round_trip::<BST<u32, u32>, RedBlackTree<u32, u32>>();
+        round_trip::<RedBlackTree<u32, u32>, BalancedTree<u32, u32>>();
+        round_trip::<BalancedTree<u32, u32>, BST<u32, u32>>();
+
+        let mut bst: BST<u32, u32> = BST::new();
+        for i in 0_u32..127 {
+            bst.put(i, i);
+        }
+        assert_eq!(bst.height(), Some(126_usize));
+        let balanced: BST<u32, u32> = convert(&bst);
+        assert_eq!(balanced.height(), Some(6_usize));
+
+        let std: StdBTreeAdapter<u32, u32> = convert(&balanced);
+        assert!(std.range(..).eq(bst.range(..)));
+        let empty: RedBlackTree<u32, u32> = convert(&StdBTreeAdapter::new());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_invert() {
+        let mut bst: BST<u32, u32> = BST::new();
+        for i in 0_u32..100 {
+            bst.put(i, i % 7);
+        }
+        let index: RedBlackTree<u32, Vec<u32>> = invert(&bst);
+        assert_eq!(index.size(), 7_usize);
+        for (v, keys) in index.range(..) {
+            assert!(keys.iter().copied().eq((*v..100).step_by(7)));
+        }
+
+        // a plain BST target comes out balanced
+        let index: BST<u32, Vec<u32>> = invert(&convert::<_, _, _, BalancedTree<_, _>>(&bst));
+        assert_eq!(index.height(), Some(2_usize));
+        let empty: StdBTreeAdapter<u32, Vec<u32>> = invert(&BST::<u32, u32>::new());
+        assert!(empty.is_empty());
+    }
+
+    fn try_build<M: SedgewickMap<u32, u32> + Default>() {
+        let map = M::try_from_iter((0_u32..50).map(|i| (i, i))).unwrap();
+        assert_eq!(map.size(), 50_usize);
+        let pairs = [(3_u32, 0_u32), (1, 1), (4, 2), (1, 3), (5, 4), (5, 5)];
+        let err = M::try_from_iter(pairs.iter().copied()).err().unwrap();
+        assert_eq!(err, DuplicateKeyError::new(1));
+        assert_eq!(
+            Error::from(err),
+            Error::DuplicateKey(DuplicateKeyError::new(1))
+        );
+        assert!(M::try_from_iter(Vec::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_try_from_iter() {
+        try_build::<BST<u32, u32>>();
+        try_build::<RedBlackTree<u32, u32>>();
+        try_build::<BalancedTree<u32, u32>>();
+    }
+
+    fn height_semantics<M: SedgewickMap<u32, u32> + Default>() {
+        let mut map = M::default();
+        assert_eq!(map.height(), None);
+        assert_eq!(map.levels(), 0_usize);
+        map.put(1, 1);
+        assert_eq!(map.height(), Some(0_usize));
+        assert_eq!(map.levels(), 1_usize);
+    }
+
+    #[test]
+    fn test_height_semantics() {
+        height_semantics::<BST<u32, u32>>();
+        height_semantics::<RedBlackTree<u32, u32>>();
+        height_semantics::<BalancedTree<u32, u32>>();
+    }
+
+    fn leaf_depth_spread<M: SedgewickMap<u64, u64> + Default>(keys: &[u64]) -> (usize, usize) {
+        let mut map = M::default();
+        assert!(map.leaf_depths().is_empty());
+        for k in keys {
+            map.put(*k, *k);
+        }
+        let depths = map.leaf_depths();
+        let (lo, hi) = (depths.iter().min(), depths.iter().max());
+        assert_eq!(hi, map.height().as_ref());
+        (*lo.unwrap(), *hi.unwrap())
+    }
+
+    #[test]
+    fn test_leaf_depths() {
+        let sorted: Vec<u64> = (0_u64..255).collect();
+        // a sorted run leaves a `BST` a single leaf at the bottom
+        assert_eq!(leaf_depth_spread::<BST<u64, u64>>(&sorted), (254, 254));
+        let (lo, hi) = leaf_depth_spread::<RedBlackTree<u64, u64>>(&sorted);
+        assert!(hi.le(&(2_usize * lo + 1_usize)));
+        let (lo, hi) = leaf_depth_spread::<BalancedTree<u64, u64>>(&sorted);
+        assert_eq!(lo, hi);
+        let shuffled = crate::bench_support::shuffled_keys(255, 25);
+        let (lo, hi) = leaf_depth_spread::<BST<u64, u64>>(&shuffled);
+        assert!(lo.lt(&hi));
+    }
+
+    fn uniform<M>() -> String
+    where
+        M: OrderedMap<u32, u32> + Default + for<'a> std::ops::Index<&'a u32, Output = u32>,
+        M: std::fmt::Display,
+    {
+        let mut map = M::default();
+        for k in [2_u32, 1, 3] {
+            map.put(k, k * 10_u32);
+        }
+        assert_eq!(map[&3], 30_u32);
+        map.to_string()
+    }
+
+    #[test]
+    fn test_default_index_display() {
+        assert_eq!(uniform::<BST<u32, u32>>(), "2:20(1:10,3:30)");
+        let rbtree = uniform::<RedBlackTree<u32, u32>>();
+        assert_eq!(rbtree, "2:20(1:10,3:30)");
+        let parsed: RedBlackTree<u32, u32> = rbtree.parse().unwrap();
+        assert_eq!(parsed.to_string(), rbtree);
+        assert_eq!(uniform::<BalancedTree<u32, u32>>(), "{1: 10, 2: 20, 3: 30}");
+        assert_eq!(BalancedTree::<u32, u32>::new().to_string(), "{}");
+    }
+
+    fn delete_ends<M: SedgewickMap<u64, u64> + Default>() {
+        let mut map = M::default();
+        let mut model = std::collections::BTreeMap::new();
+        assert_eq!((map.delete_min(), map.delete_max()), (None, None));
+        for k in crate::bench_support::shuffled_keys(500, 12) {
+            map.put(k, k * 2_u64);
+            model.insert(k, k * 2_u64);
+        }
+        for i in 0_usize..500 {
+            if i % 3_usize == 0_usize {
+                assert_eq!(map.delete_max(), model.pop_last());
+            } else {
+                assert_eq!(map.delete_min(), model.pop_first());
+            }
+            assert_eq!(map.size(), model.len());
+            assert_eq!(map.min(), model.keys().next());
+            assert_eq!(map.max(), model.keys().next_back());
+        }
+        assert!(map.is_empty() && map.height().is_none());
+    }
+
+    #[test]
+    fn test_delete_min_and_max() {
+        delete_ends::<BST<u64, u64>>();
+        delete_ends::<RedBlackTree<u64, u64>>();
+        delete_ends::<BalancedTree<u64, u64>>();
+    }
+
+    fn double_ended_queue<M: SedgewickMap<u64, u64> + Default>() {
+        let mut queue = M::default();
+        let mut model = std::collections::BTreeMap::new();
+        for (i, k) in crate::bench_support::shuffled_keys(300, 24)
+            .iter()
+            .enumerate()
+        {
+            queue.put(*k, *k);
+            model.insert(*k, *k);
+            // drain one end every third push, a scheduler under load
+            if i % 3_usize == 2_usize {
+                assert_eq!(queue.pop_min(), model.pop_first());
+                assert_eq!(queue.pop_max(), model.pop_last());
+            }
+        }
+        while let Some(entry) = queue.pop_max() {
+            assert_eq!(Some(entry), model.pop_last());
+        }
+        assert!(model.is_empty());
+    }
+
+    #[test]
+    fn test_pop_min_and_max() {
+        double_ended_queue::<BST<u64, u64>>();
+        double_ended_queue::<RedBlackTree<u64, u64>>();
+        double_ended_queue::<BalancedTree<u64, u64>>();
+    }
+
+    fn order_statistics<M: SedgewickMap<u64, u64> + Default>() {
+        let mut map = M::default();
+        assert_eq!((map.rank(&1), map.select(0)), (0_usize, None));
+        let keys: Vec<u64> = crate::bench_support::shuffled_keys(400, 15)
+            .iter()
+            .map(|k| k * 2_u64)
+            .collect();
+        for k in keys.iter() {
+            map.put(*k, *k);
+        }
+        let mut sorted = keys.clone();
+        sorted.sort_unstable();
+        for (i, k) in sorted.iter().enumerate() {
+            assert_eq!(map.select(i), Some(k));
+            assert_eq!(map.rank(k), i);
+            // odd keys are all missing
+            assert_eq!(map.rank(&(k + 1_u64)), i + 1_usize);
+        }
+        assert_eq!(map.select(sorted.len()), None);
+    }
+
+    #[test]
+    fn test_rank_and_select() {
+        order_statistics::<BST<u64, u64>>();
+        order_statistics::<RedBlackTree<u64, u64>>();
+        order_statistics::<BalancedTree<u64, u64>>();
+    }
+
+    fn partition_points<M: SedgewickMap<u64, u64> + Default>() {
+        let mut map = M::default();
+        assert_eq!(map.partition_point(|_| false), None);
+        for k in crate::bench_support::shuffled_keys(500, 21) {
+            map.put(k * 3_u64, k);
+        }
+        for key in 0_u64..1502 {
+            assert_eq!(map.partition_point(|k| k.lt(&key)), map.ceiling(&key));
+            assert_eq!(
+                map.partition_point(|k| k.le(&key)),
+                map.ceiling(&(key + 1_u64))
+            );
+        }
+        assert_eq!(map.partition_point(|_| false), map.min());
+    }
+
+    #[test]
+    fn test_partition_point() {
+        partition_points::<BST<u64, u64>>();
+        partition_points::<RedBlackTree<u64, u64>>();
+        partition_points::<BalancedTree<u64, u64>>();
+    }
+
+    fn neighbors<M: SedgewickMap<u64, u64> + Default>() {
+        let mut map = M::default();
+        assert_eq!((map.successor(&1), map.predecessor(&1)), (None, None));
+        let mut model = std::collections::BTreeMap::new();
+        for k in crate::bench_support::shuffled_keys(500, 26) {
+            map.put(k * 2_u64, k);
+            model.insert(k * 2_u64, k);
+        }
+        for key in 0_u64..1002 {
+            assert_eq!(map.successor(&key), model.range(key + 1_u64..).next());
+            assert_eq!(map.predecessor(&key), model.range(..key).next_back());
+        }
+    }
+
+    #[test]
+    fn test_successor_and_predecessor() {
+        neighbors::<BST<u64, u64>>();
+        neighbors::<RedBlackTree<u64, u64>>();
+        neighbors::<BalancedTree<u64, u64>>();
+    }
+
+    fn drain_all<M: SedgewickMap<u64, String> + Default>() {
+        let mut map = M::default();
+        assert_eq!(map.drain().len(), 0_usize);
+        let mut model = std::collections::BTreeMap::new();
+        for k in crate::bench_support::shuffled_keys(600, 20) {
+            map.put(k, k.to_string());
+            model.insert(k, k.to_string());
+        }
+        assert!(map.drain().eq(model.into_iter()));
+        assert!(map.is_empty() && map.height().is_none());
+        map.put(1, String::from("1"));
+        assert_eq!(map.size(), 1_usize);
+    }
+
+    #[test]
+    fn test_drain() {
+        drain_all::<BST<u64, String>>();
+        drain_all::<RedBlackTree<u64, String>>();
+        drain_all::<BalancedTree<u64, String>>();
+    }
+
+    fn find_firsts_and_lasts<M: SedgewickMap<u64, u64> + Default>() {
+        let mut map = M::default();
+        assert_eq!(map.find_first(|_, _| true), None);
+        let mut model = std::collections::BTreeMap::new();
+        for k in crate::bench_support::shuffled_keys(500, 29) {
+            map.put(k, k % 7);
+            model.insert(k, k % 7);
+        }
+        for r in 0_u64..8 {
+            let mut calls = 0_usize;
+            let first = map.find_first(|_, v| {
+                calls += 1_usize;
+                v.eq(&r)
+            });
+            let expected = model.iter().position(|(_, v)| v.eq(&r));
+            assert_eq!(first, model.iter().find(|(_, v)| (*v).eq(&r)));
+            // stops at the match
+            assert_eq!(calls, expected.map_or(model.len(), |i| i + 1_usize));
+            let last = map.find_last(|_, v| v.eq(&r));
+            assert_eq!(last, model.iter().rev().find(|(_, v)| (*v).eq(&r)));
+        }
+    }
+
+    #[test]
+    fn test_find_first_and_last() {
+        find_firsts_and_lasts::<BST<u64, u64>>();
+        find_firsts_and_lasts::<RedBlackTree<u64, u64>>();
+        find_firsts_and_lasts::<BalancedTree<u64, u64>>();
+    }
+
+    fn values_found<M: SedgewickMap<u64, u64> + Default>() {
+        let mut map = M::default();
+        assert!(!map.contains_value(&0));
+        let mut model = std::collections::BTreeMap::new();
+        for k in crate::bench_support::shuffled_keys(300, 32) {
+            map.put(k, k % 11);
+            model.insert(k, k % 11);
+        }
+        for v in 0_u64..12 {
+            let expected = model.iter().find(|(_, w)| (*w).eq(&v)).map(|(k, _)| k);
+            assert_eq!(map.find_by_value(&v), expected);
+            assert_eq!(map.contains_value(&v), expected.is_some());
+        }
+    }
+
+    #[test]
+    fn test_contains_value() {
+        values_found::<BST<u64, u64>>();
+        values_found::<RedBlackTree<u64, u64>>();
+        values_found::<BalancedTree<u64, u64>>();
+    }
+
+    fn extreme_values<M: SedgewickMap<u64, u64> + Default>() {
+        let mut map = M::default();
+        assert_eq!(map.min_by_value(u64::cmp), None);
+        let mut model = std::collections::BTreeMap::new();
+        for k in crate::bench_support::shuffled_keys(400, 36) {
+            map.put(k, k % 13);
+            model.insert(k, k % 13);
+        }
+        assert_eq!(
+            map.min_by_value(u64::cmp),
+            model.iter().min_by(|a, b| a.1.cmp(b.1))
+        );
+        assert_eq!(
+            map.max_by_value(u64::cmp),
+            model.iter().max_by(|a, b| a.1.cmp(b.1))
+        );
+        // ties go to the smallest key for the minimum, the largest for the maximum
+        let (lo, _) = map.min_by_value(|_, _| std::cmp::Ordering::Equal).unwrap();
+        let (hi, _) = map.max_by_value(|_, _| std::cmp::Ordering::Equal).unwrap();
+        assert_eq!((lo, hi), (map.min().unwrap(), map.max().unwrap()));
+    }
+
+    #[test]
+    fn test_min_and_max_by_value() {
+        extreme_values::<BST<u64, u64>>();
+        extreme_values::<RedBlackTree<u64, u64>>();
+        extreme_values::<BalancedTree<u64, u64>>();
+    }
+
+    fn put_policies<M: SedgewickMap<u32, u32> + Default>() {
+        let mut map = M::default();
+        for k in 0_u32..100 {
+            assert!(map.put_with_policy(k, k, &mut PutPolicy::Panic));
+        }
+        for k in 0_u32..120 {
+            assert_eq!(
+                map.put_with_policy(k, 0, &mut PutPolicy::KeepExisting),
+                k.ge(&100)
+            );
+        }
+        let mut merges = Vec::new();
+        let mut add = PutPolicy::merge_with(|old, new| {
+            merges.push(*old);
+            old + new
+        });
+        for k in (0_u32..100).step_by(2) {
+            assert!(!map.put_with_policy(k, 1000, &mut add));
+        }
+        drop(add);
+        assert!(merges.into_iter().eq((0_u32..100).step_by(2)));
+        map.put_with_policy(1, 5, &mut PutPolicy::Overwrite);
+        assert_eq!(map.size(), 120_usize);
+        assert_eq!((map.get(&0), map.get(&1)), (Some(&1000), Some(&5)));
+        assert_eq!((map.get(&2), map.get(&3)), (Some(&1002), Some(&3)));
+        assert_eq!(map.get(&110), Some(&0));
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            map.put_with_policy(4, 0, &mut PutPolicy::Panic);
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(map.get(&4), Some(&1004));
+    }
+
+    #[test]
+    fn test_put_with_policy() {
+        put_policies::<BST<u32, u32>>();
+        put_policies::<RedBlackTree<u32, u32>>();
+        put_policies::<BalancedTree<u32, u32>>();
+        // the default of `Map`, by `get` and `put`
+        put_policies::<crate::tracked::Tracked<BST<u32, u32>>>();
+    }
+
+    fn quantiles<M: SedgewickMap<u64, ()> + Default>() {
+        let mut map = M::default();
+        assert_eq!((map.median(), map.quantile(0.5)), (None, None));
+        let mut keys = crate::bench_support::shuffled_keys(777, 33);
+        for k in &keys {
+            map.put(*k, ());
+        }
+        keys.sort_unstable();
+        keys.dedup();
+        let n = keys.len();
+        assert_eq!(map.median(), Some(&keys[(n - 1_usize) / 2_usize]));
+        for (q, rank) in [
+            (0.0, 1_usize),
+            (0.5, n.div_ceil(2)),
+            (0.99, (n * 99).div_ceil(100)),
+            (1.0, n),
+        ] {
+            assert_eq!(map.quantile(q), Some(&keys[rank.max(1_usize) - 1_usize]));
+        }
+        assert_eq!(map.quantile(-0.1), None);
+        assert_eq!(map.quantile(f64::NAN), None);
+    }
+
+    #[test]
+    fn test_quantile() {
+        quantiles::<BST<u64, ()>>();
+        quantiles::<RedBlackTree<u64, ()>>();
+        quantiles::<BalancedTree<u64, ()>>();
+    }
 }
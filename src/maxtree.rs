@@ -0,0 +1,479 @@
+use crate::{reaches_above, reaches_below, Map, OrderedMap};
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+type Link<K, V> = Option<Box<Node<K, V>>>;
+
+/// An AVL tree map keeping the maximum value of every subtree.
+///
+/// Rotations and updates recompute the maximum of the nodes they touch, so
+/// `max_value_in_range` answers from whole subtrees inside the range and
+/// walks two paths down, `O(log n)` for any range. Wrap values in
+/// `std::cmp::Reverse` to track the minimum instead.
+///
+/// # Examples
+///
+/// ```
+/// use treers::maxtree::MaxTree;
+/// use treers::Map;
+///
+/// // peak latency by minute
+/// let mut peaks = MaxTree::new();
+/// for (minute, ms) in [(0_u32, 12_u32), (1, 40), (2, 9), (3, 31), (4, 55)] {
+///     peaks.put(minute, ms);
+/// }
+/// assert_eq!(peaks.max_value(), Some(&55));
+/// assert_eq!(peaks.max_value_in_range(1..4), Some(&40));
+/// assert_eq!(peaks.max_value_in_range(2..=3), Some(&31));
+/// assert_eq!(peaks.max_value_in_range(5..), None);
+///
+/// peaks.remove(&1);
+/// assert_eq!(peaks.max_value_in_range(..3), Some(&12));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MaxTree<K: Ord, V: Ord + Clone> {
+    root: Link<K, V>,
+    size: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    // Largest value of the subtree
+    max: V,
+    // Nodes on the longest path down, `1` for a leaf
+    height: usize,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+impl<K: Ord, V: Ord + Clone> MaxTree<K, V> {
+    /// Inits a new empty tree.
+    pub const fn new() -> Self {
+        Self {
+            root: None,
+            size: 0_usize,
+        }
+    }
+
+    /// Returns the largest value, `None` when empty.
+    pub fn max_value(&self) -> Option<&V> {
+        self.root.as_ref().map(|node| &node.max)
+    }
+
+    /// Returns the entry of the largest value, the one of the largest key
+    /// among equal values, as `max_by_value` with `Ord::cmp` finds it.
+    ///
+    /// The maximum of every subtree leads one path down, `O(log n)` instead
+    /// of a scan.
+    pub fn max_entry(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_ref()?;
+        loop {
+            match &node.right {
+                Some(right) if right.max.eq(&node.max) => node = right,
+                _ if node.value.eq(&node.max) => return Some((&node.key, &node.value)),
+                _ => node = node.left.as_ref()?,
+            }
+        }
+    }
+
+    /// Returns the largest value of keys inside `range`, `None` when no key
+    /// is.
+    pub fn max_value_in_range<R: RangeBounds<K>>(&self, range: R) -> Option<&V> {
+        max_in(&self.root, &range, false, false)
+    }
+
+    /// Removes `key`, returning its value if it was there.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (root, removed) = remove(self.root.take(), key);
+        self.root = root;
+        if removed.is_some() {
+            self.size -= 1_usize;
+        }
+        removed
+    }
+
+    /// Returns a count of nodes on the longest path down, `0` when empty.
+    pub const fn levels(&self) -> usize {
+        height(&self.root)
+    }
+}
+
+impl<K: Ord, V: Ord + Clone> Default for MaxTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V: Ord + Clone> Map<K, V> for MaxTree<K, V> {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        let mut link = &self.root;
+        while let Some(node) = link {
+            match key.cmp(&node.key) {
+                Ordering::Less => link = &node.left,
+                Ordering::Greater => link = &node.right,
+                _ => return Some(&node.value),
+            }
+        }
+        None
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        let mut added = false;
+        self.root = Some(insert(self.root.take(), key, value, &mut added));
+        if added {
+            self.size += 1_usize;
+        }
+    }
+}
+
+impl<K: Ord, V: Ord + Clone> OrderedMap<K, V> for MaxTree<K, V> {
+    fn min(&self) -> Option<&K> {
+        let mut node = self.root.as_ref()?;
+        while let Some(left) = &node.left {
+            node = left;
+        }
+        Some(&node.key)
+    }
+
+    fn max(&self) -> Option<&K> {
+        let mut node = self.root.as_ref()?;
+        while let Some(right) = &node.right {
+            node = right;
+        }
+        Some(&node.key)
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        let mut found = None;
+        let mut link = &self.root;
+        while let Some(node) = link {
+            match key.cmp(&node.key) {
+                Ordering::Less => link = &node.left,
+                Ordering::Greater => {
+                    found = Some(&node.key);
+                    link = &node.right;
+                }
+                _ => return Some(&node.key),
+            }
+        }
+        found
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        let mut found = None;
+        let mut link = &self.root;
+        while let Some(node) = link {
+            match key.cmp(&node.key) {
+                Ordering::Less => {
+                    found = Some(&node.key);
+                    link = &node.left;
+                }
+                Ordering::Greater => link = &node.right,
+                _ => return Some(&node.key),
+            }
+        }
+        found
+    }
+
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let vec: Vec<(&K, &V)> = self.range(..).take(n).collect();
+        vec.into_iter()
+    }
+
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let vec: Vec<(&K, &V)> = self.range(..).rev().take(n).collect();
+        vec.into_iter()
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        let mut vec = Vec::new();
+        take_range(&self.root, &range, &mut vec);
+        vec.into_iter()
+    }
+}
+
+const fn height<K, V>(link: &Link<K, V>) -> usize {
+    match link {
+        Some(node) => node.height,
+        None => 0_usize,
+    }
+}
+
+// Recomputes height and maximum of `node` from its children
+fn fix<K, V: Ord + Clone>(node: &mut Node<K, V>) {
+    node.height = 1_usize + height(&node.left).max(height(&node.right));
+    let mut max = &node.value;
+    for child in [&node.left, &node.right].iter().filter_map(|c| c.as_ref()) {
+        max = max.max(&child.max);
+    }
+    node.max = max.clone();
+}
+
+fn rotate_left<K, V: Ord + Clone>(mut node: Box<Node<K, V>>) -> Box<Node<K, V>> {
+    match node.right.take() {
+        Some(mut right) => {
+            node.right = right.left.take();
+            fix(&mut node);
+            right.left = Some(node);
+            fix(&mut right);
+            right
+        }
+        None => node,
+    }
+}
+
+fn rotate_right<K, V: Ord + Clone>(mut node: Box<Node<K, V>>) -> Box<Node<K, V>> {
+    match node.left.take() {
+        Some(mut left) => {
+            node.left = left.right.take();
+            fix(&mut node);
+            left.right = Some(node);
+            fix(&mut left);
+            left
+        }
+        None => node,
+    }
+}
+
+// Restores the AVL property at `node`, its subtrees differing by 2 at most
+fn balance<K, V: Ord + Clone>(mut node: Box<Node<K, V>>) -> Box<Node<K, V>> {
+    fix(&mut node);
+    let (left, right) = (height(&node.left), height(&node.right));
+    if left.gt(&(right + 1_usize)) {
+        if let Some(l) = node.left.take() {
+            node.left = Some(if height(&l.left).lt(&height(&l.right)) {
+                rotate_left(l)
+            } else {
+                l
+            });
+        }
+        rotate_right(node)
+    } else if right.gt(&(left + 1_usize)) {
+        if let Some(r) = node.right.take() {
+            node.right = Some(if height(&r.right).lt(&height(&r.left)) {
+                rotate_right(r)
+            } else {
+                r
+            });
+        }
+        rotate_left(node)
+    } else {
+        node
+    }
+}
+
+fn insert<K: Ord, V: Ord + Clone>(
+    link: Link<K, V>,
+    key: K,
+    value: V,
+    added: &mut bool,
+) -> Box<Node<K, V>> {
+    let mut node = match link {
+        Some(node) => node,
+        None => {
+            *added = true;
+            return Box::new(Node {
+                key,
+                max: value.clone(),
+                value,
+                height: 1_usize,
+                left: None,
+                right: None,
+            });
+        }
+    };
+    match key.cmp(&node.key) {
+        Ordering::Less => node.left = Some(insert(node.left.take(), key, value, added)),
+        Ordering::Greater => node.right = Some(insert(node.right.take(), key, value, added)),
+        _ => node.value = value,
+    }
+    balance(node)
+}
+
+fn remove<K: Ord, V: Ord + Clone>(link: Link<K, V>, key: &K) -> (Link<K, V>, Option<V>) {
+    let mut node = match link {
+        Some(node) => node,
+        None => return (None, None),
+    };
+    let removed = match key.cmp(&node.key) {
+        Ordering::Less => {
+            let (left, removed) = remove(node.left.take(), key);
+            node.left = left;
+            removed
+        }
+        Ordering::Greater => {
+            let (right, removed) = remove(node.right.take(), key);
+            node.right = right;
+            removed
+        }
+        _ => {
+            let Node {
+                key: _,
+                value,
+                max: _,
+                height: _,
+                left,
+                right,
+            } = *node;
+            return match (left, right) {
+                (None, right) => (right, Some(value)),
+                (left, None) => (left, Some(value)),
+                (left, Some(right)) => {
+                    // the successor, smallest of the right subtree, takes
+                    // the place of the node
+                    let (right, mut successor) = remove_min(right);
+                    successor.left = left;
+                    successor.right = right;
+                    (Some(balance(successor)), Some(value))
+                }
+            };
+        }
+    };
+    (Some(balance(node)), removed)
+}
+
+// Takes the node of the smallest key out of the subtree under `node`
+fn remove_min<K, V: Ord + Clone>(mut node: Box<Node<K, V>>) -> (Link<K, V>, Box<Node<K, V>>) {
+    match node.left.take() {
+        Some(left) => {
+            let (left, min) = remove_min(left);
+            node.left = left;
+            (Some(balance(node)), min)
+        }
+        None => (node.right.take(), node),
+    }
+}
+
+// `lo` and `hi` are set once the range is known to hold every key of the
+// subtree below, respectively above, so a subtree inside it answers with
+// its maximum
+fn max_in<'a, K: Ord, V: Ord, R: RangeBounds<K>>(
+    link: &'a Link<K, V>,
+    range: &R,
+    lo: bool,
+    hi: bool,
+) -> Option<&'a V> {
+    let node = link.as_ref()?;
+    if lo && hi {
+        return Some(&node.max);
+    }
+    let left = if reaches_below(range, &node.key) {
+        max_in(
+            &node.left,
+            range,
+            lo,
+            hi || ends_at_or_past(range, &node.key),
+        )
+    } else {
+        None
+    };
+    let here = if range.contains(&node.key) {
+        Some(&node.value)
+    } else {
+        None
+    };
+    let right = if reaches_above(range, &node.key) {
+        max_in(
+            &node.right,
+            range,
+            lo || starts_at_or_before(range, &node.key),
+            hi,
+        )
+    } else {
+        None
+    };
+    left.max(here).max(right)
+}
+
+// Every key less than `key` is below the end of `range`
+fn ends_at_or_past<K: Ord, R: RangeBounds<K>>(range: &R, key: &K) -> bool {
+    match range.end_bound() {
+        Bound::Included(end) | Bound::Excluded(end) => key.le(end),
+        Bound::Unbounded => true,
+    }
+}
+
+// Every key greater than `key` is above the start of `range`
+fn starts_at_or_before<K: Ord, R: RangeBounds<K>>(range: &R, key: &K) -> bool {
+    match range.start_bound() {
+        Bound::Included(start) | Bound::Excluded(start) => start.le(key),
+        Bound::Unbounded => true,
+    }
+}
+
+fn take_range<'a, K: Ord, V, R: RangeBounds<K>>(
+    link: &'a Link<K, V>,
+    range: &R,
+    vec: &mut Vec<(&'a K, &'a V)>,
+) {
+    if let Some(node) = link {
+        if reaches_below(range, &node.key) {
+            take_range(&node.left, range, vec);
+        }
+        if range.contains(&node.key) {
+            vec.push((&node.key, &node.value));
+        }
+        if reaches_above(range, &node.key) {
+            take_range(&node.right, range, vec);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MaxTree;
+    use crate::bench_support::shuffled_keys;
+    use crate::{Map, OrderedMap};
+    use std::collections::BTreeMap;
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    #[test]
+    fn test_against_model() {
+        let mut tree = MaxTree::new();
+        let mut model = BTreeMap::new();
+        let keys = shuffled_keys(3000, 23);
+        for (i, k) in keys.iter().enumerate() {
+            let (k, v) = (k % 500, keys[keys.len() - 1 - i] % 1000);
+            if i % 4 == 0 {
+                assert_eq!(tree.remove(&k), model.remove(&k));
+            } else {
+                tree.put(k, v);
+                model.insert(k, v);
+            }
+            assert_eq!(tree.size(), model.len());
+            assert_eq!(tree.max_value(), model.values().max());
+            assert_eq!(tree.max_entry(), tree.max_by_value(u64::cmp));
+        }
+        // AVL trees are at most 1.44 lg n deep
+        assert!(tree.levels().le(&13_usize));
+        assert!(tree.range(..).eq(model.iter()));
+        for lo in (0_u64..510).step_by(7) {
+            for hi in (lo..510).step_by(13) {
+                for range in [
+                    (Included(lo), Excluded(hi)),
+                    (Excluded(lo), Included(hi)),
+                    (Included(lo), Unbounded),
+                    (Unbounded, Included(hi)),
+                ] {
+                    let best = model.range(range).map(|(_, v)| v).max();
+                    assert_eq!(tree.max_value_in_range(range), best);
+                }
+            }
+        }
+        assert_eq!(
+            tree.floor(&250),
+            model.range(..=250).next_back().map(|(k, _)| k)
+        );
+        assert_eq!(
+            tree.ceiling(&250),
+            model.range(250..).next().map(|(k, _)| k)
+        );
+        assert!(tree.largest(3).eq(model.iter().rev().take(3)));
+    }
+}
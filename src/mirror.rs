@@ -0,0 +1,344 @@
+use crate::{Map, OrderedMap, SedgewickMap};
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Debug};
+use std::ops::{Bound, RangeBounds};
+
+type DivergenceHook = Box<dyn FnMut(&Divergence)>;
+
+/// A read the two maps of a `Mirrored` answered differently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    op: &'static str,
+    primary: String,
+    shadow: String,
+}
+
+impl Divergence {
+    /// Returns the name of the diverging call, e.g. `"get"`.
+    pub const fn op(&self) -> &'static str {
+        self.op
+    }
+
+    /// Returns the answer of the primary map, `Debug` formatted.
+    pub fn primary(&self) -> &str {
+        &self.primary
+    }
+
+    /// Returns the answer of the shadow map, `Debug` formatted.
+    pub fn shadow(&self) -> &str {
+        &self.shadow
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} diverged: primary {}, shadow {}",
+            self.op, self.primary, self.shadow
+        )
+    }
+}
+
+/// A map writing through to a shadow map, for migrating between backends.
+///
+/// Every mutation goes to both maps, every read to both as well, and the
+/// primary answers. Answers that differ are counted by `divergences` and
+/// passed to the `on_divergence` hooks, so a new backend runs next to the
+/// old one until it has proven itself. Heights and leaf depths are the
+/// primary's alone, shapes of different trees differ by design.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use treers::bst::BST;
+/// use treers::mirror::Mirrored;
+/// use treers::rbtree::RedBlackTree;
+/// use treers::Map;
+///
+/// let mut map = Mirrored::new(BST::new(), RedBlackTree::new());
+/// let log = Rc::new(RefCell::new(Vec::new()));
+/// let l = Rc::clone(&log);
+/// map.on_divergence(move |d| l.borrow_mut().push(d.to_string()));
+///
+/// map.put(1, 'a');
+/// map.put(2, 'b');
+/// assert_eq!(map.get(&1), Some(&'a'));
+/// assert_eq!(map.divergences(), 0_usize);
+///
+/// // a bug of one backend, simulated by writing past the wrapper
+/// let (mut bst, rbtree) = map.into_inner();
+/// bst.put(2, 'x');
+/// let mut map = Mirrored::new(bst, rbtree);
+/// let l = Rc::clone(&log);
+/// map.on_divergence(move |d| l.borrow_mut().push(d.to_string()));
+/// assert_eq!(map.get(&2), Some(&'x'));
+/// assert_eq!(map.divergences(), 1_usize);
+/// assert_eq!(*log.borrow(), ["get diverged: primary Some('x'), shadow Some('b')"]);
+/// ```
+pub struct Mirrored<A, B> {
+    primary: A,
+    shadow: B,
+    divergences: Cell<usize>,
+    // Reads take `&self`, the hooks are borrowed to run them
+    on_divergence: RefCell<Vec<DivergenceHook>>,
+}
+
+impl<A, B> Mirrored<A, B> {
+    /// Mirrors `primary` to `shadow`, both expected to hold the same
+    /// entries, e.g. both empty.
+    pub const fn new(primary: A, shadow: B) -> Self {
+        Self {
+            primary,
+            shadow,
+            divergences: Cell::new(0_usize),
+            on_divergence: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Adds a hook called with every divergence.
+    pub fn on_divergence<F: FnMut(&Divergence) + 'static>(&mut self, f: F) {
+        self.on_divergence.get_mut().push(Box::new(f));
+    }
+
+    /// Returns a count of reads answered differently so far.
+    pub const fn divergences(&self) -> usize {
+        self.divergences.get()
+    }
+
+    /// Returns a reference to the primary map.
+    pub const fn primary(&self) -> &A {
+        &self.primary
+    }
+
+    /// Returns a reference to the shadow map.
+    pub const fn shadow(&self) -> &B {
+        &self.shadow
+    }
+
+    /// Drops the hooks and returns the primary and the shadow map.
+    pub fn into_inner(self) -> (A, B) {
+        (self.primary, self.shadow)
+    }
+
+    // Answers with `primary`, reporting a `shadow` that differs
+    fn check<T: PartialEq + Debug>(&self, op: &'static str, primary: T, shadow: T) -> T {
+        if primary.ne(&shadow) {
+            self.divergences.set(self.divergences.get() + 1_usize);
+            let divergence = Divergence {
+                op,
+                primary: format!("{:?}", primary),
+                shadow: format!("{:?}", shadow),
+            };
+            for hook in self.on_divergence.borrow_mut().iter_mut() {
+                hook(&divergence);
+            }
+        }
+        primary
+    }
+}
+
+impl<A: Debug, B: Debug> Debug for Mirrored<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mirrored")
+            .field("primary", &self.primary)
+            .field("shadow", &self.shadow)
+            .field("divergences", &self.divergences())
+            .finish()
+    }
+}
+
+impl<A: Default, B: Default> Default for Mirrored<A, B> {
+    fn default() -> Self {
+        Self::new(A::default(), B::default())
+    }
+}
+
+impl<K, V, A, B> Map<K, V> for Mirrored<A, B>
+where
+    K: Clone + Debug,
+    V: Clone + PartialEq + Debug,
+    A: Map<K, V>,
+    B: Map<K, V>,
+{
+    fn size(&self) -> usize {
+        self.check("size", self.primary.size(), self.shadow.size())
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.check("get", self.primary.get(key), self.shadow.get(key))
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        self.shadow.put(key.clone(), value.clone());
+        self.primary.put(key, value);
+    }
+}
+
+impl<K, V, A, B> OrderedMap<K, V> for Mirrored<A, B>
+where
+    K: Ord + Clone + Debug,
+    V: Clone + PartialEq + Debug,
+    A: OrderedMap<K, V>,
+    B: OrderedMap<K, V>,
+{
+    fn min(&self) -> Option<&K> {
+        self.check("min", self.primary.min(), self.shadow.min())
+    }
+
+    fn max(&self) -> Option<&K> {
+        self.check("max", self.primary.max(), self.shadow.max())
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        self.check("floor", self.primary.floor(key), self.shadow.floor(key))
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        self.check(
+            "ceiling",
+            self.primary.ceiling(key),
+            self.shadow.ceiling(key),
+        )
+    }
+
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let primary: Vec<(&K, &V)> = self.primary.smallest(n).collect();
+        let shadow: Vec<(&K, &V)> = self.shadow.smallest(n).collect();
+        self.check("smallest", primary, shadow).into_iter()
+    }
+
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let primary: Vec<(&K, &V)> = self.primary.largest(n).collect();
+        let shadow: Vec<(&K, &V)> = self.shadow.largest(n).collect();
+        self.check("largest", primary, shadow).into_iter()
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        let bounds: (Bound<K>, Bound<K>) =
+            (range.start_bound().cloned(), range.end_bound().cloned());
+        let primary: Vec<(&K, &V)> = self.primary.range(bounds.clone()).collect();
+        let shadow: Vec<(&K, &V)> = self.shadow.range(bounds).collect();
+        self.check("range", primary, shadow).into_iter()
+    }
+}
+
+impl<K, V, A, B> SedgewickMap<K, V> for Mirrored<A, B>
+where
+    K: Ord + Clone + Debug,
+    V: Clone + PartialEq + Debug,
+    A: SedgewickMap<K, V>,
+    B: SedgewickMap<K, V>,
+{
+    fn height(&self) -> Option<usize> {
+        self.primary.height()
+    }
+
+    fn leaf_depths(&self) -> Vec<usize> {
+        self.primary.leaf_depths()
+    }
+
+    fn delete_min(&mut self) -> Option<(K, V)> {
+        let shadow = self.shadow.delete_min();
+        let primary = self.primary.delete_min();
+        self.check("delete_min", primary, shadow)
+    }
+
+    fn delete_max(&mut self) -> Option<(K, V)> {
+        let shadow = self.shadow.delete_max();
+        let primary = self.primary.delete_max();
+        self.check("delete_max", primary, shadow)
+    }
+
+    fn rank(&self, key: &K) -> usize {
+        self.check("rank", self.primary.rank(key), self.shadow.rank(key))
+    }
+
+    fn select(&self, rank: usize) -> Option<&K> {
+        self.check(
+            "select",
+            self.primary.select(rank),
+            self.shadow.select(rank),
+        )
+    }
+
+    fn partition_point<P: FnMut(&K) -> bool>(&self, mut pred: P) -> Option<&K> {
+        self.check(
+            "partition_point",
+            self.primary.partition_point(&mut pred),
+            self.shadow.partition_point(&mut pred),
+        )
+    }
+
+    fn successor(&self, key: &K) -> Option<(&K, &V)> {
+        self.check(
+            "successor",
+            self.primary.successor(key),
+            self.shadow.successor(key),
+        )
+    }
+
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)> {
+        self.check(
+            "predecessor",
+            self.primary.predecessor(key),
+            self.shadow.predecessor(key),
+        )
+    }
+
+    fn clear(&mut self) {
+        self.shadow.clear();
+        self.primary.clear();
+    }
+
+    fn drain(&mut self) -> std::vec::IntoIter<(K, V)> {
+        let shadow: Vec<(K, V)> = self.shadow.drain().collect();
+        let primary: Vec<(K, V)> = self.primary.drain().collect();
+        self.check("drain", primary, shadow).into_iter()
+    }
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::Mirrored;
+    use crate::adapters::StdBTreeAdapter;
+    use crate::bench_support::shuffled_keys;
+    use crate::bst::BST;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use crate::{Map, OrderedMap, SedgewickMap};
+
+    #[test]
+    fn test_backends_agree() {
+        let mut map = Mirrored::new(BST::new(), BalancedTree::new());
+        let mut to_std = Mirrored::new(RedBlackTree::new(), StdBTreeAdapter::new());
+        for k in shuffled_keys(1000, 18) {
+            map.put(k % 300, k);
+            to_std.put(k % 300, k);
+        }
+        for k in 0_u64..310 {
+            assert_eq!(map.get(&k), to_std.get(&k));
+            assert_eq!(map.floor(&k), to_std.floor(&k));
+            map.rank(&k);
+        }
+        assert_eq!(map.range(10..20).len(), 10_usize);
+        assert_eq!(to_std.smallest(5).len(), 5_usize);
+        assert_eq!(map.delete_min().map(|(k, _)| k), Some(0));
+        assert_eq!(map.select(0), Some(&1));
+        assert_eq!(
+            (map.divergences(), to_std.divergences()),
+            (0_usize, 0_usize)
+        );
+        assert_eq!(map.height(), map.primary().height());
+
+        let n = map.size();
+        let (mut bst, btree) = map.into_inner();
+        let (max, _) = bst.delete_max().unwrap();
+        let map = Mirrored::new(bst, btree);
+        assert_eq!(map.size(), n - 1_usize);
+        assert!(map.max().unwrap().lt(&max));
+        assert_eq!(map.divergences(), 2_usize);
+    }
+}
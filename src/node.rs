@@ -0,0 +1,270 @@
+use crate::Traversals;
+use std::fmt::{Display, Write};
+
+/// A node of any binary tree, its entry and children.
+///
+/// Traversals, heights and printing below only go through these
+/// accessors, so trees that are no maps, like expression or decision
+/// trees, get the same machinery as `BST` and `RedBlackTree`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use treers::node::{self, BinaryTreeNode};
+/// use treers::Traversals;
+///
+/// // `(1 + 2) * 3`, operators keyed by their position
+/// struct Expr {
+///     op: char,
+///     at: usize,
+///     children: Option<Box<(Expr, Expr)>>,
+/// }
+///
+/// impl BinaryTreeNode for Expr {
+///     type Key = usize;
+///     type Value = char;
+///
+///     fn key(&self) -> &usize {
+///         &self.at
+///     }
+///     fn value(&self) -> &char {
+///         &self.op
+///     }
+///     fn left(&self) -> Option<&Self> {
+///         self.children.as_ref().map(|c| &c.0)
+///     }
+///     fn right(&self) -> Option<&Self> {
+///         self.children.as_ref().map(|c| &c.1)
+///     }
+/// }
+///
+/// let leaf = |op, at| Expr { op, at, children: None };
+/// let sum = Expr { op: '+', at: 1, children: Some(Box::new((leaf('1', 0), leaf('2', 2)))) };
+/// let expr = Expr { op: '*', at: 3, children: Some(Box::new((sum, leaf('3', 4)))) };
+///
+/// let postfix: String = node::traverse(&expr, &Traversals::PostOrder)
+///     .map(|(_, op)| *op)
+///     .collect();
+/// assert_eq!(postfix, "12+3*");
+/// assert_eq!(node::height(&expr), 2_usize);
+/// assert_eq!(node::to_paren_string(&expr), "3:*(1:+(0:1,2:2),4:3)");
+/// ```
+pub trait BinaryTreeNode {
+    type Key;
+    type Value;
+
+    /// Returns the key of the node.
+    fn key(&self) -> &Self::Key;
+
+    /// Returns the value of the node.
+    fn value(&self) -> &Self::Value;
+
+    /// Returns the left child, `None` if there is none.
+    fn left(&self) -> Option<&Self>;
+
+    /// Returns the right child, `None` if there is none.
+    fn right(&self) -> Option<&Self>;
+}
+
+/// Returns entries of the tree under `node` in the given order.
+pub fn traverse<'a, N: BinaryTreeNode>(
+    node: &'a N,
+    traverse: &Traversals,
+) -> std::vec::IntoIter<(&'a N::Key, &'a N::Value)> {
+    let mut vec = Vec::new();
+    match traverse {
+        Traversals::PreOrder => pre_order(node, &mut vec),
+        Traversals::InOrder => in_order(node, &mut vec),
+        Traversals::PostOrder => post_order(node, &mut vec),
+        Traversals::LevelOrder => {
+            for level in 0..=height(node) {
+                level_order(node, &mut vec, level);
+            }
+        }
+    }
+    vec.into_iter()
+}
+
+/// Pushes entries of the tree under `node`, node first.
+pub fn pre_order<'a, N: BinaryTreeNode>(node: &'a N, vec: &mut Vec<(&'a N::Key, &'a N::Value)>) {
+    vec.push((node.key(), node.value()));
+    if let Some(left) = node.left() {
+        pre_order(left, vec);
+    }
+    if let Some(right) = node.right() {
+        pre_order(right, vec);
+    }
+}
+
+/// Pushes entries of the tree under `node`, node between its children.
+pub fn in_order<'a, N: BinaryTreeNode>(node: &'a N, vec: &mut Vec<(&'a N::Key, &'a N::Value)>) {
+    if let Some(left) = node.left() {
+        in_order(left, vec);
+    }
+    vec.push((node.key(), node.value()));
+    if let Some(right) = node.right() {
+        in_order(right, vec);
+    }
+}
+
+/// Calls `f` with entries of the tree under `node` in order, without
+/// collecting them, stopping at the first error.
+pub fn try_in_order<N, E, F>(node: &N, f: &mut F) -> Result<(), E>
+where
+    N: BinaryTreeNode,
+    F: FnMut(&N::Key, &N::Value) -> Result<(), E>,
+{
+    if let Some(left) = node.left() {
+        try_in_order(left, f)?;
+    }
+    f(node.key(), node.value())?;
+    if let Some(right) = node.right() {
+        try_in_order(right, f)?;
+    }
+    Ok(())
+}
+
+/// Pushes entries of the tree under `node`, node last.
+pub fn post_order<'a, N: BinaryTreeNode>(node: &'a N, vec: &mut Vec<(&'a N::Key, &'a N::Value)>) {
+    if let Some(left) = node.left() {
+        post_order(left, vec);
+    }
+    if let Some(right) = node.right() {
+        post_order(right, vec);
+    }
+    vec.push((node.key(), node.value()));
+}
+
+/// Pushes entries `level` links below `node`, from left to right.
+pub fn level_order<'a, N: BinaryTreeNode>(
+    node: &'a N,
+    vec: &mut Vec<(&'a N::Key, &'a N::Value)>,
+    level: usize,
+) {
+    match level {
+        0 => vec.push((node.key(), node.value())),
+        _ => {
+            if let Some(left) = node.left() {
+                level_order(left, vec, level - 1);
+            }
+            if let Some(right) = node.right() {
+                level_order(right, vec, level - 1);
+            }
+        }
+    }
+}
+
+/// Returns the count of links on the longest path down from `node`, `0`
+/// for a node without children, as `SedgewickMap::height` does.
+pub fn height<N: BinaryTreeNode>(node: &N) -> usize {
+    let below = |child: Option<&N>| child.map_or(0_usize, |c| 1_usize + height(c));
+    std::cmp::max(below(node.left()), below(node.right()))
+}
+
+/// Returns the depth of every leaf of the tree under `node`, from left to
+/// right, `node` itself at depth `0`.
+pub fn leaf_depths<N: BinaryTreeNode>(node: &N) -> Vec<usize> {
+    let mut vec = Vec::new();
+    push_leaf_depths(node, 0_usize, &mut vec);
+    vec
+}
+
+fn push_leaf_depths<N: BinaryTreeNode>(node: &N, depth: usize, vec: &mut Vec<usize>) {
+    match (node.left(), node.right()) {
+        (None, None) => vec.push(depth),
+        (left, right) => {
+            for child in [left, right].iter().flatten() {
+                push_leaf_depths(*child, depth + 1_usize, vec);
+            }
+        }
+    }
+}
+
+/// Returns the tree under `node` in the parenthesized notation of
+/// `BST::to_paren_string`, a missing child written `-`.
+pub fn to_paren_string<N>(node: &N) -> String
+where
+    N: BinaryTreeNode,
+    N::Key: Display,
+    N::Value: Display,
+{
+    let mut out = String::new();
+    write(Some(node), &mut out);
+    out
+}
+
+fn write<N>(node: Option<&N>, out: &mut String)
+where
+    N: BinaryTreeNode,
+    N::Key: Display,
+    N::Value: Display,
+{
+    match node {
+        Some(node) => {
+            // writing to a `String` doesn't fail
+            let _ = write!(out, "{}:{}", node.key(), node.value());
+            if node.left().is_some() || node.right().is_some() {
+                out.push('(');
+                write(node.left(), out);
+                out.push(',');
+                write(node.right(), out);
+                out.push(')');
+            }
+        }
+        None => out.push('-'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{height, to_paren_string, traverse, BinaryTreeNode};
+    use crate::Traversals;
+
+    struct Node(u32, Option<Box<Node>>, Option<Box<Node>>);
+
+    impl BinaryTreeNode for Node {
+        type Key = u32;
+        type Value = u32;
+
+        fn key(&self) -> &u32 {
+            &self.0
+        }
+        fn value(&self) -> &u32 {
+            &self.0
+        }
+        fn left(&self) -> Option<&Self> {
+            self.1.as_deref()
+        }
+        fn right(&self) -> Option<&Self> {
+            self.2.as_deref()
+        }
+    }
+
+    fn keys(tree: &Node, order: &Traversals) -> Vec<u32> {
+        traverse(tree, order).map(|(k, _)| *k).collect()
+    }
+
+    #[test]
+    fn test_traverse_user_tree() {
+        let leaf = |k| Some(Box::new(Node(k, None, None)));
+        // 4 with 2(1, 3) on the left and 5(-, 6) on the right
+        let tree = Node(
+            4,
+            Some(Box::new(Node(2, leaf(1), leaf(3)))),
+            Some(Box::new(Node(5, None, leaf(6)))),
+        );
+        assert_eq!(keys(&tree, &Traversals::PreOrder), [4, 2, 1, 3, 5, 6]);
+        assert_eq!(keys(&tree, &Traversals::InOrder), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(keys(&tree, &Traversals::PostOrder), [1, 3, 2, 6, 5, 4]);
+        assert_eq!(keys(&tree, &Traversals::LevelOrder), [4, 2, 5, 1, 3, 6]);
+        assert_eq!(height(&tree), 2_usize);
+        assert_eq!(to_paren_string(tree.right().unwrap()), "5:5(-,6:6)");
+
+        let single = Node(7, None, None);
+        assert_eq!(height(&single), 0_usize);
+        assert_eq!(keys(&single, &Traversals::LevelOrder), [7]);
+        assert_eq!(to_paren_string(&single), "7:7");
+    }
+}
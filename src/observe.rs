@@ -0,0 +1,353 @@
+use crate::{Map, OrderedMap, PutPolicy, SedgewickMap};
+use std::fmt;
+use std::ops::RangeBounds;
+
+type EntryHook<K, V> = Box<dyn FnMut(&K, &V)>;
+type UpdateHook<K, V> = Box<dyn FnMut(&K, &V, &V)>;
+
+/// A map calling hooks on every change, so secondary indexes and caches
+/// stay in sync with it.
+///
+/// `on_insert` hooks see new keys, `on_update` hooks see the old and new
+/// value of a key put again and `on_remove` hooks see removed entries.
+/// Hooks run in the order they were added, after the change, but
+/// `on_update` hooks see the old value just before it is replaced. Keys
+/// are cloned for the hooks, values never are.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use treers::observe::Observed;
+/// use treers::rbtree::RedBlackTree;
+/// use treers::Map;
+///
+/// // a count of entries by value, kept by the hooks
+/// let counts = Rc::new(RefCell::new(RedBlackTree::new()));
+/// let mut map = Observed::new(RedBlackTree::new());
+/// let c = Rc::clone(&counts);
+/// map.on_insert(move |_, v: &char| bump(&mut c.borrow_mut(), *v, 1));
+/// let c = Rc::clone(&counts);
+/// map.on_update(move |_, old, new| {
+///     bump(&mut c.borrow_mut(), *old, -1);
+///     bump(&mut c.borrow_mut(), *new, 1);
+/// });
+/// let c = Rc::clone(&counts);
+/// map.on_remove(move |_, v| bump(&mut c.borrow_mut(), *v, -1));
+///
+/// map.put(1, 'a');
+/// map.put(2, 'a');
+/// map.put(2, 'b');
+/// map.remove(&1);
+/// assert_eq!(counts.borrow().get(&'a'), Some(&0));
+/// assert_eq!(counts.borrow().get(&'b'), Some(&1));
+///
+/// fn bump(counts: &mut RedBlackTree<char, i32>, v: char, by: i32) {
+///     let n = counts.get(&v).copied().unwrap_or_default();
+///     counts.put(v, n + by);
+/// }
+/// ```
+pub struct Observed<K, V, M> {
+    map: M,
+    on_insert: Vec<EntryHook<K, V>>,
+    on_update: Vec<UpdateHook<K, V>>,
+    on_remove: Vec<EntryHook<K, V>>,
+}
+
+impl<K, V, M> Observed<K, V, M> {
+    /// Starts observing changes of `map`, without hooks yet.
+    pub const fn new(map: M) -> Self {
+        Self {
+            map,
+            on_insert: Vec::new(),
+            on_update: Vec::new(),
+            on_remove: Vec::new(),
+        }
+    }
+
+    /// Adds a hook called with every new entry.
+    pub fn on_insert<F: FnMut(&K, &V) + 'static>(&mut self, f: F) {
+        self.on_insert.push(Box::new(f));
+    }
+
+    /// Adds a hook called with the key, the old and the new value of every
+    /// replaced value.
+    pub fn on_update<F: FnMut(&K, &V, &V) + 'static>(&mut self, f: F) {
+        self.on_update.push(Box::new(f));
+    }
+
+    /// Adds a hook called with every removed entry.
+    pub fn on_remove<F: FnMut(&K, &V) + 'static>(&mut self, f: F) {
+        self.on_remove.push(Box::new(f));
+    }
+
+    /// Returns a reference to the observed map.
+    pub const fn get_ref(&self) -> &M {
+        &self.map
+    }
+
+    /// Drops the hooks and returns the map.
+    pub fn into_inner(self) -> M {
+        self.map
+    }
+}
+
+#[cfg(feature = "bst")]
+impl<K: Ord, V> Observed<K, V, crate::bst::BST<K, V>> {
+    /// Removes `key`, calling the `on_remove` hooks if it was there.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let v = self.map.delete(key)?;
+        for hook in &mut self.on_remove {
+            hook(key, &v);
+        }
+        Some(v)
+    }
+}
+
+#[cfg(feature = "rbtree")]
+impl<K: Ord + Clone, V: Clone> Observed<K, V, crate::rbtree::RedBlackTree<K, V>> {
+    /// Removes `key`, calling the `on_remove` hooks if it was there.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let v = self.map.delete(key)?;
+        for hook in &mut self.on_remove {
+            hook(key, &v);
+        }
+        Some(v)
+    }
+}
+
+#[cfg(feature = "btree")]
+impl<K: Ord + Clone, V: Clone> Observed<K, V, crate::btree::BalancedTree<K, V>> {
+    /// Removes `key`, calling the `on_remove` hooks if it was there.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let v = self.map.delete(key)?;
+        for hook in &mut self.on_remove {
+            hook(key, &v);
+        }
+        Some(v)
+    }
+}
+
+impl<K, V, M: fmt::Debug> fmt::Debug for Observed<K, V, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Observed")
+            .field("map", &self.map)
+            .field(
+                "hooks",
+                &(self.on_insert.len() + self.on_update.len() + self.on_remove.len()),
+            )
+            .finish()
+    }
+}
+
+impl<K, V, M: Default> Default for Observed<K, V, M> {
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+impl<K: Clone, V: Clone, M: Map<K, V>> Map<K, V> for Observed<K, V, M> {
+    fn size(&self) -> usize {
+        self.map.size()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        if self.on_insert.is_empty() && self.on_update.is_empty() {
+            self.map.put(key, value);
+            return;
+        }
+        let hook_key = key.clone();
+        let on_update = &mut self.on_update;
+        // the `on_update` hooks see the old value in place, before the merge
+        // returns the new one
+        let mut policy = if on_update.is_empty() {
+            PutPolicy::Overwrite
+        } else {
+            PutPolicy::merge_with(|old, new| {
+                for hook in on_update.iter_mut() {
+                    hook(&hook_key, old, &new);
+                }
+                new
+            })
+        };
+        let inserted = self.map.put_with_policy(key, value, &mut policy);
+        if inserted && !self.on_insert.is_empty() {
+            if let Some(new) = self.map.get(&hook_key) {
+                for hook in &mut self.on_insert {
+                    hook(&hook_key, new);
+                }
+            }
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone, M: OrderedMap<K, V>> OrderedMap<K, V> for Observed<K, V, M> {
+    fn min(&self) -> Option<&K> {
+        self.map.min()
+    }
+
+    fn max(&self) -> Option<&K> {
+        self.map.max()
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        self.map.floor(key)
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        self.map.ceiling(key)
+    }
+
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.smallest(n)
+    }
+
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.largest(n)
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.range(range)
+    }
+
+    fn try_fold_range<R, B, E, F>(&self, range: R, init: B, f: F) -> Result<B, E>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(B, &K, &V) -> Result<B, E>,
+    {
+        self.map.try_fold_range(range, init, f)
+    }
+}
+
+impl<K: Ord + Clone, V: Clone, M: SedgewickMap<K, V>> SedgewickMap<K, V> for Observed<K, V, M> {
+    fn height(&self) -> Option<usize> {
+        self.map.height()
+    }
+
+    fn leaf_depths(&self) -> Vec<usize> {
+        self.map.leaf_depths()
+    }
+
+    fn delete_min(&mut self) -> Option<(K, V)> {
+        let (k, v) = self.map.delete_min()?;
+        for hook in &mut self.on_remove {
+            hook(&k, &v);
+        }
+        Some((k, v))
+    }
+
+    fn delete_max(&mut self) -> Option<(K, V)> {
+        let (k, v) = self.map.delete_max()?;
+        for hook in &mut self.on_remove {
+            hook(&k, &v);
+        }
+        Some((k, v))
+    }
+
+    fn rank(&self, key: &K) -> usize {
+        self.map.rank(key)
+    }
+
+    fn select(&self, rank: usize) -> Option<&K> {
+        self.map.select(rank)
+    }
+
+    fn partition_point<P: FnMut(&K) -> bool>(&self, pred: P) -> Option<&K> {
+        self.map.partition_point(pred)
+    }
+
+    fn successor(&self, key: &K) -> Option<(&K, &V)> {
+        self.map.successor(key)
+    }
+
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)> {
+        self.map.predecessor(key)
+    }
+
+    fn clear(&mut self) {
+        let removed: Vec<(K, V)> = self
+            .map
+            .smallest(self.map.size())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        self.map.clear();
+        for (k, v) in &removed {
+            for hook in &mut self.on_remove {
+                hook(k, v);
+            }
+        }
+    }
+
+    fn drain(&mut self) -> std::vec::IntoIter<(K, V)> {
+        let removed: Vec<(K, V)> = self.map.drain().collect();
+        for (k, v) in &removed {
+            for hook in &mut self.on_remove {
+                hook(k, v);
+            }
+        }
+        removed.into_iter()
+    }
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::Observed;
+    use crate::bst::BST;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use crate::{Map, SedgewickMap};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    fn logged<M: SedgewickMap<u32, u32> + Default>() -> (Observed<u32, u32, M>, Log) {
+        let log: Log = Rc::default();
+        let mut map = Observed::new(M::default());
+        let l = Rc::clone(&log);
+        map.on_insert(move |k, v| l.borrow_mut().push(format!("+{}={}", k, v)));
+        let l = Rc::clone(&log);
+        map.on_update(move |k, old, new| l.borrow_mut().push(format!("{}:{}->{}", k, old, new)));
+        let l = Rc::clone(&log);
+        map.on_remove(move |k, v| l.borrow_mut().push(format!("-{}={}", k, v)));
+        (map, log)
+    }
+
+    fn notify<M: SedgewickMap<u32, u32> + Default>() {
+        let (mut map, log) = logged::<M>();
+        map.put(2, 20);
+        map.put(1, 10);
+        map.put(2, 21);
+        assert_eq!(*log.borrow(), ["+2=20", "+1=10", "2:20->21"]);
+        assert_eq!(map.get(&2), Some(&21));
+        assert_eq!(map.height(), map.get_ref().height());
+        assert_eq!(map.into_inner().size(), 2_usize);
+    }
+
+    #[test]
+    fn test_hooks() {
+        notify::<BST<u32, u32>>();
+        notify::<RedBlackTree<u32, u32>>();
+        notify::<BalancedTree<u32, u32>>();
+
+        // `remove` is implemented per tree
+        macro_rules! removes {
+            ($tree:ty) => {{
+                let (mut map, log) = logged::<$tree>();
+                map.put(1, 10);
+                assert_eq!(map.remove(&1), Some(10));
+                assert_eq!(map.remove(&1), None);
+                assert_eq!(*log.borrow(), ["+1=10", "-1=10"]);
+                assert!(format!("{:?}", map).ends_with("hooks: 3 }"));
+            }};
+        }
+        removes!(BST<u32, u32>);
+        removes!(RedBlackTree<u32, u32>);
+        removes!(BalancedTree<u32, u32>);
+    }
+}
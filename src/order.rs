@@ -0,0 +1,196 @@
+use crate::rbtree::RedBlackTree;
+use crate::{Map, OrderedMap};
+
+// Labels are below `UNIVERSE`, so windows of labels never overflow
+const LABEL_BITS: u32 = 62_u32;
+const UNIVERSE: u64 = 1_u64 << LABEL_BITS;
+// A window of `2^i` labels takes at most `2^i / TAU^i` keys, so larger
+// windows stay sparser, room for `(2 / TAU)^62`, about 5 * 10^7 keys
+const TAU: f64 = 1.5_f64;
+
+/// Integer labels for keys, ordered as the keys are.
+///
+/// Comparing two labels compares their keys in `O(1)`, without touching
+/// the tree, so a scheduler can keep labels in its own queues and
+/// timers. An insert takes the label halfway between its neighbors, and
+/// only when they are adjacent relabels the smallest aligned window of
+/// labels around them sparse enough to take one more key, spreading its
+/// keys evenly, the order-maintenance scheme of Bender et al. Labels of
+/// other keys stay the same until such a relabel, `relabels` counts the
+/// moves, `O(log n)` per insert amortized.
+///
+/// # Examples
+///
+/// ```
+/// use treers::order::OrderLabels;
+///
+/// let mut labels = OrderLabels::new();
+/// let b = labels.insert(20);
+/// let d = labels.insert(40);
+/// let c = labels.insert(30);
+/// assert!(b < c && c < d);
+/// assert_eq!(labels.label(&30), Some(c));
+/// assert_eq!(labels.insert(30), c);
+///
+/// // keys landing in the same gap over and over
+/// for k in (21..30).rev() {
+///     labels.insert(k);
+/// }
+/// let all: Vec<u64> = (20..=40).filter_map(|k| labels.label(&k)).collect();
+/// assert_eq!(all.len(), 12_usize);
+/// assert!(all.windows(2).all(|w| w[0] < w[1]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct OrderLabels<K: Ord + Clone> {
+    labels: RedBlackTree<K, u64>,
+    // Keys by label, for windows of labels
+    keys: RedBlackTree<u64, K>,
+    relabels: usize,
+}
+
+impl<K: Ord + Clone> OrderLabels<K> {
+    /// Inits an empty set of labels.
+    pub const fn new() -> Self {
+        Self {
+            labels: RedBlackTree::new(),
+            keys: RedBlackTree::new(),
+            relabels: 0_usize,
+        }
+    }
+
+    /// Returns a count of labeled keys.
+    pub fn size(&self) -> usize {
+        self.labels.size()
+    }
+
+    /// Returns `true` if no key is labeled.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Returns the current label of `key`.
+    pub fn label(&self, key: &K) -> Option<u64> {
+        self.labels.get(key).copied()
+    }
+
+    /// Returns a count of labels changed by relabels so far.
+    pub const fn relabels(&self) -> usize {
+        self.relabels
+    }
+
+    /// Labels `key` between its neighbors and returns its label, the one it
+    /// has already if it is labeled.
+    pub fn insert(&mut self, key: K) -> u64 {
+        if let Some(&label) = self.labels.get(&key) {
+            return label;
+        }
+        let below = self
+            .labels
+            .lower(&key)
+            .and_then(|k| self.labels.get(k))
+            .copied();
+        let above = self
+            .labels
+            .ceiling(&key)
+            .and_then(|k| self.labels.get(k))
+            .copied();
+        let lo = below.map_or(0_u64, |l| l + 1_u64);
+        let hi = above.unwrap_or(UNIVERSE);
+        if lo.lt(&hi) {
+            let label = lo + (hi - lo) / 2_u64;
+            self.assign(key, label);
+            label
+        } else {
+            // no free label between the neighbors
+            self.relabel(key, below.unwrap_or_default())
+        }
+    }
+
+    /// Drops the label of `key`, returning it.
+    pub fn remove(&mut self, key: &K) -> Option<u64> {
+        let label = self.labels.delete(key)?;
+        self.keys.delete(&label);
+        Some(label)
+    }
+
+    fn assign(&mut self, key: K, label: u64) {
+        self.keys.put(label, key.clone());
+        self.labels.put(key, label);
+    }
+
+    // Spreads keys of the smallest aligned window around `anchor` under
+    // its density threshold evenly over it, `key` among them
+    fn relabel(&mut self, key: K, anchor: u64) -> u64 {
+        let mut bits = 1_u32;
+        let (base, size, mut window) = loop {
+            let size = 1_u64 << bits;
+            let base = anchor & !(size - 1_u64);
+            let count = self.keys.range(base..base + size).len();
+            let room = size as f64 / TAU.powi(bits as i32);
+            if (count + 1_usize) as f64 <= room || bits.eq(&LABEL_BITS) {
+                let window: Vec<K> = self
+                    .keys
+                    .range(base..base + size)
+                    .map(|(_, k)| k.clone())
+                    .collect();
+                break (base, size, window);
+            }
+            bits += 1_u32;
+        };
+        let at = window.partition_point(|k| k.lt(&key));
+        window.insert(at, key);
+        for k in &window {
+            if let Some(label) = self.labels.delete(k) {
+                self.keys.delete(&label);
+            }
+        }
+        let gap = size / window.len() as u64;
+        let mut label = 0_u64;
+        for (i, k) in window.into_iter().enumerate() {
+            let new = base + i as u64 * gap + gap / 2_u64;
+            if i.eq(&at) {
+                label = new;
+            } else {
+                self.relabels += 1_usize;
+            }
+            self.assign(k, new);
+        }
+        label
+    }
+}
+
+impl<K: Ord + Clone> Default for OrderLabels<K> {
+    /// Creates an empty `OrderLabels<K>`.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OrderLabels;
+    use crate::bench_support::shuffled_keys;
+    use crate::{Map, OrderedMap};
+
+    #[test]
+    fn test_labels_follow_order() {
+        let mut labels = OrderLabels::new();
+        for k in shuffled_keys(3000, 4) {
+            labels.insert(k);
+        }
+        // keys bunched into one gap, the worst case for halving
+        for i in 0_u64..3000 {
+            labels.insert(u64::MAX - i);
+        }
+        let in_order: Vec<u64> = labels.labels.range(..).map(|(_, label)| *label).collect();
+        assert_eq!(in_order.len(), labels.size());
+        assert!(in_order.windows(2).all(|w| w[0].lt(&w[1])));
+        assert_eq!(labels.keys.size(), labels.size());
+        assert!(labels.relabels().lt(&(labels.size() * 64_usize)));
+
+        let k = u64::MAX - 10_u64;
+        let label = labels.label(&k);
+        assert_eq!(labels.remove(&k), label);
+        assert_eq!((labels.label(&k), labels.remove(&k)), (None, None));
+    }
+}
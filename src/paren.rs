@@ -0,0 +1,180 @@
+use crate::ParseTreeError;
+use std::fmt::{Display, Write};
+use std::str::FromStr;
+
+// Parenthesized notation of binary trees shared by `BST` and `RedBlackTree`:
+//
+//   tree := '-' | node
+//   node := ['*'] key ':' value ['(' tree ',' tree ')']
+//
+// `-` is an empty tree, `*` marks a red node and is only read when colors
+// are, a node without children leaves the parentheses out.
+
+/// Key, value, `true` if red and both children of a node.
+pub type Parts<'a, T> = (
+    &'a <T as ParenNode>::Key,
+    &'a <T as ParenNode>::Value,
+    bool,
+    &'a T,
+    &'a T,
+);
+
+/// A node of a binary tree as written in the notation.
+pub trait ParenNode: Sized {
+    type Key: Display;
+    type Value: Display;
+
+    /// Parts of the node, `None` for an empty tree.
+    fn parts(&self) -> Option<Parts<'_, Self>>;
+}
+
+/// A parsed tree, sizes not counted yet.
+pub enum Parsed<K, V> {
+    Node {
+        // byte offset of the key
+        at: usize,
+        k: K,
+        v: V,
+        red: bool,
+        left: Box<Parsed<K, V>>,
+        right: Box<Parsed<K, V>>,
+    },
+    Nil,
+}
+
+pub fn write<T: ParenNode>(node: &T, out: &mut String) {
+    match node.parts() {
+        Some((k, v, red, left, right)) => {
+            if red {
+                out.push('*');
+            }
+            // writing to a `String` doesn't fail
+            let _ = write!(out, "{}:{}", k, v);
+            if left.parts().is_some() || right.parts().is_some() {
+                out.push('(');
+                write(left, out);
+                out.push(',');
+                write(right, out);
+                out.push(')');
+            }
+        }
+        None => out.push('-'),
+    }
+}
+
+/// Parses a whole tree, `colored` reads `*` as a red mark instead of a key.
+pub fn parse<K, V>(s: &str, colored: bool) -> Result<Parsed<K, V>, ParseTreeError>
+where
+    K: FromStr + Ord,
+    V: FromStr,
+{
+    let mut parser = Parser {
+        s,
+        at: 0_usize,
+        colored,
+    };
+    let tree: Parsed<K, V> = parser.tree()?;
+    if parser.at.lt(&s.len()) {
+        return Err(ParseTreeError::new(parser.at, "end of input"));
+    }
+    let mut keys = Vec::new();
+    tree.in_order(&mut keys);
+    // a search tree needs keys ascending in order
+    for w in keys.windows(2_usize) {
+        let ((_, a), (at, b)) = (w[0], w[1]);
+        if b.le(a) {
+            return Err(ParseTreeError::new(at, "keys in ascending order"));
+        }
+    }
+    Ok(tree)
+}
+
+impl<K, V> Parsed<K, V> {
+    fn in_order<'a>(&'a self, keys: &mut Vec<(usize, &'a K)>) {
+        if let Parsed::Node {
+            at,
+            ref k,
+            v: _,
+            red: _,
+            ref left,
+            ref right,
+        } = *self
+        {
+            left.in_order(keys);
+            keys.push((at, k));
+            right.in_order(keys);
+        }
+    }
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    at: usize,
+    colored: bool,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.s.as_bytes().get(self.at).copied()
+    }
+
+    fn expect(&mut self, byte: u8, expected: &'static str) -> Result<(), ParseTreeError> {
+        if self.peek().eq(&Some(byte)) {
+            self.at += 1_usize;
+            Ok(())
+        } else {
+            Err(ParseTreeError::new(self.at, expected))
+        }
+    }
+
+    // Bytes up to one of `stops` or the end
+    fn token(&mut self, stops: &[u8]) -> &str {
+        let start = self.at;
+        while self.peek().is_some_and(|b| !stops.contains(&b)) {
+            self.at += 1_usize;
+        }
+        &self.s[start..self.at]
+    }
+
+    fn tree<K: FromStr, V: FromStr>(&mut self) -> Result<Parsed<K, V>, ParseTreeError> {
+        let bytes = self.s.as_bytes();
+        let after = bytes.get(self.at + 1_usize).copied();
+        if self.peek().eq(&Some(b'-')) && after.is_none_or(|b| b.eq(&b',') || b.eq(&b')')) {
+            self.at += 1_usize;
+            return Ok(Parsed::Nil);
+        }
+        let red = self.colored && self.peek().eq(&Some(b'*'));
+        if red {
+            self.at += 1_usize;
+        }
+        let key_at = self.at;
+        let k: K = self
+            .token(b":(),")
+            .parse()
+            .map_err(|_| ParseTreeError::new(key_at, "a key"))?;
+        self.expect(b':', "`:`")?;
+        let value_at = self.at;
+        let v: V = self
+            .token(b"(),")
+            .parse()
+            .map_err(|_| ParseTreeError::new(value_at, "a value"))?;
+        let (left, right) = if self.peek().eq(&Some(b'(')) {
+            self.at += 1_usize;
+            let left = self.tree()?;
+            self.expect(b',', "`,`")?;
+            let right = self.tree()?;
+            self.expect(b')', "`)`")?;
+            (left, right)
+        } else {
+            (Parsed::Nil, Parsed::Nil)
+        };
+        Ok(Parsed::Node {
+            at: key_at,
+            k,
+            v,
+            red,
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+    }
+}
@@ -0,0 +1,251 @@
+use crate::Map;
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::fmt;
+use std::rc::Rc;
+
+/// A value of a `PinnedMap`, shared by the map and every handle of it.
+///
+/// The value lives in its own allocation, so rotations and splits of the
+/// tree only move the pointer, and a handle reads and writes the value
+/// the map holds however the tree changes around it.
+pub struct EntryHandle<V> {
+    slot: Rc<Slot<V>>,
+}
+
+struct Slot<V> {
+    value: RefCell<V>,
+    // `false` once the entry is removed from its map
+    attached: Cell<bool>,
+}
+
+impl<V> EntryHandle<V> {
+    fn new(value: V) -> Self {
+        Self {
+            slot: Rc::new(Slot {
+                value: RefCell::new(value),
+                attached: Cell::new(true),
+            }),
+        }
+    }
+
+    /// Borrows the value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is borrowed mutably.
+    pub fn borrow(&self) -> Ref<'_, V> {
+        self.slot.value.borrow()
+    }
+
+    /// Borrows the value mutably, the map sees the change too.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is borrowed.
+    pub fn borrow_mut(&self) -> RefMut<'_, V> {
+        self.slot.value.borrow_mut()
+    }
+
+    /// Returns `true` while the entry is in its map, a removed entry keeps
+    /// its value alive for the handles left.
+    pub fn is_attached(&self) -> bool {
+        self.slot.attached.get()
+    }
+
+    /// Returns `true` if both handles are of the same entry.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.slot, &other.slot)
+    }
+}
+
+impl<V> Clone for EntryHandle<V> {
+    fn clone(&self) -> Self {
+        Self {
+            slot: Rc::clone(&self.slot),
+        }
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for EntryHandle<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntryHandle")
+            .field("value", &self.slot.value)
+            .field("attached", &self.is_attached())
+            .finish()
+    }
+}
+
+/// A map whose values stay where they are while the tree mutates, reached
+/// through `EntryHandle`s that outlive any borrow of the map.
+///
+/// A `put` of a key already in the map writes the new value into its
+/// slot, so handles of the key see it. Handles are tokens of one entry
+/// each, not of its key: once the entry is removed they are detached, and
+/// a later `put` of the key starts a new entry.
+///
+/// # Examples
+///
+/// ```
+/// use treers::pinned::PinnedMap;
+/// use treers::rbtree::RedBlackTree;
+///
+/// let mut map = PinnedMap::new(RedBlackTree::new());
+/// let a = map.put('a', 1);
+/// // thousands of puts rebalance the tree under the handle
+/// for k in 0..5000 {
+///     map.put(char::from_u32(0x100 + k).unwrap(), 0);
+/// }
+/// *a.borrow_mut() += 10;
+/// assert_eq!(*map.get(&'a').unwrap(), 11);
+///
+/// map.put('a', 2);
+/// assert_eq!(*a.borrow(), 2);
+///
+/// map.remove(&'a');
+/// assert!(!a.is_attached());
+/// assert!(map.get(&'a').is_none());
+/// ```
+#[derive(Debug, Default)]
+pub struct PinnedMap<M> {
+    map: M,
+}
+
+impl<M> PinnedMap<M> {
+    /// Wraps an empty `map` of handles.
+    pub const fn new(map: M) -> Self {
+        Self { map }
+    }
+
+    /// Returns a reference to the wrapped map.
+    pub const fn get_ref(&self) -> &M {
+        &self.map
+    }
+
+    /// Returns the wrapped map.
+    pub fn into_inner(self) -> M {
+        self.map
+    }
+
+    /// Returns a count of entries.
+    pub fn size<K, V>(&self) -> usize
+    where
+        M: Map<K, EntryHandle<V>>,
+    {
+        self.map.size()
+    }
+
+    /// Returns a handle of the entry of `key`.
+    pub fn handle<K, V>(&self, key: &K) -> Option<EntryHandle<V>>
+    where
+        M: Map<K, EntryHandle<V>>,
+    {
+        self.map.get(key).cloned()
+    }
+
+    /// Borrows the value of `key`.
+    pub fn get<K, V>(&self, key: &K) -> Option<Ref<'_, V>>
+    where
+        M: Map<K, EntryHandle<V>>,
+    {
+        self.map.get(key).map(EntryHandle::borrow)
+    }
+
+    /// Puts `value` into the entry of `key`, a new one if there is none, and
+    /// returns a handle of it.
+    pub fn put<K, V>(&mut self, key: K, value: V) -> EntryHandle<V>
+    where
+        M: Map<K, EntryHandle<V>>,
+    {
+        if let Some(handle) = self.map.get(&key) {
+            *handle.borrow_mut() = value;
+            return handle.clone();
+        }
+        let handle = EntryHandle::new(value);
+        self.map.put(key, handle.clone());
+        handle
+    }
+}
+
+#[cfg(feature = "bst")]
+impl<K: Ord, V> PinnedMap<crate::bst::BST<K, EntryHandle<V>>> {
+    /// Removes the entry of `key` and detaches its handles, returning one of
+    /// them.
+    pub fn remove(&mut self, key: &K) -> Option<EntryHandle<V>> {
+        let handle = self.map.delete(key)?;
+        handle.slot.attached.set(false);
+        Some(handle)
+    }
+}
+
+#[cfg(feature = "rbtree")]
+impl<K: Ord + Clone, V> PinnedMap<crate::rbtree::RedBlackTree<K, EntryHandle<V>>> {
+    /// Removes the entry of `key` and detaches its handles, returning one of
+    /// them.
+    pub fn remove(&mut self, key: &K) -> Option<EntryHandle<V>> {
+        let handle = self.map.delete(key)?;
+        handle.slot.attached.set(false);
+        Some(handle)
+    }
+}
+
+#[cfg(feature = "btree")]
+impl<K: Ord + Clone, V> PinnedMap<crate::btree::BalancedTree<K, EntryHandle<V>>> {
+    /// Removes the entry of `key` and detaches its handles, returning one of
+    /// them.
+    pub fn remove(&mut self, key: &K) -> Option<EntryHandle<V>> {
+        let handle = self.map.delete(key)?;
+        handle.slot.attached.set(false);
+        Some(handle)
+    }
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::{EntryHandle, PinnedMap};
+    use crate::bench_support::shuffled_keys;
+    use crate::bst::BST;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use crate::Map;
+
+    fn keep_handles<M: Map<u64, EntryHandle<u64>> + Default>() {
+        let mut map = PinnedMap::new(M::default());
+        let keys = shuffled_keys(1000, 2);
+        let handles: Vec<EntryHandle<u64>> = keys.iter().map(|&k| map.put(k, k)).collect();
+        for (k, handle) in keys.iter().zip(&handles) {
+            *handle.borrow_mut() += 1_u64;
+            assert!(handle.ptr_eq(&map.handle(k).unwrap()));
+        }
+        for k in &keys {
+            assert_eq!(*map.get(k).unwrap(), k + 1_u64);
+        }
+        assert_eq!(map.size(), keys.len());
+        assert!(handles.iter().all(EntryHandle::is_attached));
+    }
+
+    #[test]
+    fn test_handles_survive_rebalancing() {
+        keep_handles::<BST<u64, EntryHandle<u64>>>();
+        keep_handles::<RedBlackTree<u64, EntryHandle<u64>>>();
+        keep_handles::<BalancedTree<u64, EntryHandle<u64>>>();
+
+        // `remove` is implemented per tree
+        macro_rules! detaches {
+            ($tree:expr) => {{
+                let mut map = PinnedMap::new($tree);
+                let old = map.put(1_u32, String::from("one"));
+                assert!(map.remove(&1).unwrap().ptr_eq(&old));
+                let new = map.put(1, String::from("uno"));
+                assert!(!new.ptr_eq(&old) && !old.is_attached());
+                assert_eq!(
+                    (old.borrow().as_str(), new.borrow().as_str()),
+                    ("one", "uno")
+                );
+                assert!(map.remove(&2).is_none());
+            }};
+        }
+        detaches!(BST::new());
+        detaches!(RedBlackTree::new());
+        detaches!(BalancedTree::new());
+    }
+}
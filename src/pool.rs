@@ -0,0 +1,64 @@
+use std::fmt;
+
+// Free list of the nodes of `BST` and `RedBlackTree`: the emptied boxes of
+// deleted nodes wait here for the next new nodes of the same tree, so
+// puts after deletes reuse memory instead of asking the allocator.
+//
+// Only the root of a tree keeps boxes, children carry an empty pool. The
+// list is boxed itself, so that empty pool costs every node a pointer.
+
+pub struct Pool<T> {
+    #[allow(clippy::box_collection)]
+    boxes: Option<Box<Vec<Box<T>>>>,
+}
+
+impl<T> Pool<T> {
+    pub const fn new() -> Self {
+        Pool { boxes: None }
+    }
+
+    // Count of boxes kept
+    pub fn len(&self) -> usize {
+        self.boxes.as_ref().map_or(0_usize, |boxes| boxes.len())
+    }
+
+    // Keeps an emptied box of a deleted node
+    pub fn keep(&mut self, spare: Box<T>) {
+        self.boxes.get_or_insert_with(Box::default).push(spare);
+    }
+
+    // Puts `value` into a kept box, into a new one if none is left
+    pub fn boxed(&mut self, value: T) -> Box<T> {
+        match self.boxes.as_mut().and_then(|boxes| boxes.pop()) {
+            Some(mut spare) => {
+                *spare = value;
+                spare
+            }
+            None => Box::new(value),
+        }
+    }
+
+    // Returns every kept box to the allocator
+    pub fn clear(&mut self) {
+        self.boxes = None;
+    }
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Self {
+        Pool::new()
+    }
+}
+
+// A clone of a tree starts with a pool of its own
+impl<T> Clone for Pool<T> {
+    fn clone(&self) -> Self {
+        Pool::new()
+    }
+}
+
+impl<T> fmt::Debug for Pool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Pool").field(&self.len()).finish()
+    }
+}
@@ -0,0 +1,242 @@
+use crate::rbtree::RedBlackTree;
+use crate::{Map, OrderedMap, SedgewickMap};
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+// Keys of the trees of `y`, a `y` with the index of its point to tell
+// equal `y` apart
+type YKey<Y> = (Y, usize);
+
+// A node of the primary tree, over the points `lo..hi` in `x` order
+struct Node<Y: Ord + Clone> {
+    lo: usize,
+    hi: usize,
+    // `y` of every point below
+    ys: RedBlackTree<YKey<Y>, ()>,
+    children: Option<Box<(Node<Y>, Node<Y>)>>,
+}
+
+/// A static 2D range tree, counting and reporting points inside a
+/// rectangle in `O(log² n)`, plus the count of points reported.
+///
+/// The primary tree splits the points sorted by `x` in halves, every node
+/// keeps a `RedBlackTree` of the `y` of its points. A query splits the `x`
+/// range into `O(log n)` whole nodes and asks each of their trees for the
+/// `y` range, a count by `rank`. Points are stored once per level, so
+/// `O(n log n)` in all; the tree is built once and not updated.
+///
+/// # Examples
+///
+/// ```
+/// use treers::range2d::RangeTree;
+///
+/// // shops by (longitude, latitude) in a city grid
+/// let shops = RangeTree::new(vec![
+///     (3, 4, "bakery"),
+///     (5, 1, "garage"),
+///     (6, 6, "cafe"),
+///     (8, 3, "books"),
+///     (9, 9, "florist"),
+/// ]);
+/// assert_eq!(shops.count(2..=8, 2..=6), 3_usize);
+/// assert_eq!(shops.count(.., ..3), 1_usize);
+///
+/// let names: Vec<&str> = shops.report(5.., 3..).iter().map(|p| p.2).collect();
+/// assert_eq!(names, ["cafe", "books", "florist"]);
+/// ```
+pub struct RangeTree<X: Ord, Y: Ord + Clone, V> {
+    // Points sorted by `x`
+    points: Vec<(X, Y, V)>,
+    root: Option<Node<Y>>,
+}
+
+impl<X: Ord, Y: Ord + Clone, V> RangeTree<X, Y, V> {
+    /// Builds the tree of `points` in `O(n log² n)`, points may repeat.
+    pub fn new(mut points: Vec<(X, Y, V)>) -> Self {
+        points.sort_by(|a, b| a.0.cmp(&b.0));
+        let root = if points.is_empty() {
+            None
+        } else {
+            Some(build(&points, 0_usize, points.len()))
+        };
+        Self { points, root }
+    }
+
+    /// Returns a count of points.
+    pub const fn size(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns `true` if there are no points.
+    pub const fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Returns the points, sorted by `x`.
+    pub fn points(&self) -> &[(X, Y, V)] {
+        &self.points
+    }
+
+    /// Returns a count of points with `x` inside `xs` and `y` inside `ys`.
+    pub fn count<RX: RangeBounds<X>, RY: RangeBounds<Y>>(&self, xs: RX, ys: RY) -> usize {
+        let (a, b) = self.span(&xs);
+        let (lo, hi) = y_bounds(&ys);
+        let mut count = 0_usize;
+        if let Some(root) = &self.root {
+            // the bounds of `y_bounds` fall between keys, so a `rank` of
+            // either kind counts the keys below them
+            for node in canonical(root, a, b) {
+                let below_hi = match &hi {
+                    Bound::Included(k) | Bound::Excluded(k) => node.ys.rank(k),
+                    Bound::Unbounded => node.ys.size(),
+                };
+                let below_lo = match &lo {
+                    Bound::Included(k) | Bound::Excluded(k) => node.ys.rank(k),
+                    Bound::Unbounded => 0_usize,
+                };
+                count += below_hi.saturating_sub(below_lo);
+            }
+        }
+        count
+    }
+
+    /// Returns the points with `x` inside `xs` and `y` inside `ys`, sorted
+    /// by `x`.
+    pub fn report<RX: RangeBounds<X>, RY: RangeBounds<Y>>(
+        &self,
+        xs: RX,
+        ys: RY,
+    ) -> Vec<&(X, Y, V)> {
+        let (a, b) = self.span(&xs);
+        let mut found: Vec<usize> = Vec::new();
+        if let Some(root) = &self.root {
+            for node in canonical(root, a, b) {
+                found.extend(node.ys.range(y_bounds(&ys)).map(|((_, i), _)| *i));
+            }
+        }
+        found.sort_unstable();
+        found.into_iter().map(|i| &self.points[i]).collect()
+    }
+
+    // Indexes `a..b` of the points with `x` inside `xs`
+    fn span<RX: RangeBounds<X>>(&self, xs: &RX) -> (usize, usize) {
+        let a = match xs.start_bound() {
+            Bound::Included(x) => self.points.partition_point(|p| p.0.lt(x)),
+            Bound::Excluded(x) => self.points.partition_point(|p| p.0.le(x)),
+            Bound::Unbounded => 0_usize,
+        };
+        let b = match xs.end_bound() {
+            Bound::Included(x) => self.points.partition_point(|p| p.0.le(x)),
+            Bound::Excluded(x) => self.points.partition_point(|p| p.0.lt(x)),
+            Bound::Unbounded => self.points.len(),
+        };
+        (a, b.max(a))
+    }
+}
+
+// Builds the node of the points `lo..hi`, `lo < hi`
+fn build<X, Y: Ord + Clone, V>(points: &[(X, Y, V)], lo: usize, hi: usize) -> Node<Y> {
+    let mut ys = RedBlackTree::new();
+    for (i, p) in points.iter().enumerate().take(hi).skip(lo) {
+        ys.put((p.1.clone(), i), ());
+    }
+    let children = if hi - lo > 1_usize {
+        let mid = lo + (hi - lo) / 2_usize;
+        Some(Box::new((build(points, lo, mid), build(points, mid, hi))))
+    } else {
+        None
+    };
+    Node {
+        lo,
+        hi,
+        ys,
+        children,
+    }
+}
+
+// The fewest nodes covering exactly the points `a..b`, `O(log n)` of them
+fn canonical<Y: Ord + Clone>(root: &Node<Y>, a: usize, b: usize) -> Vec<&Node<Y>> {
+    let mut nodes = Vec::new();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if node.hi.le(&a) || node.lo.ge(&b) {
+            continue;
+        }
+        match &node.children {
+            Some(children) if node.lo.lt(&a) || node.hi.gt(&b) => {
+                stack.push(&children.1);
+                stack.push(&children.0);
+            }
+            _ => nodes.push(node),
+        }
+    }
+    nodes
+}
+
+// Bounds of `ys` over the keys `(y, index)` of the trees, indexes are
+// below `usize::MAX`, so `(y, 0)` and `(y, usize::MAX)` enclose every key
+// of `y`
+fn y_bounds<Y: Clone, RY: RangeBounds<Y>>(ys: &RY) -> (Bound<YKey<Y>>, Bound<YKey<Y>>) {
+    let lo = match ys.start_bound() {
+        Bound::Included(y) => Bound::Included((y.clone(), 0_usize)),
+        Bound::Excluded(y) => Bound::Excluded((y.clone(), usize::MAX)),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    let hi = match ys.end_bound() {
+        Bound::Included(y) => Bound::Included((y.clone(), usize::MAX)),
+        Bound::Excluded(y) => Bound::Excluded((y.clone(), 0_usize)),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    (lo, hi)
+}
+
+impl<X: Ord + fmt::Debug, Y: Ord + Clone + fmt::Debug, V: fmt::Debug> fmt::Debug
+    for RangeTree<X, Y, V>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangeTree")
+            .field("points", &self.points)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RangeTree;
+    use crate::bench_support::shuffled_keys;
+
+    #[test]
+    fn test_against_brute_force() {
+        let keys = shuffled_keys(1200, 30);
+        // small coordinates, so points share `x` and `y`
+        let points: Vec<(u64, u64, usize)> = keys
+            .chunks(2)
+            .enumerate()
+            .map(|(i, xy)| (xy[0] % 50, xy[1] % 50, i))
+            .collect();
+        let tree = RangeTree::new(points.clone());
+        assert_eq!(tree.size(), 600_usize);
+        for q in shuffled_keys(400, 31).chunks(4) {
+            let (x0, x1) = (q[0] % 55, q[1] % 55);
+            let (y0, y1) = (q[2] % 55, q[3] % 55);
+            let mut expected: Vec<&(u64, u64, usize)> = points
+                .iter()
+                .filter(|p| (x0..=x1).contains(&p.0) && (y0..y1).contains(&p.1))
+                .collect();
+            expected.sort_by_key(|p| p.0);
+            assert_eq!(tree.count(x0..=x1, y0..y1), expected.len());
+            let reported = tree.report(x0..=x1, y0..y1);
+            // points of an equal `x` keep no order of their own
+            let mut sorted = reported.clone();
+            sorted.sort_by_key(|p| (p.0, p.2));
+            expected.sort_by_key(|p| (p.0, p.2));
+            assert_eq!(sorted, expected);
+            assert!(reported.windows(2).all(|w| w[0].0.le(&w[1].0)));
+        }
+        assert_eq!(tree.count(.., ..), 600_usize);
+        assert_eq!(tree.count(10..10, ..), 0_usize);
+        assert!(RangeTree::<u32, u32, ()>::new(Vec::new())
+            .report(.., ..)
+            .is_empty());
+    }
+}
@@ -1,6 +1,18 @@
-use crate::{SedgewickMap, TreeTraversal};
+use crate::entry::{Entry, OccupiedEntry, VacantEntry};
+use crate::node::{self, BinaryTreeNode};
+use crate::paren::{self, ParenNode, Parsed, Parts};
+use crate::pool::Pool;
+use crate::view::{self, SubrangeView, TreeView};
+#[cfg(feature = "rayon")]
+use crate::PARALLEL_CUTOFF;
+use crate::{
+    reaches_above, reaches_below, Map, OrderedMap, ParseTreeError, PutPolicy, SedgewickMap,
+    Traversals, TreeTraversal,
+};
 use std::cmp::Ordering;
-use std::ops::Index;
+use std::fmt::{self, Display};
+use std::ops::{Index, RangeBounds};
+use std::str::FromStr;
 
 /// 3.3 Balanced Search Trees: Red-Black BST
 ///
@@ -9,7 +21,7 @@ use std::ops::Index;
 /// # Examples
 ///
 /// ```
-/// use treers::SedgewickMap;
+/// use treers::{Map, SedgewickMap};
 /// use treers::rbtree::RedBlackTree;
 ///
 /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
@@ -32,43 +44,51 @@ use std::ops::Index;
 /// assert_eq!(rbtree.height(), Some(2_usize));
 /// assert_eq!(rbtree.size(), 6_usize);
 /// ```
-#[derive(Debug)]
-pub enum RedBlackTree<K: Ord + Clone, V: Clone> {
+#[derive(Debug, Clone)]
+pub struct RedBlackTree<K: Ord, V> {
+    root: Tree<K, V>,
+    // Boxes of deleted nodes for new ones, kept by the root only
+    spare: Pool<RedBlackTree<K, V>>,
+}
+
+// Nodes are only built inside the module, so colors and sizes always
+// hold up
+#[derive(Debug, Clone)]
+enum Tree<K: Ord, V> {
     Node {
         k: K,
         v: V,
-        color: bool,
+        color: Color,
         size: usize,
         left: Box<RedBlackTree<K, V>>,
         right: Box<RedBlackTree<K, V>>,
     },
-    NIL,
+    Nil,
+}
+
+/// Color of the link from a node up to its parent, a red link glues the
+/// node and its parent into a 3-node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    Red,
+    Black,
 }
 
-impl<K: Clone + Ord, V: Clone> Clone for RedBlackTree<K, V> {
-    fn clone(&self) -> RedBlackTree<K, V> {
+impl Color {
+    /// Checks if the link is red.
+    pub const fn is_red(self) -> bool {
+        matches!(self, Color::Red)
+    }
+
+    const fn flipped(self) -> Self {
         match self {
-            RedBlackTree::Node {
-                ref k,
-                ref v,
-                ref color,
-                ref size,
-                ref left,
-                ref right,
-            } => RedBlackTree::Node {
-                k: k.clone(),
-                v: v.clone(),
-                color: *color,
-                size: *size,
-                left: left.clone(),
-                right: right.clone(),
-            },
-            RedBlackTree::NIL => RedBlackTree::NIL,
+            Color::Red => Color::Black,
+            Color::Black => Color::Red,
         }
     }
 }
 
-impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for RedBlackTree<K, V> {
+impl<K: Ord + Clone, V: Clone> RedBlackTree<K, V> {
     /// Inits a new instance of Red-Black Tree.
     ///
     /// # Examples
@@ -77,48 +97,62 @@ impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for RedBlackTree<K, V> {
     ///
     /// ```
     /// use treers::rbtree::RedBlackTree;
-    /// use treers::SedgewickMap;
+    /// use treers::Map;
     ///
     /// let rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
     /// assert!(rbtree.is_empty());
     /// ```
-    fn new() -> Self {
-        RedBlackTree::NIL
+    pub const fn new() -> Self {
+        RedBlackTree {
+            root: Tree::Nil,
+            spare: Pool::new(),
+        }
     }
 
-    /// Returns a size of elements in `Red-Black Tree`.
+    /// Returns a handle to the root node, `None` for an empty tree.
     ///
     /// # Examples
     ///
     /// Basic usage:
     ///
     /// ```
-    /// use treers::rbtree::RedBlackTree;
-    /// use treers::SedgewickMap;
+    /// use treers::rbtree::{Color, RedBlackTree};
+    /// use treers::Map;
     ///
     /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-    /// assert_eq!(rbtree.size(), 0_usize);
     /// rbtree.put('a', 1);
     /// rbtree.put('b', 2);
-    /// rbtree.put('c', 3);
-    /// rbtree.put('d', 4);
-    /// assert_eq!(rbtree.size(), 4_usize);
+    ///
+    /// let root = rbtree.root().unwrap();
+    /// assert_eq!((root.key(), root.value()), (&'b', &2));
+    /// assert_eq!(root.color(), Color::Black);
+    /// let left = root.left().root().unwrap();
+    /// assert_eq!((left.key(), left.color()), (&'a', Color::Red));
+    /// assert!(root.right().is_empty());
     /// ```
-    fn size(&self) -> usize {
-        match &self {
-            RedBlackTree::Node {
-                k: _,
-                v: _,
-                color: _,
-                ref size,
-                left: _,
-                right: _,
-            } => *size,
-            _ => 0_usize,
+    pub const fn root(&self) -> Option<NodeRef<'_, K, V>> {
+        match self.root {
+            Tree::Node {
+                ref k,
+                ref v,
+                color,
+                size,
+                ref left,
+                ref right,
+            } => Some(NodeRef {
+                k,
+                v,
+                color,
+                size,
+                left,
+                right,
+            }),
+            Tree::Nil => None,
         }
     }
 
-    /// Returns a reference to optional reference to value.
+    /// Returns the count of keys in the subtree rooted at the node of `key`,
+    /// the size the node keeps, `None` if `key` is missing.
     ///
     /// # Examples
     ///
@@ -126,60 +160,253 @@ impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for RedBlackTree<K, V> {
     ///
     /// ```
     /// use treers::rbtree::RedBlackTree;
-    /// use treers::SedgewickMap;
+    /// use treers::Map;
     ///
-    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-    /// rbtree.put('a', 1);
-    /// assert_eq!(rbtree.get(&'a'), Some(&1));
-    /// assert_eq!(rbtree[&'a'], 1);
-    /// assert_eq!(rbtree.get(&'b'), None);
+    /// let mut tree: RedBlackTree<u32, ()> = RedBlackTree::new();
+    /// for k in 1..=7 {
+    ///     tree.put(k, ());
+    /// }
+    /// // ascending keys balance into a perfect tree under 4
+    /// assert_eq!(tree.subtree_size(&4), Some(7_usize));
+    /// assert_eq!(tree.subtree_size(&2), Some(3_usize));
+    /// assert_eq!(tree.subtree_size(&7), Some(1_usize));
+    /// assert_eq!(tree.subtree_size(&9), None);
     /// ```
-    fn get(&self, key: &K) -> Option<&V> {
-        match self {
-            RedBlackTree::Node {
+    pub fn subtree_size(&self, key: &K) -> Option<usize> {
+        let node = self.root()?;
+        match key.cmp(node.key()) {
+            Ordering::Less => node.left().subtree_size(key),
+            Ordering::Greater => node.right().subtree_size(key),
+            _ => Some(node.size()),
+        }
+    }
+
+    // Value of the root node, editing it leaves the shape as it is
+    pub(crate) const fn root_value_mut(&mut self) -> Option<&mut V> {
+        match self.root {
+            Tree::Node {
+                k: _,
+                ref mut v,
+                color: _,
+                size: _,
+                left: _,
+                right: _,
+            } => Some(v),
+            Tree::Nil => None,
+        }
+    }
+}
+
+/// A node of a `RedBlackTree`, borrowed for inspection.
+///
+/// Nodes can only be read through it, so colors, sizes and key order stay
+/// the ones the tree keeps.
+#[derive(Debug)]
+pub struct NodeRef<'a, K: Ord + Clone, V: Clone> {
+    k: &'a K,
+    v: &'a V,
+    color: Color,
+    size: usize,
+    left: &'a RedBlackTree<K, V>,
+    right: &'a RedBlackTree<K, V>,
+}
+
+impl<K: Ord + Clone, V: Clone> Clone for NodeRef<'_, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Copy for NodeRef<'_, K, V> {}
+
+impl<'a, K: Ord + Clone, V: Clone> NodeRef<'a, K, V> {
+    /// Returns the key of the node.
+    pub const fn key(&self) -> &'a K {
+        self.k
+    }
+
+    /// Returns the value of the node.
+    pub const fn value(&self) -> &'a V {
+        self.v
+    }
+
+    /// Returns the color of the link from the node up to its parent.
+    pub const fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Returns the count of entries in the subtree of the node.
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the left subtree, keys less than the key of the node.
+    pub const fn left(&self) -> &'a RedBlackTree<K, V> {
+        self.left
+    }
+
+    /// Returns the right subtree, keys greater than the key of the node.
+    pub const fn right(&self) -> &'a RedBlackTree<K, V> {
+        self.right
+    }
+}
+
+/// A lazy in-order iterator over the entries of a `RedBlackTree`, made by
+/// `iter`.
+///
+/// It keeps a stack of the parents still to visit, at most `2 log n`
+/// nodes, and knows its length from the stored sizes.
+#[derive(Debug)]
+pub struct Iter<'a, K: Ord + Clone, V: Clone> {
+    stack: Vec<&'a RedBlackTree<K, V>>,
+    remaining: usize,
+}
+
+impl<K: Ord + Clone, V: Clone> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Iter {
+            stack: self.stack.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> Iter<'a, K, V> {
+    fn new(tree: &'a RedBlackTree<K, V>) -> Self {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            remaining: tree.size(),
+        };
+        iter.push_left(tree);
+        iter
+    }
+
+    // Stacks `tree` and its left spine, the smallest key on top
+    fn push_left(&mut self, mut tree: &'a RedBlackTree<K, V>) {
+        while let Tree::Node {
+            k: _,
+            v: _,
+            color: _,
+            size: _,
+            ref left,
+            right: _,
+        } = tree.root
+        {
+            self.stack.push(tree);
+            tree = left;
+        }
+    }
+}
+
+impl<'a, K: Ord + Clone, V: Clone> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &self.stack.pop()?.root {
+            Tree::Node {
                 ref k,
                 ref v,
                 color: _,
                 size: _,
-                ref left,
+                left: _,
                 ref right,
-            } => match key.cmp(k) {
-                Ordering::Less => left.get(key),
-                Ordering::Greater => right.get(key),
-                _ => Some(v),
-            },
-            _ => None,
+            } => {
+                self.push_left(right);
+                self.remaining -= 1_usize;
+                Some((k, v))
+            }
+            Tree::Nil => None,
         }
     }
 
-    /// Insert a key-value pair into the `Red-Black Tree`.
-    ///
-    /// # Examples
-    ///
-    /// Basic usage:
-    ///
-    /// ```
-    /// use treers::rbtree::RedBlackTree;
-    /// use treers::SedgewickMap;
-    ///
-    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-    /// assert!(rbtree.is_empty());
-    ///
-    /// rbtree.put('a', 1_i32);
-    /// assert_eq!(rbtree.is_empty(), false);
-    /// assert_eq!(rbtree.get(&'a'), Some(&1_i32));
-    /// assert_eq!(rbtree[&'a'], 1_i32);
-    /// ```
-    fn put(&mut self, key: K, value: V) {
-        // move values!
-        self.insert(&key, &value);
-        // set root node to black
-        self.set_color(false);
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
     }
+}
 
-    /// Get height of `Red-Black Tree`.
+impl<K: Ord + Clone, V: Clone> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K: Ord + Clone, V: Clone> std::iter::FusedIterator for Iter<'_, K, V> {}
+
+impl<'a, K: Ord + Clone, V: Clone> IntoIterator for &'a RedBlackTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A lazy in-order iterator taking apart a `RedBlackTree`, made by
+/// `into_iter`.
+///
+/// It owns the parents still to visit, at most `2 log n` of them, with
+/// their right subtrees, and frees the nodes as it passes them.
+#[derive(Debug)]
+pub struct IntoIter<K: Ord + Clone, V: Clone> {
+    stack: Vec<(K, V, RedBlackTree<K, V>)>,
+    remaining: usize,
+}
+
+impl<K: Ord + Clone, V: Clone> IntoIter<K, V> {
+    // Stacks `tree` and its left spine, the smallest key on top
+    fn push_left(&mut self, mut tree: RedBlackTree<K, V>) {
+        while let Tree::Node {
+            k,
+            v,
+            color: _,
+            size: _,
+            left,
+            right,
+        } = tree.root
+        {
+            self.stack.push((k, v, *right));
+            tree = *left;
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (k, v, right) = self.stack.pop()?;
+        self.push_left(right);
+        self.remaining -= 1_usize;
+        Some((k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K: Ord + Clone, V: Clone> std::iter::FusedIterator for IntoIter<K, V> {}
+
+impl<K: Ord + Clone, V: Clone> IntoIterator for RedBlackTree<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut iter = IntoIter {
+            stack: Vec::new(),
+            remaining: self.size(),
+        };
+        iter.push_left(self);
+        iter
+    }
+}
+
+impl<K: Ord, V> RedBlackTree<K, V> {
+    /// Returns the memory of deleted nodes to the allocator.
     ///
-    /// Red-Black Tree is a balanced tree.
+    /// `delete`, `delete_min` and `delete_max` rebuild the nodes on their
+    /// path and keep the memory of the ones they free, `put` and `entry`
+    /// build new nodes in it, so a tree with as many deletes as puts stops
+    /// allocating for them. Once a tree has shrunk for good, this releases
+    /// what it kept.
     ///
     /// # Examples
     ///
@@ -187,34 +414,23 @@ impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for RedBlackTree<K, V> {
     ///
     /// ```
     /// use treers::rbtree::RedBlackTree;
-    /// use treers::SedgewickMap;
+    /// use treers::{Map, OrderedMap};
     ///
-    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-    /// rbtree.put('a', 1);
-    /// rbtree.put('b', 2);
-    /// rbtree.put('c', 3);
-    /// rbtree.put('d', 4);
-    /// //   b         <-- height: 0
-    /// //  / \
-    /// // a   d       <-- height: 1
-    /// //    / \
-    /// //   c         <-- height: 2
-    /// // Note -The Height of red-black tree with single node is taken as zero.
-    /// assert_eq!(rbtree.get(&'a'), Some(&1_i32));
-    /// assert_eq!(rbtree.height(), Some(2_usize));
-    /// assert_eq!(rbtree.size(), 4_usize);
+    /// let mut rbtree: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// for i in 0..100 {
+    ///     rbtree.put(i, i);
+    /// }
+    /// for i in 10..100 {
+    ///     rbtree.delete(&i);
+    /// }
+    /// rbtree.shrink_to_fit();
+    /// assert_eq!(rbtree.max(), Some(&9));
     /// ```
-    fn height(&self) -> Option<usize> {
-        let height_rbtree = self.get_height();
-        if height_rbtree > 0_usize {
-            Some(height_rbtree - 1_usize)
-        } else {
-            None
-        }
+    pub fn shrink_to_fit(&mut self) {
+        self.spare.clear();
     }
 
-    /// Checks if `Red-Black Tree` node is empty.
-    ///
+    /// Returns a read-only, copyable view over `Red-Black Tree`.
     ///
     /// # Examples
     ///
@@ -222,19 +438,19 @@ impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for RedBlackTree<K, V> {
     ///
     /// ```
     /// use treers::rbtree::RedBlackTree;
-    /// use treers::SedgewickMap;
+    /// use treers::Map;
     ///
     /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-    /// assert!(rbtree.is_empty());
-    /// rbtree.put('a', 2);
-    /// assert_eq!(rbtree.is_empty(), false);
+    /// rbtree.put('a', 1);
+    /// let view = rbtree.view();
+    /// assert_eq!(view.get(&'a'), Some(&1));
+    /// assert_eq!(view.size(), 1_usize);
     /// ```
-    fn is_empty(&self) -> bool {
-        !matches!(self, RedBlackTree::Node { .. })
+    pub fn view(&self) -> TreeView<'_, K, V> {
+        TreeView::from(self)
     }
 
-    /// Returns a optional reference to minimal key
-    ///
+    /// Returns a read-only view over keys of `Red-Black Tree` inside `range`.
     ///
     /// # Examples
     ///
@@ -242,511 +458,3002 @@ impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for RedBlackTree<K, V> {
     ///
     /// ```
     /// use treers::rbtree::RedBlackTree;
-    /// use treers::SedgewickMap;
+    /// use treers::{Map, OrderedMap};
     ///
     /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-    /// assert_eq!(rbtree.min(), None);
-    /// rbtree.put('c', 1);
-    /// rbtree.put('a', 2);
-    /// rbtree.put('b', 3);
-    /// rbtree.put('d', 4);
-    /// assert_eq!(rbtree.min(), Some(&'a'));
+    /// for (v, k) in ('a'..='f').enumerate() {
+    ///     rbtree.put(k, v as i32);
+    /// }
+    /// let sub = rbtree.subrange_view('b'..'e');
+    /// assert_eq!(sub.size(), 3_usize);
+    /// assert_eq!(sub.min(), Some(&'b'));
+    /// assert_eq!(sub.max(), Some(&'d'));
+    /// assert_eq!(sub.get(&'f'), None);
     /// ```
-    fn min(&self) -> Option<&K> {
-        match self {
-            RedBlackTree::Node {
-                ref k,
-                v: _,
-                color: _,
-                size: _,
-                ref left,
-                right: _,
-            } => {
-                if let Some(l) = left.min() {
-                    Some(l)
-                } else {
-                    Some(k)
-                }
+    pub fn subrange_view<R: RangeBounds<K>>(&self, range: R) -> SubrangeView<'_, K, V>
+    where
+        K: Clone,
+    {
+        self.view().subrange(range)
+    }
+
+    fn take_range<'a, R: RangeBounds<K>>(&'a self, range: &R, vec: &mut Vec<(&'a K, &'a V)>) {
+        if let Tree::Node {
+            ref k,
+            ref v,
+            color: _,
+            size: _,
+            ref left,
+            ref right,
+        } = &self.root
+        {
+            if reaches_below(range, k) {
+                left.take_range(range, vec);
+            }
+            if range.contains(k) {
+                vec.push((k, v));
+            }
+            if reaches_above(range, k) {
+                right.take_range(range, vec);
             }
-            _ => None,
         }
     }
+}
 
-    /// Returns a optional reference to maximum key
-    ///
-    ///
-    /// # Examples
-    ///
-    /// Basic usage:
-    ///
-    /// ```
-    /// use treers::rbtree::RedBlackTree;
-    /// use treers::SedgewickMap;
-    ///
-    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-    /// assert_eq!(rbtree.max(), None);
-    /// rbtree.put('c', 1);
-    /// rbtree.put('a', 2);
-    /// rbtree.put('b', 3);
-    /// rbtree.put('d', 4);
-    /// assert_eq!(rbtree.max(), Some(&'d'));
-    /// ```
-    fn max(&self) -> Option<&K> {
-        match self {
-            RedBlackTree::Node {
-                ref k,
+impl<K: Ord, V> view::Read<K, V> for RedBlackTree<K, V> {
+    fn size(&self) -> usize {
+        match self.root {
+            Tree::Node {
+                k: _,
                 v: _,
                 color: _,
-                size: _,
+                ref size,
                 left: _,
-                ref right,
-            } => {
-                if let Some(l) = right.max() {
-                    Some(l)
-                } else {
-                    Some(k)
-                }
-            }
-            _ => None,
+                right: _,
+            } => *size,
+            _ => 0_usize,
         }
     }
-}
 
-impl<K: Ord + Clone, V: Clone> TreeTraversal<K, V> for RedBlackTree<K, V> {
-    /// Returns traverse post ordered
-    ///
-    ///
-    /// # Examples
-    ///
-    /// Basic usage:
-    ///
-    /// ```
-    /// use treers::rbtree::RedBlackTree;
-    /// use treers::{SedgewickMap, TreeTraversal, Traversals};
-    ///
-    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-    /// rbtree.put('a', 1);
-    /// rbtree.put('b', 2);
-    /// rbtree.put('c', 3);
-    /// rbtree.put('d', 4);
-    /// rbtree.put('e', 5);
-    /// rbtree.put('f', 6);
-    /// // Generate a balanced Red-Black Binary Search Tree
-    /// //          d(B)           <-- height: 0
-    /// //        /      \
-    /// //     (R)b       f(B)     <-- height: 1
-    /// //      / \      /    \
-    /// //   (B)a  c(B) e(R)       <-- height: 2
-    /// assert_eq!(rbtree.traverse(&Traversals::PreOrder).as_slice(),
-    ///       &[(&'d', &4), (&'b', &2), (&'a', &1),
-    ///         (&'c', &3), (&'f', &6), (&'e', &5)]);
-    /// ```
-    fn pre_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>) {
-        if let RedBlackTree::Node {
+    fn get(&self, key: &K) -> Option<&V> {
+        let mut tree = &self.root;
+        while let Tree::Node {
             ref k,
             ref v,
             color: _,
             size: _,
             ref left,
             ref right,
-        } = self
+        } = tree
         {
-            vec.push((k, v));
-            left.pre_order(vec);
-            right.pre_order(vec);
+            match key.cmp(k) {
+                Ordering::Less => tree = &left.root,
+                Ordering::Greater => tree = &right.root,
+                _ => return Some(v),
+            }
         }
+        None
     }
 
-    /// Returns traverse in ordered
-    ///
-    ///
-    /// # Examples
-    ///
-    /// Basic usage:
-    ///
-    /// ```
-    /// use treers::rbtree::RedBlackTree;
-    /// use treers::{SedgewickMap, TreeTraversal, Traversals};
-    ///
-    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-    /// rbtree.put('a', 1);
-    /// rbtree.put('b', 2);
-    /// rbtree.put('c', 3);
-    /// rbtree.put('d', 4);
-    /// rbtree.put('e', 5);
-    /// rbtree.put('f', 6);
-    /// // Generate a balanced Red-Black Binary Search Tree
-    /// //          d(B)           <-- height: 0
-    /// //        /      \
-    /// //     (R)b       f(B)     <-- height: 1
-    /// //      / \      /    \
-    /// //   (B)a  c(B) e(R)       <-- height: 2
-    /// assert_eq!(rbtree.traverse(&Traversals::InOrder).as_slice(),
-    ///       &[(&'a', &1), (&'b', &2), (&'c', &3),
-    ///         (&'d', &4), (&'e', &5), (&'f', &6)]);
-    /// ```
-    fn in_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>) {
-        if let RedBlackTree::Node {
+    fn min(&self) -> Option<&K> {
+        let mut found = None;
+        let mut tree = &self.root;
+        while let Tree::Node {
             ref k,
-            ref v,
+            v: _,
             color: _,
             size: _,
             ref left,
-            ref right,
-        } = self
+            right: _,
+        } = tree
         {
-            left.in_order(vec);
-            vec.push((k, v));
-            right.in_order(vec);
+            found = Some(k);
+            tree = &left.root;
         }
+        found
     }
 
-    /// Returns traverse post ordered
-    ///
-    ///
-    /// # Examples
-    ///
-    /// Basic usage:
-    ///
-    /// ```
-    /// use treers::rbtree::RedBlackTree;
-    /// use treers::{SedgewickMap, TreeTraversal, Traversals};
-    ///
-    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-    /// rbtree.put('a', 1);
-    /// rbtree.put('b', 2);
-    /// rbtree.put('c', 3);
-    /// rbtree.put('d', 4);
-    /// rbtree.put('e', 5);
-    /// rbtree.put('f', 6);
-    /// // Generate a balanced Red-Black Binary Search Tree
-    /// //          d(B)           <-- height: 0
-    /// //        /      \
-    /// //     (R)b       f(B)     <-- height: 1
-    /// //      / \      /    \
-    /// //   (B)a  c(B) e(R)       <-- height: 2
-    /// assert_eq!(rbtree.traverse(&Traversals::PostOrder).as_slice(),
-    ///       &[(&'a', &1), (&'c', &3), (&'b', &2),
-    ///         (&'e', &5), (&'f', &6), (&'d', &4)]);
-    /// ```
-    fn post_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>) {
-        if let RedBlackTree::Node {
+    fn max(&self) -> Option<&K> {
+        let mut found = None;
+        let mut tree = &self.root;
+        while let Tree::Node {
             ref k,
-            ref v,
+            v: _,
             color: _,
             size: _,
-            ref left,
+            left: _,
             ref right,
-        } = self
+        } = tree
         {
-            left.post_order(vec);
-            right.post_order(vec);
-            vec.push((k, v));
+            found = Some(k);
+            tree = &right.root;
         }
+        found
     }
 
-    /// Returns traverse level ordered
-    ///
-    ///
-    /// # Examples
-    ///
-    /// Basic usage:
-    ///
-    /// ```
-    /// use treers::rbtree::RedBlackTree;
-    /// use treers::{SedgewickMap, TreeTraversal, Traversals};
-    ///
-    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-    /// rbtree.put('a', 1);
-    /// rbtree.put('b', 2);
-    /// rbtree.put('c', 3);
-    /// rbtree.put('d', 4);
-    /// rbtree.put('e', 5);
-    /// rbtree.put('f', 6);
-    /// // Generate a balanced Red-Black Binary Search Tree
-    /// //          d(B)           <-- height: 0
-    /// //        /      \
-    /// //     (R)b       f(B)     <-- height: 1
-    /// //      / \      /    \
-    /// //   (B)a  c(B) e(R)       <-- height: 2
-    /// assert_eq!(rbtree.traverse(&Traversals::LevelOrder).as_slice(),
-    ///       &[            (&'d', &4),               // <-- height: 0
-    ///                (&'b', &2), (&'f', &6),        // <-- height: 1
-    ///         (&'a', &1), (&'c', &3), (&'e', &5)]); // <-- height: 2
-    /// ```
-    fn level_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>, level: usize) {
-        if let RedBlackTree::Node {
+    fn floor(&self, key: &K) -> Option<&K> {
+        let mut found = None;
+        let mut tree = &self.root;
+        while let Tree::Node {
             ref k,
-            ref v,
+            v: _,
             color: _,
             size: _,
             ref left,
             ref right,
-        } = self
+        } = tree
         {
-            match level {
-                0 => vec.push((k, v)),
-                _ => {
-                    left.level_order(vec, level - 1);
-                    right.level_order(vec, level - 1);
+            match key.cmp(k) {
+                Ordering::Less => tree = &left.root,
+                Ordering::Greater => {
+                    found = Some(k);
+                    tree = &right.root;
                 }
+                _ => return Some(k),
             }
         }
+        found
     }
-}
 
-// internal methods
-impl<'a, K: 'a + Ord + Clone, V: 'a + Clone> RedBlackTree<K, V> {
-    fn insert(&mut self, key: &'a K, value: &'a V) {
-        match self {
-            RedBlackTree::Node {
-                ref mut k,
-                ref mut v,
-                ref mut color,
-                ref mut size,
-                ref mut left,
-                ref mut right,
-            } => {
-                match key.cmp(k) {
-                    // pass by reference, with same lifetime
-                    Ordering::Less => left.insert(&key, &value),
-                    Ordering::Greater => right.insert(&key, &value),
-                    _ => {}
-                }
-                // Rotate Left
-                if right.is_red() && !left.is_red() {
-                    let right_clone = right.clone();
-                    *right = right_clone.get_right_clone();
-                    let right_size = right_clone.size();
-                    *color = right_clone.is_right_red();
-                    left.set_vals(
-                        &k,
-                        &v,
-                        true,
-                        right_size,
-                        *left.clone(),
-                        *right_clone.get_left_clone(),
-                    );
-                    // Don't move, but use clone, instead
-                    if let Some(kk) = right_clone.get_key() {
-                        *k = kk.clone();
-                    }
-                    if let Some(vv) = right_clone.get_val() {
-                        *v = vv.clone();
-                    }
-                }
-                // Balance 4-node
-                // Rotate Right
-                if left.is_red() && left.is_left_red() {
-                    let left_clone = left.clone();
-                    *left = left_clone.get_left_clone();
-                    let left_size = left.size();
-                    *color = true;
-                    right.set_vals(
-                        &k,
-                        &v,
-                        true,
-                        left_size,
-                        *left_clone.get_right_clone(),
-                        *right.clone(),
-                    );
-                    // Don't move, but use clone, instead, from left clone
-                    if let Some(kk) = left_clone.get_key() {
-                        *k = kk.clone();
-                    }
-                    if let Some(vv) = left_clone.get_val() {
-                        *v = vv.clone();
-                    }
-                }
-                // Split 4-node
-                // Flip colors
-                if left.is_red() && right.is_red() {
-                    *color = true;
-                    left.set_color(false);
-                    right.set_color(false);
-                }
-                *size = left.size() + right.size() + 1_usize;
-            }
-            RedBlackTree::NIL => {
-                // Insert a leaf node
-                *self = RedBlackTree::Node {
-                    k: key.clone(),
-                    v: value.clone(),
-                    color: true,
-                    size: 1,
-                    left: Box::new(RedBlackTree::NIL),
-                    right: Box::new(RedBlackTree::NIL),
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        let mut found = None;
+        let mut tree = &self.root;
+        while let Tree::Node {
+            ref k,
+            v: _,
+            color: _,
+            size: _,
+            ref left,
+            ref right,
+        } = tree
+        {
+            match key.cmp(k) {
+                Ordering::Less => {
+                    found = Some(k);
+                    tree = &left.root;
                 }
+                Ordering::Greater => tree = &right.root,
+                _ => return Some(k),
             }
         }
+        found
     }
 
-    fn get_height(&self) -> usize {
-        match self {
-            RedBlackTree::Node {
-                k: _,
-                v: _,
-                color: _,
-                size: _,
-                ref left,
-                ref right,
-            } => 1_usize + std::cmp::max(left.get_height(), right.get_height()),
-            _ => 0_usize,
-        }
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        let mut vec = Vec::new();
+        self.take_range(&range, &mut vec);
+        vec.into_iter()
     }
 
-    fn set_vals(
-        &mut self,
-        key: &'a K,
-        val: &'a V,
-        c: bool,
-        s: usize,
-        l: RedBlackTree<K, V>,
-        r: RedBlackTree<K, V>,
-    ) {
-        match self {
-            RedBlackTree::Node {
-                ref mut k,
-                ref mut v,
-                ref mut color,
-                ref mut size,
-                ref mut left,
-                ref mut right,
-            } => {
-                *k = key.clone();
-                *v = val.clone();
-                *color = c;
-                *size = s;
-                *left = Box::new(l);
-                *right = Box::new(r);
-            }
-            RedBlackTree::NIL => {
-                *self = RedBlackTree::Node {
-                    k: key.clone(),
-                    v: val.clone(),
-                    color: c,
-                    size: 1,
-                    left: Box::new(l),
-                    right: Box::new(r),
+    fn rank(&self, key: &K) -> usize {
+        let mut rank = 0_usize;
+        let mut tree = &self.root;
+        while let Tree::Node {
+            ref k,
+            v: _,
+            color: _,
+            size: _,
+            ref left,
+            ref right,
+        } = tree
+        {
+            match key.cmp(k) {
+                Ordering::Less => tree = &left.root,
+                Ordering::Greater => {
+                    rank += view::Read::size(left.as_ref()) + 1_usize;
+                    tree = &right.root;
                 }
+                _ => return rank + view::Read::size(left.as_ref()),
             }
         }
+        rank
     }
 
-    fn get_key(&self) -> Option<&K> {
-        if let RedBlackTree::Node {
+    fn successor(&self, key: &K) -> Option<(&K, &V)> {
+        let mut found = None;
+        let mut tree = &self.root;
+        while let Tree::Node {
             ref k,
-            v: _,
+            ref v,
             color: _,
             size: _,
-            left: _,
-            right: _,
-        } = self
+            ref left,
+            ref right,
+        } = tree
         {
-            Some(k)
-        } else {
-            None
+            if key.lt(k) {
+                found = Some((k, v));
+                tree = &left.root;
+            } else {
+                tree = &right.root;
+            }
         }
+        found
     }
 
-    fn get_val(&self) -> Option<&V> {
-        if let RedBlackTree::Node {
-            k: _,
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)> {
+        let mut found = None;
+        let mut tree = &self.root;
+        while let Tree::Node {
+            ref k,
             ref v,
             color: _,
             size: _,
-            left: _,
-            right: _,
-        } = self
+            ref left,
+            ref right,
+        } = tree
+        {
+            if key.gt(k) {
+                found = Some((k, v));
+                tree = &right.root;
+            } else {
+                tree = &left.root;
+            }
+        }
+        found
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Map<K, V> for RedBlackTree<K, V> {
+    /// Returns a size of elements in `Red-Black Tree`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// assert_eq!(rbtree.size(), 0_usize);
+    /// rbtree.put('a', 1);
+    /// rbtree.put('b', 2);
+    /// rbtree.put('c', 3);
+    /// rbtree.put('d', 4);
+    /// assert_eq!(rbtree.size(), 4_usize);
+    /// ```
+    fn size(&self) -> usize {
+        view::Read::size(self)
+    }
+
+    /// Returns a reference to optional reference to value.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// rbtree.put('a', 1);
+    /// assert_eq!(rbtree.get(&'a'), Some(&1));
+    /// assert_eq!(rbtree[&'a'], 1);
+    /// assert_eq!(rbtree.get(&'b'), None);
+    /// ```
+    fn get(&self, key: &K) -> Option<&V> {
+        view::Read::get(self, key)
+    }
+
+    /// Insert a key-value pair into the `Red-Black Tree`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// assert!(rbtree.is_empty());
+    ///
+    /// rbtree.put('a', 1_i32);
+    /// assert_eq!(rbtree.is_empty(), false);
+    /// assert_eq!(rbtree.get(&'a'), Some(&1_i32));
+    /// assert_eq!(rbtree[&'a'], 1_i32);
+    ///
+    /// rbtree.put('a', 2_i32);
+    /// assert_eq!(rbtree.get(&'a'), Some(&2_i32));
+    /// assert_eq!(rbtree.size(), 1_usize);
+    /// ```
+    fn put(&mut self, key: K, value: V) {
+        let mut spare = std::mem::take(&mut self.spare);
+        // move values!
+        self.insert(&key, &value, &mut spare);
+        // set root node to black
+        self.set_color(Color::Black);
+        self.spare = spare;
+    }
+
+    fn put_with_policy(&mut self, key: K, value: V, policy: &mut PutPolicy<'_, V>) -> bool {
+        match self.value_mut(&key) {
+            Some(v) => {
+                policy.resolve(v, value);
+                false
+            }
+            None => {
+                self.put(key, value);
+                true
+            }
+        }
+    }
+
+    /// Checks if `Red-Black Tree` node is empty.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// assert!(rbtree.is_empty());
+    /// rbtree.put('a', 2);
+    /// assert_eq!(rbtree.is_empty(), false);
+    /// ```
+    fn is_empty(&self) -> bool {
+        !matches!(self.root, Tree::Node { .. })
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> OrderedMap<K, V> for RedBlackTree<K, V> {
+    /// Returns a optional reference to minimal key
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// assert_eq!(rbtree.min(), None);
+    /// rbtree.put('c', 1);
+    /// rbtree.put('a', 2);
+    /// rbtree.put('b', 3);
+    /// rbtree.put('d', 4);
+    /// assert_eq!(rbtree.min(), Some(&'a'));
+    /// ```
+    fn min(&self) -> Option<&K> {
+        view::Read::min(self)
+    }
+
+    /// Returns a optional reference to maximum key
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// assert_eq!(rbtree.max(), None);
+    /// rbtree.put('c', 1);
+    /// rbtree.put('a', 2);
+    /// rbtree.put('b', 3);
+    /// rbtree.put('d', 4);
+    /// assert_eq!(rbtree.max(), Some(&'d'));
+    /// ```
+    fn max(&self) -> Option<&K> {
+        view::Read::max(self)
+    }
+
+    /// Returns a optional reference to the largest key less than or equal to `key`
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// assert_eq!(rbtree.floor(&'c'), None);
+    /// rbtree.put('b', 1);
+    /// rbtree.put('d', 2);
+    /// assert_eq!(rbtree.floor(&'a'), None);
+    /// assert_eq!(rbtree.floor(&'b'), Some(&'b'));
+    /// assert_eq!(rbtree.floor(&'c'), Some(&'b'));
+    /// assert_eq!(rbtree.floor(&'z'), Some(&'d'));
+    /// ```
+    fn floor(&self, key: &K) -> Option<&K> {
+        view::Read::floor(self, key)
+    }
+
+    /// Returns a optional reference to the smallest key greater than or equal to `key`
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// assert_eq!(rbtree.ceiling(&'c'), None);
+    /// rbtree.put('b', 1);
+    /// rbtree.put('d', 2);
+    /// assert_eq!(rbtree.ceiling(&'a'), Some(&'b'));
+    /// assert_eq!(rbtree.ceiling(&'c'), Some(&'d'));
+    /// assert_eq!(rbtree.ceiling(&'d'), Some(&'d'));
+    /// assert_eq!(rbtree.ceiling(&'z'), None);
+    /// ```
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        view::Read::ceiling(self, key)
+    }
+
+    /// Returns the `n` entries with the smallest keys, in ascending order.
+    ///
+    /// Only the visited part of `Red-Black Tree` is walked, not the whole of it.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// rbtree.put('c', 3);
+    /// rbtree.put('a', 1);
+    /// rbtree.put('d', 4);
+    /// rbtree.put('b', 2);
+    /// assert_eq!(rbtree.smallest(2).as_slice(), &[(&'a', &1), (&'b', &2)]);
+    /// ```
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let mut vec = Vec::with_capacity(std::cmp::min(n, self.size()));
+        self.take_smallest(&mut vec, n);
+        vec.into_iter()
+    }
+
+    /// Returns the `n` entries with the largest keys, in descending order.
+    ///
+    /// Only the visited part of `Red-Black Tree` is walked, not the whole of it.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// rbtree.put('c', 3);
+    /// rbtree.put('a', 1);
+    /// rbtree.put('d', 4);
+    /// rbtree.put('b', 2);
+    /// assert_eq!(rbtree.largest(2).as_slice(), &[(&'d', &4), (&'c', &3)]);
+    /// ```
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let mut vec = Vec::with_capacity(std::cmp::min(n, self.size()));
+        self.take_largest(&mut vec, n);
+        vec.into_iter()
+    }
+
+    /// Returns entries of `Red-Black Tree` with keys inside `range`, in key order.
+    ///
+    /// Subtrees outside of `range` are not visited.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// for (v, k) in ('a'..='f').enumerate() {
+    ///     rbtree.put(k, v as i32);
+    /// }
+    /// assert_eq!(rbtree.range('b'..'d').as_slice(), &[(&'b', &1), (&'c', &2)]);
+    /// assert_eq!(rbtree.range('e'..).as_slice(), &[(&'e', &4), (&'f', &5)]);
+    /// assert_eq!(rbtree.range(..='a').as_slice(), &[(&'a', &0)]);
+    /// ```
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        view::Read::range(self, range)
+    }
+
+    fn try_fold_range<R, B, E, F>(&self, range: R, init: B, mut f: F) -> Result<B, E>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(B, &K, &V) -> Result<B, E>,
+    {
+        self.try_fold_at(&range, init, &mut f)
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> SedgewickMap<K, V> for RedBlackTree<K, V> {
+    /// Get height of `Red-Black Tree`.
+    ///
+    /// Red-Black Tree is a balanced tree.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// rbtree.put('a', 1);
+    /// rbtree.put('b', 2);
+    /// rbtree.put('c', 3);
+    /// rbtree.put('d', 4);
+    /// //   b         <-- height: 0
+    /// //  / \
+    /// // a   d       <-- height: 1
+    /// //    / \
+    /// //   c         <-- height: 2
+    /// // Note -The Height of red-black tree with single node is taken as zero.
+    /// assert_eq!(rbtree.get(&'a'), Some(&1_i32));
+    /// assert_eq!(rbtree.height(), Some(2_usize));
+    /// assert_eq!(rbtree.size(), 4_usize);
+    /// ```
+    fn height(&self) -> Option<usize> {
+        let height_rbtree = self.get_height();
+        if height_rbtree > 0_usize {
+            Some(height_rbtree - 1_usize)
+        } else {
+            None
+        }
+    }
+
+    fn leaf_depths(&self) -> Vec<usize> {
+        self.root.node().map_or_else(Vec::new, node::leaf_depths)
+    }
+
+    fn delete_min(&mut self) -> Option<(K, V)> {
+        let mut spare = std::mem::take(&mut self.spare);
+        let mut tree = std::mem::take(self);
+        tree.redden_root();
+        let (mut tree, min) = tree.without_min(&mut spare);
+        tree.set_color(Color::Black);
+        *self = tree;
+        self.spare = spare;
+        min
+    }
+
+    fn delete_max(&mut self) -> Option<(K, V)> {
+        let mut spare = std::mem::take(&mut self.spare);
+        let mut tree = std::mem::take(self);
+        tree.redden_root();
+        let (mut tree, max) = tree.without_max(&mut spare);
+        tree.set_color(Color::Black);
+        *self = tree;
+        self.spare = spare;
+        max
+    }
+
+    fn rank(&self, key: &K) -> usize {
+        view::Read::rank(self, key)
+    }
+
+    fn select(&self, rank: usize) -> Option<&K> {
+        match &self.root {
+            Tree::Node {
+                ref k,
+                v: _,
+                color: _,
+                size: _,
+                ref left,
+                ref right,
+            } => match rank.cmp(&left.size()) {
+                Ordering::Less => left.select(rank),
+                Ordering::Greater => right.select(rank - left.size() - 1_usize),
+                _ => Some(k),
+            },
+            _ => None,
+        }
+    }
+
+    fn partition_point<P: FnMut(&K) -> bool>(&self, mut pred: P) -> Option<&K> {
+        let mut found = None;
+        let mut tree = &self.root;
+        while let Tree::Node {
+            ref k,
+            v: _,
+            color: _,
+            size: _,
+            ref left,
+            ref right,
+        } = tree
+        {
+            if pred(k) {
+                tree = &right.root;
+            } else {
+                found = Some(k);
+                tree = &left.root;
+            }
+        }
+        found
+    }
+
+    fn successor(&self, key: &K) -> Option<(&K, &V)> {
+        view::Read::successor(self, key)
+    }
+
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)> {
+        view::Read::predecessor(self, key)
+    }
+
+    fn clear(&mut self) {
+        // balanced, so the recursive drop stays `O(log n)` deep
+        *self = Self::new();
+    }
+
+    fn drain(&mut self) -> std::vec::IntoIter<(K, V)> {
+        std::mem::take(self).into_sorted_vec().into_iter()
+    }
+}
+
+// `left` and `right` skip empty subtrees, so only nodes are handed out
+impl<K: Ord + Clone, V: Clone> BinaryTreeNode for Tree<K, V> {
+    type Key = K;
+    type Value = V;
+
+    fn key(&self) -> &K {
+        match self {
+            Tree::Node {
+                ref k,
+                v: _,
+                color: _,
+                size: _,
+                left: _,
+                right: _,
+            } => k,
+            Tree::Nil => unreachable!(),
+        }
+    }
+
+    fn value(&self) -> &V {
+        match self {
+            Tree::Node {
+                k: _,
+                ref v,
+                color: _,
+                size: _,
+                left: _,
+                right: _,
+            } => v,
+            Tree::Nil => unreachable!(),
+        }
+    }
+
+    fn left(&self) -> Option<&Self> {
+        match self {
+            Tree::Node {
+                k: _,
+                v: _,
+                color: _,
+                size: _,
+                ref left,
+                right: _,
+            } => left.root.node(),
+            Tree::Nil => None,
+        }
+    }
+
+    fn right(&self) -> Option<&Self> {
+        match self {
+            Tree::Node {
+                k: _,
+                v: _,
+                color: _,
+                size: _,
+                left: _,
+                ref right,
+            } => right.root.node(),
+            Tree::Nil => None,
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Tree<K, V> {
+    const fn node(&self) -> Option<&Self> {
+        match self {
+            Tree::Node { .. } => Some(self),
+            Tree::Nil => None,
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> TreeTraversal<K, V> for RedBlackTree<K, V> {
+    /// Returns traverse post ordered
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, Traversals, TreeTraversal};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// rbtree.put('a', 1);
+    /// rbtree.put('b', 2);
+    /// rbtree.put('c', 3);
+    /// rbtree.put('d', 4);
+    /// rbtree.put('e', 5);
+    /// rbtree.put('f', 6);
+    /// // Generate a balanced Red-Black Binary Search Tree
+    /// //          d(B)           <-- height: 0
+    /// //        /      \
+    /// //     (R)b       f(B)     <-- height: 1
+    /// //      / \      /    \
+    /// //   (B)a  c(B) e(R)       <-- height: 2
+    /// assert_eq!(rbtree.traverse(&Traversals::PreOrder).as_slice(),
+    ///       &[(&'d', &4), (&'b', &2), (&'a', &1),
+    ///         (&'c', &3), (&'f', &6), (&'e', &5)]);
+    /// ```
+    fn pre_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>) {
+        if let Some(node) = self.root.node() {
+            node::pre_order(node, vec);
+        }
+    }
+
+    /// Returns traverse in ordered
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, Traversals, TreeTraversal};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// rbtree.put('a', 1);
+    /// rbtree.put('b', 2);
+    /// rbtree.put('c', 3);
+    /// rbtree.put('d', 4);
+    /// rbtree.put('e', 5);
+    /// rbtree.put('f', 6);
+    /// // Generate a balanced Red-Black Binary Search Tree
+    /// //          d(B)           <-- height: 0
+    /// //        /      \
+    /// //     (R)b       f(B)     <-- height: 1
+    /// //      / \      /    \
+    /// //   (B)a  c(B) e(R)       <-- height: 2
+    /// assert_eq!(rbtree.traverse(&Traversals::InOrder).as_slice(),
+    ///       &[(&'a', &1), (&'b', &2), (&'c', &3),
+    ///         (&'d', &4), (&'e', &5), (&'f', &6)]);
+    /// ```
+    fn in_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>) {
+        if let Some(node) = self.root.node() {
+            node::in_order(node, vec);
+        }
+    }
+
+    /// Returns traverse post ordered
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, Traversals, TreeTraversal};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// rbtree.put('a', 1);
+    /// rbtree.put('b', 2);
+    /// rbtree.put('c', 3);
+    /// rbtree.put('d', 4);
+    /// rbtree.put('e', 5);
+    /// rbtree.put('f', 6);
+    /// // Generate a balanced Red-Black Binary Search Tree
+    /// //          d(B)           <-- height: 0
+    /// //        /      \
+    /// //     (R)b       f(B)     <-- height: 1
+    /// //      / \      /    \
+    /// //   (B)a  c(B) e(R)       <-- height: 2
+    /// assert_eq!(rbtree.traverse(&Traversals::PostOrder).as_slice(),
+    ///       &[(&'a', &1), (&'c', &3), (&'b', &2),
+    ///         (&'e', &5), (&'f', &6), (&'d', &4)]);
+    /// ```
+    fn post_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>) {
+        if let Some(node) = self.root.node() {
+            node::post_order(node, vec);
+        }
+    }
+
+    /// Returns traverse level ordered
+    ///
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, Traversals, TreeTraversal};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// rbtree.put('a', 1);
+    /// rbtree.put('b', 2);
+    /// rbtree.put('c', 3);
+    /// rbtree.put('d', 4);
+    /// rbtree.put('e', 5);
+    /// rbtree.put('f', 6);
+    /// // Generate a balanced Red-Black Binary Search Tree
+    /// //          d(B)           <-- height: 0
+    /// //        /      \
+    /// //     (R)b       f(B)     <-- height: 1
+    /// //      / \      /    \
+    /// //   (B)a  c(B) e(R)       <-- height: 2
+    /// assert_eq!(rbtree.traverse(&Traversals::LevelOrder).as_slice(),
+    ///       &[            (&'d', &4),               // <-- height: 0
+    ///                (&'b', &2), (&'f', &6),        // <-- height: 1
+    ///         (&'a', &1), (&'c', &3), (&'e', &5)]); // <-- height: 2
+    /// ```
+    fn level_order<'a>(&'a self, vec: &mut Vec<(&'a K, &'a V)>, level: usize) {
+        if let Some(node) = self.root.node() {
+            node::level_order(node, vec, level);
+        }
+    }
+}
+
+// Value of the node at the end of `path` under a node of `v`, `left` and
+// `right`, the first side of `path` last
+fn follow<'a, K, V>(
+    v: &'a mut V,
+    left: &'a mut RedBlackTree<K, V>,
+    right: &'a mut RedBlackTree<K, V>,
+    path: &[Ordering],
+) -> &'a mut V
+where
+    K: Ord + Clone,
+    V: Clone,
+{
+    let (side, rest) = match path.split_last() {
+        Some((side, rest)) => (side, rest),
+        None => return v,
+    };
+    let child = if side.eq(&Ordering::Less) {
+        left
+    } else {
+        right
+    };
+    match child.root {
+        Tree::Node {
+            k: _,
+            ref mut v,
+            color: _,
+            size: _,
+            ref mut left,
+            ref mut right,
+        } => follow(v, left, right, rest),
+        Tree::Nil => v,
+    }
+}
+
+// Updates a path from a node, its first side last, after the child on
+// side `up` is rotated up into the place of the node
+fn rotated(path: &mut Vec<Ordering>, up: Ordering) {
+    let down = up.reverse();
+    match path.last() {
+        Some(side) if side.eq(&up) => {
+            path.pop();
+            match path.pop() {
+                Some(inner) if inner.eq(&down) => {
+                    // the inner grandchild moves under the old node
+                    path.push(up);
+                    path.push(down);
+                }
+                Some(outer) => path.push(outer),
+                None => {}
+            }
+        }
+        _ => path.push(down),
+    }
+}
+
+// internal methods
+impl<'a, K: 'a + Ord + Clone, V: 'a + Clone> RedBlackTree<K, V> {
+    fn insert(&mut self, key: &'a K, value: &'a V, spare: &mut Pool<Self>) {
+        match &mut self.root {
+            Tree::Node {
+                ref mut k,
+                ref mut v,
+                ref mut color,
+                ref mut size,
+                ref mut left,
+                ref mut right,
+            } => {
+                match key.cmp(k) {
+                    // pass by reference, with same lifetime
+                    Ordering::Less => left.insert(key, value, spare),
+                    Ordering::Greater => right.insert(key, value, spare),
+                    Ordering::Equal => *v = value.clone(),
+                }
+                // Rotate Left
+                if right.is_red() && !left.is_red() {
+                    let right_clone = right.clone();
+                    *right = right_clone.get_right_clone();
+                    let right_size = left.size() + right_clone.get_left_clone().size() + 1_usize;
+                    // rotated up node keeps the color of the old root
+                    left.set_vals(
+                        k,
+                        v,
+                        Color::Red,
+                        right_size,
+                        *left.clone(),
+                        *right_clone.get_left_clone(),
+                    );
+                    // Don't move, but use clone, instead
+                    if let Some(kk) = right_clone.get_key() {
+                        *k = kk.clone();
+                    }
+                    if let Some(vv) = right_clone.get_val() {
+                        *v = vv.clone();
+                    }
+                }
+                // Balance 4-node
+                // Rotate Right
+                if left.is_red() && left.is_left_red() {
+                    let left_clone = left.clone();
+                    *left = left_clone.get_left_clone();
+                    let left_size = left_clone.get_right_clone().size() + right.size() + 1_usize;
+                    *color = Color::Red;
+                    right.set_vals(
+                        k,
+                        v,
+                        Color::Red,
+                        left_size,
+                        *left_clone.get_right_clone(),
+                        *right.clone(),
+                    );
+                    // Don't move, but use clone, instead, from left clone
+                    if let Some(kk) = left_clone.get_key() {
+                        *k = kk.clone();
+                    }
+                    if let Some(vv) = left_clone.get_val() {
+                        *v = vv.clone();
+                    }
+                }
+                // Split 4-node
+                // Flip colors
+                if left.is_red() && right.is_red() {
+                    *color = Color::Red;
+                    left.set_color(Color::Black);
+                    right.set_color(Color::Black);
+                }
+                *size = left.size() + right.size() + 1_usize;
+            }
+            Tree::Nil => {
+                // Insert a leaf node
+                self.root = Tree::Node {
+                    k: key.clone(),
+                    v: value.clone(),
+                    color: Color::Red,
+                    size: 1,
+                    left: spare.boxed(RedBlackTree::new()),
+                    right: spare.boxed(RedBlackTree::new()),
+                }
+            }
+        }
+    }
+
+    fn take_smallest(&'a self, vec: &mut Vec<(&'a K, &'a V)>, n: usize) {
+        if let Tree::Node {
+            ref k,
+            ref v,
+            color: _,
+            size: _,
+            ref left,
+            ref right,
+        } = &self.root
+        {
+            left.take_smallest(vec, n);
+            if vec.len().lt(&n) {
+                vec.push((k, v));
+                right.take_smallest(vec, n);
+            }
+        }
+    }
+
+    fn take_largest(&'a self, vec: &mut Vec<(&'a K, &'a V)>, n: usize) {
+        if let Tree::Node {
+            ref k,
+            ref v,
+            color: _,
+            size: _,
+            ref left,
+            ref right,
+        } = &self.root
+        {
+            right.take_largest(vec, n);
+            if vec.len().lt(&n) {
+                vec.push((k, v));
+                left.take_largest(vec, n);
+            }
+        }
+    }
+
+    fn get_height(&self) -> usize {
+        match &self.root {
+            Tree::Node {
+                k: _,
+                v: _,
+                color: _,
+                size: _,
+                ref left,
+                ref right,
+            } => 1_usize + std::cmp::max(left.get_height(), right.get_height()),
+            _ => 0_usize,
+        }
+    }
+
+    fn try_fold_at<R, B, E, F>(&self, range: &R, init: B, f: &mut F) -> Result<B, E>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(B, &K, &V) -> Result<B, E>,
+    {
+        let mut acc = init;
+        if let Tree::Node {
+            ref k,
+            ref v,
+            color: _,
+            size: _,
+            ref left,
+            ref right,
+        } = &self.root
+        {
+            if reaches_below(range, k) {
+                acc = left.try_fold_at(range, acc, f)?;
+            }
+            if range.contains(k) {
+                acc = f(acc, k, v)?;
+            }
+            if reaches_above(range, k) {
+                acc = right.try_fold_at(range, acc, f)?;
+            }
+        }
+        Ok(acc)
+    }
+
+    fn set_vals(
+        &mut self,
+        key: &'a K,
+        val: &'a V,
+        c: Color,
+        s: usize,
+        l: RedBlackTree<K, V>,
+        r: RedBlackTree<K, V>,
+    ) {
+        match &mut self.root {
+            Tree::Node {
+                ref mut k,
+                ref mut v,
+                ref mut color,
+                ref mut size,
+                ref mut left,
+                ref mut right,
+            } => {
+                *k = key.clone();
+                *v = val.clone();
+                *color = c;
+                *size = s;
+                **left = l;
+                **right = r;
+            }
+            Tree::Nil => {
+                self.root = Tree::Node {
+                    k: key.clone(),
+                    v: val.clone(),
+                    color: c,
+                    size: 1,
+                    left: Box::new(l),
+                    right: Box::new(r),
+                }
+            }
+        }
+    }
+
+    const fn get_key(&self) -> Option<&K> {
+        if let Tree::Node {
+            ref k,
+            v: _,
+            color: _,
+            size: _,
+            left: _,
+            right: _,
+        } = &self.root
+        {
+            Some(k)
+        } else {
+            None
+        }
+    }
+
+    const fn get_val(&self) -> Option<&V> {
+        if let Tree::Node {
+            k: _,
+            ref v,
+            color: _,
+            size: _,
+            left: _,
+            right: _,
+        } = &self.root
+        {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    const fn set_color(&mut self, c: Color) {
+        if let Tree::Node {
+            k: _,
+            v: _,
+            ref mut color,
+            size: _,
+            left: _,
+            right: _,
+        } = &mut self.root
+        {
+            *color = c;
+        }
+    }
+
+    const fn is_red(&self) -> bool {
+        match &self.root {
+            Tree::Node {
+                k: _,
+                v: _,
+                ref color,
+                size: _,
+                left: _,
+                right: _,
+            } => color.is_red(),
+            _ => false,
+        }
+    }
+
+    fn is_left_red(&self) -> bool {
+        match &self.root {
+            Tree::Node {
+                k: _,
+                v: _,
+                color: _,
+                size: _,
+                ref left,
+                right: _,
+            } => left.is_red(),
+            _ => false,
+        }
+    }
+
+    fn get_left_clone(&self) -> Box<RedBlackTree<K, V>> {
+        match &self.root {
+            Tree::Node {
+                k: _,
+                v: _,
+                color: _,
+                size: _,
+                ref left,
+                right: _,
+            } => left.clone(),
+            _ => Box::new(RedBlackTree::new()),
+        }
+    }
+
+    fn get_right_clone(&self) -> Box<RedBlackTree<K, V>> {
+        match &self.root {
+            Tree::Node {
+                k: _,
+                v: _,
+                color: _,
+                size: _,
+                left: _,
+                ref right,
+            } => right.clone(),
+            _ => Box::new(RedBlackTree::new()),
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> RedBlackTree<K, V> {
+    /// Returns the value of `key`, putting the value `f` returns first if the
+    /// key is missing.
+    ///
+    /// On an error of `f` nothing is put and the error is returned, so a
+    /// fallible constructor runs only for missing keys, without a separate
+    /// `contains` and `put`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use std::num::ParseIntError;
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<&str, u32> = RedBlackTree::new();
+    /// let parsed: Result<&mut u32, ParseIntError> = rbtree.get_or_try_insert_with("a", || "12".parse());
+    /// *parsed.unwrap() += 1;
+    /// assert_eq!(rbtree.get(&"a"), Some(&13));
+    ///
+    /// // a present key never calls `f`
+    /// let again = rbtree.get_or_try_insert_with("a", || "not called".parse::<u32>());
+    /// assert_eq!(again.copied(), Ok(13));
+    ///
+    /// assert!(rbtree.get_or_try_insert_with("b", || "x".parse::<u32>()).is_err());
+    /// assert_eq!(rbtree.size(), 1_usize);
+    /// ```
+    pub fn get_or_try_insert_with<E, F: FnOnce() -> Result<V, E>>(
+        &mut self,
+        key: K,
+        f: F,
+    ) -> Result<&mut V, E> {
+        match self.entry(key) {
+            Entry::Occupied(e) => Ok(e.into_mut()),
+            Entry::Vacant(e) => Ok(e.insert(f()?)),
+        }
+    }
+
+    /// Returns the entry of `key`.
+    ///
+    /// The key is searched for once, the directions taken on the way down
+    /// are kept. An occupied entry follows them to its value, filling a
+    /// vacant one puts the new node at their end and rebalances the same
+    /// path back up, without comparing keys again.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::entry::Entry;
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// for k in [1, 2, 1, 1] {
+    ///     rbtree.entry(k).and_modify(|n| *n += 1).or_insert(1);
+    /// }
+    /// assert_eq!((rbtree.get(&1), rbtree.get(&2)), (Some(&3), Some(&1)));
+    ///
+    /// if let Entry::Occupied(mut e) = rbtree.entry(2) {
+    ///     assert_eq!(e.insert(20), 1);
+    /// }
+    /// assert_eq!(rbtree.get(&2), Some(&20));
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let mut path = Vec::new();
+        let found = self.search_path(&key, &mut path);
+        path.reverse();
+        if found {
+            if let Tree::Node {
+                k: _,
+                ref mut v,
+                color: _,
+                size: _,
+                ref mut left,
+                ref mut right,
+            } = self.root
+            {
+                return Entry::Occupied(OccupiedEntry::new(key, follow(v, left, right, &path)));
+            }
+        }
+        Entry::Vacant(VacantEntry::new(
+            key,
+            Box::new(move |key, value| {
+                let mut spare = std::mem::take(&mut self.spare);
+                let (mut tree, path) =
+                    std::mem::take(self).insert_on_path(&path, key, value, &mut spare);
+                tree.set_color(Color::Black);
+                *self = tree;
+                self.spare = spare;
+                match self.root {
+                    Tree::Node {
+                        k: _,
+                        ref mut v,
+                        color: _,
+                        size: _,
+                        ref mut left,
+                        ref mut right,
+                    } => follow(v, left, right, &path),
+                    // an insert leaves a node at the root
+                    Tree::Nil => unreachable!(),
+                }
+            }),
+        ))
+    }
+
+    // Searches for `key`, pushing the side taken at every node
+    fn search_path(&self, key: &K, path: &mut Vec<Ordering>) -> bool {
+        match self.root {
+            Tree::Node {
+                ref k,
+                v: _,
+                color: _,
+                size: _,
+                ref left,
+                ref right,
+            } => match key.cmp(k) {
+                Ordering::Equal => true,
+                side => {
+                    path.push(side);
+                    let child = if side.eq(&Ordering::Less) {
+                        left
+                    } else {
+                        right
+                    };
+                    child.search_path(key, path)
+                }
+            },
+            Tree::Nil => false,
+        }
+    }
+
+    // Puts a new node down `sides`, the last one taken first, rebalancing
+    // on the way up as `insert` does; returns the path to the new node,
+    // its first side last
+    fn insert_on_path(
+        self,
+        sides: &[Ordering],
+        key: K,
+        value: V,
+        spare: &mut Pool<Self>,
+    ) -> (Self, Vec<Ordering>) {
+        let (k, v, color, left, right) = match self.into_parts_in(spare) {
+            Some(parts) => parts,
+            None => {
+                let leaf = Self::node_in(key, value, Color::Red, Self::new(), Self::new(), spare);
+                return (leaf, Vec::new());
+            }
+        };
+        let (side, rest) = match sides.split_last() {
+            Some((side, rest)) => (*side, rest),
+            // no side left, the node is of `key` itself
+            None => {
+                return (
+                    Self::node_in(k, value, color, left, right, spare),
+                    Vec::new(),
+                )
+            }
+        };
+        let (left, right, mut path) = if side.eq(&Ordering::Less) {
+            let (left, path) = left.insert_on_path(rest, key, value, spare);
+            (left, right, path)
+        } else {
+            let (right, path) = right.insert_on_path(rest, key, value, spare);
+            (left, right, path)
+        };
+        path.push(side);
+        let tree = Self::node_in(k, v, color, left, right, spare);
+        // Rotate Left
+        let tree = if tree.is_right_red() && !tree.is_left_red() {
+            rotated(&mut path, Ordering::Greater);
+            tree.rotate_left(spare)
+        } else {
+            tree
+        };
+        // Rotate Right
+        let tree = if tree.is_left_red() && tree.is_left_left_red() {
+            rotated(&mut path, Ordering::Less);
+            tree.rotate_right(spare)
+        } else {
+            tree
+        };
+        // Flip colors
+        let tree = if tree.is_left_red() && tree.is_right_red() {
+            tree.flip_colors(spare)
+        } else {
+            tree
+        };
+        (tree, path)
+    }
+
+    fn value_mut(&mut self, key: &K) -> Option<&mut V> {
+        match self.root {
+            Tree::Node {
+                ref k,
+                ref mut v,
+                color: _,
+                size: _,
+                ref mut left,
+                ref mut right,
+            } => match key.cmp(k) {
+                Ordering::Less => left.value_mut(key),
+                Ordering::Greater => right.value_mut(key),
+                _ => Some(v),
+            },
+            Tree::Nil => None,
+        }
+    }
+
+    /// Streams entries to `writer` in key order, each one written by `fmt`,
+    /// then flushes it.
+    ///
+    /// Entries go to `fmt` as the tree is walked, nothing is collected on
+    /// the way, so a tree of any size is exported in constant memory.
+    /// `dump::tsv` and `dump::jsonl` write common formats, a closure any
+    /// other; pass files in a `BufWriter`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{dump, Map};
+    ///
+    /// let mut rbtree: RedBlackTree<u32, &str> = RedBlackTree::new();
+    /// rbtree.put(2, "b");
+    /// rbtree.put(1, "a");
+    ///
+    /// let mut out = Vec::new();
+    /// rbtree.write_in_order(&mut out, dump::tsv).unwrap();
+    /// assert_eq!(out, b"1\ta\n2\tb\n");
+    ///
+    /// let mut out = Vec::new();
+    /// rbtree.write_in_order(&mut out, |w, k, v| write!(w, "{}={};", k, v)).unwrap();
+    /// assert_eq!(out, b"1=a;2=b;");
+    /// ```
+    pub fn write_in_order<W, F>(&self, mut writer: W, mut fmt: F) -> std::io::Result<()>
+    where
+        W: std::io::Write,
+        F: FnMut(&mut W, &K, &V) -> std::io::Result<()>,
+    {
+        if let Some(node) = self.root.node() {
+            node::try_in_order(node, &mut |k: &K, v: &V| fmt(&mut writer, k, v))?;
+        }
+        writer.flush()
+    }
+
+    /// Rebuilds the `Red-Black Tree` with the same shape and colors,
+    /// transforming every value with `f`.
+    ///
+    /// `f` is called once per entry, in key order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// rbtree.put('a', 1);
+    /// rbtree.put('b', 2);
+    /// rbtree.put('c', 3);
+    ///
+    /// let rbtree: RedBlackTree<char, String> = rbtree.map_values(|k, v| format!("{}{}", k, v));
+    /// assert_eq!(rbtree.get(&'a'), Some(&String::from("a1")));
+    /// assert_eq!(rbtree.get(&'c'), Some(&String::from("c3")));
+    /// assert_eq!(rbtree.height(), Some(1_usize));
+    /// ```
+    pub fn map_values<U: Clone, F: FnMut(&K, V) -> U>(self, mut f: F) -> RedBlackTree<K, U> {
+        self.map_with(&mut f)
+    }
+
+    fn map_with<U: Clone, F: FnMut(&K, V) -> U>(self, f: &mut F) -> RedBlackTree<K, U> {
+        match self.root {
+            Tree::Node {
+                k,
+                v,
+                color,
+                size,
+                left,
+                right,
+            } => {
+                let left = Box::new((*left).map_with(f));
+                let v = f(&k, v);
+                let right = Box::new((*right).map_with(f));
+                RedBlackTree {
+                    root: Tree::Node {
+                        k,
+                        v,
+                        color,
+                        size,
+                        left,
+                        right,
+                    },
+                    spare: Pool::new(),
+                }
+            }
+            Tree::Nil => RedBlackTree::new(),
+        }
+    }
+
+    /// Returns a lazy iterator over entries in key order, walking the tree
+    /// with a stack of parents instead of collecting it first.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// for k in 0..1000 {
+    ///     rbtree.put(k, k * k);
+    /// }
+    /// let mut iter = rbtree.iter();
+    /// assert_eq!(iter.size_hint(), (1000, Some(1000)));
+    /// assert_eq!(iter.next(), Some((&0, &0)));
+    /// assert_eq!(iter.len(), 999_usize);
+    ///
+    /// // the first square above 5000, the rest of the tree not visited
+    /// let (k, _) = rbtree.iter().find(|(_, sq)| **sq > 5000).unwrap();
+    /// assert_eq!(*k, 71);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter::new(self)
+    }
+
+    /// Returns cloned key-value pairs of `Red-Black Tree` in key order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// rbtree.put('c', 3);
+    /// rbtree.put('a', 1);
+    /// rbtree.put('b', 2);
+    /// assert_eq!(rbtree.to_sorted_vec(), vec![('a', 1), ('b', 2), ('c', 3)]);
+    /// assert_eq!(rbtree.size(), 3_usize);
+    /// ```
+    pub fn to_sorted_vec(&self) -> Vec<(K, V)> {
+        let mut vec = Vec::with_capacity(self.size());
+        for (k, v) in self.traverse(&Traversals::InOrder) {
+            vec.push((k.clone(), v.clone()));
+        }
+        vec
+    }
+
+    /// Consumes `Red-Black Tree`, returning its key-value pairs in key order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<char, String> = RedBlackTree::new();
+    /// rbtree.put('b', String::from("b"));
+    /// rbtree.put('a', String::from("a"));
+    /// assert_eq!(
+    ///     rbtree.into_sorted_vec(),
+    ///     vec![('a', String::from("a")), ('b', String::from("b"))]
+    /// );
+    /// ```
+    pub fn into_sorted_vec(self) -> Vec<(K, V)> {
+        let mut vec = Vec::with_capacity(self.size());
+        self.into_in_order(&mut vec);
+        vec
+    }
+
+    fn into_in_order(self, vec: &mut Vec<(K, V)>) {
+        if let Tree::Node {
+            k,
+            v,
+            color: _,
+            size: _,
+            left,
+            right,
+        } = self.root
+        {
+            left.into_in_order(vec);
+            vec.push((k, v));
+            right.into_in_order(vec);
+        }
+    }
+
+    /// Consumes `Red-Black Tree`, returning its keys in key order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// rbtree.put('b', 2);
+    /// rbtree.put('a', 1);
+    /// rbtree.put('c', 3);
+    /// let keys: Vec<char> = rbtree.into_keys().collect();
+    /// assert_eq!(keys, vec!['a', 'b', 'c']);
+    /// ```
+    pub fn into_keys(self) -> impl Iterator<Item = K> {
+        self.into_iter().map(|(k, _)| k)
+    }
+
+    /// Consumes `Red-Black Tree`, returning its values in key order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// rbtree.put('b', 2);
+    /// rbtree.put('a', 1);
+    /// rbtree.put('c', 3);
+    /// let values: Vec<i32> = rbtree.into_values().collect();
+    /// assert_eq!(values, vec![1, 2, 3]);
+    /// ```
+    pub fn into_values(self) -> impl Iterator<Item = V> {
+        self.into_iter().map(|(_, v)| v)
+    }
+
+    /// Joins `left`, a `key` and `right` into a single `Red-Black Tree`,
+    /// in `O(log n)`.
+    ///
+    /// Every key of `left` has to be less than `key`, and every key of
+    /// `right` greater than it.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if keys are not ordered that way.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut left: RedBlackTree<u32, char> = RedBlackTree::new();
+    /// let mut right: RedBlackTree<u32, char> = RedBlackTree::new();
+    /// left.put(1, 'a');
+    /// left.put(2, 'b');
+    /// right.put(9, 'z');
+    ///
+    /// let rbtree = RedBlackTree::join(left, 5, 'e', right);
+    /// assert_eq!(rbtree.size(), 4_usize);
+    /// assert_eq!(rbtree.get(&5), Some(&'e'));
+    /// assert_eq!(rbtree.max(), Some(&9));
+    /// ```
+    pub fn join(mut left: Self, key: K, value: V, mut right: Self) -> Self {
+        debug_assert!(left.max().is_none_or(|k| k.lt(&key)));
+        debug_assert!(right.min().is_none_or(|k| k.gt(&key)));
+        left.set_color(Color::Black);
+        right.set_color(Color::Black);
+        let (bh_left, bh_right) = (left.black_height(), right.black_height());
+        let mut tree = match bh_left.cmp(&bh_right) {
+            Ordering::Greater => Self::join_right(left, bh_left, key, value, right, bh_right),
+            Ordering::Less => Self::join_left(left, bh_left, key, value, right, bh_right),
+            Ordering::Equal => Self::node(key, value, Color::Red, left, right),
+        };
+        tree.set_color(Color::Black);
+        tree
+    }
+
+    /// Splits `Red-Black Tree` by `key` into keys less than it, the value
+    /// stored under it and keys greater than it, in `O(log n)`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// for i in 0..10 {
+    ///     rbtree.put(i, i * 10);
+    /// }
+    ///
+    /// let (less, value, greater) = rbtree.split(&4);
+    /// assert_eq!(less.size(), 4_usize);
+    /// assert_eq!(value, Some(40));
+    /// assert_eq!(greater.size(), 5_usize);
+    /// ```
+    pub fn split(self, key: &K) -> (Self, Option<V>, Self) {
+        match self.into_parts() {
+            Some((k, v, _, left, right)) => match key.cmp(&k) {
+                Ordering::Less => {
+                    let (less, found, greater) = left.split(key);
+                    (less, found, Self::join(greater, k, v, right))
+                }
+                Ordering::Greater => {
+                    let (less, found, greater) = right.split(key);
+                    (Self::join(left, k, v, less), found, greater)
+                }
+                Ordering::Equal => {
+                    let (mut less, mut greater) = (left, right);
+                    less.set_color(Color::Black);
+                    greater.set_color(Color::Black);
+                    (less, Some(v), greater)
+                }
+            },
+            None => (RedBlackTree::new(), None, RedBlackTree::new()),
+        }
+    }
+
+    /// Removes `key` and returns its value, in `O(log n)`.
+    ///
+    /// It is the left-leaning red-black deletion of Sedgewick: red links
+    /// are pushed down the search path, so the node removed at the bottom
+    /// is red, and the tree is rebalanced on the way back up.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut rbtree: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// for i in 0..10 {
+    ///     rbtree.put(i, i * 10);
+    /// }
+    ///
+    /// assert_eq!(rbtree.delete(&4), Some(40));
+    /// assert_eq!(rbtree.delete(&4), None);
+    /// assert_eq!(rbtree.size(), 9_usize);
+    /// assert_eq!(rbtree.floor(&4), Some(&3));
+    /// ```
+    pub fn delete(&mut self, key: &K) -> Option<V> {
+        if !self.contains(key) {
+            return None;
+        }
+        let mut spare = std::mem::take(&mut self.spare);
+        let mut tree = std::mem::take(self);
+        tree.redden_root();
+        let (mut tree, found) = tree.delete_node(key, &mut spare);
+        tree.set_color(Color::Black);
+        *self = tree;
+        self.spare = spare;
+        found
+    }
+
+    /// Returns a `Red-Black Tree` with keys of both trees, on equal keys
+    /// values of `self` are kept.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut a: RedBlackTree<u32, char> = RedBlackTree::new();
+    /// let mut b: RedBlackTree<u32, char> = RedBlackTree::new();
+    /// a.put(1, 'a');
+    /// a.put(2, 'a');
+    /// b.put(2, 'b');
+    /// b.put(3, 'b');
+    ///
+    /// let union = a.union(b);
+    /// assert_eq!(union.range(..).as_slice(), &[(&1, &'a'), (&2, &'a'), (&3, &'b')]);
+    /// ```
+    pub fn union(self, other: Self) -> Self {
+        if other.is_empty() {
+            return self;
+        }
+        match self.into_parts() {
+            Some((k, v, _, left, right)) => {
+                let (less, _, greater) = other.split(&k);
+                Self::join(left.union(less), k, v, right.union(greater))
+            }
+            None => other,
+        }
+    }
+
+    /// Returns a `Red-Black Tree` with keys present in both trees, with
+    /// values of `self`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut a: RedBlackTree<u32, char> = RedBlackTree::new();
+    /// let mut b: RedBlackTree<u32, char> = RedBlackTree::new();
+    /// a.put(1, 'a');
+    /// a.put(2, 'a');
+    /// b.put(2, 'b');
+    /// b.put(3, 'b');
+    ///
+    /// let intersection = a.intersection(b);
+    /// assert_eq!(intersection.range(..).as_slice(), &[(&2, &'a')]);
+    /// ```
+    pub fn intersection(self, other: Self) -> Self {
+        if other.is_empty() {
+            return RedBlackTree::new();
+        }
+        match self.into_parts() {
+            Some((k, v, _, left, right)) => {
+                let (less, found, greater) = other.split(&k);
+                let (left, right) = (left.intersection(less), right.intersection(greater));
+                match found {
+                    Some(_) => Self::join(left, k, v, right),
+                    None => Self::join_pair(left, right),
+                }
+            }
+            None => RedBlackTree::new(),
+        }
+    }
+
+    /// Returns a `Red-Black Tree` with keys of `self` not present in `other`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, OrderedMap};
+    ///
+    /// let mut a: RedBlackTree<u32, char> = RedBlackTree::new();
+    /// let mut b: RedBlackTree<u32, char> = RedBlackTree::new();
+    /// a.put(1, 'a');
+    /// a.put(2, 'a');
+    /// b.put(2, 'b');
+    /// b.put(3, 'b');
+    ///
+    /// let difference = a.difference(b);
+    /// assert_eq!(difference.range(..).as_slice(), &[(&1, &'a')]);
+    /// ```
+    pub fn difference(self, other: Self) -> Self {
+        if self.is_empty() {
+            return RedBlackTree::new();
+        }
+        match other.into_parts() {
+            Some((k, _, _, left, right)) => {
+                let (less, _, greater) = self.split(&k);
+                Self::join_pair(less.difference(left), greater.difference(right))
+            }
+            None => self,
+        }
+    }
+
+    /// Checks if `other` is built of nodes in exactly the same places,
+    /// keys, values and colors aside.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut a: RedBlackTree<u32, ()> = RedBlackTree::new();
+    /// let mut b: RedBlackTree<char, i32> = RedBlackTree::new();
+    /// for (k, c) in (0..7).zip('a'..) {
+    ///     a.put(k, ());
+    ///     b.put(c, 0);
+    /// }
+    /// assert!(a.same_shape(&b));
+    ///
+    /// a.put(7, ());
+    /// assert!(!a.same_shape(&b));
+    /// ```
+    pub fn same_shape<L: Ord + Clone, W: Clone>(&self, other: &RedBlackTree<L, W>) -> bool {
+        match (&self.root, &other.root) {
+            (
+                Tree::Node {
+                    k: _,
+                    v: _,
+                    color: _,
+                    size,
+                    ref left,
+                    ref right,
+                },
+                Tree::Node {
+                    k: _,
+                    v: _,
+                    color: _,
+                    size: other_size,
+                    left: ref other_left,
+                    right: ref other_right,
+                },
+            ) => {
+                size.eq(other_size) && left.same_shape(other_left) && right.same_shape(other_right)
+            }
+            (Tree::Nil, Tree::Nil) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if `other` turns into the shape of `self` by swapping
+    /// children of some nodes, keys, values and colors aside.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut a: RedBlackTree<u32, ()> = RedBlackTree::new();
+    /// let mut b: RedBlackTree<u32, ()> = RedBlackTree::new();
+    /// a.put(1, ());
+    /// a.put(2, ());
+    /// b.put(2, ());
+    /// b.put(1, ());
+    /// // Left-leaning, so both have a red left child
+    /// assert!(a.same_shape(&b));
+    /// assert!(a.is_isomorphic(&b));
+    /// ```
+    pub fn is_isomorphic<L: Ord + Clone, W: Clone>(&self, other: &RedBlackTree<L, W>) -> bool {
+        match (&self.root, &other.root) {
+            (
+                Tree::Node {
+                    k: _,
+                    v: _,
+                    color: _,
+                    size,
+                    ref left,
+                    ref right,
+                },
+                Tree::Node {
+                    k: _,
+                    v: _,
+                    color: _,
+                    size: other_size,
+                    left: ref other_left,
+                    right: ref other_right,
+                },
+            ) => {
+                // subtree sizes rule out most pairings without walking them
+                size.eq(other_size)
+                    && ((left.size().eq(&other_left.size())
+                        && left.is_isomorphic(other_left)
+                        && right.is_isomorphic(other_right))
+                        || (left.size().eq(&other_right.size())
+                            && left.is_isomorphic(other_right)
+                            && right.is_isomorphic(other_left)))
+            }
+            (Tree::Nil, Tree::Nil) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the color of the link from the node of `key` up to its
+    /// parent, `None` if the key is missing. The root is always black.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::{Color, RedBlackTree};
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<u32, ()> = RedBlackTree::new();
+    /// rbtree.put(1, ());
+    /// rbtree.put(2, ());
+    /// //    2(B)
+    /// //   /
+    /// // 1(R)
+    /// assert_eq!(rbtree.color_of(&2), Some(Color::Black));
+    /// assert_eq!(rbtree.color_of(&1), Some(Color::Red));
+    /// assert_eq!(rbtree.color_of(&3), None);
+    /// ```
+    pub fn color_of(&self, key: &K) -> Option<Color> {
+        let mut node = self;
+        while let Tree::Node {
+            ref k,
+            v: _,
+            color,
+            size: _,
+            ref left,
+            ref right,
+        } = node.root
+        {
+            node = match key.cmp(k) {
+                Ordering::Less => left,
+                Ordering::Greater => right,
+                Ordering::Equal => return Some(color),
+            };
+        }
+        None
+    }
+
+    /// Returns the count of red links, every one a 3-node of the 2-3 tree
+    /// the `Red-Black Tree` stands for.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut rbtree: RedBlackTree<u32, ()> = RedBlackTree::new();
+    /// for k in 0..7 {
+    ///     rbtree.put(k, ());
+    /// }
+    /// // ascending puts end up in a perfect tree of 2-nodes
+    /// assert_eq!(rbtree.count_red_links(), 0_usize);
+    /// assert_eq!(rbtree.height(), Some(2_usize));
+    ///
+    /// rbtree.put(7, ());
+    /// assert_eq!(rbtree.count_red_links(), 1_usize);
+    /// ```
+    pub fn count_red_links(&self) -> usize {
+        match &self.root {
+            Tree::Node {
+                k: _,
+                v: _,
+                color,
+                size: _,
+                ref left,
+                ref right,
+            } => usize::from(color.is_red()) + left.count_red_links() + right.count_red_links(),
+            Tree::Nil => 0_usize,
+        }
+    }
+
+    /// Rebuilds the `Red-Black Tree` into a balanced shape, the same for
+    /// the same keys whatever order they were put in.
+    ///
+    /// Seen as a 2-3 tree, it has the fewest levels able to hold all
+    /// entries, with entries split evenly among children and 3-nodes
+    /// (red left links) only where 2-nodes can't hold them.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::{Map, SedgewickMap};
+    ///
+    /// let mut a: RedBlackTree<u32, ()> = RedBlackTree::new();
+    /// let mut b: RedBlackTree<u32, ()> = RedBlackTree::new();
+    /// for k in 0..100 {
+    ///     a.put(k, ());
+    ///     b.put(99 - k, ());
+    /// }
+    /// assert!(!a.same_shape(&b));
+    ///
+    /// a.canonicalize();
+    /// b.canonicalize();
+    /// assert!(a.same_shape(&b));
+    /// assert_eq!(a.height(), Some(7_usize));
+    /// ```
+    pub fn canonicalize(&mut self) {
+        let entries = std::mem::take(self).into_sorted_vec();
+        let size = entries.len();
+        // a 2-3 tree of 3-nodes with `levels` levels holds 3^levels - 1 entries
+        let mut capacity = 0_usize;
+        while capacity.lt(&size) {
+            capacity = capacity * 3_usize + 2_usize;
+        }
+        *self = Self::balanced(&mut entries.into_iter(), size, capacity);
+    }
+
+    /// Looks up a batch of keys sorted in ascending order, returning values
+    /// in the same order as `keys`.
+    ///
+    /// Keys are split among subtrees as the tree is walked, so every node
+    /// is visited once for the whole batch, and subtrees without a key of
+    /// the batch are skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `keys` are not sorted.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbt: RedBlackTree<u32, char> = RedBlackTree::new();
+    /// for (i, c) in ('a'..='j').enumerate() {
+    ///     rbt.put(i as u32 * 10, c);
+    /// }
+    /// assert_eq!(
+    ///     rbt.get_sorted(&[0, 5, 20, 20, 90, 100]),
+    ///     [Some(&'a'), None, Some(&'c'), Some(&'c'), Some(&'j'), None]
+    /// );
+    /// ```
+    pub fn get_sorted(&self, keys: &[K]) -> Vec<Option<&V>> {
+        debug_assert!(keys.windows(2).all(|w| w[0].le(&w[1])));
+        let mut out = vec![None; keys.len()];
+        self.fill_sorted(keys, &mut out);
+        out
+    }
+
+    fn fill_sorted<'a>(&'a self, keys: &[K], out: &mut [Option<&'a V>]) {
+        if keys.is_empty() {
+            return;
+        }
+        if let Tree::Node {
+            ref k,
+            ref v,
+            color: _,
+            size: _,
+            ref left,
+            ref right,
+        } = &self.root
         {
-            Some(v)
+            let lo = keys.partition_point(|q| q.lt(k));
+            let hi = lo + keys[lo..].partition_point(|q| q.eq(k));
+            left.fill_sorted(&keys[..lo], &mut out[..lo]);
+            out[lo..hi].iter_mut().for_each(|o| *o = Some(v));
+            right.fill_sorted(&keys[hi..], &mut out[hi..]);
+        }
+    }
+}
+
+// Owned building blocks of join-based algorithms
+impl<K: Ord + Clone, V: Clone> RedBlackTree<K, V> {
+    // Black rooted subtree of `size` entries, `capacity` is 3^levels - 1
+    fn balanced<I: Iterator<Item = (K, V)>>(entries: &mut I, size: usize, capacity: usize) -> Self {
+        if size.eq(&0_usize) {
+            return RedBlackTree::new();
+        }
+        let child = (capacity - 2_usize) / 3_usize;
+        let rest = size - 1_usize;
+        if (rest - rest / 2_usize).le(&child) {
+            let left = Self::balanced(entries, rest - rest / 2_usize, child);
+            return match entries.next() {
+                Some((k, v)) => {
+                    let right = Self::balanced(entries, rest / 2_usize, child);
+                    Self::node(k, v, Color::Black, left, right)
+                }
+                None => RedBlackTree::new(),
+            };
+        }
+        // a 3-node, red left child and black parent over three children
+        let rest = size - 2_usize;
+        let first = Self::balanced(entries, rest.div_ceil(3_usize), child);
+        let (k, v) = match entries.next() {
+            Some(entry) => entry,
+            None => return RedBlackTree::new(),
+        };
+        let second = Self::balanced(entries, (rest + 1_usize) / 3_usize, child);
+        let red = Self::node(k, v, Color::Red, first, second);
+        match entries.next() {
+            Some((k, v)) => {
+                let third = Self::balanced(entries, rest / 3_usize, child);
+                Self::node(k, v, Color::Black, red, third)
+            }
+            None => RedBlackTree::new(),
+        }
+    }
+
+    pub(crate) fn node(k: K, v: V, color: Color, left: Self, right: Self) -> Self {
+        RedBlackTree {
+            root: Tree::Node {
+                size: left.size() + right.size() + 1_usize,
+                k,
+                v,
+                color,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+            spare: Pool::new(),
+        }
+    }
+
+    pub(crate) fn into_parts(self) -> Option<(K, V, Color, Self, Self)> {
+        match self.root {
+            Tree::Node {
+                k,
+                v,
+                color,
+                size: _,
+                left,
+                right,
+            } => Some((k, v, color, *left, *right)),
+            Tree::Nil => None,
+        }
+    }
+
+    // `node` in boxes taken from `spare`
+    fn node_in(k: K, v: V, color: Color, left: Self, right: Self, spare: &mut Pool<Self>) -> Self {
+        RedBlackTree {
+            root: Tree::Node {
+                size: left.size() + right.size() + 1_usize,
+                k,
+                v,
+                color,
+                left: spare.boxed(left),
+                right: spare.boxed(right),
+            },
+            spare: Pool::new(),
+        }
+    }
+
+    // `into_parts` keeping the emptied boxes of the children in `spare`
+    fn into_parts_in(self, spare: &mut Pool<Self>) -> Option<(K, V, Color, Self, Self)> {
+        match self.root {
+            Tree::Node {
+                k,
+                v,
+                color,
+                size: _,
+                mut left,
+                mut right,
+            } => {
+                let parts = (
+                    k,
+                    v,
+                    color,
+                    std::mem::take(&mut *left),
+                    std::mem::take(&mut *right),
+                );
+                spare.keep(left);
+                spare.keep(right);
+                Some(parts)
+            }
+            Tree::Nil => None,
+        }
+    }
+
+    // Count of black nodes on a path to a leaf
+    fn black_height(&self) -> usize {
+        match &self.root {
+            Tree::Node {
+                k: _,
+                v: _,
+                ref color,
+                size: _,
+                ref left,
+                right: _,
+            } => left.black_height() + if color.is_red() { 0_usize } else { 1_usize },
+            _ => 0_usize,
+        }
+    }
+
+    // Same rotations and color flip as `insert`, on an owned node
+    fn balance(self) -> Self {
+        match self.into_parts() {
+            Some((mut k, mut v, color, mut left, mut right)) => {
+                // Rotate Left
+                if right.is_red() && !left.is_red() {
+                    if let Some((rk, rv, _, rl, rr)) = right.into_parts() {
+                        left = Self::node(k, v, Color::Red, left, rl);
+                        k = rk;
+                        v = rv;
+                        right = rr;
+                    } else {
+                        unreachable!()
+                    }
+                }
+                // Rotate Right
+                if left.is_red() && left.is_left_red() {
+                    if let Some((lk, lv, _, ll, lr)) = left.into_parts() {
+                        right = Self::node(k, v, Color::Red, lr, right);
+                        k = lk;
+                        v = lv;
+                        left = ll;
+                    } else {
+                        unreachable!()
+                    }
+                }
+                // Flip colors
+                if left.is_red() && right.is_red() {
+                    left.set_color(Color::Black);
+                    right.set_color(Color::Black);
+                    Self::node(k, v, Color::Red, left, right)
+                } else {
+                    Self::node(k, v, color, left, right)
+                }
+            }
+            None => RedBlackTree::new(),
+        }
+    }
+
+    // Hangs `key` and `right` down the right spine of taller `left`
+    fn join_right(
+        left: Self,
+        bh_left: usize,
+        key: K,
+        value: V,
+        right: Self,
+        bh_right: usize,
+    ) -> Self {
+        if !left.is_red() && bh_left.eq(&bh_right) {
+            return Self::node(key, value, Color::Red, left, right);
+        }
+        match left.into_parts() {
+            Some((k, v, color, l, r)) => {
+                let bh_r = if color.is_red() {
+                    bh_left
+                } else {
+                    bh_left - 1_usize
+                };
+                let r = Self::join_right(r, bh_r, key, value, right, bh_right);
+                Self::node(k, v, color, l, r).balance()
+            }
+            None => Self::node(key, value, Color::Red, RedBlackTree::new(), right),
+        }
+    }
+
+    // Hangs `left` and `key` down the left spine of taller `right`
+    fn join_left(
+        left: Self,
+        bh_left: usize,
+        key: K,
+        value: V,
+        right: Self,
+        bh_right: usize,
+    ) -> Self {
+        if !right.is_red() && bh_left.eq(&bh_right) {
+            return Self::node(key, value, Color::Red, left, right);
+        }
+        match right.into_parts() {
+            Some((k, v, color, l, r)) => {
+                let bh_l = if color.is_red() {
+                    bh_right
+                } else {
+                    bh_right - 1_usize
+                };
+                let l = Self::join_left(left, bh_left, key, value, l, bh_l);
+                Self::node(k, v, color, l, r).balance()
+            }
+            None => Self::node(key, value, Color::Red, left, RedBlackTree::new()),
+        }
+    }
+
+    // Removes the maximum entry
+    fn split_last(self) -> Option<(Self, K, V)> {
+        self.into_parts()
+            .map(|(k, v, _, left, right)| match right.split_last() {
+                Some((right, max_k, max_v)) => (Self::join(left, k, v, right), max_k, max_v),
+                None => {
+                    let mut left = left;
+                    left.set_color(Color::Black);
+                    (left, k, v)
+                }
+            })
+    }
+
+    // Joins two trees without a middle key
+    fn join_pair(left: Self, mut right: Self) -> Self {
+        match left.split_last() {
+            Some((left, k, v)) => Self::join(left, k, v, right),
+            None => {
+                right.set_color(Color::Black);
+                right
+            }
+        }
+    }
+
+    fn rotate_left(self, spare: &mut Pool<Self>) -> Self {
+        match self.into_parts_in(spare) {
+            Some((k, v, color, left, right)) => match right.into_parts_in(spare) {
+                Some((rk, rv, _, rl, rr)) => {
+                    let left = Self::node_in(k, v, Color::Red, left, rl, spare);
+                    Self::node_in(rk, rv, color, left, rr, spare)
+                }
+                None => Self::node_in(k, v, color, left, RedBlackTree::new(), spare),
+            },
+            None => RedBlackTree::new(),
+        }
+    }
+
+    fn rotate_right(self, spare: &mut Pool<Self>) -> Self {
+        match self.into_parts_in(spare) {
+            Some((k, v, color, left, right)) => match left.into_parts_in(spare) {
+                Some((lk, lv, _, ll, lr)) => {
+                    let right = Self::node_in(k, v, Color::Red, lr, right, spare);
+                    Self::node_in(lk, lv, color, ll, right, spare)
+                }
+                None => Self::node_in(k, v, color, RedBlackTree::new(), right, spare),
+            },
+            None => RedBlackTree::new(),
+        }
+    }
+
+    // Inverts colors of a node and its children, pushing a red link down
+    // on the way to a deletion and back up after it
+    fn flip_colors(self, spare: &mut Pool<Self>) -> Self {
+        match self.into_parts_in(spare) {
+            Some((k, v, color, mut left, mut right)) => {
+                left.set_color(left.flipped_color());
+                right.set_color(right.flipped_color());
+                Self::node_in(k, v, color.flipped(), left, right, spare)
+            }
+            None => RedBlackTree::new(),
+        }
+    }
+
+    const fn flipped_color(&self) -> Color {
+        if self.is_red() {
+            Color::Black
         } else {
-            None
+            Color::Red
         }
     }
 
-    fn set_color(&mut self, c: bool) {
-        if let RedBlackTree::Node {
-            k: _,
-            v: _,
-            ref mut color,
+    fn is_right_red(&self) -> bool {
+        self.root().is_some_and(|node| node.right().is_red())
+    }
+
+    fn is_left_left_red(&self) -> bool {
+        self.root().is_some_and(|node| node.left().is_left_red())
+    }
+
+    fn is_right_left_red(&self) -> bool {
+        self.root().is_some_and(|node| node.right().is_left_red())
+    }
+
+    // Makes the left child or one of its children red
+    fn move_red_left(self, spare: &mut Pool<Self>) -> Self {
+        let tree = self.flip_colors(spare);
+        if tree.is_right_left_red() {
+            tree.map_right(spare, Self::rotate_right)
+                .rotate_left(spare)
+                .flip_colors(spare)
+        } else {
+            tree
+        }
+    }
+
+    // Makes the right child or one of its children red
+    fn move_red_right(self, spare: &mut Pool<Self>) -> Self {
+        let tree = self.flip_colors(spare);
+        if tree.is_left_left_red() {
+            tree.rotate_right(spare).flip_colors(spare)
+        } else {
+            tree
+        }
+    }
+
+    fn map_right<F: FnOnce(Self, &mut Pool<Self>) -> Self>(
+        self,
+        spare: &mut Pool<Self>,
+        f: F,
+    ) -> Self {
+        match self.into_parts_in(spare) {
+            Some((k, v, color, left, right)) => {
+                let right = f(right, spare);
+                Self::node_in(k, v, color, left, right, spare)
+            }
+            None => RedBlackTree::new(),
+        }
+    }
+
+    // Restores left leaning links on the way up from a deletion, the flip
+    // inverts colors as `flip_colors`
+    fn fix_up(self, spare: &mut Pool<Self>) -> Self {
+        let mut tree = self;
+        if tree.is_right_red() && !tree.is_left_red() {
+            tree = tree.rotate_left(spare);
+        }
+        if tree.is_left_red() && tree.is_left_left_red() {
+            tree = tree.rotate_right(spare);
+        }
+        if tree.is_left_red() && tree.is_right_red() {
+            tree = tree.flip_colors(spare);
+        }
+        tree
+    }
+
+    // Removes the minimum entry, the root or its left child is red
+    fn without_min(self, spare: &mut Pool<Self>) -> (Self, Option<(K, V)>) {
+        let mut tree = self;
+        if tree.root().is_some_and(|node| node.left().is_empty()) {
+            // a node without a left child has no right child either
+            return (
+                RedBlackTree::new(),
+                tree.into_parts_in(spare).map(|(k, v, ..)| (k, v)),
+            );
+        }
+        if !tree.is_left_red() && !tree.is_left_left_red() {
+            tree = tree.move_red_left(spare);
+        }
+        match tree.into_parts_in(spare) {
+            Some((k, v, color, left, right)) => {
+                let (left, min) = left.without_min(spare);
+                (
+                    Self::node_in(k, v, color, left, right, spare).fix_up(spare),
+                    min,
+                )
+            }
+            None => (RedBlackTree::new(), None),
+        }
+    }
+
+    // Removes the maximum entry, the root or its right child is red
+    fn without_max(self, spare: &mut Pool<Self>) -> (Self, Option<(K, V)>) {
+        let mut tree = self;
+        if tree.is_left_red() {
+            tree = tree.rotate_right(spare);
+        }
+        if tree.root().is_some_and(|node| node.right().is_empty()) {
+            // a node without a right child has no left child either
+            return (
+                RedBlackTree::new(),
+                tree.into_parts_in(spare).map(|(k, v, ..)| (k, v)),
+            );
+        }
+        if !tree.is_right_red() && !tree.is_right_left_red() {
+            tree = tree.move_red_right(spare);
+        }
+        match tree.into_parts_in(spare) {
+            Some((k, v, color, left, right)) => {
+                let (right, max) = right.without_max(spare);
+                (
+                    Self::node_in(k, v, color, left, right, spare).fix_up(spare),
+                    max,
+                )
+            }
+            None => (RedBlackTree::new(), None),
+        }
+    }
+
+    // Red root for a deletion below it, unless a child of it is red
+    fn redden_root(&mut self) {
+        if !self.is_left_red() && !self.is_right_red() {
+            self.set_color(Color::Red);
+        }
+    }
+
+    // Removes `key` from below a red root or red left child
+    fn delete_node(self, key: &K, spare: &mut Pool<Self>) -> (Self, Option<V>) {
+        let mut tree = self;
+        let less = match tree.get_key() {
+            Some(k) => key.lt(k),
+            None => return (tree, None),
+        };
+        if less {
+            if !tree.is_left_red() && !tree.is_left_left_red() {
+                tree = tree.move_red_left(spare);
+            }
+            return match tree.into_parts_in(spare) {
+                Some((k, v, color, left, right)) => {
+                    let (left, found) = left.delete_node(key, spare);
+                    (
+                        Self::node_in(k, v, color, left, right, spare).fix_up(spare),
+                        found,
+                    )
+                }
+                None => (RedBlackTree::new(), None),
+            };
+        }
+        if tree.is_left_red() {
+            tree = tree.rotate_right(spare);
+        }
+        let leaf = tree
+            .root()
+            .is_some_and(|node| key.eq(node.key()) && node.right().is_empty());
+        if leaf {
+            return (
+                RedBlackTree::new(),
+                tree.into_parts_in(spare).map(|(_, v, ..)| v),
+            );
+        }
+        if !tree.is_right_red() && !tree.is_right_left_red() {
+            tree = tree.move_red_right(spare);
+        }
+        match tree.into_parts_in(spare) {
+            Some((k, v, color, left, right)) => {
+                if key.eq(&k) {
+                    // the successor takes the place of the node
+                    match right.without_min(spare) {
+                        (right, Some((min_k, min_v))) => (
+                            Self::node_in(min_k, min_v, color, left, right, spare).fix_up(spare),
+                            Some(v),
+                        ),
+                        (right, None) => (Self::node_in(k, v, color, left, right, spare), None),
+                    }
+                } else {
+                    let (right, found) = right.delete_node(key, spare);
+                    (
+                        Self::node_in(k, v, color, left, right, spare).fix_up(spare),
+                        found,
+                    )
+                }
+            }
+            None => (RedBlackTree::new(), None),
+        }
+    }
+
+    // Largest key strictly less than `key`, a `floor` skipping `key` itself
+    pub(crate) fn lower(&self, key: &K) -> Option<&K> {
+        match &self.root {
+            Tree::Node {
+                ref k,
+                v: _,
+                color: _,
+                size: _,
+                ref left,
+                ref right,
+            } => match key.cmp(k) {
+                Ordering::Greater => right.lower(key).or(Some(k)),
+                _ => left.lower(key),
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Parallel set operations, the two halves of every split are processed
+/// with `rayon::join`.
+#[cfg(feature = "rayon")]
+impl<K: Ord + Clone + Send, V: Clone + Send> RedBlackTree<K, V> {
+    /// Parallel `union`, on equal keys values of `self` are kept.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut a: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// let mut b: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// for i in 0..5000 {
+    ///     a.put(i * 2, 0);
+    ///     b.put(i * 3, 1);
+    /// }
+    ///
+    /// let union = a.par_union(b);
+    /// assert_eq!(union.size(), 5000 + 5000 - 1667);
+    /// assert_eq!(union.get(&6), Some(&0));
+    /// ```
+    pub fn par_union(self, other: Self) -> Self {
+        if (self.size() + other.size()).lt(&PARALLEL_CUTOFF) {
+            return self.union(other);
+        }
+        if other.is_empty() {
+            return self;
+        }
+        match self.into_parts() {
+            Some((k, v, _, left, right)) => {
+                let (less, _, greater) = other.split(&k);
+                let (left, right) =
+                    rayon::join(|| left.par_union(less), || right.par_union(greater));
+                Self::join(left, k, v, right)
+            }
+            None => other,
+        }
+    }
+
+    /// Parallel `intersection`, with values of `self`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut a: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// let mut b: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// for i in 0..5000 {
+    ///     a.put(i * 2, 0);
+    ///     b.put(i * 3, 1);
+    /// }
+    ///
+    /// let intersection = a.par_intersection(b);
+    /// assert_eq!(intersection.size(), 1667_usize);
+    /// assert_eq!(intersection.get(&6), Some(&0));
+    /// ```
+    pub fn par_intersection(self, other: Self) -> Self {
+        if (self.size() + other.size()).lt(&PARALLEL_CUTOFF) {
+            return self.intersection(other);
+        }
+        if other.is_empty() {
+            return RedBlackTree::new();
+        }
+        match self.into_parts() {
+            Some((k, v, _, left, right)) => {
+                let (less, found, greater) = other.split(&k);
+                let (left, right) = rayon::join(
+                    || left.par_intersection(less),
+                    || right.par_intersection(greater),
+                );
+                match found {
+                    Some(_) => Self::join(left, k, v, right),
+                    None => Self::join_pair(left, right),
+                }
+            }
+            None => RedBlackTree::new(),
+        }
+    }
+
+    /// Parallel `difference`, keys of `self` not present in `other`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut a: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// let mut b: RedBlackTree<u32, u32> = RedBlackTree::new();
+    /// for i in 0..5000 {
+    ///     a.put(i * 2, 0);
+    ///     b.put(i * 3, 1);
+    /// }
+    ///
+    /// let difference = a.par_difference(b);
+    /// assert_eq!(difference.size(), 5000 - 1667);
+    /// assert_eq!(difference.get(&6), None);
+    /// ```
+    pub fn par_difference(self, other: Self) -> Self {
+        if (self.size() + other.size()).lt(&PARALLEL_CUTOFF) {
+            return self.difference(other);
+        }
+        if self.is_empty() {
+            return RedBlackTree::new();
+        }
+        match other.into_parts() {
+            Some((k, _, _, left, right)) => {
+                let (less, _, greater) = self.split(&k);
+                let (left, right) = rayon::join(
+                    || less.par_difference(left),
+                    || greater.par_difference(right),
+                );
+                Self::join_pair(left, right)
+            }
+            None => self,
+        }
+    }
+
+    /// Calls `f` on every entry with key inside `range`, splitting the
+    /// walk across subtrees on multiple threads. Entries are visited in no
+    /// particular order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<u64, u64> = RedBlackTree::new();
+    /// for i in 0..10_000 {
+    ///     rbtree.put(i, 1);
+    /// }
+    ///
+    /// let sum = AtomicU64::new(0);
+    /// rbtree.par_range_for_each(100..200, |k, _| {
+    ///     sum.fetch_add(*k, Ordering::Relaxed);
+    /// });
+    /// assert_eq!(sum.into_inner(), (100..200).sum::<u64>());
+    /// ```
+    pub fn par_range_for_each<R, F>(&self, range: R, f: F)
+    where
+        K: Sync,
+        V: Sync,
+        R: RangeBounds<K> + Sync,
+        F: Fn(&K, &V) + Sync,
+    {
+        self.par_range_walk(&range, &f);
+    }
+
+    fn par_range_walk<R, F>(&self, range: &R, f: &F)
+    where
+        K: Sync,
+        V: Sync,
+        R: RangeBounds<K> + Sync,
+        F: Fn(&K, &V) + Sync,
+    {
+        if self.size().lt(&PARALLEL_CUTOFF) {
+            let mut vec = Vec::new();
+            self.take_range(range, &mut vec);
+            return vec.into_iter().for_each(|(k, v)| f(k, v));
+        }
+        if let Tree::Node {
+            ref k,
+            ref v,
+            color: _,
             size: _,
-            left: _,
-            right: _,
-        } = self
+            ref left,
+            ref right,
+        } = &self.root
         {
-            *color = c;
+            rayon::join(
+                || {
+                    if reaches_below(range, k) {
+                        left.par_range_walk(range, f);
+                    }
+                },
+                || {
+                    if reaches_above(range, k) {
+                        right.par_range_walk(range, f);
+                    }
+                },
+            );
+            if range.contains(k) {
+                f(k, v);
+            }
+        }
+    }
+}
+
+/// Queries of maps keyed by pairs.
+impl<A: Ord + Clone, B: Ord + Clone, V: Clone> RedBlackTree<(A, B), V> {
+    /// Returns entries whose key starts with `prefix`, in key order.
+    ///
+    /// Subtrees with first components on the wrong side of `prefix` are
+    /// skipped, so only the matching part of the tree is walked.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree: RedBlackTree<(char, u32), ()> = RedBlackTree::new();
+    /// for (i, c) in "abcabca".chars().enumerate() {
+    ///     rbtree.put((c, i as u32), ());
+    /// }
+    /// let positions: Vec<u32> = rbtree.range_prefix(&'a').map(|(k, _)| k.1).collect();
+    /// assert_eq!(positions, vec![0, 3, 6]);
+    /// ```
+    pub fn range_prefix(&self, prefix: &A) -> std::vec::IntoIter<(&(A, B), &V)> {
+        let mut vec = Vec::new();
+        self.take_prefix(prefix, &mut vec);
+        vec.into_iter()
+    }
+
+    fn take_prefix<'a>(&'a self, prefix: &A, vec: &mut Vec<(&'a (A, B), &'a V)>) {
+        if let Tree::Node {
+            ref k,
+            ref v,
+            color: _,
+            size: _,
+            ref left,
+            ref right,
+        } = &self.root
+        {
+            let ord = k.0.cmp(prefix);
+            if ord.ne(&Ordering::Less) {
+                left.take_prefix(prefix, vec);
+            }
+            if ord.eq(&Ordering::Equal) {
+                vec.push((k, v));
+            }
+            if ord.ne(&Ordering::Greater) {
+                right.take_prefix(prefix, vec);
+            }
         }
     }
+}
 
-    fn is_red(&self) -> bool {
-        match self {
-            RedBlackTree::Node {
-                k: _,
-                v: _,
-                ref color,
-                size: _,
-                left: _,
-                right: _,
-            } => *color,
-            _ => false,
-        }
+impl<K: Ord + Clone + Display, V: Clone + Display> RedBlackTree<K, V> {
+    /// Returns the exact shape and colors of the tree in a parenthesized
+    /// notation, read back by `parse`.
+    ///
+    /// A node is `key:value(left,right)` with a `*` in front of red ones,
+    /// an empty tree `-`, and a node without children leaves the
+    /// parentheses out. Keys and values must not display `:`, `,` or
+    /// parentheses, or start with `*`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rbtree::RedBlackTree;
+    /// use treers::Map;
+    ///
+    /// let mut rbtree = RedBlackTree::new();
+    /// for k in 1..=4 {
+    ///     rbtree.put(k, k * 10);
+    /// }
+    /// assert_eq!(rbtree.to_paren_string(), "2:20(1:10,4:40(*3:30,-))");
+    ///
+    /// let parsed: RedBlackTree<u32, u32> = "2:20(1:10,4:40(*3:30,-))".parse().unwrap();
+    /// assert_eq!(parsed.to_paren_string(), rbtree.to_paren_string());
+    /// assert_eq!(parsed.get(&3), Some(&30));
+    /// ```
+    pub fn to_paren_string(&self) -> String {
+        let mut out = String::new();
+        paren::write(self, &mut out);
+        out
     }
+}
 
-    fn is_left_red(&self) -> bool {
-        match self {
-            RedBlackTree::Node {
-                k: _,
-                v: _,
-                color: _,
-                size: _,
-                ref left,
-                right: _,
-            } => left.is_red(),
-            _ => false,
-        }
-    }
+impl<K: Ord + Clone + Display, V: Clone + Display> ParenNode for RedBlackTree<K, V> {
+    type Key = K;
+    type Value = V;
 
-    fn is_right_red(&self) -> bool {
-        match self {
-            RedBlackTree::Node {
-                k: _,
-                v: _,
-                color: _,
+    fn parts(&self) -> Option<Parts<'_, Self>> {
+        match &self.root {
+            Tree::Node {
+                ref k,
+                ref v,
+                ref color,
                 size: _,
-                left: _,
+                ref left,
                 ref right,
-            } => right.is_red(),
-            _ => false,
+            } => Some((k, v, color.is_red(), left, right)),
+            Tree::Nil => None,
         }
     }
+}
 
-    fn get_left_clone(&self) -> Box<RedBlackTree<K, V>> {
-        match self {
-            RedBlackTree::Node {
-                k: _,
-                v: _,
-                color: _,
-                size: _,
-                ref left,
-                right: _,
-            } => left.clone(),
-            _ => Box::new(RedBlackTree::NIL),
+impl<K: Ord + Clone, V: Clone> RedBlackTree<K, V> {
+    fn from_parsed(parsed: Parsed<K, V>) -> Self {
+        match parsed {
+            Parsed::Node {
+                at: _,
+                k,
+                v,
+                red,
+                left,
+                right,
+            } => RedBlackTree::node(
+                k,
+                v,
+                if red { Color::Red } else { Color::Black },
+                RedBlackTree::from_parsed(*left),
+                RedBlackTree::from_parsed(*right),
+            ),
+            Parsed::Nil => RedBlackTree::new(),
         }
     }
+}
 
-    fn get_right_clone(&self) -> Box<RedBlackTree<K, V>> {
-        match self {
-            RedBlackTree::Node {
-                k: _,
-                v: _,
-                color: _,
-                size: _,
-                left: _,
-                ref right,
-            } => right.clone(),
-            _ => Box::new(RedBlackTree::NIL),
-        }
+impl<K: Ord + Clone + FromStr, V: Clone + FromStr> FromStr for RedBlackTree<K, V> {
+    type Err = ParseTreeError;
+
+    /// Rebuilds a tree of the exact shape and colors written by
+    /// `to_paren_string`.
+    ///
+    /// Fails on malformed text, keys or values that don't parse, and keys
+    /// out of search tree order. Colors are taken as written, so a tree
+    /// broken by a bug can be rebuilt as it was.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        paren::parse(s, true).map(RedBlackTree::from_parsed)
     }
 }
 
@@ -772,10 +3479,391 @@ impl<K: Ord + Clone, V: Clone> Index<&K> for RedBlackTree<K, V> {
     }
 }
 
+impl<K: Ord + Clone + Display, V: Clone + Display> Display for RedBlackTree<K, V> {
+    /// Writes the tree as `to_paren_string` does, so `parse` reads it back.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_paren_string())
+    }
+}
+
+/// One step of a put run by a `Stepper`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step<K> {
+    /// The key was compared with the key of the node, the stepper is at
+    Compare(K, Ordering),
+    /// Moved down to the left child
+    DescendLeft,
+    /// Moved down to the right child
+    DescendRight,
+    /// Hung a new red node at the null link reached
+    Insert,
+    /// The key was there, its value replaced
+    Update,
+    /// Rotated the node of the key left, a right leaning red link
+    RotateLeft(K),
+    /// Rotated the node of the key right, two red links in a row
+    RotateRight(K),
+    /// Flipped colors of the node of the key and its children, splitting a
+    /// 4-node and passing a red link up
+    FlipColors(K),
+    /// Colored the red root black
+    BlackRoot,
+}
+
+// Where a `Stepper` is in a put
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Compare,
+    Descend(Ordering),
+    // Checks of the node on the way up: rotate left, rotate right, flip,
+    // then up to the parent
+    Fix(usize),
+    Root,
+    Done,
+}
+
+/// A put into a `RedBlackTree` in single, inspectable steps.
+///
+/// `step` runs one comparison, move down, rotation or color flip of the
+/// left-leaning put and returns it; in between, `tree` and `node` show the
+/// tree and the node the stepper is at, `to_paren_string` or `color_of`
+/// draw them. Only the colors of the path may break the invariants
+/// between steps, sizes always hold. The tree is left as `put` leaves it.
+///
+/// # Examples
+///
+/// ```
+/// use std::cmp::Ordering;
+/// use treers::rbtree::{RedBlackTree, Step, Stepper};
+/// use treers::Map;
+///
+/// let mut tree = RedBlackTree::new();
+/// tree.put(1, 'a');
+/// tree.put(2, 'b');
+///
+/// let mut stepper = Stepper::new(tree, 3, 'c');
+/// assert_eq!(stepper.step(), Some(Step::Compare(2, Ordering::Greater)));
+/// assert_eq!(stepper.step(), Some(Step::DescendRight));
+/// assert_eq!(stepper.step(), Some(Step::Insert));
+/// // a 4-node, both links of 2 are red
+/// assert_eq!(stepper.tree().to_paren_string(), "2:b(*1:a,*3:c)");
+/// assert_eq!(stepper.step(), Some(Step::FlipColors(2)));
+/// assert_eq!(stepper.tree().to_paren_string(), "*2:b(1:a,3:c)");
+/// assert_eq!(stepper.step(), Some(Step::BlackRoot));
+/// assert_eq!(stepper.step(), None);
+/// assert_eq!(stepper.into_tree().to_paren_string(), "2:b(1:a,3:c)");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Stepper<K: Ord + Clone, V: Clone> {
+    tree: RedBlackTree<K, V>,
+    key: K,
+    // Taken once put
+    value: Option<V>,
+    // Moves from the root to the node the stepper is at, `true` to the right
+    path: Vec<bool>,
+    phase: Phase,
+}
+
+impl<K: Ord + Clone, V: Clone> Stepper<K, V> {
+    /// Starts a put of `key` and `value` into `tree`, no step is run yet.
+    pub const fn new(tree: RedBlackTree<K, V>, key: K, value: V) -> Self {
+        Stepper {
+            tree,
+            key,
+            value: Some(value),
+            path: Vec::new(),
+            phase: Phase::Compare,
+        }
+    }
+
+    /// Returns the tree as it is between steps.
+    pub const fn tree(&self) -> &RedBlackTree<K, V> {
+        &self.tree
+    }
+
+    /// Returns the node the stepper is at, `None` at a null link or once
+    /// done.
+    pub fn node(&self) -> Option<NodeRef<'_, K, V>> {
+        if self.is_done() {
+            return None;
+        }
+        let mut at = &self.tree;
+        for &right in &self.path {
+            at = match &at.root {
+                Tree::Node {
+                    k: _,
+                    v: _,
+                    color: _,
+                    size: _,
+                    ref left,
+                    right: ref r,
+                } => {
+                    if right {
+                        r
+                    } else {
+                        left
+                    }
+                }
+                Tree::Nil => return None,
+            };
+        }
+        at.root()
+    }
+
+    /// Returns the depth of the node the stepper is at, `0` at the root.
+    pub const fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Checks if the put is over.
+    pub const fn is_done(&self) -> bool {
+        matches!(self.phase, Phase::Done)
+    }
+
+    /// Runs the next step and returns it, `None` once the put is over.
+    pub fn step(&mut self) -> Option<Step<K>> {
+        loop {
+            match self.phase {
+                Phase::Compare => {
+                    let key = match self.at_mut().get_key() {
+                        Some(k) => k.clone(),
+                        None => {
+                            self.grow();
+                            let value = self.value.take()?;
+                            let leaf = RedBlackTree::node(
+                                self.key.clone(),
+                                value,
+                                Color::Red,
+                                RedBlackTree::new(),
+                                RedBlackTree::new(),
+                            );
+                            *self.at_mut() = leaf;
+                            self.phase = Phase::Fix(0_usize);
+                            return Some(Step::Insert);
+                        }
+                    };
+                    let ordering = self.key.cmp(&key);
+                    self.phase = Phase::Descend(ordering);
+                    return Some(Step::Compare(key, ordering));
+                }
+                Phase::Descend(Ordering::Less) | Phase::Descend(Ordering::Greater) => {
+                    let right = matches!(self.phase, Phase::Descend(Ordering::Greater));
+                    self.path.push(right);
+                    self.phase = Phase::Compare;
+                    return Some(if right {
+                        Step::DescendRight
+                    } else {
+                        Step::DescendLeft
+                    });
+                }
+                Phase::Descend(Ordering::Equal) => {
+                    let value = self.value.take()?;
+                    if let Tree::Node {
+                        k: _,
+                        ref mut v,
+                        color: _,
+                        size: _,
+                        left: _,
+                        right: _,
+                    } = &mut self.at_mut().root
+                    {
+                        *v = value;
+                    }
+                    self.phase = Phase::Fix(0_usize);
+                    return Some(Step::Update);
+                }
+                Phase::Fix(check) => {
+                    self.phase = Phase::Fix(check + 1_usize);
+                    // boxes a rotation frees serve the nodes it builds
+                    let mut spare = Pool::new();
+                    let at = self.at_mut();
+                    let key = at.get_key().cloned();
+                    match (check, key) {
+                        (0, Some(k)) if at.is_right_red() && !at.is_left_red() => {
+                            *at = std::mem::take(at).rotate_left(&mut spare);
+                            return Some(Step::RotateLeft(k));
+                        }
+                        (1, Some(k)) if at.is_left_red() && at.is_left_left_red() => {
+                            *at = std::mem::take(at).rotate_right(&mut spare);
+                            return Some(Step::RotateRight(k));
+                        }
+                        (2, Some(k)) if at.is_left_red() && at.is_right_red() => {
+                            *at = std::mem::take(at).flip_colors(&mut spare);
+                            return Some(Step::FlipColors(k));
+                        }
+                        (0..=2, _) => {}
+                        _ => {
+                            self.phase = match self.path.pop() {
+                                Some(_) => Phase::Fix(0_usize),
+                                None => Phase::Root,
+                            };
+                        }
+                    }
+                }
+                Phase::Root => {
+                    self.phase = Phase::Done;
+                    if self.tree.is_red() {
+                        self.tree.set_color(Color::Black);
+                        return Some(Step::BlackRoot);
+                    }
+                }
+                Phase::Done => return None,
+            }
+        }
+    }
+
+    /// Runs the steps left and returns the tree.
+    pub fn finish(mut self) -> RedBlackTree<K, V> {
+        while self.step().is_some() {}
+        self.tree
+    }
+
+    /// Returns the tree as it is, a put stopped halfway may break the
+    /// invariants.
+    pub fn into_tree(self) -> RedBlackTree<K, V> {
+        self.tree
+    }
+
+    fn at_mut(&mut self) -> &mut RedBlackTree<K, V> {
+        let mut at = &mut self.tree;
+        for &right in &self.path {
+            at = match &mut at.root {
+                Tree::Node {
+                    k: _,
+                    v: _,
+                    color: _,
+                    size: _,
+                    ref mut left,
+                    right: ref mut r,
+                } => {
+                    if right {
+                        r
+                    } else {
+                        left
+                    }
+                }
+                Tree::Nil => unreachable!(),
+            };
+        }
+        at
+    }
+
+    // Counts the new node in the sizes of the nodes above it
+    fn grow(&mut self) {
+        let mut at = &mut self.tree;
+        for &right in &self.path {
+            at = match &mut at.root {
+                Tree::Node {
+                    k: _,
+                    v: _,
+                    color: _,
+                    ref mut size,
+                    ref mut left,
+                    right: ref mut r,
+                } => {
+                    *size += 1_usize;
+                    if right {
+                        r
+                    } else {
+                        left
+                    }
+                }
+                Tree::Nil => unreachable!(),
+            };
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::rbtree::RedBlackTree;
-    use crate::{SedgewickMap, Traversals, TreeTraversal};
+    use crate::entry::Entry;
+    use crate::rbtree::{Color, RedBlackTree, Step, Stepper};
+    use crate::{Map, OrderedMap, SedgewickMap, Traversals, TreeTraversal};
+    use std::collections::BTreeSet;
+
+    // Black height of a valid left leaning Red-Black Tree
+    fn checked_black_height(tree: &RedBlackTree<u32, u32>, parent_red: bool) -> Option<usize> {
+        let node = match tree.root() {
+            Some(node) => node,
+            None => return Some(0_usize),
+        };
+        let red = node.color().is_red();
+        let red_violation = (red && parent_red) || node.right().is_red();
+        let size_violation = node
+            .size()
+            .ne(&(node.left().size() + node.right().size() + 1_usize));
+        if red_violation || size_violation {
+            return None;
+        }
+        let l = checked_black_height(node.left(), red)?;
+        let r = checked_black_height(node.right(), red)?;
+        if l.eq(&r) {
+            Some(l + if red { 0_usize } else { 1_usize })
+        } else {
+            None
+        }
+    }
+
+    fn assert_valid(tree: &RedBlackTree<u32, u32>) {
+        assert!(!tree.is_red());
+        assert!(checked_black_height(tree, false).is_some());
+    }
+
+    // Pseudo random keys below `bound`
+    fn random_tree(seed: u32, n: usize, bound: u32) -> (RedBlackTree<u32, u32>, BTreeSet<u32>) {
+        let mut rbtree = RedBlackTree::new();
+        let mut keys = BTreeSet::new();
+        let mut x = seed;
+        for _ in 0..n {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let k = (x >> 8) % bound;
+            rbtree.put(k, seed);
+            keys.insert(k);
+        }
+        (rbtree, keys)
+    }
+
+    #[test]
+    fn test_paren_round_trip() {
+        for seed in 1_u32..20 {
+            let (rbtree, _) = random_tree(seed, 300, 1000);
+            let text = rbtree.to_paren_string();
+            let parsed: RedBlackTree<u32, u32> = text.parse().unwrap();
+            assert_valid(&parsed);
+            assert_eq!(parsed.to_paren_string(), text);
+            assert_eq!(parsed.size(), rbtree.size());
+            assert!(parsed.same_shape(&rbtree));
+        }
+        // colors are kept as written, even when invalid
+        let broken: RedBlackTree<u32, u32> = "*2:0(*1:0,-)".parse().unwrap();
+        assert_eq!(broken.to_paren_string(), "*2:0(*1:0,-)");
+        assert_eq!(broken.get(&1), Some(&0));
+        assert!("2:0(*1:0,*0:0)".parse::<RedBlackTree<u32, u32>>().is_err());
+    }
+
+    #[test]
+    fn test_colors() {
+        for seed in 1_u32..10 {
+            let (rbtree, keys) = random_tree(seed, 500, 2000);
+            let red: Vec<u32> = keys
+                .iter()
+                .copied()
+                .filter(|k| rbtree.color_of(k).is_some_and(Color::is_red))
+                .collect();
+            assert_eq!(rbtree.count_red_links(), red.len());
+            assert_eq!(rbtree.to_paren_string().matches('*').count(), red.len());
+            assert!(keys.iter().all(|k| rbtree.color_of(k).is_some()));
+            assert_eq!(rbtree.color_of(&2000), None);
+            // a red link per 3-node, and nodes of a 2-3 tree hold 1 or 2 keys
+            assert!(red.len().le(&(keys.len() / 2_usize)));
+        }
+        let empty: RedBlackTree<u32, u32> = RedBlackTree::new();
+        assert_eq!(empty.count_red_links(), 0_usize);
+    }
+
+    fn keys_of(tree: &RedBlackTree<u32, u32>) -> BTreeSet<u32> {
+        tree.range(..).map(|(k, _)| *k).collect()
+    }
 
     #[test]
     fn test_is_empty() {
@@ -788,7 +3876,7 @@ mod tests {
         let mut rbtree: RedBlackTree<i32, i32> = RedBlackTree::new();
         rbtree.put(1, 2);
         rbtree.put(2, 3);
-        assert_eq!(rbtree.is_empty(), false);
+        assert!(!rbtree.is_empty());
     }
 
     #[test]
@@ -828,14 +3916,14 @@ mod tests {
         let mut rbtree: RedBlackTree<i32, i32> = RedBlackTree::new();
         rbtree.put(1_i32, -1_i32);
         assert!(rbtree.contains(&1_i32));
-        assert_eq!(rbtree.contains(&-1_i32), false);
+        assert!(!rbtree.contains(&-1_i32));
     }
 
     #[test]
     fn test_left_rotate_min() {
         let mut rbtree: RedBlackTree<u32, u32> = RedBlackTree::new();
         assert_eq!(rbtree.min(), None);
-        for i in vec![1_u32, 2, 3, 4, 5, 6] {
+        for i in [1_u32, 2, 3, 4, 5, 6] {
             rbtree.put(i, i);
         }
         assert_eq!(rbtree.min(), Some(&1_u32));
@@ -856,7 +3944,7 @@ mod tests {
     fn test_shuffle_max() {
         let mut rbtree: RedBlackTree<u32, u32> = RedBlackTree::new();
         assert_eq!(rbtree.max(), None);
-        for i in vec![6_u32, 4, 5, 2, 1, 3] {
+        for i in [6_u32, 4, 5, 2, 1, 3] {
             rbtree.put(i, i);
         }
         assert_eq!(rbtree.max(), Some(&6_u32));
@@ -873,10 +3961,26 @@ mod tests {
         assert_eq!(rbtree.get(rbtree.max().unwrap()), rbtree.max());
     }
 
+    #[test]
+    fn test_floor_and_ceiling() {
+        let mut rbtree: RedBlackTree<u32, u32> = RedBlackTree::new();
+        for i in (10..=100_u32).step_by(10) {
+            rbtree.put(i, i);
+        }
+        assert_eq!(rbtree.floor(&5_u32), None);
+        assert_eq!(rbtree.floor(&35_u32), Some(&30_u32));
+        assert_eq!(rbtree.floor(&40_u32), Some(&40_u32));
+        assert_eq!(rbtree.floor(&999_u32), Some(&100_u32));
+        assert_eq!(rbtree.ceiling(&5_u32), Some(&10_u32));
+        assert_eq!(rbtree.ceiling(&35_u32), Some(&40_u32));
+        assert_eq!(rbtree.ceiling(&40_u32), Some(&40_u32));
+        assert_eq!(rbtree.ceiling(&999_u32), None);
+    }
+
     #[test]
     fn test_random_in_order() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let res = vec!['a', 'b', 'c', 'd'];
+        let res = ['a', 'b', 'c', 'd'];
         let mut it = res.iter();
         rbtree.put('c', 3);
         rbtree.put('d', 4);
@@ -891,7 +3995,7 @@ mod tests {
     #[test]
     fn test_random_pre_order() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let res = vec!['c', 'b', 'a', 'd'];
+        let res = ['c', 'b', 'a', 'd'];
         let mut it = res.iter();
         rbtree.put('c', 3);
         rbtree.put('d', 4);
@@ -906,7 +4010,7 @@ mod tests {
     #[test]
     fn test_random_post_order() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let res = vec!['a', 'b', 'd', 'c'];
+        let res = ['a', 'b', 'd', 'c'];
         let mut it = res.iter();
         rbtree.put('c', 3);
         rbtree.put('d', 4);
@@ -921,7 +4025,7 @@ mod tests {
     #[test]
     fn test_random_level_order() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let res = vec!['c', 'b', 'd', 'a'];
+        let res = ['c', 'b', 'd', 'a'];
         let mut it = res.iter();
         rbtree.put('c', 3);
         rbtree.put('d', 4);
@@ -933,13 +4037,92 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_size_after_rotations() {
+        let mut rbtree: RedBlackTree<u32, u32> = RedBlackTree::new();
+        let keys = [50_u32, 25, 12, 6, 3, 1, 0, 2, 4, 5, 9, 7, 8, 10, 11, 18, 15];
+        for (n, k) in (1_usize..).zip(keys.iter()) {
+            rbtree.put(*k, *k);
+            assert_eq!(rbtree.size(), n);
+            assert_eq!(rbtree.traverse(&Traversals::InOrder).len(), n);
+        }
+    }
+
+    #[test]
+    fn test_map_values() {
+        let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+        for (i, c) in (1..).zip('a'..='i') {
+            rbtree.put(c, i);
+        }
+        let mut seen = Vec::new();
+        let mapped = rbtree.map_values(|k, v| {
+            seen.push(*k);
+            i64::from(v) * 10
+        });
+        assert_eq!(seen, ('a'..='i').collect::<Vec<_>>());
+        assert_eq!(mapped.size(), 9_usize);
+        assert_eq!(mapped.height(), Some(3_usize));
+        let res = [
+            ('d', 40),
+            ('b', 20),
+            ('a', 10),
+            ('c', 30),
+            ('h', 80),
+            ('f', 60),
+            ('e', 50),
+            ('g', 70),
+            ('i', 90),
+        ];
+        for ((a, v), (c, x)) in mapped.traverse(&Traversals::PreOrder).zip(res.iter()) {
+            assert_eq!(a, c);
+            assert_eq!(v, x);
+        }
+    }
+
+    #[test]
+    fn test_sorted_vec() {
+        let mut rbtree: RedBlackTree<u32, String> = RedBlackTree::new();
+        assert!(rbtree.to_sorted_vec().is_empty());
+        for i in (1..=100_u32).rev() {
+            rbtree.put(i, i.to_string());
+        }
+        let expected: Vec<(u32, String)> = (1..=100_u32).map(|i| (i, i.to_string())).collect();
+        assert_eq!(rbtree.to_sorted_vec(), expected);
+        let sorted = rbtree.into_sorted_vec();
+        assert_eq!(sorted.capacity(), 100_usize);
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn test_into_keys_and_values() {
+        let build = || {
+            let mut rbtree: RedBlackTree<u32, String> = RedBlackTree::new();
+            for i in [6_u32, 4, 5, 2, 1, 3] {
+                rbtree.put(i, i.to_string());
+            }
+            rbtree
+        };
+        let keys: Vec<u32> = build().into_keys().collect();
+        assert_eq!(keys, (1..=6_u32).collect::<Vec<_>>());
+        let values: Vec<String> = build().into_values().collect();
+        assert_eq!(values, ["1", "2", "3", "4", "5", "6"]);
+        let mut iter = build().into_iter();
+        assert_eq!(iter.len(), 6_usize);
+        assert_eq!(iter.next(), Some((1, String::from("1"))));
+        assert_eq!(iter.len(), 5_usize);
+
+        let mut rbtree = RedBlackTree::new();
+        for k in crate::bench_support::shuffled_keys(2000, 4) {
+            rbtree.put(k, k);
+        }
+        assert!(rbtree.clone().into_iter().eq(rbtree.into_sorted_vec()));
+    }
+
     #[test]
     fn test_left_rotate_size_and_height() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let mut i = 1;
-        for c in 'a'..='i' {
+        for (i, c) in (1..).zip('a'..='i') {
             rbtree.put(c, i);
-            i += 1;
         }
         assert_eq!(rbtree.size(), 9_usize);
         assert_eq!(rbtree.height(), Some(3_usize));
@@ -948,12 +4131,10 @@ mod tests {
     #[test]
     fn test_left_rotate_pre_order() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let res = vec!['d', 'b', 'a', 'c', 'h', 'f', 'e', 'g', 'i'];
+        let res = ['d', 'b', 'a', 'c', 'h', 'f', 'e', 'g', 'i'];
         let mut it = res.iter();
-        let mut i = 1;
-        for c in 'a'..='i' {
+        for (i, c) in (1..).zip('a'..='i') {
             rbtree.put(c, i);
-            i += 1;
         }
         assert_eq!(rbtree.size(), res.len());
         for (a, _) in rbtree.traverse(&Traversals::PreOrder) {
@@ -964,12 +4145,10 @@ mod tests {
     #[test]
     fn test_left_rotate_in_order() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let res = vec!['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i'];
+        let res = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i'];
         let mut it = res.iter();
-        let mut i = 1;
-        for c in 'a'..='i' {
+        for (i, c) in (1..).zip('a'..='i') {
             rbtree.put(c, i);
-            i += 1;
         }
         assert_eq!(rbtree.size(), res.len());
         for (a, _) in rbtree.traverse(&Traversals::InOrder) {
@@ -980,12 +4159,10 @@ mod tests {
     #[test]
     fn test_left_rotate_post_order() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let res = vec!['a', 'c', 'b', 'e', 'g', 'f', 'i', 'h', 'd'];
+        let res = ['a', 'c', 'b', 'e', 'g', 'f', 'i', 'h', 'd'];
         let mut it = res.iter();
-        let mut i = 1;
-        for c in 'a'..='i' {
+        for (i, c) in (1..).zip('a'..='i') {
             rbtree.put(c, i);
-            i += 1;
         }
         assert_eq!(rbtree.size(), res.len());
         for (a, _) in rbtree.traverse(&Traversals::PostOrder) {
@@ -996,12 +4173,10 @@ mod tests {
     #[test]
     fn test_left_rotate_level_order() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let res = vec!['d', 'b', 'h', 'a', 'c', 'f', 'i', 'e', 'g'];
+        let res = ['d', 'b', 'h', 'a', 'c', 'f', 'i', 'e', 'g'];
         let mut it = res.iter();
-        let mut i = 1;
-        for c in 'a'..='i' {
+        for (i, c) in (1..).zip('a'..='i') {
             rbtree.put(c, i);
-            i += 1;
         }
         assert_eq!(rbtree.size(), res.len());
         for (a, _) in rbtree.traverse(&Traversals::LevelOrder) {
@@ -1012,10 +4187,8 @@ mod tests {
     #[test]
     fn test_right_rotate_size_and_height() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let mut i = 1;
-        for c in ('a'..='i').rev() {
+        for (i, c) in (1..).zip(('a'..='i').rev()) {
             rbtree.put(c, i);
-            i += 1;
         }
         assert_eq!(rbtree.size(), 9_usize);
         assert_eq!(rbtree.height(), Some(3_usize));
@@ -1024,12 +4197,10 @@ mod tests {
     #[test]
     fn test_right_rotate_pre_order() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let res = vec!['f', 'd', 'b', 'a', 'c', 'e', 'h', 'g', 'i'];
+        let res = ['f', 'd', 'b', 'a', 'c', 'e', 'h', 'g', 'i'];
         let mut it = res.iter();
-        let mut i = 1;
-        for c in ('a'..='i').rev() {
+        for (i, c) in (1..).zip(('a'..='i').rev()) {
             rbtree.put(c, i);
-            i += 1;
         }
         assert_eq!(rbtree.size(), res.len());
         for (a, _) in rbtree.traverse(&Traversals::PreOrder) {
@@ -1040,12 +4211,10 @@ mod tests {
     #[test]
     fn test_right_rotate_in_order() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let res = vec!['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i'];
+        let res = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i'];
         let mut it = res.iter();
-        let mut i = 1;
-        for c in ('a'..='i').rev() {
+        for (i, c) in (1..).zip(('a'..='i').rev()) {
             rbtree.put(c, i);
-            i += 1;
         }
         assert_eq!(rbtree.size(), res.len());
         for (a, _) in rbtree.traverse(&Traversals::InOrder) {
@@ -1056,12 +4225,10 @@ mod tests {
     #[test]
     fn test_right_rotate_post_order() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let res = vec!['a', 'c', 'b', 'e', 'd', 'g', 'i', 'h', 'f'];
+        let res = ['a', 'c', 'b', 'e', 'd', 'g', 'i', 'h', 'f'];
         let mut it = res.iter();
-        let mut i = 1;
-        for c in ('a'..='i').rev() {
+        for (i, c) in (1..).zip(('a'..='i').rev()) {
             rbtree.put(c, i);
-            i += 1;
         }
         assert_eq!(rbtree.size(), res.len());
         for (a, _) in rbtree.traverse(&Traversals::PostOrder) {
@@ -1072,12 +4239,10 @@ mod tests {
     #[test]
     fn test_right_rotate_level_order() {
         let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
-        let res = vec!['f', 'd', 'h', 'b', 'e', 'g', 'i', 'a', 'c'];
+        let res = ['f', 'd', 'h', 'b', 'e', 'g', 'i', 'a', 'c'];
         let mut it = res.iter();
-        let mut i = 1;
-        for c in ('a'..='i').rev() {
+        for (i, c) in (1..).zip(('a'..='i').rev()) {
             rbtree.put(c, i);
-            i += 1;
         }
         assert_eq!(rbtree.size(), res.len());
         for (a, _) in rbtree.traverse(&Traversals::LevelOrder) {
@@ -1116,4 +4281,308 @@ mod tests {
         assert_eq!(rbtree.max(), Some(&1000_u32));
         assert_eq!(rbtree.get(&501_u32), Some(&501_u32));
     }
+
+    #[test]
+    fn test_put_keeps_invariants() {
+        for seed in 1_u32..20 {
+            let (rbtree, keys) = random_tree(seed, 300, 1000);
+            assert_valid(&rbtree);
+            assert_eq!(rbtree.size(), keys.len());
+        }
+    }
+
+    #[test]
+    fn test_entry_fills_as_put() {
+        for seed in 1_u32..20 {
+            let (rbtree, keys) = random_tree(seed, 300, 1000);
+            let mut filled = RedBlackTree::new();
+            let mut x = seed;
+            for _ in 0..300 {
+                x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                *filled.entry((x >> 8) % 1000).or_insert(0) += 1;
+            }
+            assert_valid(&filled);
+            assert!(filled.same_shape(&rbtree));
+            assert_eq!(filled.size(), keys.len());
+            for k in &keys {
+                let mut value = 0;
+                if let Entry::Occupied(mut e) = filled.entry(*k) {
+                    value = e.insert(seed);
+                }
+                assert!(value > 0);
+                assert_eq!(filled.get(k), Some(&seed));
+            }
+        }
+    }
+
+    #[test]
+    fn test_join_and_split() {
+        let (rbtree, keys) = random_tree(7, 500, 1000);
+        for at in [0_u32, 1, 250, 499, 500, 999, 1000] {
+            let (less, found, greater) = rbtree.clone().split(&at);
+            assert_valid(&less);
+            assert_valid(&greater);
+            assert_eq!(found.is_some(), keys.contains(&at));
+            assert!(keys_of(&less).iter().all(|k| k.lt(&at)));
+            assert!(keys_of(&greater).iter().all(|k| k.gt(&at)));
+            assert_eq!(
+                less.size() + greater.size() + found.iter().count(),
+                keys.len()
+            );
+
+            let joined = RedBlackTree::join(less, at, 0, greater);
+            assert_valid(&joined);
+            let mut expected = keys.clone();
+            expected.insert(at);
+            assert_eq!(keys_of(&joined), expected);
+        }
+        let small: RedBlackTree<u32, u32> = random_tree(3, 5, 10).0;
+        let (big, _) = random_tree(5, 400, 1000);
+        let (_, _, big) = big.split(&20);
+        let joined = RedBlackTree::join(small, 15, 0, big);
+        assert_valid(&joined);
+        assert_eq!(joined.get(&15), Some(&0));
+    }
+
+    #[test]
+    fn test_same_shape_and_isomorphism() {
+        let shaped = |keys: &[u32]| {
+            let mut rbtree: RedBlackTree<u32, ()> = RedBlackTree::new();
+            for k in keys {
+                rbtree.put(*k, ());
+            }
+            rbtree
+        };
+        let descending = shaped(&[5, 4, 3, 2, 1, 0]);
+        let zigzag = shaped(&[0, 5, 1, 4, 2, 3]);
+        let ascending = shaped(&[0, 1, 2, 3, 4, 5]);
+        assert!(!descending.same_shape(&zigzag));
+        assert!(descending.is_isomorphic(&zigzag));
+        assert!(!descending.is_isomorphic(&ascending));
+        assert!(ascending.same_shape(&ascending.clone().map_values(|k, _| *k)));
+        assert!(!ascending.same_shape(&RedBlackTree::<u32, ()>::new()));
+    }
+
+    #[test]
+    fn test_range_prefix() {
+        let mut rbtree: RedBlackTree<(u32, u32), u32> = RedBlackTree::new();
+        for i in 0_u32..500 {
+            rbtree.put(((i * 7919) % 20, (i * 31) % 97), i);
+        }
+        for prefix in 0_u32..22 {
+            let expected: Vec<_> = rbtree.range(..).filter(|(k, _)| k.0 == prefix).collect();
+            assert_eq!(rbtree.range_prefix(&prefix).as_slice(), expected.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_delete() {
+        let (mut rbtree, mut keys) = random_tree(21, 500, 1000);
+        let mut x = 5_u32;
+        for _ in 0..400 {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let k = (x >> 8) % 1000;
+            assert_eq!(rbtree.delete(&k).is_some(), keys.remove(&k));
+            assert_valid(&rbtree);
+            rbtree.put(k + 1000, 21);
+            keys.insert(k + 1000);
+            assert_valid(&rbtree);
+        }
+        assert!(rbtree.range(..).map(|(k, _)| k).eq(keys.iter()));
+        // smallest, largest and middle keys, down to an empty tree
+        while let Some(&k) = keys.iter().nth(keys.len() / 3_usize) {
+            for k in [
+                k,
+                *keys.iter().next().unwrap(),
+                *keys.iter().last().unwrap(),
+            ] {
+                if keys.remove(&k) {
+                    assert_eq!(rbtree.delete(&k), Some(21));
+                }
+            }
+            assert_valid(&rbtree);
+            assert_eq!(rbtree.size(), keys.len());
+        }
+        assert!(rbtree.is_empty());
+    }
+
+    #[test]
+    fn test_delete_min_and_max() {
+        let (mut rbtree, mut keys) = random_tree(22, 600, 10_000);
+        while !keys.is_empty() {
+            assert_eq!(rbtree.delete_min().map(|(k, _)| k), keys.pop_first());
+            assert_valid(&rbtree);
+            assert_eq!(rbtree.delete_max().map(|(k, _)| k), keys.pop_last());
+            assert_valid(&rbtree);
+            assert_eq!(rbtree.size(), keys.len());
+        }
+        assert_eq!(rbtree.delete_min(), None);
+        assert!(rbtree.is_empty());
+    }
+
+    #[test]
+    fn test_get_sorted() {
+        let mut tree: RedBlackTree<u32, u32> = RedBlackTree::new();
+        for i in 0_u32..1000 {
+            tree.put((i * 7919) % 1000 * 3, i);
+        }
+        let mut keys: Vec<u32> = (0_u32..600).map(|i| (i * 104_729) % 3100).collect();
+        keys.sort_unstable();
+        let expected: Vec<Option<&u32>> = keys.iter().map(|k| tree.get(k)).collect();
+        assert_eq!(tree.get_sorted(&keys), expected);
+        assert!(tree.get_sorted(&[]).is_empty());
+        assert_eq!(
+            RedBlackTree::<u32, u32>::new().get_sorted(&[1, 2]),
+            [None, None]
+        );
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        for n in [0_usize, 1, 2, 3, 8, 9, 26, 27, 100, 1000] {
+            let (mut rbtree, keys) = random_tree(n as u32 + 1, n, 5000);
+            let mut ordered: RedBlackTree<u32, u32> = RedBlackTree::new();
+            for k in keys.iter() {
+                ordered.put(*k, 0);
+            }
+            rbtree.canonicalize();
+            ordered.canonicalize();
+            assert_valid(&rbtree);
+            assert!(rbtree.same_shape(&ordered));
+            assert!(rbtree.range(..).map(|(k, _)| k).eq(keys.iter()));
+            assert_eq!(rbtree.size(), keys.len());
+        }
+    }
+
+    #[test]
+    fn test_set_operations() {
+        for (seed, n) in [(1_u32, 0_usize), (2, 10), (3, 300), (4, 1000)] {
+            let (a, a_keys) = random_tree(seed, n, 2000);
+            let (b, b_keys) = random_tree(seed + 100, 600, 2000);
+
+            let union = a.clone().union(b.clone());
+            assert_valid(&union);
+            assert_eq!(keys_of(&union), &a_keys | &b_keys);
+            assert!(a_keys.iter().all(|k| union.get(k) == Some(&seed)));
+
+            let intersection = a.clone().intersection(b.clone());
+            assert_valid(&intersection);
+            assert_eq!(keys_of(&intersection), &a_keys & &b_keys);
+            assert!(intersection.range(..).all(|(_, v)| *v == seed));
+
+            let difference = a.clone().difference(b.clone());
+            assert_valid(&difference);
+            assert_eq!(keys_of(&difference), &a_keys - &b_keys);
+
+            let difference = b.difference(a);
+            assert_valid(&difference);
+            assert_eq!(keys_of(&difference), &b_keys - &a_keys);
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_set_operations() {
+        let (a, _) = random_tree(11, 5000, 20000);
+        let (b, _) = random_tree(12, 5000, 20000);
+
+        let union = a.clone().par_union(b.clone());
+        assert_valid(&union);
+        assert_eq!(
+            union.to_sorted_vec(),
+            a.clone().union(b.clone()).to_sorted_vec()
+        );
+
+        let intersection = a.clone().par_intersection(b.clone());
+        assert_valid(&intersection);
+        assert_eq!(
+            intersection.to_sorted_vec(),
+            a.clone().intersection(b.clone()).to_sorted_vec()
+        );
+
+        let difference = a.clone().par_difference(b.clone());
+        assert_valid(&difference);
+        assert_eq!(difference.to_sorted_vec(), a.difference(b).to_sorted_vec());
+    }
+
+    #[test]
+    fn test_iter() {
+        let (rbtree, keys) = random_tree(21, 5000, 20000);
+        assert!(rbtree.iter().eq(rbtree.traverse(&Traversals::InOrder)));
+        assert!(rbtree.iter().map(|(k, _)| k).eq(keys.iter()));
+        let mut iter = rbtree.iter();
+        for seen in 0..keys.len() {
+            assert_eq!(iter.len(), keys.len() - seen);
+            // the stack holds one path, the tree is balanced
+            assert!(iter.stack.len().le(&(rbtree.height().unwrap() + 1_usize)));
+            iter.next();
+        }
+        assert_eq!(
+            (iter.next(), iter.next(), iter.len()),
+            (None, None, 0_usize)
+        );
+        let empty: RedBlackTree<u32, u32> = RedBlackTree::new();
+        assert_eq!((&empty).into_iter().next(), None);
+    }
+
+    #[test]
+    fn test_stepper_puts_as_put() {
+        let mut stepped = RedBlackTree::new();
+        let mut put = RedBlackTree::new();
+        let mut x = 7_u32;
+        let mut rotations = 0_usize;
+        for i in 0_u32..600 {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let k = (x >> 8) % 400;
+            put.put(k, i);
+            let mut stepper = Stepper::new(stepped, k, i);
+            let mut compares = 0_usize;
+            while let Some(step) = stepper.step() {
+                match step {
+                    Step::Compare(..) => compares += 1_usize,
+                    Step::RotateLeft(_) | Step::RotateRight(_) => rotations += 1_usize,
+                    _ => {}
+                }
+                // sizes hold between steps
+                let tree = stepper.tree();
+                assert_eq!(tree.size(), tree.to_sorted_vec().len());
+            }
+            assert!(stepper.is_done() && stepper.node().is_none());
+            assert!(compares.le(&(put.height().unwrap_or(0_usize) + 2_usize)));
+            stepped = stepper.into_tree();
+            assert_valid(&stepped);
+            assert_eq!(stepped.to_paren_string(), put.to_paren_string());
+        }
+        assert!(rotations.gt(&0_usize));
+
+        let mut stepper = Stepper::new(stepped, 0, 1);
+        assert_eq!(
+            stepper.node().map(|n| *n.key()),
+            put.root().map(|n| *n.key())
+        );
+        stepper.step();
+        stepper.step();
+        assert_eq!(stepper.depth(), 1_usize);
+        assert_eq!(stepper.finish().get(&0), Some(&1));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_range_for_each() {
+        use std::sync::Mutex;
+
+        let (rbtree, keys) = random_tree(13, 20000, 50000);
+        for range in [
+            (0_u32, 50000_u32),
+            (1000, 1001),
+            (7000, 31000),
+            (60000, 70000),
+        ] {
+            let seen = Mutex::new(Vec::new());
+            rbtree.par_range_for_each(range.0..range.1, |k, _| seen.lock().unwrap().push(*k));
+            let mut seen = seen.into_inner().unwrap();
+            seen.sort_unstable();
+            assert!(seen.into_iter().eq(keys.range(range.0..range.1).copied()));
+        }
+    }
 }
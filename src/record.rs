@@ -0,0 +1,251 @@
+use crate::{Map, ParseScriptError};
+use std::cell::RefCell;
+use std::fmt;
+use std::str::FromStr;
+
+/// One call on a map, as kept in a `Script`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op<K, V> {
+    Put(K, V),
+    Get(K),
+}
+
+/// Calls on a map in the order they were made, written one per line as
+/// `put`, key and value or `get` and key, separated by tabs.
+///
+/// Replaying a script on an empty tree gives the exact tree it was
+/// recorded on, shape included, so the text fits an issue report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Script<K, V> {
+    ops: Vec<Op<K, V>>,
+}
+
+impl<K, V> Script<K, V> {
+    /// Returns the recorded calls, oldest first.
+    pub fn ops(&self) -> &[Op<K, V>] {
+        &self.ops
+    }
+
+    /// Makes every call of the script on `map`.
+    pub fn replay_into<M: Map<K, V>>(&self, map: &mut M)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        for op in &self.ops {
+            match op {
+                Op::Put(k, v) => map.put(k.clone(), v.clone()),
+                Op::Get(k) => {
+                    map.get(k);
+                }
+            }
+        }
+    }
+
+    /// Makes every call of the script on an empty map and returns it.
+    pub fn replay<M: Map<K, V> + Default>(&self) -> M
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut map = M::default();
+        self.replay_into(&mut map);
+        map
+    }
+}
+
+impl<K: fmt::Display, V: fmt::Display> fmt::Display for Script<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for op in &self.ops {
+            match op {
+                Op::Put(k, v) => writeln!(f, "put\t{}\t{}", k, v)?,
+                Op::Get(k) => writeln!(f, "get\t{}", k)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K: FromStr, V: FromStr> FromStr for Script<K, V> {
+    type Err = ParseScriptError;
+
+    /// Reads a script back from its `Display` text, blank lines skipped.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ops = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let line_no = i + 1_usize;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let err = |expected| ParseScriptError::new(line_no, expected);
+            let mut fields = line.split('\t');
+            let op = fields.next().unwrap_or_default();
+            let key: K = fields
+                .next()
+                .ok_or(err("a key"))?
+                .parse()
+                .map_err(|_| err("a key"))?;
+            ops.push(match op {
+                "put" => Op::Put(
+                    key,
+                    fields
+                        .next()
+                        .ok_or(err("a value"))?
+                        .parse()
+                        .map_err(|_| err("a value"))?,
+                ),
+                "get" => Op::Get(key),
+                _ => return Err(err("`put` or `get`")),
+            });
+            if fields.next().is_some() {
+                return Err(err("end of line"));
+            }
+        }
+        Ok(Self { ops })
+    }
+}
+
+/// A map recording every `put` and `get` made through it into a `Script`.
+///
+/// Wrap the map a failing test or program builds, and the script replays
+/// the same calls on a new map, for bug reports that reproduce exactly.
+/// The trees have no `delete`, so puts and gets are all there is to
+/// record.
+///
+/// # Examples
+///
+/// ```
+/// use treers::bst::BST;
+/// use treers::record::{Recorder, Script};
+/// use treers::Map;
+///
+/// let mut recorder = Recorder::new(BST::new());
+/// recorder.put(2, 'b');
+/// recorder.put(1, 'a');
+/// assert_eq!(recorder.get(&2), Some(&'b'));
+///
+/// let text = recorder.script().to_string();
+/// assert_eq!(text, "put\t2\tb\nput\t1\ta\nget\t2\n");
+///
+/// let script: Script<u32, char> = text.parse().unwrap();
+/// let bst: BST<u32, char> = script.replay();
+/// assert_eq!(bst.to_paren_string(), recorder.get_ref().to_paren_string());
+/// ```
+#[derive(Debug, Default)]
+pub struct Recorder<K, V, M> {
+    map: M,
+    // Lookups take `&self`, so they are recorded through a `RefCell`
+    ops: RefCell<Vec<Op<K, V>>>,
+}
+
+impl<K, V, M> Recorder<K, V, M> {
+    /// Starts recording calls on `map`, skipping whatever it holds already.
+    pub const fn new(map: M) -> Self {
+        Self {
+            map,
+            ops: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Returns a reference to the recorded map.
+    pub const fn get_ref(&self) -> &M {
+        &self.map
+    }
+
+    /// Returns a copy of the calls recorded so far.
+    pub fn script(&self) -> Script<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        Script {
+            ops: self.ops.borrow().clone(),
+        }
+    }
+
+    /// Stops recording and returns the map and its script.
+    pub fn into_parts(self) -> (M, Script<K, V>) {
+        (
+            self.map,
+            Script {
+                ops: self.ops.into_inner(),
+            },
+        )
+    }
+}
+
+impl<K: Clone, V: Clone, M: Map<K, V>> Map<K, V> for Recorder<K, V, M> {
+    fn size(&self) -> usize {
+        self.map.size()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.ops.borrow_mut().push(Op::Get(key.clone()));
+        self.map.get(key)
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        self.ops.get_mut().push(Op::Put(key.clone(), value.clone()));
+        self.map.put(key, value);
+    }
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::{Op, Recorder, Script};
+    use crate::bench_support::shuffled_keys;
+    use crate::bst::BST;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use crate::{Map, OrderedMap, SedgewickMap};
+
+    #[test]
+    fn test_replay_same_tree() {
+        let keys = shuffled_keys(500, 3);
+        let mut recorder = Recorder::new(RedBlackTree::new());
+        for &k in &keys {
+            recorder.put(k % 200, k);
+            recorder.get(&(k / 2));
+        }
+        let (rbtree, script) = recorder.into_parts();
+        assert_eq!(script.ops().len(), 1000_usize);
+        assert_eq!(script.ops()[1], Op::Get(keys[0] / 2));
+
+        let replayed: Script<u64, u64> = script.to_string().parse().unwrap();
+        assert_eq!(replayed, script);
+        let again: RedBlackTree<u64, u64> = replayed.replay();
+        assert_eq!(again.to_paren_string(), rbtree.to_paren_string());
+
+        // the same calls on another tree give the same entries
+        let btree: BalancedTree<u64, u64> = script.replay();
+        assert!(btree.range(..).eq(rbtree.range(..)));
+        let mut bst = BST::new();
+        bst.put(1000_u64, 0_u64);
+        script.replay_into(&mut bst);
+        assert_eq!(bst.size(), rbtree.size() + 1_usize);
+        assert!(bst.height().gt(&rbtree.height()));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let ok: Script<u32, char> = "put\t1\ta\n\nget\t1\n".parse().unwrap();
+        assert_eq!(ok.ops(), &[Op::Put(1, 'a'), Op::Get(1)]);
+
+        let err = "put\t1\ta\ndel\t1"
+            .parse::<Script<u32, char>>()
+            .unwrap_err();
+        assert_eq!(err.line(), 2_usize);
+        assert_eq!(
+            err.to_string(),
+            "invalid script at line 2, expected `put` or `get`"
+        );
+        for (text, line) in [
+            ("put\tx\ta", 1_usize),
+            ("get\t1\nput\t2", 2),
+            ("get\t1\t2", 1),
+        ] {
+            let err = text.parse::<Script<u32, char>>().unwrap_err();
+            assert_eq!(err.line(), line);
+        }
+    }
+}
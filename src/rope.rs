@@ -0,0 +1,607 @@
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+// Most chars kept in a single leaf
+const CHUNK: usize = 64_usize;
+
+/// A rope, text kept as a binary tree of string chunks.
+///
+/// Inserting and deleting split the tree at char positions and join the
+/// pieces back, so edits cost about the depth of the tree instead of a
+/// copy of the whole text. Joins are the AVL join, heights of siblings
+/// stay within one, so the tree is never deeper than about `1.44 log n`
+/// of its chunks and `append` and `split_off` take `O(log n)` too.
+///
+/// All positions are char indices, not byte offsets.
+///
+/// # Examples
+///
+/// ```
+/// use treers::rope::Rope;
+///
+/// let mut rope = Rope::from("hello world");
+/// rope.insert(5, ",");
+/// rope.insert(12, "!");
+/// assert_eq!(rope.to_string(), "hello, world!");
+///
+/// rope.delete(0..7);
+/// assert_eq!(rope.slice(..5), "world");
+/// assert_eq!(rope.len_chars(), 6_usize);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Rope {
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf {
+        text: String,
+        chars: usize,
+    },
+    Concat {
+        chars: usize,
+        height: usize,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Node::leaf(String::new())
+    }
+}
+
+impl Node {
+    fn leaf(text: String) -> Self {
+        Node::Leaf {
+            chars: text.chars().count(),
+            text,
+        }
+    }
+
+    const fn chars(&self) -> usize {
+        match self {
+            Node::Leaf { text: _, chars } => *chars,
+            Node::Concat {
+                chars,
+                height: _,
+                left: _,
+                right: _,
+            } => *chars,
+        }
+    }
+
+    const fn height(&self) -> usize {
+        match self {
+            Node::Leaf { text: _, chars: _ } => 0_usize,
+            Node::Concat {
+                chars: _,
+                height,
+                left: _,
+                right: _,
+            } => *height,
+        }
+    }
+
+    // Concat node over `left` and `right`
+    fn branch(left: Node, right: Node) -> Node {
+        Node::Concat {
+            chars: left.chars() + right.chars(),
+            height: 1_usize + left.height().max(right.height()),
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    // Children of a concat node, a leaf is its own left child
+    fn into_children(self) -> (Node, Node) {
+        match self {
+            Node::Concat {
+                chars: _,
+                height: _,
+                left,
+                right,
+            } => (*left, *right),
+            leaf => (leaf, Node::default()),
+        }
+    }
+
+    // Neighbouring nodes under a concat node, small leaves are merged
+    fn pair(left: Node, right: Node) -> Node {
+        match (left, right) {
+            (
+                Node::Leaf {
+                    text: mut left,
+                    chars: left_chars,
+                },
+                Node::Leaf {
+                    text: right,
+                    chars: right_chars,
+                },
+            ) if (left_chars + right_chars).le(&CHUNK) => {
+                left.push_str(&right);
+                Node::Leaf {
+                    text: left,
+                    chars: left_chars + right_chars,
+                }
+            }
+            (left, right) => Node::branch(left, right),
+        }
+    }
+
+    // Joins two ropes in `O(log n)`, the AVL join: the lower tree is hung
+    // next to the node of its height on the spine of the taller one, and
+    // rotations on the way back up keep heights of siblings within one
+    fn concat(left: Node, right: Node) -> Node {
+        if left.chars().eq(&0_usize) {
+            return right;
+        }
+        if right.chars().eq(&0_usize) {
+            return left;
+        }
+        let (hl, hr) = (left.height(), right.height());
+        if hl.gt(&(hr + 1_usize)) {
+            Node::join_right(left, right)
+        } else if hr.gt(&(hl + 1_usize)) {
+            Node::join_left(left, right)
+        } else {
+            Node::pair(left, right)
+        }
+    }
+
+    // Joins `right`, lower by two or more, down the right spine of `left`
+    fn join_right(left: Node, right: Node) -> Node {
+        let (l, r) = left.into_children();
+        let t = if r.height().le(&(right.height() + 1_usize)) {
+            Node::pair(r, right)
+        } else {
+            Node::join_right(r, right)
+        };
+        if t.height().le(&(l.height() + 1_usize)) {
+            return Node::branch(l, t);
+        }
+        let (tl, tr) = t.into_children();
+        if tl.height().gt(&tr.height()) {
+            // double rotation, the inner grandchild goes up
+            let (tll, tlr) = tl.into_children();
+            Node::branch(Node::branch(l, tll), Node::branch(tlr, tr))
+        } else {
+            Node::branch(Node::branch(l, tl), tr)
+        }
+    }
+
+    // Same as `join_right`, `left` down the left spine of `right`
+    fn join_left(left: Node, right: Node) -> Node {
+        let (l, r) = right.into_children();
+        let t = if l.height().le(&(left.height() + 1_usize)) {
+            Node::pair(left, l)
+        } else {
+            Node::join_left(left, l)
+        };
+        if t.height().le(&(r.height() + 1_usize)) {
+            return Node::branch(t, r);
+        }
+        let (tl, tr) = t.into_children();
+        if tr.height().gt(&tl.height()) {
+            let (trl, trr) = tr.into_children();
+            Node::branch(Node::branch(tl, trl), Node::branch(trr, r))
+        } else {
+            Node::branch(tl, Node::branch(tr, r))
+        }
+    }
+
+    // Splits off chars before `at` from the rest, joining the pieces cut
+    // off on the way back up, `O(log n)` in all
+    fn split(self, at: usize) -> (Node, Node) {
+        match self {
+            Node::Leaf { mut text, chars } => {
+                let byte = text
+                    .char_indices()
+                    .nth(at)
+                    .map_or(text.len(), |(byte, _)| byte);
+                let rest = text.split_off(byte);
+                (
+                    Node::Leaf { text, chars: at },
+                    Node::Leaf {
+                        text: rest,
+                        chars: chars - at,
+                    },
+                )
+            }
+            Node::Concat {
+                chars: _,
+                height: _,
+                left,
+                right,
+            } => {
+                let left_chars = left.chars();
+                if at.le(&left_chars) {
+                    let (less, greater) = left.split(at);
+                    (less, Node::concat(greater, *right))
+                } else {
+                    let (less, greater) = right.split(at - left_chars);
+                    (Node::concat(*left, less), greater)
+                }
+            }
+        }
+    }
+
+    fn push_range(&self, from: usize, to: usize, out: &mut String) {
+        match self {
+            Node::Leaf { text, chars: _ } => {
+                out.extend(text.chars().skip(from).take(to - from));
+            }
+            Node::Concat {
+                chars: _,
+                height: _,
+                left,
+                right,
+            } => {
+                let left_chars = left.chars();
+                if from.lt(&left_chars) {
+                    left.push_range(from, to.min(left_chars), out);
+                }
+                if to.gt(&left_chars) {
+                    right.push_range(from.max(left_chars) - left_chars, to - left_chars, out);
+                }
+            }
+        }
+    }
+
+    // Balanced tree over chunks, middle chunk first
+    fn balanced(chunks: &mut Vec<String>) -> Node {
+        match chunks.len() {
+            0_usize => Node::default(),
+            1_usize => Node::leaf(chunks.pop().unwrap_or_default()),
+            len => {
+                let mut right = chunks.split_off(len / 2_usize);
+                let left = Node::balanced(chunks);
+                Node::concat(left, Node::balanced(&mut right))
+            }
+        }
+    }
+}
+
+// Cuts text into chunks of at most `CHUNK` chars
+fn chunks_of(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    let mut chars = 0_usize;
+    for c in text.chars() {
+        if chars.eq(&CHUNK) {
+            chunks.push(std::mem::take(&mut chunk));
+            chars = 0_usize;
+        }
+        chunk.push(c);
+        chars += 1_usize;
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+impl Rope {
+    /// Inits a new empty rope.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the count of chars in the rope.
+    pub const fn len_chars(&self) -> usize {
+        self.root.chars()
+    }
+
+    /// Checks if the rope holds no text.
+    pub fn is_empty(&self) -> bool {
+        self.root.chars().eq(&0_usize)
+    }
+
+    /// Returns the depth of the chunk tree, `0` for a single chunk.
+    pub const fn height(&self) -> usize {
+        self.root.height()
+    }
+
+    /// Inserts `text` so that it starts at char index `at`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is greater than `len_chars`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rope::Rope;
+    ///
+    /// let mut rope = Rope::from("čaj");
+    /// rope.insert(1, "ü");
+    /// rope.insert(4, "!");
+    /// assert_eq!(rope.to_string(), "čüaj!");
+    /// ```
+    pub fn insert(&mut self, at: usize, text: &str) {
+        assert!(
+            at.le(&self.len_chars()),
+            "insert index {} out of bounds for rope of {} chars",
+            at,
+            self.len_chars()
+        );
+        let (less, greater) = std::mem::take(&mut self.root).split(at);
+        let middle = Node::balanced(&mut chunks_of(text));
+        self.root = Node::concat(Node::concat(less, middle), greater);
+    }
+
+    /// Removes chars with indices inside `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range starts after it ends or ends after `len_chars`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rope::Rope;
+    ///
+    /// let mut rope = Rope::from("hello, world");
+    /// rope.delete(5..7);
+    /// assert_eq!(rope.to_string(), "helloworld");
+    /// rope.delete(5..);
+    /// assert_eq!(rope.to_string(), "hello");
+    /// ```
+    pub fn delete<R: RangeBounds<usize>>(&mut self, range: R) {
+        let (from, to) = self.bounds(&range);
+        let (less, rest) = std::mem::take(&mut self.root).split(from);
+        let (_, greater) = rest.split(to - from);
+        self.root = Node::concat(less, greater);
+    }
+
+    /// Returns chars with indices inside `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range starts after it ends or ends after `len_chars`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rope::Rope;
+    ///
+    /// let rope = Rope::from("hello, world");
+    /// assert_eq!(rope.slice(7..), "world");
+    /// assert_eq!(rope.slice(..=4), "hello");
+    /// assert_eq!(rope.slice(3..3), "");
+    /// ```
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> String {
+        let (from, to) = self.bounds(&range);
+        let mut out = String::new();
+        if from.lt(&to) {
+            self.root.push_range(from, to, &mut out);
+        }
+        out
+    }
+
+    /// Moves the text of `other` to the end of the rope, in `O(log n)`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rope::Rope;
+    ///
+    /// let mut rope = Rope::from("hello");
+    /// rope.append(Rope::from(", world"));
+    /// assert_eq!(rope.to_string(), "hello, world");
+    /// ```
+    pub fn append(&mut self, other: Rope) {
+        self.root = Node::concat(std::mem::take(&mut self.root), other.root);
+    }
+
+    /// Splits the rope at char index `at`, returning the chars from `at`
+    /// on, in `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is greater than `len_chars`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::rope::Rope;
+    ///
+    /// let mut rope = Rope::from("hello, world");
+    /// let world = rope.split_off(7);
+    /// assert_eq!((rope.to_string(), world.to_string()), ("hello, ".into(), "world".into()));
+    /// ```
+    pub fn split_off(&mut self, at: usize) -> Rope {
+        assert!(
+            at.le(&self.len_chars()),
+            "split index {} out of bounds for rope of {} chars",
+            at,
+            self.len_chars()
+        );
+        let (less, greater) = std::mem::take(&mut self.root).split(at);
+        self.root = less;
+        Self { root: greater }
+    }
+
+    fn bounds<R: RangeBounds<usize>>(&self, range: &R) -> (usize, usize) {
+        let from = match range.start_bound() {
+            Bound::Included(i) => *i,
+            Bound::Excluded(i) => i + 1_usize,
+            Bound::Unbounded => 0_usize,
+        };
+        let to = match range.end_bound() {
+            Bound::Included(i) => i + 1_usize,
+            Bound::Excluded(i) => *i,
+            Bound::Unbounded => self.len_chars(),
+        };
+        assert!(
+            from.le(&to) && to.le(&self.len_chars()),
+            "range {}..{} out of bounds for rope of {} chars",
+            from,
+            to,
+            self.len_chars()
+        );
+        (from, to)
+    }
+}
+
+impl From<&str> for Rope {
+    fn from(text: &str) -> Self {
+        Self {
+            root: Node::balanced(&mut chunks_of(text)),
+        }
+    }
+}
+
+impl fmt::Display for Rope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.slice(..))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Node, Rope, CHUNK};
+
+    // Heights of siblings within one, sizes and heights kept right;
+    // returns the count of chunks
+    fn assert_balanced(node: &Node) -> usize {
+        match node {
+            Node::Leaf { text, chars } => {
+                assert_eq!(text.chars().count(), *chars);
+                1_usize
+            }
+            Node::Concat {
+                chars,
+                height,
+                left,
+                right,
+            } => {
+                assert!(left.height().abs_diff(right.height()).le(&1_usize));
+                assert_eq!(*height, 1_usize + left.height().max(right.height()));
+                assert_eq!(*chars, left.chars() + right.chars());
+                assert_balanced(left) + assert_balanced(right)
+            }
+        }
+    }
+
+    // AVL bound, `1.44 log2(n + 2)`
+    fn avl_height(chunks: usize) -> usize {
+        (1.4405 * ((chunks + 2_usize) as f64).log2()) as usize
+    }
+
+    #[test]
+    fn test_edits_match_string() {
+        let mut rope = Rope::new();
+        let mut model: Vec<char> = Vec::new();
+        let mut x = 11_u32;
+        for step in 0_usize..3000 {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let at = (x >> 8) as usize % (model.len() + 1_usize);
+            if step % 4_usize == 3_usize {
+                let to = (at + (x >> 20) as usize % 12).min(model.len());
+                rope.delete(at..to);
+                model.drain(at..to);
+            } else {
+                let text: String = "ab€d\nfgh"
+                    .chars()
+                    .cycle()
+                    .skip(step % 8)
+                    .take(step % 13)
+                    .collect();
+                rope.insert(at, &text);
+                for (i, c) in text.chars().enumerate() {
+                    model.insert(at + i, c);
+                }
+            }
+            assert_eq!(rope.len_chars(), model.len());
+        }
+        let text: String = model.iter().collect();
+        assert_eq!(rope.to_string(), text);
+        assert_eq!(
+            rope.slice(100..200),
+            model[100..200].iter().collect::<String>()
+        );
+
+        let chunks = assert_balanced(&rope.root);
+        assert!(rope.height().le(&avl_height(chunks)));
+
+        // stays shallow for its length
+        let chunks = rope.len_chars() / CHUNK + 1_usize;
+        assert!(rope.height() <= 2_usize * (chunks.ilog2() as usize + 1_usize) + 1_usize);
+    }
+
+    #[test]
+    fn test_append_and_split_scale() {
+        // appending one chunk at a time, a rope of stacked concat nodes
+        // would get as deep as it is long
+        let mut rope = Rope::new();
+        let chunk: String = "x".repeat(CHUNK);
+        for i in 0_usize..4096 {
+            rope.append(Rope::from(chunk.as_str()));
+            if i % 512_usize == 0_usize {
+                assert!(rope.height().le(&avl_height(i + 1_usize)));
+            }
+        }
+        assert_eq!(assert_balanced(&rope.root), 4096_usize);
+        assert!(rope.height().le(&avl_height(4096)));
+
+        // prepending on the other spine
+        let mut front = Rope::new();
+        for _ in 0_usize..4096 {
+            let mut rope = Rope::from(chunk.as_str());
+            rope.append(front);
+            front = rope;
+        }
+        assert!(front.height().le(&avl_height(4096)));
+
+        // splits at every depth, then joined back
+        let len = rope.len_chars();
+        let mut x = 7_u32;
+        for _ in 0_usize..200 {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let at = (x >> 4) as usize % (len + 1_usize);
+            let rest = rope.split_off(at);
+            assert_eq!((rope.len_chars(), rest.len_chars()), (at, len - at));
+            assert!(rope.height().le(&avl_height(assert_balanced(&rope.root))));
+            assert!(rest.height().le(&avl_height(assert_balanced(&rest.root))));
+            rope.append(rest);
+            assert_balanced(&rope.root);
+        }
+        assert_eq!(rope.len_chars(), len);
+        assert!(rope.height().le(&avl_height(assert_balanced(&rope.root))));
+
+        let mut hello = Rope::from("hello, world");
+        assert_eq!(hello.split_off(12).to_string(), "");
+        assert_eq!(hello.split_off(0).to_string(), "hello, world");
+        assert!(hello.is_empty());
+    }
+
+    #[test]
+    fn test_empty_and_bounds() {
+        let mut rope = Rope::from("");
+        assert!(rope.is_empty());
+        assert_eq!(rope.slice(..), "");
+        rope.insert(0, "x");
+        rope.delete(..);
+        assert!(rope.is_empty());
+        assert_eq!(Rope::from("abc").slice(1..=1), "b");
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_slice_out_of_bounds() {
+        Rope::from("abc").slice(2..5);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::rbtree::RedBlackTree;
+use crate::Map;
+
+/// Tree Sort
+///
+/// Sorts a vector by inserting every item into a `RedBlackTree` and reading
+/// it back with an in-order traversal. The sort is stable, equal items are
+/// kept in their original order.
+///
+/// # Examples
+///
+/// ```
+/// use treers::sort::tree_sort;
+///
+/// let sorted = tree_sort(vec![5, 3, 8, 1, 3]);
+/// assert_eq!(sorted, vec![1, 3, 3, 5, 8]);
+/// ```
+pub fn tree_sort<T: Ord + Clone>(vec: Vec<T>) -> Vec<T> {
+    let mut rbtree: RedBlackTree<(T, usize), ()> = RedBlackTree::new();
+    for (i, t) in vec.into_iter().enumerate() {
+        // index makes equal items distinct, and keeps them in input order
+        rbtree.put((t, i), ());
+    }
+    rbtree.into_keys().map(|(t, _)| t).collect()
+}
+
+/// Tree Sort by key
+///
+/// Sorts a vector by the key extracted with `f`, see `tree_sort`.
+///
+/// # Examples
+///
+/// ```
+/// use treers::sort::tree_sort_by_key;
+///
+/// let sorted = tree_sort_by_key(vec!["ccc", "a", "bb", "d"], |s| s.len());
+/// assert_eq!(sorted, vec!["a", "d", "bb", "ccc"]);
+/// ```
+pub fn tree_sort_by_key<T, K, F>(vec: Vec<T>, mut f: F) -> Vec<T>
+where
+    T: Clone,
+    K: Ord + Clone,
+    F: FnMut(&T) -> K,
+{
+    let mut rbtree: RedBlackTree<(K, usize), T> = RedBlackTree::new();
+    for (i, t) in vec.into_iter().enumerate() {
+        rbtree.put((f(&t), i), t);
+    }
+    rbtree.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tree_sort, tree_sort_by_key};
+
+    #[test]
+    fn test_tree_sort_empty() {
+        let v: Vec<i32> = Vec::new();
+        assert!(tree_sort(v).is_empty());
+    }
+
+    #[test]
+    fn test_tree_sort() {
+        let mut seed = 42_u64;
+        let v: Vec<u64> = (0..1_000)
+            .map(|_| {
+                seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+                (seed >> 33) % 100
+            })
+            .collect();
+        let mut expected = v.clone();
+        expected.sort();
+        assert_eq!(tree_sort(v), expected);
+    }
+
+    #[test]
+    fn test_tree_sort_by_key_is_stable() {
+        let v = vec![(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd'), (0, 'e')];
+        let sorted = tree_sort_by_key(v, |&(k, _)| k);
+        assert_eq!(
+            sorted,
+            vec![(0, 'e'), (1, 'b'), (1, 'd'), (2, 'a'), (2, 'c')]
+        );
+    }
+}
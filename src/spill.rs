@@ -0,0 +1,399 @@
+use crate::btree::BalancedTree;
+use crate::rbtree::RedBlackTree;
+use crate::{Map, OrderedMap};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+// Tells apart spill files of maps living at the same time
+static SPILLS: AtomicUsize = AtomicUsize::new(0_usize);
+
+// Spill files below this many dead bytes are never compacted
+const COMPACT_BYTES: u64 = 4096_u64;
+
+// Offset and length of a line of the spill file
+type Line = (u64, u64);
+
+/// A map keeping its most recently used entries in memory and spilling
+/// the rest to disk.
+///
+/// Up to `hot_capacity` entries live in a `RedBlackTree`, the least
+/// recently used one is written to a spill file of JSON lines when it
+/// overflows. Every key stays in memory, spilled ones in a `BalancedTree`
+/// of their file offsets, so only values of spilled entries are kept on
+/// disk. A lookup of a spilled key reads its value back and promotes it
+/// into memory.
+///
+/// Spilled values are appended, a promoted or overwritten one is left in
+/// the file as dead bytes. Once dead bytes outgrow the live ones, the file
+/// is rewritten with live values only. It is removed when the map is
+/// dropped.
+///
+/// On an I/O error nothing is lost: a value failing to read stays
+/// spilled, an entry failing to spill stays in memory, past
+/// `hot_capacity` until a later eviction succeeds.
+///
+/// # Examples
+///
+/// ```
+/// use treers::spill::SpillMap;
+///
+/// let mut map = SpillMap::new(2).unwrap();
+/// for k in 0_u32..5 {
+///     map.put(k, k.to_string()).unwrap();
+/// }
+/// assert_eq!(map.size(), 5_usize);
+/// assert_eq!(map.hot_size(), 2_usize);
+/// assert!(!map.is_hot(&0));
+///
+/// // read back from disk and kept in memory
+/// assert_eq!(map.get(&0).unwrap(), Some(&String::from("0")));
+/// assert!(map.is_hot(&0));
+/// assert_eq!(map.get(&9).unwrap(), None);
+/// ```
+#[derive(Debug)]
+pub struct SpillMap<K: Ord + Clone, V: Clone> {
+    hot_capacity: usize,
+    // Values in memory, with the tick of their last use
+    hot: RedBlackTree<K, (V, u64)>,
+    // Keys in memory by the tick of their last use, least recent first
+    recent: RedBlackTree<u64, K>,
+    // Line of every spilled value
+    spilled: BalancedTree<K, Line>,
+    // Bytes of lines of spilled values, the rest of the file is dead
+    live_bytes: u64,
+    tick: u64,
+    file: File,
+    file_len: u64,
+    path: PathBuf,
+}
+
+impl<K, V> SpillMap<K, V>
+where
+    K: Ord + Clone + Serialize,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    /// Inits an empty map of up to `hot_capacity` entries in memory,
+    /// spilling into the system temp directory.
+    pub fn new(hot_capacity: usize) -> io::Result<Self> {
+        Self::with_temp_dir(hot_capacity, std::env::temp_dir())
+    }
+
+    /// Inits an empty map spilling into `temp_dir`.
+    pub fn with_temp_dir<P: AsRef<Path>>(hot_capacity: usize, temp_dir: P) -> io::Result<Self> {
+        let path = temp_dir.as_ref().join(format!(
+            "treers-spill-{}-{}.jsonl",
+            std::process::id(),
+            SPILLS.fetch_add(1_usize, AtomicOrdering::Relaxed)
+        ));
+        let file = open_spill(&path)?;
+        Ok(Self {
+            hot_capacity: hot_capacity.max(1_usize),
+            hot: RedBlackTree::new(),
+            recent: RedBlackTree::new(),
+            spilled: BalancedTree::new(),
+            live_bytes: 0_u64,
+            tick: 0_u64,
+            file,
+            file_len: 0_u64,
+            path,
+        })
+    }
+
+    /// Returns a count of entries, in memory and spilled.
+    pub fn size(&self) -> usize {
+        self.hot.size() + self.spilled.size()
+    }
+
+    /// Returns `true` if the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.size().eq(&0_usize)
+    }
+
+    /// Returns a count of entries in memory.
+    pub fn hot_size(&self) -> usize {
+        self.hot.size()
+    }
+
+    /// Returns `true` if the entry of `key` is in memory.
+    pub fn is_hot(&self, key: &K) -> bool {
+        self.hot.contains(key)
+    }
+
+    /// Returns `true` if the map holds `key`, without reading the disk.
+    pub fn contains(&self, key: &K) -> bool {
+        self.is_hot(key) || self.spilled.contains(key)
+    }
+
+    /// Returns the path of the spill file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the length of the spill file, dead bytes included.
+    pub const fn file_len(&self) -> u64 {
+        self.file_len
+    }
+
+    /// Puts an entry in memory, spilling the least recently used one if
+    /// it overflows.
+    pub fn put(&mut self, key: K, value: V) -> io::Result<()> {
+        if let Some((_, tick)) = self.hot.delete(&key) {
+            self.recent.delete(&tick);
+        } else if let Some((_, len)) = self.spilled.delete(&key) {
+            self.live_bytes -= len;
+        }
+        self.promote(key, value)?;
+        self.compact()
+    }
+
+    /// Returns the value of `key`, reading it from the spill file and
+    /// promoting it into memory if it was spilled.
+    pub fn get(&mut self, key: &K) -> io::Result<Option<&V>> {
+        if let Some((value, tick)) = self.hot.delete(key) {
+            self.recent.delete(&tick);
+            self.promote(key.clone(), value)?;
+        } else if let Some(&(offset, len)) = self.spilled.get(key) {
+            // the key stays spilled until its value is read
+            let value = self.read_at(offset, len)?;
+            self.spilled.delete(key);
+            self.live_bytes -= len;
+            self.promote(key.clone(), value)?;
+            self.compact()?;
+        }
+        Ok(self.hot.get(key).map(|(v, _)| v))
+    }
+
+    // Puts an entry as the most recently used one, evicting least
+    // recently used ones beyond `hot_capacity`
+    fn promote(&mut self, key: K, value: V) -> io::Result<()> {
+        self.tick += 1_u64;
+        self.recent.put(self.tick, key.clone());
+        self.hot.put(key, (value, self.tick));
+        while self.hot.size().gt(&self.hot_capacity) {
+            let oldest = match self.recent.min() {
+                Some(&oldest) => oldest,
+                None => break,
+            };
+            let key = match self.recent.get(&oldest) {
+                Some(key) => key.clone(),
+                None => break,
+            };
+            // written out first, an entry failing to spill stays in memory
+            if let Some((value, _)) = self.hot.get(&key) {
+                let line = serde_json::to_vec(value)?;
+                self.spill(key.clone(), line)?;
+            }
+            self.recent.delete(&oldest);
+            self.hot.delete(&key);
+        }
+        Ok(())
+    }
+
+    fn spill(&mut self, key: K, mut line: Vec<u8>) -> io::Result<()> {
+        line.push(b'\n');
+        if let Err(e) = self.file.write_all(&line) {
+            // a partly written line is dead bytes
+            self.file_len = self.file.metadata().map_or(self.file_len, |m| m.len());
+            return Err(e);
+        }
+        let len = line.len() as u64;
+        self.spilled.put(key, (self.file_len, len));
+        self.file_len += len;
+        self.live_bytes += len;
+        Ok(())
+    }
+
+    fn read_at(&self, offset: u64, len: u64) -> io::Result<V> {
+        Ok(serde_json::from_slice(&read_line(
+            &self.file, offset, len,
+        )?)?)
+    }
+
+    // Rewrites the spill file with live values only, once dead bytes
+    // outgrow them; on an error the old file is kept
+    fn compact(&mut self) -> io::Result<()> {
+        let dead = self.file_len - self.live_bytes;
+        if dead.lt(&COMPACT_BYTES) || dead.le(&self.live_bytes) {
+            return Ok(());
+        }
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".compact");
+        let tmp = PathBuf::from(tmp);
+        let moved = self.rewrite(&tmp);
+        match moved {
+            Ok((file, offsets)) => {
+                for (key, offset) in offsets {
+                    self.spilled.put(key, offset);
+                }
+                self.file = file;
+                self.file_len = self.live_bytes;
+                Ok(())
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&tmp);
+                Err(e)
+            }
+        }
+    }
+
+    fn rewrite(&self, tmp: &Path) -> io::Result<(File, Vec<(K, Line)>)> {
+        let mut file = open_spill(tmp)?;
+        let mut offsets = Vec::with_capacity(self.spilled.size());
+        let mut written = 0_u64;
+        for (key, &(offset, len)) in self.spilled.iter() {
+            file.write_all(&read_line(&self.file, offset, len)?)?;
+            offsets.push((key.clone(), (written, len)));
+            written += len;
+        }
+        fs::rename(tmp, &self.path)?;
+        Ok((file, offsets))
+    }
+}
+
+fn open_spill(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create_new(true)
+        .open(path)
+}
+
+fn read_line(file: &File, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    let mut file = file;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut line = vec![0_u8; len as usize];
+    file.read_exact(&mut line)?;
+    Ok(line)
+}
+
+impl<K: Ord + Clone, V: Clone> Drop for SpillMap<K, V> {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SpillMap, COMPACT_BYTES};
+    use crate::bench_support::shuffled_keys;
+    use crate::Map;
+    use std::collections::BTreeMap;
+    use std::fs::{File, OpenOptions};
+
+    #[test]
+    fn test_spill_and_promote() {
+        let mut map = SpillMap::new(64).unwrap();
+        let mut expected = BTreeMap::new();
+        for k in shuffled_keys(2000, 5) {
+            map.put(k % 500, k).unwrap();
+            expected.insert(k % 500, k);
+        }
+        assert_eq!(map.size(), expected.len());
+        assert_eq!(map.hot_size(), 64_usize);
+        let path = map.path().to_path_buf();
+        assert!(path.exists());
+
+        // every value comes back, spilled ones promoted on the way
+        for (k, v) in &expected {
+            assert!(map.contains(k));
+            assert_eq!(map.get(k).unwrap(), Some(v));
+            assert!(map.is_hot(k));
+            assert_eq!(map.size(), expected.len());
+        }
+        assert_eq!(map.get(&500).unwrap(), None);
+        assert!(!map.contains(&500));
+
+        // overwriting a spilled key drops its spilled value
+        assert!(!map.is_hot(&0));
+        map.put(0, 7).unwrap();
+        assert_eq!(map.size(), expected.len());
+        assert_eq!(map.get(&0).unwrap(), Some(&7));
+
+        drop(map);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_recent_stay_hot() {
+        let mut map = SpillMap::new(3).unwrap();
+        for k in 0_u32..3 {
+            map.put(k, k).unwrap();
+        }
+        // a lookup makes 0 the most recent, so 1 spills
+        assert_eq!(map.get(&0).unwrap(), Some(&0));
+        map.put(3, 3).unwrap();
+        assert!(map.is_hot(&0) && !map.is_hot(&1));
+        assert!(map.is_hot(&2) && map.is_hot(&3));
+
+        assert!(SpillMap::<u32, u32>::with_temp_dir(3, "/nonexistent/treers").is_err());
+    }
+
+    // Reopens the spill file of `map` with reads or writes failing
+    fn break_file(map: &mut SpillMap<u32, u32>, read: bool) -> File {
+        let broken = OpenOptions::new()
+            .read(!read)
+            .append(read)
+            .open(map.path())
+            .unwrap();
+        std::mem::replace(&mut map.file, broken)
+    }
+
+    #[test]
+    fn test_failed_read_keeps_value() {
+        let mut map = SpillMap::new(2).unwrap();
+        for k in 0_u32..4 {
+            map.put(k, k * 10).unwrap();
+        }
+        let file = break_file(&mut map, true);
+        assert!(map.get(&0).is_err());
+        assert!(map.contains(&0) && !map.is_hot(&0));
+        assert_eq!(map.size(), 4_usize);
+
+        map.file = file;
+        assert_eq!(map.get(&0).unwrap(), Some(&0));
+        assert_eq!(map.size(), 4_usize);
+    }
+
+    #[test]
+    fn test_failed_spill_keeps_entry() {
+        let mut map = SpillMap::new(2).unwrap();
+        for k in 0_u32..2 {
+            map.put(k, k * 10).unwrap();
+        }
+        let file = break_file(&mut map, false);
+        assert!(map.put(2, 20).is_err());
+        // over capacity, nothing spilled
+        assert_eq!((map.size(), map.hot_size()), (3_usize, 3_usize));
+        assert!(map.is_hot(&0));
+
+        map.file = file;
+        map.put(3, 30).unwrap();
+        assert_eq!((map.size(), map.hot_size()), (4_usize, 2_usize));
+        for k in 0_u32..4 {
+            assert_eq!(map.get(&k).unwrap(), Some(&(k * 10)));
+        }
+    }
+
+    #[test]
+    fn test_churn_is_compacted() {
+        let mut map = SpillMap::new(16).unwrap();
+        let mut expected = BTreeMap::new();
+        for k in shuffled_keys(20_000, 6) {
+            map.put(k % 200, k).unwrap();
+            expected.insert(k % 200, k);
+            assert_eq!(map.get(&(k % 211)).unwrap(), expected.get(&(k % 211)));
+        }
+        // promoted keys are dropped from the spilled ones
+        assert_eq!(map.spilled.size() + map.hot_size(), 200_usize);
+        assert!(map.file_len().le(&(2_u64 * map.live_bytes + COMPACT_BYTES)));
+        assert_eq!(map.file.metadata().unwrap().len(), map.file_len());
+
+        for (k, v) in &expected {
+            assert_eq!(map.get(k).unwrap(), Some(v));
+        }
+    }
+}
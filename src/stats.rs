@@ -0,0 +1,375 @@
+use std::cmp::Ordering;
+
+type Link<T> = Option<Box<Node<T>>>;
+
+/// Samples kept in order, answering quantiles, cumulative fractions and
+/// histograms in `O(log d)` a query for `d` distinct samples.
+///
+/// Every distinct sample is one node of an AVL tree holding how many times
+/// it was inserted, and every node the total count of its subtree. Totals
+/// give the weighted rank of a value and the sample of a weighted rank on
+/// one path down, so a sample observed a million times costs one node.
+///
+/// # Examples
+///
+/// ```
+/// use treers::stats::Quantiles;
+///
+/// let mut latencies = Quantiles::new();
+/// for ms in [12_u32, 7, 30, 7, 9, 250, 11, 8, 10, 13] {
+///     latencies.insert(ms);
+/// }
+/// assert_eq!(latencies.quantile(0.5), Some(&10));
+/// assert_eq!(latencies.quantile(0.9), Some(&30));
+/// assert_eq!(latencies.quantile(1.0), Some(&250));
+/// assert_eq!(latencies.cdf(&10), 0.5);
+/// // below 10ms, 10ms up to 100ms, from 100ms on
+/// assert_eq!(latencies.histogram(&[10, 100]), [4, 5, 1]);
+///
+/// // a million more requests at 9ms, still one node
+/// latencies.insert_weighted(9, 1_000_000);
+/// assert_eq!((latencies.size(), latencies.distinct()), (1_000_010, 9));
+/// assert_eq!(latencies.rank_weighted(&9), 3);
+/// assert_eq!(latencies.select_weighted(3), Some(&9));
+/// assert_eq!(latencies.select_weighted(1_000_003), Some(&9));
+/// assert_eq!(latencies.select_weighted(1_000_004), Some(&10));
+/// assert_eq!(latencies.quantile(0.5), Some(&9));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Quantiles<T: Ord> {
+    root: Link<T>,
+    distinct: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    sample: T,
+    // Times the sample was inserted
+    count: usize,
+    // Sum of the counts of the subtree
+    total: usize,
+    // Nodes on the longest path down, `1` for a leaf
+    height: usize,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+impl<T: Ord> Quantiles<T> {
+    /// Inits an empty sketch.
+    pub const fn new() -> Self {
+        Self {
+            root: None,
+            distinct: 0_usize,
+        }
+    }
+
+    /// Returns a count of samples, repeats included.
+    pub const fn size(&self) -> usize {
+        total(&self.root)
+    }
+
+    /// Returns a count of distinct samples, the nodes kept.
+    pub const fn distinct(&self) -> usize {
+        self.distinct
+    }
+
+    /// Returns `true` if there are no samples.
+    pub const fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Adds a sample.
+    pub fn insert(&mut self, sample: T) {
+        self.insert_weighted(sample, 1_usize);
+    }
+
+    /// Adds `weight` samples equal to `sample` at once, a weight of `0`
+    /// adding nothing.
+    pub fn insert_weighted(&mut self, sample: T, weight: usize) {
+        if weight.eq(&0_usize) {
+            return;
+        }
+        let mut added = false;
+        self.root = Some(insert(self.root.take(), sample, weight, &mut added));
+        if added {
+            self.distinct += 1_usize;
+        }
+    }
+
+    /// Returns the sample of quantile `q`, the smallest one with at least
+    /// a `q` fraction of samples less than or equal to it.
+    ///
+    /// `None` if there are no samples or `q` is outside `0.0..=1.0`.
+    pub fn quantile(&self, q: f64) -> Option<&T> {
+        if !(0.0_f64..=1.0_f64).contains(&q) || self.is_empty() {
+            return None;
+        }
+        // nearest rank, counts of samples fit into `f64` exactly
+        let rank = (q * self.size() as f64).ceil() as usize;
+        self.select_weighted(rank.saturating_sub(1_usize))
+    }
+
+    /// Returns the fraction of samples less than or equal to `x`, `0.0`
+    /// without samples.
+    pub fn cdf(&self, x: &T) -> f64 {
+        if self.is_empty() {
+            return 0.0_f64;
+        }
+        self.rank(x, true) as f64 / self.size() as f64
+    }
+
+    /// Returns counts of samples in buckets between ascending `edges`: below
+    /// the first edge, from each edge up to the next and from the last one
+    /// on, one bucket more than edges.
+    pub fn histogram(&self, edges: &[T]) -> Vec<usize> {
+        let mut below = 0_usize;
+        let mut counts = Vec::with_capacity(edges.len() + 1_usize);
+        for edge in edges {
+            let rank = self.rank(edge, false);
+            counts.push(rank.saturating_sub(below));
+            below = below.max(rank);
+        }
+        counts.push(self.size() - below);
+        counts
+    }
+
+    /// Returns a count of samples less than `x`, each weighing as many
+    /// times as it was inserted.
+    pub fn rank_weighted(&self, x: &T) -> usize {
+        self.rank(x, false)
+    }
+
+    /// Returns the sample at position `i` of the samples in order, repeats
+    /// included, so one inserted with weight `w` fills `w` positions.
+    ///
+    /// `None` if `i` is not less than `size()`.
+    pub fn select_weighted(&self, mut i: usize) -> Option<&T> {
+        let mut node = &self.root;
+        while let Some(n) = node {
+            let left = total(&n.left);
+            if i.lt(&left) {
+                node = &n.left;
+            } else if i.lt(&(left + n.count)) {
+                return Some(&n.sample);
+            } else {
+                i -= left + n.count;
+                node = &n.right;
+            }
+        }
+        None
+    }
+
+    // Count of samples less than `x`, or equal to it if `inclusive`
+    fn rank(&self, x: &T, inclusive: bool) -> usize {
+        let mut rank = 0_usize;
+        let mut node = &self.root;
+        while let Some(n) = node {
+            match n.sample.cmp(x) {
+                Ordering::Less => {
+                    rank += total(&n.left) + n.count;
+                    node = &n.right;
+                }
+                Ordering::Equal => {
+                    rank += total(&n.left);
+                    if inclusive {
+                        rank += n.count;
+                    }
+                    return rank;
+                }
+                Ordering::Greater => node = &n.left,
+            }
+        }
+        rank
+    }
+}
+
+impl<T: Ord> Default for Quantiles<T> {
+    /// Creates an empty `Quantiles<T>`.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> Extend<T> for Quantiles<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for sample in iter {
+            self.insert(sample);
+        }
+    }
+}
+
+const fn height<T>(link: &Link<T>) -> usize {
+    match link {
+        Some(node) => node.height,
+        None => 0_usize,
+    }
+}
+
+const fn total<T>(link: &Link<T>) -> usize {
+    match link {
+        Some(node) => node.total,
+        None => 0_usize,
+    }
+}
+
+// Recomputes height and total of `node` from its children
+fn fix<T>(node: &mut Node<T>) {
+    node.height = 1_usize + height(&node.left).max(height(&node.right));
+    node.total = node.count + total(&node.left) + total(&node.right);
+}
+
+fn rotate_left<T>(mut node: Box<Node<T>>) -> Box<Node<T>> {
+    match node.right.take() {
+        Some(mut right) => {
+            node.right = right.left.take();
+            fix(&mut node);
+            right.left = Some(node);
+            fix(&mut right);
+            right
+        }
+        None => node,
+    }
+}
+
+fn rotate_right<T>(mut node: Box<Node<T>>) -> Box<Node<T>> {
+    match node.left.take() {
+        Some(mut left) => {
+            node.left = left.right.take();
+            fix(&mut node);
+            left.right = Some(node);
+            fix(&mut left);
+            left
+        }
+        None => node,
+    }
+}
+
+// Restores the AVL property at `node`, its subtrees differing by 2 at most
+fn balance<T>(mut node: Box<Node<T>>) -> Box<Node<T>> {
+    fix(&mut node);
+    let (left, right) = (height(&node.left), height(&node.right));
+    if left.gt(&(right + 1_usize)) {
+        if let Some(l) = node.left.take() {
+            node.left = Some(if height(&l.left).lt(&height(&l.right)) {
+                rotate_left(l)
+            } else {
+                l
+            });
+        }
+        rotate_right(node)
+    } else if right.gt(&(left + 1_usize)) {
+        if let Some(r) = node.right.take() {
+            node.right = Some(if height(&r.right).lt(&height(&r.left)) {
+                rotate_right(r)
+            } else {
+                r
+            });
+        }
+        rotate_left(node)
+    } else {
+        node
+    }
+}
+
+fn insert<T: Ord>(link: Link<T>, sample: T, weight: usize, added: &mut bool) -> Box<Node<T>> {
+    let mut node = match link {
+        Some(node) => node,
+        None => {
+            *added = true;
+            return Box::new(Node {
+                sample,
+                count: weight,
+                total: weight,
+                height: 1_usize,
+                left: None,
+                right: None,
+            });
+        }
+    };
+    match sample.cmp(&node.sample) {
+        Ordering::Less => node.left = Some(insert(node.left.take(), sample, weight, added)),
+        Ordering::Greater => node.right = Some(insert(node.right.take(), sample, weight, added)),
+        Ordering::Equal => node.count += weight,
+    }
+    balance(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Quantiles;
+    use crate::bench_support::shuffled_keys;
+
+    #[test]
+    fn test_against_sorted_samples() {
+        let mut quantiles = Quantiles::default();
+        // few distinct values, so most samples repeat
+        let samples: Vec<u64> = shuffled_keys(1000, 13).iter().map(|k| k % 97).collect();
+        quantiles.extend(samples.iter().copied());
+        let mut sorted = samples.clone();
+        sorted.sort_unstable();
+        assert_eq!(quantiles.size(), sorted.len());
+
+        for i in 0_usize..=100 {
+            let q = i as f64 / 100.0_f64;
+            let rank = ((q * 1000.0_f64).ceil() as usize).max(1_usize);
+            assert_eq!(quantiles.quantile(q), Some(&sorted[rank - 1_usize]));
+        }
+        for x in 0_u64..100 {
+            let le = sorted.iter().filter(|s| s.le(&&x)).count();
+            assert_eq!(quantiles.cdf(&x), le as f64 / 1000.0_f64);
+        }
+        let counts = quantiles.histogram(&[10, 50, 50, 96]);
+        assert_eq!(counts.iter().sum::<usize>(), 1000_usize);
+        assert_eq!(counts[2], 0_usize);
+        assert_eq!(
+            counts[1],
+            sorted.iter().filter(|s| (10..50).contains(*s)).count()
+        );
+
+        assert_eq!(
+            (quantiles.quantile(-0.1), quantiles.quantile(1.5)),
+            (None, None)
+        );
+        let empty: Quantiles<u64> = Quantiles::new();
+        assert_eq!((empty.quantile(0.5), empty.cdf(&1)), (None, 0.0));
+        assert_eq!(empty.histogram(&[1]), [0, 0]);
+    }
+
+    #[test]
+    fn test_weighted_against_expanded_samples() {
+        let mut quantiles = Quantiles::new();
+        let mut expanded = Vec::new();
+        for key in shuffled_keys(300, 29) {
+            let (sample, weight) = (key % 50, (key % 7) as usize);
+            quantiles.insert_weighted(sample, weight);
+            expanded.resize(expanded.len() + weight, sample);
+        }
+        expanded.sort_unstable();
+        let mut distinct = expanded.clone();
+        distinct.dedup();
+        assert_eq!(quantiles.size(), expanded.len());
+        assert_eq!(quantiles.distinct(), distinct.len());
+
+        for (i, sample) in expanded.iter().enumerate() {
+            assert_eq!(quantiles.select_weighted(i), Some(sample));
+        }
+        assert_eq!(quantiles.select_weighted(expanded.len()), None);
+        for x in 0_u64..=50 {
+            let below = expanded.iter().filter(|s| s.lt(&&x)).count();
+            assert_eq!(quantiles.rank_weighted(&x), below);
+        }
+        for i in 0_usize..=100 {
+            let q = i as f64 / 100.0_f64;
+            let rank = ((q * expanded.len() as f64).ceil() as usize).max(1_usize);
+            assert_eq!(quantiles.quantile(q), Some(&expanded[rank - 1_usize]));
+        }
+
+        let mut empty = Quantiles::new();
+        empty.insert_weighted(1_u64, 0_usize);
+        assert!(empty.is_empty());
+        assert_eq!(
+            (empty.rank_weighted(&1), empty.select_weighted(0)),
+            (0, None)
+        );
+    }
+}
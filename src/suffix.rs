@@ -0,0 +1,189 @@
+/// 6.3 Suffix Array
+///
+/// Suffixes of a text in sorted order, with the longest common prefix of
+/// every pair of neighbours, as in Robert Sedgewick book, "Algorithms"
+/// 4th edition. Suffixes start at char boundaries and compare as bytes,
+/// which for UTF-8 is the same as comparing chars.
+///
+/// # Examples
+///
+/// ```
+/// use treers::suffix::SuffixArray;
+///
+/// let sa = SuffixArray::new("abracadabra");
+/// assert!(sa.contains_substring("cad"));
+/// assert!(!sa.contains_substring("dab!"));
+/// assert_eq!(sa.longest_repeated_substring(), "abra");
+/// assert_eq!(sa.select(0), "a");
+/// assert_eq!(sa.rank("b"), 5_usize);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SuffixArray<'a> {
+    text: &'a str,
+    suffixes: Vec<usize>,
+    lcp: Vec<usize>,
+}
+
+impl<'a> SuffixArray<'a> {
+    /// Sorts suffixes of `text`.
+    pub fn new(text: &'a str) -> Self {
+        let bytes = text.as_bytes();
+        let mut suffixes: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+        suffixes.sort_unstable_by(|a, b| bytes[*a..].cmp(&bytes[*b..]));
+        let lcp = (0..suffixes.len())
+            .map(|i| {
+                if i.eq(&0_usize) {
+                    0_usize
+                } else {
+                    common_prefix(text, suffixes[i - 1], suffixes[i])
+                }
+            })
+            .collect();
+        Self {
+            text,
+            suffixes,
+            lcp,
+        }
+    }
+
+    /// Returns the count of suffixes, same as the count of chars.
+    pub const fn len(&self) -> usize {
+        self.suffixes.len()
+    }
+
+    /// Checks if the text is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.suffixes.is_empty()
+    }
+
+    /// Returns the byte offset in the text of the `i`th smallest suffix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not less than `len`.
+    pub fn index(&self, i: usize) -> usize {
+        self.suffixes[i]
+    }
+
+    /// Returns the `i`th smallest suffix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not less than `len`.
+    pub fn select(&self, i: usize) -> &'a str {
+        &self.text[self.suffixes[i]..]
+    }
+
+    /// Returns the length in bytes of the longest common prefix of the
+    /// `i`th smallest suffix and the one before it, `0` for the first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not less than `len`.
+    pub fn lcp(&self, i: usize) -> usize {
+        self.lcp[i]
+    }
+
+    /// Returns the count of suffixes less than `query`.
+    pub fn rank(&self, query: &str) -> usize {
+        self.suffixes
+            .partition_point(|s| self.text.as_bytes()[*s..].lt(query.as_bytes()))
+    }
+
+    /// Checks if `pattern` occurs anywhere in the text.
+    ///
+    /// Suffixes starting with `pattern` are neighbours in the array, so a
+    /// binary search finds the first of them.
+    pub fn contains_substring(&self, pattern: &str) -> bool {
+        let i = self.rank(pattern);
+        i.lt(&self.len()) && self.select(i).starts_with(pattern)
+    }
+
+    /// Returns the longest substring occurring at least twice, occurrences
+    /// may overlap. The leftmost in suffix order is returned on ties.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use treers::suffix::SuffixArray;
+    ///
+    /// assert_eq!(SuffixArray::new("aaaa").longest_repeated_substring(), "aaa");
+    /// assert_eq!(SuffixArray::new("abc").longest_repeated_substring(), "");
+    /// assert_eq!(SuffixArray::new("čaj i čaj").longest_repeated_substring(), "čaj");
+    /// ```
+    pub fn longest_repeated_substring(&self) -> &'a str {
+        let mut best = 0_usize;
+        for i in 1_usize..self.len() {
+            if self.lcp[i].gt(&self.lcp[best]) {
+                best = i;
+            }
+        }
+        if self.is_empty() {
+            return "";
+        }
+        let start = self.suffixes[best];
+        &self.text[start..start + self.lcp[best]]
+    }
+}
+
+// Common prefix of two suffixes in bytes, cut back to a char boundary
+fn common_prefix(text: &str, a: usize, b: usize) -> usize {
+    let bytes = text.as_bytes();
+    let mut len = bytes[a..]
+        .iter()
+        .zip(bytes[b..].iter())
+        .take_while(|(x, y)| x.eq(y))
+        .count();
+    while !text.is_char_boundary(a + len) {
+        len -= 1_usize;
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SuffixArray;
+
+    #[test]
+    fn test_suffixes_sorted() {
+        let text = "it was the best of times it was the worst of times";
+        let sa = SuffixArray::new(text);
+        assert_eq!(sa.len(), text.len());
+        for i in 1..sa.len() {
+            assert!(sa.select(i - 1) < sa.select(i));
+            let common = sa
+                .select(i - 1)
+                .bytes()
+                .zip(sa.select(i).bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            assert_eq!(sa.lcp(i), common);
+            assert_eq!(sa.rank(sa.select(i)), i);
+            assert_eq!(&text[sa.index(i)..], sa.select(i));
+        }
+        // ties with "st of times", which sorts later
+        assert_eq!(sa.longest_repeated_substring(), "it was the ");
+    }
+
+    #[test]
+    fn test_contains_substring() {
+        let text = "mississippi ünïcödé";
+        let sa = SuffixArray::new(text);
+        for (i, _) in text.char_indices() {
+            for (j, _) in text[i..].char_indices().skip(1) {
+                assert!(sa.contains_substring(&text[i..i + j]));
+            }
+        }
+        for missing in ["ssm", "pii", "ïu", "é!", "z"] {
+            assert!(!sa.contains_substring(missing));
+        }
+        assert!(sa.contains_substring(""));
+
+        let empty = SuffixArray::new("");
+        assert!(empty.is_empty());
+        assert!(!empty.contains_substring("a"));
+        assert_eq!(empty.longest_repeated_substring(), "");
+    }
+}
@@ -0,0 +1,157 @@
+use crate::btree::M;
+use crate::concurrent::ConcurrentTree;
+use loom::model::Builder;
+use loom::sync::Arc;
+use loom::thread;
+
+// Preemptions per interleaving, enough for any race of two latches and
+// small enough that a scenario checks in seconds
+const PREEMPTIONS: usize = 2_usize;
+
+/// Checks `f` under every interleaving of its loom threads, up to a bound
+/// of preemptions.
+///
+/// Built with `RUSTFLAGS="--cfg loom"`, the latches of `ConcurrentTree`
+/// are the ones of loom, so interleavings inside tree operations are
+/// explored too, and a tree only works inside `model`; pass the flag for
+/// model checking test runs alone. Without it every tree operation is a
+/// single step of the model.
+pub fn model<F: Fn() + Sync + Send + 'static>(f: F) {
+    let mut builder = Builder::new();
+    builder.preemption_bound = Some(PREEMPTIONS);
+    builder.check(f);
+}
+
+/// Shares a tree made by `tree` between `threads` loom threads, thread `i`
+/// calling `f(&tree, i)`, then hands the tree to `check` once all of them
+/// are joined, under every interleaving `model` explores.
+///
+/// # Examples
+///
+/// ```
+/// use treers::concurrent::ConcurrentTree;
+/// use treers::testing::loom;
+///
+/// // integration code of a downstream crate, a counter per key
+/// fn bump(tree: &ConcurrentTree<u32, u32>, key: u32) {
+///     let n = tree.get(&key).unwrap_or_default();
+///     tree.insert(key, n + 1);
+/// }
+///
+/// loom::shared_tree(
+///     ConcurrentTree::new,
+///     2,
+///     |tree, i| bump(tree, i as u32),
+///     |tree| assert_eq!(tree.into_sorted_vec(), vec![(0, 1), (1, 1)]),
+/// );
+/// ```
+pub fn shared_tree<K, V, T, F, C>(tree: T, threads: usize, f: F, check: C)
+where
+    K: Ord + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+    T: Fn() -> ConcurrentTree<K, V> + Sync + Send + 'static,
+    F: Fn(&ConcurrentTree<K, V>, usize) + Sync + Send + 'static,
+    C: Fn(ConcurrentTree<K, V>) + Sync + Send + 'static,
+{
+    // the closures are no state of the model, so a std `Arc` shares them
+    let f = std::sync::Arc::new(f);
+    model(move || {
+        let shared = Arc::new(tree());
+        let handles: Vec<thread::JoinHandle<()>> = (0..threads)
+            .map(|i| {
+                let shared = Arc::clone(&shared);
+                let f = std::sync::Arc::clone(&f);
+                thread::spawn(move || f(&shared, i))
+            })
+            .collect();
+        for handle in handles {
+            if let Err(panic) = handle.join() {
+                std::panic::resume_unwind(panic);
+            }
+        }
+        match Arc::try_unwrap(shared) {
+            Ok(tree) => check(tree),
+            Err(_) => unreachable!(),
+        }
+    });
+}
+
+/// Two writers put different keys while a reader gets both: the reader
+/// sees every key missing or with its value, and both are there after.
+pub fn put_get_race() {
+    shared_tree(
+        ConcurrentTree::new,
+        3_usize,
+        |tree, i| match i {
+            0 | 1 => {
+                tree.insert(i as u32, i);
+            }
+            _ => {
+                for k in 0_u32..2 {
+                    let seen = tree.get(&k);
+                    assert!(seen.is_none_or(|v| v.eq(&(k as usize))));
+                }
+            }
+        },
+        |tree| assert_eq!(tree.into_sorted_vec(), vec![(0, 0), (1, 1)]),
+    );
+}
+
+/// Two writers fill a full root leaf from both ends, so one of them
+/// splits it under the other and under a reader of a key already there.
+pub fn split_race() {
+    shared_tree(
+        || {
+            let tree = ConcurrentTree::with_fanout(M);
+            for k in 1_u32..=M as u32 {
+                tree.insert(k * 10, k);
+            }
+            tree
+        },
+        3_usize,
+        |tree, i| match i {
+            0 => {
+                tree.insert(0, 0);
+            }
+            1 => {
+                tree.insert(99, 0);
+            }
+            _ => assert_eq!(tree.get(&20), Some(2)),
+        },
+        |tree| {
+            assert_eq!(tree.size(), M + 2_usize);
+            let keys: Vec<u32> = tree.into_sorted_vec().into_iter().map(|(k, _)| k).collect();
+            assert!(keys.windows(2).all(|w| w[0].lt(&w[1])));
+        },
+    );
+}
+
+/// Two writers put the same key: exactly one of them finds it missing,
+/// and the tree keeps one entry of it.
+pub fn same_key_race() {
+    shared_tree(
+        ConcurrentTree::new,
+        2_usize,
+        |tree, i| {
+            if tree.insert(0, i).is_none() {
+                // a marker of the writer that came first
+                tree.insert(10 + i as u32, i);
+            }
+        },
+        |tree| {
+            let entries = tree.into_sorted_vec();
+            assert_eq!(entries.len(), 2_usize);
+            assert!(entries[0].1.lt(&2_usize));
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_scenarios() {
+        super::put_get_race();
+        super::split_race();
+        super::same_key_race();
+    }
+}
@@ -0,0 +1,237 @@
+use crate::bst::BST;
+use crate::entry::Entry;
+use crate::{Map, OrderedMap, SedgewickMap};
+use std::fmt;
+use std::ops::RangeBounds;
+
+/// A `BST` deleting lazily, a deleted key keeps its node as a tombstone.
+///
+/// `delete` only marks the node, queries skip the marked ones and `put`
+/// revives them, so deletes never restructure the tree. `compact` then
+/// rebuilds it balanced, without the tombstones, e.g. once `tombstones`
+/// outgrow the live entries.
+///
+/// # Examples
+///
+/// ```
+/// use treers::tombstone::Tombstoned;
+/// use treers::{Map, OrderedMap, SedgewickMap};
+///
+/// let mut map = Tombstoned::new();
+/// for k in 1..=5 {
+///     map.put(k, k * 10);
+/// }
+/// assert_eq!(map.delete(&1), Some(10));
+/// assert_eq!(map.delete(&3), Some(30));
+/// assert_eq!(map.delete(&3), None);
+/// assert_eq!((map.size(), map.tombstones()), (3_usize, 2_usize));
+/// assert_eq!((map.min(), map.floor(&3)), (Some(&2), Some(&2)));
+///
+/// map.compact();
+/// assert_eq!((map.size(), map.tombstones()), (3_usize, 0_usize));
+/// assert_eq!(map.get_ref().height(), Some(1_usize));
+/// ```
+pub struct Tombstoned<K: Ord, V> {
+    tree: BST<K, Option<V>>,
+    live: usize,
+}
+
+impl<K: Ord, V> Tombstoned<K, V> {
+    /// Inits a new instance without entries.
+    pub const fn new() -> Self {
+        Self {
+            tree: BST::new(),
+            live: 0_usize,
+        }
+    }
+
+    /// Marks the node of `key` deleted, returning its value if it was
+    /// live.
+    pub fn delete(&mut self, key: &K) -> Option<V>
+    where
+        K: Clone,
+    {
+        match self.tree.entry(key.clone()) {
+            Entry::Occupied(mut e) => {
+                let v = e.get_mut().take()?;
+                self.live -= 1_usize;
+                Some(v)
+            }
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    /// Returns a count of nodes marked deleted.
+    pub fn tombstones(&self) -> usize {
+        self.tree.size() - self.live
+    }
+
+    /// Rebuilds the tree balanced, from its live entries only.
+    pub fn compact(&mut self) {
+        let mut entries = self.tree.drain().filter(|(_, v)| v.is_some());
+        self.tree = BST::balanced(&mut entries, self.live);
+    }
+
+    /// Returns a reference to the tree, tombstones being `None` values.
+    pub const fn get_ref(&self) -> &BST<K, Option<V>> {
+        &self.tree
+    }
+
+    /// Returns the tree, tombstones being `None` values.
+    pub fn into_inner(self) -> BST<K, Option<V>> {
+        self.tree
+    }
+
+    fn is_live(&self, key: &K) -> bool {
+        matches!(self.tree.get(key), Some(Some(_)))
+    }
+
+    // Scans up from `rank` for a live key, tombstones are passed one by one
+    fn live_from(&self, mut rank: usize) -> Option<&K> {
+        while let Some(k) = self.tree.select(rank) {
+            if self.is_live(k) {
+                return Some(k);
+            }
+            rank += 1_usize;
+        }
+        None
+    }
+
+    // Scans down from the key ranked below `rank` for a live key
+    fn live_below(&self, mut rank: usize) -> Option<&K> {
+        while rank.gt(&0_usize) {
+            rank -= 1_usize;
+            let k = self.tree.select(rank)?;
+            if self.is_live(k) {
+                return Some(k);
+            }
+        }
+        None
+    }
+}
+
+impl<K: Ord, V> Default for Tombstoned<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + fmt::Debug, V: fmt::Debug> fmt::Debug for Tombstoned<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tombstoned")
+            .field("tree", &self.tree)
+            .field("live", &self.live)
+            .finish()
+    }
+}
+
+impl<K: Ord, V> Map<K, V> for Tombstoned<K, V> {
+    fn size(&self) -> usize {
+        self.live
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.tree.get(key)?.as_ref()
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        if !self.is_live(&key) {
+            self.live += 1_usize;
+        }
+        self.tree.put(key, Some(value));
+    }
+}
+
+impl<K: Ord, V> OrderedMap<K, V> for Tombstoned<K, V> {
+    fn min(&self) -> Option<&K> {
+        self.live_from(0_usize)
+    }
+
+    fn max(&self) -> Option<&K> {
+        self.live_below(self.tree.size())
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        if self.is_live(key) {
+            return self.tree.floor(key);
+        }
+        self.live_below(self.tree.rank(key))
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        self.live_from(self.tree.rank(key))
+    }
+
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        // `n` live entries are among the `n + tombstones` smallest nodes
+        let vec: Vec<(&K, &V)> = self
+            .tree
+            .smallest(n.saturating_add(self.tombstones()))
+            .filter_map(|(k, v)| Some((k, v.as_ref()?)))
+            .take(n)
+            .collect();
+        vec.into_iter()
+    }
+
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        let vec: Vec<(&K, &V)> = self
+            .tree
+            .largest(n.saturating_add(self.tombstones()))
+            .filter_map(|(k, v)| Some((k, v.as_ref()?)))
+            .take(n)
+            .collect();
+        vec.into_iter()
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        let vec: Vec<(&K, &V)> = self
+            .tree
+            .range(range)
+            .filter_map(|(k, v)| Some((k, v.as_ref()?)))
+            .collect();
+        vec.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tombstoned;
+    use crate::bench_support::shuffled_keys;
+    use crate::{Map, OrderedMap, SedgewickMap};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_against_model() {
+        let mut map = Tombstoned::new();
+        let mut model = BTreeMap::new();
+        for (i, k) in shuffled_keys(2000, 22).into_iter().enumerate() {
+            let k = k % 400;
+            if i % 3 == 0 {
+                assert_eq!(map.delete(&k), model.remove(&k));
+            } else {
+                map.put(k, i);
+                model.insert(k, i);
+            }
+            assert_eq!(map.size(), model.len());
+        }
+        assert!(map.tombstones().gt(&0_usize));
+        for _ in 0..2 {
+            for k in 0_u64..402 {
+                assert_eq!(map.get(&k), model.get(&k));
+                assert_eq!(map.floor(&k), model.range(..=k).next_back().map(|(k, _)| k));
+                assert_eq!(map.ceiling(&k), model.range(k..).next().map(|(k, _)| k));
+            }
+            assert_eq!(
+                (map.min(), map.max()),
+                (model.keys().next(), model.keys().next_back())
+            );
+            assert!(map.range(100..300).eq(model.range(100..300)));
+            assert!(map.smallest(50).eq(model.iter().take(50)));
+            assert!(map.largest(50).eq(model.iter().rev().take(50)));
+            map.compact();
+            assert_eq!(map.tombstones(), 0_usize);
+            assert_eq!(map.get_ref().size(), model.len());
+        }
+        assert!(map.get_ref().height().lt(&Some(9_usize)));
+    }
+}
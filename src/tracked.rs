@@ -0,0 +1,326 @@
+use crate::{Error, Map, OrderedMap, SedgewickMap};
+use std::ops::{Bound, RangeBounds};
+
+/// A map counting its structural modifications, inserts and removals of
+/// keys; overwriting the value of a present key isn't one.
+///
+/// Borrowing iterators can't outlive a mutation, but a `Cursor` doesn't
+/// borrow the map between steps (e.g. a map kept in a `RefCell`), so it
+/// compares the modification count on every step instead and refuses to
+/// yield entries of a map that changed under it. A step is a `successor`
+/// of the last key, nothing is copied up front.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::RefCell;
+/// use treers::rbtree::RedBlackTree;
+/// use treers::tracked::Tracked;
+/// use treers::{Error, Map};
+///
+/// let map = RefCell::new(Tracked::new(RedBlackTree::new()));
+/// map.borrow_mut().put(1, 'a');
+/// map.borrow_mut().put(2, 'b');
+///
+/// let mut cursor = map.borrow().cursor();
+/// assert_eq!(cursor.next(&map.borrow()), Some((1, &'a')));
+///
+/// map.borrow_mut().put(3, 'c');
+/// assert_eq!(
+///     cursor.try_next(&map.borrow()),
+///     Err(Error::ConcurrentModification)
+/// );
+/// ```
+#[derive(Debug, Default)]
+pub struct Tracked<M> {
+    map: M,
+    modifications: u64,
+}
+
+impl<M> Tracked<M> {
+    /// Starts tracking modifications of `map`.
+    pub const fn new(map: M) -> Self {
+        Self {
+            map,
+            modifications: 0_u64,
+        }
+    }
+
+    /// Returns a count of inserts and removals since tracking started.
+    pub const fn modifications(&self) -> u64 {
+        self.modifications
+    }
+
+    /// Returns a reference to the tracked map.
+    pub const fn get_ref(&self) -> &M {
+        &self.map
+    }
+
+    /// Stops tracking and returns the map.
+    pub fn into_inner(self) -> M {
+        self.map
+    }
+
+    /// Returns a cursor over all keys, in key order.
+    pub fn cursor<K: Ord + Clone, V>(&self) -> Cursor<K>
+    where
+        M: SedgewickMap<K, V>,
+    {
+        self.cursor_range(..)
+    }
+
+    /// Returns a cursor over keys inside `range`, in key order.
+    pub fn cursor_range<K: Ord + Clone, V, R: RangeBounds<K>>(&self, range: R) -> Cursor<K>
+    where
+        M: SedgewickMap<K, V>,
+    {
+        Cursor {
+            from: range.start_bound().cloned(),
+            to: range.end_bound().cloned(),
+            modifications: self.modifications,
+        }
+    }
+}
+
+impl<M> From<M> for Tracked<M> {
+    fn from(map: M) -> Self {
+        Self::new(map)
+    }
+}
+
+impl<K, V, M: Map<K, V>> Map<K, V> for Tracked<M> {
+    fn size(&self) -> usize {
+        self.map.size()
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    fn put(&mut self, key: K, value: V) {
+        // only a new key changes the structure, an overwrite keeps cursors
+        let size = self.map.size();
+        self.map.put(key, value);
+        if self.map.size().ne(&size) {
+            self.modifications += 1_u64;
+        }
+    }
+}
+
+impl<K: Ord, V, M: OrderedMap<K, V>> OrderedMap<K, V> for Tracked<M> {
+    fn min(&self) -> Option<&K> {
+        self.map.min()
+    }
+
+    fn max(&self) -> Option<&K> {
+        self.map.max()
+    }
+
+    fn floor(&self, key: &K) -> Option<&K> {
+        self.map.floor(key)
+    }
+
+    fn ceiling(&self, key: &K) -> Option<&K> {
+        self.map.ceiling(key)
+    }
+
+    fn smallest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.smallest(n)
+    }
+
+    fn largest(&self, n: usize) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.largest(n)
+    }
+
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)> {
+        self.map.range(range)
+    }
+
+    fn try_fold_range<R, B, E, F>(&self, range: R, init: B, f: F) -> Result<B, E>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(B, &K, &V) -> Result<B, E>,
+    {
+        self.map.try_fold_range(range, init, f)
+    }
+}
+
+impl<K: Ord, V, M: SedgewickMap<K, V>> SedgewickMap<K, V> for Tracked<M> {
+    fn height(&self) -> Option<usize> {
+        self.map.height()
+    }
+
+    fn leaf_depths(&self) -> Vec<usize> {
+        self.map.leaf_depths()
+    }
+
+    fn delete_min(&mut self) -> Option<(K, V)> {
+        let entry = self.map.delete_min()?;
+        self.modifications += 1_u64;
+        Some(entry)
+    }
+
+    fn delete_max(&mut self) -> Option<(K, V)> {
+        let entry = self.map.delete_max()?;
+        self.modifications += 1_u64;
+        Some(entry)
+    }
+
+    fn rank(&self, key: &K) -> usize {
+        self.map.rank(key)
+    }
+
+    fn select(&self, rank: usize) -> Option<&K> {
+        self.map.select(rank)
+    }
+
+    fn partition_point<P: FnMut(&K) -> bool>(&self, pred: P) -> Option<&K> {
+        self.map.partition_point(pred)
+    }
+
+    fn successor(&self, key: &K) -> Option<(&K, &V)> {
+        self.map.successor(key)
+    }
+
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)> {
+        self.map.predecessor(key)
+    }
+
+    fn clear(&mut self) {
+        if !self.map.is_empty() {
+            self.modifications += 1_u64;
+        }
+        self.map.clear();
+    }
+
+    fn drain(&mut self) -> std::vec::IntoIter<(K, V)> {
+        if !self.map.is_empty() {
+            self.modifications += 1_u64;
+        }
+        self.map.drain()
+    }
+}
+
+/// A position in a `Tracked` map that doesn't borrow it between steps.
+///
+/// Created by `Tracked::cursor`, it only yields entries while the map is
+/// unmodified since the cursor was created.
+#[derive(Debug, Clone)]
+pub struct Cursor<K> {
+    // Bounds of keys still ahead, `from` excludes the last key yielded
+    from: Bound<K>,
+    to: Bound<K>,
+    modifications: u64,
+}
+
+impl<K: Ord + Clone> Cursor<K> {
+    /// Advances the cursor, `Err` if `map` was modified since the cursor
+    /// was created.
+    pub fn try_next<'a, V, M: SedgewickMap<K, V>>(
+        &mut self,
+        map: &'a Tracked<M>,
+    ) -> Result<Option<(K, &'a V)>, Error<K>>
+    where
+        K: 'a,
+    {
+        if map.modifications.ne(&self.modifications) {
+            return Err(Error::ConcurrentModification);
+        }
+        let next = match &self.from {
+            Bound::Included(k) => map.ceiling(k).and_then(|k| map.get(k).map(|v| (k, v))),
+            Bound::Excluded(k) => map.successor(k),
+            Bound::Unbounded => map.min().and_then(|k| map.get(k).map(|v| (k, v))),
+        };
+        let next = next.filter(|(k, _)| match &self.to {
+            Bound::Included(hi) => k.le(&hi),
+            Bound::Excluded(hi) => k.lt(&hi),
+            Bound::Unbounded => true,
+        });
+        Ok(next.map(|(k, v)| {
+            self.from = Bound::Excluded(k.clone());
+            (k.clone(), v)
+        }))
+    }
+
+    /// Advances the cursor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `map` was modified since the cursor was created.
+    pub fn next<'a, V, M: SedgewickMap<K, V>>(&mut self, map: &'a Tracked<M>) -> Option<(K, &'a V)>
+    where
+        K: 'a,
+    {
+        match self.try_next(map) {
+            Ok(entry) => entry,
+            Err(_) => panic!("map modified during iteration"),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::Tracked;
+    use crate::bst::BST;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use crate::{Error, Map, SedgewickMap};
+    use std::cell::RefCell;
+    use std::ops::Bound;
+
+    fn walk<M: SedgewickMap<u32, u32> + Default>() {
+        let mut map = Tracked::new(M::default());
+        for i in [5_u32, 3, 8, 1, 4, 7, 9, 2, 6] {
+            map.put(i, i * 10);
+        }
+        assert_eq!(map.modifications(), 9_u64);
+        assert_eq!(map.height(), map.get_ref().height());
+
+        let mut cursor = map.cursor();
+        for i in 1_u32..=9 {
+            assert_eq!(cursor.next(&map), Some((i, &(i * 10))));
+        }
+        assert_eq!(cursor.next(&map), None);
+
+        let mut cursor = map.cursor_range(3..5);
+        assert_eq!(cursor.try_next(&map), Ok(Some((3, &30))));
+        // a cursor only holds bounds, a clone steps on its own
+        assert_eq!(cursor.clone().try_next(&map), Ok(Some((4, &40))));
+        assert_eq!(cursor.clone().try_next(&map), Ok(Some((4, &40))));
+        let mut tail = map.cursor_range((Bound::Excluded(7), Bound::Included(9)));
+        assert_eq!(tail.next(&map), Some((8, &80)));
+        assert_eq!(tail.next(&map), Some((9, &90)));
+        assert_eq!((tail.next(&map), tail.next(&map)), (None, None));
+        // a new value for a present key leaves the cursor going
+        map.put(4, 41);
+        assert_eq!(map.modifications(), 9_u64);
+        assert_eq!(cursor.clone().try_next(&map), Ok(Some((4, &41))));
+        map.put(10, 100);
+        assert_eq!(cursor.try_next(&map), Err(Error::ConcurrentModification));
+        assert_eq!(map.into_inner().size(), 10_usize);
+    }
+
+    #[test]
+    fn test_cursors() {
+        walk::<BST<u32, u32>>();
+        walk::<RedBlackTree<u32, u32>>();
+        walk::<BalancedTree<u32, u32>>();
+    }
+
+    #[test]
+    #[should_panic(expected = "map modified during iteration")]
+    fn test_cursor_panics_on_modification() {
+        let map = RefCell::new(Tracked::new(BST::new()));
+        for i in 0_u32..4 {
+            map.borrow_mut().put(i, i);
+        }
+        let mut cursor = map.borrow().cursor();
+        loop {
+            let key = cursor.next(&map.borrow()).map(|(k, _)| k);
+            match key {
+                Some(k) => map.borrow_mut().put(k + 10, k),
+                None => break,
+            }
+        }
+    }
+}
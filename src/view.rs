@@ -0,0 +1,394 @@
+#[cfg(feature = "bst")]
+use crate::bst::BST;
+#[cfg(feature = "btree")]
+use crate::btree::BalancedTree;
+#[cfg(feature = "rbtree")]
+use crate::rbtree::RedBlackTree;
+use std::ops::{Bound, RangeBounds};
+
+// Reads of a tree a view forwards, with no bounds beyond what reading
+// takes, where the map traits of a tree may ask for clonable entries
+pub(crate) trait Read<K: Ord, V> {
+    fn size(&self) -> usize;
+    fn get(&self, key: &K) -> Option<&V>;
+    fn min(&self) -> Option<&K>;
+    fn max(&self) -> Option<&K>;
+    fn floor(&self, key: &K) -> Option<&K>;
+    fn ceiling(&self, key: &K) -> Option<&K>;
+    fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&K, &V)>;
+    fn rank(&self, key: &K) -> usize;
+    fn successor(&self, key: &K) -> Option<(&K, &V)>;
+    fn predecessor(&self, key: &K) -> Option<(&K, &V)>;
+}
+
+enum Tree<'a, K: Ord, V> {
+    #[cfg(feature = "bst")]
+    Bst(&'a BST<K, V>),
+    #[cfg(feature = "rbtree")]
+    RedBlack(&'a RedBlackTree<K, V>),
+    #[cfg(feature = "btree")]
+    Balanced(&'a BalancedTree<K, V>),
+}
+
+// Forwards a read-only call to the borrowed tree
+macro_rules! dispatch {
+    ($view:expr, $t:ident => $call:expr) => {
+        match $view.tree {
+            #[cfg(feature = "bst")]
+            Tree::Bst($t) => $call,
+            #[cfg(feature = "rbtree")]
+            Tree::RedBlack($t) => $call,
+            #[cfg(feature = "btree")]
+            Tree::Balanced($t) => $call,
+        }
+    };
+}
+
+/// A read-only view over a borrowed tree.
+///
+/// `TreeView` is `Copy` and, for `Sync` keys and values, `Sync`, so read
+/// access can be handed to other threads without exposing mutation or
+/// cloning the tree.
+///
+/// # Examples
+///
+/// ```
+/// use treers::rbtree::RedBlackTree;
+/// use treers::view::TreeView;
+/// use treers::{Map, OrderedMap};
+///
+/// let mut rbtree: RedBlackTree<char, i32> = RedBlackTree::new();
+/// rbtree.put('a', 1);
+/// rbtree.put('b', 2);
+/// rbtree.put('c', 3);
+///
+/// let view: TreeView<char, i32> = rbtree.view();
+/// let copy = view;
+/// assert_eq!(view.get(&'a'), Some(&1));
+/// assert_eq!(copy.range('b'..).as_slice(), &[(&'b', &2), (&'c', &3)]);
+/// assert_eq!(view.iter().count(), 3_usize);
+/// ```
+///
+/// Viewing takes no bounds beyond ordered keys, so a `BST` of values that
+/// cannot be cloned is viewed the same way:
+///
+/// ```
+/// use treers::bst::BST;
+/// use treers::Map;
+///
+/// struct Socket(u16);
+///
+/// let mut bst: BST<u32, Socket> = BST::new();
+/// bst.put(1, Socket(8080));
+/// assert_eq!(bst.view().get(&1).map(|s| s.0), Some(8080));
+/// ```
+pub struct TreeView<'a, K: Ord, V> {
+    tree: Tree<'a, K, V>,
+}
+
+impl<'a, K: Ord, V> TreeView<'a, K, V> {
+    /// Returns a size of elements in viewed tree.
+    pub fn size(&self) -> usize {
+        dispatch!(self, t => t.size())
+    }
+
+    /// Checks if viewed tree is empty.
+    pub fn is_empty(&self) -> bool {
+        self.size().eq(&0_usize)
+    }
+
+    /// Returns a optional reference to value.
+    pub fn get(&self, key: &K) -> Option<&'a V> {
+        dispatch!(self, t => t.get(key))
+    }
+
+    /// Checks if key exists in viewed tree.
+    pub fn contains(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns a optional reference to minimal key.
+    pub fn min(&self) -> Option<&'a K> {
+        dispatch!(self, t => t.min())
+    }
+
+    /// Returns a optional reference to maximum key.
+    pub fn max(&self) -> Option<&'a K> {
+        dispatch!(self, t => t.max())
+    }
+
+    /// Returns a optional reference to the largest key less than or equal to `key`.
+    pub fn floor(&self, key: &K) -> Option<&'a K> {
+        dispatch!(self, t => t.floor(key))
+    }
+
+    /// Returns a optional reference to the smallest key greater than or equal to `key`.
+    pub fn ceiling(&self, key: &K) -> Option<&'a K> {
+        dispatch!(self, t => t.ceiling(key))
+    }
+
+    /// Returns entries with keys inside `range`, in key order.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> std::vec::IntoIter<(&'a K, &'a V)> {
+        dispatch!(self, t => t.range(range))
+    }
+
+    /// Returns all entries, in key order.
+    pub fn iter(&self) -> std::vec::IntoIter<(&'a K, &'a V)> {
+        self.range(..)
+    }
+
+    /// Restricts the view to keys inside `range`.
+    pub fn subrange<R: RangeBounds<K>>(&self, range: R) -> SubrangeView<'a, K, V>
+    where
+        K: Clone,
+    {
+        SubrangeView {
+            view: *self,
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+        }
+    }
+}
+
+/// A read-only view over the keys of a borrowed tree inside a range.
+///
+/// Keys outside of the range are invisible to every query, so a scoped
+/// slice of a map can be passed to other components.
+///
+/// # Examples
+///
+/// ```
+/// use treers::bst::BST;
+/// use treers::{Map, OrderedMap};
+///
+/// let mut bst: BST<u32, &str> = BST::new();
+/// bst.put(10, "a");
+/// bst.put(20, "b");
+/// bst.put(30, "c");
+/// bst.put(40, "d");
+///
+/// let sub = bst.subrange_view(15..=30);
+/// assert_eq!(sub.size(), 2_usize);
+/// assert_eq!(sub.get(&20), Some(&"b"));
+/// assert_eq!(sub.get(&10), None);
+/// assert_eq!(sub.min(), Some(&20));
+/// assert_eq!(sub.max(), Some(&30));
+/// assert_eq!(sub.iter().as_slice(), &[(&20, &"b"), (&30, &"c")]);
+/// ```
+pub struct SubrangeView<'a, K: Ord, V> {
+    view: TreeView<'a, K, V>,
+    start: Bound<K>,
+    end: Bound<K>,
+}
+
+impl<'a, K: Ord, V> SubrangeView<'a, K, V> {
+    fn bounds(&self) -> (Bound<&K>, Bound<&K>) {
+        (self.start.as_ref(), self.end.as_ref())
+    }
+
+    /// Returns a count of keys inside the range, the difference of the
+    /// ranks of its bounds.
+    pub fn size(&self) -> usize {
+        let below_start = match self.start {
+            Bound::Included(ref lo) => dispatch!(self.view, t => t.rank(lo)),
+            Bound::Excluded(ref lo) => {
+                dispatch!(self.view, t => t.rank(lo)) + usize::from(self.view.contains(lo))
+            }
+            Bound::Unbounded => 0_usize,
+        };
+        let below_end = match self.end {
+            Bound::Included(ref hi) => {
+                dispatch!(self.view, t => t.rank(hi)) + usize::from(self.view.contains(hi))
+            }
+            Bound::Excluded(ref hi) => dispatch!(self.view, t => t.rank(hi)),
+            Bound::Unbounded => self.view.size(),
+        };
+        below_end.saturating_sub(below_start)
+    }
+
+    /// Checks if no key of viewed tree is inside the range.
+    pub fn is_empty(&self) -> bool {
+        self.min().is_none()
+    }
+
+    /// Returns a optional reference to value, `None` for keys outside of the range.
+    pub fn get(&self, key: &K) -> Option<&'a V> {
+        if self.bounds().contains(key) {
+            self.view.get(key)
+        } else {
+            None
+        }
+    }
+
+    /// Checks if key exists in viewed tree and is inside the range.
+    pub fn contains(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns a optional reference to minimal key inside the range.
+    pub fn min(&self) -> Option<&'a K> {
+        let candidate = match self.start {
+            Bound::Unbounded => self.view.min(),
+            Bound::Included(ref lo) => self.view.ceiling(lo),
+            Bound::Excluded(ref lo) => dispatch!(self.view, t => t.successor(lo)).map(|(k, _)| k),
+        };
+        candidate.filter(|k| self.bounds().contains(*k))
+    }
+
+    /// Returns a optional reference to maximum key inside the range.
+    pub fn max(&self) -> Option<&'a K> {
+        let candidate = match self.end {
+            Bound::Unbounded => self.view.max(),
+            Bound::Included(ref hi) => self.view.floor(hi),
+            Bound::Excluded(ref hi) => dispatch!(self.view, t => t.predecessor(hi)).map(|(k, _)| k),
+        };
+        candidate.filter(|k| self.bounds().contains(*k))
+    }
+
+    /// Returns entries inside the range, in key order.
+    pub fn iter(&self) -> std::vec::IntoIter<(&'a K, &'a V)> {
+        self.view.range(self.bounds())
+    }
+}
+
+impl<K: Ord, V> Clone for TreeView<'_, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: Ord, V> Copy for TreeView<'_, K, V> {}
+
+impl<K: Ord, V> Clone for Tree<'_, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: Ord, V> Copy for Tree<'_, K, V> {}
+
+#[cfg(feature = "bst")]
+impl<'a, K: Ord, V> From<&'a BST<K, V>> for TreeView<'a, K, V> {
+    fn from(bst: &'a BST<K, V>) -> Self {
+        TreeView {
+            tree: Tree::Bst(bst),
+        }
+    }
+}
+
+#[cfg(feature = "rbtree")]
+impl<'a, K: Ord, V> From<&'a RedBlackTree<K, V>> for TreeView<'a, K, V> {
+    fn from(rbtree: &'a RedBlackTree<K, V>) -> Self {
+        TreeView {
+            tree: Tree::RedBlack(rbtree),
+        }
+    }
+}
+
+#[cfg(feature = "btree")]
+impl<'a, K: Ord, V> From<&'a BalancedTree<K, V>> for TreeView<'a, K, V> {
+    fn from(btree: &'a BalancedTree<K, V>) -> Self {
+        TreeView {
+            tree: Tree::Balanced(btree),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::TreeView;
+    use crate::bst::BST;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use crate::SedgewickMap;
+    use std::ops::Bound;
+
+    fn filled<M: SedgewickMap<u32, u32> + Default>() -> M {
+        let mut map = M::default();
+        for i in [5_u32, 3, 8, 1, 4, 7, 9, 2, 6] {
+            map.put(i, i * 10);
+        }
+        map
+    }
+
+    fn check(view: TreeView<u32, u32>) {
+        assert_eq!(view.size(), 9_usize);
+        assert!(!view.is_empty());
+        assert_eq!(view.get(&4), Some(&40));
+        assert_eq!(view.get(&10), None);
+        assert!(view.contains(&9));
+        assert_eq!(view.min(), Some(&1));
+        assert_eq!(view.max(), Some(&9));
+        assert!(view.range(3..6).map(|(k, _)| *k).eq(3..6));
+        assert!(view.iter().map(|(k, _)| *k).eq(1..=9));
+    }
+
+    #[test]
+    fn test_views() {
+        let bst: BST<u32, u32> = filled();
+        let rbtree: RedBlackTree<u32, u32> = filled();
+        let btree: BalancedTree<u32, u32> = filled();
+        check(bst.view());
+        check(rbtree.view());
+        check(btree.view());
+    }
+
+    #[test]
+    fn test_view_shared_across_threads() {
+        let rbtree: RedBlackTree<u32, u32> = filled();
+        let view = rbtree.view();
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(move || check(view));
+            }
+        });
+    }
+
+    #[test]
+    fn test_subrange_views() {
+        let bst: BST<u32, u32> = filled();
+        let rbtree: RedBlackTree<u32, u32> = filled();
+        let btree: BalancedTree<u32, u32> = filled();
+        for view in [bst.view(), rbtree.view(), btree.view()] {
+            let sub = view.subrange(3..7);
+            assert_eq!(sub.size(), 4_usize);
+            assert!(!sub.is_empty());
+            assert_eq!(sub.get(&3), Some(&30));
+            assert_eq!(sub.get(&7), None);
+            assert!(!sub.contains(&2));
+            assert_eq!(sub.min(), Some(&3));
+            assert_eq!(sub.max(), Some(&6));
+            assert!(sub.iter().map(|(k, _)| *k).eq(3..7));
+
+            let sub = view.subrange((Bound::Excluded(4), Bound::Unbounded));
+            assert_eq!(sub.min(), Some(&5));
+            assert_eq!(sub.max(), Some(&9));
+
+            let sub = view.subrange(20..);
+            assert!(sub.is_empty());
+            assert_eq!(sub.size(), 0_usize);
+            assert_eq!(sub.min(), None);
+            assert_eq!(sub.max(), None);
+        }
+    }
+
+    #[test]
+    fn test_subrange_bounds() {
+        let bst: BST<u32, u32> = filled();
+        let rbtree: RedBlackTree<u32, u32> = filled();
+        let btree: BalancedTree<u32, u32> = filled();
+        let bounds = |k| [Bound::Included(k), Bound::Excluded(k), Bound::Unbounded];
+        for view in [bst.view(), rbtree.view(), btree.view()] {
+            for start in (0_u32..=10).flat_map(bounds) {
+                for end in (0_u32..=10).flat_map(bounds) {
+                    let sub = view.subrange((start, end));
+                    let keys: Vec<u32> = sub.iter().map(|(k, _)| *k).collect();
+                    assert_eq!(sub.size(), keys.len());
+                    assert_eq!(sub.is_empty(), keys.is_empty());
+                    assert_eq!(sub.min(), keys.first());
+                    assert_eq!(sub.max(), keys.last());
+                }
+            }
+        }
+    }
+}
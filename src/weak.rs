@@ -0,0 +1,231 @@
+use crate::{Map, OrderedMap};
+use std::ops::RangeBounds;
+use std::rc::{Rc, Weak};
+
+// Fewest entries before puts start pruning
+const MIN_PRUNE_SIZE: usize = 16_usize;
+
+/// A map holding weak references to its values, for caches keyed by id.
+///
+/// Entries don't keep their values alive, `get` upgrades the reference and
+/// finds nothing once every `Rc` of the value is dropped. Entries of
+/// dropped values are removed by `prune`, or by a `put` once the map has
+/// doubled since the last prune, so a cache that keeps putting new ids
+/// doesn't grow without bound.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+/// use treers::rbtree::RedBlackTree;
+/// use treers::weak::WeakValueTree;
+///
+/// let mut cache = WeakValueTree::new(RedBlackTree::new());
+/// let alice = Rc::new(String::from("Alice"));
+/// let bob = Rc::new(String::from("Bob"));
+/// cache.put(1_u32, &alice);
+/// cache.put(2_u32, &bob);
+/// assert_eq!(cache.get(&1), Some(alice.clone()));
+///
+/// drop(bob);
+/// assert_eq!(cache.get(&2), None);
+/// assert_eq!(cache.prune(), 1_usize);
+/// assert_eq!(cache.size(), 1_usize);
+/// ```
+#[derive(Debug, Default)]
+pub struct WeakValueTree<M> {
+    map: M,
+    // Size after the last prune
+    pruned_size: usize,
+}
+
+impl<M> WeakValueTree<M> {
+    /// Wraps an empty `map`.
+    pub const fn new(map: M) -> Self {
+        Self {
+            map,
+            pruned_size: 0_usize,
+        }
+    }
+
+    /// Returns a reference to the wrapped map, dropped values included.
+    pub const fn get_ref(&self) -> &M {
+        &self.map
+    }
+
+    /// Returns the wrapped map.
+    pub fn into_inner(self) -> M {
+        self.map
+    }
+
+    /// Returns a count of entries, dropped values included.
+    pub fn size<K, V>(&self) -> usize
+    where
+        M: Map<K, Weak<V>>,
+    {
+        self.map.size()
+    }
+
+    /// Returns the value of `key`, if it is still alive.
+    pub fn get<K, V>(&self, key: &K) -> Option<Rc<V>>
+    where
+        M: Map<K, Weak<V>>,
+    {
+        self.map.get(key).and_then(Weak::upgrade)
+    }
+
+    /// Returns live entries with keys inside `range`, in key order,
+    /// skipping dropped values.
+    pub fn range<'a, K: Ord, V: 'a, R>(&'a self, range: R) -> Vec<(&'a K, Rc<V>)>
+    where
+        M: OrderedMap<K, Weak<V>>,
+        R: RangeBounds<K>,
+    {
+        self.map
+            .range(range)
+            .filter_map(|(k, v)| v.upgrade().map(|v| (k, v)))
+            .collect()
+    }
+
+    // Whether the map has doubled since the last prune
+    fn prune_due<K, V>(&self) -> bool
+    where
+        M: Map<K, Weak<V>>,
+    {
+        self.map
+            .size()
+            .ge(&(self.pruned_size * 2_usize).max(MIN_PRUNE_SIZE))
+    }
+
+    // Keys of dropped values, in key order
+    #[cfg(any(feature = "rbtree", feature = "btree"))]
+    fn dead_keys<K: Ord + Clone, V>(&self) -> Vec<K>
+    where
+        M: OrderedMap<K, Weak<V>>,
+    {
+        self.map
+            .range(..)
+            .filter(|(_, v)| v.strong_count().eq(&0_usize))
+            .map(|(k, _)| k.clone())
+            .collect()
+    }
+}
+
+#[cfg(feature = "bst")]
+impl<K: Ord, V> WeakValueTree<crate::bst::BST<K, Weak<V>>> {
+    /// Puts a weak reference to `value`, replacing the value of `key`.
+    ///
+    /// Prunes first once the map has doubled since the last prune.
+    pub fn put(&mut self, key: K, value: &Rc<V>) {
+        if self.prune_due() {
+            self.prune();
+        }
+        self.map.put(key, Rc::downgrade(value));
+    }
+
+    /// Removes entries of dropped values, returns a count of them.
+    pub fn prune(&mut self) -> usize {
+        let size = self.map.size();
+        self.map.retain(|_, v| v.strong_count().gt(&0_usize));
+        self.pruned_size = self.map.size();
+        size - self.pruned_size
+    }
+}
+
+#[cfg(feature = "rbtree")]
+impl<K: Ord + Clone, V> WeakValueTree<crate::rbtree::RedBlackTree<K, Weak<V>>> {
+    /// Puts a weak reference to `value`, replacing the value of `key`.
+    ///
+    /// Prunes first once the map has doubled since the last prune.
+    pub fn put(&mut self, key: K, value: &Rc<V>) {
+        if self.prune_due() {
+            self.prune();
+        }
+        self.map.put(key, Rc::downgrade(value));
+    }
+
+    /// Removes entries of dropped values, returns a count of them.
+    pub fn prune(&mut self) -> usize {
+        let dead = self.dead_keys();
+        for k in &dead {
+            self.map.delete(k);
+        }
+        self.pruned_size = self.map.size();
+        dead.len()
+    }
+}
+
+#[cfg(feature = "btree")]
+impl<K: Ord + Clone, V> WeakValueTree<crate::btree::BalancedTree<K, Weak<V>>> {
+    /// Puts a weak reference to `value`, replacing the value of `key`.
+    ///
+    /// Prunes first once the map has doubled since the last prune.
+    pub fn put(&mut self, key: K, value: &Rc<V>) {
+        if self.prune_due() {
+            self.prune();
+        }
+        self.map.put(key, Rc::downgrade(value));
+    }
+
+    /// Removes entries of dropped values, returns a count of them.
+    pub fn prune(&mut self) -> usize {
+        let dead = self.dead_keys();
+        for k in &dead {
+            self.map.delete(k);
+        }
+        self.pruned_size = self.map.size();
+        dead.len()
+    }
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree", feature = "btree"))]
+mod tests {
+    use super::WeakValueTree;
+    use crate::bst::BST;
+    use crate::btree::BalancedTree;
+    use crate::rbtree::RedBlackTree;
+    use std::rc::{Rc, Weak};
+
+    // `put` and `prune` are implemented per tree, a macro runs them all
+    macro_rules! cache {
+        ($tree:ty) => {{
+            let mut cache = WeakValueTree::new(<$tree>::default());
+            let values: Vec<Rc<u32>> = (0_u32..100).map(Rc::new).collect();
+            for v in &values {
+                cache.put(**v, v);
+            }
+            assert_eq!(cache.size(), 100_usize);
+            assert_eq!(cache.get(&42), Some(Rc::new(42)));
+
+            // keep every third value alive
+            let kept: Vec<Rc<u32>> = values.into_iter().step_by(3_usize).collect();
+            assert_eq!(cache.get(&1), None);
+            assert!(cache
+                .range(10..20)
+                .into_iter()
+                .map(|(k, _)| *k)
+                .eq([12_u32, 15, 18]));
+            assert_eq!(cache.prune(), 66_usize);
+            assert_eq!(cache.size(), kept.len());
+            assert_eq!(cache.prune(), 0_usize);
+
+            // putting short lived values prunes along the way
+            for k in 1000_u32..2000 {
+                cache.put(k, &Rc::new(k));
+            }
+            assert!(cache.size().lt(&(kept.len() * 2_usize + 2_usize)));
+            assert!(cache
+                .range(..)
+                .into_iter()
+                .map(|(_, v)| *v)
+                .eq(kept.iter().map(|v| **v)));
+        }};
+    }
+
+    #[test]
+    fn test_prunes_dropped_values() {
+        cache!(BST<u32, Weak<u32>>);
+        cache!(RedBlackTree<u32, Weak<u32>>);
+        cache!(BalancedTree<u32, Weak<u32>>);
+    }
+}
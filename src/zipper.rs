@@ -0,0 +1,326 @@
+#[cfg(feature = "bst")]
+use crate::bst::BST;
+#[cfg(feature = "rbtree")]
+use crate::rbtree::{Color, RedBlackTree};
+use crate::Map;
+use std::fmt;
+
+/// Binary trees a `Zipper` walks, split into a node and its children and
+/// joined back.
+pub trait Zippable: Default {
+    type Key;
+    type Value;
+    /// Everything of a node but its children.
+    type Hole;
+
+    /// Splits off both children, `Err` with the tree if it is empty.
+    fn unzip(self) -> Result<(Self::Hole, Self, Self), Self>;
+
+    /// Joins a node back with its children, counting its size again.
+    fn zip(hole: Self::Hole, left: Self, right: Self) -> Self;
+
+    /// Returns the entry of the root node, `None` for an empty tree.
+    fn entry(&self) -> Option<(&Self::Key, &Self::Value)>;
+
+    /// Returns the value of the root node, `None` for an empty tree.
+    fn value_mut(&mut self) -> Option<&mut Self::Value>;
+}
+
+// Where the focus was taken from, with the rest of its parent
+enum Step<T: Zippable> {
+    Left { parent: T::Hole, right: T },
+    Right { parent: T::Hole, left: T },
+}
+
+/// A focused subtree of a binary tree with the path back to the root.
+///
+/// Moving down takes the focused node apart and keeps its parent and the
+/// other child on a stack, moving up puts them back, so every move and
+/// edit is O(1) and the tree is whole again by `into_tree`. On paper the
+/// same path copying persistent trees do on every update.
+///
+/// Edits are limited to values for all trees, and rotations for a `BST`,
+/// so keys stay in search tree order and Red-Black colors stay valid.
+///
+/// # Examples
+///
+/// ```
+/// use treers::bst::BST;
+/// use treers::zipper::Zipper;
+/// use treers::Map;
+///
+/// let mut bst = BST::new();
+/// for k in [4, 2, 6, 1, 3] {
+///     bst.put(k, k * 10);
+/// }
+/// let mut zipper = Zipper::new(bst);
+/// assert!(zipper.left());
+/// assert!(zipper.right());
+/// assert_eq!(zipper.key(), Some(&3));
+/// zipper.set_value(33);
+///
+/// assert!(zipper.up());
+/// assert!(zipper.rotate_right());
+/// assert_eq!(zipper.key(), Some(&1));
+///
+/// let bst = zipper.into_tree();
+/// assert_eq!(bst.get(&3), Some(&33));
+/// assert_eq!(bst.to_paren_string(), "4:40(1:10(-,2:20(-,3:33)),6:60)");
+/// ```
+pub struct Zipper<T: Zippable> {
+    focus: T,
+    path: Vec<Step<T>>,
+}
+
+impl<T: Zippable> Zipper<T> {
+    /// Focuses the root of `tree`.
+    pub const fn new(tree: T) -> Self {
+        Self {
+            focus: tree,
+            path: Vec::new(),
+        }
+    }
+
+    /// Returns the focused subtree.
+    pub const fn focus(&self) -> &T {
+        &self.focus
+    }
+
+    /// Returns the count of moves up to the root.
+    pub const fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Returns the key of the focused node, `None` at an empty subtree.
+    pub fn key(&self) -> Option<&T::Key> {
+        self.focus.entry().map(|(k, _)| k)
+    }
+
+    /// Returns the value of the focused node, `None` at an empty subtree.
+    pub fn value(&self) -> Option<&T::Value> {
+        self.focus.entry().map(|(_, v)| v)
+    }
+
+    /// Returns the value of the focused node for an edit in place.
+    pub fn value_mut(&mut self) -> Option<&mut T::Value> {
+        self.focus.value_mut()
+    }
+
+    /// Replaces the value of the focused node, returning the old one, or
+    /// `None` and dropping `value` at an empty subtree.
+    pub fn set_value(&mut self, value: T::Value) -> Option<T::Value> {
+        self.focus.value_mut().map(|v| std::mem::replace(v, value))
+    }
+
+    /// Moves to the left child, `false` at an empty subtree.
+    pub fn left(&mut self) -> bool {
+        match std::mem::take(&mut self.focus).unzip() {
+            Ok((parent, left, right)) => {
+                self.path.push(Step::Left { parent, right });
+                self.focus = left;
+                true
+            }
+            Err(focus) => {
+                self.focus = focus;
+                false
+            }
+        }
+    }
+
+    /// Moves to the right child, `false` at an empty subtree.
+    pub fn right(&mut self) -> bool {
+        match std::mem::take(&mut self.focus).unzip() {
+            Ok((parent, left, right)) => {
+                self.path.push(Step::Right { parent, left });
+                self.focus = right;
+                true
+            }
+            Err(focus) => {
+                self.focus = focus;
+                false
+            }
+        }
+    }
+
+    /// Moves to the parent, `false` at the root.
+    pub fn up(&mut self) -> bool {
+        let focus = std::mem::take(&mut self.focus);
+        self.focus = match self.path.pop() {
+            Some(Step::Left { parent, right }) => T::zip(parent, focus, right),
+            Some(Step::Right { parent, left }) => T::zip(parent, left, focus),
+            None => {
+                self.focus = focus;
+                return false;
+            }
+        };
+        true
+    }
+
+    /// Moves to the root.
+    pub fn top(&mut self) {
+        while self.up() {}
+    }
+
+    /// Rebuilds the whole tree, edits included.
+    pub fn into_tree(mut self) -> T {
+        self.top();
+        self.focus
+    }
+}
+
+// Holes aren't required to be `Debug`, so the path shows as its length
+impl<T: Zippable + fmt::Debug> fmt::Debug for Zipper<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Zipper")
+            .field("focus", &self.focus)
+            .field("depth", &self.path.len())
+            .finish()
+    }
+}
+
+impl<T> Zipper<T>
+where
+    T: Zippable + Map<<T as Zippable>::Key, <T as Zippable>::Value>,
+{
+    /// Returns the count of entries in the focused subtree.
+    pub fn size(&self) -> usize {
+        self.focus.size()
+    }
+}
+
+#[cfg(feature = "bst")]
+impl<K: Ord, V> Zippable for BST<K, V> {
+    type Key = K;
+    type Value = V;
+    type Hole = (K, V);
+
+    fn unzip(self) -> Result<((K, V), Self, Self), Self> {
+        self.into_root()
+            .map(|(k, v, left, right)| ((k, v), left, right))
+    }
+
+    fn zip((k, v): (K, V), left: Self, right: Self) -> Self {
+        BST::from_root(k, v, left, right)
+    }
+
+    fn entry(&self) -> Option<(&K, &V)> {
+        self.root().map(|node| (node.key(), node.value()))
+    }
+
+    fn value_mut(&mut self) -> Option<&mut V> {
+        self.root_value_mut()
+    }
+}
+
+#[cfg(feature = "bst")]
+impl<K: Ord, V> Zipper<BST<K, V>> {
+    /// Rotates the focused node down to the right, its left child takes
+    /// its place and the focus. `false` without a left child.
+    pub fn rotate_right(&mut self) -> bool {
+        let lifted = self.focus.root().is_some_and(|n| !n.left().is_empty());
+        if lifted {
+            self.focus.rotate_right();
+        }
+        lifted
+    }
+
+    /// Rotates the focused node down to the left, its right child takes
+    /// its place and the focus. `false` without a right child.
+    pub fn rotate_left(&mut self) -> bool {
+        let lifted = self.focus.root().is_some_and(|n| !n.right().is_empty());
+        if lifted {
+            self.focus.rotate_left();
+        }
+        lifted
+    }
+}
+
+#[cfg(feature = "rbtree")]
+impl<K: Ord + Clone, V: Clone> Zippable for RedBlackTree<K, V> {
+    type Key = K;
+    type Value = V;
+    type Hole = (K, V, Color);
+
+    fn unzip(self) -> Result<((K, V, Color), Self, Self), Self> {
+        self.into_parts()
+            .map(|(k, v, color, left, right)| ((k, v, color), left, right))
+            .ok_or_else(RedBlackTree::new)
+    }
+
+    fn zip((k, v, color): (K, V, Color), left: Self, right: Self) -> Self {
+        RedBlackTree::node(k, v, color, left, right)
+    }
+
+    fn entry(&self) -> Option<(&K, &V)> {
+        self.root().map(|node| (node.key(), node.value()))
+    }
+
+    fn value_mut(&mut self) -> Option<&mut V> {
+        self.root_value_mut()
+    }
+}
+
+#[cfg(all(test, feature = "bst", feature = "rbtree"))]
+mod tests {
+    use super::Zipper;
+    use crate::bst::BST;
+    use crate::rbtree::RedBlackTree;
+    use crate::{Map, OrderedMap, SedgewickMap};
+
+    #[test]
+    fn test_walk_and_rebuild() {
+        let mut rbtree = RedBlackTree::new();
+        for k in 0_u32..100 {
+            rbtree.put(k, k);
+        }
+        let text = rbtree.to_paren_string();
+        let mut zipper = Zipper::new(rbtree);
+        // down the left spine to the min, doubling every value on the way
+        while zipper.key().is_some() {
+            if let Some(v) = zipper.value_mut() {
+                *v *= 2;
+            }
+            zipper.left();
+        }
+        assert!(!zipper.left() && !zipper.right());
+        assert_eq!(zipper.size(), 0_usize);
+        assert!(zipper.up());
+        assert_eq!(zipper.key(), Some(&0));
+        assert_eq!(zipper.depth(), 6_usize);
+        assert_eq!(zipper.set_value(7), Some(0));
+
+        let rbtree = zipper.into_tree();
+        assert_eq!(rbtree.size(), 100_usize);
+        assert_eq!(rbtree.get(&0), Some(&7));
+        assert_eq!(rbtree.get(&1), Some(&2));
+        assert_eq!(rbtree.get(&99), Some(&99));
+        let doubled = rbtree.range(..).filter(|(k, v)| *v != *k).count();
+        assert_eq!(doubled, 7_usize);
+        assert!(rbtree.same_shape(&text.parse::<RedBlackTree<u32, u32>>().unwrap()));
+    }
+
+    #[test]
+    fn test_rotations_balance() {
+        let mut bst = BST::new();
+        for k in 0_u32..7 {
+            bst.put(k, 0_u8);
+        }
+        // a right leaning list becomes a perfect tree by left rotations
+        let mut zipper = Zipper::new(bst);
+        assert!(!zipper.rotate_right());
+        for _ in 0_usize..3 {
+            assert!(zipper.rotate_left());
+        }
+        assert!(zipper.left());
+        assert!(zipper.rotate_right());
+        assert!(zipper.up() && zipper.right());
+        assert!(zipper.rotate_left());
+        assert_eq!(zipper.size(), 3_usize);
+        assert!(!zipper.focus().is_empty());
+
+        let bst = zipper.into_tree();
+        assert_eq!(bst.to_paren_string(), "3:0(1:0(0:0,2:0),5:0(4:0,6:0))");
+        assert_eq!(bst.height(), Some(2_usize));
+        assert!(bst.range(..).map(|(k, _)| *k).eq(0_u32..7));
+    }
+}